@@ -8,7 +8,26 @@
 };
 [[block]] struct Light {
 	location: vec3<f32>;
+	// Luminous intensity in candela
+	intensity: f32;
 	color: vec3<f32>;
+	// Distance where the falloff reaches zero
+	radius: f32;
+	// Unit vector pointing toward the sun
+	sun_direction: vec3<f32>;
+	// Illuminance in lux, zero when the scene has no sun
+	sun_illuminance: f32;
+	sun_color: vec3<f32>;
+	// Half the angle the sun's disk spans in the sky, in radians
+	sun_angular_radius: f32;
+	// Aim of the lamp's IES profile, if it has one
+	lamp_direction: vec3<f32>;
+	lamp_has_profile: u32;
+	// Whether each light casts shadows traced through the distance field
+	lamp_distance_field_shadows: u32;
+	sun_distance_field_shadows: u32;
+	// Light arriving evenly from the whole sky rather than from the sun's disk
+	sky_ambient: vec3<f32>;
 };
 struct SummedColorCell {
 	r: atomic<u32>;
@@ -19,20 +38,25 @@ struct SummedColorCell {
 [[block]] struct SummedColors {
 	cells: array<SummedColorCell>;
 };
+[[block]] struct VoxelUpdateRegion {
+	z_min: u32;
+	z_max: u32;
+};
 
 // Uniforms
-[[group(0), binding(0)]] var<uniform> light: Light;
-[[group(1), binding(0)]] var<uniform> camera: Camera;
-[[group(1), binding(1)]] var t_albedo: texture_2d<f32>;
-[[group(1), binding(2)]] var s_albedo: sampler;
-[[group(1), binding(3)]] var<storage, read_write> voxel_buffer: SummedColors;
+[[group(2), binding(0)]] var<uniform> light: Light;
+[[group(3), binding(0)]] var<uniform> camera: Camera;
+[[group(3), binding(1)]] var t_albedo: texture_2d<f32>;
+[[group(3), binding(2)]] var s_albedo: sampler;
+[[group(3), binding(3)]] var<storage, read_write> voxel_buffer: SummedColors;
+[[group(3), binding(4)]] var<uniform> update_region: VoxelUpdateRegion;
 
 // Attributes
 struct VertexInput {
 	[[location(0)]] position: vec3<f32>;
 	[[location(1)]] uv: vec2<f32>;
-	[[location(2)]] normal: vec3<f32>;
-	[[location(3)]] tangent: vec3<f32>;
+	[[location(2)]] normal: vec4<f32>;
+	[[location(3)]] tangent: vec4<f32>;
 };
 struct InstanceInput {
 	[[location(4)]] m_matrix_0: vec4<f32>;
@@ -92,8 +116,11 @@ fn main(in: VertexOutput) -> FragmentOutput {
 	let texture_dimensions = vec3<f32>(f32(texture_dim_one));
 	var pos = vec3<i32>(normalized_position * texture_dimensions); // 0 to 256
 
+	// Only the slabs being re-voxelized this frame accumulate, since the rest of the volume keeps its resolved lighting
+	let in_update_region = pos.z >= i32(update_region.z_min) && pos.z < i32(update_region.z_max);
+
 	let buffer_index = pos.x + pos.y * texture_dim_one + pos.z * texture_dim_one * texture_dim_one;
-	if (buffer_index > 0) {
+	if (buffer_index > 0 && in_update_region) {
 		var old_value = atomicAdd(&voxel_buffer.cells[buffer_index].r, u32(color.r * 256.));
 		old_value = atomicAdd(&voxel_buffer.cells[buffer_index].g, u32(color.g * 256.));
 		old_value = atomicAdd(&voxel_buffer.cells[buffer_index].b, u32(color.b * 256.));
@@ -0,0 +1,85 @@
+// Must match `LUMINANCE_HISTOGRAM_BINS`
+[[block]] struct Histogram {
+	bins: array<atomic<u32>, 64>;
+};
+
+[[block]] struct AdaptedExposure {
+	average_log_luminance: f32;
+	exposure: f32;
+	adapted: u32;
+};
+
+[[block]] struct AutoExposure {
+	delta_time: f32;
+	enabled: u32;
+	// How quickly the adapted luminance closes in on the measured one each second
+	brightening_speed: f32;
+	darkening_speed: f32;
+	// Range in stops the exposure is kept within
+	min_ev: f32;
+	max_ev: f32;
+	manual: u32;
+	manual_ev: f32;
+};
+
+// Uniforms
+[[group(3), binding(0)]] var<storage, read_write> histogram: Histogram;
+[[group(3), binding(1)]] var<storage, read_write> adapted_exposure: AdaptedExposure;
+[[group(3), binding(2)]] var<uniform> auto_exposure: AutoExposure;
+
+// Must match the range the histogram shader spreads the bins over
+let MIN_LOG_LUMINANCE: f32 = -10.;
+let MAX_LOG_LUMINANCE: f32 = 6.;
+// The average luminance is brought to middle gray, the reflectance of a surface halfway between black and white as the eye sees it
+let MIDDLE_GRAY: f32 = 0.18;
+
+var<workgroup> counts: array<u32, 64>;
+
+// One invocation per bin reads its count and zeroes it for the next frame, then the first sums them up
+[[stage(compute), workgroup_size(64)]]
+fn main([[builtin(local_invocation_index)]] bin: u32) {
+	counts[bin] = atomicExchange(&histogram.bins[bin], 0u);
+	workgroupBarrier();
+
+	if (bin != 0u) {
+		return;
+	}
+
+	if (auto_exposure.manual != 0u) {
+		adapted_exposure.exposure = exp2(auto_exposure.manual_ev);
+		adapted_exposure.adapted = 0u;
+		return;
+	}
+	if (auto_exposure.enabled == 0u) {
+		adapted_exposure.exposure = 1.;
+		adapted_exposure.adapted = 0u;
+		return;
+	}
+
+	// The first bin collects the black pixels, which are left out so the empty parts of the frame don't drag the average down to nothing
+	// The counts are weighted by the metering, so the average favors the pixels it counts more of
+	var weighted_log_luminance = 0.;
+	var pixel_count = 0u;
+	for (var i = 1u; i < 64u; i = i + 1u) {
+		let bin_log_luminance = MIN_LOG_LUMINANCE + (f32(i) - 0.5) / 62. * (MAX_LOG_LUMINANCE - MIN_LOG_LUMINANCE);
+		weighted_log_luminance = weighted_log_luminance + f32(counts[i]) * bin_log_luminance;
+		pixel_count = pixel_count + counts[i];
+	}
+	// A frame with nothing but black in it keeps whatever the eye had adapted to
+	if (pixel_count == 0u) {
+		return;
+	}
+	let measured = weighted_log_luminance / f32(pixel_count);
+
+	var average_log_luminance = measured;
+	if (adapted_exposure.adapted != 0u) {
+		let previous = adapted_exposure.average_log_luminance;
+		let speed = select(auto_exposure.darkening_speed, auto_exposure.brightening_speed, measured > previous);
+		// Framerate independent exponential smoothing
+		average_log_luminance = previous + (measured - previous) * (1. - exp(-auto_exposure.delta_time * speed));
+	}
+
+	adapted_exposure.average_log_luminance = average_log_luminance;
+	adapted_exposure.exposure = clamp(MIDDLE_GRAY / exp2(average_log_luminance), exp2(auto_exposure.min_ev), exp2(auto_exposure.max_ev));
+	adapted_exposure.adapted = 1u;
+}
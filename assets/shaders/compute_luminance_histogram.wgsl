@@ -0,0 +1,59 @@
+// Must match `LUMINANCE_HISTOGRAM_BINS`
+[[block]] struct Histogram {
+	bins: array<atomic<u32>, 64>;
+};
+
+[[block]] struct Metering {
+	// 0 for average, 1 for center-weighted, and 2 for spot
+	mode: u32;
+};
+
+// Uniforms
+[[group(3), binding(0)]] var t_frame: texture_2d<f32>;
+[[group(3), binding(1)]] var s_frame: sampler;
+[[group(3), binding(2)]] var<storage, read_write> histogram: Histogram;
+[[group(3), binding(3)]] var<uniform> metering: Metering;
+
+// Range of log2 luminance spread over every bin but the first, which collects the black pixels
+let MIN_LOG_LUMINANCE: f32 = -10.;
+let MAX_LOG_LUMINANCE: f32 = 6.;
+// Radius of the spot metered, in units of half the screen height so it stays round at any aspect ratio
+let SPOT_RADIUS: f32 = 0.15;
+
+// How many times each pixel is counted, which is 4 for every pixel with average metering
+fn metering_weight(texel: vec2<i32>, dimensions: vec2<i32>) -> u32 {
+	let distance = length(vec2<f32>(texel) + vec2<f32>(0.5) - vec2<f32>(dimensions) * 0.5) / (f32(dimensions.y) * 0.5);
+
+	if (metering.mode == 1u) {
+		// From 4 in the middle down to 1 by the edges of the screen's height
+		return 1u + u32(round(3. * (1. - clamp(distance, 0., 1.))));
+	}
+	if (metering.mode == 2u) {
+		return select(0u, 4u, distance < SPOT_RADIUS);
+	}
+	return 4u;
+}
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn main([[builtin(global_invocation_id)]] invocation_id: vec3<u32>) {
+	let dimensions = textureDimensions(t_frame);
+	let texel = vec2<i32>(invocation_id.xy);
+	if (texel.x >= dimensions.x || texel.y >= dimensions.y) {
+		return;
+	}
+
+	let weight = metering_weight(texel, dimensions);
+	if (weight == 0u) {
+		return;
+	}
+
+	let color = textureLoad(t_frame, texel, 0).rgb;
+	let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+
+	var bin = 0u;
+	if (luminance > exp2(MIN_LOG_LUMINANCE)) {
+		let t = clamp((log2(luminance) - MIN_LOG_LUMINANCE) / (MAX_LOG_LUMINANCE - MIN_LOG_LUMINANCE), 0., 1.);
+		bin = u32(t * 62.) + 1u;
+	}
+	let previous_count = atomicAdd(&histogram.bins[bin], weight);
+}
@@ -0,0 +1,34 @@
+// Must match `LUMINANCE_HISTOGRAM_BINS`, read without atomics since the counting has finished
+[[block]] struct Histogram {
+	bins: array<u32, 64>;
+};
+
+// Uniforms
+[[group(3), binding(0)]] var<storage, read> histogram: Histogram;
+[[group(3), binding(1)]] var t_histogram: texture_storage_2d<rgba8unorm, write>;
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn main([[builtin(global_invocation_id)]] invocation_id: vec3<u32>) {
+	let dimensions = textureDimensions(t_histogram);
+	let texel = vec2<i32>(invocation_id.xy);
+	if (texel.x >= dimensions.x || texel.y >= dimensions.y) {
+		return;
+	}
+
+	// Bars are scaled to the fullest bin
+	var max_count = 1u;
+	for (var i = 0u; i < 64u; i = i + 1u) {
+		max_count = max(max_count, histogram.bins[i]);
+	}
+
+	let bin = u32(texel.x) * 64u / u32(dimensions.x);
+	let bar_height = f32(histogram.bins[bin]) / f32(max_count);
+	let height = 1. - (f32(texel.y) + 0.5) / f32(dimensions.y);
+
+	// Darkens the scene behind the empty part of the graph so the bars stay readable
+	var color = vec4<f32>(0., 0., 0., 0.6);
+	if (height < bar_height) {
+		color = vec4<f32>(1., 1., 1., 1.);
+	}
+	textureStore(t_histogram, texel, color);
+}
@@ -0,0 +1,137 @@
+[[block]] struct Camera {
+	v_matrix: mat4x4<f32>;
+	p_matrix: mat4x4<f32>;
+	inv_v_matrix: mat4x4<f32>;
+	inv_p_matrix: mat4x4<f32>;
+	prev_v_matrix: mat4x4<f32>;
+	prev_p_matrix: mat4x4<f32>;
+};
+[[block]] struct CullingSettings {
+	instance_count: u32;
+	meshlet_count: u32;
+	occlusion_culling: u32;
+	padding: u32;
+};
+struct Meshlet {
+	// World space bounding sphere with the radius in the w component
+	bounds: vec4<f32>;
+	first_index: u32;
+	index_count: u32;
+	padding_0: u32;
+	padding_1: u32;
+};
+[[block]] struct Meshlets {
+	meshlets: array<Meshlet>;
+};
+// Laid out as the arguments of `draw_indexed_indirect()`
+struct DrawIndexedIndirect {
+	index_count: u32;
+	instance_count: u32;
+	first_index: u32;
+	base_vertex: i32;
+	first_instance: u32;
+};
+[[block]] struct DrawCommands {
+	commands: array<DrawIndexedIndirect>;
+};
+
+// Uniforms
+[[group(3), binding(0)]] var<uniform> camera: Camera;
+[[group(3), binding(1)]] var t_hi_z: texture_2d<f32>;
+[[group(3), binding(3)]] var<uniform> settings: CullingSettings;
+[[group(3), binding(4)]] var<storage, read> meshlets: Meshlets;
+[[group(3), binding(5)]] var<storage, read_write> draw_commands: DrawCommands;
+
+fn visible_in_frustum(center: vec3<f32>, radius: f32) -> bool {
+	let vp = camera.p_matrix * camera.v_matrix;
+	let row_0 = vec4<f32>(vp[0].x, vp[1].x, vp[2].x, vp[3].x);
+	let row_1 = vec4<f32>(vp[0].y, vp[1].y, vp[2].y, vp[3].y);
+	let row_2 = vec4<f32>(vp[0].z, vp[1].z, vp[2].z, vp[3].z);
+	let row_3 = vec4<f32>(vp[0].w, vp[1].w, vp[2].w, vp[3].w);
+
+	// Same planes as `Frustum::from_matrix()`, with the near plane being the third row since clip space depth goes from 0 to 1
+	var planes = array<vec4<f32>, 6>(row_3 + row_0, row_3 - row_0, row_3 + row_1, row_3 - row_1, row_2, row_3 - row_2);
+	for (var i = 0; i < 6; i = i + 1) {
+		let plane = planes[i] / length(planes[i].xyz);
+		if (dot(plane.xyz, center) + plane.w < -radius) {
+			return false;
+		}
+	}
+
+	return true;
+}
+
+// Tests the sphere against the Hi-Z pyramid built from last frame's depth, which was rendered with the previous view and projection matrices
+fn visible_past_occluders(center: vec3<f32>, radius: f32) -> bool {
+	let prev_vp = camera.prev_p_matrix * camera.prev_v_matrix;
+
+	// Project the corners of the sphere's bounding box to find the screen rectangle it covers
+	var uv_min = vec2<f32>(1., 1.);
+	var uv_max = vec2<f32>(0., 0.);
+	for (var corner = 0u; corner < 8u; corner = corner + 1u) {
+		let offset = vec3<f32>(f32(corner & 1u), f32((corner >> 1u) & 1u), f32((corner >> 2u) & 1u)) * 2. - 1.;
+		let clip = prev_vp * vec4<f32>(center + offset * radius, 1.);
+
+		// A sphere reaching behind the camera can't be bounded on screen, so it is conservatively kept
+		if (clip.w <= 0.) {
+			return true;
+		}
+
+		let uv = clip.xy / clip.w * vec2<f32>(0.5, -0.5) + 0.5;
+		uv_min = min(uv_min, uv);
+		uv_max = max(uv_max, uv);
+	}
+	uv_min = clamp(uv_min, vec2<f32>(0., 0.), vec2<f32>(1., 1.));
+	uv_max = clamp(uv_max, vec2<f32>(0., 0.), vec2<f32>(1., 1.));
+
+	// Depth increases with view distance, so the sphere's nearest depth is that of the point closest to the camera along the view axis
+	let view_center = camera.prev_v_matrix * vec4<f32>(center, 1.);
+	let nearest_clip = camera.prev_p_matrix * vec4<f32>(view_center.xy, view_center.z + radius, 1.);
+	if (nearest_clip.w <= 0.) {
+		return true;
+	}
+	let nearest_depth = nearest_clip.z / nearest_clip.w;
+
+	// Pick the mip level where the rectangle spans at most two texels in each direction, so four reads cover it
+	let base_dimensions = vec2<f32>(textureDimensions(t_hi_z, 0));
+	let rectangle_texels = (uv_max - uv_min) * base_dimensions;
+	let max_level = textureNumLevels(t_hi_z) - 1;
+	let level = clamp(i32(ceil(log2(max(max(rectangle_texels.x, rectangle_texels.y), 1.)))), 0, max_level);
+
+	let level_dimensions = textureDimensions(t_hi_z, level);
+	let texel_min = clamp(vec2<i32>(uv_min * vec2<f32>(level_dimensions)), vec2<i32>(0, 0), level_dimensions - 1);
+	let texel_max = clamp(vec2<i32>(uv_max * vec2<f32>(level_dimensions)), vec2<i32>(0, 0), level_dimensions - 1);
+
+	var farthest_occluder_depth = 0.;
+	for (var x = texel_min.x; x <= texel_max.x; x = x + 1) {
+		for (var y = texel_min.y; y <= texel_max.y; y = y + 1) {
+			farthest_occluder_depth = max(farthest_occluder_depth, textureLoad(t_hi_z, vec2<i32>(x, y), level).r);
+		}
+	}
+
+	return nearest_depth <= farthest_occluder_depth;
+}
+
+[[stage(compute), workgroup_size(64)]]
+fn main([[builtin(global_invocation_id)]] invocation_id: vec3<u32>) {
+	let index = invocation_id.x;
+	if (index >= settings.meshlet_count) {
+		return;
+	}
+
+	let meshlet = meshlets.meshlets[index];
+	let center = meshlet.bounds.xyz;
+	let radius = meshlet.bounds.w;
+
+	var visible = visible_in_frustum(center, radius);
+	if (visible && settings.occlusion_culling != 0u) {
+		visible = visible_past_occluders(center, radius);
+	}
+
+	// Culled meshlets stay in the buffer as draws of zero instances, since the draw calls are recorded before the GPU decides
+	var instance_count = 0u;
+	if (visible) {
+		instance_count = settings.instance_count;
+	}
+	draw_commands.commands[index] = DrawIndexedIndirect(meshlet.index_count, instance_count, meshlet.first_index, 0, 0u);
+}
@@ -0,0 +1,33 @@
+// Uniforms
+[[group(3), binding(0)]] var t_overdraw: texture_2d<f32>;
+[[group(3), binding(1)]] var s_overdraw: sampler;
+[[group(3), binding(2)]] var t_heatmap: texture_storage_2d<rgba8unorm, write>;
+
+// Layer count shown as fully red, with everything from a single layer up to it spread over the ramp
+let MAX_LAYERS: f32 = 8.;
+
+// Blue through green and yellow to red
+fn heat(t: f32) -> vec3<f32> {
+	let r = clamp(3. * t - 1., 0., 1.);
+	let g = clamp(3. * t, 0., 1.) - clamp(3. * t - 2., 0., 1.);
+	let b = clamp(1. - 3. * t, 0., 1.);
+	return vec3<f32>(r, g, b);
+}
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn main([[builtin(global_invocation_id)]] invocation_id: vec3<u32>) {
+	let dimensions = textureDimensions(t_heatmap);
+	let texel = vec2<i32>(invocation_id.xy);
+	if (texel.x >= dimensions.x || texel.y >= dimensions.y) {
+		return;
+	}
+
+	let layers = textureLoad(t_overdraw, texel, 0).r;
+
+	// Pixels nothing was drawn to stay black
+	var color = vec4<f32>(0., 0., 0., 1.);
+	if (layers > 0.5) {
+		color = vec4<f32>(heat(clamp((layers - 1.) / (MAX_LAYERS - 1.), 0., 1.)), 1.);
+	}
+	textureStore(t_heatmap, texel, color);
+}
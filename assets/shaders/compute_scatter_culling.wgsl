@@ -0,0 +1,117 @@
+[[block]] struct Camera {
+	v_matrix: mat4x4<f32>;
+	p_matrix: mat4x4<f32>;
+	inv_v_matrix: mat4x4<f32>;
+	inv_p_matrix: mat4x4<f32>;
+	prev_v_matrix: mat4x4<f32>;
+	prev_p_matrix: mat4x4<f32>;
+};
+[[block]] struct CullingSettings {
+	instance_count: u32;
+	lod_count: u32;
+	// Radius around each instance's origin enclosing every LOD at a scale of one
+	bounds_radius: f32;
+	padding: u32;
+	// Distance from the camera where each LOD stops being drawn
+	lod_distances: vec4<f32>;
+};
+struct ScatterInstance {
+	location: vec3<f32>;
+	scale: f32;
+	// Quaternion with the real part last
+	rotation: vec4<f32>;
+};
+[[block]] struct ScatterInstances {
+	instances: array<ScatterInstance>;
+};
+// Laid out as the arguments of `draw_indexed_indirect()`, with the instance count added to by every visible instance
+struct DrawIndexedIndirect {
+	index_count: u32;
+	instance_count: atomic<u32>;
+	first_index: u32;
+	base_vertex: i32;
+	first_instance: u32;
+};
+[[block]] struct DrawCommands {
+	commands: array<DrawIndexedIndirect>;
+};
+// Laid out like the instance vertex buffer of the scene shaders
+struct Instance {
+	model: mat4x4<f32>;
+	morph_weights: array<vec4<f32>, 2>;
+	receives_shadows: f32;
+	fade: f32;
+	// Scattered instances never move, so this is always the same as the model matrix
+	previous_model: mat4x4<f32>;
+};
+[[block]] struct Instances {
+	instances: array<Instance>;
+};
+
+// Uniforms
+[[group(3), binding(0)]] var<uniform> camera: Camera;
+[[group(3), binding(1)]] var<uniform> settings: CullingSettings;
+[[group(3), binding(2)]] var<storage, read> scatter: ScatterInstances;
+[[group(3), binding(3)]] var<storage, read_write> draw_commands: DrawCommands;
+[[group(3), binding(4)]] var<storage, read_write> visible: Instances;
+
+// Must match the same function in `compute_meshlet_culling.wgsl`
+fn visible_in_frustum(center: vec3<f32>, radius: f32) -> bool {
+	let vp = camera.p_matrix * camera.v_matrix;
+	let row_0 = vec4<f32>(vp[0].x, vp[1].x, vp[2].x, vp[3].x);
+	let row_1 = vec4<f32>(vp[0].y, vp[1].y, vp[2].y, vp[3].y);
+	let row_2 = vec4<f32>(vp[0].z, vp[1].z, vp[2].z, vp[3].z);
+	let row_3 = vec4<f32>(vp[0].w, vp[1].w, vp[2].w, vp[3].w);
+
+	// Same planes as `Frustum::from_matrix()`, with the near plane being the third row since clip space depth goes from 0 to 1
+	var planes = array<vec4<f32>, 6>(row_3 + row_0, row_3 - row_0, row_3 + row_1, row_3 - row_1, row_2, row_3 - row_2);
+	for (var i = 0; i < 6; i = i + 1) {
+		let plane = planes[i] / length(planes[i].xyz);
+		if (dot(plane.xyz, center) + plane.w < -radius) {
+			return false;
+		}
+	}
+
+	return true;
+}
+
+fn model_matrix(location: vec3<f32>, rotation: vec4<f32>, scale: f32) -> mat4x4<f32> {
+	let q = rotation;
+	let x = vec3<f32>(1. - 2. * (q.y * q.y + q.z * q.z), 2. * (q.x * q.y + q.w * q.z), 2. * (q.x * q.z - q.w * q.y));
+	let y = vec3<f32>(2. * (q.x * q.y - q.w * q.z), 1. - 2. * (q.x * q.x + q.z * q.z), 2. * (q.y * q.z + q.w * q.x));
+	let z = vec3<f32>(2. * (q.x * q.z + q.w * q.y), 2. * (q.y * q.z - q.w * q.x), 1. - 2. * (q.x * q.x + q.y * q.y));
+
+	return mat4x4<f32>(vec4<f32>(x * scale, 0.), vec4<f32>(y * scale, 0.), vec4<f32>(z * scale, 0.), vec4<f32>(location, 1.));
+}
+
+[[stage(compute), workgroup_size(64)]]
+fn main([[builtin(global_invocation_id)]] invocation_id: vec3<u32>) {
+	let index = invocation_id.x;
+	if (index >= settings.instance_count) {
+		return;
+	}
+
+	let instance = scatter.instances[index];
+	if (!visible_in_frustum(instance.location, settings.bounds_radius * instance.scale)) {
+		return;
+	}
+
+	// The first LOD whose distance the instance is within, with instances past the last LOD culled
+	let distance = length(instance.location - camera.inv_v_matrix[3].xyz);
+	var lod = 0u;
+	loop {
+		if (lod >= settings.lod_count || distance < settings.lod_distances[lod]) {
+			break;
+		}
+		lod = lod + 1u;
+	}
+	if (lod >= settings.lod_count) {
+		return;
+	}
+
+	// Each LOD's instances are packed into its own range of the buffer, in whatever order the invocations claim their slots
+	let slot = atomicAdd(&draw_commands.commands[lod].instance_count, 1u);
+	let morph_weights = array<vec4<f32>, 2>(vec4<f32>(0.), vec4<f32>(0.));
+	let model = model_matrix(instance.location, instance.rotation, instance.scale);
+	visible.instances[lod * settings.instance_count + slot] = Instance(model, morph_weights, 1., 1., model);
+}
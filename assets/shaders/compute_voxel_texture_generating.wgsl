@@ -7,16 +7,30 @@ struct SummedColorCell {
 [[block]] struct SummedColors {
 	cells: array<SummedColorCell>;
 };
+[[block]] struct VoxelUpdateRegion {
+	z_min: u32;
+	z_max: u32;
+};
 
 // Uniforms
-[[group(0), binding(0)]] var t_voxel_lightmap: texture_storage_3d<rgba8unorm, write>;
-[[group(0), binding(1)]] var<storage, read_write> voxel_buffer: SummedColors;
+[[group(3), binding(0)]] var t_voxel_lightmap: texture_storage_3d<rgba8unorm, write>;
+[[group(3), binding(1)]] var<storage, read_write> voxel_buffer: SummedColors;
+[[group(3), binding(2)]] var<uniform> update_region: VoxelUpdateRegion;
 
 [[stage(compute), workgroup_size(128)]]
 fn main([[builtin(global_invocation_id)]] invocation_id: vec3<u32>) {
 	let texture_dim = 128u;
-	let buffer_index = invocation_id.x + invocation_id.y * texture_dim + invocation_id.z * texture_dim * texture_dim;
+	// Dispatched once per Z layer of the update region rather than over the whole volume
+	let voxel = vec3<u32>(invocation_id.x, invocation_id.y, invocation_id.z + update_region.z_min);
+	let buffer_index = voxel.x + voxel.y * texture_dim + voxel.z * texture_dim * texture_dim;
 	let cell = voxel_buffer.cells[buffer_index];
-	let color = (vec4<f32>(f32(cell.r), f32(cell.g), f32(cell.b), 0.) / f32(cell.count)) / 256.;
-	textureStore(t_voxel_lightmap, vec3<i32>(invocation_id), color);
+
+	var color = vec4<f32>(0.);
+	if (cell.count > 0u) {
+		color = (vec4<f32>(f32(cell.r), f32(cell.g), f32(cell.b), 0.) / f32(cell.count)) / 256.;
+	}
+	textureStore(t_voxel_lightmap, vec3<i32>(voxel), color);
+
+	// Reset the accumulated sums so the next re-voxelization of this region starts clean
+	voxel_buffer.cells[buffer_index] = SummedColorCell(0u, 0u, 0u, 0u);
 }
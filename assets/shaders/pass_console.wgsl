@@ -0,0 +1,72 @@
+[[block]] struct Globals {
+	screen_size: vec2<f32>;
+	inv_screen_size: vec2<f32>;
+	time: f32;
+	delta_time: f32;
+	frame_index: u32;
+	exposure: f32;
+	debug: vec4<f32>;
+};
+[[block]] struct ConsoleText {
+	columns: u32;
+	rows: u32;
+	// Cell the blinking cursor is drawn under
+	cursor: u32;
+	// Screen pixels per font pixel
+	scale: u32;
+	// Characters from space (32) to underscore (95), each with bit `row * 5 + column` set where its 5 by 6 glyph is filled
+	glyphs: array<u32, 64>;
+	// Character code in each cell, row by row from the top
+	cells: array<u32>;
+};
+
+// Uniforms
+[[group(0), binding(0)]] var<uniform> globals: Globals;
+[[group(3), binding(0)]] var<storage, read> console: ConsoleText;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput) -> VertexOutput {
+	return VertexOutput(vec4<f32>(model.position, 1.));
+}
+
+// Fragment shader
+// Drawn into a viewport along the top of the screen, over a translucent backdrop that keeps the text readable against the scene
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let backdrop = vec4<f32>(0.01, 0.01, 0.015, 0.8);
+
+	let font_pixel = vec2<u32>(in.position.xy) / vec2<u32>(console.scale);
+	let cell = font_pixel / vec2<u32>(6u, 8u);
+	if (cell.x >= console.columns || cell.y >= console.rows) {
+		return backdrop;
+	}
+	let index = cell.y * console.columns + cell.x;
+
+	// Glyphs sit one font pixel in from the top left of their cell, so wrapping below zero puts the gap outside of them
+	let texel = font_pixel - cell * vec2<u32>(6u, 8u) - vec2<u32>(1u);
+	let character = console.cells[index];
+	var filled = false;
+	if (texel.x < 5u && texel.y < 6u && character >= 32u && character < 96u) {
+		filled = ((console.glyphs[character - 32u] >> (texel.y * 5u + texel.x)) & 1u) != 0u;
+	}
+
+	// The cursor is an underline blinking twice a second
+	if (index == console.cursor && texel.x < 5u && texel.y == 6u && fract(globals.time * 2.) < 0.5) {
+		filled = true;
+	}
+
+	// The line being typed stands out from the output above it
+	let text = select(vec3<f32>(0.75, 0.8, 0.75), vec3<f32>(1., 0.95, 0.7), cell.y == console.rows - 1u);
+	return select(backdrop, vec4<f32>(text, 1.), filled);
+}
@@ -1,13 +1,10 @@
 // Uniforms
-[[group(0), binding(0)]] var t_frame: texture_2d<f32>;
-[[group(0), binding(1)]] var s_frame: sampler;
+[[group(3), binding(0)]] var t_debug_view: texture_2d<f32>;
+[[group(3), binding(1)]] var s_debug_view: sampler;
 
 // Attributes
 struct VertexInput {
 	[[location(0)]] position: vec3<f32>;
-	[[location(1)]] uv: vec2<f32>;
-	[[location(2)]] normal: vec3<f32>;
-	[[location(3)]] tangent: vec3<f32>;
 };
 
 // Varyings
@@ -25,13 +22,6 @@ fn main(model: VertexInput) -> VertexOutput {
 // Fragment shader
 [[stage(fragment)]]
 fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-	var color = textureSample(t_frame, s_frame, in.uv).rgb;
-
-	// Tone mapping
-	color = color / (color + vec3<f32>(1.));
-
-	// Gamma correction (linear to gamma)
-	color = pow(color, vec3<f32>(1. / 2.2));
-
-	return vec4<f32>(color, 1.);
+	// Drawn over the finished frame, blended by the debug texture's own alpha
+	return textureSample(t_debug_view, s_debug_view, in.uv);
 }
@@ -0,0 +1,90 @@
+[[block]] struct Camera {
+	v_matrix: mat4x4<f32>;
+	p_matrix: mat4x4<f32>;
+	inv_v_matrix: mat4x4<f32>;
+	inv_p_matrix: mat4x4<f32>;
+	prev_v_matrix: mat4x4<f32>;
+	prev_p_matrix: mat4x4<f32>;
+};
+[[block]] struct DistanceField {
+	// Center of the first voxel, where the volume's first texel lies
+	min: vec3<f32>;
+	voxel_size: f32;
+	resolution: vec3<f32>;
+	shading: u32;
+};
+
+// Uniforms
+[[group(1), binding(0)]] var<uniform> camera: Camera;
+[[group(3), binding(0)]] var<uniform> distance_field: DistanceField;
+[[group(3), binding(1)]] var t_distance_field: texture_3d<f32>;
+[[group(3), binding(2)]] var s_distance_field: sampler;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] uv: vec2<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput) -> VertexOutput {
+	return VertexOutput(vec4<f32>(model.position, 1.), model.position.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5));
+}
+
+// Gradient in RGB and distance in alpha, with points beyond the volume adding their distance to its bounds
+fn sample_distance_field(position: vec3<f32>) -> vec4<f32> {
+	let bounds_max = distance_field.min + (distance_field.resolution - 1.) * distance_field.voxel_size;
+	let clamped = clamp(position, distance_field.min, bounds_max);
+
+	let uvw = ((clamped - distance_field.min) / distance_field.voxel_size + 0.5) / distance_field.resolution;
+	let texel = textureSampleLevel(t_distance_field, s_distance_field, uvw, 0.);
+
+	return vec4<f32>(texel.xyz, texel.w + length(position - clamped));
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let MAX_STEPS = 128u;
+	let MAX_DISTANCE = 200.;
+
+	// Rays leave the camera through a point partway into the frustum, which works with either depth convention
+	let origin = (camera.inv_v_matrix * vec4<f32>(0., 0., 0., 1.)).xyz;
+	let xy = vec2<f32>(in.uv.x, 1. - in.uv.y) * 2. - 1.;
+	let through = camera.inv_v_matrix * camera.inv_p_matrix * vec4<f32>(xy, 0.5, 1.);
+	let direction = normalize(through.xyz / through.w - origin);
+
+	var travelled = 0.;
+	var steps = 0u;
+	var field = vec4<f32>(0.);
+	loop {
+		if (steps >= MAX_STEPS) {
+			break;
+		}
+
+		field = sample_distance_field(origin + direction * travelled);
+		// Surfaces count as hit within a fraction of a voxel, since the field is blurry below that
+		if (abs(field.w) < distance_field.voxel_size * 0.25 || travelled > MAX_DISTANCE) {
+			break;
+		}
+
+		travelled = travelled + max(abs(field.w), distance_field.voxel_size * 0.1);
+		steps = steps + 1u;
+	}
+
+	// Rays that took more steps skimmed past more geometry, which darkens them like a cheap ambient occlusion
+	let effort = f32(steps) / f32(MAX_STEPS);
+	if (travelled > MAX_DISTANCE || steps == MAX_STEPS) {
+		return vec4<f32>(vec3<f32>(0.05, 0.05, 0.1) + vec3<f32>(effort), 1.);
+	}
+
+	let normal = normalize(field.xyz + vec3<f32>(0.00001));
+	let shading = (normal * 0.5 + 0.5) * (1. - effort);
+	return vec4<f32>(shading, 1.);
+}
@@ -0,0 +1,91 @@
+[[block]] struct Globals {
+	screen_size: vec2<f32>;
+	inv_screen_size: vec2<f32>;
+	time: f32;
+	delta_time: f32;
+	frame_index: u32;
+	exposure: f32;
+	debug: vec4<f32>;
+};
+
+// Written by the auto exposure compute shader, with an exposure of 1 while it's turned off
+[[block]] struct AdaptedExposure {
+	average_log_luminance: f32;
+	exposure: f32;
+	adapted: u32;
+};
+
+[[block]] struct FinalComposite {
+	saturation: f32;
+	contrast: f32;
+	vignette: f32;
+	grain: f32;
+	// Must match the stages in final_composite.rs: 1 for color grading, 2 for the vignette, and 4 for grain
+	stages: u32;
+};
+
+// Uniforms
+[[group(0), binding(0)]] var<uniform> globals: Globals;
+[[group(3), binding(0)]] var t_frame: texture_2d<f32>;
+[[group(3), binding(1)]] var s_frame: sampler;
+[[group(3), binding(2)]] var<storage, read> adapted_exposure: AdaptedExposure;
+[[group(3), binding(3)]] var<uniform> final_composite: FinalComposite;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] uv: vec2<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput) -> VertexOutput {
+	return VertexOutput(vec4<f32>(model.position, 1.), model.position.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5));
+}
+
+// A random number between 0 and 1 for each pixel, different every frame
+fn grain_noise(pixel: vec2<u32>) -> f32 {
+	var hash = pixel.x * 1973u + pixel.y * 9277u + globals.frame_index * 26699u;
+	hash = (hash ^ (hash >> 16u)) * 0x45d9f3bu;
+	hash = (hash ^ (hash >> 16u)) * 0x45d9f3bu;
+	hash = hash ^ (hash >> 16u);
+	return f32(hash & 0xffffu) / 65535.;
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	// The camera's exposure compensates on top of whatever the eye has adapted to
+	var color = textureSample(t_frame, s_frame, in.uv).rgb * globals.exposure * adapted_exposure.exposure;
+
+	// Tone mapping
+	color = color / (color + vec3<f32>(1.));
+
+	// Gamma correction (linear to gamma)
+	color = pow(color, vec3<f32>(1. / 2.2));
+
+	// The rest is graded on the gamma corrected colors, whose middle gray of 0.5 is what the eye sees as halfway between black and white
+	if ((final_composite.stages & 1u) != 0u) {
+		let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+		color = mix(vec3<f32>(luminance), color, final_composite.saturation);
+		color = (color - vec3<f32>(0.5)) * final_composite.contrast + vec3<f32>(0.5);
+	}
+
+	if ((final_composite.stages & 2u) != 0u) {
+		// 0 in the middle of the screen and 1 in its corners
+		let offset = in.uv - vec2<f32>(0.5);
+		let falloff = dot(offset, offset) * 2.;
+		color = color * (1. - final_composite.vignette * falloff * falloff);
+	}
+
+	if ((final_composite.stages & 4u) != 0u) {
+		color = color + vec3<f32>((grain_noise(vec2<u32>(in.position.xy)) - 0.5) * final_composite.grain);
+	}
+
+	return vec4<f32>(clamp(color, vec3<f32>(0.), vec3<f32>(1.)), 1.);
+}
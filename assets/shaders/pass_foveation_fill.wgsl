@@ -0,0 +1,132 @@
+[[block]] struct Globals {
+	screen_size: vec2<f32>;
+	inv_screen_size: vec2<f32>;
+	time: f32;
+	delta_time: f32;
+	frame_index: u32;
+	exposure: f32;
+	debug: vec4<f32>;
+};
+[[block]] struct Foveation {
+	center: vec2<f32>;
+	inner_radius: f32;
+	outer_radius: f32;
+	enabled: u32;
+	checkerboard: u32;
+	// Zero until a checkerboard shaded frame has been reconstructed, so there's a previous one to fill the skipped half in from
+	history_valid: u32;
+};
+
+// Uniforms
+[[group(0), binding(0)]] var<uniform> globals: Globals;
+[[group(3), binding(0)]] var<uniform> foveation: Foveation;
+[[group(3), binding(1)]] var t_shaded: texture_2d<f32>;
+[[group(3), binding(2)]] var s_shaded: sampler;
+[[group(3), binding(3)]] var t_history: texture_2d<f32>;
+[[group(3), binding(4)]] var s_history: sampler;
+[[group(3), binding(5)]] var t_velocity: texture_2d<f32>;
+[[group(3), binding(6)]] var s_velocity: sampler;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] uv: vec2<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput) -> VertexOutput {
+	return VertexOutput(vec4<f32>(model.position, 1.), model.position.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5));
+}
+
+// Must match the same function in the PBR shading pass, which takes turns between the two halves of a checkerboard each frame
+fn checkerboard_shaded(pixel: vec2<i32>) -> bool {
+	return foveation.checkerboard == 0u || (pixel.x + pixel.y + i32(globals.frame_index % 2u)) % 2 == 0;
+}
+
+// Must match the same function in the PBR shading pass, which decides which pixels get shaded
+fn foveation_shaded(pixel: vec2<i32>) -> bool {
+	if (foveation.enabled == 0u) {
+		return checkerboard_shaded(pixel);
+	}
+
+	let center = foveation.center * globals.screen_size;
+	let distance = length(vec2<f32>(pixel) + vec2<f32>(0.5) - center) / (globals.screen_size.y * 0.5);
+
+	if (distance < foveation.inner_radius) {
+		return checkerboard_shaded(pixel);
+	}
+	if (distance < foveation.outer_radius) {
+		return (pixel.x + pixel.y) % 2 == 0;
+	}
+	return pixel.x % 2 == 0 && pixel.y % 2 == 0;
+}
+
+// Where foveation shades every pixel, so only checkerboard shading could have left one out
+fn full_rate(pixel: vec2<i32>) -> bool {
+	if (foveation.enabled == 0u) {
+		return true;
+	}
+
+	let center = foveation.center * globals.screen_size;
+	return length(vec2<f32>(pixel) + vec2<f32>(0.5) - center) / (globals.screen_size.y * 0.5) < foveation.inner_radius;
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let pixel = vec2<i32>(in.position.xy);
+
+	// The history is sampled before any pixel is discarded, since implicit derivatives need every fragment in the quad to still be running
+	let uv = (vec2<f32>(pixel) + vec2<f32>(0.5)) * globals.inv_screen_size;
+	let history_uv = uv - textureLoad(t_velocity, pixel, 0).xy;
+	let history = textureSample(t_history, s_history, history_uv);
+
+	// Shaded pixels are already in the target, which is loaded rather than cleared
+	if (foveation_shaded(pixel)) {
+		discard;
+	}
+
+	// The half of the checkerboard skipped this frame was shaded last frame, so it's found there by its motion since
+	// The history is clamped to the colors of the four neighbors shaded this frame, which throws it out where the pixel was uncovered or has changed since
+	if (foveation.checkerboard != 0u && foveation.history_valid != 0u && full_rate(pixel)) {
+		if (all(history_uv >= vec2<f32>(0.)) && all(history_uv <= vec2<f32>(1.))) {
+			let last_pixel = vec2<i32>(globals.screen_size) - vec2<i32>(1);
+			var neighborhood_min = vec4<f32>(65504.);
+			var neighborhood_max = vec4<f32>(-65504.);
+			for (var y: i32 = -1; y <= 1; y = y + 1) {
+				for (var x: i32 = -1; x <= 1; x = x + 1) {
+					let neighbor = clamp(pixel + vec2<i32>(x, y), vec2<i32>(0), last_pixel);
+					if (foveation_shaded(neighbor) && abs(x) + abs(y) == 1) {
+						let color = textureLoad(t_shaded, neighbor, 0);
+						neighborhood_min = min(neighborhood_min, color);
+						neighborhood_max = max(neighborhood_max, color);
+					}
+				}
+			}
+
+			return clamp(history, neighborhood_min, neighborhood_max);
+		}
+	}
+
+	// Every rate leaves each skipped pixel at least one shaded neighbor to average
+	let last_pixel = vec2<i32>(globals.screen_size) - vec2<i32>(1);
+	var sum = vec4<f32>(0.);
+	var count = 0.;
+	for (var y: i32 = -1; y <= 1; y = y + 1) {
+		for (var x: i32 = -1; x <= 1; x = x + 1) {
+			let neighbor = clamp(pixel + vec2<i32>(x, y), vec2<i32>(0), last_pixel);
+			if (foveation_shaded(neighbor) && !(x == 0 && y == 0)) {
+				sum = sum + textureLoad(t_shaded, neighbor, 0);
+				count = count + 1.;
+			}
+		}
+	}
+
+	return sum / max(count, 1.);
+}
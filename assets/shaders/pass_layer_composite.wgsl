@@ -0,0 +1,27 @@
+// Uniforms
+[[group(3), binding(0)]] var t_layer: texture_2d<f32>;
+[[group(3), binding(1)]] var s_layer: sampler;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] uv: vec2<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput) -> VertexOutput {
+	return VertexOutput(vec4<f32>(model.position, 1.), model.position.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5));
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	// The layer was cleared to transparent black, so its color is already premultiplied by its coverage and the pipeline's blend state does the rest
+	return textureSample(t_layer, s_layer, in.uv);
+}
@@ -1,27 +1,15 @@
-[[block]] struct Camera {
-	v_matrix: mat4x4<f32>;
-	p_matrix: mat4x4<f32>;
-	inv_v_matrix: mat4x4<f32>;
-	inv_p_matrix: mat4x4<f32>;
-	prev_v_matrix: mat4x4<f32>;
-	prev_p_matrix: mat4x4<f32>;
-};
-
 // Uniforms
-[[group(0), binding(0)]] var<uniform> camera: Camera;
-[[group(1), binding(0)]] var t_color: texture_2d<f32>;
-[[group(1), binding(1)]] var s_color: sampler;
-[[group(1), binding(2)]] var t_z_buffer_previous: texture_depth_2d;
-[[group(1), binding(3)]] var s_z_buffer_previous: sampler;
-[[group(1), binding(4)]] var t_z_buffer: texture_depth_2d;
-[[group(1), binding(5)]] var s_z_buffer: sampler;
+[[group(3), binding(0)]] var t_color: texture_2d<f32>;
+[[group(3), binding(1)]] var s_color: sampler;
+// Screen space motion of each surface since the previous frame, which includes the motion of moving objects and not only the camera's
+[[group(3), binding(2)]] var t_velocity: texture_2d<f32>;
+[[group(3), binding(3)]] var s_velocity: sampler;
+[[group(3), binding(4)]] var t_z_buffer: texture_depth_2d;
+[[group(3), binding(5)]] var s_z_buffer: sampler;
 
 // Attributes
 struct VertexInput {
 	[[location(0)]] position: vec3<f32>;
-	[[location(1)]] uv: vec2<f32>;
-	[[location(2)]] normal: vec3<f32>;
-	[[location(3)]] tangent: vec3<f32>;
 };
 
 // Varyings
@@ -36,34 +24,16 @@ fn main(model: VertexInput) -> VertexOutput {
 	return VertexOutput(vec4<f32>(model.position, 1.), model.position.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5));
 }
 
-fn world_position_from_depth(uv: vec2<f32>, z: f32) -> vec3<f32> {
-	if (z == 1.) {
-		return vec3<f32>(0.);
-	}
-
-	let xy = vec2<f32>(uv.x, 1. - uv.y) * 2. - 1.;
-	let clip_space_position = vec4<f32>(xy, z, 1.);
-
-	let view_space_position = (camera.inv_v_matrix * camera.inv_p_matrix) * clip_space_position;
-	// return view_space_position.xyz;
-	return view_space_position.xyz / view_space_position.w;
-}
-
 // Fragment shader
 [[stage(fragment)]]
 fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-	let BLUR_DISTANCE = 0.01;
-	
-	let current_frame_depth = textureSample(t_z_buffer, s_z_buffer, in.uv);
-	let current_frame_world_position = world_position_from_depth(in.uv, current_frame_depth);
+	// Fraction of the last frame's motion each of the samples steps back along
+	let BLUR_DISTANCE = 0.02;
 
-	let xy = vec2<f32>(in.uv.x, 1. - in.uv.y) * 2. - 1.;
-	let clip_space_position = vec4<f32>(xy, current_frame_depth, 1.);
+	let current_frame_depth = textureSample(t_z_buffer, s_z_buffer, in.uv);
 
-	let currentPos = clip_space_position;
-	let previousPos = (camera.prev_p_matrix * camera.prev_v_matrix) * vec4<f32>(current_frame_world_position, 1.);
-	let previousPos = previousPos / previousPos.w;
-	let velocity = (currentPos - previousPos).xy * BLUR_DISTANCE;
+	// The velocity points from where the surface was to where it is, so the samples trail back toward where it came from
+	let velocity = -textureSample(t_velocity, s_velocity, in.uv).xy * BLUR_DISTANCE;
 
 	var texCoord = in.uv;
 	var color = textureSample(t_color, s_color, texCoord);
@@ -1,5 +1,23 @@
 let PI: f32 = 3.14159265359;
 
+[[block]] struct Globals {
+	screen_size: vec2<f32>;
+	inv_screen_size: vec2<f32>;
+	time: f32;
+	delta_time: f32;
+	frame_index: u32;
+	exposure: f32;
+	debug: vec4<f32>;
+	// 0 for the materials, otherwise the G-buffer pass wrote a debug color as the emission to be shown unlit
+	debug_shading: u32;
+};
+[[block]] struct AudioAnalysis {
+	// Root mean square of the most recent audio, from 0 for silence to 1 for full scale
+	amplitude: f32;
+	peak: f32;
+	// Levels of eight logarithmically spaced frequency bands from 0 to 1, lowest first, packed four to a vector
+	bands: array<vec4<f32>, 2>;
+};
 [[block]] struct Camera {
 	v_matrix: mat4x4<f32>;
 	p_matrix: mat4x4<f32>;
@@ -10,29 +28,165 @@ let PI: f32 = 3.14159265359;
 };
 [[block]] struct Light {
 	location: vec3<f32>;
+	// Luminous intensity in candela
+	intensity: f32;
+	color: vec3<f32>;
+	// Distance where the falloff reaches zero
+	radius: f32;
+	// Unit vector pointing toward the sun
+	sun_direction: vec3<f32>;
+	// Illuminance in lux, zero when the scene has no sun
+	sun_illuminance: f32;
+	sun_color: vec3<f32>;
+	// Half the angle the sun's disk spans in the sky, in radians
+	sun_angular_radius: f32;
+	// Aim of the lamp's IES profile, if it has one
+	lamp_direction: vec3<f32>;
+	lamp_has_profile: u32;
+	// Whether each light casts shadows traced through the distance field
+	lamp_distance_field_shadows: u32;
+	sun_distance_field_shadows: u32;
+	// How many of the punctual lights are in use
+	punctual_light_count: u32;
+	// Light arriving evenly from the whole sky rather than from the sun's disk
+	sky_ambient: vec3<f32>;
+	// Colors of the hemisphere light above and below, black while the voxel GI takes its place
+	hemisphere_sky_color: vec3<f32>;
+	hemisphere_ground_color: vec3<f32>;
+};
+struct PunctualLight {
+	// Ignored for a sun
+	position: vec3<f32>;
+	// Candela for a lamp or lux for a sun
+	intensity: f32;
 	color: vec3<f32>;
+	// Distance where a lamp's falloff reaches zero, or half the angle a sun's disk spans in radians
+	radius: f32;
+	// Aim of a lamp's IES profile, or the unit vector pointing toward a sun
+	direction: vec3<f32>;
+	// 0 for a lamp and 1 for a sun
+	kind: u32;
+	has_profile: u32;
+	distance_field_shadows: u32;
+	// Whether this sun's shadows are looked up in the cascaded shadow maps
+	cascaded_shadows: u32;
+	// Whether this lamp's shadows are looked up in the point shadow map
+	point_shadows: u32;
+};
+[[block]] struct PunctualLights {
+	lights: array<PunctualLight>;
+};
+[[block]] struct Shadows {
+	cascade_vp_matrices: array<mat4x4<f32>, 4>;
+	// Distance along the view direction where each cascade ends
+	cascade_far_distances: vec4<f32>;
+	// World space width of one texel of each cascade
+	cascade_texel_sizes: vec4<f32>;
+	// World space distance between each cascade's near and far planes
+	cascade_depth_ranges: vec4<f32>;
+	// Zero while the scene has no sun to cast shadows
+	enabled: u32;
+	// 0 for PCF, 1 for exponential, 2 for variance, and 3 for PCSS
+	filter: u32;
+	// How far the penumbra widens per meter between an occluder and the surface behind it
+	penumbra_spread: f32;
+};
+[[block]] struct PointShadows {
+	// Each face of the lamp's cube in the order +X, -X, +Y, -Y, +Z, -Z
+	face_vp_matrices: array<mat4x4<f32>, 6>;
+	position: vec3<f32>;
+	// Zero while the scene has no lamp to cast shadows
+	enabled: u32;
+	// World space width of one texel per meter from the lamp
+	texel_scale: f32;
+};
+[[block]] struct Foveation {
+	center: vec2<f32>;
+	inner_radius: f32;
+	outer_radius: f32;
+	enabled: u32;
+	checkerboard: u32;
+};
+[[block]] struct DistanceField {
+	// Center of the first voxel, where the volume's first texel lies
+	min: vec3<f32>;
+	voxel_size: f32;
+	resolution: vec3<f32>;
+	// 0 for no tracing, 1 for shadows, and 2 for shadows and ambient occlusion
+	shading: u32;
+};
+[[block]] struct Weather {
+	wind: vec3<f32>;
+	precipitation: u32;
+	intensity: f32;
+	// How soaked the surfaces open to the sky are, from 0 for dry to 1 for standing water in every dip
+	wetness: f32;
+	// How much snow has settled on the surfaces facing up, from 0 for none to 1 for a full cover
+	snow_cover: f32;
+	fall_speed: f32;
+	area: f32;
+};
+[[block]] struct Environment {
+	fog_color: vec3<f32>;
+	// 0 for off, 1 for linear, 2 for exponential, and 3 for height
+	fog_mode: u32;
+	fog_start: f32;
+	fog_end: f32;
+	fog_density: f32;
+	fog_height_falloff: f32;
+	fog_base_height: f32;
+	// Zero while there's no sky map
+	sky_map_intensity: f32;
+	// The prefiltered map's last mip level, which is blurred for a roughness of 1
+	prefiltered_max_mip: f32;
 };
 
 // Uniforms
-[[group(0), binding(0)]] var<uniform> camera: Camera;
-[[group(1), binding(0)]] var<uniform> light: Light;
-[[group(2), binding(0)]] var t_z_buffer: texture_depth_2d;
-[[group(2), binding(1)]] var s_z_buffer: sampler;
-[[group(2), binding(2)]] var t_world_space_normal: texture_2d<f32>;
-[[group(2), binding(3)]] var s_world_space_normal: sampler;
-[[group(2), binding(4)]] var t_albedo_map: texture_2d<f32>;
-[[group(2), binding(5)]] var s_albedo_map: sampler;
-[[group(2), binding(6)]] var t_arm_map: texture_2d<f32>;
-[[group(2), binding(7)]] var s_arm_map: sampler;
-[[group(2), binding(8)]] var t_ssao: texture_2d<f32>;
-[[group(2), binding(9)]] var s_ssao: sampler;
+[[group(0), binding(0)]] var<uniform> globals: Globals;
+[[group(0), binding(1)]] var<uniform> audio: AudioAnalysis;
+[[group(1), binding(0)]] var<uniform> camera: Camera;
+[[group(2), binding(0)]] var<uniform> light: Light;
+[[group(2), binding(1)]] var t_light_profile: texture_2d<f32>;
+[[group(2), binding(2)]] var s_light_profile: sampler;
+[[group(2), binding(3)]] var<storage, read> punctual_lights: PunctualLights;
+[[group(3), binding(0)]] var t_z_buffer: texture_depth_2d;
+[[group(3), binding(1)]] var s_z_buffer: sampler;
+[[group(3), binding(2)]] var t_world_space_normal: texture_2d<f32>;
+[[group(3), binding(3)]] var s_world_space_normal: sampler;
+[[group(3), binding(4)]] var t_albedo_map: texture_2d<f32>;
+[[group(3), binding(5)]] var s_albedo_map: sampler;
+[[group(3), binding(6)]] var t_arm_map: texture_2d<f32>;
+[[group(3), binding(7)]] var s_arm_map: sampler;
+[[group(3), binding(8)]] var t_ssao: texture_2d<f32>;
+[[group(3), binding(9)]] var s_ssao: sampler;
+[[group(3), binding(10)]] var<uniform> foveation: Foveation;
+[[group(3), binding(11)]] var<uniform> distance_field: DistanceField;
+[[group(3), binding(12)]] var t_distance_field: texture_3d<f32>;
+[[group(3), binding(13)]] var s_distance_field: sampler;
+[[group(3), binding(14)]] var<uniform> weather: Weather;
+[[group(3), binding(15)]] var t_weather_noise: texture_2d<f32>;
+[[group(3), binding(16)]] var s_weather_noise: sampler;
+[[group(3), binding(17)]] var<uniform> shadows: Shadows;
+[[group(3), binding(18)]] var t_shadow_cascades: texture_depth_2d_array;
+[[group(3), binding(19)]] var s_shadow_cascades: sampler_comparison;
+[[group(3), binding(20)]] var t_emissive_map: texture_2d<f32>;
+[[group(3), binding(21)]] var s_emissive_map: sampler;
+[[group(3), binding(22)]] var t_shadow_moments: texture_2d<f32>;
+[[group(3), binding(23)]] var s_shadow_moments: sampler;
+[[group(3), binding(24)]] var<uniform> environment: Environment;
+[[group(3), binding(25)]] var<uniform> point_shadows: PointShadows;
+[[group(3), binding(26)]] var t_point_shadow: texture_depth_2d_array;
+[[group(3), binding(27)]] var s_point_shadow: sampler_comparison;
+[[group(3), binding(28)]] var t_irradiance_map: texture_cube<f32>;
+[[group(3), binding(29)]] var s_irradiance_map: sampler;
+[[group(3), binding(30)]] var t_prefiltered_map: texture_cube<f32>;
+[[group(3), binding(31)]] var s_prefiltered_map: sampler;
+[[group(3), binding(32)]] var t_brdf_lut: texture_2d<f32>;
+[[group(3), binding(33)]] var s_brdf_lut: sampler;
 
 // Attributes
 struct VertexInput {
 	[[location(0)]] position: vec3<f32>;
-	[[location(1)]] uv: vec2<f32>;
-	[[location(2)]] normal: vec3<f32>;
-	[[location(3)]] tangent: vec3<f32>;
 };
 
 // Varyings
@@ -47,6 +201,29 @@ fn main(model: VertexInput) -> VertexOutput {
 	return VertexOutput(vec4<f32>(model.position, 1.), model.position.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5));
 }
 
+// Checkerboard shading takes turns between the two halves of a checkerboard each frame, where the pixels would otherwise all be shaded
+fn checkerboard_shaded(pixel: vec2<i32>) -> bool {
+	return foveation.checkerboard == 0u || (pixel.x + pixel.y + i32(globals.frame_index % 2u)) % 2 == 0;
+}
+
+// Must match the same function in the foveation fill pass, which fills in the pixels this one skips
+fn foveation_shaded(pixel: vec2<i32>) -> bool {
+	if (foveation.enabled == 0u) {
+		return checkerboard_shaded(pixel);
+	}
+
+	let center = foveation.center * globals.screen_size;
+	let distance = length(vec2<f32>(pixel) + vec2<f32>(0.5) - center) / (globals.screen_size.y * 0.5);
+
+	if (distance < foveation.inner_radius) {
+		return checkerboard_shaded(pixel);
+	}
+	if (distance < foveation.outer_radius) {
+		return (pixel.x + pixel.y) % 2 == 0;
+	}
+	return pixel.x % 2 == 0 && pixel.y % 2 == 0;
+}
+
 fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
 	return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
 }
@@ -97,87 +274,464 @@ fn world_position_from_depth(uv: vec2<f32>, z: f32) -> vec3<f32> {
 	return view_space_position.xyz / view_space_position.w;
 }
 
+// Inverse square falloff, windowed to smoothly reach zero at the light's radius (Karis 2013, equation 9)
+fn light_attenuation(distance: f32, radius: f32) -> f32 {
+	let window = clamp(1. - pow(distance / radius, 4.), 0., 1.);
+	return window * window / max(distance * distance, 0.0001);
+}
+
+// Fraction of the lamp's peak intensity its IES profile emits toward `l`, the direction from the fragment back to the lamp
+fn lamp_profile(lamp: PunctualLight, l: vec3<f32>) -> f32 {
+	if (lamp.has_profile == 0u) {
+		return 1.;
+	}
+
+	// Type C photometry measures the vertical angle away from the fixture's aim and the horizontal angle around it
+	let d = -l;
+	let aim = normalize(lamp.direction);
+	let reference = select(vec3<f32>(1., 0., 0.), vec3<f32>(0., 0., 1.), abs(aim.x) > 0.9);
+	let tangent = normalize(cross(aim, reference));
+	let bitangent = cross(aim, tangent);
+	let vertical_angle = acos(clamp(dot(d, aim), -1., 1.));
+	let horizontal_angle = atan2(dot(d, bitangent), dot(d, tangent));
+
+	let uv = vec2<f32>(vertical_angle / PI, fract(horizontal_angle / (2. * PI)));
+	return textureSampleLevel(t_light_profile, s_light_profile, uv, 0.).r;
+}
+
+// Distance to the nearest static surface, with points beyond the field's volume adding their distance to its bounds
+fn distance_field_distance(position: vec3<f32>) -> f32 {
+	let bounds_max = distance_field.min + (distance_field.resolution - 1.) * distance_field.voxel_size;
+	let clamped = clamp(position, distance_field.min, bounds_max);
+
+	let uvw = ((clamped - distance_field.min) / distance_field.voxel_size + 0.5) / distance_field.resolution;
+	return textureSampleLevel(t_distance_field, s_distance_field, uvw, 0.).a + length(position - clamped);
+}
+
+// Marches toward a light through the distance field, darkening by how closely the ray passes surfaces compared to how far it has gone (Quilez 2010)
+// Higher sharpness narrows the penumbra, as for a light that looks smaller from the surface
+fn distance_field_shadow(location: vec3<f32>, n: vec3<f32>, l: vec3<f32>, max_distance: f32, sharpness: f32) -> f32 {
+	// Starting off the surface keeps the ray from shadowing itself where the field is coarser than the geometry
+	let start = location + n * distance_field.voxel_size * 1.5;
+
+	var visibility = 1.;
+	var travelled = distance_field.voxel_size;
+	for (var i = 0u; i < 48u; i = i + 1u) {
+		if (travelled >= max_distance) {
+			break;
+		}
+
+		let distance = distance_field_distance(start + l * travelled);
+		visibility = min(visibility, sharpness * distance / travelled);
+		if (visibility < 0.01) {
+			return 0.;
+		}
+
+		travelled = travelled + clamp(distance, distance_field.voxel_size * 0.5, distance_field.voxel_size * 8.);
+	}
+
+	return clamp(visibility, 0., 1.);
+}
+
+// Samples the distance field at doubling distances along the normal, where any surface nearer than a sample's distance from the fragment blocks part of the surroundings (Evans 2006)
+fn distance_field_ambient_occlusion(location: vec3<f32>, n: vec3<f32>) -> f32 {
+	var occlusion = 0.;
+	var weight = 0.5;
+	for (var i = 0u; i < 5u; i = i + 1u) {
+		let sample_distance = distance_field.voxel_size * pow(2., f32(i));
+		let distance = distance_field_distance(location + n * sample_distance);
+
+		occlusion = occlusion + weight * max(sample_distance - distance, 0.) / sample_distance;
+		weight = weight * 0.5;
+	}
+
+	return clamp(1. - occlusion, 0., 1.);
+}
+
+// Must match `ESM_SHARPNESS` in the shadow moments pass
+let ESM_SHARPNESS: f32 = 8.;
+// Widest the PCSS comparisons spread, in texels of the cascade's depth map
+let PCSS_MAX_FILTER_RADIUS: f32 = 12.;
+
+// One filtered comparison of the cascade's layer of the depth array against `depth`, which is 1 where nothing lies between it and the sun
+fn shadow_cascade_compare(cascade: u32, uv: vec2<f32>, depth: f32) -> f32 {
+	return textureSampleCompareLevel(t_shadow_cascades, s_shadow_cascades, uv, i32(cascade), depth);
+}
+
+// Fraction of one cascade's texels around `uv` with nothing between `depth` and the sun, averaged over a grid of filtered comparisons spaced `radius` texels apart to soften the edges
+fn shadow_cascade_visibility(cascade: u32, uv: vec2<f32>, depth: f32, radius: f32) -> f32 {
+	let texel = radius / vec2<f32>(textureDimensions(t_shadow_cascades));
+
+	var visibility = 0.;
+	for (var y = -1; y <= 1; y = y + 1) {
+		for (var x = -1; x <= 1; x = x + 1) {
+			visibility = visibility + shadow_cascade_compare(cascade, uv + vec2<f32>(f32(x), f32(y)) * texel, depth);
+		}
+	}
+	return visibility / 9.;
+}
+
+// One texel of the cascade's quarter of the moments atlas, clamped to stay inside that quarter
+fn shadow_moments_texel(cascade: u32, texel: vec2<i32>) -> vec2<f32> {
+	let quarter_size = textureDimensions(t_shadow_moments) / 2;
+	let quarter_min = vec2<i32>(i32(cascade % 2u), i32(cascade / 2u)) * quarter_size;
+	return textureLoad(t_shadow_moments, quarter_min + clamp(texel, vec2<i32>(0), quarter_size - vec2<i32>(1)), 0).rg;
+}
+
+// The cascade's moments at `uv`, bilinearly filtered by hand since their 32 bit floats can't be filtered by a sampler
+fn shadow_moments(cascade: u32, uv: vec2<f32>) -> vec2<f32> {
+	let position = uv * vec2<f32>(textureDimensions(t_shadow_moments) / 2) - vec2<f32>(0.5);
+	let texel = vec2<i32>(floor(position));
+	let blend = fract(position);
+
+	let top = mix(shadow_moments_texel(cascade, texel), shadow_moments_texel(cascade, texel + vec2<i32>(1, 0)), blend.x);
+	let bottom = mix(shadow_moments_texel(cascade, texel + vec2<i32>(0, 1)), shadow_moments_texel(cascade, texel + vec2<i32>(1, 1)), blend.x);
+	return mix(top, bottom, blend.y);
+}
+
+// Exponential shadow maps (Annen et al. 2008), where the blurred exponential of the occluders' depth falls off exponentially behind them
+fn exponential_shadow(cascade: u32, uv: vec2<f32>, depth: f32) -> f32 {
+	let occluder_exponent = shadow_moments(cascade, uv).r;
+	let receiver_exponent = depth * shadows.cascade_depth_ranges[cascade] * ESM_SHARPNESS;
+	return clamp(exp(occluder_exponent - receiver_exponent), 0., 1.);
+}
+
+// Variance shadow maps (Donnelly and Lauritzen 2006), bounding the fraction of occluders nearer than the surface with Chebyshev's inequality
+fn variance_shadow(cascade: u32, uv: vec2<f32>, depth: f32) -> f32 {
+	let moments = shadow_moments(cascade, uv);
+	if (depth <= moments.x) {
+		return 1.;
+	}
+
+	// A floor on the variance of about a centimeter keeps flat surfaces from speckling with their own depth's imprecision
+	let min_deviation = 0.01 / shadows.cascade_depth_ranges[cascade];
+	let variance = max(moments.y - moments.x * moments.x, min_deviation * min_deviation);
+	let difference = depth - moments.x;
+	let upper_bound = variance / (variance + difference * difference);
+
+	// Cutting off the bound's low tail hides most of the light bleeding through where occluders overlap
+	return smoothStep(0.2, 1., upper_bound);
+}
+
+// Percentage-closer soft shadows (Fernando 2005), which averages the occluders found near `uv` to estimate how wide the penumbra is there
+fn pcss_shadow(cascade: u32, uv: vec2<f32>, depth: f32) -> f32 {
+	let moments_texel = 1. / vec2<f32>(textureDimensions(t_shadow_moments) / 2);
+
+	var occluder_depth_sum = 0.;
+	var occluder_count = 0.;
+	for (var y = -2; y <= 2; y = y + 1) {
+		for (var x = -2; x <= 2; x = x + 1) {
+			let occluder_depth = shadow_moments(cascade, uv + vec2<f32>(f32(x), f32(y)) * 2. * moments_texel).r;
+			if (occluder_depth < depth) {
+				occluder_depth_sum = occluder_depth_sum + occluder_depth;
+				occluder_count = occluder_count + 1.;
+			}
+		}
+	}
+	if (occluder_count == 0.) {
+		return 1.;
+	}
+
+	let occluder_distance = (depth - occluder_depth_sum / occluder_count) * shadows.cascade_depth_ranges[cascade];
+	let penumbra_texels = occluder_distance * shadows.penumbra_spread / shadows.cascade_texel_sizes[cascade];
+	return shadow_cascade_visibility(cascade, uv, depth, clamp(penumbra_texels * 0.5, 1., PCSS_MAX_FILTER_RADIUS));
+}
+
+// How much of the sun reaches a surface, looked up in the nearest cascade that covers it
+fn cascaded_shadow(location: vec3<f32>, n: vec3<f32>, l: vec3<f32>) -> f32 {
+	let eye = camera.inv_v_matrix[3].xyz;
+	let forward = -normalize(camera.inv_v_matrix[2].xyz);
+	let view_distance = dot(location - eye, forward);
+
+	// Beyond the last cascade nothing is shadowed
+	if (view_distance > shadows.cascade_far_distances[3]) {
+		return 1.;
+	}
+	var cascade = 3u;
+	for (var i = 2; i >= 0; i = i - 1) {
+		if (view_distance <= shadows.cascade_far_distances[i]) {
+			cascade = u32(i);
+		}
+	}
+
+	// Pushing the point out along the normal by more on surfaces at a grazing angle to the sun keeps them from shadowing themselves
+	let texel_size = shadows.cascade_texel_sizes[cascade];
+	let grazing = 1. - max(dot(n, l), 0.);
+	let offset_location = location + n * texel_size * (1. + 2. * grazing);
+
+	let clip = shadows.cascade_vp_matrices[cascade] * vec4<f32>(offset_location, 1.);
+	let ndc = clip.xyz / clip.w;
+	let uv = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+
+	// The depth bias is applied when the cascades are rendered, by their pipeline
+	if (shadows.filter == 1u) {
+		return exponential_shadow(cascade, uv, ndc.z);
+	} elseif (shadows.filter == 2u) {
+		return variance_shadow(cascade, uv, ndc.z);
+	} elseif (shadows.filter == 3u) {
+		return pcss_shadow(cascade, uv, ndc.z);
+	}
+	return shadow_cascade_visibility(cascade, uv, ndc.z, 1.);
+}
+
+// How much of the lamp reaches a surface, looked up in the face of its cube that the surface lies in
+fn point_shadow(location: vec3<f32>, n: vec3<f32>, l: vec3<f32>) -> f32 {
+	// The texels grow with distance from the lamp, and so does how far the point is pushed out of its own shadow
+	let distance = length(location - point_shadows.position);
+	let texel_size = distance * point_shadows.texel_scale;
+	let grazing = 1. - max(dot(n, l), 0.);
+	let offset_location = location + n * texel_size * (1. + 2. * grazing);
+
+	// The face is the one looking along the direction's major axis
+	let direction = offset_location - point_shadows.position;
+	let magnitude = abs(direction);
+	var face = select(0, 1, direction.x < 0.);
+	if (magnitude.y > magnitude.x && magnitude.y >= magnitude.z) {
+		face = select(2, 3, direction.y < 0.);
+	} elseif (magnitude.z > magnitude.x && magnitude.z > magnitude.y) {
+		face = select(4, 5, direction.z < 0.);
+	}
+
+	let clip = point_shadows.face_vp_matrices[face] * vec4<f32>(offset_location, 1.);
+	let ndc = clip.xyz / clip.w;
+	let uv = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+
+	// Beyond the lamp's reach it has no light left to shadow
+	if (ndc.z > 1.) {
+		return 1.;
+	}
+
+	let texel = 1. / vec2<f32>(textureDimensions(t_point_shadow));
+	var visibility = 0.;
+	for (var y = -1; y <= 1; y = y + 1) {
+		for (var x = -1; x <= 1; x = x + 1) {
+			visibility = visibility + textureSampleCompareLevel(t_point_shadow, s_point_shadow, uv + vec2<f32>(f32(x), f32(y)) * texel, face, ndc.z);
+		}
+	}
+	return visibility / 9.;
+}
+
+// How open a surface is to the sky above, found by tracing a soft shadow straight up through the distance field when it's available
+// Without the field every surface counts as outdoors, so floors under a roof get rained and snowed on too
+fn sky_exposure(location: vec3<f32>, n: vec3<f32>) -> f32 {
+	if (distance_field.shading == 0u) {
+		return 1.;
+	}
+	return distance_field_shadow(location, n, vec3<f32>(0., 1., 0.), 30., 4.);
+}
+
+// Reflected illumination from a light arriving along `l`, per unit of radiance
+// The specular roughness can be widened beyond the surface's roughness to approximate the highlight of a light with a visible size
+fn direct_lighting(n: vec3<f32>, v: vec3<f32>, l: vec3<f32>, albedo: vec3<f32>, roughness: f32, specular_roughness: f32, metallic: f32) -> vec3<f32> {
+	let h = normalize(v + l);
+
+	let n_dot_l = max(dot(n, l), 0.0);
+
+	// Fresnel color
+	let good_dielectric_f0 = vec3<f32>(0.04);
+	let f0 = mix(good_dielectric_f0, albedo, metallic);
+	let f = fresnel_schlick(max(dot(h, v), 0.0), f0);
+
+	// Normal distribution factor (specular highlight alignment of microfacets with halfway vector)
+	let ndf = distribution_ggx(n, h, specular_roughness);
+
+	// Geometry self-occlusion factor
+	let g = geometry_smith(n, v, l, roughness);
+
+	// Specular contribution
+	let specular = (f * ndf * g) / (4.0 * max(dot(n, v), 0.0) * n_dot_l + 0.0001);
+
+	// Portion of illumination that is not specular is diffuse
+	let specular_component = f;
+	var diffuse_component = (vec3<f32>(1.0) - specular_component);
+	diffuse_component = diffuse_component * (1.0 - metallic); // Nullify diffuse when surface is metallic
+
+	// Diffuse contribution
+	let diffuse = diffuse_component * albedo / PI;
+
+	// Bidirectional reflectance distribution function
+	let reflectance = diffuse + specular;
+	return reflectance * n_dot_l;
+}
+
+// The sky's color on surfaces facing up, the ground's on those facing down, and a blend of them on everything between
+fn hemisphere_ambient(n: vec3<f32>) -> vec3<f32> {
+	return mix(light.hemisphere_ground_color, light.hemisphere_sky_color, n.y * 0.5 + 0.5);
+}
+
+// Light from the surroundings, split into what's scattered into the surface and what its microfacets reflect toward the eye with the split-sum approximation (Karis 2013)
+// The sky map's light comes from its irradiance and prefiltered maps, while the uniform and hemisphere ambient light stand in for a sky around it, so metals still have something to reflect without one
+fn image_based_lighting(n: vec3<f32>, v: vec3<f32>, albedo: vec3<f32>, roughness: f32, metallic: f32) -> vec3<f32> {
+	let n_dot_v = max(dot(n, v), 0.);
+	let r = reflect(-v, n);
+
+	let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+	let brdf = textureSampleLevel(t_brdf_lut, s_brdf_lut, vec2<f32>(n_dot_v, roughness), 0.).rg;
+	let specular_weight = f0 * brdf.x + vec3<f32>(brdf.y);
+
+	let irradiance = light.sky_ambient + hemisphere_ambient(n) + textureSampleLevel(t_irradiance_map, s_irradiance_map, n, 0.).rgb * environment.sky_map_intensity;
+	let prefiltered = textureSampleLevel(t_prefiltered_map, s_prefiltered_map, r, roughness * environment.prefiltered_max_mip).rgb * environment.sky_map_intensity;
+	let reflected = light.sky_ambient + hemisphere_ambient(r) + prefiltered;
+
+	// Whatever isn't reflected is scattered, except by metals which absorb it
+	let diffuse = (vec3<f32>(1.) - specular_weight) * (1. - metallic) * albedo * irradiance;
+	return diffuse + reflected * specular_weight;
+}
+
+// How much of the view from the eye to the fragment the fog hides, from 0 for clear to 1 for fully fogged
+fn fog_amount(eye_location: vec3<f32>, fragment_location: vec3<f32>) -> f32 {
+	let distance = length(fragment_location - eye_location);
+
+	if (environment.fog_mode == 1u) {
+		return clamp((distance - environment.fog_start) / (environment.fog_end - environment.fog_start), 0., 1.);
+	}
+	if (environment.fog_mode == 2u) {
+		return 1. - exp(-environment.fog_density * distance);
+	}
+	if (environment.fog_mode == 3u) {
+		// The density falling off exponentially with height integrates along the ray in closed form (Wenzel 2006)
+		let falloff = max(environment.fog_height_falloff, 0.0001);
+		let eye_density = environment.fog_density * exp(-falloff * (eye_location.y - environment.fog_base_height));
+		let height_change = (fragment_location.y - eye_location.y) * falloff;
+		var height_integral = 1.;
+		if (abs(height_change) > 0.0001) {
+			height_integral = (1. - exp(-height_change)) / height_change;
+		}
+		return 1. - exp(-eye_density * distance * height_integral);
+	}
+	return 0.;
+}
+
 // Fragment shader
 [[stage(fragment)]]
 fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
 	// Texture lookup
 	let z_depth = textureSample(t_z_buffer, s_z_buffer, in.uv);
-	let normal = textureSample(t_world_space_normal, s_world_space_normal, in.uv).xyz;
+	let normal_map = textureSample(t_world_space_normal, s_world_space_normal, in.uv);
+	let normal = normal_map.xyz;
 	let albedo_map = textureSample(t_albedo_map, s_albedo_map, in.uv);
 	let arm_map = textureSample(t_arm_map, s_arm_map, in.uv);
+	let emissive = textureSample(t_emissive_map, s_emissive_map, in.uv).rgb;
 	let ssao = textureSample(t_ssao, s_ssao, in.uv).r;
 
+	// Pixels left out by foveated or checkerboard shading skip the lighting, which is most of the pass's cost
+	if (!foveation_shaded(vec2<i32>(in.position.xy))) {
+		discard;
+	}
+
+	// Debug shading shows the G-buffer pass's colors without any lighting or weather
+	if (globals.debug_shading != 0u && z_depth < 1.) {
+		return vec4<f32>(emissive, 1.);
+	}
+
 	let fragment_location = world_position_from_depth(in.uv, z_depth);
 
 	// PBR input data
-	let eye_location = camera.v_matrix[3].xyz;
-	let light_location = light.location;
-	let albedo = pow(albedo_map.rgb, vec3<f32>(2.2));
+	let eye_location = camera.inv_v_matrix[3].xyz;
+	var albedo = pow(albedo_map.rgb, vec3<f32>(2.2));
 	let alpha = albedo_map.a;
-	let ambient = vec3<f32>(0.05);
 	let ao = (1. - arm_map.x);
-	let roughness = arm_map.y;
-	let metallic = arm_map.z;
-	let light_color = vec3<f32>(5.);
-
-	// Lights
-	let lights_count = 1u;
-	var light_locations = array<vec3<f32>, 1>(light_location);
-	var light_colors = array<vec3<f32>, 1>(light_color);
+	var roughness = arm_map.y;
+	var metallic = arm_map.z;
+	// Lamps pulse with the bass of any audio being analyzed, and stay as they are in silence
+	let lamp_pulse = 1. + audio.bands[0].x + audio.bands[0].y;
 
 	// Per-fragment unit vectors
-	let v = normalize(fragment_location - eye_location);
-	let n = normalize(normal);
+	let v = normalize(eye_location - fragment_location);
+	var n = normalize(normal);
+
+	// The sky has nothing to trace from
+	let distance_field_traced = z_depth < 1.;
+
+	// Weather changes the surfaces before they're lit, only where the sky can reach them
+	if (distance_field_traced && (weather.wetness > 0. || weather.snow_cover > 0.)) {
+		let exposure = sky_exposure(fragment_location, n);
+		let noise = textureSampleLevel(t_weather_noise, s_weather_noise, fragment_location.xz * 0.1, 0.).r;
+
+		// Water filling a porous surface darkens it, and the film over it smooths its microsurface (Lagarde 2013)
+		let wetness = weather.wetness * exposure;
+		albedo = albedo * mix(1., 0.4, wetness * (1. - metallic));
+		roughness = mix(roughness, roughness * 0.3, wetness);
+
+		// Puddles spread outward from the low points of the noise across flat ground as it gets wetter, each one a smooth mirror of still water
+		let flatness = smoothStep(0.9, 0.98, n.y);
+		let puddle = flatness * smoothStep(1. - wetness, 1.1 - wetness, 1. - noise);
+		albedo = albedo * mix(1., 0.6, puddle);
+		roughness = mix(roughness, 0.02, puddle);
+		metallic = mix(metallic, 0., puddle);
+		n = normalize(mix(n, vec3<f32>(0., 1., 0.), puddle));
+
+		// Snow settles first on the surfaces facing most directly up, creeping onto steeper ones as it gets deeper, with its edge broken up by the noise
+		let snow_cover = weather.snow_cover * exposure;
+		let snow = smoothStep(1. - snow_cover, 1.15 - snow_cover, n.y * (0.85 + 0.15 * noise));
+		albedo = mix(albedo, vec3<f32>(0.9), snow);
+		roughness = mix(roughness, 0.7, snow);
+		metallic = mix(metallic, 0., snow);
+	}
+	// Models that don't receive shadows are lit as if nothing stood between them and the lights
+	let receives_shadows = normal_map.w > 0.5;
+	let distance_field_shadows = distance_field_traced && receives_shadows && distance_field.shading >= 1u;
 
 	var color = vec3<f32>(0.0);
-	for (var i: u32 = 0u; i < lights_count; i = i + 1u) {
-		let light_location = light_locations[i];
+	for (var i: u32 = 0u; i < light.punctual_light_count; i = i + 1u) {
+		let punctual_light = punctual_lights.lights[i];
+
+		if (punctual_light.kind == 1u) {
+			// A sun is far enough away that it arrives from one direction everywhere without falling off
+			// Its disk still covers a small angle of the sky, so the highlight is widened by that angle to keep smooth surfaces from reflecting it as a pinpoint
+			let l = normalize(punctual_light.direction);
+			var radiance = punctual_light.color * punctual_light.intensity;
+			var visibility = 1.;
+			// The sun's disk is small enough for sharp shadows, so its sharpness is capped at what the field can resolve
+			if (distance_field_shadows && punctual_light.distance_field_shadows != 0u && punctual_light.intensity > 0.) {
+				let sharpness = min(1. / max(tan(punctual_light.radius), 0.0001), 16.);
+				visibility = distance_field_shadow(fragment_location, n, l, 100., sharpness);
+			}
+			// The shadow maps catch the dynamic objects and fine details the distance field misses, so whichever finds the surface more shadowed wins
+			if (distance_field_traced && receives_shadows && punctual_light.cascaded_shadows != 0u && shadows.enabled != 0u) {
+				visibility = min(visibility, cascaded_shadow(fragment_location, n, l));
+			}
+			radiance = radiance * visibility;
+			let sun_roughness = sqrt(clamp(roughness * roughness + punctual_light.radius, 0., 1.));
+			color = color + direct_lighting(n, v, l, albedo, roughness, sun_roughness, metallic) * radiance;
+			continue;
+		}
 
 		// Per-light unit vectors
-		let l = normalize(light_location - fragment_location);
-		let h = normalize(v + l);
-
-		let n_dot_l = max(dot(n, l), 0.0);
+		let l = normalize(punctual_light.position - fragment_location);
 
 		// Radiance contribution by this light
-		let distance = length(light_location - fragment_location);
-		let falloff = 1.0 / (distance * distance);
-		let radiance = light_colors[i] * falloff;
-
-		// Fresnel color
-		let good_dielectric_f0 = vec3<f32>(0.04);
-		let f0 = mix(good_dielectric_f0, albedo, metallic);
-		let f = fresnel_schlick(max(dot(h, v), 0.0), f0);
-
-		// Normal distribution factor (specular highlight alignment of microfacets with halfway vector)
-		let ndf = distribution_ggx(n, h, roughness);
-
-		// Geometry self-occlusion factor
-		let g = geometry_smith(n, v, l, roughness);
-
-		// Specular contribution
-		let specular = (f * ndf * g) / (4.0 * max(dot(n, v), 0.0) * n_dot_l + 0.0001);
-
-		// Portion of illumination that is not specular is diffuse
-		let specular_component = f;
-		var diffuse_component = (vec3<f32>(1.0) - specular_component);
-		diffuse_component = diffuse_component * (1.0 - metallic); // Nullify diffuse when surface is metallic
-
-		// Diffuse contribution
-		let diffuse = diffuse_component * albedo / PI;
-
-		// Bidirectional reflectance distribution function
-		let reflectance = diffuse + specular;
-		let light_illumination = reflectance * radiance * n_dot_l;
+		let distance = length(punctual_light.position - fragment_location);
+		let falloff = light_attenuation(distance, punctual_light.radius);
+		var radiance = punctual_light.color * punctual_light.intensity * lamp_pulse * falloff * lamp_profile(punctual_light, l);
+
+		// A point light has no size to blur its shadows, so they get a fixed softness that hides the field's coarseness
+		var visibility = 1.;
+		if (distance_field_shadows && punctual_light.distance_field_shadows != 0u) {
+			visibility = distance_field_shadow(fragment_location, n, l, distance, 8.);
+		}
+		// As with the sun, the point shadow map catches what the distance field misses
+		if (distance_field_traced && receives_shadows && punctual_light.point_shadows != 0u && point_shadows.enabled != 0u) {
+			visibility = min(visibility, point_shadow(fragment_location, n, l));
+		}
+		radiance = radiance * visibility;
 
 		// Add this light to the fragment's sum of illumination
-		color = color + light_illumination;
+		color = color + direct_lighting(n, v, l, albedo, roughness, roughness, metallic) * radiance;
 	}
 
-	// Add ambient occlusion
-	let ambient_removal = ao * ssao;
-	let ambient_component = albedo * ambient * pow(ambient_removal, 3.);
+	// Add ambient occlusion, with the distance field's medium range occlusion multiplied over the finer SSAO
+	var ambient_removal = ao * ssao;
+	if (distance_field_traced && distance_field.shading >= 2u) {
+		ambient_removal = ambient_removal * distance_field_ambient_occlusion(fragment_location, n);
+	}
+	let ambient_component = image_based_lighting(n, v, albedo, roughness, metallic) * pow(ambient_removal, 3.);
+
+	color = color + ambient_component + emissive;
 
-	color = color + ambient_component;
+	// The sky is left clear, and the transparent models drawn after this pass aren't fogged
+	if (distance_field_traced) {
+		color = mix(color, environment.fog_color, fog_amount(eye_location, fragment_location));
+	}
 	return vec4<f32>(color, 1.);
 }
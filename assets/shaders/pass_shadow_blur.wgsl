@@ -0,0 +1,66 @@
+// Structs
+[[block]] struct Shadows {
+	cascade_vp_matrices: array<mat4x4<f32>, 4>;
+	cascade_far_distances: vec4<f32>;
+	cascade_texel_sizes: vec4<f32>;
+	cascade_depth_ranges: vec4<f32>;
+	enabled: u32;
+	// 0 for PCF, 1 for exponential, 2 for variance, and 3 for PCSS
+	filter: u32;
+	penumbra_spread: f32;
+};
+[[block]] struct BlurDirection {
+	direction: vec4<f32>;
+};
+
+// Uniforms
+[[group(3), binding(0)]] var<uniform> shadows: Shadows;
+[[group(3), binding(1)]] var<uniform> blur: BlurDirection;
+[[group(3), binding(2)]] var t_moments: texture_2d<f32>;
+[[group(3), binding(3)]] var s_moments: sampler;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput) -> VertexOutput {
+	return VertexOutput(vec4<f32>(model.position, 1.));
+}
+
+// Fragment shader
+// One direction of a separable 9 texel binomial blur, kept inside the cascade's own quarter of the atlas so neighboring cascades don't bleed into each other
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	var weights = array<f32, 5>(70. / 256., 56. / 256., 28. / 256., 8. / 256., 1. / 256.);
+
+	let quarter_size = textureDimensions(t_moments) / 2;
+	let texel = vec2<i32>(in.position.xy);
+	let quarter_min = (texel / quarter_size) * quarter_size;
+	let quarter_max = quarter_min + quarter_size - vec2<i32>(1);
+	let direction = vec2<i32>(blur.direction.xy);
+
+	// Exponential moments are stored as their exponents, so they're averaged in log space around the center one
+	if (shadows.filter == 1u) {
+		let center = textureLoad(t_moments, texel, 0).r;
+		var sum = 0.;
+		for (var i = -4; i <= 4; i = i + 1) {
+			let exponent = textureLoad(t_moments, clamp(texel + direction * i, quarter_min, quarter_max), 0).r;
+			sum = sum + weights[abs(i)] * exp(exponent - center);
+		}
+		return vec4<f32>(center + log(sum), 0., 0., 1.);
+	}
+
+	var sum = vec2<f32>(0.);
+	for (var i = -4; i <= 4; i = i + 1) {
+		sum = sum + weights[abs(i)] * textureLoad(t_moments, clamp(texel + direction * i, quarter_min, quarter_max), 0).rg;
+	}
+	return vec4<f32>(sum, 0., 1.);
+}
@@ -0,0 +1,71 @@
+// Structs
+[[block]] struct Shadows {
+	cascade_vp_matrices: array<mat4x4<f32>, 4>;
+	cascade_far_distances: vec4<f32>;
+	cascade_texel_sizes: vec4<f32>;
+	cascade_depth_ranges: vec4<f32>;
+	enabled: u32;
+	// 0 for PCF, 1 for exponential, 2 for variance, and 3 for PCSS
+	filter: u32;
+	penumbra_spread: f32;
+};
+
+// Uniforms
+[[group(3), binding(0)]] var<uniform> shadows: Shadows;
+[[group(3), binding(1)]] var t_shadow_cascades: texture_depth_2d_array;
+[[group(3), binding(2)]] var s_shadow_cascades: sampler;
+
+// How sharply an exponential shadow falls off per meter behind its occluder, where higher values leak less light but ring more at the edges
+let ESM_SHARPNESS: f32 = 8.;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput) -> VertexOutput {
+	return VertexOutput(vec4<f32>(model.position, 1.));
+}
+
+fn cascade_depth(cascade: u32, texel: vec2<i32>) -> f32 {
+	return textureLoad(t_shadow_cascades, texel, i32(cascade), 0);
+}
+
+// Fragment shader
+// Each cascade fills its own quarter of the atlas, with every moments texel standing for 2x2 texels of the cascade's depth map
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let quarter_size = vec2<i32>(textureDimensions(t_shadow_cascades)) / 2;
+	let atlas_texel = vec2<i32>(in.position.xy);
+	let quarter = atlas_texel / quarter_size;
+	let cascade = u32(quarter.x + quarter.y * 2);
+	let texel = (atlas_texel - quarter * quarter_size) * 2;
+
+	let depths = vec4<f32>(
+		cascade_depth(cascade, texel),
+		cascade_depth(cascade, texel + vec2<i32>(1, 0)),
+		cascade_depth(cascade, texel + vec2<i32>(0, 1)),
+		cascade_depth(cascade, texel + vec2<i32>(1, 1))
+	);
+
+	// Exponential: the exponent alone, since the exponential itself would overflow over a cascade's depth range, summed over the texels in log space
+	if (shadows.filter == 1u) {
+		let exponents = depths * shadows.cascade_depth_ranges[cascade] * ESM_SHARPNESS;
+		let largest = max(max(exponents.x, exponents.y), max(exponents.z, exponents.w));
+		let exponent = largest + log(dot(exp(exponents - vec4<f32>(largest)), vec4<f32>(0.25)));
+		return vec4<f32>(exponent, 0., 0., 1.);
+	}
+	// Variance: the mean depth and mean squared depth
+	if (shadows.filter == 2u) {
+		return vec4<f32>(dot(depths, vec4<f32>(0.25)), dot(depths * depths, vec4<f32>(0.25)), 0., 1.);
+	}
+	// PCSS: the nearest depth, so the occluder search can't miss thin occluders
+	return vec4<f32>(min(min(depths.x, depths.y), min(depths.z, depths.w)), 0., 0., 1.);
+}
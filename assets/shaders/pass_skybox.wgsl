@@ -0,0 +1,61 @@
+[[block]] struct Camera {
+	v_matrix: mat4x4<f32>;
+	p_matrix: mat4x4<f32>;
+	inv_v_matrix: mat4x4<f32>;
+	inv_p_matrix: mat4x4<f32>;
+	prev_v_matrix: mat4x4<f32>;
+	prev_p_matrix: mat4x4<f32>;
+};
+[[block]] struct Environment {
+	fog_color: vec3<f32>;
+	fog_mode: u32;
+	fog_start: f32;
+	fog_end: f32;
+	fog_density: f32;
+	fog_height_falloff: f32;
+	fog_base_height: f32;
+	sky_map_intensity: f32;
+	prefiltered_max_mip: f32;
+};
+
+// Uniforms
+[[group(1), binding(0)]] var<uniform> camera: Camera;
+[[group(3), binding(0)]] var<uniform> environment: Environment;
+[[group(3), binding(1)]] var t_sky_map: texture_cube<f32>;
+[[group(3), binding(2)]] var s_sky_map: sampler;
+[[group(3), binding(3)]] var t_z_buffer: texture_depth_2d;
+[[group(3), binding(4)]] var s_z_buffer: sampler;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] uv: vec2<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput) -> VertexOutput {
+	return VertexOutput(vec4<f32>(model.position, 1.), model.position.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5));
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	// Anything the deferred pass drew is in front of the sky
+	if (textureSample(t_z_buffer, s_z_buffer, in.uv) < 1.) {
+		discard;
+	}
+
+	// The direction the pixel looks in, from the eye through the far plane
+	let ndc = vec2<f32>(in.uv.x, 1. - in.uv.y) * 2. - 1.;
+	let far = camera.inv_v_matrix * camera.inv_p_matrix * vec4<f32>(ndc, 1., 1.);
+	let direction = normalize(far.xyz / far.w - camera.inv_v_matrix[3].xyz);
+
+	let sky = textureSampleLevel(t_sky_map, s_sky_map, direction, 0.).rgb * environment.sky_map_intensity;
+	return vec4<f32>(sky, 1.);
+}
@@ -1,13 +1,10 @@
 // Uniforms
-[[group(0), binding(0)]] var t_ao: texture_2d<f32>;
-[[group(0), binding(1)]] var s_ao: sampler;
+[[group(3), binding(0)]] var t_ao: texture_2d<f32>;
+[[group(3), binding(1)]] var s_ao: sampler;
 
 // Attributes
 struct VertexInput {
 	[[location(0)]] position: vec3<f32>;
-	[[location(1)]] uv: vec2<f32>;
-	[[location(2)]] normal: vec3<f32>;
-	[[location(3)]] tangent: vec3<f32>;
 };
 
 // Varyings
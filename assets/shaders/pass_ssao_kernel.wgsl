@@ -9,23 +9,38 @@
 [[block]] struct Samples {
 	samples: array<vec4<f32>, 64>;
 };
+[[block]] struct Globals {
+	screen_size: vec2<f32>;
+	inv_screen_size: vec2<f32>;
+	time: f32;
+	delta_time: f32;
+	frame_index: u32;
+	exposure: f32;
+	debug: vec4<f32>;
+	debug_shading: u32;
+};
+[[block]] struct SsaoTemporal {
+	// Zero while there's no history to blend with
+	history_weight: f32;
+};
 
 // Uniforms
-[[group(0), binding(0)]] var<uniform> camera: Camera;
-[[group(1), binding(0)]] var<uniform> samples: Samples;
-[[group(1), binding(1)]] var t_noise: texture_2d<f32>;
-[[group(1), binding(2)]] var s_noise: sampler;
-[[group(1), binding(3)]] var t_z_buffer: texture_depth_2d;
-[[group(1), binding(4)]] var s_z_buffer: sampler;
-[[group(1), binding(5)]] var t_world_space_normal: texture_2d<f32>;
-[[group(1), binding(6)]] var s_world_space_normal: sampler;
+[[group(0), binding(0)]] var<uniform> globals: Globals;
+[[group(1), binding(0)]] var<uniform> camera: Camera;
+[[group(3), binding(0)]] var<uniform> samples: Samples;
+[[group(3), binding(1)]] var t_noise: texture_2d<f32>;
+[[group(3), binding(2)]] var s_noise: sampler;
+[[group(3), binding(3)]] var t_z_buffer: texture_depth_2d;
+[[group(3), binding(4)]] var s_z_buffer: sampler;
+[[group(3), binding(5)]] var t_world_space_normal: texture_2d<f32>;
+[[group(3), binding(6)]] var s_world_space_normal: sampler;
+[[group(3), binding(7)]] var<uniform> ssao_temporal: SsaoTemporal;
+[[group(3), binding(8)]] var t_velocity: texture_2d<f32>;
+[[group(3), binding(9)]] var s_velocity: sampler;
 
 // Attributes
 struct VertexInput {
 	[[location(0)]] position: vec3<f32>;
-	[[location(1)]] uv: vec2<f32>;
-	[[location(2)]] normal: vec3<f32>;
-	[[location(3)]] tangent: vec3<f32>;
 };
 
 // Varyings
@@ -52,16 +67,35 @@ fn world_position_from_depth(uv: vec2<f32>, z: f32) -> vec3<f32> {
 	return view_space_position.xyz / view_space_position.w;
 }
 
+// How far the history can be trusted to hold this pixel's occlusion, from 1 while it holds still to 0 once it has moved a few pixels since last frame
+// Must match `history_confidence` in the SSAO temporal pass
+fn history_confidence(velocity: vec2<f32>) -> f32 {
+	let MOTION_REJECTION_PIXELS = 4.;
+
+	if (ssao_temporal.history_weight == 0.) {
+		return 0.;
+	}
+	let motion_pixels = length(velocity * vec2<f32>(textureDimensions(t_velocity)));
+	return clamp(1. - motion_pixels / MOTION_REJECTION_PIXELS, 0., 1.);
+}
+
 // Fragment shader
 [[stage(fragment)]]
 fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-	let KERNEL_SIZE = 32u;
+	// Pixels the history has converged for take the fewest samples, while those without one take enough to stand on their own
+	let MIN_KERNEL_SIZE = 8.;
+	let MAX_KERNEL_SIZE = 32.;
 	let RADIUS = 1.;
 	let BIAS = 0.1;
 
+	let confidence = history_confidence(textureSample(t_velocity, s_velocity, in.uv).xy);
+	let kernel_size = u32(mix(MAX_KERNEL_SIZE, MIN_KERNEL_SIZE, confidence));
+
+	// The noise pattern shifts by a texel each frame so the rotations the history blends together differ from frame to frame
 	let noise_scale = vec2<f32>(textureDimensions(t_z_buffer)) / vec2<f32>(textureDimensions(t_noise));
+	let noise_offset = vec2<f32>(f32(globals.frame_index % 4u), f32((globals.frame_index / 4u) % 4u)) / vec2<f32>(textureDimensions(t_noise));
 
-	let noise = textureSample(t_noise, s_noise, in.uv * noise_scale).xyz;
+	let noise = textureSample(t_noise, s_noise, in.uv * noise_scale + noise_offset).xyz;
 	let z_depth = textureSample(t_z_buffer, s_z_buffer, in.uv);
 
 	let fragment_location = world_position_from_depth(in.uv, z_depth);
@@ -73,9 +107,12 @@ fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
 	let TBN = mat3x3<f32>(tangent, bitangent, view_space_normal);
 
 	var occlusion = 0.0;
-	for (var i = 0u; i < KERNEL_SIZE; i = i + 1u) {
+	for (var i = 0u; i < kernel_size; i = i + 1u) {
+		// The samples grow farther out along the array, so each frame takes them spread across it, starting a step further along than last frame
+		let sample_index = ((i % 8u) * 8u + i / 8u + globals.frame_index) % 64u;
+
 		// get sample position
-		var sample_position = TBN * samples.samples[i].xyz; // from tangent to view-space
+		var sample_position = TBN * samples.samples[sample_index].xyz; // from tangent to view-space
 		sample_position = view_space_fragment_location + sample_position * RADIUS;
 		
 		// Transform from view space to clip space
@@ -92,7 +129,7 @@ fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
 			occlusion = occlusion + range_check;
 		}
 	}
-	occlusion = 1. - (occlusion / f32(KERNEL_SIZE));
+	occlusion = 1. - (occlusion / f32(kernel_size));
 
 	return vec4<f32>(occlusion, occlusion, occlusion, 1.);
 }
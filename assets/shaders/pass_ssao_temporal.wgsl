@@ -0,0 +1,61 @@
+[[block]] struct SsaoTemporal {
+	// Zero while there's no history to blend with
+	history_weight: f32;
+};
+
+// Uniforms
+[[group(3), binding(0)]] var<uniform> ssao_temporal: SsaoTemporal;
+[[group(3), binding(1)]] var t_ao: texture_2d<f32>;
+[[group(3), binding(2)]] var s_ao: sampler;
+[[group(3), binding(3)]] var t_history: texture_2d<f32>;
+[[group(3), binding(4)]] var s_history: sampler;
+[[group(3), binding(5)]] var t_velocity: texture_2d<f32>;
+[[group(3), binding(6)]] var s_velocity: sampler;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] uv: vec2<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput) -> VertexOutput {
+	return VertexOutput(vec4<f32>(model.position, 1.), model.position.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5));
+}
+
+// Must match `history_confidence` in the SSAO kernel pass, so pixels take as many samples as the history they're blended with falls short by
+fn history_confidence(velocity: vec2<f32>) -> f32 {
+	let MOTION_REJECTION_PIXELS = 4.;
+
+	if (ssao_temporal.history_weight == 0.) {
+		return 0.;
+	}
+	let motion_pixels = length(velocity * vec2<f32>(textureDimensions(t_velocity)));
+	return clamp(1. - motion_pixels / MOTION_REJECTION_PIXELS, 0., 1.);
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let current = textureSample(t_ao, s_ao, in.uv).r;
+
+	// The velocity points from where the surface was last frame to where it is now
+	let velocity = textureSample(t_velocity, s_velocity, in.uv).xy;
+	let history_uv = in.uv - velocity;
+	var history_weight = ssao_temporal.history_weight * history_confidence(velocity);
+
+	// Pixels that were off screen last frame have no history to blend with
+	if (any(history_uv < vec2<f32>(0.)) || any(history_uv > vec2<f32>(1.))) {
+		history_weight = 0.;
+	}
+	let history = textureSample(t_history, s_history, history_uv).r;
+
+	let occlusion = mix(current, history, history_weight);
+	return vec4<f32>(occlusion, occlusion, occlusion, 1.);
+}
@@ -0,0 +1,95 @@
+[[block]] struct Camera {
+	v_matrix: mat4x4<f32>;
+	p_matrix: mat4x4<f32>;
+	inv_v_matrix: mat4x4<f32>;
+	inv_p_matrix: mat4x4<f32>;
+	prev_v_matrix: mat4x4<f32>;
+	prev_p_matrix: mat4x4<f32>;
+	clip_plane: vec4<f32>;
+	// This frame's jitter in XY and the previous frame's in ZW
+	jitter: vec4<f32>;
+};
+[[block]] struct TemporalAntiAliasing {
+	// Zero while the history holds nothing usable
+	history_weight: f32;
+};
+
+// Uniforms
+[[group(1), binding(0)]] var<uniform> camera: Camera;
+[[group(3), binding(0)]] var<uniform> taa: TemporalAntiAliasing;
+[[group(3), binding(1)]] var t_color: texture_2d<f32>;
+[[group(3), binding(2)]] var s_color: sampler;
+[[group(3), binding(3)]] var t_history: texture_2d<f32>;
+[[group(3), binding(4)]] var s_history: sampler;
+[[group(3), binding(5)]] var t_velocity: texture_2d<f32>;
+[[group(3), binding(6)]] var s_velocity: sampler;
+[[group(3), binding(7)]] var t_z_buffer: texture_depth_2d;
+[[group(3), binding(8)]] var s_z_buffer: sampler;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] uv: vec2<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput) -> VertexOutput {
+	return VertexOutput(vec4<f32>(model.position, 1.), model.position.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5));
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+	return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// The sky isn't drawn by the deferred pass, so its motion comes from the camera turning, found by reprojecting the far plane through last frame's matrices
+fn sky_velocity(uv: vec2<f32>) -> vec2<f32> {
+	let ndc = vec2<f32>(uv.x, 1. - uv.y) * 2. - 1.;
+	let world = camera.inv_v_matrix * camera.inv_p_matrix * vec4<f32>(ndc, 1., 1.);
+	let previous = camera.prev_p_matrix * camera.prev_v_matrix * vec4<f32>(world.xyz / world.w, 1.);
+	let motion = (ndc - camera.jitter.xy) - (previous.xy / previous.w - camera.jitter.zw);
+	return motion * vec2<f32>(0.5, -0.5);
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let texel = 1. / vec2<f32>(textureDimensions(t_color));
+	let current = textureSample(t_color, s_color, in.uv).rgb;
+
+	// The history is clamped to the range of colors around the pixel this frame, which throws out whatever was uncovered or has changed since
+	var neighborhood_min = current;
+	var neighborhood_max = current;
+	for (var y = -1; y <= 1; y = y + 1) {
+		for (var x = -1; x <= 1; x = x + 1) {
+			let neighbor = textureSample(t_color, s_color, in.uv + vec2<f32>(f32(x), f32(y)) * texel).rgb;
+			neighborhood_min = min(neighborhood_min, neighbor);
+			neighborhood_max = max(neighborhood_max, neighbor);
+		}
+	}
+
+	var velocity = textureSample(t_velocity, s_velocity, in.uv).xy;
+	if (textureSample(t_z_buffer, s_z_buffer, in.uv) == 1.) {
+		velocity = sky_velocity(in.uv);
+	}
+	let history_uv = in.uv - velocity;
+
+	// Pixels that were off screen last frame have no history to blend with
+	var history_weight = taa.history_weight;
+	if (any(history_uv < vec2<f32>(0.)) || any(history_uv > vec2<f32>(1.))) {
+		history_weight = 0.;
+	}
+	let history = clamp(textureSample(t_history, s_history, history_uv).rgb, neighborhood_min, neighborhood_max);
+
+	// Weighting each by its inverse luminance keeps single bright pixels in the HDR scene from flickering through the blend
+	let current_weight = (1. - history_weight) / (1. + luminance(current));
+	let previous_weight = history_weight / (1. + luminance(history));
+	let resolved = (current * current_weight + history * previous_weight) / max(current_weight + previous_weight, 0.0001);
+
+	return vec4<f32>(resolved, 1.);
+}
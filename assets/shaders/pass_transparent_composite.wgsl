@@ -0,0 +1,36 @@
+// Uniforms
+[[group(3), binding(0)]] var t_accumulation: texture_2d<f32>;
+[[group(3), binding(1)]] var s_accumulation: sampler;
+[[group(3), binding(2)]] var t_coverage: texture_2d<f32>;
+[[group(3), binding(3)]] var s_coverage: sampler;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] uv: vec2<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput) -> VertexOutput {
+	return VertexOutput(vec4<f32>(model.position, 1.), model.position.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5));
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let accumulation = textureSample(t_accumulation, s_accumulation, in.uv);
+	let log_transmittance = textureSample(t_coverage, s_coverage, in.uv).r;
+
+	// The fraction of the opaque scene still visible through every transparent surface
+	let revealage = exp(-log_transmittance);
+
+	// Weighted average of the transparent colors, blended over the opaque scene by their combined opacity
+	let average_color = accumulation.rgb / max(accumulation.a, 0.00001);
+	return vec4<f32>(average_color, 1. - revealage);
+}
@@ -5,40 +5,99 @@
 	inv_p_matrix: mat4x4<f32>;
 	prev_v_matrix: mat4x4<f32>;
 	prev_p_matrix: mat4x4<f32>;
+	clip_plane: vec4<f32>;
+	// This frame's jitter in XY and the previous frame's in ZW
+	jitter: vec4<f32>;
 };
 [[block]] struct Light {
 	location: vec3<f32>;
+	// Luminous intensity in candela
+	intensity: f32;
 	color: vec3<f32>;
+	// Distance where the falloff reaches zero
+	radius: f32;
+	// Unit vector pointing toward the sun
+	sun_direction: vec3<f32>;
+	// Illuminance in lux, zero when the scene has no sun
+	sun_illuminance: f32;
+	sun_color: vec3<f32>;
+	// Half the angle the sun's disk spans in the sky, in radians
+	sun_angular_radius: f32;
+	// Aim of the lamp's IES profile, if it has one
+	lamp_direction: vec3<f32>;
+	lamp_has_profile: u32;
+	// Whether each light casts shadows traced through the distance field
+	lamp_distance_field_shadows: u32;
+	sun_distance_field_shadows: u32;
+	// Light arriving evenly from the whole sky rather than from the sun's disk
+	sky_ambient: vec3<f32>;
 };
-[[block]] struct Debug {
-	values: vec4<f32>;
+[[block]] struct Globals {
+	screen_size: vec2<f32>;
+	inv_screen_size: vec2<f32>;
+	time: f32;
+	delta_time: f32;
+	frame_index: u32;
+	exposure: f32;
+	debug: vec4<f32>;
+	// 0 for the materials, 1 for normals, 2 for UVs, and 3 for a checkerboard over the UVs
+	debug_shading: u32;
+};
+struct MorphDelta {
+	position: vec4<f32>;
+	normal: vec4<f32>;
+};
+// Must match `MaterialParams`, whose factors scale what the maps give
+[[block]] struct MaterialParams {
+	base_color_factor: vec4<f32>;
+	emissive_factor: vec3<f32>;
+	roughness_factor: f32;
+	uv_tiling: vec2<f32>;
+	metallic_factor: f32;
+};
+[[block]] struct MorphTargets {
+	vertex_count: u32;
+	target_count: u32;
+	deltas: array<MorphDelta>;
 };
 
 // Uniforms
-[[group(0), binding(0)]] var<uniform> camera: Camera;
-[[group(1), binding(0)]] var<uniform> light: Light;
-[[group(2), binding(0)]] var<uniform> debug: Debug;
+[[group(0), binding(0)]] var<uniform> globals: Globals;
+[[group(1), binding(0)]] var<uniform> camera: Camera;
+[[group(2), binding(0)]] var<uniform> light: Light;
 [[group(3), binding(0)]] var t_albedo: texture_2d<f32>;
 [[group(3), binding(1)]] var s_albedo: sampler;
 [[group(3), binding(2)]] var t_arm: texture_2d<f32>;
 [[group(3), binding(3)]] var s_arm: sampler;
 [[group(3), binding(4)]] var t_normal: texture_2d<f32>;
 [[group(3), binding(5)]] var s_normal: sampler;
-[[group(3), binding(6)]] var t_voxel_lightmap: texture_3d<f32>;
-[[group(3), binding(7)]] var s_voxel_lightmap: sampler;
+[[group(3), binding(6)]] var t_emissive: texture_2d<f32>;
+[[group(3), binding(7)]] var s_emissive: sampler;
+[[group(3), binding(8)]] var t_voxel_lightmap: texture_3d<f32>;
+[[group(3), binding(9)]] var s_voxel_lightmap: sampler;
+[[group(3), binding(10)]] var<storage, read> morph_targets: MorphTargets;
+[[group(3), binding(11)]] var<uniform> material_params: MaterialParams;
 
 // Attributes
 struct VertexInput {
 	[[location(0)]] position: vec3<f32>;
 	[[location(1)]] uv: vec2<f32>;
 	[[location(2)]] normal: vec3<f32>;
-	[[location(3)]] tangent: vec3<f32>;
+	[[location(3)]] tangent: vec4<f32>;
 };
 struct InstanceInput {
 	[[location(4)]] m_matrix_0: vec4<f32>;
 	[[location(5)]] m_matrix_1: vec4<f32>;
 	[[location(6)]] m_matrix_2: vec4<f32>;
 	[[location(7)]] m_matrix_3: vec4<f32>;
+	[[location(8)]] morph_weights_0: vec4<f32>;
+	[[location(9)]] morph_weights_1: vec4<f32>;
+	[[location(10)]] receives_shadows: f32;
+	[[location(11)]] fade: f32;
+	[[location(12)]] previous_m_matrix_0: vec4<f32>;
+	[[location(13)]] previous_m_matrix_1: vec4<f32>;
+	[[location(14)]] previous_m_matrix_2: vec4<f32>;
+	[[location(15)]] previous_m_matrix_3: vec4<f32>;
 };
 
 // Varyings
@@ -46,8 +105,12 @@ struct VertexOutput {
 	[[builtin(position)]] clip_space_fragment_location: vec4<f32>;
 	[[location(0)]] world_space_fragment_location: vec3<f32>;
 	[[location(1)]] world_space_normal: vec3<f32>;
-	[[location(2)]] world_space_tangent: vec3<f32>;
+	[[location(2)]] world_space_tangent: vec4<f32>;
 	[[location(3)]] uv: vec2<f32>;
+	[[location(4)]] receives_shadows: f32;
+	[[location(5)]] fade: f32;
+	// Where the vertex was in the world the frame before, carried along by the instance's previous model matrix
+	[[location(6)]] previous_world_space_fragment_location: vec3<f32>;
 };
 
 // Frames
@@ -55,19 +118,47 @@ struct FragmentOutput {
 	[[location(0)]] world_space_normal: vec4<f32>;
 	[[location(1)]] world_space_albedo: vec4<f32>;
 	[[location(2)]] world_space_arm: vec4<f32>;
+	// Linear light given off by the surface itself, added on top of its shading
+	[[location(3)]] world_space_emissive: vec4<f32>;
+	[[location(4)]] velocity: vec2<f32>;
 };
 
+// Screen space motion since the previous frame, with the jitter taken out so still surfaces stay put
+// Both the camera's motion and the instance's own are captured, though morph targets and skinning are taken to have stood still
+fn screen_space_velocity(world_location: vec3<f32>, previous_world_location: vec3<f32>) -> vec2<f32> {
+	let current = camera.p_matrix * camera.v_matrix * vec4<f32>(world_location, 1.);
+	let previous = camera.prev_p_matrix * camera.prev_v_matrix * vec4<f32>(previous_world_location, 1.);
+	let motion = (current.xy / current.w - camera.jitter.xy) - (previous.xy / previous.w - camera.jitter.zw);
+	return motion * vec2<f32>(0.5, -0.5);
+}
+
 // Vertex shader
 [[stage(vertex)]]
-fn main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+fn main([[builtin(vertex_index)]] vertex_index: u32, model: VertexInput, instance: InstanceInput) -> VertexOutput {
 	// MVP matrices
 	let m = mat4x4<f32>(instance.m_matrix_0, instance.m_matrix_1, instance.m_matrix_2, instance.m_matrix_3);
+	let previous_m = mat4x4<f32>(instance.previous_m_matrix_0, instance.previous_m_matrix_1, instance.previous_m_matrix_2, instance.previous_m_matrix_3);
 	let vp = camera.p_matrix * camera.v_matrix;
 
+	// Blend the morph target deltas by this instance's weights
+	var morph_weights = array<f32, 8>(
+		instance.morph_weights_0.x, instance.morph_weights_0.y, instance.morph_weights_0.z, instance.morph_weights_0.w,
+		instance.morph_weights_1.x, instance.morph_weights_1.y, instance.morph_weights_1.z, instance.morph_weights_1.w,
+	);
+	var position = model.position;
+	var normal = model.normal;
+	for (var i = 0u; i < min(morph_targets.target_count, 8u); i = i + 1u) {
+		let delta = morph_targets.deltas[i * morph_targets.vertex_count + vertex_index];
+		position = position + delta.position.xyz * morph_weights[i];
+		normal = normal + delta.normal.xyz * morph_weights[i];
+	}
+
 	// Vertex data in world space
-	let world_space_fragment_location = m * vec4<f32>(model.position, 1.0);
-	let world_space_normal = m * vec4<f32>(model.normal, 0.0);
-	let world_space_tangent = m * vec4<f32>(model.tangent, 0.0);
+	let world_space_fragment_location = m * vec4<f32>(position, 1.0);
+	let previous_world_space_fragment_location = previous_m * vec4<f32>(position, 1.0);
+	let world_space_normal = m * vec4<f32>(normal, 0.0);
+	// The handedness in W is carried through untransformed
+	let world_space_tangent = vec4<f32>((m * vec4<f32>(model.tangent.xyz, 0.0)).xyz, model.tangent.w);
 
 	// Vertex data in clip space (XY: -1 to 1, Z: 0 to 1)
 	let clip_space_fragment_location = vp * world_space_fragment_location;
@@ -77,27 +168,33 @@ fn main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
 		clip_space_fragment_location,
 		world_space_fragment_location.xyz,
 		world_space_normal.xyz,
-		world_space_tangent.xyz,
+		world_space_tangent,
 		model.uv,
+		instance.receives_shadows,
+		instance.fade,
+		previous_world_space_fragment_location.xyz,
 	);
 }
 
 // Fragment shader
 [[stage(fragment)]]
-fn main(in: VertexOutput) -> FragmentOutput {
+fn main(in: VertexOutput, [[builtin(front_facing)]] front_facing: bool) -> FragmentOutput {
 	let NORMAL_MAP_STRENGTH = 1.0;
 
-	let uv = vec2<f32>(in.uv.x, 1. - in.uv.y);
+	let uv = vec2<f32>(in.uv.x, 1. - in.uv.y) * material_params.uv_tiling;
 
-	// Normal
+	// Normal, flipped on the back faces that two-sided materials don't cull so they're lit from the side they're seen from
 	var world_space_normal = normalize(in.world_space_normal);
+	if (!front_facing) {
+		world_space_normal = -world_space_normal;
+	}
 
 	// Tangent
-	var world_space_tangent = normalize(in.world_space_tangent);
+	var world_space_tangent = normalize(in.world_space_tangent.xyz);
 	world_space_tangent = normalize(world_space_tangent - dot(world_space_tangent, world_space_normal) * world_space_normal);
 
-	// Bitangent
-	let world_space_bitangent = cross(world_space_normal, world_space_tangent);
+	// Bitangent, flipped by the tangent's handedness for mirrored UVs
+	let world_space_bitangent = cross(world_space_normal, world_space_tangent) * in.world_space_tangent.w;
 
 	// Normal map
 	let from_tangent_space = mat3x3<f32>(world_space_tangent, world_space_bitangent, world_space_normal);
@@ -112,12 +209,43 @@ fn main(in: VertexOutput) -> FragmentOutput {
 	normalized_position = normalized_position + vec3<f32>(1.); // 0 to 2
 	normalized_position = normalized_position * 0.5; // 0 to 1
 
-	let lightmap_sample = textureSampleLevel(t_voxel_lightmap, s_voxel_lightmap, normalized_position, debug.values[0]);
-
+	let lightmap_sample = textureSampleLevel(t_voxel_lightmap, s_voxel_lightmap, normalized_position, globals.debug[0]);
+
+	var albedo = textureSample(t_albedo, s_albedo, uv).rgba * material_params.base_color_factor;
+	var emissive = vec4<f32>(textureSample(t_emissive, s_emissive, uv).rgb * material_params.emissive_factor, 1.);
+	let arm = textureSample(t_arm, s_arm, uv).rgba;
+
+	// Debug shading is written as emission, divided by the exposure so it reaches the screen at about its own brightness, and the PBR shading pass shows it unlit
+	// The albedo's alpha is kept so cutout materials still cut out
+	if (globals.debug_shading != 0u) {
+		var debug_color = world_space_normal * 0.5 + 0.5;
+		if (globals.debug_shading == 2u) {
+			debug_color = vec3<f32>(fract(in.uv), 0.);
+		} elseif (globals.debug_shading == 3u) {
+			let cell = floor(in.uv * 8.);
+			debug_color = vec3<f32>(mix(0.2, 0.8, step(0.25, fract((cell.x + cell.y) * 0.5))));
+		}
+		albedo = vec4<f32>(0., 0., 0., albedo.a);
+		emissive = vec4<f32>(debug_color / globals.exposure, 1.);
+	}
+
+	// Models streamed in fade in by dropping fewer of their pixels each frame, in a noise pattern the temporal anti-aliasing blends into a smooth fade
+	// Interleaved gradient noise (Jimenez 2014) spreads the dropped pixels evenly so no blocky pattern shows through
+	// Only tested once every texture is sampled, since implicit derivatives need every fragment in the quad to still be running
+	if (in.fade < 1.) {
+		let noise = fract(52.9829189 * fract(dot(in.clip_space_fragment_location.xy, vec2<f32>(0.06711056, 0.00583715))));
+		if (in.fade <= noise) {
+			discard;
+		}
+	}
+
+	// The normal's W tells the shading pass whether the shadow maps apply to the pixel
 	return FragmentOutput(
-		vec4<f32>(world_space_normal, 1.),
+		vec4<f32>(world_space_normal, in.receives_shadows),
 		// lightmap_sample,
-		textureSample(t_albedo, s_albedo, uv).rgba,
-		textureSample(t_arm, s_arm, uv).rgba,
+		albedo,
+		vec4<f32>(arm.r, arm.g * material_params.roughness_factor, arm.b * material_params.metallic_factor, arm.a),
+		emissive,
+		screen_space_velocity(in.world_space_fragment_location, in.previous_world_space_fragment_location),
 	);
 }
@@ -0,0 +1,135 @@
+let PI: f32 = 3.14159265359;
+
+[[block]] struct Camera {
+	v_matrix: mat4x4<f32>;
+	p_matrix: mat4x4<f32>;
+	inv_v_matrix: mat4x4<f32>;
+	inv_p_matrix: mat4x4<f32>;
+	prev_v_matrix: mat4x4<f32>;
+	prev_p_matrix: mat4x4<f32>;
+	clip_plane: vec4<f32>;
+	// This frame's jitter in XY and the previous frame's in ZW
+	jitter: vec4<f32>;
+};
+[[block]] struct Impostor {
+	bounds_center: vec3<f32>;
+	bounds_radius: f32;
+	yaw_steps: f32;
+	pitch_steps: f32;
+	max_pitch: f32;
+};
+
+// Uniforms
+[[group(1), binding(0)]] var<uniform> camera: Camera;
+[[group(3), binding(0)]] var<uniform> impostor: Impostor;
+[[group(3), binding(1)]] var t_normal_atlas: texture_2d<f32>;
+[[group(3), binding(2)]] var s_normal_atlas: sampler;
+[[group(3), binding(3)]] var t_albedo_atlas: texture_2d<f32>;
+[[group(3), binding(4)]] var s_albedo_atlas: sampler;
+[[group(3), binding(5)]] var t_arm_atlas: texture_2d<f32>;
+[[group(3), binding(6)]] var s_arm_atlas: sampler;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+};
+struct InstanceInput {
+	[[location(4)]] m_matrix_0: vec4<f32>;
+	[[location(5)]] m_matrix_1: vec4<f32>;
+	[[location(6)]] m_matrix_2: vec4<f32>;
+	[[location(7)]] m_matrix_3: vec4<f32>;
+	[[location(10)]] receives_shadows: f32;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] clip_space_fragment_location: vec4<f32>;
+	[[location(0)]] atlas_uv: vec2<f32>;
+	// Columns of the instance's rotation, which turn the baked model space normals into world space
+	[[location(1)]] rotation_0: vec3<f32>;
+	[[location(2)]] rotation_1: vec3<f32>;
+	[[location(3)]] rotation_2: vec3<f32>;
+	[[location(4)]] receives_shadows: f32;
+	[[location(5)]] world_space_fragment_location: vec3<f32>;
+};
+
+// Frames
+struct FragmentOutput {
+	[[location(0)]] world_space_normal: vec4<f32>;
+	[[location(1)]] world_space_albedo: vec4<f32>;
+	[[location(2)]] world_space_arm: vec4<f32>;
+	[[location(3)]] world_space_emissive: vec4<f32>;
+	[[location(4)]] velocity: vec2<f32>;
+};
+
+// Screen space motion since the previous frame, with the jitter taken out so still surfaces stay put
+// Instances don't keep their previous transforms, so only the camera's own motion is captured
+fn screen_space_velocity(world_location: vec3<f32>) -> vec2<f32> {
+	let current = camera.p_matrix * camera.v_matrix * vec4<f32>(world_location, 1.);
+	let previous = camera.prev_p_matrix * camera.prev_v_matrix * vec4<f32>(world_location, 1.);
+	let motion = (current.xy / current.w - camera.jitter.xy) - (previous.xy / previous.w - camera.jitter.zw);
+	return motion * vec2<f32>(0.5, -0.5);
+}
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+	let m = mat4x4<f32>(instance.m_matrix_0, instance.m_matrix_1, instance.m_matrix_2, instance.m_matrix_3);
+	let vp = camera.p_matrix * camera.v_matrix;
+
+	// World space bounding sphere of this instance
+	let world_center = (m * vec4<f32>(impostor.bounds_center, 1.)).xyz;
+	let world_radius = impostor.bounds_radius * max(length(m[0].xyz), max(length(m[1].xyz), length(m[2].xyz)));
+	let rotation = mat3x3<f32>(normalize(m[0].xyz), normalize(m[1].xyz), normalize(m[2].xyz));
+
+	// The direction to the camera in model space picks the baked view closest to it
+	let eye_location = camera.inv_v_matrix[3].xyz;
+	let model_space_direction = transpose(rotation) * normalize(eye_location - world_center);
+	let yaw = atan2(model_space_direction.z, model_space_direction.x);
+	let pitch = asin(clamp(model_space_direction.y, -1., 1.));
+	var column = round(yaw / (2. * PI) * impostor.yaw_steps);
+	column = column - floor(column / impostor.yaw_steps) * impostor.yaw_steps;
+	let row = round(clamp(pitch / impostor.max_pitch, 0., 1.) * (impostor.pitch_steps - 1.));
+
+	// The quad faces the camera and covers the bounding sphere, matching the orthographic framing of the baked views
+	let camera_right = camera.inv_v_matrix[0].xyz;
+	let camera_up = camera.inv_v_matrix[1].xyz;
+	let world_space_fragment_location = world_center + (camera_right * model.position.x + camera_up * model.position.y) * world_radius;
+	let cell_uv = model.position.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5);
+	let atlas_uv = (vec2<f32>(column, row) + cell_uv) / vec2<f32>(impostor.yaw_steps, impostor.pitch_steps);
+
+	return VertexOutput(
+		vp * vec4<f32>(world_space_fragment_location, 1.),
+		atlas_uv,
+		rotation[0],
+		rotation[1],
+		rotation[2],
+		instance.receives_shadows,
+		world_space_fragment_location,
+	);
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> FragmentOutput {
+	let normal = textureSample(t_normal_atlas, s_normal_atlas, in.atlas_uv);
+	let albedo = textureSample(t_albedo_atlas, s_albedo_atlas, in.atlas_uv);
+	let arm = textureSample(t_arm_atlas, s_arm_atlas, in.atlas_uv);
+
+	// Texels the model didn't cover during baking were cleared to zero alpha
+	if (albedo.a < 0.5) {
+		discard;
+	}
+
+	let rotation = mat3x3<f32>(in.rotation_0, in.rotation_1, in.rotation_2);
+	let world_space_normal = normalize(rotation * normal.xyz);
+
+	return FragmentOutput(
+		vec4<f32>(world_space_normal, in.receives_shadows),
+		vec4<f32>(albedo.rgb, 1.),
+		vec4<f32>(arm.rgb, 1.),
+		// Emissive surfaces aren't baked into the atlases, so impostors give off no light of their own
+		vec4<f32>(0., 0., 0., 1.),
+		screen_space_velocity(in.world_space_fragment_location),
+	);
+}
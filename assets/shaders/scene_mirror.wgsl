@@ -0,0 +1,68 @@
+[[block]] struct Camera {
+	v_matrix: mat4x4<f32>;
+	p_matrix: mat4x4<f32>;
+	inv_v_matrix: mat4x4<f32>;
+	inv_p_matrix: mat4x4<f32>;
+	prev_v_matrix: mat4x4<f32>;
+	prev_p_matrix: mat4x4<f32>;
+	// World space plane (normal in XYZ, distance in W) that fragments behind are discarded by, all zero when the camera has none
+	clip_plane: vec4<f32>;
+};
+
+// Uniforms
+[[group(1), binding(0)]] var<uniform> camera: Camera;
+[[group(3), binding(0)]] var<uniform> reflection_camera: Camera;
+[[group(3), binding(1)]] var t_reflection: texture_2d<f32>;
+[[group(3), binding(2)]] var s_reflection: sampler;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+	[[location(1)]] uv: vec2<f32>;
+	[[location(2)]] normal: vec3<f32>;
+	[[location(3)]] tangent: vec4<f32>;
+};
+struct InstanceInput {
+	[[location(4)]] m_matrix_0: vec4<f32>;
+	[[location(5)]] m_matrix_1: vec4<f32>;
+	[[location(6)]] m_matrix_2: vec4<f32>;
+	[[location(7)]] m_matrix_3: vec4<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] clip_space_fragment_location: vec4<f32>;
+	[[location(0)]] world_space_fragment_location: vec3<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+	let m = mat4x4<f32>(instance.m_matrix_0, instance.m_matrix_1, instance.m_matrix_2, instance.m_matrix_3);
+	let vp = camera.p_matrix * camera.v_matrix;
+
+	let world_space_fragment_location = m * vec4<f32>(model.position, 1.0);
+
+	return VertexOutput(vp * world_space_fragment_location, world_space_fragment_location.xyz);
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	// Fragments behind the camera's clip plane would show up in front of the surface being seen through, like a mirror
+	if (dot(camera.clip_plane, vec4<f32>(in.world_space_fragment_location, 1.)) < 0.) {
+		discard;
+	}
+
+	// Real mirrors absorb a little of the light they reflect
+	let REFLECTANCE = vec3<f32>(0.9);
+
+	// Points on the plane land in the same place for the viewing and reflection cameras, so projecting through the reflection camera finds the reflected color
+	// Inside another mirror's reflection this still shows the mirror as seen from the main view, which approximates nesting with only one render per mirror per level
+	let reflection_clip_space = reflection_camera.p_matrix * reflection_camera.v_matrix * vec4<f32>(in.world_space_fragment_location, 1.);
+	let reflection_uv = reflection_clip_space.xy / reflection_clip_space.w * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5);
+
+	let reflection = textureSampleLevel(t_reflection, s_reflection, reflection_uv, 0.).rgb;
+
+	return vec4<f32>(reflection * REFLECTANCE, 1.);
+}
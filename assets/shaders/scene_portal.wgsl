@@ -0,0 +1,64 @@
+[[block]] struct Camera {
+	v_matrix: mat4x4<f32>;
+	p_matrix: mat4x4<f32>;
+	inv_v_matrix: mat4x4<f32>;
+	inv_p_matrix: mat4x4<f32>;
+	prev_v_matrix: mat4x4<f32>;
+	prev_p_matrix: mat4x4<f32>;
+	// World space plane (normal in XYZ, distance in W) that fragments behind are discarded by, all zero when the camera has none
+	clip_plane: vec4<f32>;
+};
+
+// Uniforms
+[[group(1), binding(0)]] var<uniform> camera: Camera;
+[[group(3), binding(0)]] var t_portal: texture_2d<f32>;
+[[group(3), binding(1)]] var s_portal: sampler;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+	[[location(1)]] uv: vec2<f32>;
+	[[location(2)]] normal: vec3<f32>;
+	[[location(3)]] tangent: vec4<f32>;
+};
+struct InstanceInput {
+	[[location(4)]] m_matrix_0: vec4<f32>;
+	[[location(5)]] m_matrix_1: vec4<f32>;
+	[[location(6)]] m_matrix_2: vec4<f32>;
+	[[location(7)]] m_matrix_3: vec4<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] clip_space_fragment_location: vec4<f32>;
+	[[location(0)]] world_space_fragment_location: vec3<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+	let m = mat4x4<f32>(instance.m_matrix_0, instance.m_matrix_1, instance.m_matrix_2, instance.m_matrix_3);
+	let vp = camera.p_matrix * camera.v_matrix;
+
+	let world_space_fragment_location = m * vec4<f32>(model.position, 1.0);
+
+	return VertexOutput(vp * world_space_fragment_location, world_space_fragment_location.xyz);
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	// Fragments behind the camera's clip plane would show up in front of the surface being seen through, like a mirror
+	if (dot(camera.clip_plane, vec4<f32>(in.world_space_fragment_location, 1.)) < 0.) {
+		discard;
+	}
+
+	// The portal camera sees through the linked portal exactly where this portal covers the screen, so the view is sampled at the fragment's own screen position
+	// Inside another portal's view this still shows the portal as seen from the main view, which approximates nesting with only one render per portal per level
+	let clip_space = camera.p_matrix * camera.v_matrix * vec4<f32>(in.world_space_fragment_location, 1.);
+	let portal_uv = clip_space.xy / clip_space.w * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5);
+
+	let view = textureSampleLevel(t_portal, s_portal, portal_uv, 0.).rgb;
+
+	return vec4<f32>(view, 1.);
+}
@@ -0,0 +1,142 @@
+let PI: f32 = 3.14159265359;
+
+[[block]] struct Globals {
+	screen_size: vec2<f32>;
+	inv_screen_size: vec2<f32>;
+	time: f32;
+	delta_time: f32;
+	frame_index: u32;
+	exposure: f32;
+	debug: vec4<f32>;
+};
+[[block]] struct Camera {
+	v_matrix: mat4x4<f32>;
+	p_matrix: mat4x4<f32>;
+	inv_v_matrix: mat4x4<f32>;
+	inv_p_matrix: mat4x4<f32>;
+	prev_v_matrix: mat4x4<f32>;
+	prev_p_matrix: mat4x4<f32>;
+};
+[[block]] struct Light {
+	location: vec3<f32>;
+	// Luminous intensity in candela
+	intensity: f32;
+	color: vec3<f32>;
+	// Distance where the falloff reaches zero
+	radius: f32;
+	// Unit vector pointing toward the sun
+	sun_direction: vec3<f32>;
+	// Illuminance in lux, zero when the scene has no sun
+	sun_illuminance: f32;
+	sun_color: vec3<f32>;
+	// Half the angle the sun's disk spans in the sky, in radians
+	sun_angular_radius: f32;
+	// Aim of the lamp's IES profile, if it has one
+	lamp_direction: vec3<f32>;
+	lamp_has_profile: u32;
+	// Whether each light casts shadows traced through the distance field
+	lamp_distance_field_shadows: u32;
+	sun_distance_field_shadows: u32;
+	// Light arriving evenly from the whole sky rather than from the sun's disk
+	sky_ambient: vec3<f32>;
+};
+[[block]] struct Weather {
+	// Meters per second the wind carries the particles sideways
+	wind: vec3<f32>;
+	// 0 for none, 1 for rain, and 2 for snow
+	precipitation: u32;
+	intensity: f32;
+	wetness: f32;
+	snow_cover: f32;
+	fall_speed: f32;
+	// Half the size of the box around the camera the particles wrap around in
+	area: f32;
+};
+
+// Uniforms
+[[group(0), binding(0)]] var<uniform> globals: Globals;
+[[group(1), binding(0)]] var<uniform> camera: Camera;
+[[group(2), binding(0)]] var<uniform> light: Light;
+[[group(3), binding(0)]] var<uniform> weather: Weather;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	// Position within the particle's quad, from -1 to 1 on each axis
+	[[location(0)]] corner: vec2<f32>;
+	[[location(1)]] fade: f32;
+};
+
+// Random value from 0 to 1 for each input (Jarzynski and Olano 2020, PCG hash)
+fn hash(value: u32) -> f32 {
+	let state = value * 747796405u + 2891336453u;
+	let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+	return f32((word >> 22u) ^ word) / 4294967295.;
+}
+
+// Vertex shader
+// Each instance is one particle, placed from its index and the time alone so none of them need to be stored or simulated
+[[stage(vertex)]]
+fn main(model: VertexInput, [[builtin(instance_index)]] instance_index: u32) -> VertexOutput {
+	let eye = camera.inv_v_matrix[3].xyz;
+	let size = weather.area * 2.;
+
+	let seed = vec3<f32>(hash(instance_index * 4u), hash(instance_index * 4u + 1u), hash(instance_index * 4u + 2u));
+	let phase = hash(instance_index * 4u + 3u);
+
+	// Particles fall at slightly different speeds so they don't move in lockstep
+	let velocity = vec3<f32>(weather.wind.x, weather.wind.y - weather.fall_speed * (0.8 + 0.4 * phase), weather.wind.z);
+	var travelled = velocity * globals.time;
+	// Snowflakes flutter from side to side as they fall
+	if (weather.precipitation == 2u) {
+		let angle = globals.time * 1.5 + phase * 2. * PI;
+		travelled = travelled + vec3<f32>(sin(angle), 0., cos(angle * 0.8)) * 0.3;
+	}
+
+	// Wrapped into the box around the eye, so the few particles that exist seem to fill the whole sky as the camera moves through it
+	let wrapped = fract((seed * size + travelled - eye) / size) - 0.5;
+	let center = eye + wrapped * size;
+
+	// Fading out toward the sides of the box hides particles jumping across it as they wrap around
+	let edge = max(abs(wrapped.x), max(abs(wrapped.y), abs(wrapped.z)));
+	let fade = 1. - smoothStep(0.35, 0.5, edge);
+
+	let camera_right = camera.inv_v_matrix[0].xyz;
+	let camera_up = camera.inv_v_matrix[1].xyz;
+	var right = camera_right * 0.015;
+	var up = camera_up * 0.015;
+	// Raindrops are seen as streaks stretched along their path by motion blur, turned around that path to face the eye
+	if (weather.precipitation == 1u) {
+		let direction = normalize(velocity);
+		let side = cross(direction, normalize(eye - center));
+		right = select(camera_right, normalize(side), length(side) > 0.001) * 0.004;
+		up = direction * 0.2;
+	}
+
+	let location = center + right * model.position.x + up * model.position.y;
+	return VertexOutput(camera.p_matrix * camera.v_matrix * vec4<f32>(location, 1.), model.position.xy, fade);
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	// Lit only by the sun and the ambient light, since the particles are too small for missing shadows to be noticed
+	let ambient = light.sky_ambient;
+	let sun_irradiance = light.sun_color * light.sun_illuminance * max(normalize(light.sun_direction).y, 0.);
+
+	// Water is mostly seen through, while snow is a bright soft edged disk
+	var albedo = vec3<f32>(0.6);
+	var alpha = 0.3 * (1. - abs(in.corner.x));
+	if (weather.precipitation == 2u) {
+		albedo = vec3<f32>(0.9);
+		alpha = 1. - smoothStep(0.4, 1., length(in.corner));
+	}
+
+	let color = albedo / PI * sun_irradiance + albedo * ambient;
+	return vec4<f32>(color, alpha * in.fade);
+}
@@ -0,0 +1,50 @@
+[[block]] struct Camera {
+	v_matrix: mat4x4<f32>;
+	p_matrix: mat4x4<f32>;
+	inv_v_matrix: mat4x4<f32>;
+	inv_p_matrix: mat4x4<f32>;
+	prev_v_matrix: mat4x4<f32>;
+	prev_p_matrix: mat4x4<f32>;
+};
+
+// Uniforms
+[[group(1), binding(0)]] var<uniform> camera: Camera;
+[[group(3), binding(0)]] var t_albedo: texture_2d<f32>;
+[[group(3), binding(1)]] var s_albedo: sampler;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+	[[location(1)]] uv: vec2<f32>;
+};
+struct InstanceInput {
+	[[location(4)]] m_matrix_0: vec4<f32>;
+	[[location(5)]] m_matrix_1: vec4<f32>;
+	[[location(6)]] m_matrix_2: vec4<f32>;
+	[[location(7)]] m_matrix_3: vec4<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] clip_space_fragment_location: vec4<f32>;
+	[[location(0)]] uv: vec2<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+	let m = mat4x4<f32>(instance.m_matrix_0, instance.m_matrix_1, instance.m_matrix_2, instance.m_matrix_3);
+	let vp = camera.p_matrix * camera.v_matrix;
+
+	return VertexOutput(vp * m * vec4<f32>(model.position, 1.), model.uv);
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) {
+	// Only the depth is written, from the sun's point of view, with the cut out parts of alpha-tested surfaces like leaves left out so they cast their holes too
+	let uv = vec2<f32>(in.uv.x, 1. - in.uv.y);
+	if (textureSample(t_albedo, s_albedo, uv).a < 0.5) {
+		discard;
+	}
+}
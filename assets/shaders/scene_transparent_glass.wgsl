@@ -0,0 +1,134 @@
+[[block]] struct Camera {
+	v_matrix: mat4x4<f32>;
+	p_matrix: mat4x4<f32>;
+	inv_v_matrix: mat4x4<f32>;
+	inv_p_matrix: mat4x4<f32>;
+	prev_v_matrix: mat4x4<f32>;
+	prev_p_matrix: mat4x4<f32>;
+};
+[[block]] struct Light {
+	location: vec3<f32>;
+	// Luminous intensity in candela
+	intensity: f32;
+	color: vec3<f32>;
+	// Distance where the falloff reaches zero
+	radius: f32;
+	// Unit vector pointing toward the sun
+	sun_direction: vec3<f32>;
+	// Illuminance in lux, zero when the scene has no sun
+	sun_illuminance: f32;
+	sun_color: vec3<f32>;
+	// Half the angle the sun's disk spans in the sky, in radians
+	sun_angular_radius: f32;
+	// Aim of the lamp's IES profile, if it has one
+	lamp_direction: vec3<f32>;
+	lamp_has_profile: u32;
+	// Whether each light casts shadows traced through the distance field
+	lamp_distance_field_shadows: u32;
+	sun_distance_field_shadows: u32;
+	// Light arriving evenly from the whole sky rather than from the sun's disk
+	sky_ambient: vec3<f32>;
+};
+[[block]] struct TransparentSurface {
+	// RGB color with the alpha channel controlling how strongly it tints the refracted scene
+	tint: vec4<f32>;
+	index_of_refraction: f32;
+	roughness: f32;
+};
+
+// Uniforms
+[[group(1), binding(0)]] var<uniform> camera: Camera;
+[[group(2), binding(0)]] var<uniform> light: Light;
+[[group(3), binding(0)]] var<uniform> surface: TransparentSurface;
+[[group(3), binding(1)]] var t_scene_color: texture_2d<f32>;
+[[group(3), binding(2)]] var s_scene_color: sampler;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+	[[location(1)]] uv: vec2<f32>;
+	[[location(2)]] normal: vec3<f32>;
+	[[location(3)]] tangent: vec4<f32>;
+};
+struct InstanceInput {
+	[[location(4)]] m_matrix_0: vec4<f32>;
+	[[location(5)]] m_matrix_1: vec4<f32>;
+	[[location(6)]] m_matrix_2: vec4<f32>;
+	[[location(7)]] m_matrix_3: vec4<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] clip_space_fragment_location: vec4<f32>;
+	[[location(0)]] world_space_fragment_location: vec3<f32>;
+	[[location(1)]] world_space_normal: vec3<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+	let m = mat4x4<f32>(instance.m_matrix_0, instance.m_matrix_1, instance.m_matrix_2, instance.m_matrix_3);
+	let vp = camera.p_matrix * camera.v_matrix;
+
+	let world_space_fragment_location = m * vec4<f32>(model.position, 1.0);
+	let world_space_normal = m * vec4<f32>(model.normal, 0.0);
+
+	return VertexOutput(vp * world_space_fragment_location, world_space_fragment_location.xyz, world_space_normal.xyz);
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+	return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Inverse square falloff, windowed to smoothly reach zero at the light's radius (Karis 2013, equation 9)
+fn light_attenuation(distance: f32, radius: f32) -> f32 {
+	let window = clamp(1. - pow(distance / radius, 4.), 0., 1.);
+	return window * window / max(distance * distance, 0.0001);
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	// How far the view space normal shifts the sampled scene, and the widest blur radius at full roughness, both in UV units
+	let REFRACTION_STRENGTH = 0.1;
+	let MAX_BLUR_RADIUS = 0.02;
+
+	let ambient = light.sky_ambient;
+	let light_color = light.color * light.intensity;
+
+	let eye_location = camera.inv_v_matrix[3].xyz;
+	let n = normalize(in.world_space_normal);
+	let v = normalize(eye_location - in.world_space_fragment_location);
+	let l = normalize(light.location - in.world_space_fragment_location);
+	let h = normalize(v + l);
+
+	// Bend the view ray by offsetting the screen position along the normal, further for denser glass
+	let screen_uv = in.clip_space_fragment_location.xy / vec2<f32>(textureDimensions(t_scene_color));
+	let view_space_normal = (camera.v_matrix * vec4<f32>(n, 0.)).xyz;
+	let refracted_uv = screen_uv - view_space_normal.xy * vec2<f32>(1., -1.) * (surface.index_of_refraction - 1.) * REFRACTION_STRENGTH;
+
+	// Rough glass scatters the light passing through it, approximated by averaging a disk of samples around the refracted position
+	let blur_radius = surface.roughness * MAX_BLUR_RADIUS;
+	var refracted = textureSampleLevel(t_scene_color, s_scene_color, refracted_uv, 0.).rgb;
+	for (var i = 0; i < 8; i = i + 1) {
+		let angle = f32(i) * 0.785398;
+		let ring = select(1., 0.5, i % 2 == 0);
+		let offset = vec2<f32>(cos(angle), sin(angle)) * blur_radius * ring;
+		refracted = refracted + textureSampleLevel(t_scene_color, s_scene_color, refracted_uv + offset, 0.).rgb;
+	}
+	refracted = refracted / 9.;
+	refracted = mix(refracted, refracted * surface.tint.rgb, surface.tint.a);
+
+	// Glancing angles reflect more and transmit less
+	let f0 = vec3<f32>(pow((surface.index_of_refraction - 1.) / (surface.index_of_refraction + 1.), 2.));
+	let f = fresnel_schlick(abs(dot(n, v)), f0);
+
+	let distance = length(light.location - in.world_space_fragment_location);
+	let radiance = light_color * light_attenuation(distance, light.radius);
+	let shininess = mix(256., 16., surface.roughness);
+	let specular = vec3<f32>(pow(max(dot(n, h), 0.), shininess)) * radiance;
+
+	let color = refracted * (vec3<f32>(1.) - f) + (specular + ambient) * f;
+
+	return vec4<f32>(color, 1.);
+}
@@ -0,0 +1,105 @@
+[[block]] struct Camera {
+	v_matrix: mat4x4<f32>;
+	p_matrix: mat4x4<f32>;
+	inv_v_matrix: mat4x4<f32>;
+	inv_p_matrix: mat4x4<f32>;
+	prev_v_matrix: mat4x4<f32>;
+	prev_p_matrix: mat4x4<f32>;
+};
+[[block]] struct Light {
+	location: vec3<f32>;
+	// Luminous intensity in candela
+	intensity: f32;
+	color: vec3<f32>;
+	// Distance where the falloff reaches zero
+	radius: f32;
+	// Unit vector pointing toward the sun
+	sun_direction: vec3<f32>;
+	// Illuminance in lux, zero when the scene has no sun
+	sun_illuminance: f32;
+	sun_color: vec3<f32>;
+	// Half the angle the sun's disk spans in the sky, in radians
+	sun_angular_radius: f32;
+	// Aim of the lamp's IES profile, if it has one
+	lamp_direction: vec3<f32>;
+	lamp_has_profile: u32;
+	// Whether each light casts shadows traced through the distance field
+	lamp_distance_field_shadows: u32;
+	sun_distance_field_shadows: u32;
+	// Light arriving evenly from the whole sky rather than from the sun's disk
+	sky_ambient: vec3<f32>;
+};
+[[block]] struct TransparentSurface {
+	// RGB color with the opacity in the alpha channel
+	tint: vec4<f32>;
+	// Only used by glass
+	index_of_refraction: f32;
+	roughness: f32;
+};
+
+// Uniforms
+[[group(1), binding(0)]] var<uniform> camera: Camera;
+[[group(2), binding(0)]] var<uniform> light: Light;
+[[group(3), binding(0)]] var<uniform> surface: TransparentSurface;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+	[[location(1)]] uv: vec2<f32>;
+	[[location(2)]] normal: vec3<f32>;
+	[[location(3)]] tangent: vec4<f32>;
+};
+struct InstanceInput {
+	[[location(4)]] m_matrix_0: vec4<f32>;
+	[[location(5)]] m_matrix_1: vec4<f32>;
+	[[location(6)]] m_matrix_2: vec4<f32>;
+	[[location(7)]] m_matrix_3: vec4<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] clip_space_fragment_location: vec4<f32>;
+	[[location(0)]] world_space_fragment_location: vec3<f32>;
+	[[location(1)]] world_space_normal: vec3<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+	let m = mat4x4<f32>(instance.m_matrix_0, instance.m_matrix_1, instance.m_matrix_2, instance.m_matrix_3);
+	let vp = camera.p_matrix * camera.v_matrix;
+
+	let world_space_fragment_location = m * vec4<f32>(model.position, 1.0);
+	let world_space_normal = m * vec4<f32>(model.normal, 0.0);
+
+	return VertexOutput(vp * world_space_fragment_location, world_space_fragment_location.xyz, world_space_normal.xyz);
+}
+
+// Inverse square falloff, windowed to smoothly reach zero at the light's radius (Karis 2013, equation 9)
+fn light_attenuation(distance: f32, radius: f32) -> f32 {
+	let window = clamp(1. - pow(distance / radius, 4.), 0., 1.);
+	return window * window / max(distance * distance, 0.0001);
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let ambient = light.sky_ambient;
+	let light_color = light.color * light.intensity;
+
+	let eye_location = camera.inv_v_matrix[3].xyz;
+	let n = normalize(in.world_space_normal);
+	let v = normalize(eye_location - in.world_space_fragment_location);
+	let l = normalize(light.location - in.world_space_fragment_location);
+	let h = normalize(v + l);
+
+	let distance = length(light.location - in.world_space_fragment_location);
+	let radiance = light_color * light_attenuation(distance, light.radius);
+
+	// Thin surfaces are lit from whichever side faces the light
+	let diffuse = surface.tint.rgb * abs(dot(n, l));
+	let specular = vec3<f32>(pow(max(dot(n, h), 0.), 64.));
+	let color = (diffuse + specular) * radiance + surface.tint.rgb * ambient;
+
+	return vec4<f32>(color, surface.tint.a);
+}
@@ -0,0 +1,75 @@
+let PI: f32 = 3.14159265359;
+let BRDF_SAMPLE_COUNT: u32 = 512u;
+
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] tex_coords: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+	var out: VertexOutput;
+	let x = i32(vertex_index) / 2;
+	let y = i32(vertex_index) & 1;
+	let tc = vec2<f32>(f32(x) * 2.0, f32(y) * 2.0);
+	out.position = vec4<f32>(tc.x * 2.0 - 1.0, 1.0 - tc.y * 2.0, 0.0, 1.0);
+	out.tex_coords = tc;
+	return out;
+}
+
+// Low discrepancy sequence of the bits of the index mirrored behind the decimal point, which spreads the samples more evenly than random numbers
+fn van_der_corput(index: u32) -> f32 {
+	var result = 0.;
+	var digit = 0.5;
+	for (var bits = index; bits > 0u; bits = bits >> 1u) {
+		if ((bits & 1u) != 0u) {
+			result = result + digit;
+		}
+		digit = digit * 0.5;
+	}
+	return result;
+}
+
+// A microfacet normal around +Z, picked more often where the GGX distribution of the roughness has more of them
+fn importance_sample_ggx(xi: vec2<f32>, roughness: f32) -> vec3<f32> {
+	let a = roughness * roughness;
+	let phi = 2. * PI * xi.x;
+	let cos_theta = sqrt((1. - xi.y) / (1. + (a * a - 1.) * xi.y));
+	let sin_theta = sqrt(1. - cos_theta * cos_theta);
+	return vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+}
+
+// Must match `geometry_schlick_ggx` in the PBR shading pass, except for the remapping of the roughness that's meant only for punctual lights
+fn geometry_schlick_ggx(n_dot_v: f32, roughness: f32) -> f32 {
+	let k = roughness * roughness / 2.;
+	return n_dot_v / (n_dot_v * (1. - k) + k);
+}
+
+// The specular BRDF integrated over the hemisphere for a white sky, split into the scale and bias it applies to F0 (Karis 2013)
+// The angle between the view and the normal runs along U as its cosine, and the roughness along V
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let n_dot_v = max(in.tex_coords.x, 0.001);
+	let roughness = in.tex_coords.y;
+	let v = vec3<f32>(sqrt(1. - n_dot_v * n_dot_v), 0., n_dot_v);
+
+	var scale = 0.;
+	var bias = 0.;
+	for (var i = 0u; i < BRDF_SAMPLE_COUNT; i = i + 1u) {
+		let xi = vec2<f32>(f32(i) / f32(BRDF_SAMPLE_COUNT), van_der_corput(i));
+		let h = importance_sample_ggx(xi, roughness);
+		let l = normalize(2. * dot(v, h) * h - v);
+		let n_dot_l = max(l.z, 0.);
+		if (n_dot_l > 0.) {
+			let n_dot_h = max(h.z, 0.);
+			let v_dot_h = max(dot(v, h), 0.);
+			let geometry = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+			// The geometry term divided by the probability the sample was picked with, leaving out the distribution that cancels between them
+			let visibility = geometry * v_dot_h / max(n_dot_h * n_dot_v, 0.0001);
+			let fresnel = pow(1. - v_dot_h, 5.);
+			scale = scale + (1. - fresnel) * visibility;
+			bias = bias + fresnel * visibility;
+		}
+	}
+	return vec4<f32>(scale / f32(BRDF_SAMPLE_COUNT), bias / f32(BRDF_SAMPLE_COUNT), 0., 1.);
+}
@@ -0,0 +1,148 @@
+let PI: f32 = 3.14159265359;
+// The irradiance is summed over a fixed grid of directions around the hemisphere, this many around and this many up from the horizon
+let IRRADIANCE_AZIMUTH_STEPS: i32 = 64;
+let IRRADIANCE_ELEVATION_STEPS: i32 = 16;
+// More samples smooth out the noise of the rougher mips, at a cost paid only when the sky map loads
+let PREFILTER_SAMPLE_COUNT: u32 = 512u;
+
+// Structs
+[[block]] struct CubeFilter {
+	// Which face is being rendered, in the order +X, -X, +Y, -Y, +Z, -Z
+	face: u32;
+	// Roughness of the GGX lobe the prefiltered mip being rendered is blurred by
+	roughness: f32;
+	// Width of a face of the source's largest mip and of the mip being rendered
+	source_resolution: f32;
+	target_resolution: f32;
+};
+
+// Uniforms
+[[group(0), binding(0)]] var<uniform> cube_filter: CubeFilter;
+[[group(0), binding(1)]] var t_source: texture_cube<f32>;
+[[group(0), binding(2)]] var s_source: sampler;
+
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] tex_coords: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+	var out: VertexOutput;
+	let x = i32(vertex_index) / 2;
+	let y = i32(vertex_index) & 1;
+	let tc = vec2<f32>(f32(x) * 2.0, f32(y) * 2.0);
+	out.position = vec4<f32>(tc.x * 2.0 - 1.0, 1.0 - tc.y * 2.0, 0.0, 1.0);
+	out.tex_coords = tc;
+	return out;
+}
+
+// Must match `cube_face_direction` in texture.rs, which follows the face order and orientation the GPU samples cubemaps with
+fn cube_face_direction(face: u32, uv: vec2<f32>) -> vec3<f32> {
+	let c = uv * 2. - vec2<f32>(1.);
+	if (face == 0u) {
+		return normalize(vec3<f32>(1., -c.y, -c.x));
+	} elseif (face == 1u) {
+		return normalize(vec3<f32>(-1., -c.y, c.x));
+	} elseif (face == 2u) {
+		return normalize(vec3<f32>(c.x, 1., c.y));
+	} elseif (face == 3u) {
+		return normalize(vec3<f32>(c.x, -1., -c.y));
+	} elseif (face == 4u) {
+		return normalize(vec3<f32>(c.x, -c.y, 1.));
+	}
+	return normalize(vec3<f32>(-c.x, -c.y, -1.));
+}
+
+// Turns a direction given around +Z into the same direction around `n`
+fn around_normal(n: vec3<f32>, local: vec3<f32>) -> vec3<f32> {
+	let up = select(vec3<f32>(1., 0., 0.), vec3<f32>(0., 0., 1.), abs(n.z) < 0.999);
+	let tangent = normalize(cross(up, n));
+	let bitangent = cross(n, tangent);
+	return normalize(tangent * local.x + bitangent * local.y + n * local.z);
+}
+
+// The source's mip level whose texels each cover about the given solid angle, so samples spread far apart read an average of what lies between them instead of aliasing
+fn source_mip(sample_solid_angle: f32) -> f32 {
+	let texel_solid_angle = 4. * PI / (6. * cube_filter.source_resolution * cube_filter.source_resolution);
+	return max(0.5 * log2(sample_solid_angle / texel_solid_angle), 0.);
+}
+
+// Low discrepancy sequence of the bits of the index mirrored behind the decimal point, which spreads the samples more evenly than random numbers
+fn van_der_corput(index: u32) -> f32 {
+	var result = 0.;
+	var digit = 0.5;
+	for (var bits = index; bits > 0u; bits = bits >> 1u) {
+		if ((bits & 1u) != 0u) {
+			result = result + digit;
+		}
+		digit = digit * 0.5;
+	}
+	return result;
+}
+
+// A microfacet normal around `n`, picked more often where the GGX distribution of the roughness has more of them
+fn importance_sample_ggx(xi: vec2<f32>, n: vec3<f32>, roughness: f32) -> vec3<f32> {
+	let a = roughness * roughness;
+	let phi = 2. * PI * xi.x;
+	let cos_theta = sqrt((1. - xi.y) / (1. + (a * a - 1.) * xi.y));
+	let sin_theta = sqrt(1. - cos_theta * cos_theta);
+	return around_normal(n, vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta));
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+	let a = roughness * roughness;
+	let a2 = a * a;
+	let denominator = n_dot_h * n_dot_h * (a2 - 1.) + 1.;
+	return a2 / (PI * denominator * denominator);
+}
+
+// The light arriving over the hemisphere around each direction, weighted by how directly it faces it, which a diffuse surface facing that way scatters evenly
+// Scaled so a sky of the same radiance everywhere gives back that radiance, the way the uniform ambient light is added
+[[stage(fragment)]]
+fn fs_irradiance(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let n = cube_face_direction(cube_filter.face, in.tex_coords);
+	let mip = source_mip(2. * PI / f32(IRRADIANCE_AZIMUTH_STEPS * IRRADIANCE_ELEVATION_STEPS));
+
+	var irradiance = vec3<f32>(0.);
+	for (var i = 0; i < IRRADIANCE_AZIMUTH_STEPS; i = i + 1) {
+		let phi = (f32(i) + 0.5) / f32(IRRADIANCE_AZIMUTH_STEPS) * 2. * PI;
+		for (var j = 0; j < IRRADIANCE_ELEVATION_STEPS; j = j + 1) {
+			let theta = (f32(j) + 0.5) / f32(IRRADIANCE_ELEVATION_STEPS) * 0.5 * PI;
+			let direction = around_normal(n, vec3<f32>(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta)));
+			// The sine weights each ring of the grid by how much of the sphere it covers
+			irradiance = irradiance + textureSampleLevel(t_source, s_source, direction, mip).rgb * cos(theta) * sin(theta);
+		}
+	}
+	return vec4<f32>(PI * irradiance / f32(IRRADIANCE_AZIMUTH_STEPS * IRRADIANCE_ELEVATION_STEPS), 1.);
+}
+
+// The sky blurred by the GGX lobe of the roughness around each direction, for the split-sum approximation of reflections (Karis 2013)
+// The view is taken to look straight along the normal, which keeps the lobe from stretching at grazing angles but lets it be filtered ahead of time
+[[stage(fragment)]]
+fn fs_prefilter(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let n = cube_face_direction(cube_filter.face, in.tex_coords);
+	let target_texel_solid_angle = 4. * PI / (6. * cube_filter.target_resolution * cube_filter.target_resolution);
+
+	// A mirror reflects the sky as it is, averaged only over the texel being rendered
+	if (cube_filter.roughness == 0.) {
+		return vec4<f32>(textureSampleLevel(t_source, s_source, n, source_mip(target_texel_solid_angle)).rgb, 1.);
+	}
+
+	var color = vec3<f32>(0.);
+	var weight = 0.;
+	for (var i = 0u; i < PREFILTER_SAMPLE_COUNT; i = i + 1u) {
+		let xi = vec2<f32>(f32(i) / f32(PREFILTER_SAMPLE_COUNT), van_der_corput(i));
+		let h = importance_sample_ggx(xi, n, cube_filter.roughness);
+		let l = normalize(2. * dot(n, h) * h - n);
+		let n_dot_l = dot(n, l);
+		if (n_dot_l > 0.) {
+			// With the view along the normal, the probability of picking `l` is D * n_dot_h / (4 * v_dot_h), where v_dot_h is n_dot_h
+			let pdf = distribution_ggx(max(dot(n, h), 0.), cube_filter.roughness) / 4.;
+			let sample_solid_angle = max(1. / (f32(PREFILTER_SAMPLE_COUNT) * pdf + 0.0001), target_texel_solid_angle);
+			color = color + textureSampleLevel(t_source, s_source, l, source_mip(sample_solid_angle)).rgb * n_dot_l;
+			weight = weight + n_dot_l;
+		}
+	}
+	return vec4<f32>(color / max(weight, 0.0001), 1.);
+}
@@ -0,0 +1,30 @@
+[[block]] struct DebugView {
+	vp_matrix: mat4x4<f32>;
+};
+
+// Uniforms
+[[group(0), binding(0)]] var<uniform> view: DebugView;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+	[[location(1)]] color: vec4<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] color: vec4<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(in: VertexInput) -> VertexOutput {
+	return VertexOutput(view.vp_matrix * vec4<f32>(in.position, 1.), in.color);
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	return in.color;
+}
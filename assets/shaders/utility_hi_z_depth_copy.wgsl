@@ -0,0 +1,15 @@
+// Uniforms
+[[group(0), binding(0)]] var t_depth: texture_depth_2d;
+[[group(0), binding(1)]] var out_depth: texture_storage_2d<r32float, write>;
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn main([[builtin(global_invocation_id)]] invocation_id: vec3<u32>) {
+	let coordinates = vec2<i32>(invocation_id.xy);
+	let dimensions = textureDimensions(t_depth);
+	if (coordinates.x >= dimensions.x || coordinates.y >= dimensions.y) {
+		return;
+	}
+
+	let depth = textureLoad(t_depth, coordinates, 0);
+	textureStore(out_depth, coordinates, vec4<f32>(depth, 0., 0., 0.));
+}
@@ -0,0 +1,30 @@
+// Uniforms
+[[group(0), binding(0)]] var t_in_depth: texture_2d<f32>;
+[[group(0), binding(1)]] var out_depth: texture_storage_2d<r32float, write>;
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn main([[builtin(global_invocation_id)]] invocation_id: vec3<u32>) {
+	let coordinates = vec2<i32>(invocation_id.xy);
+	let out_dimensions = textureDimensions(out_depth);
+	if (coordinates.x >= out_dimensions.x || coordinates.y >= out_dimensions.y) {
+		return;
+	}
+
+	// Keep the farthest depth so a texel never claims to occlude more than the finer levels did
+	let in_dimensions = textureDimensions(t_in_depth);
+	var depth = 0.;
+	for (var x = 0; x < 3; x = x + 1) {
+		for (var y = 0; y < 3; y = y + 1) {
+			let in_coordinates = coordinates * 2 + vec2<i32>(x, y);
+
+			// The third row and column only exist to cover the leftover texel of odd sized levels
+			let in_x_range = x < 2 || in_coordinates.x == in_dimensions.x - 1;
+			let in_y_range = y < 2 || in_coordinates.y == in_dimensions.y - 1;
+			if (in_x_range && in_y_range && in_coordinates.x < in_dimensions.x && in_coordinates.y < in_dimensions.y) {
+				depth = max(depth, textureLoad(t_in_depth, in_coordinates, 0).r);
+			}
+		}
+	}
+
+	textureStore(out_depth, coordinates, vec4<f32>(depth, 0., 0., 0.));
+}
@@ -0,0 +1,51 @@
+[[block]] struct BakeView {
+	vp_matrix: mat4x4<f32>;
+};
+
+// Uniforms
+[[group(0), binding(0)]] var<uniform> view: BakeView;
+[[group(1), binding(0)]] var t_albedo: texture_2d<f32>;
+[[group(1), binding(1)]] var s_albedo: sampler;
+[[group(1), binding(2)]] var t_arm: texture_2d<f32>;
+[[group(1), binding(3)]] var s_arm: sampler;
+
+// Attributes
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+	[[location(1)]] uv: vec2<f32>;
+	[[location(2)]] normal: vec3<f32>;
+	[[location(3)]] tangent: vec4<f32>;
+};
+
+// Varyings
+struct VertexOutput {
+	[[builtin(position)]] clip_space_fragment_location: vec4<f32>;
+	[[location(0)]] model_space_normal: vec3<f32>;
+	[[location(1)]] uv: vec2<f32>;
+};
+
+// Frames
+struct FragmentOutput {
+	[[location(0)]] model_space_normal: vec4<f32>;
+	[[location(1)]] albedo: vec4<f32>;
+	[[location(2)]] arm: vec4<f32>;
+};
+
+// Vertex shader
+[[stage(vertex)]]
+fn main(model: VertexInput) -> VertexOutput {
+	return VertexOutput(view.vp_matrix * vec4<f32>(model.position, 1.), model.normal, model.uv);
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> FragmentOutput {
+	let uv = vec2<f32>(in.uv.x, 1. - in.uv.y);
+
+	// Normals stay in model space so the billboard can rotate them by each instance's own rotation, and alpha marks the covered texels
+	return FragmentOutput(
+		vec4<f32>(normalize(in.model_space_normal), 1.),
+		vec4<f32>(textureSample(t_albedo, s_albedo, uv).rgb, 1.),
+		vec4<f32>(textureSample(t_arm, s_arm, uv).rgb, 1.),
+	);
+}
@@ -0,0 +1,111 @@
+use crate::mesh::{DecodedMesh, Mesh};
+use crate::texture::Texture;
+
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+// Pixels tall the loading screen's progress bar is drawn
+pub const LOADING_BAR_HEIGHT: i32 = 12;
+
+// How many of the requested assets have been decoded and handed back so far, polled each frame to draw the loading screen
+#[derive(Debug, Copy, Clone, Default)]
+pub struct LoadProgress {
+	pub loaded: usize,
+	// Grows as the model files are read and turn out to need more textures, so the fraction can step back when they do
+	pub total: usize,
+}
+
+impl LoadProgress {
+	pub fn fraction(&self) -> f32 {
+		match self.total {
+			0 => 0.,
+			total => self.loaded as f32 / total as f32,
+		}
+	}
+
+	pub fn finished(&self) -> bool {
+		self.loaded == self.total
+	}
+}
+
+// An asset read and decoded on a worker thread, waiting for the main thread to upload it to the GPU
+pub enum DecodedAsset {
+	Meshes {
+		file: String,
+		meshes: Result<Vec<DecodedMesh>>,
+	},
+	Texture {
+		file: String,
+		format: wgpu::TextureFormat,
+		repeat_mode: wgpu::AddressMode,
+		image: Result<image::DynamicImage>,
+	},
+}
+
+// Reads and decodes model files and textures on rayon's worker threads, so the window keeps drawing frames while they load
+// Each decoded asset is sent back to be received on the main thread, which owns the GPU and uploads them as they arrive
+pub struct AssetLoader {
+	directory: PathBuf,
+	skipped_texture_mips: u32,
+	sender: Sender<DecodedAsset>,
+	receiver: Receiver<DecodedAsset>,
+	requested_textures: HashSet<String>,
+	progress: LoadProgress,
+	// The names of the meshes in each model file uploaded so far, in the order the file lists them
+	pub model_meshes: HashMap<String, Vec<String>>,
+}
+
+impl AssetLoader {
+	pub fn new(directory: &Path, skipped_texture_mips: u32) -> Self {
+		let (sender, receiver) = mpsc::channel();
+
+		Self {
+			directory: directory.to_path_buf(),
+			skipped_texture_mips,
+			sender,
+			receiver,
+			requested_textures: HashSet::new(),
+			progress: LoadProgress::default(),
+			model_meshes: HashMap::new(),
+		}
+	}
+
+	pub fn request_meshes(&mut self, file: &str) {
+		let (directory, file, sender) = (self.directory.clone(), String::from(file), self.sender.clone());
+		self.progress.total += 1;
+
+		rayon::spawn(move || {
+			let meshes = Mesh::decode(&directory, &file);
+			// The receiver only goes away if loading is abandoned, which leaves nobody to hand the result to
+			let _ = sender.send(DecodedAsset::Meshes { file, meshes });
+		});
+	}
+
+	// Textures shared by several meshes are only decoded once
+	pub fn request_texture(&mut self, file: &str, format: wgpu::TextureFormat, repeat_mode: wgpu::AddressMode) {
+		if !self.requested_textures.insert(String::from(file)) {
+			return;
+		}
+
+		let (directory, file, sender, skipped_mips) = (self.directory.clone(), String::from(file), self.sender.clone(), self.skipped_texture_mips);
+		self.progress.total += 1;
+
+		rayon::spawn(move || {
+			let image = Texture::decode(&directory, &file, skipped_mips);
+			let _ = sender.send(DecodedAsset::Texture { file, format, repeat_mode, image });
+		});
+	}
+
+	// The next asset that's finished decoding, if any are waiting, without blocking for the rest
+	pub fn try_receive(&mut self) -> Option<DecodedAsset> {
+		let asset = self.receiver.try_recv().ok()?;
+		self.progress.loaded += 1;
+		Some(asset)
+	}
+
+	pub fn progress(&self) -> LoadProgress {
+		self.progress
+	}
+}
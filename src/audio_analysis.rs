@@ -0,0 +1,227 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Number of samples transformed each frame, which must be a power of two for the FFT
+const FFT_SIZE: usize = 1024;
+// Frequency bands handed to the shaders, spaced logarithmically so each covers a similar musical range
+pub const BAND_COUNT: usize = 8;
+const LOWEST_FREQUENCY: f32 = 20.;
+const HIGHEST_FREQUENCY: f32 = 20000.;
+// Band levels are mapped from this many decibels below full scale up to full scale onto 0 to 1
+const DECIBEL_RANGE: f32 = 60.;
+// Seconds for the smoothed values to rise or fall most of the way toward a new level, rising quickly so beats stay sharp
+const ATTACK_TIME: f32 = 0.02;
+const RELEASE_TIME: f32 = 0.25;
+
+// Levels of the most recent audio, bound alongside the globals so any material or light shader can read them
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AudioAnalysisUniform {
+	// Root mean square of the samples, from 0 for silence to 1 for a full scale signal
+	pub amplitude: f32,
+	// Largest absolute sample value
+	pub peak: f32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: [f32; 2],
+	// Level of each band from the lowest to the highest frequencies, packed four to a vector since uniform arrays have a 16 byte stride
+	pub bands: [[f32; 4]; BAND_COUNT / 4],
+}
+
+#[derive(Debug)]
+struct AudioSamples {
+	// Mono samples, keeping only the most recent `FFT_SIZE`
+	samples: VecDeque<f32>,
+	sample_rate: u32,
+}
+
+// The hook an audio source feeds with whatever it plays, cloned onto the audio thread since playback usually runs there
+#[derive(Debug, Clone)]
+pub struct AudioInput {
+	shared: Arc<Mutex<AudioSamples>>,
+}
+
+impl AudioInput {
+	// Takes interleaved samples with the given number of channels, which are mixed down to mono
+	pub fn push_samples(&self, interleaved: &[f32], channels: usize, sample_rate: u32) {
+		let channels = channels.max(1);
+		let mut shared = self.shared.lock().unwrap();
+		shared.sample_rate = sample_rate;
+
+		for frame in interleaved.chunks_exact(channels) {
+			if shared.samples.len() == FFT_SIZE {
+				shared.samples.pop_front();
+			}
+			shared.samples.push_back(frame.iter().sum::<f32>() / channels as f32);
+		}
+	}
+}
+
+// Turns the audio pushed through `AudioInput` into levels the shaders can react to, such as lights pulsing to the bass of a demo's soundtrack
+// Nothing in the engine plays audio, so without an input the levels fall silent
+#[derive(Debug)]
+pub struct AudioAnalysis {
+	pub uniform: AudioAnalysisUniform,
+	input: AudioInput,
+	window: Vec<f32>,
+}
+
+impl Default for AudioAnalysis {
+	fn default() -> Self {
+		let samples = AudioSamples {
+			samples: VecDeque::with_capacity(FFT_SIZE),
+			sample_rate: 48000,
+		};
+
+		// Hann window, which tapers the ends of the samples so the cut at their edges doesn't smear energy into every band
+		let window = (0..FFT_SIZE).map(|index| 0.5 - 0.5 * (2. * PI * index as f32 / FFT_SIZE as f32).cos()).collect();
+
+		Self {
+			uniform: AudioAnalysisUniform::default(),
+			input: AudioInput {
+				shared: Arc::new(Mutex::new(samples)),
+			},
+			window,
+		}
+	}
+}
+
+impl AudioAnalysis {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn input(&self) -> AudioInput {
+		self.input.clone()
+	}
+
+	pub fn update(&mut self, delta_time: Duration) {
+		let (mut real, sample_rate) = {
+			let shared = self.input.shared.lock().unwrap();
+			// Missing samples count as silence, so the levels fall off when the audio stops
+			let mut samples = vec![0.; FFT_SIZE - shared.samples.len()];
+			samples.extend(shared.samples.iter());
+			(samples, shared.sample_rate)
+		};
+
+		let amplitude = (real.iter().map(|sample| sample * sample).sum::<f32>() / FFT_SIZE as f32).sqrt();
+		let peak = real.iter().fold(0_f32, |peak, sample| peak.max(sample.abs()));
+
+		for (sample, weight) in real.iter_mut().zip(&self.window) {
+			*sample *= weight;
+		}
+		let mut imaginary = vec![0.; FFT_SIZE];
+		fft(&mut real, &mut imaginary);
+
+		// Scaled so a full scale sine wave reads as 1, accounting for the half of its energy in the mirrored bins and the Hann window's average of one half
+		let magnitudes = (0..FFT_SIZE / 2)
+			.map(|bin| (real[bin] * real[bin] + imaginary[bin] * imaginary[bin]).sqrt() * 4. / FFT_SIZE as f32)
+			.collect::<Vec<_>>();
+
+		let bin_width = sample_rate as f32 / FFT_SIZE as f32;
+		let highest_frequency = HIGHEST_FREQUENCY.min(sample_rate as f32 * 0.5);
+		let mut bands = [0.; BAND_COUNT];
+		for (index, band) in bands.iter_mut().enumerate() {
+			let edge = |index: usize| LOWEST_FREQUENCY * (highest_frequency / LOWEST_FREQUENCY).powf(index as f32 / BAND_COUNT as f32);
+			// Every band covers at least one bin, even the lowest ones which are narrower than a bin
+			let first_bin = ((edge(index) / bin_width) as usize).clamp(1, magnitudes.len() - 1);
+			let last_bin = ((edge(index + 1) / bin_width) as usize).clamp(first_bin + 1, magnitudes.len());
+
+			let magnitude = magnitudes[first_bin..last_bin].iter().fold(0_f32, |loudest, magnitude| loudest.max(*magnitude));
+			let decibels = 20. * magnitude.max(f32::MIN_POSITIVE).log10();
+			*band = ((decibels + DECIBEL_RANGE) / DECIBEL_RANGE).clamp(0., 1.);
+		}
+
+		let delta_time = delta_time.as_secs_f32();
+		let smooth = |current: f32, target: f32| {
+			let time = if target > current { ATTACK_TIME } else { RELEASE_TIME };
+			current + (target - current) * (1. - (-delta_time / time).exp())
+		};
+
+		self.uniform.amplitude = smooth(self.uniform.amplitude, amplitude);
+		self.uniform.peak = smooth(self.uniform.peak, peak);
+		for (index, band) in bands.iter().enumerate() {
+			let value = &mut self.uniform.bands[index / 4][index % 4];
+			*value = smooth(*value, *band);
+		}
+	}
+}
+
+// A synthesized kick drum on every beat, standing in for a soundtrack to check that scenes react to the audio
+#[derive(Debug)]
+pub struct TestBeat {
+	pub beats_per_minute: f32,
+	pub sample_rate: u32,
+	time: f64,
+}
+
+impl TestBeat {
+	pub fn new(beats_per_minute: f32) -> Self {
+		Self {
+			beats_per_minute,
+			sample_rate: 48000,
+			time: 0.,
+		}
+	}
+
+	// Pushes the samples that would have played over the last frame
+	pub fn play(&mut self, input: &AudioInput, delta_time: Duration) {
+		let sample_count = (delta_time.as_secs_f64() * self.sample_rate as f64) as usize;
+		let beat_length = 60. / self.beats_per_minute as f64;
+
+		let samples = (0..sample_count)
+			.map(|index| {
+				let time_since_beat = ((self.time + index as f64 / self.sample_rate as f64) % beat_length) as f32;
+				// A low sine that quickly decays, dropping in pitch like a drum's skin settling
+				let frequency = 50. + 100. * (-time_since_beat * 30.).exp();
+				(2. * PI * frequency * time_since_beat).sin() * (-time_since_beat * 8.).exp()
+			})
+			.collect::<Vec<_>>();
+		self.time += sample_count as f64 / self.sample_rate as f64;
+
+		input.push_samples(&samples, 1, self.sample_rate);
+	}
+}
+
+// In-place iterative radix-2 Cooley-Tukey FFT, where the length must be a power of two
+fn fft(real: &mut [f32], imaginary: &mut [f32]) {
+	let length = real.len();
+
+	// Reorder the samples by bit-reversed index so the butterflies can combine neighbors
+	let mut j = 0;
+	for i in 1..length {
+		let mut bit = length >> 1;
+		while j & bit != 0 {
+			j ^= bit;
+			bit >>= 1;
+		}
+		j |= bit;
+
+		if i < j {
+			real.swap(i, j);
+			imaginary.swap(i, j);
+		}
+	}
+
+	let mut size = 2;
+	while size <= length {
+		let angle = -2. * PI / size as f32;
+		for start in (0..length).step_by(size) {
+			for k in 0..size / 2 {
+				let (twiddle_imaginary, twiddle_real) = (angle * k as f32).sin_cos();
+				let even = start + k;
+				let odd = even + size / 2;
+
+				let odd_real = real[odd] * twiddle_real - imaginary[odd] * twiddle_imaginary;
+				let odd_imaginary = real[odd] * twiddle_imaginary + imaginary[odd] * twiddle_real;
+
+				real[odd] = real[even] - odd_real;
+				imaginary[odd] = imaginary[even] - odd_imaginary;
+				real[even] += odd_real;
+				imaginary[even] += odd_imaginary;
+			}
+		}
+		size *= 2;
+	}
+}
@@ -0,0 +1,121 @@
+use crate::debug_view::LUMINANCE_HISTOGRAM_BINS;
+use crate::render_settings::{DebugShading, MeteringMode, RenderSettings};
+
+use wgpu::util::DeviceExt;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AutoExposureUniform {
+	delta_time: f32,
+	// 0 holds the exposure at 1 and forgets the adapted luminance, so it starts over from the next frame's once turned back on
+	enabled: u32,
+	brightening_speed: f32,
+	darkening_speed: f32,
+	min_ev: f32,
+	max_ev: f32,
+	// 1 holds the exposure at the manual EV instead, likewise forgetting the adapted luminance
+	manual: u32,
+	manual_ev: f32,
+}
+
+// Read by the histogram shader, which weighs each pixel it counts by how much the metering mode says it matters
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MeteringUniform {
+	// 0 for average, 1 for center-weighted, and 2 for spot
+	mode: u32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: [u32; 3],
+}
+
+// Written only by the auto exposure compute shader, which the final composite pass reads the exposure from
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct AdaptedExposure {
+	average_log_luminance: f32,
+	exposure: f32,
+	// 0 until the first frame has been measured, which is taken as it is instead of adapted to from black
+	adapted: u32,
+	_padding: u32,
+}
+
+// Eye adaptation: the scene's luminance is counted into a histogram each frame, whose average log luminance the exposure slowly follows like an eye adjusting to the dark or the light
+// The camera's and environment's exposures still apply on top, as a compensation to brighten or darken what the adaptation settles on
+pub struct AutoExposure {
+	pub histogram_buffer: wgpu::Buffer,
+	pub exposure_buffer: wgpu::Buffer,
+	pub uniform_buffer: wgpu::Buffer,
+	pub metering_buffer: wgpu::Buffer,
+	// Always average metering, for the histogram debug view to count every pixel the same whatever the eye adaptation meters by
+	pub average_metering_buffer: wgpu::Buffer,
+}
+
+impl AutoExposure {
+	pub fn new(device: &wgpu::Device) -> Self {
+		// The compute shader zeroes the bins once it's read them, so they start out zeroed and never need clearing from the CPU
+		let histogram_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Auto Exposure Histogram Buffer"),
+			contents: bytemuck::cast_slice(&[0_u32; LUMINANCE_HISTOGRAM_BINS as usize]),
+			usage: wgpu::BufferUsages::STORAGE,
+		});
+		let exposure_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Auto Exposure Buffer"),
+			contents: bytemuck::cast_slice(&[AdaptedExposure {
+				average_log_luminance: 0.,
+				exposure: 1.,
+				adapted: 0,
+				_padding: 0,
+			}]),
+			usage: wgpu::BufferUsages::STORAGE,
+		});
+		let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Auto Exposure Uniform Buffer"),
+			contents: bytemuck::cast_slice(&[<AutoExposureUniform as bytemuck::Zeroable>::zeroed()]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
+		let metering_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Auto Exposure Metering Buffer"),
+			contents: bytemuck::cast_slice(&[<MeteringUniform as bytemuck::Zeroable>::zeroed()]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
+		let average_metering_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Average Metering Buffer"),
+			contents: bytemuck::cast_slice(&[<MeteringUniform as bytemuck::Zeroable>::zeroed()]),
+			usage: wgpu::BufferUsages::UNIFORM,
+		});
+
+		Self {
+			histogram_buffer,
+			exposure_buffer,
+			uniform_buffer,
+			metering_buffer,
+			average_metering_buffer,
+		}
+	}
+
+	pub fn update(&self, queue: &wgpu::Queue, delta_time: std::time::Duration, enabled: bool, render_settings: &RenderSettings) {
+		// Debug shading is meant to be seen under an exposure of 1, which the manual exposure would throw off as much as the adaptation
+		let manual_ev = render_settings.manual_exposure_ev.filter(|_| render_settings.debug_shading == DebugShading::None);
+		let uniform = AutoExposureUniform {
+			delta_time: delta_time.as_secs_f32(),
+			enabled: enabled as u32,
+			brightening_speed: render_settings.exposure_brightening_speed,
+			darkening_speed: render_settings.exposure_darkening_speed,
+			min_ev: render_settings.exposure_min_ev,
+			max_ev: render_settings.exposure_max_ev,
+			manual: manual_ev.is_some() as u32,
+			manual_ev: manual_ev.unwrap_or_default(),
+		};
+		queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+		let metering = MeteringUniform {
+			mode: match render_settings.metering {
+				MeteringMode::Average => 0,
+				MeteringMode::CenterWeighted => 1,
+				MeteringMode::Spot => 2,
+			},
+			_padding: [0; 3],
+		};
+		queue.write_buffer(&self.metering_buffer, 0, bytemuck::cast_slice(&[metering]));
+	}
+}
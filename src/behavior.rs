@@ -1,7 +1,60 @@
 use crate::entity::Entity;
+use crate::time_of_day::TimeOfDayEvent;
+use crate::timeline::TimelineEvent;
+use crate::transform::Transform;
 
 use core::fmt::Debug;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use winit::event::{ElementState, VirtualKeyCode};
 
-pub trait Behavior: Debug {
-	fn update(&self, entity: &mut Entity);
+// Behaviors are shared with the parallel scene traversal, so they must be thread safe
+pub trait Behavior: Debug + Send + Sync {
+	fn update(&mut self, entity: &mut Entity, context: &BehaviorContext);
+
+	// Called when the time of day passes dawn or dusk, for scripts like street lamps switching on at night
+	fn time_of_day_event(&mut self, _entity: &mut Entity, _event: TimeOfDayEvent) {}
+
+	// Called when a playing timeline reaches one of its events, for scripts to act out their part of a cinematic
+	fn timeline_event(&mut self, _entity: &mut Entity, _event: &TimelineEvent) {}
+}
+
+// The keys held down, tracked from the keyboard events so scripts can respond to the player
+#[derive(Debug, Default)]
+pub struct BehaviorInput {
+	held_keys: HashSet<VirtualKeyCode>,
+}
+
+impl BehaviorInput {
+	pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) {
+		match state {
+			ElementState::Pressed => self.held_keys.insert(key),
+			ElementState::Released => self.held_keys.remove(&key),
+		};
+	}
+}
+
+// What a behavior sees of the rest of the engine while it updates, since the entity tree itself is borrowed by the traversal
+pub struct BehaviorContext<'a> {
+	pub delta_time: Duration,
+	input: &'a BehaviorInput,
+	// Every entity's world transform by name, as it was placed last frame before any behavior ran this frame
+	world_transforms: HashMap<String, Transform>,
+}
+
+impl<'a> BehaviorContext<'a> {
+	pub fn new(delta_time: Duration, input: &'a BehaviorInput, root: &Entity) -> Self {
+		let world_transforms = root.iter().map(|entity| (entity.name.clone(), entity.world_transform)).collect();
+
+		Self { delta_time, input, world_transforms }
+	}
+
+	pub fn key_held(&self, key: VirtualKeyCode) -> bool {
+		self.input.held_keys.contains(&key)
+	}
+
+	// Where another entity is in the world, or `None` if there's no entity by that name
+	pub fn world_transform(&self, entity_name: &str) -> Option<&Transform> {
+		self.world_transforms.get(entity_name)
+	}
 }
@@ -0,0 +1,42 @@
+use anyhow::{bail, Result};
+
+// Walks through the bytes of a file cached by the engine, failing instead of panicking when it's cut short
+pub struct CacheReader<'a> {
+	bytes: &'a [u8],
+}
+
+impl<'a> CacheReader<'a> {
+	pub fn new(bytes: &'a [u8]) -> Self {
+		Self { bytes }
+	}
+
+	pub fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+		if self.bytes.len() < count {
+			bail!("The cache ends early");
+		}
+
+		let (taken, rest) = self.bytes.split_at(count);
+		self.bytes = rest;
+		Ok(taken)
+	}
+
+	pub fn u32(&mut self) -> Result<u32> {
+		Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+	}
+
+	pub fn u64(&mut self) -> Result<u64> {
+		Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+	}
+
+	pub fn f32(&mut self) -> Result<f32> {
+		Ok(f32::from_le_bytes(self.take(4)?.try_into()?))
+	}
+}
+
+// FNV-1a over the values' bits, which unlike the standard library's hasher stays the same between builds of the engine so caches can be checked against their source
+pub fn hash_floats(values: impl IntoIterator<Item = f32>) -> u64 {
+	values
+		.into_iter()
+		.flat_map(|value| value.to_bits().to_le_bytes())
+		.fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
@@ -1,7 +1,7 @@
 use crate::context::Context;
 use crate::transform::Transform;
 
-use cgmath::{Euler, InnerSpace, Matrix4, Point3, Rad, SquareMatrix, Vector3};
+use cgmath::{Euler, InnerSpace, Matrix4, Point3, Rad, SquareMatrix, Vector2, Vector3, Vector4};
 use wgpu::util::DeviceExt;
 use wgpu::{BindGroup, BindGroupLayout, Buffer};
 
@@ -19,6 +19,15 @@ pub struct Camera {
 	pub pitch: Rad<f32>,
 	pub yaw: Rad<f32>,
 	pub projection: Projection,
+	// Scales the scene's radiance before tone mapping, brightening dim scenes above 1 and darkening bright ones below it
+	pub exposure: f32,
+	// Effects applied to this camera's view after shading, so cameras that only feed other passes can skip what they don't need
+	pub post_effects: Vec<PostEffect>,
+	// World space plane, with its normal in XYZ and distance in W, that the scene shaders discard everything behind, since views through mirrors and portals must not see what's behind the surface
+	// This is done per fragment, as wgpu has no hardware clip distances, so only the forward shaders drawn by those views check it
+	pub clip_plane: Option<Vector4<f32>>,
+	// Offset of the projection in normalized device coordinates, a fraction of a pixel that temporal anti-aliasing changes every frame
+	pub jitter: Vector2<f32>,
 	pub camera_uniform: CameraUniform,
 	pub camera_buffer: Buffer,
 	pub camera_bind_group_layout: BindGroupLayout,
@@ -72,6 +81,10 @@ impl Camera {
 			pitch,
 			yaw,
 			projection,
+			exposure: 1.,
+			post_effects: PostEffect::ALL.to_vec(),
+			clip_plane: None,
+			jitter: Vector2::new(0., 0.),
 			camera_uniform,
 			camera_buffer,
 			camera_bind_group_layout,
@@ -88,11 +101,10 @@ impl Camera {
 
 	pub fn update_v_p_matrices(&mut self, queue: &mut wgpu::Queue) {
 		let v = Self::calculate_v_matrix(self.location, self.pitch, self.yaw);
-		let p = match &self.projection {
-			Projection::Perspective(p) => p.p_matrix(),
-			Projection::Orthographic(o) => o.p_matrix(),
-		};
-		self.camera_uniform = CameraUniform::from_vp(v, p, self.camera_uniform.v_matrix, self.camera_uniform.p_matrix);
+		let p = self.jittered_p_matrix();
+		self.camera_uniform = CameraUniform::from_vp(v, p, self.camera_uniform.v_matrix, self.camera_uniform.p_matrix)
+			.with_clip_plane(self.clip_plane)
+			.with_jitter(self.jitter, self.camera_uniform.jitter());
 
 		queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[self.camera_uniform]));
 	}
@@ -101,22 +113,51 @@ impl Camera {
 		Matrix4::look_to_rh(location, Vector3::new(yaw.0.cos(), pitch.0.sin(), yaw.0.sin()).normalize(), Vector3::unit_y())
 	}
 
+	// For cameras derived from another one, like a mirror's reflection camera, whose matrices can't be described by a location and projection
+	pub fn update_from_matrices(&mut self, location: Point3<f32>, v: Matrix4<f32>, p: Matrix4<f32>, queue: &wgpu::Queue) {
+		self.location = location;
+		self.camera_uniform = CameraUniform::from_vp(v, p, self.camera_uniform.v_matrix, self.camera_uniform.p_matrix).with_clip_plane(self.clip_plane);
+
+		queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[self.camera_uniform]));
+	}
+
 	pub fn update_transform_and_matrices(&mut self, transform: &Transform, queue: &mut wgpu::Queue) {
 		self.update_transform(transform);
 		let translation = cgmath::Vector3::new(transform.location.x as f32, transform.location.y as f32, transform.location.z as f32);
 		let rotation = cgmath::Quaternion::new(transform.rotation.s as f32, transform.rotation.v.x as f32, transform.rotation.v.y as f32, transform.rotation.v.z as f32);
 
 		let v = cgmath::Matrix4::from_translation(translation) * cgmath::Matrix4::from(rotation);
+		let p = self.jittered_p_matrix();
+		self.camera_uniform = CameraUniform::from_vp(v, p, self.camera_uniform.v_matrix, self.camera_uniform.p_matrix)
+			.with_clip_plane(self.clip_plane)
+			.with_jitter(self.jitter, self.camera_uniform.jitter());
+
+		queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[self.camera_uniform]));
+	}
+
+	// Shifting the clip space position by the jitter times its W moves every point on screen by the same amount, whatever its depth
+	fn jittered_p_matrix(&self) -> Matrix4<f32> {
 		let p = match &self.projection {
 			Projection::Perspective(p) => p.p_matrix(),
 			Projection::Orthographic(o) => o.p_matrix(),
 		};
-		self.camera_uniform = CameraUniform::from_vp(v, p, self.camera_uniform.v_matrix, self.camera_uniform.p_matrix);
-
-		queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[self.camera_uniform]));
+		Matrix4::from_translation(self.jitter.extend(0.)) * p
 	}
 }
 
+// Screen space effects in a camera's post-processing stack, which all lead into the final composite pass that every view ends with
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PostEffect {
+	AmbientOcclusion,
+	// Jitters the camera by a fraction of a pixel each frame and blends the frames together, smoothing the edges the G-buffer can't multisample
+	TemporalAntiAliasing,
+	MotionBlur,
+}
+
+impl PostEffect {
+	pub const ALL: [PostEffect; 3] = [PostEffect::AmbientOcclusion, PostEffect::TemporalAntiAliasing, PostEffect::MotionBlur];
+}
+
 // We need this for Rust to store our data correctly for the shaders
 #[repr(C)]
 // This is so we can store this in a buffer
@@ -130,6 +171,9 @@ pub struct CameraUniform {
 	inv_p_matrix: [[f32; 4]; 4],
 	prev_v_matrix: [[f32; 4]; 4],
 	prev_p_matrix: [[f32; 4]; 4],
+	clip_plane: [f32; 4],
+	// This frame's jitter in XY and the previous frame's in ZW, which the velocity buffer takes out so still surfaces don't appear to move
+	jitter: [f32; 4],
 }
 
 impl CameraUniform {
@@ -142,6 +186,18 @@ impl CameraUniform {
 		)
 	}
 
+	pub fn v_matrix(&self) -> cgmath::Matrix4<f32> {
+		cgmath::Matrix4::from(self.v_matrix)
+	}
+
+	pub fn p_matrix(&self) -> cgmath::Matrix4<f32> {
+		cgmath::Matrix4::from(self.p_matrix)
+	}
+
+	pub fn vp_matrix(&self) -> cgmath::Matrix4<f32> {
+		self.p_matrix() * self.v_matrix()
+	}
+
 	pub fn from_vp(v: cgmath::Matrix4<f32>, p: cgmath::Matrix4<f32>, prev_v: [[f32; 4]; 4], prev_p: [[f32; 4]; 4]) -> Self {
 		Self {
 			v_matrix: v.into(),
@@ -150,8 +206,25 @@ impl CameraUniform {
 			inv_p_matrix: cgmath::Matrix4::invert(&p).unwrap().into(),
 			prev_v_matrix: prev_v,
 			prev_p_matrix: prev_p,
+			clip_plane: [0.; 4],
+			jitter: [0.; 4],
 		}
 	}
+
+	pub fn jitter(&self) -> Vector2<f32> {
+		Vector2::new(self.jitter[0], self.jitter[1])
+	}
+
+	// A zero plane keeps every fragment, since the shaders only discard those at a negative distance from it
+	pub fn with_clip_plane(mut self, clip_plane: Option<Vector4<f32>>) -> Self {
+		self.clip_plane = clip_plane.map_or([0.; 4], Into::into);
+		self
+	}
+
+	pub fn with_jitter(mut self, jitter: Vector2<f32>, prev_jitter: Vector2<f32>) -> Self {
+		self.jitter = [jitter.x, jitter.y, prev_jitter.x, prev_jitter.y];
+		self
+	}
 }
 
 impl Default for CameraUniform {
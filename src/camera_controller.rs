@@ -1,7 +1,7 @@
 use cgmath::{InnerSpace, Rad, Vector3};
 use std::time::Duration;
 use winit::dpi::PhysicalPosition;
-use winit::event::{ElementState, MouseScrollDelta, VirtualKeyCode};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode};
 
 use crate::camera::Camera;
 
@@ -20,6 +20,11 @@ pub struct CameraController {
 	scroll: f32,
 	speed: f32,
 	sensitivity: f32,
+	// The mouse only turns the camera while the right button is held, or while mouse look is toggled on with F1, and otherwise moves the cursor like in any other window
+	look_held: bool,
+	look_toggled: bool,
+	// Keeps a held F1 key from toggling mouse look over and over if the key repeats
+	look_toggle_key_down: bool,
 }
 
 impl CameraController {
@@ -36,9 +41,30 @@ impl CameraController {
 			scroll: 0.0,
 			speed,
 			sensitivity,
+			look_held: false,
+			look_toggled: false,
+			look_toggle_key_down: false,
 		}
 	}
 
+	// Whether mouse motion turns the camera, during which the cursor is hidden and held in place by the engine
+	pub fn looking(&self) -> bool {
+		self.look_held || self.look_toggled
+	}
+
+	pub fn process_mouse_button(&mut self, button: MouseButton, state: ElementState) {
+		if button == MouseButton::Right {
+			self.look_held = state == ElementState::Pressed;
+		}
+	}
+
+	// Lets go of the mouse when the window loses focus, since the button's release or the toggle key would go to another window
+	pub fn release_look(&mut self) {
+		self.look_held = false;
+		self.look_toggled = false;
+		self.look_toggle_key_down = false;
+	}
+
 	pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
 		let amount = if state == ElementState::Pressed { 1.0 } else { 0.0 };
 		match key {
@@ -66,6 +92,14 @@ impl CameraController {
 				self.amount_down = amount;
 				true
 			}
+			VirtualKeyCode::F1 => {
+				let pressed = state == ElementState::Pressed;
+				if pressed && !self.look_toggle_key_down {
+					self.look_toggled = !self.look_toggled;
+				}
+				self.look_toggle_key_down = pressed;
+				true
+			}
 			_ => false,
 		}
 	}
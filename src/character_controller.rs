@@ -0,0 +1,187 @@
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Rad, Vector3};
+use std::time::Duration;
+use winit::event::{ElementState, VirtualKeyCode};
+
+use crate::camera::Camera;
+use crate::collision::{Capsule, CollisionWorld};
+
+const SAFE_FRAC_PI_2: f32 = std::f32::consts::FRAC_PI_2 - 0.0001;
+
+const WALK_SPEED: f32 = 3.;
+const JUMP_SPEED: f32 = 4.5;
+const GRAVITY: f32 = 9.81;
+// How quickly the walking velocity catches up to the keys held, with only a fraction of that control while in the air
+const ACCELERATION: f32 = 12.;
+const AIR_CONTROL: f32 = 0.2;
+const EYE_HEIGHT: f32 = 1.6;
+// Long frames are simulated as if shorter so a hitch can't carry the capsule through a floor in one step
+const MAX_TIME_STEP: f32 = 1. / 30.;
+// Falling out of the level puts the character back where it was first placed
+const RESPAWN_HEIGHT: f32 = -50.;
+
+// The eyes dip twice and sway once per stride, eased in and out as walking starts and stops
+const BOB_STRIDE_LENGTH: f32 = 1.4;
+const BOB_HEIGHT: f32 = 0.04;
+const BOB_SWAY: f32 = 0.02;
+const BOB_EASING_SPEED: f32 = 8.;
+
+// Walks a capsule around the static scene geometry and puts the camera at its eyes, as an alternative to flying the camera freely
+#[derive(Debug)]
+pub struct CharacterController {
+	active: bool,
+	// The feet are placed under the camera on the first update after being activated
+	needs_placement: bool,
+	amount_left: f32,
+	amount_right: f32,
+	amount_forward: f32,
+	amount_backward: f32,
+	jump: bool,
+	rotate_horizontal: f32,
+	rotate_vertical: f32,
+	sensitivity: f32,
+	capsule: Capsule,
+	feet_location: Vector3<f32>,
+	spawn_location: Vector3<f32>,
+	velocity: Vector3<f32>,
+	on_ground: bool,
+	bob_phase: f32,
+	bob_amount: f32,
+}
+
+impl CharacterController {
+	pub fn new(sensitivity: f32) -> Self {
+		Self {
+			active: false,
+			needs_placement: false,
+			amount_left: 0.0,
+			amount_right: 0.0,
+			amount_forward: 0.0,
+			amount_backward: 0.0,
+			jump: false,
+			rotate_horizontal: 0.0,
+			rotate_vertical: 0.0,
+			sensitivity,
+			capsule: Capsule { radius: 0.3, height: 1.8 },
+			feet_location: Vector3::new(0., 0., 0.),
+			spawn_location: Vector3::new(0., 0., 0.),
+			velocity: Vector3::new(0., 0., 0.),
+			on_ground: false,
+			bob_phase: 0.,
+			bob_amount: 0.,
+		}
+	}
+
+	// While inactive the keys are left to the fly camera, other than the one toggling between the two
+	pub fn active(&self) -> bool {
+		self.active
+	}
+
+	pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
+		if key == VirtualKeyCode::F {
+			if state == ElementState::Pressed {
+				self.active = !self.active;
+				self.needs_placement = self.active;
+			}
+			return true;
+		}
+
+		let amount = if state == ElementState::Pressed { 1.0 } else { 0.0 };
+		match key {
+			VirtualKeyCode::W => {
+				self.amount_forward = amount;
+				true
+			}
+			VirtualKeyCode::S => {
+				self.amount_backward = amount;
+				true
+			}
+			VirtualKeyCode::A => {
+				self.amount_left = amount;
+				true
+			}
+			VirtualKeyCode::D => {
+				self.amount_right = amount;
+				true
+			}
+			VirtualKeyCode::Space => {
+				self.jump = state == ElementState::Pressed;
+				true
+			}
+			_ => false,
+		}
+	}
+
+	pub fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
+		self.rotate_horizontal = mouse_dx as f32;
+		self.rotate_vertical = mouse_dy as f32;
+	}
+
+	pub fn update_camera(&mut self, camera: &mut Camera, collision_world: &CollisionWorld, dt: Duration) {
+		let dt = dt.as_secs_f32().min(MAX_TIME_STEP);
+		let up = Vector3::unit_y();
+
+		if self.needs_placement {
+			self.feet_location = camera.location.to_vec() - up * EYE_HEIGHT;
+			self.spawn_location = self.feet_location;
+			self.velocity = Vector3::new(0., 0., 0.);
+			self.needs_placement = false;
+		}
+
+		// Rotate
+		camera.yaw += Rad(self.rotate_horizontal) * self.sensitivity * dt;
+		camera.pitch += Rad(-self.rotate_vertical) * self.sensitivity * dt;
+		self.rotate_horizontal = 0.0;
+		self.rotate_vertical = 0.0;
+
+		// Keep the camera's angle from going too high/low.
+		if camera.pitch < -Rad(SAFE_FRAC_PI_2) {
+			camera.pitch = -Rad(SAFE_FRAC_PI_2);
+		} else if camera.pitch > Rad(SAFE_FRAC_PI_2) {
+			camera.pitch = Rad(SAFE_FRAC_PI_2);
+		}
+
+		// Walk along the ground in the direction the camera faces, however far up or down it looks
+		let (yaw_sin, yaw_cos) = camera.yaw.0.sin_cos();
+		let forward = Vector3::new(yaw_cos, 0.0, yaw_sin);
+		let right = Vector3::new(-yaw_sin, 0.0, yaw_cos);
+		let mut walk_direction = forward * (self.amount_forward - self.amount_backward) + right * (self.amount_right - self.amount_left);
+		if walk_direction.magnitude2() > 1. {
+			walk_direction = walk_direction.normalize();
+		}
+
+		let control = if self.on_ground { 1. } else { AIR_CONTROL };
+		let blend = (ACCELERATION * control * dt).min(1.);
+		let target_velocity = walk_direction * WALK_SPEED;
+		self.velocity.x += (target_velocity.x - self.velocity.x) * blend;
+		self.velocity.z += (target_velocity.z - self.velocity.z) * blend;
+
+		// Jump and fall
+		if self.on_ground && self.jump {
+			self.velocity.y = JUMP_SPEED;
+		}
+		self.velocity.y -= GRAVITY * dt;
+
+		// Move, then push back out of whatever was walked into
+		let contact = collision_world.resolve_capsule(&self.capsule, self.feet_location + self.velocity * dt);
+		self.feet_location = contact.location;
+		self.on_ground = contact.on_ground;
+		if (contact.on_ground && self.velocity.y < 0.) || (contact.on_ceiling && self.velocity.y > 0.) {
+			self.velocity.y = 0.;
+		}
+
+		if self.feet_location.y < RESPAWN_HEIGHT {
+			self.feet_location = self.spawn_location;
+			self.velocity = Vector3::new(0., 0., 0.);
+		}
+
+		// Head bob, advancing with the distance walked so it keeps pace with the footsteps at any speed
+		let walk_speed = Vector3::new(self.velocity.x, 0., self.velocity.z).magnitude();
+		let walking = if self.on_ground && walk_speed > 0.1 { 1. } else { 0. };
+		self.bob_amount += (walking - self.bob_amount) * (BOB_EASING_SPEED * dt).min(1.);
+		self.bob_phase = (self.bob_phase + walk_speed * dt / BOB_STRIDE_LENGTH * std::f32::consts::TAU) % std::f32::consts::TAU;
+		let bob_height = ((self.bob_phase * 2.).cos() - 1.) * 0.5 * BOB_HEIGHT * self.bob_amount;
+		let bob_sway = self.bob_phase.sin() * BOB_SWAY * self.bob_amount;
+
+		camera.location = Point3::from_vec(self.feet_location + up * (EYE_HEIGHT + bob_height) + right * bob_sway);
+	}
+}
@@ -0,0 +1,487 @@
+use crate::binary_cache::{hash_floats, CacheReader};
+
+use anyhow::{bail, Result};
+use cgmath::{InnerSpace, Point3, Vector3};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// Written at the start of every cached collider, with the version bumped whenever the format or the generation changes so stale caches are rebuilt
+const CACHE_MAGIC: &[u8; 4] = b"SCOL";
+const CACHE_VERSION: u32 = 1;
+// Vertices closer together than this are merged before building the shapes
+const WELD_DISTANCE: f32 = 1e-4;
+// The simplified proxy snaps every vertex to a grid with this many cells along the mesh's longest side
+const PROXY_RESOLUTION: f32 = 16.;
+// Spacing of the heightfield's samples, widened for large meshes so neither side has more than the maximum number of samples
+const HEIGHTFIELD_CELL_SIZE: f32 = 0.25;
+const HEIGHTFIELD_MAX_SAMPLES: u32 = 256;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ColliderKind {
+	// Every triangle of the mesh, exact but the most expensive to test against
+	TriangleMesh,
+	// Smallest convex shape enclosing the mesh, cheap for moving objects that don't need their concave parts
+	ConvexHull,
+	// The mesh with its vertices clustered together, keeping its rough shape at a fraction of the triangles
+	Proxy,
+	// Top surface sampled on a regular grid from above, for terrain and floors
+	Heightfield,
+}
+
+impl ColliderKind {
+	pub const ALL: [ColliderKind; 4] = [Self::TriangleMesh, Self::ConvexHull, Self::Proxy, Self::Heightfield];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::TriangleMesh => "triangle_mesh",
+			Self::ConvexHull => "convex_hull",
+			Self::Proxy => "proxy",
+			Self::Heightfield => "heightfield",
+		}
+	}
+}
+
+// The collider's edges as pairs of endpoints in its object space
+pub type Wireframe = Vec<[Point3<f32>; 2]>;
+
+// Collision shapes extracted from a mesh's triangles in its object space, for the physics to test against instead of the render geometry
+#[derive(Debug, Clone)]
+pub enum Collider {
+	// Used for the triangle mesh, convex hull, and proxy kinds, which all come out as indexed triangles
+	Triangles {
+		kind: ColliderKind,
+		vertices: Vec<[f32; 3]>,
+		indices: Vec<u32>,
+	},
+	Heightfield {
+		// Location of the first sample on the XZ plane, with columns running along X and rows along Z
+		origin: [f32; 2],
+		cell_size: f32,
+		columns: u32,
+		rows: u32,
+		// Height of each sample, row by row, or NaN where nothing lies below it
+		heights: Vec<f32>,
+	},
+}
+
+impl Collider {
+	pub fn generate(kind: ColliderKind, triangles: &[[[f32; 3]; 3]]) -> Self {
+		match kind {
+			ColliderKind::TriangleMesh => {
+				let (vertices, indices) = weld(triangles);
+				Self::Triangles { kind, vertices, indices }
+			}
+			ColliderKind::ConvexHull => {
+				let (vertices, _) = weld(triangles);
+				let (vertices, indices) = convex_hull(&vertices);
+				Self::Triangles { kind, vertices, indices }
+			}
+			ColliderKind::Proxy => {
+				let (vertices, indices) = cluster_vertices(triangles);
+				Self::Triangles { kind, vertices, indices }
+			}
+			ColliderKind::Heightfield => heightfield(triangles),
+		}
+	}
+
+	// Reads the collider from the cache directory if it was generated from the same triangles before, otherwise generates it and caches it for next time
+	pub fn load_or_generate(cache_directory: &Path, name: &str, kind: ColliderKind, triangles: &[[[f32; 3]; 3]]) -> Self {
+		// Mesh names come from model files, so anything that isn't safe in a file name is replaced
+		let file_name = name.chars().map(|character| if character.is_ascii_alphanumeric() { character } else { '_' }).collect::<String>();
+		let path = cache_directory.join(format!("{}.{}.collider", file_name, kind.name()));
+		let source_hash = hash_floats(triangles.iter().flatten().flatten().copied());
+
+		if let Ok(bytes) = std::fs::read(&path) {
+			match Self::from_cache(&bytes, source_hash, kind) {
+				Ok(collider) => return collider,
+				Err(error) => eprintln!("Regenerating the cached collider {:?}: {}", path, error),
+			}
+		}
+
+		let collider = Self::generate(kind, triangles);
+
+		let written = std::fs::create_dir_all(cache_directory).and_then(|_| std::fs::write(&path, collider.to_cache(source_hash)));
+		if let Err(error) = written {
+			eprintln!("Couldn't cache the collider {:?}: {}", path, error);
+		}
+
+		collider
+	}
+
+	pub fn kind(&self) -> ColliderKind {
+		match self {
+			Self::Triangles { kind, .. } => *kind,
+			Self::Heightfield { .. } => ColliderKind::Heightfield,
+		}
+	}
+
+	// Height of a heightfield's surface at a point on the XZ plane, blended between the surrounding samples
+	// Other kinds of colliders, points off the grid, and points beside a missing sample have no height
+	pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+		let (origin, cell_size, columns, rows, heights) = match self {
+			Self::Heightfield {
+				origin,
+				cell_size,
+				columns,
+				rows,
+				heights,
+			} => (origin, *cell_size, *columns, *rows, heights),
+			Self::Triangles { .. } => return None,
+		};
+
+		let (column, row) = ((x - origin[0]) / cell_size, (z - origin[1]) / cell_size);
+		if column < 0. || row < 0. || column > (columns - 1) as f32 || row > (rows - 1) as f32 {
+			return None;
+		}
+
+		// The last sample of each side is treated as the far corner of the cell before it
+		let first_column = (column.floor() as u32).min(columns.saturating_sub(2));
+		let first_row = (row.floor() as u32).min(rows.saturating_sub(2));
+		let sample = |column: u32, row: u32| heights[(row.min(rows - 1) * columns + column.min(columns - 1)) as usize];
+		let corners = [
+			sample(first_column, first_row),
+			sample(first_column + 1, first_row),
+			sample(first_column, first_row + 1),
+			sample(first_column + 1, first_row + 1),
+		];
+		if corners.iter().any(|height| height.is_nan()) {
+			return None;
+		}
+
+		let (blend_x, blend_z) = ((column - first_column as f32).clamp(0., 1.), (row - first_row as f32).clamp(0., 1.));
+		let near = corners[0] + (corners[1] - corners[0]) * blend_x;
+		let far = corners[2] + (corners[3] - corners[2]) * blend_x;
+		Some(near + (far - near) * blend_z)
+	}
+
+	// Each edge once, for drawing the collider as lines
+	pub fn wireframe(&self) -> Wireframe {
+		match self {
+			Self::Triangles { vertices, indices, .. } => {
+				let mut edges = HashSet::new();
+				for triangle in indices.chunks_exact(3) {
+					for corner in 0..3 {
+						let (start, end) = (triangle[corner], triangle[(corner + 1) % 3]);
+						edges.insert((start.min(end), start.max(end)));
+					}
+				}
+
+				edges
+					.into_iter()
+					.map(|(start, end)| [Point3::from(vertices[start as usize]), Point3::from(vertices[end as usize])])
+					.collect()
+			}
+			Self::Heightfield {
+				origin,
+				cell_size,
+				columns,
+				rows,
+				heights,
+			} => {
+				let sample = |column: u32, row: u32| {
+					let height = heights[(row * columns + column) as usize];
+					(!height.is_nan()).then(|| Point3::new(origin[0] + column as f32 * cell_size, height, origin[1] + row as f32 * cell_size))
+				};
+
+				let mut lines = Vec::new();
+				for row in 0..*rows {
+					for column in 0..*columns {
+						let neighbors = [(column + 1 < *columns).then_some((column + 1, row)), (row + 1 < *rows).then_some((column, row + 1))];
+						for (neighbor_column, neighbor_row) in neighbors.into_iter().flatten() {
+							if let (Some(start), Some(end)) = (sample(column, row), sample(neighbor_column, neighbor_row)) {
+								lines.push([start, end]);
+							}
+						}
+					}
+				}
+				lines
+			}
+		}
+	}
+
+	fn to_cache(&self, source_hash: u64) -> Vec<u8> {
+		let mut bytes = Vec::new();
+		bytes.extend(CACHE_MAGIC);
+		bytes.extend(CACHE_VERSION.to_le_bytes());
+		bytes.extend(source_hash.to_le_bytes());
+		bytes.push(ColliderKind::ALL.iter().position(|kind| *kind == self.kind()).unwrap() as u8);
+
+		match self {
+			Self::Triangles { vertices, indices, .. } => {
+				bytes.extend((vertices.len() as u32).to_le_bytes());
+				bytes.extend(vertices.iter().flatten().flat_map(|coordinate| coordinate.to_le_bytes()));
+				bytes.extend((indices.len() as u32).to_le_bytes());
+				bytes.extend(indices.iter().flat_map(|index| index.to_le_bytes()));
+			}
+			Self::Heightfield {
+				origin,
+				cell_size,
+				columns,
+				rows,
+				heights,
+			} => {
+				bytes.extend(origin.iter().flat_map(|coordinate| coordinate.to_le_bytes()));
+				bytes.extend(cell_size.to_le_bytes());
+				bytes.extend(columns.to_le_bytes());
+				bytes.extend(rows.to_le_bytes());
+				bytes.extend(heights.iter().flat_map(|height| height.to_le_bytes()));
+			}
+		}
+
+		bytes
+	}
+
+	fn from_cache(bytes: &[u8], source_hash: u64, kind: ColliderKind) -> Result<Self> {
+		let mut reader = CacheReader::new(bytes);
+
+		if reader.take(4)? != CACHE_MAGIC || reader.u32()? != CACHE_VERSION {
+			bail!("Not a collider cache from this version of the engine");
+		}
+		if reader.u64()? != source_hash {
+			bail!("The mesh has changed since it was cached");
+		}
+		if ColliderKind::ALL.get(reader.take(1)?[0] as usize) != Some(&kind) {
+			bail!("The cache holds a different kind of collider");
+		}
+
+		let collider = match kind {
+			ColliderKind::Heightfield => {
+				let origin = [reader.f32()?, reader.f32()?];
+				let cell_size = reader.f32()?;
+				let (columns, rows) = (reader.u32()?, reader.u32()?);
+				let heights = (0..columns * rows).map(|_| reader.f32()).collect::<Result<Vec<_>>>()?;
+
+				Self::Heightfield {
+					origin,
+					cell_size,
+					columns,
+					rows,
+					heights,
+				}
+			}
+			_ => {
+				let vertex_count = reader.u32()?;
+				let vertices = (0..vertex_count).map(|_| Ok([reader.f32()?, reader.f32()?, reader.f32()?])).collect::<Result<Vec<_>>>()?;
+				let index_count = reader.u32()?;
+				let indices = (0..index_count).map(|_| reader.u32()).collect::<Result<Vec<_>>>()?;
+
+				if indices.iter().any(|index| *index >= vertex_count) {
+					bail!("The cache has indices beyond its vertices");
+				}
+
+				Self::Triangles { kind, vertices, indices }
+			}
+		};
+
+		Ok(collider)
+	}
+}
+
+// Merges coincident corners into shared vertices, dropping the triangles that collapse
+fn weld(triangles: &[[[f32; 3]; 3]]) -> (Vec<[f32; 3]>, Vec<u32>) {
+	let key = |point: [f32; 3]| point.map(|coordinate| (coordinate / WELD_DISTANCE).round() as i64);
+
+	let mut welded = HashMap::new();
+	let mut vertices = Vec::new();
+	let mut indices = Vec::new();
+	for triangle in triangles {
+		let corners = triangle.map(|corner| {
+			*welded.entry(key(corner)).or_insert_with(|| {
+				vertices.push(corner);
+				vertices.len() as u32 - 1
+			})
+		});
+
+		if corners[0] != corners[1] && corners[1] != corners[2] && corners[2] != corners[0] {
+			indices.extend(corners);
+		}
+	}
+
+	(vertices, indices)
+}
+
+// Snaps the vertices to a coarse grid and merges each cell's vertices into their average, keeping only the triangles that still span three cells
+fn cluster_vertices(triangles: &[[[f32; 3]; 3]]) -> (Vec<[f32; 3]>, Vec<u32>) {
+	let (min, max) = bounds(triangles.iter().flatten());
+	let extent = (0..3).map(|axis| max[axis] - min[axis]).fold(0_f32, f32::max);
+	let cell_size = (extent / PROXY_RESOLUTION).max(WELD_DISTANCE);
+	let cell = |point: [f32; 3]| [0, 1, 2].map(|axis| ((point[axis] - min[axis]) / cell_size).floor() as i32);
+
+	let mut cells = HashMap::<[i32; 3], (u32, Vector3<f32>, u32)>::new();
+	let mut indices = Vec::new();
+	let mut seen_triangles = HashSet::new();
+	for triangle in triangles {
+		let corners = triangle.map(|corner| {
+			let cell_count = cells.len() as u32;
+			let (index, sum, count) = cells.entry(cell(corner)).or_insert((cell_count, Vector3::new(0., 0., 0.), 0));
+			*sum += Vector3::from(corner);
+			*count += 1;
+			*index
+		});
+
+		// The same cells can be spanned by many small triangles, which only need to be kept once
+		let mut sorted = corners;
+		sorted.sort_unstable();
+		if corners[0] != corners[1] && corners[1] != corners[2] && corners[2] != corners[0] && seen_triangles.insert(sorted) {
+			indices.extend(corners);
+		}
+	}
+
+	let mut vertices = vec![[0.; 3]; cells.len()];
+	for (index, sum, count) in cells.values() {
+		vertices[*index as usize] = (*sum / *count as f32).into();
+	}
+
+	// Cells whose every triangle collapsed are left out
+	let mut remap = vec![u32::MAX; vertices.len()];
+	let mut used_vertices = Vec::new();
+	for index in &mut indices {
+		if remap[*index as usize] == u32::MAX {
+			remap[*index as usize] = used_vertices.len() as u32;
+			used_vertices.push(vertices[*index as usize]);
+		}
+		*index = remap[*index as usize];
+	}
+
+	(used_vertices, indices)
+}
+
+// Incremental convex hull: starting from a tetrahedron, each point outside the hull replaces the faces it can see with a fan from itself to their outline
+// Meshes that are flat or degenerate have no volume to enclose, so they return no triangles
+fn convex_hull(points: &[[f32; 3]]) -> (Vec<[f32; 3]>, Vec<u32>) {
+	let points = points.iter().map(|point| Vector3::from(*point).cast::<f64>().unwrap()).collect::<Vec<_>>();
+	if points.len() < 4 {
+		return (vec![], vec![]);
+	}
+
+	let epsilon = WELD_DISTANCE as f64;
+	let farthest = |score: &dyn Fn(Vector3<f64>) -> f64| (0..points.len()).max_by(|a, b| score(points[*a]).total_cmp(&score(points[*b]))).unwrap();
+
+	// The initial tetrahedron spans as much of the points as possible so few points start outside it
+	let first = farthest(&|point| -point.x);
+	let second = farthest(&|point| point.x);
+	let axis = points[second] - points[first];
+	let third = farthest(&|point| axis.cross(point - points[first]).magnitude2());
+	let normal = axis.cross(points[third] - points[first]);
+	let fourth = farthest(&|point| normal.dot(point - points[first]).abs());
+	if axis.magnitude2() <= epsilon * epsilon || normal.magnitude2() <= epsilon * epsilon || normal.normalize().dot(points[fourth] - points[first]).abs() <= epsilon {
+		return (vec![], vec![]);
+	}
+
+	let center = (points[first] + points[second] + points[third] + points[fourth]) / 4.;
+	let face_normal = |face: &[usize; 3]| (points[face[1]] - points[face[0]]).cross(points[face[2]] - points[face[0]]);
+	let outward = |face: [usize; 3]| {
+		if face_normal(&face).dot(points[face[0]] - center) < 0. {
+			[face[0], face[2], face[1]]
+		} else {
+			face
+		}
+	};
+
+	let mut faces = vec![
+		outward([first, second, third]),
+		outward([first, second, fourth]),
+		outward([first, third, fourth]),
+		outward([second, third, fourth]),
+	];
+
+	for (index, point) in points.iter().enumerate() {
+		let visible = faces
+			.iter()
+			.map(|face| {
+				let normal = face_normal(face);
+				normal.dot(*point - points[face[0]]) > epsilon * normal.magnitude()
+			})
+			.collect::<Vec<_>>();
+		if !visible.contains(&true) {
+			continue;
+		}
+
+		// The outline of the visible faces is made of their edges that aren't shared with another visible face
+		let visible_edges = faces
+			.iter()
+			.zip(&visible)
+			.filter(|(_, visible)| **visible)
+			.flat_map(|(face, _)| [(face[0], face[1]), (face[1], face[2]), (face[2], face[0])])
+			.collect::<HashSet<_>>();
+		let horizon = visible_edges.iter().filter(|(start, end)| !visible_edges.contains(&(*end, *start))).copied().collect::<Vec<_>>();
+
+		let mut kept_faces = faces.iter().zip(&visible).filter(|(_, visible)| !**visible).map(|(face, _)| *face).collect::<Vec<_>>();
+		kept_faces.extend(horizon.into_iter().map(|(start, end)| [start, end, index]));
+		faces = kept_faces;
+	}
+
+	// Only the points on the hull are kept, renumbered in the order the faces use them
+	let mut remap = HashMap::new();
+	let mut vertices = Vec::new();
+	let mut indices = Vec::new();
+	for corner in faces.iter().flatten() {
+		let index = *remap.entry(*corner).or_insert_with(|| {
+			vertices.push(points[*corner].cast::<f32>().unwrap().into());
+			vertices.len() as u32 - 1
+		});
+		indices.push(index);
+	}
+
+	(vertices, indices)
+}
+
+// Rasterizes the triangles from above onto the grid, keeping the highest surface at each sample
+fn heightfield(triangles: &[[[f32; 3]; 3]]) -> Collider {
+	let (min, max) = bounds(triangles.iter().flatten());
+	let longest_side = (max[0] - min[0]).max(max[2] - min[2]).max(0.);
+	let cell_size = HEIGHTFIELD_CELL_SIZE.max(longest_side / (HEIGHTFIELD_MAX_SAMPLES - 1) as f32);
+	let columns = ((max[0] - min[0]).max(0.) / cell_size).floor() as u32 + 1;
+	let rows = ((max[2] - min[2]).max(0.) / cell_size).floor() as u32 + 1;
+
+	let mut heights = vec![f32::NAN; (columns * rows) as usize];
+	for triangle in triangles {
+		let [a, b, c] = triangle.map(Point3::from);
+
+		// Walls seen edge-on from above cover no area on the grid
+		let area = (b.x - a.x) * (c.z - a.z) - (c.x - a.x) * (b.z - a.z);
+		if area.abs() <= f32::MIN_POSITIVE {
+			continue;
+		}
+
+		let (triangle_min, triangle_max) = bounds(triangle.iter());
+		let first_column = ((triangle_min[0] - min[0]) / cell_size).ceil().max(0.) as u32;
+		let last_column = (((triangle_max[0] - min[0]) / cell_size).floor() as u32).min(columns - 1);
+		let first_row = ((triangle_min[2] - min[2]) / cell_size).ceil().max(0.) as u32;
+		let last_row = (((triangle_max[2] - min[2]) / cell_size).floor() as u32).min(rows - 1);
+
+		for row in first_row..=last_row {
+			for column in first_column..=last_column {
+				let (x, z) = (min[0] + column as f32 * cell_size, min[2] + row as f32 * cell_size);
+
+				// Barycentric weights of the sample within the triangle's shadow on the XZ plane
+				let weight_b = ((x - a.x) * (c.z - a.z) - (c.x - a.x) * (z - a.z)) / area;
+				let weight_c = ((b.x - a.x) * (z - a.z) - (x - a.x) * (b.z - a.z)) / area;
+				let weight_a = 1. - weight_b - weight_c;
+				if weight_a < 0. || weight_b < 0. || weight_c < 0. {
+					continue;
+				}
+
+				let height = a.y * weight_a + b.y * weight_b + c.y * weight_c;
+				let sample = &mut heights[(row * columns + column) as usize];
+				if sample.is_nan() || height > *sample {
+					*sample = height;
+				}
+			}
+		}
+	}
+
+	Collider::Heightfield {
+		origin: [min[0], min[2]],
+		cell_size,
+		columns,
+		rows,
+		heights,
+	}
+}
+
+fn bounds<'a>(points: impl Iterator<Item = &'a [f32; 3]>) -> ([f32; 3], [f32; 3]) {
+	points.fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), point| {
+		([0, 1, 2].map(|axis| min[axis].min(point[axis])), [0, 1, 2].map(|axis| max[axis].max(point[axis])))
+	})
+}
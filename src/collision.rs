@@ -0,0 +1,285 @@
+use crate::model::Mobility;
+use crate::scene::Scene;
+
+use cgmath::{InnerSpace, Vector3};
+use std::collections::HashMap;
+
+// Width of the cubic cells the triangles are bucketed into, so a capsule only tests the triangles around it
+const CELL_SIZE: f32 = 2.;
+// Overlaps are pushed out one after another, so a capsule wedged into a corner takes a few passes to settle clear of every triangle
+const RESOLVE_ITERATIONS: usize = 4;
+// How many times the step along a sphere cast that first touched something is halved to narrow down where
+const SPHERE_CAST_REFINEMENTS: usize = 6;
+// Contacts whose push is at least this steep count as standing on the ground, which lets the capsule walk up slopes of about 45° but not walls
+const GROUND_NORMAL_Y: f32 = 0.7;
+
+// Upright capsule, placed by the point at the bottom of its lower hemisphere
+#[derive(Debug, Copy, Clone)]
+pub struct Capsule {
+	pub radius: f32,
+	// Overall height from the bottom to the top, including both hemispheres
+	pub height: f32,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct CapsuleContact {
+	// Where the capsule was pushed to so it no longer overlaps anything
+	pub location: Vector3<f32>,
+	pub on_ground: bool,
+	pub on_ceiling: bool,
+}
+
+// World space triangles of the static models, which never move after the scene is built so they are gathered once at load time
+// There's no physics system, so this only answers the overlap queries a character needs to walk around the scene
+#[derive(Debug, Default)]
+pub struct CollisionWorld {
+	triangles: Vec<[Vector3<f32>; 3]>,
+	cells: HashMap<(i32, i32, i32), Vec<u32>>,
+}
+
+impl CollisionWorld {
+	pub fn build(scene: &Scene) -> Self {
+		let mut world = Self::default();
+
+		for entity in &scene.root {
+			for model in entity.get_models() {
+				let mesh = match model.mesh {
+					Some(mesh) if model.mobility == Mobility::Static => &scene.resources.meshes[mesh],
+					_ => continue,
+				};
+
+				for instance in model.instances.world_instances() {
+					let scale = instance.scale;
+					let to_world = |point: [f32; 3]| instance.location + instance.rotation * Vector3::new(point[0] * scale.x, point[1] * scale.y, point[2] * scale.z);
+
+					for triangle in &mesh.collision_triangles {
+						world.insert(triangle.map(to_world));
+					}
+				}
+			}
+		}
+
+		world
+	}
+
+	// Every static triangle in world space, with degenerate ones left out
+	pub fn triangles(&self) -> &[[Vector3<f32>; 3]] {
+		&self.triangles
+	}
+
+	fn insert(&mut self, triangle: [Vector3<f32>; 3]) {
+		// Degenerate triangles have no facing to push anything out along
+		if (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]).magnitude2() <= f32::MIN_POSITIVE {
+			return;
+		}
+
+		let index = self.triangles.len() as u32;
+		let (min, max) = triangle_bounds(&triangle);
+
+		for cell in cells_overlapping(min, max) {
+			self.cells.entry(cell).or_default().push(index);
+		}
+
+		self.triangles.push(triangle);
+	}
+
+	// Each triangle is listed once even where it spans several of the cells in the box
+	fn triangles_near(&self, min: Vector3<f32>, max: Vector3<f32>) -> Vec<u32> {
+		let mut indices = cells_overlapping(min, max).filter_map(|cell| self.cells.get(&cell)).flatten().copied().collect::<Vec<_>>();
+		indices.sort_unstable();
+		indices.dedup();
+		indices
+	}
+
+	// Moves the capsule out of any triangles it overlaps at this location, along the shortest way out of each
+	pub fn resolve_capsule(&self, capsule: &Capsule, location: Vector3<f32>) -> CapsuleContact {
+		let mut contact = CapsuleContact {
+			location,
+			on_ground: false,
+			on_ceiling: false,
+		};
+
+		let up = Vector3::unit_y();
+		let margin = Vector3::new(capsule.radius, capsule.radius, capsule.radius);
+		let nearby = self.triangles_near(location - margin, location + up * capsule.height + margin);
+
+		for _ in 0..RESOLVE_ITERATIONS {
+			let mut pushed = false;
+
+			for &index in &nearby {
+				// The centers of the two hemispheres, which the capsule's surface surrounds at its radius
+				let bottom = contact.location + up * capsule.radius;
+				let top = contact.location + up * (capsule.height - capsule.radius).max(capsule.radius);
+
+				let triangle = &self.triangles[index as usize];
+				let (center, closest) = closest_points_segment_triangle(bottom, top, triangle);
+
+				let offset = center - closest;
+				let distance = offset.magnitude();
+				if distance >= capsule.radius {
+					continue;
+				}
+
+				// A segment passing right through the triangle has no direction to leave by, so it backs out the side of the triangle it's on
+				let direction = if distance > f32::EPSILON {
+					offset / distance
+				} else {
+					let normal = (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]).normalize();
+					if normal.dot(bottom - triangle[0]) < 0. {
+						-normal
+					} else {
+						normal
+					}
+				};
+
+				contact.location += direction * (capsule.radius - distance);
+				contact.on_ground |= direction.y >= GROUND_NORMAL_Y;
+				contact.on_ceiling |= direction.y <= -GROUND_NORMAL_Y;
+				pushed = true;
+			}
+
+			if !pushed {
+				break;
+			}
+		}
+
+		contact
+	}
+
+	// How far a sphere can travel from the origin along the direction before it touches any triangle, up to the given distance
+	// The sphere steps along by half its radius, which is short enough that it can't skip past a triangle, and the first step to touch one is then bisected
+	pub fn sphere_cast(&self, origin: Vector3<f32>, direction: Vector3<f32>, max_distance: f32, radius: f32) -> f32 {
+		let end = origin + direction * max_distance;
+		let margin = Vector3::new(radius, radius, radius);
+		let min = Vector3::new(origin.x.min(end.x), origin.y.min(end.y), origin.z.min(end.z)) - margin;
+		let max = Vector3::new(origin.x.max(end.x), origin.y.max(end.y), origin.z.max(end.z)) + margin;
+		let nearby = self.triangles_near(min, max);
+
+		let overlaps = |distance: f32| {
+			let center = origin + direction * distance;
+			nearby
+				.iter()
+				.any(|&index| (center - closest_point_on_triangle(center, &self.triangles[index as usize])).magnitude2() < radius * radius)
+		};
+
+		if overlaps(0.) {
+			return 0.;
+		}
+
+		let step = (radius * 0.5).max(0.01);
+		let mut free = 0.;
+		while free < max_distance {
+			let next = (free + step).min(max_distance);
+			if overlaps(next) {
+				let mut touching = next;
+				for _ in 0..SPHERE_CAST_REFINEMENTS {
+					let middle = (free + touching) * 0.5;
+					if overlaps(middle) {
+						touching = middle;
+					} else {
+						free = middle;
+					}
+				}
+				return free;
+			}
+			free = next;
+		}
+
+		max_distance
+	}
+}
+
+fn triangle_bounds(triangle: &[Vector3<f32>; 3]) -> (Vector3<f32>, Vector3<f32>) {
+	let min = Vector3::new(
+		triangle[0].x.min(triangle[1].x).min(triangle[2].x),
+		triangle[0].y.min(triangle[1].y).min(triangle[2].y),
+		triangle[0].z.min(triangle[1].z).min(triangle[2].z),
+	);
+	let max = Vector3::new(
+		triangle[0].x.max(triangle[1].x).max(triangle[2].x),
+		triangle[0].y.max(triangle[1].y).max(triangle[2].y),
+		triangle[0].z.max(triangle[1].z).max(triangle[2].z),
+	);
+
+	(min, max)
+}
+
+fn cells_overlapping(min: Vector3<f32>, max: Vector3<f32>) -> impl Iterator<Item = (i32, i32, i32)> {
+	let cell = |value: f32| (value / CELL_SIZE).floor() as i32;
+	let (min_x, min_y, min_z) = (cell(min.x), cell(min.y), cell(min.z));
+	let (max_x, max_y, max_z) = (cell(max.x), cell(max.y), cell(max.z));
+
+	(min_x..=max_x).flat_map(move |x| (min_y..=max_y).flat_map(move |y| (min_z..=max_z).map(move |z| (x, y, z))))
+}
+
+// The point on the segment nearest the triangle, along with the point on the triangle nearest to it
+// The nearest point is approached by first finding where the segment meets the triangle's plane, which is exact wherever the segment crosses the triangle and close enough elsewhere for pushing a capsule out
+fn closest_points_segment_triangle(start: Vector3<f32>, end: Vector3<f32>, triangle: &[Vector3<f32>; 3]) -> (Vector3<f32>, Vector3<f32>) {
+	let normal = (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]);
+	let direction = end - start;
+
+	// A segment parallel to the plane has no crossing, so its start stands in for it
+	let along = normal.dot(direction);
+	let plane_point = if along.abs() > f32::EPSILON {
+		let t = (normal.dot(triangle[0] - start) / along).clamp(0., 1.);
+		start + direction * t
+	} else {
+		start
+	};
+
+	let reference = closest_point_on_triangle(plane_point, triangle);
+	let center = closest_point_on_segment(reference, start, end);
+	(center, closest_point_on_triangle(center, triangle))
+}
+
+fn closest_point_on_segment(point: Vector3<f32>, start: Vector3<f32>, end: Vector3<f32>) -> Vector3<f32> {
+	let direction = end - start;
+	let length_squared = direction.magnitude2();
+	if length_squared <= f32::EPSILON {
+		return start;
+	}
+
+	let t = ((point - start).dot(direction) / length_squared).clamp(0., 1.);
+	start + direction * t
+}
+
+// Finds which of the triangle's corners, edges, or face the point lies nearest to, from Christer Ericson's Real-Time Collision Detection
+pub fn closest_point_on_triangle(point: Vector3<f32>, triangle: &[Vector3<f32>; 3]) -> Vector3<f32> {
+	let [a, b, c] = *triangle;
+	let (ab, ac, ap) = (b - a, c - a, point - a);
+
+	let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+	if d1 <= 0. && d2 <= 0. {
+		return a;
+	}
+
+	let bp = point - b;
+	let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+	if d3 >= 0. && d4 <= d3 {
+		return b;
+	}
+
+	let vc = d1 * d4 - d3 * d2;
+	if vc <= 0. && d1 >= 0. && d3 <= 0. {
+		return a + ab * (d1 / (d1 - d3));
+	}
+
+	let cp = point - c;
+	let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+	if d6 >= 0. && d5 <= d6 {
+		return c;
+	}
+
+	let vb = d5 * d2 - d1 * d6;
+	if vb <= 0. && d2 >= 0. && d6 <= 0. {
+		return a + ac * (d2 / (d2 - d6));
+	}
+
+	let va = d3 * d6 - d5 * d4;
+	if va <= 0. && d4 - d3 >= 0. && d5 - d6 >= 0. {
+		return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+	}
+
+	let denominator = 1. / (va + vb + vc);
+	a + ab * (vb * denominator) + ac * (vc * denominator)
+}
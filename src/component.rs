@@ -1,12 +1,16 @@
 use crate::behavior::Behavior;
 use crate::camera::Camera;
 use crate::light::Light;
+use crate::mirror::Mirror;
 use crate::model::Model;
+use crate::portal::Portal;
 
 #[derive(Debug)]
 pub enum Component {
-	Model(Model),
+	Model(Box<Model>),
 	Light(Light),
 	Camera(Box<Camera>),
 	Behavior(Box<dyn Behavior>),
+	Mirror(Box<Mirror>),
+	Portal(Box<Portal>),
 }
@@ -0,0 +1,252 @@
+use crate::render_settings::RenderSettings;
+
+use wgpu::util::DeviceExt;
+use winit::event::{ElementState, VirtualKeyCode};
+
+// Rows of text shown at once, the last of which is the line being typed
+pub const CONSOLE_ROWS: u32 = 16;
+// Font pixels in each character cell, fitting a 5 by 6 glyph with a gap of one pixel around it
+const CELL_SIZE: (u32, u32) = (6, 8);
+// Screen pixels per font pixel
+const FONT_SCALE: u32 = 2;
+// Cells in each row on the widest screens, which the console's buffer is sized for
+const MAX_COLUMNS: u32 = 320;
+// Lines of output kept for scrolling back through, beyond which the oldest are dropped
+const SCROLLBACK: usize = 256;
+const PROMPT: &str = "> ";
+
+// Glyphs for the characters from space (32) to underscore (95), each packed into the low 30 bits with bit `row * 5 + column` set where the glyph is filled
+// There are no lowercase letters, so text is shown in capitals
+#[rustfmt::skip]
+const FONT: [u32; 64] = [
+	0x00000000, 0x08021084, 0x0000014a, 0x00afabea, 0x08fa38be, 0x019d1173, 0x2c9a9926, 0x00000084,
+	0x10421088, 0x04421082, 0x00051140, 0x00023880, 0x04400000, 0x00003800, 0x08000000, 0x00111110,
+	0x1d19d72e, 0x1c4210c4, 0x3e22222e, 0x1f083a0f, 0x108fa988, 0x1f083c3f, 0x1d18bc2e, 0x0842221f,
+	0x1d18ba2e, 0x1d0f462e, 0x00400080, 0x04400080, 0x00820888, 0x000701c0, 0x00222082, 0x0802222e,
+	0x1d9af62e, 0x231fc62e, 0x1f18be2f, 0x3c10843e, 0x1f18c62f, 0x3e10bc3f, 0x0210bc3f, 0x3d18e43e,
+	0x2318fe31, 0x1c42108e, 0x0c94211c, 0x23149d31, 0x3e108421, 0x2318d771, 0x231cd671, 0x1d18c62e,
+	0x0217c62f, 0x2c9ac62e, 0x2297c62f, 0x1f08383e, 0x0842109f, 0x1d18c631, 0x08a8c631, 0x23bac631,
+	0x22a21151, 0x08421151, 0x3e11111f, 0x1842108c, 0x01041041, 0x0c421086, 0x00000144, 0x3e000000,
+];
+
+// Commands the engine runs, each with its usage and what it does, for `help` and tab completion
+// The render settings can also be read by typing their name, and changed by following it with a value
+pub const CONSOLE_COMMANDS: [(&str, &str); 15] = [
+	("help", "Lists the commands and settings"),
+	("clear", "Clears the console"),
+	("exposure [value]", "Shows or sets the camera's exposure"),
+	(
+		"ambient [sky|ground <r> <g> <b>, intensity <value>, mode <fallback|additive>]",
+		"Shows or changes the hemisphere light filling in for the voxel GI",
+	),
+	(
+		"fog [off|linear|exponential|height, color <r> <g> <b>, distance <start> <end>, density <value>, height <base> <falloff>]",
+		"Shows or changes the fog over the scene",
+	),
+	(
+		"grading [saturation|contrast|vignette|grain <value>]",
+		"Shows or changes the color grading, vignette, and film grain finishing the frame",
+	),
+	(
+		"environment [sky <r> <g> <b>, exposure <value>, sky_map <panorama|6 faces|none>, sky_map_intensity <value>, save]",
+		"Shows or changes the scene's sky light, exposure, and sky map, or saves its environment to the scene file",
+	),
+	("spawn <prefab>", "Places a prefab in front of the camera"),
+	("despawn <entity>", "Removes an entity and its children from the scene"),
+	(
+		"scene [load|unload <file>]",
+		"Lists the scene files loaded on top of the scene, or loads or unloads one from the scenes folder",
+	),
+	(
+		"stream [<world>|off]",
+		"Shows how far along streaming the world is, or starts or stops streaming the chunks of a world manifest in the scenes folder",
+	),
+	("load <timeline>", "Loads a timeline from the timelines folder and plays it"),
+	("stats", "Prints the last frame's draw statistics and CPU timings"),
+	(
+		"benchmark [entities]",
+		"Times updating the world transforms, building the draw lists, and culling a generated scene of 20000 or the given number of entities on growing numbers of threads",
+	),
+	("gpu", "Prints the GPU, its limits, and which optional features are in use"),
+];
+
+// What `spawn` can place, all beveled cubes stretched to these scales
+pub const CONSOLE_PREFABS: [(&str, [f64; 3]); 3] = [("cube", [0.25, 0.25, 0.25]), ("pillar", [0.15, 1., 0.15]), ("slab", [1., 0.1, 1.])];
+
+// Drop down overlay for typing commands into the running engine, opened and closed with the grave key
+// Typed lines are queued for the engine to run, since most commands need the whole engine, and their output is printed back here
+// The text is drawn by the console pass from a storage buffer of the character in each cell along with the font
+pub struct Console {
+	pub open: bool,
+	input: String,
+	// Oldest first, unwrapped
+	output: Vec<String>,
+	// Submitted lines, oldest first, stepped through with the up and down keys
+	history: Vec<String>,
+	history_index: Option<usize>,
+	// Rows scrolled back from the newest output with the page up and page down keys
+	scroll: usize,
+	submitted: Vec<String>,
+	pub console_buffer: wgpu::Buffer,
+}
+
+impl Console {
+	pub fn new(device: &wgpu::Device) -> Self {
+		// A header of the columns, rows, cursor cell, and font scale, then the font, then the cells
+		let contents = [0; 4]
+			.into_iter()
+			.chain(FONT)
+			.chain(std::iter::repeat(b' ' as u32).take((MAX_COLUMNS * CONSOLE_ROWS) as usize))
+			.collect::<Vec<u32>>();
+		let console_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Console Buffer"),
+			contents: bytemuck::cast_slice(&contents),
+			usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+		});
+
+		Self {
+			open: false,
+			input: String::new(),
+			output: vec![String::from("Type help for a list of commands")],
+			history: vec![],
+			history_index: None,
+			scroll: 0,
+			submitted: vec![],
+			console_buffer,
+		}
+	}
+
+	// Returns whether the key was taken by the console, which takes every key press while it's open so nothing else reacts to the typing
+	// Releases are left for the others, so keys held down as the console opens aren't stuck down
+	pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
+		if state != ElementState::Pressed {
+			return false;
+		}
+
+		match key {
+			VirtualKeyCode::Grave => self.open = !self.open,
+			_ if !self.open => return false,
+			VirtualKeyCode::Up => {
+				let index = self.history_index.map_or(self.history.len(), |index| index).saturating_sub(1);
+				if let Some(line) = self.history.get(index) {
+					self.input = line.clone();
+					self.history_index = Some(index);
+				}
+			}
+			VirtualKeyCode::Down => {
+				self.history_index = self.history_index.map(|index| index + 1).filter(|index| *index < self.history.len());
+				self.input = self.history_index.map_or_else(String::new, |index| self.history[index].clone());
+			}
+			VirtualKeyCode::PageUp => self.scroll = (self.scroll + (CONSOLE_ROWS - 2) as usize).min(self.output.len()),
+			VirtualKeyCode::PageDown => self.scroll = self.scroll.saturating_sub((CONSOLE_ROWS - 2) as usize),
+			_ => {}
+		}
+
+		true
+	}
+
+	// Typed text arrives as characters rather than keys, so it follows the keyboard layout and repeats while held
+	pub fn process_character(&mut self, character: char) {
+		if !self.open {
+			return;
+		}
+
+		match character {
+			'\u{8}' => {
+				self.input.pop();
+			}
+			'\r' | '\n' => self.submit(),
+			'\t' => self.complete(),
+			// Typed along with the key that toggles the console
+			'`' | '~' => {}
+			_ if character.is_ascii() && !character.is_ascii_control() => self.input.push(character),
+			_ => {}
+		}
+	}
+
+	fn submit(&mut self) {
+		let line = std::mem::take(&mut self.input).trim().to_string();
+		self.history_index = None;
+		if line.is_empty() {
+			return;
+		}
+
+		self.print(&format!("{}{}", PROMPT, line));
+		if self.history.last() != Some(&line) {
+			self.history.push(line.clone());
+		}
+
+		if line == "clear" {
+			self.output.clear();
+		} else {
+			self.submitted.push(line);
+		}
+	}
+
+	// Finishes the command or setting name being typed if only one starts that way, or lists them all if several do
+	fn complete(&mut self) {
+		if self.input.contains(' ') {
+			return;
+		}
+
+		let command_names = CONSOLE_COMMANDS.iter().map(|(usage, _)| usage.split(' ').next().unwrap_or_default());
+		let names = command_names
+			.chain(RenderSettings::SETTING_NAMES)
+			.filter(|name| name.starts_with(self.input.as_str()))
+			.collect::<Vec<_>>();
+		match names.as_slice() {
+			[] => {}
+			[name] => self.input = format!("{} ", name),
+			_ => self.print(&names.join("  ")),
+		}
+	}
+
+	// Lines typed since the last call, for the engine to run
+	pub fn take_submitted(&mut self) -> Vec<String> {
+		std::mem::take(&mut self.submitted)
+	}
+
+	pub fn print(&mut self, text: &str) {
+		self.scroll = 0;
+		self.output.extend(text.lines().map(String::from));
+		if self.output.len() > SCROLLBACK {
+			self.output.drain(..self.output.len() - SCROLLBACK);
+		}
+	}
+
+	// Height of the overlay in screen pixels
+	pub fn height(&self) -> u32 {
+		CONSOLE_ROWS * CELL_SIZE.1 * FONT_SCALE
+	}
+
+	// Lays the newest output out above the input line, wrapped to the width of the screen, and uploads it for the console pass
+	pub fn update(&self, queue: &wgpu::Queue, screen_width: u32) {
+		if !self.open {
+			return;
+		}
+
+		let columns = (screen_width / (CELL_SIZE.0 * FONT_SCALE)).clamp(1, MAX_COLUMNS) as usize;
+		let wrap = |line: &str| line.chars().collect::<Vec<_>>().chunks(columns).map(|row| row.to_vec()).collect::<Vec<_>>();
+
+		let output_rows = self.output.iter().flat_map(|line| if line.is_empty() { vec![vec![]] } else { wrap(line) }).collect::<Vec<_>>();
+		// A line typed past the edge of the screen scrolls to keep its end in view
+		let input = format!("{}{}", PROMPT, self.input).chars().collect::<Vec<_>>();
+		let input_row = input[input.len().saturating_sub(columns - 1)..].to_vec();
+
+		let rows = (CONSOLE_ROWS - 1) as usize;
+		let last_row = output_rows.len().saturating_sub(self.scroll).max(rows.min(output_rows.len()));
+		let visible_rows = output_rows[last_row.saturating_sub(rows)..last_row].iter().cloned();
+		let mut cells = vec![b' ' as u32; columns * CONSOLE_ROWS as usize];
+		for (row_index, row) in std::iter::repeat(vec![]).take(rows.saturating_sub(last_row)).chain(visible_rows).chain([input_row.clone()]).enumerate() {
+			for (column_index, character) in row.into_iter().enumerate() {
+				// Anything outside the font is shown as a question mark
+				let character = character.to_ascii_uppercase() as u32;
+				cells[row_index * columns + column_index] = if (32..96).contains(&character) { character } else { b'?' as u32 };
+			}
+		}
+
+		let cursor = (rows * columns + input_row.len()) as u32;
+		queue.write_buffer(&self.console_buffer, 0, bytemuck::cast_slice(&[columns as u32, CONSOLE_ROWS, cursor, FONT_SCALE]));
+		queue.write_buffer(&self.console_buffer, ((4 + FONT.len()) * std::mem::size_of::<u32>()) as u64, bytemuck::cast_slice(&cells));
+	}
+}
@@ -1,64 +1,140 @@
+use crate::globals::Globals;
+use crate::gpu_features::GpuFeatures;
+use crate::gpu_selection::GpuSelection;
+
 use winit::window::Window;
 
+// Surface formats in order of preference, with sRGB first so the tone mapped output is encoded for display by the surface rather than the shaders
+const SURFACE_FORMATS: [wgpu::TextureFormat; 4] = [
+	wgpu::TextureFormat::Bgra8UnormSrgb,
+	wgpu::TextureFormat::Rgba8UnormSrgb,
+	wgpu::TextureFormat::Bgra8Unorm,
+	wgpu::TextureFormat::Rgba8Unorm,
+];
+
 pub struct Context {
 	pub surface: wgpu::Surface,
+	// Kept to negotiate the surface's format again if the surface is lost
+	pub adapter: wgpu::Adapter,
 	pub device: wgpu::Device,
 	pub queue: wgpu::Queue,
 	pub surface_configuration: wgpu::SurfaceConfiguration,
+	// Which GPU and driver the device is on, kept for crash reports
+	pub adapter_info: wgpu::AdapterInfo,
+	// The optional capabilities the device was created with, which the engine checks before taking the paths that need them
+	pub gpu_features: GpuFeatures,
+	// The adapter's name, limits, and which optional paths were taken, printed by the `gpu` console command
+	pub gpu_report: String,
+	// Bound to every pipeline built from a `Shader`
+	pub globals: Globals,
+	// Fills the camera and lighting slots of pipelines that don't use them, so every other bind group keeps its fixed index
+	pub placeholder_bind_group_layout: wgpu::BindGroupLayout,
+	pub placeholder_bind_group: wgpu::BindGroup,
 }
 
 impl Context {
-	pub async fn new(window: &Window) -> Self {
+	pub async fn new(window: &Window, gpu_selection: &GpuSelection) -> Self {
 		// Get the pixel resolution of the window's render area
 		let viewport_size = window.inner_size();
 
 		// The WGPU runtime
-		let instance = wgpu::Instance::new(wgpu::Backends::all());
+		let instance = wgpu::Instance::new(gpu_selection.backends);
 
 		// The viewport to draw on
 		let surface = unsafe { instance.create_surface(window) };
 
 		// Handle to the GPU
-		let adapter = instance
-			.request_adapter(&wgpu::RequestAdapterOptions {
-				power_preference: wgpu::PowerPreference::default(),
-				compatible_surface: Some(&surface),
-				force_fallback_adapter: false,
-			})
+		let adapter = gpu_selection
+			.request_adapter(&instance, &surface)
 			.await
-			.unwrap();
+			.expect("No GPU adapter can draw to the window with the chosen backends");
+
+		let gpu_features = GpuFeatures::from_adapter(&adapter);
+		let gpu_report = gpu_features.report(&adapter);
+
+		// With the `api-trace` feature, every wgpu call is recorded into the directory named by `SEURAT_API_TRACE` for replaying with wgpu's player
+		// The trace can only be chosen when the device is created, so it covers the whole run
+		#[cfg(feature = "api-trace")]
+		let trace_path = std::env::var_os("SEURAT_API_TRACE").map(std::path::PathBuf::from);
+		#[cfg(not(feature = "api-trace"))]
+		let trace_path: Option<std::path::PathBuf> = None;
+		if let Some(path) = &trace_path {
+			std::fs::create_dir_all(path).unwrap_or_else(|_| panic!("Can't create the API trace directory: {}", path.display()));
+		}
 
 		// Device is the living connection to the GPU
 		// Queue is where commands are submitted to the GPU
 		let (device, queue) = adapter
 			.request_device(
 				&wgpu::DeviceDescriptor {
-					features: wgpu::Features::empty(),
+					features: gpu_features.wgpu_features(),
 					limits: wgpu::Limits::default(),
 					label: None,
 				},
-				None,
+				trace_path.as_deref(),
 			)
 			.await
 			.unwrap();
 
 		// Build the configuration for the surface
 		let config = wgpu::SurfaceConfiguration {
-			usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-			format: surface.get_preferred_format(&adapter).unwrap(),
+			// Copied from so crash reports can include the last presented frame
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+			format: negotiate_surface_format(&surface, &adapter),
 			width: viewport_size.width,
 			height: viewport_size.height,
-			present_mode: wgpu::PresentMode::Fifo,
+			// Wgpu falls back to FIFO, which every surface supports, if the chosen mode isn't supported
+			present_mode: gpu_selection.present_mode,
 		};
 
 		// Configure the surface with the properties defined above
 		surface.configure(&device, &config);
 
+		let globals = Globals::new(&device, &config);
+		let placeholder_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			entries: &[],
+			label: Some("Placeholder bind group layout"),
+		});
+		let placeholder_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			layout: &placeholder_bind_group_layout,
+			entries: &[],
+			label: Some("Placeholder bind group"),
+		});
+
+		let adapter_info = adapter.get_info();
 		Self {
 			surface,
+			adapter,
 			device,
 			queue,
 			surface_configuration: config,
+			adapter_info,
+			gpu_features,
+			gpu_report,
+			globals,
+			placeholder_bind_group_layout,
+			placeholder_bind_group,
 		}
 	}
+
+	// A lost surface may come back supporting different formats, like after the window moves to a display on another GPU output
+	// Returns whether the format changed, in which case everything drawing into the surface has to be rebuilt before it's configured again
+	pub fn renegotiate_surface_format(&mut self) -> bool {
+		let format = negotiate_surface_format(&self.surface, &self.adapter);
+		let changed = format != self.surface_configuration.format;
+		self.surface_configuration.format = format;
+
+		changed
+	}
+}
+
+// The surface's own preferred format when it has one, otherwise the first of `SURFACE_FORMATS` the adapter can render into
+// Wgpu can't list the formats a surface supports, so the adapter's support is the closest check there is for the fallbacks
+fn negotiate_surface_format(surface: &wgpu::Surface, adapter: &wgpu::Adapter) -> wgpu::TextureFormat {
+	surface.get_preferred_format(adapter).unwrap_or_else(|| {
+		let renderable = |format: &wgpu::TextureFormat| adapter.get_texture_format_features(*format).allowed_usages.contains(wgpu::TextureUsages::RENDER_ATTACHMENT);
+		let format = SURFACE_FORMATS.into_iter().find(renderable).unwrap_or(SURFACE_FORMATS[0]);
+		eprintln!("The surface has no preferred format, so {:?} is used instead", format);
+		format
+	})
 }
@@ -0,0 +1,215 @@
+use crate::component::Component;
+use crate::context::Context;
+use crate::engine::Engine;
+use crate::entity::Entity;
+use crate::light::Light;
+use crate::render_settings::RenderSettings;
+
+use anyhow::{anyhow, Result};
+use std::fmt::Write;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Each crash gets its own folder in here, named by the milliseconds since the Unix epoch when it happened
+const CRASH_REPORT_FOLDER: &str = "crash_reports";
+
+// The folder the panic hook started for the panic currently unwinding, which the engine fills in with its state once the unwind reaches it
+static CRASH_FOLDER: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+// Writes the panic message, where it happened, and a backtrace into a new crash folder, then prints the panic as usual
+// This runs for every panic, even those before the engine exists, so it only records what the panic itself knows
+pub fn install_panic_hook() {
+	let default_hook = std::panic::take_hook();
+
+	std::panic::set_hook(Box::new(move |info| {
+		let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_millis());
+		let folder = Path::new(CRASH_REPORT_FOLDER).join(timestamp.to_string());
+		let report = format!("{}\n\n{}", info, std::backtrace::Backtrace::force_capture());
+
+		match std::fs::create_dir_all(&folder).and_then(|_| std::fs::write(folder.join("panic.txt"), report)) {
+			Ok(_) => {
+				eprintln!("Writing a crash report to {}", folder.display());
+				if let Ok(mut crash_folder) = CRASH_FOLDER.lock() {
+					*crash_folder = Some(folder);
+				}
+			}
+			Err(e) => eprintln!("Can't write a crash report: {:?}", e),
+		}
+
+		default_hook(info);
+	}));
+}
+
+// Runs the action, and if it panics, adds the engine's state to the crash report before letting the panic carry on
+pub fn report_crashes<T>(engine: &mut Engine, action: impl FnOnce(&mut Engine) -> T) -> T {
+	match std::panic::catch_unwind(AssertUnwindSafe(|| action(engine))) {
+		Ok(result) => result,
+		Err(payload) => {
+			let crash_folder = CRASH_FOLDER.lock().ok().and_then(|mut crash_folder| crash_folder.take());
+			if let Some(folder) = crash_folder {
+				engine.write_crash_report(&folder);
+			}
+
+			std::panic::resume_unwind(payload)
+		}
+	}
+}
+
+// Writes one file of the report, carrying on with the rest if it fails
+pub fn write_file(folder: &Path, file: &str, write: impl FnOnce(&Path) -> Result<()>) {
+	if let Err(e) = write(&folder.join(file)) {
+		eprintln!("Can't write {} to the crash report: {:?}", file, e);
+	}
+}
+
+// The GPU and the surface being drawn to, which is often the first thing needed to reproduce a rendering bug
+pub fn describe_adapter(context: &Context) -> String {
+	let configuration = &context.surface_configuration;
+	format!(
+		"{:#?}\n\n{:#?}\n\nSurface: {}x{} {:?}, {:?}\n",
+		context.adapter_info, context.gpu_features, configuration.width, configuration.height, configuration.format, configuration.present_mode
+	)
+}
+
+// The entity hierarchy as an indented outline, with each entity's transform and the kinds of its components
+pub fn describe_scene(root: &Entity) -> String {
+	fn describe_entity(entity: &Entity, depth: usize, text: &mut String) {
+		let indent = "\t".repeat(depth);
+		let location = entity.transform.location;
+		let rotation = entity.transform.rotation;
+		let scale = entity.transform.scale;
+
+		let _ = writeln!(text, "{}{}{}", indent, entity.name, if entity.enabled { "" } else { " (disabled)" });
+		let _ = writeln!(text, "{}  location: [{}, {}, {}]", indent, location.x, location.y, location.z);
+		let _ = writeln!(text, "{}  rotation: [{}, {}, {}, {}]", indent, rotation.s, rotation.v.x, rotation.v.y, rotation.v.z);
+		let _ = writeln!(text, "{}  scale: [{}, {}, {}]", indent, scale.x, scale.y, scale.z);
+
+		for component in &entity.components {
+			let kind = match component {
+				Component::Model(model) => format!("model {}:{}", model.mesh_name.0, model.mesh_name.1),
+				Component::Light(Light::Lamp(_)) => String::from("lamp"),
+				Component::Light(Light::Sun(_)) => String::from("sun"),
+				Component::Camera(_) => String::from("camera"),
+				Component::Behavior(_) => String::from("behavior"),
+				Component::Mirror(_) => String::from("mirror"),
+				Component::Portal(_) => String::from("portal"),
+			};
+			let _ = writeln!(text, "{}  + {}", indent, kind);
+		}
+
+		for child in &entity.children {
+			describe_entity(child, depth + 1, text);
+		}
+	}
+
+	let mut text = String::new();
+	describe_entity(root, 0, &mut text);
+	text
+}
+
+pub fn describe_render_settings(render_settings: &RenderSettings) -> String {
+	let settings = RenderSettings::SETTING_NAMES
+		.iter()
+		.map(|name| format!("{}: {}\n", name, render_settings.setting(name).unwrap_or_default()));
+	format!("{}\n{:#?}\n", settings.collect::<String>(), render_settings)
+}
+
+// A copy of the frame last presented to the window, kept since the surface's own texture can't be read back once it's presented
+pub struct LastFrame {
+	texture: wgpu::Texture,
+	format: wgpu::TextureFormat,
+	size: wgpu::Extent3d,
+}
+
+impl LastFrame {
+	pub fn new(device: &wgpu::Device, configuration: &wgpu::SurfaceConfiguration) -> Self {
+		let size = wgpu::Extent3d {
+			width: configuration.width,
+			height: configuration.height,
+			depth_or_array_layers: 1,
+		};
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("Last Frame"),
+			size,
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: configuration.format,
+			usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST,
+		});
+
+		Self {
+			texture,
+			format: configuration.format,
+			size,
+		}
+	}
+
+	pub fn resize(&mut self, device: &wgpu::Device, configuration: &wgpu::SurfaceConfiguration) {
+		*self = Self::new(device, configuration);
+	}
+
+	// Copies the frame about to be presented, which costs a single texture copy on the GPU each frame
+	pub fn record_copy(&self, encoder: &mut wgpu::CommandEncoder, surface_texture: &wgpu::Texture) {
+		encoder.copy_texture_to_texture(surface_texture.as_image_copy(), self.texture.as_image_copy(), self.size);
+	}
+
+	// Reads the frame back from the GPU and saves it as a PNG
+	pub fn save(&self, context: &Context, path: &Path) -> Result<()> {
+		let (width, height) = (self.size.width as usize, self.size.height as usize);
+
+		// Rows copied into a buffer have to start at a multiple of the alignment
+		let row_size = width * 4;
+		let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize;
+		let padded_row_size = row_size.div_ceil(alignment) * alignment;
+
+		let buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Last Frame Readback Buffer"),
+			size: (padded_row_size * height) as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+			mapped_at_creation: false,
+		});
+
+		let mut encoder = context.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+			label: Some("Last Frame Readback Encoder"),
+		});
+		encoder.copy_texture_to_buffer(
+			self.texture.as_image_copy(),
+			wgpu::ImageCopyBuffer {
+				buffer: &buffer,
+				layout: wgpu::ImageDataLayout {
+					offset: 0,
+					bytes_per_row: std::num::NonZeroU32::new(padded_row_size as u32),
+					rows_per_image: std::num::NonZeroU32::new(height as u32),
+				},
+			},
+			self.size,
+		);
+		context.queue.submit(std::iter::once(encoder.finish()));
+
+		let slice = buffer.slice(..);
+		let mapping = slice.map_async(wgpu::MapMode::Read);
+		context.device.poll(wgpu::Maintain::Wait);
+		pollster::block_on(mapping).map_err(|_| anyhow!("The last frame couldn't be read back from the GPU"))?;
+
+		// The surface is either RGBA or BGRA depending on the platform, and its alpha is meaningless once presented
+		let swap_red_and_blue = matches!(self.format, wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb);
+		let mut pixels = Vec::with_capacity(row_size * height);
+		for row in slice.get_mapped_range().chunks(padded_row_size) {
+			for pixel in row[..row_size].chunks(4) {
+				if swap_red_and_blue {
+					pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0], 255]);
+				} else {
+					pixels.extend_from_slice(&[pixel[0], pixel[1], pixel[2], 255]);
+				}
+			}
+		}
+
+		let image = image::RgbaImage::from_raw(width as u32, height as u32, pixels).ok_or_else(|| anyhow!("The last frame's pixels don't fill its size"))?;
+		image.save(path)?;
+
+		Ok(())
+	}
+}
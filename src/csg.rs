@@ -0,0 +1,515 @@
+use crate::mesh::{generate_tangents, ModelVertex};
+
+use cgmath::{ElementWise, EuclideanSpace, InnerSpace, Point3, Vector3, Zero};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+// Points within this distance of a plane count as lying on it
+const PLANE_EPSILON: f64 = 1e-5;
+// Vertices closer together than this are merged, so faces cut apart by the operations share their edges again
+const WELD_DISTANCE: f64 = 1e-4;
+// Faces meeting at a shallower angle than this are shaded smoothly across their shared edges, while sharper creases stay hard
+const SMOOTHING_ANGLE_DEGREES: f64 = 40.;
+
+const COPLANAR: u8 = 0;
+const FRONT: u8 = 1;
+const BACK: u8 = 2;
+const SPANNING: u8 = 3;
+
+#[derive(Debug, Copy, Clone)]
+struct Plane {
+	normal: Vector3<f64>,
+	// Distance from the origin along the normal
+	w: f64,
+}
+
+impl Plane {
+	fn from_points(a: Point3<f64>, b: Point3<f64>, c: Point3<f64>) -> Option<Self> {
+		let normal = (b - a).cross(c - a);
+		if normal.magnitude2() <= f64::MIN_POSITIVE {
+			return None;
+		}
+
+		let normal = normal.normalize();
+		Some(Self { normal, w: normal.dot(a.to_vec()) })
+	}
+
+	fn flip(&mut self) {
+		self.normal = -self.normal;
+		self.w = -self.w;
+	}
+
+	// Sorts the polygon by which side of the plane it lies on, cutting it in two if it straddles the plane
+	fn split_polygon(&self, polygon: &Polygon, coplanar_front: &mut Vec<Polygon>, coplanar_back: &mut Vec<Polygon>, front: &mut Vec<Polygon>, back: &mut Vec<Polygon>) {
+		let sides = polygon
+			.vertices
+			.iter()
+			.map(|vertex| {
+				let distance = self.normal.dot(vertex.to_vec()) - self.w;
+				if distance < -PLANE_EPSILON {
+					BACK
+				} else if distance > PLANE_EPSILON {
+					FRONT
+				} else {
+					COPLANAR
+				}
+			})
+			.collect::<Vec<_>>();
+
+		match sides.iter().fold(COPLANAR, |polygon_side, side| polygon_side | side) {
+			COPLANAR if self.normal.dot(polygon.plane.normal) > 0. => coplanar_front.push(polygon.clone()),
+			COPLANAR => coplanar_back.push(polygon.clone()),
+			FRONT => front.push(polygon.clone()),
+			BACK => back.push(polygon.clone()),
+			_ => {
+				let (mut front_vertices, mut back_vertices) = (Vec::new(), Vec::new());
+
+				for i in 0..polygon.vertices.len() {
+					let j = (i + 1) % polygon.vertices.len();
+					let (side_i, side_j) = (sides[i], sides[j]);
+					let (vertex_i, vertex_j) = (polygon.vertices[i], polygon.vertices[j]);
+
+					if side_i != BACK {
+						front_vertices.push(vertex_i);
+					}
+					if side_i != FRONT {
+						back_vertices.push(vertex_i);
+					}
+					if side_i | side_j == SPANNING {
+						let t = (self.w - self.normal.dot(vertex_i.to_vec())) / self.normal.dot(vertex_j - vertex_i);
+						let crossing = vertex_i + (vertex_j - vertex_i) * t;
+						front_vertices.push(crossing);
+						back_vertices.push(crossing);
+					}
+				}
+
+				if front_vertices.len() >= 3 {
+					front.push(Polygon {
+						vertices: front_vertices,
+						plane: polygon.plane,
+					});
+				}
+				if back_vertices.len() >= 3 {
+					back.push(Polygon {
+						vertices: back_vertices,
+						plane: polygon.plane,
+					});
+				}
+			}
+		}
+	}
+}
+
+// Convex and planar, wound counter-clockwise when seen from the front
+#[derive(Debug, Clone)]
+struct Polygon {
+	vertices: Vec<Point3<f64>>,
+	plane: Plane,
+}
+
+impl Polygon {
+	fn new(vertices: Vec<Point3<f64>>) -> Option<Self> {
+		let plane = Plane::from_points(vertices[0], vertices[1], vertices[2])?;
+		Some(Self { vertices, plane })
+	}
+
+	fn flip(&mut self) {
+		self.vertices.reverse();
+		self.plane.flip();
+	}
+}
+
+// Binary space partitioning tree of polygons, where each node splits space by the plane of its first polygon
+#[derive(Debug, Default)]
+struct Node {
+	plane: Option<Plane>,
+	front: Option<Box<Node>>,
+	back: Option<Box<Node>>,
+	polygons: Vec<Polygon>,
+}
+
+impl Node {
+	fn new(polygons: Vec<Polygon>) -> Self {
+		let mut node = Self::default();
+		node.build(polygons);
+		node
+	}
+
+	// Swaps solid space and empty space
+	fn invert(&mut self) {
+		for polygon in &mut self.polygons {
+			polygon.flip();
+		}
+		if let Some(plane) = &mut self.plane {
+			plane.flip();
+		}
+		if let Some(front) = &mut self.front {
+			front.invert();
+		}
+		if let Some(back) = &mut self.back {
+			back.invert();
+		}
+		std::mem::swap(&mut self.front, &mut self.back);
+	}
+
+	// Removes the parts of the polygons that are inside this tree's solid
+	fn clip_polygons(&self, polygons: Vec<Polygon>) -> Vec<Polygon> {
+		let plane = match self.plane {
+			Some(plane) => plane,
+			None => return polygons,
+		};
+
+		let (mut front, mut back) = (Vec::new(), Vec::new());
+		let (mut coplanar_front, mut coplanar_back) = (Vec::new(), Vec::new());
+		for polygon in &polygons {
+			plane.split_polygon(polygon, &mut coplanar_front, &mut coplanar_back, &mut front, &mut back);
+		}
+		front.append(&mut coplanar_front);
+		back.append(&mut coplanar_back);
+
+		let mut front = match &self.front {
+			Some(node) => node.clip_polygons(front),
+			None => front,
+		};
+		let back = match &self.back {
+			Some(node) => node.clip_polygons(back),
+			None => Vec::new(),
+		};
+
+		front.extend(back);
+		front
+	}
+
+	// Removes the parts of this tree's polygons that are inside the other tree's solid
+	fn clip_to(&mut self, other: &Node) {
+		self.polygons = other.clip_polygons(std::mem::take(&mut self.polygons));
+		if let Some(front) = &mut self.front {
+			front.clip_to(other);
+		}
+		if let Some(back) = &mut self.back {
+			back.clip_to(other);
+		}
+	}
+
+	fn all_polygons(&self) -> Vec<Polygon> {
+		let mut polygons = self.polygons.clone();
+		if let Some(front) = &self.front {
+			polygons.extend(front.all_polygons());
+		}
+		if let Some(back) = &self.back {
+			polygons.extend(back.all_polygons());
+		}
+		polygons
+	}
+
+	fn build(&mut self, polygons: Vec<Polygon>) {
+		if polygons.is_empty() {
+			return;
+		}
+
+		let plane = *self.plane.get_or_insert(polygons[0].plane);
+		let (mut front, mut back) = (Vec::new(), Vec::new());
+		let (mut coplanar_front, mut coplanar_back) = (Vec::new(), Vec::new());
+		for polygon in &polygons {
+			plane.split_polygon(polygon, &mut coplanar_front, &mut coplanar_back, &mut front, &mut back);
+		}
+		self.polygons.append(&mut coplanar_front);
+		self.polygons.append(&mut coplanar_back);
+
+		if !front.is_empty() {
+			self.front.get_or_insert_with(Default::default).build(front);
+		}
+		if !back.is_empty() {
+			self.back.get_or_insert_with(Default::default).build(back);
+		}
+	}
+}
+
+// A closed solid for constructive solid geometry, combined with others by union, subtraction, and intersection to prototype level geometry
+// Follows the BSP tree approach of csg.js by Evan Wallace, splitting the polygons of each solid by the planes of the other and keeping the pieces on the wanted side
+#[derive(Debug, Clone, Default)]
+pub struct Csg {
+	polygons: Vec<Polygon>,
+}
+
+impl Csg {
+	// The triangles must enclose a solid, such as a loaded mesh's collision triangles, and degenerate ones are dropped
+	pub fn from_triangles(triangles: &[[[f32; 3]; 3]]) -> Self {
+		let polygons = triangles
+			.iter()
+			.filter_map(|triangle| Polygon::new(triangle.iter().map(|corner| Point3::from(*corner).cast::<f64>().unwrap()).collect()))
+			.collect();
+
+		Self { polygons }
+	}
+
+	pub fn cuboid(center: Point3<f32>, half_extents: Vector3<f32>) -> Self {
+		let center = center.cast::<f64>().unwrap();
+		let half_extents = half_extents.cast::<f64>().unwrap();
+
+		// Each face as its normal axis and the two axes it spans, ordered so the corners wind counter-clockwise from outside
+		let faces = [
+			(Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()),
+			(Vector3::unit_y(), Vector3::unit_z(), Vector3::unit_x()),
+			(Vector3::unit_z(), Vector3::unit_x(), Vector3::unit_y()),
+		];
+
+		let polygons = faces
+			.iter()
+			.flat_map(|(normal, u, v)| {
+				[1., -1.].map(|sign| {
+					let (normal, u, v) = (*normal * sign, *u * sign, *v);
+					let face_center = center + normal.mul_element_wise(half_extents);
+					let (u, v) = (u.mul_element_wise(half_extents), v.mul_element_wise(half_extents));
+
+					Polygon::new(vec![face_center - u - v, face_center + u - v, face_center + u + v, face_center - u + v]).unwrap()
+				})
+			})
+			.collect();
+
+		Self { polygons }
+	}
+
+	pub fn sphere(center: Point3<f32>, radius: f32, slices: usize, stacks: usize) -> Self {
+		let (center, radius) = (center.cast::<f64>().unwrap(), radius as f64);
+		let (slices, stacks) = (slices.max(3), stacks.max(2));
+
+		let point = |slice: usize, stack: usize| {
+			let theta = 2. * PI * slice as f64 / slices as f64;
+			let phi = PI * stack as f64 / stacks as f64;
+			center + Vector3::new(theta.cos() * phi.sin(), phi.cos(), theta.sin() * phi.sin()) * radius
+		};
+
+		let mut polygons = Vec::new();
+		for slice in 0..slices {
+			for stack in 0..stacks {
+				// The quads touching the poles collapse into triangles
+				let mut vertices = vec![point(slice, stack)];
+				if stack > 0 {
+					vertices.push(point(slice + 1, stack));
+				}
+				if stack < stacks - 1 {
+					vertices.push(point(slice + 1, stack + 1));
+				}
+				vertices.push(point(slice, stack + 1));
+
+				polygons.extend(Polygon::new(vertices));
+			}
+		}
+
+		Self { polygons }
+	}
+
+	pub fn cylinder(start: Point3<f32>, end: Point3<f32>, radius: f32, slices: usize) -> Self {
+		let (start, end, radius) = (start.cast::<f64>().unwrap(), end.cast::<f64>().unwrap(), radius as f64);
+		let slices = slices.max(3);
+
+		let axis = (end - start).normalize();
+		let reference = if axis.y.abs() > 0.999 { Vector3::unit_x() } else { Vector3::unit_y() };
+		let side = axis.cross(reference).normalize();
+		let other_side = side.cross(axis);
+		let rim = |slice: usize| {
+			let angle = 2. * PI * slice as f64 / slices as f64;
+			(side * angle.cos() + other_side * angle.sin()) * radius
+		};
+
+		let mut polygons = Vec::new();
+		for slice in 0..slices {
+			let (current, next) = (rim(slice), rim(slice + 1));
+
+			polygons.extend(Polygon::new(vec![start, start + current, start + next]));
+			polygons.extend(Polygon::new(vec![start + current, end + current, end + next, start + next]));
+			polygons.extend(Polygon::new(vec![end, end + next, end + current]));
+		}
+
+		Self { polygons }
+	}
+
+	pub fn union(&self, other: &Csg) -> Csg {
+		let mut a = Node::new(self.polygons.clone());
+		let mut b = Node::new(other.polygons.clone());
+
+		a.clip_to(&b);
+		b.clip_to(&a);
+		// Faces the solids share would otherwise be kept twice, so the ones from the other solid are dropped
+		b.invert();
+		b.clip_to(&a);
+		b.invert();
+		a.build(b.all_polygons());
+
+		Csg { polygons: a.all_polygons() }
+	}
+
+	pub fn subtract(&self, other: &Csg) -> Csg {
+		let mut a = Node::new(self.polygons.clone());
+		let mut b = Node::new(other.polygons.clone());
+
+		a.invert();
+		a.clip_to(&b);
+		b.clip_to(&a);
+		b.invert();
+		b.clip_to(&a);
+		b.invert();
+		a.build(b.all_polygons());
+		a.invert();
+
+		Csg { polygons: a.all_polygons() }
+	}
+
+	pub fn intersect(&self, other: &Csg) -> Csg {
+		let mut a = Node::new(self.polygons.clone());
+		let mut b = Node::new(other.polygons.clone());
+
+		a.invert();
+		b.clip_to(&a);
+		b.invert();
+		a.clip_to(&b);
+		b.clip_to(&a);
+		a.build(b.all_polygons());
+		a.invert();
+
+		Csg { polygons: a.all_polygons() }
+	}
+
+	// Triangulates the solid for a runtime mesh, with normals recomputed from the faces and UVs projected along each face's dominant axis every `uv_scale` meters
+	// Splitting leaves vertices partway along the edges of neighboring faces, so those are inserted into the neighbors too, keeping the surface watertight without cracks at the T-junctions
+	pub fn to_mesh(&self, uv_scale: f32) -> (Vec<ModelVertex>, Vec<u32>) {
+		let weld_key = |point: Point3<f64>| {
+			(
+				(point.x / WELD_DISTANCE).round() as i64,
+				(point.y / WELD_DISTANCE).round() as i64,
+				(point.z / WELD_DISTANCE).round() as i64,
+			)
+		};
+
+		let mut welded = HashMap::new();
+		let mut positions = Vec::new();
+		for polygon in &self.polygons {
+			for vertex in &polygon.vertices {
+				welded.entry(weld_key(*vertex)).or_insert_with(|| {
+					positions.push(*vertex);
+					positions.len() - 1
+				});
+			}
+		}
+
+		let polygons = self
+			.polygons
+			.iter()
+			.map(|polygon| {
+				let corners = polygon.vertices.iter().map(|vertex| welded[&weld_key(*vertex)]).collect::<Vec<_>>();
+
+				let mut outline = Vec::new();
+				for (index, corner) in corners.iter().enumerate() {
+					let next = corners[(index + 1) % corners.len()];
+					outline.push(*corner);
+					outline.extend(points_on_edge(&positions, *corner, next));
+				}
+				// Edges shorter than the weld distance collapse, leaving repeated positions
+				outline.dedup();
+				if outline.len() > 1 && outline.first() == outline.last() {
+					outline.pop();
+				}
+
+				let inserted_points = outline.len() > corners.len();
+				(outline, inserted_points, polygon.plane.normal)
+			})
+			.collect::<Vec<_>>();
+
+		// Each welded position's faces, weighted by area so slivers left by the splitting barely sway the smoothed normals
+		let mut faces_at_position = vec![Vec::new(); positions.len()];
+		for (polygon, (outline, _, normal)) in self.polygons.iter().zip(&polygons) {
+			let area = polygon_area(&polygon.vertices);
+			for position in outline {
+				faces_at_position[*position].push((*normal, area));
+			}
+		}
+
+		let smoothing_threshold = SMOOTHING_ANGLE_DEGREES.to_radians().cos();
+		let mut vertices = Vec::new();
+		let mut indices = Vec::new();
+		for (outline, inserted_points, normal) in &polygons {
+			if outline.len() < 3 {
+				continue;
+			}
+
+			let first_vertex = vertices.len() as u32;
+			let vertex = |position: Point3<f64>, smoothed: Vector3<f64>| {
+				// Project onto the plane facing the normal most directly, keeping the texture upright on walls
+				let uv = if normal.y.abs() >= normal.x.abs() && normal.y.abs() >= normal.z.abs() {
+					[position.x, position.z]
+				} else if normal.x.abs() >= normal.z.abs() {
+					[position.z, -position.y]
+				} else {
+					[position.x, -position.y]
+				};
+
+				ModelVertex {
+					position: position.cast::<f32>().unwrap().into(),
+					uv: uv.map(|coordinate| (coordinate / uv_scale as f64) as f32),
+					normal: smoothed.cast::<f32>().unwrap().into(),
+					tangent: [0.; 4],
+				}
+			};
+
+			for position in outline {
+				let smoothed = faces_at_position[*position]
+					.iter()
+					.filter(|(other, _)| other.dot(*normal) >= smoothing_threshold)
+					.fold(Vector3::zero(), |sum, (other, area)| sum + *other * *area);
+				let smoothed = if smoothed.magnitude2() > f64::MIN_POSITIVE { smoothed.normalize() } else { *normal };
+
+				vertices.push(vertex(positions[*position], smoothed));
+			}
+
+			// Outlines with inserted points have collinear runs that would make degenerate triangles in a fan from a corner, so those fan from the center instead
+			if *inserted_points {
+				let center = outline.iter().fold(Vector3::zero(), |sum, position| sum + positions[*position].to_vec()) / outline.len() as f64;
+				let center_vertex = vertices.len() as u32;
+				vertices.push(vertex(Point3::from_vec(center), *normal));
+
+				for index in 0..outline.len() as u32 {
+					let next = (index + 1) % outline.len() as u32;
+					indices.extend([center_vertex, first_vertex + index, first_vertex + next]);
+				}
+			} else {
+				for index in 1..outline.len() as u32 - 1 {
+					indices.extend([first_vertex, first_vertex + index, first_vertex + index + 1]);
+				}
+			}
+		}
+
+		generate_tangents(&mut vertices, &indices);
+
+		(vertices, indices)
+	}
+}
+
+// Welded positions lying strictly between the two ends of an edge, in order from the start
+fn points_on_edge(positions: &[Point3<f64>], start: usize, end: usize) -> Vec<usize> {
+	let (a, b) = (positions[start], positions[end]);
+	let edge = b - a;
+	let length2 = edge.magnitude2();
+	if length2 <= f64::MIN_POSITIVE {
+		return Vec::new();
+	}
+
+	let mut on_edge = positions
+		.iter()
+		.enumerate()
+		.filter(|(index, _)| *index != start && *index != end)
+		.filter_map(|(index, point)| {
+			let t = (point - a).dot(edge) / length2;
+			let distance2 = (a + edge * t - point).magnitude2();
+			(t > 0. && t < 1. && distance2 < WELD_DISTANCE * WELD_DISTANCE).then_some((t, index))
+		})
+		.collect::<Vec<_>>();
+	on_edge.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+	on_edge.into_iter().map(|(_, index)| index).collect()
+}
+
+fn polygon_area(vertices: &[Point3<f64>]) -> f64 {
+	let first = vertices[0];
+	vertices.windows(2).skip(1).map(|pair| (pair[0] - first).cross(pair[1] - first).magnitude() * 0.5).sum()
+}
@@ -0,0 +1,221 @@
+use crate::context::Context;
+
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Vector3, Vector4};
+use std::borrow::Cow;
+use std::mem;
+
+const CIRCLE_SEGMENTS: u32 = 32;
+const INITIAL_VERTEX_CAPACITY: u64 = 4096;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct DebugVertex {
+	position: [f32; 3],
+	color: [f32; 4],
+}
+
+// Lines queued from anywhere during the update and drawn over the finished frame, for visualizing things like lights that have no geometry of their own
+// They're drawn after the final composite pass, so they keep the same brightness however the scene is exposed
+pub struct DebugDraw {
+	vertices: Vec<DebugVertex>,
+	vertex_buffer: wgpu::Buffer,
+	vertex_capacity: u64,
+	uploaded_vertex_count: u32,
+	view_buffer: wgpu::Buffer,
+	view_bind_group: wgpu::BindGroup,
+	pipeline: wgpu::RenderPipeline,
+}
+
+impl DebugDraw {
+	pub fn new(context: &Context) -> Self {
+		let device = &context.device;
+
+		let vertex_buffer = create_vertex_buffer(device, INITIAL_VERTEX_CAPACITY);
+		let view_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Debug Draw view buffer"),
+			size: mem::size_of::<[[f32; 4]; 4]>() as u64,
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+
+		let view_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Debug Draw bind group layout"),
+			entries: &[wgpu::BindGroupLayoutEntry {
+				binding: 0,
+				visibility: wgpu::ShaderStages::VERTEX,
+				ty: wgpu::BindingType::Buffer {
+					ty: wgpu::BufferBindingType::Uniform,
+					has_dynamic_offset: false,
+					min_binding_size: None,
+				},
+				count: None,
+			}],
+		});
+		let view_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Debug Draw bind group"),
+			layout: &view_bind_group_layout,
+			entries: &[wgpu::BindGroupEntry {
+				binding: 0,
+				resource: view_buffer.as_entire_binding(),
+			}],
+		});
+
+		let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+			label: Some("Debug Draw"),
+			source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../assets/shaders/utility_debug_lines.wgsl"))),
+		});
+		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("Debug Draw"),
+			bind_group_layouts: &[&view_bind_group_layout],
+			push_constant_ranges: &[],
+		});
+		let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+			label: Some("Debug Draw"),
+			layout: Some(&pipeline_layout),
+			vertex: wgpu::VertexState {
+				module: &shader,
+				entry_point: "main",
+				buffers: &[wgpu::VertexBufferLayout {
+					array_stride: mem::size_of::<DebugVertex>() as wgpu::BufferAddress,
+					step_mode: wgpu::VertexStepMode::Vertex,
+					attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4],
+				}],
+			},
+			fragment: Some(wgpu::FragmentState {
+				module: &shader,
+				entry_point: "main",
+				targets: &[wgpu::ColorTargetState {
+					format: context.surface_configuration.format,
+					blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+					write_mask: wgpu::ColorWrites::ALL,
+				}],
+			}),
+			primitive: wgpu::PrimitiveState {
+				topology: wgpu::PrimitiveTopology::LineList,
+				..Default::default()
+			},
+			// Drawn on top of everything, since a light buried inside a wall still needs to be found and grabbed
+			depth_stencil: None,
+			multisample: wgpu::MultisampleState::default(),
+		});
+
+		Self {
+			vertices: Vec::new(),
+			vertex_buffer,
+			vertex_capacity: INITIAL_VERTEX_CAPACITY,
+			uploaded_vertex_count: 0,
+			view_buffer,
+			view_bind_group,
+			pipeline,
+		}
+	}
+
+	// Forgets last frame's lines, called before anything queues new ones
+	pub fn clear(&mut self) {
+		self.vertices.clear();
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.vertices.is_empty()
+	}
+
+	pub fn line(&mut self, start: Point3<f32>, end: Point3<f32>, color: [f32; 4]) {
+		self.vertices.push(DebugVertex { position: start.into(), color });
+		self.vertices.push(DebugVertex { position: end.into(), color });
+	}
+
+	pub fn circle(&mut self, center: Point3<f32>, normal: Vector3<f32>, radius: f32, color: [f32; 4]) {
+		let (tangent, bitangent) = perpendicular_axes(normal);
+		let point = |segment: u32| {
+			let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+			center + (tangent * angle.cos() + bitangent * angle.sin()) * radius
+		};
+
+		for segment in 0..CIRCLE_SEGMENTS {
+			self.line(point(segment), point(segment + 1), color);
+		}
+	}
+
+	// A circle around each axis, which reads as a sphere from any direction
+	pub fn sphere(&mut self, center: Point3<f32>, radius: f32, color: [f32; 4]) {
+		for axis in [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()] {
+			self.circle(center, axis, radius, color);
+		}
+	}
+
+	// Half angle in degrees, with the base drawn as a circle joined to the apex by four lines
+	pub fn cone(&mut self, apex: Point3<f32>, direction: Vector3<f32>, length: f32, half_angle: f32, color: [f32; 4]) {
+		let direction = direction.normalize();
+		let half_angle = half_angle.to_radians();
+		let base_center = apex + direction * length * half_angle.cos();
+		let base_radius = length * half_angle.sin();
+		self.circle(base_center, direction, base_radius, color);
+
+		let (tangent, bitangent) = perpendicular_axes(direction);
+		for edge in [tangent, bitangent, -tangent, -bitangent] {
+			self.line(apex, base_center + edge * base_radius, color);
+		}
+	}
+
+	// The edges of the volume a view-projection matrix sees, found by taking the corners of clip space back into the world
+	pub fn frustum(&mut self, vp_matrix: Matrix4<f32>, color: [f32; 4]) {
+		let inverse = match vp_matrix.invert() {
+			Some(inverse) => inverse,
+			None => return,
+		};
+		// wgpu clip space depth goes from 0 at the near plane to 1 at the far plane
+		let corner = |x: f32, y: f32, z: f32| Point3::from_homogeneous(inverse * Vector4::new(x, y, z, 1.));
+		let near = [corner(-1., -1., 0.), corner(1., -1., 0.), corner(1., 1., 0.), corner(-1., 1., 0.)];
+		let far = [corner(-1., -1., 1.), corner(1., -1., 1.), corner(1., 1., 1.), corner(-1., 1., 1.)];
+
+		for (i, (near_corner, far_corner)) in near.iter().zip(&far).enumerate() {
+			self.line(*near_corner, near[(i + 1) % 4], color);
+			self.line(*far_corner, far[(i + 1) % 4], color);
+			self.line(*near_corner, *far_corner, color);
+		}
+	}
+
+	// Writes the queued lines to the GPU along with the camera they're seen through, growing the vertex buffer if they no longer fit
+	pub fn upload(&mut self, context: &Context, vp_matrix: Matrix4<f32>) {
+		let vertex_count = self.vertices.len() as u64;
+		if vertex_count > self.vertex_capacity {
+			self.vertex_capacity = vertex_count.next_power_of_two();
+			self.vertex_buffer = create_vertex_buffer(&context.device, self.vertex_capacity);
+		}
+
+		let vp_matrix: [[f32; 4]; 4] = vp_matrix.into();
+		context.queue.write_buffer(&self.view_buffer, 0, bytemuck::cast_slice(&[vp_matrix]));
+		context.queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(self.vertices.as_slice()));
+		self.uploaded_vertex_count = vertex_count as u32;
+	}
+
+	pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+		if self.uploaded_vertex_count == 0 {
+			return;
+		}
+
+		render_pass.set_pipeline(&self.pipeline);
+		render_pass.set_bind_group(0, &self.view_bind_group, &[]);
+		render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+		render_pass.draw(0..self.uploaded_vertex_count, 0..1);
+	}
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, vertex_capacity: u64) -> wgpu::Buffer {
+	device.create_buffer(&wgpu::BufferDescriptor {
+		label: Some("Debug Draw vertex buffer"),
+		size: vertex_capacity * mem::size_of::<DebugVertex>() as u64,
+		usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+		mapped_at_creation: false,
+	})
+}
+
+// Two unit vectors perpendicular to the normal and each other
+pub fn perpendicular_axes(normal: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+	let normal = normal.normalize();
+	let reference = if normal.x.abs() > 0.9 { Vector3::unit_z() } else { Vector3::unit_x() };
+	let tangent = normal.cross(reference).normalize();
+	let bitangent = normal.cross(tangent);
+
+	(tangent, bitangent)
+}
@@ -0,0 +1,407 @@
+use crate::component::Component;
+use crate::entity::Entity;
+use crate::frame_stats::{CpuTimingScope, FrameStats, PassStats};
+use crate::json::{JsonParser, JsonValue};
+use crate::light::Light;
+use crate::render_settings::RenderSettings;
+
+use anyhow::{anyhow, bail, Result};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+// Appended to the key a client sends in its handshake before hashing it into the reply, which proves the server speaks WebSocket (RFC 6455)
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+// Requests are small, so anything bigger is taken as a broken client and dropped
+const MAX_MESSAGE_SIZE: usize = 1 << 16;
+// Longest a slow client can hold up the frame while a reply is written to it
+const WRITE_TIMEOUT: Duration = Duration::from_millis(250);
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+// One request from a client, which is a JSON object like `{ "id": 1, "method": "set", "params": { "name": "stereo", "value": "on" } }`
+// The reply is `{ "id": 1, "result": ... }` on success or `{ "id": 1, "error": "..." }` on failure, with the same ID so clients can match them up
+pub struct DebugRequest {
+	client: u64,
+	pub id: JsonValue,
+	pub method: String,
+	pub params: JsonValue,
+}
+
+struct Client {
+	id: u64,
+	stream: TcpStream,
+	// Bytes read that don't yet make up a whole handshake or frame
+	received: Vec<u8>,
+	upgraded: bool,
+	closed: bool,
+}
+
+// Lets external tools, like a browser page, inspect and tweak the running engine over a WebSocket speaking JSON
+// Everything happens on the main thread between frames, with the sockets polled without blocking so an idle server costs next to nothing
+pub struct DebugServer {
+	listener: TcpListener,
+	clients: Vec<Client>,
+	next_client_id: u64,
+}
+
+impl DebugServer {
+	pub fn start(address: &str) -> Result<Self> {
+		let listener = TcpListener::bind(address)?;
+		listener.set_nonblocking(true)?;
+
+		Ok(Self {
+			listener,
+			clients: vec![],
+			next_client_id: 0,
+		})
+	}
+
+	// Accepts new connections and returns every whole request that arrived since the last poll
+	pub fn poll(&mut self) -> Vec<DebugRequest> {
+		loop {
+			match self.listener.accept() {
+				Ok((stream, _)) => {
+					if stream.set_nonblocking(true).and_then(|_| stream.set_write_timeout(Some(WRITE_TIMEOUT))).is_ok() {
+						self.clients.push(Client {
+							id: self.next_client_id,
+							stream,
+							received: vec![],
+							upgraded: false,
+							closed: false,
+						});
+						self.next_client_id += 1;
+					}
+				}
+				Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+				Err(error) => {
+					eprintln!("Debug server couldn't accept a connection: {}", error);
+					break;
+				}
+			}
+		}
+
+		let mut requests = vec![];
+		for client in &mut self.clients {
+			if let Err(error) = client.receive(&mut requests) {
+				eprintln!("Debug server dropped a client: {}", error);
+				client.closed = true;
+			}
+		}
+		self.clients.retain(|client| !client.closed);
+
+		requests
+	}
+
+	pub fn respond(&mut self, request: &DebugRequest, result: Result<JsonValue>) {
+		let reply = match result {
+			Ok(result) => JsonValue::object([("id", request.id.clone()), ("result", result)]),
+			Err(error) => JsonValue::object([("id", request.id.clone()), ("error", JsonValue::String(error.to_string()))]),
+		};
+
+		if let Some(client) = self.clients.iter_mut().find(|client| client.id == request.client) {
+			client.send(OPCODE_TEXT, reply.to_string().as_bytes());
+		}
+	}
+}
+
+impl Client {
+	fn receive(&mut self, requests: &mut Vec<DebugRequest>) -> Result<()> {
+		let mut buffer = [0; 4096];
+		loop {
+			match self.stream.read(&mut buffer) {
+				Ok(0) => {
+					self.closed = true;
+					break;
+				}
+				Ok(length) => self.received.extend_from_slice(&buffer[..length]),
+				Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+				Err(error) => return Err(error.into()),
+			}
+			if self.received.len() > MAX_MESSAGE_SIZE * 2 {
+				bail!("it sent more than the largest message allowed");
+			}
+		}
+
+		if !self.upgraded {
+			self.handshake()?;
+		}
+
+		while self.upgraded && !self.closed {
+			let (opcode, payload) = match take_frame(&mut self.received)? {
+				Some(frame) => frame,
+				None => break,
+			};
+
+			match opcode {
+				OPCODE_TEXT => requests.push(parse_request(self.id, &payload)?),
+				OPCODE_PING => self.send(OPCODE_PONG, &payload),
+				OPCODE_CLOSE => {
+					self.send(OPCODE_CLOSE, &[]);
+					self.closed = true;
+				}
+				// Binary messages have no meaning here, and pongs answer pings the server never sends
+				_ => {}
+			}
+		}
+
+		Ok(())
+	}
+
+	// Answers the HTTP upgrade request once all of it has arrived
+	fn handshake(&mut self) -> Result<()> {
+		let end = match self.received.windows(4).position(|window| window == b"\r\n\r\n") {
+			Some(end) => end + 4,
+			None => return Ok(()),
+		};
+		let request = String::from_utf8_lossy(&self.received[..end]).into_owned();
+		self.received.drain(..end);
+
+		let key = request.lines().find_map(|line| {
+			let (name, value) = line.split_once(':')?;
+			name.trim().eq_ignore_ascii_case("Sec-WebSocket-Key").then(|| value.trim().to_string())
+		});
+		let key = match key {
+			Some(key) => key,
+			None => {
+				let _ = self.stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n");
+				bail!("it didn't ask for a WebSocket");
+			}
+		};
+
+		let accept = base64(&sha1(format!("{}{}", key, WEBSOCKET_GUID).as_bytes()));
+		let response = format!(
+			"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+			accept
+		);
+		self.write(response.as_bytes());
+		self.upgraded = true;
+
+		Ok(())
+	}
+
+	// Frames from the server are never masked or fragmented
+	fn send(&mut self, opcode: u8, payload: &[u8]) {
+		let mut frame = vec![0x80 | opcode];
+		match payload.len() {
+			length @ 0..=125 => frame.push(length as u8),
+			length @ 126..=0xFFFF => {
+				frame.push(126);
+				frame.extend_from_slice(&(length as u16).to_be_bytes());
+			}
+			length => {
+				frame.push(127);
+				frame.extend_from_slice(&(length as u64).to_be_bytes());
+			}
+		}
+		frame.extend_from_slice(payload);
+
+		self.write(&frame);
+	}
+
+	// Written while blocking, up to the timeout, since a reply cut off partway would corrupt every frame after it
+	fn write(&mut self, bytes: &[u8]) {
+		let written = self
+			.stream
+			.set_nonblocking(false)
+			.and_then(|_| self.stream.write_all(bytes))
+			.and_then(|_| self.stream.set_nonblocking(true));
+		if written.is_err() {
+			self.closed = true;
+		}
+	}
+}
+
+// Removes the first whole frame from the buffer, unmasking its payload, or leaves the buffer alone if the frame hasn't all arrived
+fn take_frame(buffer: &mut Vec<u8>) -> Result<Option<(u8, Vec<u8>)>> {
+	if buffer.len() < 2 {
+		return Ok(None);
+	}
+
+	let final_fragment = buffer[0] & 0x80 != 0;
+	let opcode = buffer[0] & 0x0F;
+	let masked = buffer[1] & 0x80 != 0;
+	let (length, header_length) = match buffer[1] & 0x7F {
+		126 if buffer.len() >= 4 => (u16::from_be_bytes([buffer[2], buffer[3]]) as usize, 4),
+		127 if buffer.len() >= 10 => (u64::from_be_bytes(buffer[2..10].try_into().unwrap()) as usize, 10),
+		126 | 127 => return Ok(None),
+		length => (length as usize, 2),
+	};
+
+	if length > MAX_MESSAGE_SIZE {
+		bail!("it sent a message of {} bytes", length);
+	}
+	if !final_fragment {
+		bail!("it sent a fragmented message");
+	}
+
+	let mask_length = if masked { 4 } else { 0 };
+	if buffer.len() < header_length + mask_length + length {
+		return Ok(None);
+	}
+
+	let mask = buffer[header_length..header_length + mask_length].to_vec();
+	let payload = buffer[header_length + mask_length..header_length + mask_length + length]
+		.iter()
+		.enumerate()
+		.map(|(index, byte)| if masked { byte ^ mask[index % 4] } else { *byte })
+		.collect();
+	buffer.drain(..header_length + mask_length + length);
+
+	Ok(Some((opcode, payload)))
+}
+
+fn parse_request(client: u64, payload: &[u8]) -> Result<DebugRequest> {
+	let text = std::str::from_utf8(payload)?;
+	let request = JsonParser::new(text).parse_document()?;
+
+	Ok(DebugRequest {
+		client,
+		id: request.get("id").cloned().unwrap_or(JsonValue::Null),
+		method: request
+			.get("method")
+			.and_then(JsonValue::as_str)
+			.ok_or_else(|| anyhow!("it sent a request without a method"))?
+			.to_string(),
+		params: request.get("params").cloned().unwrap_or(JsonValue::Null),
+	})
+}
+
+// The entity and everything under it, with each component summarized by its kind
+pub fn entity_json(entity: &Entity) -> JsonValue {
+	let numbers = |values: &[f64]| JsonValue::Array(values.iter().map(|value| JsonValue::Number(*value)).collect());
+	let location = entity.transform.location;
+	let rotation = entity.transform.rotation;
+	let scale = entity.transform.scale;
+
+	let components = entity
+		.components
+		.iter()
+		.map(|component| match component {
+			Component::Model(model) => format!("model {}:{}", model.mesh_name.0, model.mesh_name.1),
+			Component::Light(Light::Lamp(_)) => String::from("lamp"),
+			Component::Light(Light::Sun(_)) => String::from("sun"),
+			Component::Camera(_) => String::from("camera"),
+			Component::Behavior(_) => String::from("behavior"),
+			Component::Mirror(_) => String::from("mirror"),
+			Component::Portal(_) => String::from("portal"),
+		})
+		.map(JsonValue::String)
+		.collect();
+
+	JsonValue::object([
+		("name", JsonValue::String(entity.name.clone())),
+		("enabled", JsonValue::Bool(entity.enabled)),
+		("location", numbers(&[location.x, location.y, location.z])),
+		("rotation", numbers(&[rotation.s, rotation.v.x, rotation.v.y, rotation.v.z])),
+		("scale", numbers(&[scale.x, scale.y, scale.z])),
+		("components", JsonValue::Array(components)),
+		("children", JsonValue::Array(entity.children.iter().map(entity_json).collect())),
+	])
+}
+
+// The last frame's draw statistics for each pass and in total, and the CPU timings in milliseconds
+pub fn frame_stats_json(frame_stats: &FrameStats) -> JsonValue {
+	let pass_json = |stats: &PassStats| {
+		JsonValue::object([
+			("draw_calls", JsonValue::Number(stats.draw_calls as f64)),
+			("instances", JsonValue::Number(stats.instances as f64)),
+			("triangles", JsonValue::Number(stats.triangles as f64)),
+			("pipeline_switches", JsonValue::Number(stats.pipeline_switches as f64)),
+			("bind_group_switches", JsonValue::Number(stats.bind_group_switches as f64)),
+			("culled", JsonValue::Number(stats.culled as f64)),
+		])
+	};
+
+	let passes = frame_stats.passes.iter().map(|(name, stats)| (name.clone(), pass_json(stats))).collect();
+	let cpu_timings = CpuTimingScope::ALL
+		.iter()
+		.map(|scope| (scope.name(), JsonValue::Number(frame_stats.cpu_time(*scope).as_secs_f64() * 1000.)));
+
+	JsonValue::object([
+		("total", pass_json(&frame_stats.total())),
+		("passes", JsonValue::Object(passes)),
+		("cpu_milliseconds", JsonValue::object(cpu_timings)),
+		(
+			"gpu_milliseconds",
+			frame_stats.gpu_time().map_or(JsonValue::Null, |gpu_time| JsonValue::Number(gpu_time.as_secs_f64() * 1000.)),
+		),
+	])
+}
+
+pub fn render_settings_json(render_settings: &RenderSettings) -> JsonValue {
+	JsonValue::object(
+		RenderSettings::SETTING_NAMES
+			.iter()
+			.map(|name| (*name, render_settings.setting(name).map_or(JsonValue::Null, JsonValue::String))),
+	)
+}
+
+// SHA-1 (RFC 3174), needed only for the handshake, where its weaknesses don't matter
+fn sha1(message: &[u8]) -> [u8; 20] {
+	let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+	// Padded with a 1 bit, then zeros up to 8 bytes short of a whole block, then the message's length in bits
+	let mut padded = message.to_vec();
+	padded.push(0x80);
+	while padded.len() % 64 != 56 {
+		padded.push(0);
+	}
+	padded.extend_from_slice(&((message.len() as u64) * 8).to_be_bytes());
+
+	for block in padded.chunks(64) {
+		let mut words = [0u32; 80];
+		for (index, word) in block.chunks(4).enumerate() {
+			words[index] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+		}
+		for index in 16..80 {
+			words[index] = (words[index - 3] ^ words[index - 8] ^ words[index - 14] ^ words[index - 16]).rotate_left(1);
+		}
+
+		let [mut a, mut b, mut c, mut d, mut e] = state;
+		for (index, word) in words.iter().enumerate() {
+			let (f, k) = match index {
+				0..=19 => ((b & c) | (!b & d), 0x5A827999),
+				20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+				40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+				_ => (b ^ c ^ d, 0xCA62C1D6),
+			};
+			let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*word);
+			e = d;
+			d = c;
+			c = b.rotate_left(30);
+			b = a;
+			a = temp;
+		}
+
+		for (value, added) in state.iter_mut().zip([a, b, c, d, e]) {
+			*value = value.wrapping_add(added);
+		}
+	}
+
+	let mut digest = [0; 20];
+	for (bytes, value) in digest.chunks_mut(4).zip(state) {
+		bytes.copy_from_slice(&value.to_be_bytes());
+	}
+	digest
+}
+
+fn base64(bytes: &[u8]) -> String {
+	const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+	let mut encoded = String::new();
+	for chunk in bytes.chunks(3) {
+		let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| group | (*byte as u32) << (16 - index * 8));
+		for index in 0..4 {
+			if index <= chunk.len() {
+				encoded.push(ALPHABET[(group >> (18 - index * 6) & 0x3F) as usize] as char);
+			} else {
+				encoded.push('=');
+			}
+		}
+	}
+	encoded
+}
@@ -0,0 +1,57 @@
+use crate::frame_texture::FrameTexture;
+use crate::texture::Texture;
+
+// Must match the bin count in the histogram shaders
+pub const LUMINANCE_HISTOGRAM_BINS: u64 = 64;
+// Size of the histogram graph, which is drawn in a corner of the frame at this size
+pub const LUMINANCE_HISTOGRAM_DIMENSIONS: (u32, u32) = (256, 128);
+// Texels per side of the tiles the debug compute shaders are dispatched in
+const WORKGROUP_SIZE: u32 = 8;
+
+// Targets of the debug visualizations, kept apart from the frame textures since they're only written while their view is shown
+pub struct DebugViewTextures {
+	// Number of fragments shaded at each pixel, accumulated by additively blending one for each fragment
+	pub overdraw_map: FrameTexture,
+	pub overdraw_heatmap: Texture,
+	pub luminance_histogram_buffer: wgpu::Buffer,
+	pub luminance_histogram_map: Texture,
+}
+
+impl DebugViewTextures {
+	pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+		let overdraw_map = FrameTexture::new(device, config, wgpu::TextureFormat::R16Float, "Overdraw Map frame texture", None);
+		let overdraw_heatmap = Texture::storage(device, (config.width, config.height), "Overdraw Heatmap", wgpu::TextureFormat::Rgba8Unorm);
+
+		let luminance_histogram_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Luminance Histogram Buffer"),
+			size: LUMINANCE_HISTOGRAM_BINS * std::mem::size_of::<u32>() as u64,
+			usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+		let luminance_histogram_map = Texture::storage(device, LUMINANCE_HISTOGRAM_DIMENSIONS, "Luminance Histogram Map", wgpu::TextureFormat::Rgba8Unorm);
+
+		Self {
+			overdraw_map,
+			overdraw_heatmap,
+			luminance_histogram_buffer,
+			luminance_histogram_map,
+		}
+	}
+
+	pub fn recreate(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+		self.overdraw_map.recreate(device, config);
+		self.overdraw_heatmap = Texture::storage(device, (config.width, config.height), "Overdraw Heatmap", wgpu::TextureFormat::Rgba8Unorm);
+	}
+
+	// The bins are only ever added to, so they're zeroed before each frame counts its pixels
+	pub fn clear_luminance_histogram(&self, queue: &wgpu::Queue) {
+		let zeroes = [0_u32; LUMINANCE_HISTOGRAM_BINS as usize];
+		queue.write_buffer(&self.luminance_histogram_buffer, 0, bytemuck::cast_slice(&zeroes));
+	}
+}
+
+// Enough workgroups for one invocation per texel of a texture with these dimensions
+pub fn work_groups_for(dimensions: (u32, u32)) -> (u32, u32, u32) {
+	let groups = |texels: u32| texels.div_ceil(WORKGROUP_SIZE);
+	(groups(dimensions.0), groups(dimensions.1), 1)
+}
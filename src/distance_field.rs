@@ -0,0 +1,314 @@
+use crate::binary_cache::{hash_floats, CacheReader};
+use crate::collision::closest_point_on_triangle;
+use crate::render_settings::DistanceFieldShading;
+
+use anyhow::{bail, Result};
+use cgmath::{InnerSpace, Vector3};
+use half::f16;
+use std::path::Path;
+
+// Name the baked volume is registered under in the scene's textures
+pub const DISTANCE_FIELD_TEXTURE: &str = "DISTANCE_FIELD";
+// Written at the start of the cached field, with the version bumped whenever the format or the bake changes so stale caches are rebuilt
+const CACHE_MAGIC: &[u8; 4] = b"SSDF";
+const CACHE_VERSION: u32 = 1;
+// Voxels along the longest side of the geometry's bounds, with the voxels kept from getting smaller than the minimum size for little scenes
+const MAX_RESOLUTION: u32 = 128;
+const MIN_VOXEL_SIZE: f32 = 0.05;
+// Voxels of empty space around the bounds so surfaces at the edge still have an outside to fall off into
+const PADDING: u32 = 2;
+// Forward and backward sweeps passing the closest triangle between neighbors, where a second round fixes the voxels the first got wrong around corners
+const PROPAGATION_ROUNDS: usize = 2;
+// Distance filling a field with nothing in it, far enough that ray marches step straight through
+const EMPTY_DISTANCE: f32 = 1000.;
+
+// Locates the field's volume texture in world space, since its texels lie on voxel centers
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DistanceFieldUniform {
+	// Center of the first voxel
+	pub min: [f32; 3],
+	pub voxel_size: f32,
+	// Voxels along each axis, as floats for the shaders' texture coordinate math
+	pub resolution: [f32; 3],
+	// Which lighting the shading pass traces through the field, from 0 for none through 1 for shadows to 2 for shadows and ambient occlusion
+	pub shading: u32,
+}
+
+// Signed distances from a grid of points to the nearest static triangle, baked once after loading for soft shadows, ambient occlusion cones, particle collision, and ray marched effects
+// Distances are negative behind the nearest triangle, so they're only truly inside for closed meshes
+#[derive(Debug, Clone)]
+pub struct DistanceField {
+	pub min: Vector3<f32>,
+	pub voxel_size: f32,
+	pub resolution: [u32; 3],
+	// Ordered by X, then Y, then Z
+	pub distances: Vec<f32>,
+}
+
+impl Default for DistanceField {
+	// A single voxel far from everything, which stands in until the scene is baked
+	fn default() -> Self {
+		Self {
+			min: Vector3::new(0., 0., 0.),
+			voxel_size: 1.,
+			resolution: [1, 1, 1],
+			distances: vec![EMPTY_DISTANCE],
+		}
+	}
+}
+
+impl DistanceField {
+	// Finds the closest triangle to every voxel by seeding the voxels around each triangle with exact distances, then sweeping through the grid with each voxel trying its neighbors' closest triangles
+	// This is an approximation of the closest triangle transform which can be off slightly around sharp concave corners, but each distance is still exact for the triangle it picked
+	pub fn bake(triangles: &[[Vector3<f32>; 3]]) -> Self {
+		let corners = || triangles.iter().flatten();
+		let (min, max) = match corners().next() {
+			Some(first) => corners().fold((*first, *first), |(min, max), corner| {
+				(
+					Vector3::new(min.x.min(corner.x), min.y.min(corner.y), min.z.min(corner.z)),
+					Vector3::new(max.x.max(corner.x), max.y.max(corner.y), max.z.max(corner.z)),
+				)
+			}),
+			None => return Self::default(),
+		};
+
+		let extent = max - min;
+		let voxel_size = (extent.x.max(extent.y).max(extent.z) / (MAX_RESOLUTION - 1) as f32).max(MIN_VOXEL_SIZE);
+		let min = min - Vector3::new(1., 1., 1.) * (voxel_size * PADDING as f32);
+		let resolution = [extent.x, extent.y, extent.z].map(|length| (length / voxel_size).ceil() as u32 + 1 + PADDING * 2);
+
+		let mut field = Self {
+			min,
+			voxel_size,
+			resolution,
+			distances: vec![f32::INFINITY; resolution.iter().product::<u32>() as usize],
+		};
+		let mut closest = vec![u32::MAX; field.distances.len()];
+
+		// Seed the voxels within one voxel of each triangle's bounds
+		for (index, triangle) in triangles.iter().enumerate() {
+			let low = triangle.iter().fold(Vector3::new(f32::MAX, f32::MAX, f32::MAX), |low, corner| {
+				Vector3::new(low.x.min(corner.x), low.y.min(corner.y), low.z.min(corner.z))
+			});
+			let high = triangle.iter().fold(Vector3::new(f32::MIN, f32::MIN, f32::MIN), |high, corner| {
+				Vector3::new(high.x.max(corner.x), high.y.max(corner.y), high.z.max(corner.z))
+			});
+			let first = field.voxel_containing(low).map(|coordinate| coordinate.saturating_sub(1));
+			let last = field.voxel_containing(high).map(|coordinate| coordinate + 1);
+
+			for z in first[2]..=last[2].min(resolution[2] - 1) {
+				for y in first[1]..=last[1].min(resolution[1] - 1) {
+					for x in first[0]..=last[0].min(resolution[0] - 1) {
+						let (voxel, center) = (field.index(x, y, z), field.voxel_center(x, y, z));
+						let distance = (center - closest_point_on_triangle(center, triangle)).magnitude();
+
+						if distance < field.distances[voxel] {
+							field.distances[voxel] = distance;
+							closest[voxel] = index as u32;
+						}
+					}
+				}
+			}
+		}
+
+		// The 13 neighbors already visited when walking forward through the grid, which are negated for the backward sweep
+		let neighbors = (-1..=1)
+			.flat_map(|z| (-1..=1).flat_map(move |y| (-1..=1).map(move |x| [x, y, z])))
+			.filter(|[x, y, z]| (*z, *y, *x) < (0, 0, 0))
+			.collect::<Vec<[i32; 3]>>();
+
+		for _ in 0..PROPAGATION_ROUNDS {
+			for direction in [1, -1] {
+				let voxel_count = field.distances.len();
+				for step in 0..voxel_count {
+					let voxel = if direction == 1 { step } else { voxel_count - 1 - step };
+					let [x, y, z] = field.coordinates(voxel);
+					let center = field.voxel_center(x, y, z);
+
+					for offset in &neighbors {
+						let neighbor = [x as i32 + offset[0] * direction, y as i32 + offset[1] * direction, z as i32 + offset[2] * direction];
+						if (0..3).any(|axis| neighbor[axis] < 0 || neighbor[axis] >= resolution[axis] as i32) {
+							continue;
+						}
+
+						let candidate = closest[field.index(neighbor[0] as u32, neighbor[1] as u32, neighbor[2] as u32)];
+						if candidate == u32::MAX || candidate == closest[voxel] {
+							continue;
+						}
+
+						let distance = (center - closest_point_on_triangle(center, &triangles[candidate as usize])).magnitude();
+						if distance < field.distances[voxel] {
+							field.distances[voxel] = distance;
+							closest[voxel] = candidate;
+						}
+					}
+				}
+			}
+		}
+
+		// Voxels behind their closest triangle's face are inside, which is a guess where the closest point is on an edge shared with a triangle facing another way
+		for (voxel, triangle) in closest.iter().enumerate() {
+			let [x, y, z] = field.coordinates(voxel);
+			let center = field.voxel_center(x, y, z);
+
+			match triangles.get(*triangle as usize) {
+				Some(triangle) => {
+					let normal = (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]);
+					if (center - closest_point_on_triangle(center, triangle)).dot(normal) < 0. {
+						field.distances[voxel] = -field.distances[voxel];
+					}
+				}
+				None => field.distances[voxel] = EMPTY_DISTANCE,
+			}
+		}
+
+		field
+	}
+
+	// Reads the field from the cache if it was baked from the same triangles before, otherwise bakes it and caches it for next time
+	pub fn load_or_bake(cache_path: &Path, triangles: &[[Vector3<f32>; 3]]) -> Self {
+		let source_hash = hash_floats(triangles.iter().flatten().flat_map(|corner| [corner.x, corner.y, corner.z]));
+
+		if let Ok(bytes) = std::fs::read(cache_path) {
+			match Self::from_cache(&bytes, source_hash) {
+				Ok(field) => return field,
+				Err(error) => eprintln!("Rebaking the cached distance field {:?}: {}", cache_path, error),
+			}
+		}
+
+		let field = Self::bake(triangles);
+
+		let written = match cache_path.parent() {
+			Some(directory) => std::fs::create_dir_all(directory).and_then(|_| std::fs::write(cache_path, field.to_cache(source_hash))),
+			None => std::fs::write(cache_path, field.to_cache(source_hash)),
+		};
+		if let Err(error) = written {
+			eprintln!("Couldn't cache the distance field {:?}: {}", cache_path, error);
+		}
+
+		field
+	}
+
+	// Interpolated between the nearest voxels, with points beyond the volume adding their distance to it
+	pub fn sample(&self, point: Vector3<f32>) -> f32 {
+		let grid = (point - self.min) / self.voxel_size;
+		let last = self.resolution.map(|count| (count - 1) as f32);
+		let clamped = Vector3::new(grid.x.clamp(0., last[0]), grid.y.clamp(0., last[1]), grid.z.clamp(0., last[2]));
+		let outside = (grid - clamped).magnitude() * self.voxel_size;
+
+		let base = [clamped.x, clamped.y, clamped.z].map(|coordinate| coordinate.floor() as u32);
+		let blend = clamped - Vector3::new(base[0] as f32, base[1] as f32, base[2] as f32);
+
+		let mut distance = 0.;
+		for corner in 0..8 {
+			let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+			let coordinates = [0, 1, 2].map(|axis| (base[axis] + offset[axis]).min(self.resolution[axis] - 1));
+			let weight = [blend.x, blend.y, blend.z]
+				.iter()
+				.zip(offset)
+				.map(|(blend, offset)| if offset == 1 { *blend } else { 1. - blend })
+				.product::<f32>();
+
+			distance += self.distances[self.index(coordinates[0], coordinates[1], coordinates[2])] * weight;
+		}
+
+		distance + outside
+	}
+
+	// Direction away from the nearest surface, for pushing particles back out or shading ray marched hits
+	pub fn gradient(&self, point: Vector3<f32>) -> Vector3<f32> {
+		let step = self.voxel_size * 0.5;
+		let difference = |axis: Vector3<f32>| self.sample(point + axis * step) - self.sample(point - axis * step);
+		let gradient = Vector3::new(difference(Vector3::unit_x()), difference(Vector3::unit_y()), difference(Vector3::unit_z()));
+
+		if gradient.magnitude2() > f32::MIN_POSITIVE {
+			gradient.normalize()
+		} else {
+			Vector3::new(0., 0., 0.)
+		}
+	}
+
+	pub fn uniform(&self, shading: DistanceFieldShading) -> DistanceFieldUniform {
+		DistanceFieldUniform {
+			min: self.min.into(),
+			voxel_size: self.voxel_size,
+			resolution: self.resolution.map(|count| count as f32),
+			shading: match shading {
+				DistanceFieldShading::Off => 0,
+				DistanceFieldShading::Shadows => 1,
+				DistanceFieldShading::ShadowsAndAmbientOcclusion => 2,
+			},
+		}
+	}
+
+	// Texels for the volume texture, holding the gradient in RGB so shaders get normals without extra samples, and the distance in alpha
+	pub fn texels(&self) -> Vec<[f16; 4]> {
+		(0..self.distances.len())
+			.map(|voxel| {
+				let [x, y, z] = self.coordinates(voxel);
+				let gradient = self.gradient(self.voxel_center(x, y, z));
+
+				[gradient.x, gradient.y, gradient.z, self.distances[voxel]].map(f16::from_f32)
+			})
+			.collect()
+	}
+
+	fn index(&self, x: u32, y: u32, z: u32) -> usize {
+		((z * self.resolution[1] + y) * self.resolution[0] + x) as usize
+	}
+
+	fn coordinates(&self, index: usize) -> [u32; 3] {
+		let index = index as u32;
+		let slice = self.resolution[0] * self.resolution[1];
+		[index % self.resolution[0], index % slice / self.resolution[0], index / slice]
+	}
+
+	fn voxel_center(&self, x: u32, y: u32, z: u32) -> Vector3<f32> {
+		self.min + Vector3::new(x as f32, y as f32, z as f32) * self.voxel_size
+	}
+
+	// Nearest voxel to a point, clamped to the grid
+	fn voxel_containing(&self, point: Vector3<f32>) -> [u32; 3] {
+		let grid = (point - self.min) / self.voxel_size;
+		[0, 1, 2].map(|axis| ((grid[axis] + 0.5).max(0.) as u32).min(self.resolution[axis] - 1))
+	}
+
+	fn to_cache(&self, source_hash: u64) -> Vec<u8> {
+		let mut bytes = Vec::new();
+		bytes.extend(CACHE_MAGIC);
+		bytes.extend(CACHE_VERSION.to_le_bytes());
+		bytes.extend(source_hash.to_le_bytes());
+		bytes.extend([self.min.x, self.min.y, self.min.z, self.voxel_size].iter().flat_map(|value| value.to_le_bytes()));
+		bytes.extend(self.resolution.iter().flat_map(|count| count.to_le_bytes()));
+		bytes.extend(self.distances.iter().flat_map(|distance| distance.to_le_bytes()));
+
+		bytes
+	}
+
+	fn from_cache(bytes: &[u8], source_hash: u64) -> Result<Self> {
+		let mut reader = CacheReader::new(bytes);
+
+		if reader.take(4)? != CACHE_MAGIC || reader.u32()? != CACHE_VERSION {
+			bail!("Not a distance field cache from this version of the engine");
+		}
+		if reader.u64()? != source_hash {
+			bail!("The static geometry has changed since it was cached");
+		}
+
+		let min = Vector3::new(reader.f32()?, reader.f32()?, reader.f32()?);
+		let voxel_size = reader.f32()?;
+		let resolution = [reader.u32()?, reader.u32()?, reader.u32()?];
+		if resolution.contains(&0) || resolution.iter().any(|count| *count > MAX_RESOLUTION + PADDING * 2) {
+			bail!("The cache has an unexpected resolution");
+		}
+		let distances = (0..resolution.iter().product::<u32>()).map(|_| reader.f32()).collect::<Result<Vec<_>>>()?;
+
+		Ok(Self {
+			min,
+			voxel_size,
+			resolution,
+			distances,
+		})
+	}
+}
@@ -0,0 +1,292 @@
+use crate::component::Component;
+use crate::entity::Entity;
+use crate::frustum::Frustum;
+use crate::instance::Instance;
+use crate::mesh::Mesh;
+use crate::model::{Mobility, Model};
+use crate::scene::{MaterialHandle, MeshHandle, Scene};
+
+use cgmath::{EuclideanSpace, InnerSpace, Matrix3, Point3, Vector3};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
+use std::collections::HashMap;
+
+// The passes that draw scene geometry, each of which gets its own draw list
+pub const SCENE_PASSES: [&str; 12] = [
+	"Scene: Render Voxel Lightmap",
+	"Scene: Render Shadows",
+	"Scene: Render Deferred",
+	"Scene: Render Impostors",
+	"Scene: Render Reflection",
+	"Scene: Render Mirrors",
+	"Scene: Render Portals",
+	"Scene: Render Portal View",
+	"Scene: Render Glass",
+	"Scene: Render Transparent Sorted",
+	"Scene: Render Transparent Weighted",
+	"Scene: Render Layers",
+];
+
+// An instance's world space bounding sphere center and radius, along with the axes of its bounding box
+pub type InstanceBounds = (Vector3<f32>, f32, Matrix3<f32>);
+
+// A model component to draw, located by the child indices leading to its entity so it can be looked up again without a full traversal
+#[derive(Debug)]
+pub struct DrawItem {
+	pub entity_path: Vec<usize>,
+	pub component_index: usize,
+	pub mesh: MeshHandle,
+	pub material: MaterialHandle,
+	// The bounding sphere and box of each instance of a static model, worked out once when the list is built rather than every time it's culled
+	pub static_bounds: Option<Vec<InstanceBounds>>,
+}
+
+// Per-pass draw lists that are only rebuilt when the scene's generation counter shows it was mutated since they were built
+#[derive(Debug, Default)]
+pub struct DrawListCache {
+	generation: Option<u64>,
+	lists: HashMap<String, Vec<DrawItem>>,
+	// Indices into each pass's draw list that survived this frame's culling
+	visible: HashMap<String, Vec<usize>>,
+}
+
+impl DrawListCache {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn refresh(&mut self, scene: &Scene) {
+		if self.generation == Some(scene.generation()) {
+			return;
+		}
+
+		self.lists = SCENE_PASSES
+			.iter()
+			.map(|pass_name| (String::from(*pass_name), collect_draw_items(&scene.root, vec![], pass_name)))
+			.collect();
+		for draw_items in self.lists.values_mut() {
+			draw_items.par_iter_mut().for_each(|draw_item| {
+				let model = draw_item.model(scene);
+				if model.mobility != Mobility::Static {
+					return;
+				}
+
+				let mesh = &scene.resources.meshes[draw_item.mesh];
+				let bounds = model.instances.world_instances().map(|instance| {
+					let (center, radius) = instance_bounds(mesh, &instance);
+					(center, radius, instance_box_half_axes(mesh, &instance))
+				});
+				draw_item.static_bounds = Some(bounds.collect());
+			});
+		}
+		self.visible = self.lists.iter().map(|(pass_name, draw_items)| (pass_name.clone(), (0..draw_items.len()).collect())).collect();
+		self.generation = Some(scene.generation());
+	}
+
+	// Keeps only the draw items with at least one instance whose bounds touch the frustum, testing the items in parallel
+	// The bounding sphere rules out most of them cheaply, and the tighter bounding box catches the long and flat meshes whose spheres reach far past them
+	pub fn cull(&mut self, scene: &Scene, pass_name: &str, frustum: &Frustum) {
+		let draw_items = match self.lists.get(pass_name) {
+			Some(draw_items) => draw_items,
+			None => return,
+		};
+
+		let visible = draw_items
+			.par_iter()
+			.enumerate()
+			.filter(|(_, draw_item)| {
+				let intersects = |center: Vector3<f32>, radius: f32, half_axes: Matrix3<f32>| {
+					frustum.intersects_sphere(Point3::from_vec(center), radius) && frustum.intersects_box(Point3::from_vec(center), half_axes)
+				};
+
+				match &draw_item.static_bounds {
+					Some(static_bounds) => static_bounds.iter().any(|(center, radius, half_axes)| intersects(*center, *radius, *half_axes)),
+					None => {
+						let mesh = &scene.resources.meshes[draw_item.mesh];
+
+						draw_item.model(scene).instances.world_instances().any(|instance| {
+							let (center, radius) = instance_bounds(mesh, &instance);
+							intersects(center, radius, instance_box_half_axes(mesh, &instance))
+						})
+					}
+				}
+			})
+			.map(|(index, _)| index)
+			.collect();
+
+		self.visible.insert(String::from(pass_name), visible);
+	}
+
+	// Orders the surviving items farthest first so alpha blending composites them correctly, which is only exact between models since each model's instances are drawn together
+	pub fn sort_back_to_front(&mut self, scene: &Scene, pass_name: &str, eye: Point3<f32>) {
+		let draw_items = match self.lists.get(pass_name) {
+			Some(draw_items) => draw_items,
+			None => return,
+		};
+		let visible = match self.visible.get_mut(pass_name) {
+			Some(visible) => visible,
+			None => return,
+		};
+
+		let distance_squared = |index: &usize| {
+			let draw_item = &draw_items[*index];
+			let mesh = &scene.resources.meshes[draw_item.mesh];
+
+			let (center, _) = instance_bounds(mesh, &draw_item.model(scene).instances.world_instances().next().unwrap());
+			(center - eye.to_vec()).magnitude2()
+		};
+		visible.sort_by(|a, b| distance_squared(b).total_cmp(&distance_squared(a)));
+	}
+
+	// Drops the surviving draw items whose model doesn't pass the predicate, such as a mirror's own surface from its reflection
+	pub fn retain_visible(&mut self, scene: &Scene, pass_name: &str, predicate: impl Fn(&Model) -> bool) {
+		let draw_items = match self.lists.get(pass_name) {
+			Some(draw_items) => draw_items,
+			None => return,
+		};
+		let visible = match self.visible.get_mut(pass_name) {
+			Some(visible) => visible,
+			None => return,
+		};
+
+		visible.retain(|index| predicate(draw_items[*index].model(scene)));
+	}
+
+	// Whether every draw item that survived the pass's culling belongs to a static model, so whatever it drew last time can't have moved
+	pub fn visible_all_static(&self, pass_name: &str) -> bool {
+		self.visible(pass_name).all(|(_, draw_item)| draw_item.static_bounds.is_some())
+	}
+
+	// How many of the pass's draw items were dropped by this frame's culling, for the frame stats
+	pub fn culled_count(&self, pass_name: &str) -> usize {
+		let visible_count = self.visible.get(pass_name).map_or(0, Vec::len);
+		self.items(pass_name).len().saturating_sub(visible_count)
+	}
+
+	pub fn items(&self, pass_name: &str) -> &[DrawItem] {
+		self.lists.get(pass_name).map(Vec::as_slice).unwrap_or_default()
+	}
+
+	// Yields each surviving draw item along with its index in the pass's full draw list
+	pub fn visible<'a>(&'a self, pass_name: &str) -> impl Iterator<Item = (usize, &'a DrawItem)> {
+		let draw_items = self.items(pass_name);
+		let visible = self.visible.get(pass_name).map(Vec::as_slice).unwrap_or_default();
+
+		visible.iter().map(move |index| (*index, &draw_items[*index]))
+	}
+}
+
+impl DrawItem {
+	pub fn model<'a>(&self, scene: &'a Scene) -> &'a Model {
+		let entity = self.entity_path.iter().fold(&scene.root, |entity, index| &entity.children[*index]);
+
+		match &entity.components[self.component_index] {
+			Component::Model(model) => model,
+			_ => panic!("The draw list for entity '{}' is stale, the scene was mutated without calling `Scene::mark_changed()`", entity.name),
+		}
+	}
+}
+
+// Each subtree is collected on its own rayon task, which keeps the rebuild fast for scenes with many entities
+fn collect_draw_items(entity: &Entity, path: Vec<usize>, pass_name: &str) -> Vec<DrawItem> {
+	// Disabled entities hide their whole subtree
+	if !entity.enabled {
+		return vec![];
+	}
+
+	let own_draw_items = entity.components.iter().enumerate().filter_map(|(component_index, component)| match component {
+		Component::Model(model) => {
+			let mesh = model
+				.mesh
+				.unwrap_or_else(|| panic!("The mesh '{}:{}' is not loaded but is trying to be drawn", model.mesh_name.0, model.mesh_name.1));
+			let transparent = model.transparent_surface.is_some();
+			let mirror = model.mirror_surface.is_some();
+			let portal = model.portal_surface.is_some();
+			let layered = model.layer.is_some();
+			let maybe_material = match pass_name {
+				"Scene: Render Voxel Lightmap" if !transparent && !layered => model.voxel_lightmap_material,
+				"Scene: Render Deferred" if !transparent && !mirror && !portal && !layered => model.scene_deferred_material,
+				// The shadow cascades draw the deferred models with their depth-only shadow caster materials
+				"Scene: Render Shadows" if !transparent && !mirror && !portal && !layered && model.casts_shadows => model.scene_shadow_material,
+				// Models with an impostor are in both lists, and each frame's culling keeps them in only one of the two
+				"Scene: Render Impostors" if !transparent && !mirror && !portal && !layered && model.impostor_distance.is_some() => model.impostor_material,
+				// Reflections only contain the opaque models, along with the mirrors themselves, but not portals whose views are only rendered for the main camera
+				"Scene: Render Reflection" if !transparent && !mirror && !portal && !layered => model.reflection_material,
+				"Scene: Render Mirrors" if mirror && !layered => model.mirror_material,
+				"Scene: Render Portals" if portal && !layered => model.portal_material,
+				// Views through portals contain the opaque models, with the mirrors and portals seen through them drawn from their own lists
+				"Scene: Render Portal View" if !transparent && !mirror && !portal && !layered => model.forward_material,
+				// Each transparent model only has materials for either the glass pass or the other transparent passes
+				"Scene: Render Glass" if transparent && !layered => Some(model.glass_material?),
+				"Scene: Render Transparent Sorted" if transparent && !layered => Some(model.transparent_sorted_material?),
+				"Scene: Render Transparent Weighted" if transparent && !layered => Some(model.transparent_weighted_material?),
+				// Every layer shares one list, and each layer keeps only its own models when it's drawn
+				"Scene: Render Layers" if layered => model.forward_material,
+				// Opaque models are left out of the transparent passes and vice versa
+				"Scene: Render Voxel Lightmap"
+				| "Scene: Render Shadows"
+				| "Scene: Render Deferred"
+				| "Scene: Render Impostors"
+				| "Scene: Render Reflection"
+				| "Scene: Render Mirrors"
+				| "Scene: Render Portals"
+				| "Scene: Render Portal View"
+				| "Scene: Render Glass"
+				| "Scene: Render Transparent Sorted"
+				| "Scene: Render Transparent Weighted"
+				| "Scene: Render Layers" => return None,
+				_ => panic!("Invalid render pass for drawing scene {}", pass_name),
+			};
+			let material = maybe_material.unwrap_or_else(|| {
+				panic!(
+					"The material for pass '{}' is not loaded but is trying to be drawn with model '{}:{}'",
+					pass_name, model.mesh_name.0, model.mesh_name.1
+				)
+			});
+
+			Some(DrawItem {
+				entity_path: path.clone(),
+				component_index,
+				mesh,
+				material,
+				static_bounds: None,
+			})
+		}
+		_ => None,
+	});
+
+	let descendant_draw_items = entity
+		.children
+		.par_iter()
+		.enumerate()
+		.flat_map(|(index, child)| {
+			let mut child_path = path.clone();
+			child_path.push(index);
+			collect_draw_items(child, child_path, pass_name)
+		})
+		.collect::<Vec<_>>();
+
+	own_draw_items.chain(descendant_draw_items).collect()
+}
+
+// World space bounding sphere of a mesh placed by one of its model's instances
+pub fn instance_bounds(mesh: &Mesh, instance: &Instance) -> (Vector3<f32>, f32) {
+	let mesh_center = Vector3::from(mesh.bounds_center);
+	let scale = instance.scale;
+
+	let center = instance.location + instance.rotation * Vector3::new(mesh_center.x * scale.x, mesh_center.y * scale.y, mesh_center.z * scale.z);
+	let radius = mesh.bounds_radius * scale.x.abs().max(scale.y.abs()).max(scale.z.abs());
+
+	(center, radius)
+}
+
+// The world space axes of a mesh's bounding box placed by one of its model's instances, centered on its bounding sphere
+pub fn instance_box_half_axes(mesh: &Mesh, instance: &Instance) -> Matrix3<f32> {
+	let [x, y, z] = mesh.bounds_half_extents;
+	let scale = instance.scale;
+
+	Matrix3::from_cols(
+		instance.rotation * Vector3::new(x * scale.x, 0., 0.),
+		instance.rotation * Vector3::new(0., y * scale.y, 0.),
+		instance.rotation * Vector3::new(0., 0., z * scale.z),
+	)
+}
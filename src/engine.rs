@@ -1,46 +1,176 @@
-use crate::camera::{OrthographicProjection, PerspectiveProjection, Projection};
+use crate::asset_loader::{AssetLoader, DecodedAsset, LoadProgress, LOADING_BAR_HEIGHT};
+use crate::audio_analysis::{AudioAnalysis, TestBeat};
+use crate::auto_exposure::AutoExposure;
+use crate::behavior::{BehaviorContext, BehaviorInput};
+use crate::camera::{Camera, PostEffect, Projection};
 use crate::camera_controller::CameraController;
+use crate::character_controller::CharacterController;
+use crate::collider::{Collider, ColliderKind, Wireframe};
+use crate::collision::CollisionWorld;
 use crate::component::Component;
+use crate::console::{Console, CONSOLE_COMMANDS, CONSOLE_PREFABS};
 use crate::context::Context;
+use crate::crash_report::{self, LastFrame};
+use crate::csg::Csg;
 use crate::debug_buffer::DebugBuffer;
+use crate::debug_draw::DebugDraw;
+#[cfg(feature = "debug-server")]
+use crate::debug_server::{entity_json, frame_stats_json, render_settings_json, DebugServer};
+use crate::debug_view::{work_groups_for, DebugViewTextures, LUMINANCE_HISTOGRAM_DIMENSIONS};
+use crate::distance_field::{DistanceField, DISTANCE_FIELD_TEXTURE};
+use crate::draw_list::DrawListCache;
+use crate::entity::Entity;
+use crate::environment::{cube_face_files, EnvironmentBindings, FogMode};
+use crate::final_composite::FinalComposite;
+use crate::follow_camera_controller::FollowCameraController;
+use crate::foveation::Foveation;
+use crate::frame_stats::{CpuTimingScope, FrameStats, GpuTimer, PassCounter};
 use crate::frame_texture::{FrameTexture, FrameTextures};
+use crate::frustum::Frustum;
+use crate::gizmo::{GizmoView, LightGizmos};
+use crate::gpu_capture::GpuCapture;
+use crate::gpu_selection::GpuSelection;
+use crate::hi_z::HiZPyramid;
+use crate::impostor::ImpostorAtlas;
 use crate::instance::Instance;
-use crate::light::SceneLighting;
-use crate::material::{Material, MaterialDataBinding};
-use crate::mesh::Mesh;
-use crate::model::Model;
-use crate::pass::{ComputePass, Pass, RenderPass};
-use crate::scene::Scene;
-use crate::shader::{ComputePipelineOptions, PipelineOptions, RenderPipelineOptions, Shader, ShaderBinding, ShaderBindingBuffer, ShaderBindingTexture};
-use crate::texture::Texture;
+use crate::light::{AmbientMode, Light, LightUniform, SceneLighting, ShadowFilter};
+use crate::material::{BindingOverrides, Material, MaterialDataBinding, MaterialTemplate};
+use crate::material_file::{MaterialFile, MaterialFileBinding, MaterialFileResources};
+use crate::mesh::{collision_triangles, GeneratedMap, MaterialParameters, Mesh, VertexFormat};
+use crate::meshlet::{MeshletDraw, MeshletRenderer};
+use crate::mirror::Mirror;
+use crate::model::{Mobility, Model};
+use crate::noise::{NoiseSettings, NoiseType};
+use crate::pass::{ColorAttachment, ComputePass, CopyTexturePass, DepthAttachment, EffectOperation, EffectPass, LoadPolicy, Pass, RenderPass, Viewport};
+use crate::portal::Portal;
+use crate::render_graph::{temporal_material_name, RenderGraph, TransientAttachments, FRAME_GRAPH_EXPORT_PATH};
+use crate::render_layer::{LayerBlend, RenderLayer};
+use crate::render_settings::{DebugShading, DebugView, DistanceFieldShading, Rasterization, RenderProfile, RenderSettings, TransparencyMode};
+use crate::scatter::{foliage_cards, ScatterLayer, ScatterLod, ScatterMask, ScatterRenderer, ScatterSettings};
+use crate::scene::{MeshHandle, Scene};
+use crate::scene_hooks::{SceneEvent, SceneHook};
+use crate::scripts::spline_follow::SplineFollow;
+use crate::shader::{
+	ComputePipelineOptions, PipelineOptions, RenderPipelineOptions, Shader, ShaderBinding, ShaderBindingBuffer, ShaderBindingTexture, ShaderWatcher, ShadowCasterOptions, CAMERA_BIND_GROUP,
+	GLOBALS_BIND_GROUP, LIGHTING_BIND_GROUP, MATERIAL_BIND_GROUP,
+};
+use crate::shadow_map::{PointShadowMap, ShadowMaps};
+use crate::spline::{drape, Profile, Spline, SplineKind};
+use crate::ssao::SsaoTemporal;
+use crate::stereo::{Eye, StereoRig};
+use crate::temporal_anti_aliasing::TemporalAntiAliasing;
+use crate::texture::{CubemapFaces, CubemapSource, Texture};
+use crate::time_of_day::{GlobalIllumination, TimeOfDay};
+use crate::timeline::{Sequencer, Timeline, TimelineParameter};
 use crate::transform::Transform;
+use crate::transparency::TRANSPARENT_SURFACES;
+use crate::traversal_benchmark;
 use crate::voxel_texture::VoxelTexture;
+use crate::voxel_update::{VoxelUpdateScheduler, VOXEL_RESOLUTION};
+use crate::weather::Weather;
+use crate::world_streaming::{ChunkState, WorldStreaming, CHUNK_FADE_IN_TIME};
 
-use cgmath::{InnerSpace, Rotation, Rotation3, Zero};
+use anyhow::{anyhow, bail};
+use cgmath::{EuclideanSpace, InnerSpace, MetricSpace, Rotation3, SquareMatrix, Zero};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use wgpu::util::DeviceExt;
 use wgpu::BufferBinding;
 use winit::event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
-use winit::{event_loop::ControlFlow, window::Window};
+use winit::{
+	event_loop::ControlFlow,
+	window::{CursorGrabMode, Window},
+};
 
 pub struct Engine {
 	context: Context,
 	frame_textures: FrameTextures,
 	voxel_light_map: VoxelTexture,
+	// Written by the voxel lightmap materials of every model, including those of scenes loaded later on, then turned into the voxel light map
+	voxel_storage_buffer: wgpu::Buffer,
+	voxel_update_scheduler: VoxelUpdateScheduler,
 	frame_time: std::time::Instant,
 	scene: Scene,
+	draw_lists: DrawListCache,
+	render_settings: RenderSettings,
+	// The profile the frame textures and voxel GI were last set up for, so a change of profile is noticed and applied
+	applied_render_profile: RenderProfile,
+	// The polygon mode the G-buffer pipelines were last switched to
+	applied_rasterization: Rasterization,
+	gpu_capture: GpuCapture,
+	last_frame: LastFrame,
+	frame_stats: FrameStats,
+	gpu_timer: Option<GpuTimer>,
+	shader_watcher: ShaderWatcher,
+	hi_z_pyramid: HiZPyramid,
+	meshlet_renderer: MeshletRenderer,
+	scatter_renderer: ScatterRenderer,
+	frames_since_resize: u32,
 	active_camera: String,
 	camera_controller: CameraController,
+	character_controller: CharacterController,
+	follow_camera_controller: FollowCameraController,
+	// Whether the window currently holds and hides the cursor, kept in step with whether the camera controller is looking around
+	cursor_grabbed: bool,
+	collision_world: CollisionWorld,
+	// Generated the first time the collider view shows them, keyed by mesh index, along with their outlines
+	colliders: HashMap<(MeshHandle, ColliderKind), (Collider, Wireframe)>,
+	collider_cache_directory: PathBuf,
+	// Kept for loading assets asked for after startup, like timelines from the console
+	assets_path: PathBuf,
+	// The meshes of each model file loaded so far, which the scene files name their models' meshes from
+	model_meshes: HashMap<String, Vec<String>>,
+	// Loaded at startup and kept while the engine runs, since code like the spawned prefabs draws their meshes without any scene file naming them
+	resident_model_files: HashSet<String>,
+	// Signed distances to the static geometry, baked once the scene has loaded
+	distance_field: DistanceField,
+	distance_field_buffer: wgpu::Buffer,
 	scene_lighting: SceneLighting,
+	shadow_maps: ShadowMaps,
+	point_shadow_map: PointShadowMap,
+	temporal_anti_aliasing: TemporalAntiAliasing,
+	ssao_temporal: SsaoTemporal,
+	effects: Vec<EffectPass>,
+	transient_attachments: TransientAttachments,
+	// Reported once when it first appears, rather than every frame the graph stays unschedulable
+	render_graph_error: Option<String>,
 	debug_buffer: DebugBuffer,
+	// Keys held down, passed on to the scripts through their `BehaviorContext`
+	behavior_input: BehaviorInput,
+	audio_analysis: AudioAnalysis,
+	test_beat: TestBeat,
+	debug_view_textures: DebugViewTextures,
+	debug_draw: DebugDraw,
+	// The view-projection matrix and location of the camera when the frustum was frozen, which culling uses in place of the camera's while it is
+	frozen_culling_view: Option<(cgmath::Matrix4<f32>, cgmath::Point3<f32>)>,
+	light_gizmos: LightGizmos,
+	render_layers: Vec<RenderLayer>,
+	stereo_rig: StereoRig,
+	foveation: Foveation,
+	auto_exposure: AutoExposure,
+	final_composite: FinalComposite,
+	// Bound by the materials built from the material files, so they're kept until those are rebuilt
+	material_file_resources: MaterialFileResources,
+	weather: Weather,
+	environment_bindings: EnvironmentBindings,
+	time_of_day: TimeOfDay,
+	sequencer: Sequencer,
+	console: Console,
+	// Holds the model files and textures still being decoded in the background, until everything's loaded and the scene can be built
+	asset_loader: Option<AssetLoader>,
+	// The world whose chunks are loaded and unloaded around the camera, if one is being streamed
+	world_streaming: Option<WorldStreaming>,
+	#[cfg(feature = "debug-server")]
+	debug_server: Option<DebugServer>,
 }
 
 impl Engine {
 	// Creating some of the wgpu types requires async code
-	pub async fn new(window: &Window) -> Self {
+	pub async fn new(window: &Window, gpu_selection: &GpuSelection) -> Self {
 		// Mechanical details of the GPU rendering process
-		let context = Context::new(window).await;
+		let context = Context::new(window, gpu_selection).await;
 
 		// Prepare the frame textures
 		let z_buffer = FrameTexture::new(
@@ -54,7 +184,7 @@ impl Engine {
 			&context.device,
 			&context.surface_configuration,
 			wgpu::TextureFormat::Depth32Float,
-			"Z-buffer frame texture",
+			"Z-buffer Previous frame texture",
 			Some(wgpu::CompareFunction::LessEqual),
 		);
 		let world_space_normal = FrameTexture::new(
@@ -66,7 +196,23 @@ impl Engine {
 		);
 		let albedo_map = FrameTexture::new(&context.device, &context.surface_configuration, wgpu::TextureFormat::Bgra8UnormSrgb, "Albedo Map frame texture", None);
 		let arm_map = FrameTexture::new(&context.device, &context.surface_configuration, wgpu::TextureFormat::Bgra8Unorm, "ARM Map frame texture", None);
+		let emissive_map = FrameTexture::new(&context.device, &context.surface_configuration, wgpu::TextureFormat::Rgba16Float, "Emissive Map frame texture", None);
+		let velocity_map = FrameTexture::new(&context.device, &context.surface_configuration, wgpu::TextureFormat::Rg16Float, "Velocity Map frame texture", None);
 		let ssao_kernel_map = FrameTexture::new(&context.device, &context.surface_configuration, wgpu::TextureFormat::Rgba16Float, "SSAO Kernel Map frame texture", None);
+		let ssao_history_map = FrameTexture::new(
+			&context.device,
+			&context.surface_configuration,
+			wgpu::TextureFormat::Rgba16Float,
+			"SSAO History Map frame texture",
+			None,
+		);
+		let ssao_history_previous_map = FrameTexture::new(
+			&context.device,
+			&context.surface_configuration,
+			wgpu::TextureFormat::Rgba16Float,
+			"SSAO History Previous Map frame texture",
+			None,
+		);
 		let ssao_blurred_map = FrameTexture::new(
 			&context.device,
 			&context.surface_configuration,
@@ -75,7 +221,50 @@ impl Engine {
 			None,
 		);
 		let pbr_shaded_map = FrameTexture::new(&context.device, &context.surface_configuration, wgpu::TextureFormat::Rgba16Float, "PBR Shaded Map frame texture", None);
+		let checkerboard_history_map = FrameTexture::new(
+			&context.device,
+			&context.surface_configuration,
+			wgpu::TextureFormat::Rgba16Float,
+			"Checkerboard History Map frame texture",
+			None,
+		);
+		let checkerboard_history_previous_map = FrameTexture::new(
+			&context.device,
+			&context.surface_configuration,
+			wgpu::TextureFormat::Rgba16Float,
+			"Checkerboard History Previous Map frame texture",
+			None,
+		);
 		let motion_blur_map = FrameTexture::new(&context.device, &context.surface_configuration, wgpu::TextureFormat::Rgba16Float, "Motion Blur Map frame texture", None);
+		let taa_history_map = FrameTexture::new(&context.device, &context.surface_configuration, wgpu::TextureFormat::Rgba16Float, "TAA History Map frame texture", None);
+		let taa_history_previous_map = FrameTexture::new(
+			&context.device,
+			&context.surface_configuration,
+			wgpu::TextureFormat::Rgba16Float,
+			"TAA History Previous Map frame texture",
+			None,
+		);
+		let transparent_accumulation_map = FrameTexture::new(
+			&context.device,
+			&context.surface_configuration,
+			wgpu::TextureFormat::Rgba16Float,
+			"Transparent Accumulation Map frame texture",
+			None,
+		);
+		let transparent_coverage_map = FrameTexture::new(
+			&context.device,
+			&context.surface_configuration,
+			wgpu::TextureFormat::R16Float,
+			"Transparent Coverage Map frame texture",
+			None,
+		);
+		let scene_color_copy_map = FrameTexture::new(
+			&context.device,
+			&context.surface_configuration,
+			wgpu::TextureFormat::Rgba16Float,
+			"Scene Color Copy Map frame texture",
+			None,
+		);
 
 		let frame_textures = FrameTextures {
 			z_buffer,
@@ -83,13 +272,32 @@ impl Engine {
 			world_space_normal,
 			albedo_map,
 			arm_map,
+			emissive_map,
+			velocity_map,
 			ssao_kernel_map,
+			ssao_history_map,
+			ssao_history_previous_map,
 			ssao_blurred_map,
 			pbr_shaded_map,
+			checkerboard_history_map,
+			checkerboard_history_previous_map,
 			motion_blur_map,
+			taa_history_map,
+			taa_history_previous_map,
+			transparent_accumulation_map,
+			transparent_coverage_map,
+			scene_color_copy_map,
+			frame_index: 0,
 		};
 
 		let voxel_light_map = VoxelTexture::new(&context.device, (128, 128, 128), wgpu::TextureFormat::Rgba8Unorm, "Voxel Light Map (u32)", None);
+		let voxel_storage_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("Voxel storage buffer"),
+			size: 128 * 128 * 128 * 4 * 4,
+			usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+			mapped_at_creation: false,
+		});
+		let voxel_update_scheduler = VoxelUpdateScheduler::new(&context.device, 16, 2);
 
 		// Prepare the initial time value used to calculate the delta time since last frame
 		let frame_time = std::time::Instant::now();
@@ -97,107 +305,682 @@ impl Engine {
 		// Camera
 		let active_camera = String::from("Main Camera");
 		let camera_controller = CameraController::new(4.0, 0.4);
+		let character_controller = CharacterController::new(0.4);
+		let follow_targets = ["Tracked Cube", "Cubes"].map(String::from).to_vec();
+		let follow_camera_controller = FollowCameraController::new(follow_targets, 5., 0.3, 0.4);
 
 		// Lights
 		let scene_lighting = SceneLighting::new(&context);
+		let shadow_maps = ShadowMaps::new(&context);
+		let point_shadow_map = PointShadowMap::new(&context);
+		let temporal_anti_aliasing = TemporalAntiAliasing::new(&context.device);
+		let ssao_temporal = SsaoTemporal::new(&context.device);
+
+		// Effects declared by the textures they read and write, which the render graph slots into the frame on its own
+		// Anti-aliasing comes before motion blur, so the blur smears the resolved edges rather than the jittering ones
+		// The resolve writes straight into this frame's history, which is then copied over the shaded scene for the passes after it
+		let effects = vec![
+			EffectPass {
+				label: String::from("Pass: TAA Resolve"),
+				operation: EffectOperation::Blit {
+					material: String::from("pass_taa_resolve.material"),
+					output: String::from("TAA History Map frame texture"),
+				},
+				before: String::from("Pass: Motion Blur"),
+				post_effect: Some(PostEffect::TemporalAntiAliasing),
+			},
+			EffectPass {
+				label: String::from("Copy: TAA Resolved"),
+				operation: EffectOperation::Copy {
+					source: String::from("TAA History Map frame texture"),
+					destination: String::from("PBR Shaded Map frame texture"),
+				},
+				before: String::from("Pass: Motion Blur"),
+				post_effect: Some(PostEffect::TemporalAntiAliasing),
+			},
+		];
+
+		// Render layers, composited over the world in order
+		let render_layers = vec![RenderLayer::new(&context, "First Person", LayerBlend::AlphaOver, cgmath::Deg(60.), 0.01, 5.)];
+
+		// Stereo views, only rendered while the stereo mode is on
+		let stereo_rig = StereoRig::new(&context);
+
+		// Shading rates across the screen, only reduced while foveated shading is on
+		let foveation = Foveation::new(&context.device);
+		let auto_exposure = AutoExposure::new(&context.device);
+		let final_composite = FinalComposite::new(&context.device);
+		let weather = Weather::new(&context.device);
+		let environment_bindings = EnvironmentBindings::new(&context.device, &context.queue);
+		let time_of_day = TimeOfDay::default();
+		let sequencer = Sequencer::new();
+		let console = Console::new(&context.device);
+
+		// With the `debug-server` feature, external tools can connect to the address named by `SEURAT_DEBUG_SERVER`, like `127.0.0.1:9001`
+		#[cfg(feature = "debug-server")]
+		let debug_server = std::env::var("SEURAT_DEBUG_SERVER").ok().and_then(|address| match DebugServer::start(&address) {
+			Ok(server) => Some(server),
+			Err(error) => {
+				eprintln!("Couldn't start the debug server at {}: {}", address, error);
+				None
+			}
+		});
+
+		// Distances to the static geometry, which stay empty until the scene is loaded and baked
+		let distance_field = DistanceField::default();
+		let distance_field_buffer = context.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Distance Field Buffer"),
+			contents: bytemuck::cast_slice(&[distance_field.uniform(DistanceFieldShading::Off)]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
 
 		// Debugging
-		let debug_buffer = DebugBuffer::new(&context);
+		let debug_buffer = DebugBuffer::new();
+		let audio_analysis = AudioAnalysis::new();
+		let test_beat = TestBeat::new(120.);
+		let debug_view_textures = DebugViewTextures::new(&context.device, &context.surface_configuration);
+		let debug_draw = DebugDraw::new(&context);
+		let light_gizmos = LightGizmos::new();
+		let gpu_capture = GpuCapture::new();
+		let last_frame = LastFrame::new(&context.device, &context.surface_configuration);
+		let frame_stats = FrameStats::new();
+		let gpu_timer = context.gpu_features.timestamp_queries.then(|| GpuTimer::new(&context.device, &context.queue));
+		let shader_watcher = ShaderWatcher::new(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"));
 
 		// Scene
 		let scene = Scene::new();
+		let draw_lists = DrawListCache::new();
+
+		// Experimental rendering paths, starting with the profile suited to the GPU, which is applied by the first update
+		let mut render_settings = RenderSettings::new();
+		render_settings.profile = RenderProfile::for_adapter(&context.adapter_info);
+		let applied_render_profile = RenderProfile::Full;
+		let applied_rasterization = Rasterization::Filled;
+		let hi_z_pyramid = HiZPyramid::new(&context.device, frame_textures.z_buffers_previous_by_parity());
+		let meshlet_renderer = MeshletRenderer::new();
+		let scatter_renderer = ScatterRenderer::new();
 
 		Self {
 			context,
 			frame_textures,
 			voxel_light_map,
+			voxel_storage_buffer,
+			voxel_update_scheduler,
 			frame_time,
 			scene,
+			draw_lists,
+			render_settings,
+			applied_render_profile,
+			applied_rasterization,
+			gpu_capture,
+			last_frame,
+			frame_stats,
+			gpu_timer,
+			shader_watcher,
+			hi_z_pyramid,
+			meshlet_renderer,
+			scatter_renderer,
+			frames_since_resize: 0,
 			active_camera,
 			camera_controller,
+			character_controller,
+			follow_camera_controller,
+			cursor_grabbed: false,
+			collision_world: CollisionWorld::default(),
+			colliders: HashMap::new(),
+			collider_cache_directory: PathBuf::new(),
+			assets_path: PathBuf::new(),
+			model_meshes: HashMap::new(),
+			resident_model_files: HashSet::new(),
+			distance_field,
+			distance_field_buffer,
 			scene_lighting,
+			shadow_maps,
+			point_shadow_map,
+			temporal_anti_aliasing,
+			ssao_temporal,
+			effects,
+			transient_attachments: TransientAttachments::default(),
+			render_graph_error: None,
 			debug_buffer,
+			behavior_input: BehaviorInput::default(),
+			audio_analysis,
+			test_beat,
+			debug_view_textures,
+			debug_draw,
+			frozen_culling_view: None,
+			light_gizmos,
+			render_layers,
+			stereo_rig,
+			foveation,
+			auto_exposure,
+			final_composite,
+			material_file_resources: MaterialFileResources::default(),
+			weather,
+			environment_bindings,
+			time_of_day,
+			sequencer,
+			console,
+			asset_loader: None,
+			world_streaming: None,
+			#[cfg(feature = "debug-server")]
+			debug_server,
 		}
 	}
 
+	// Starts reading the model files on worker threads and returns right away, with the frames drawing the loading screen until `poll_loading` finds them done
 	pub fn load(&mut self, assets_path: &Path) {
-		let model_files = ["cube.obj", "sponza_pbr.obj"];
-		let model_meshes = self.preload_model_files(&model_files, assets_path);
+		self.assets_path = assets_path.to_path_buf();
+
+		let mut asset_loader = AssetLoader::new(assets_path, self.render_settings.profile.skipped_texture_mips());
+		for model_file in ["cube.obj", "sponza_pbr.obj"] {
+			asset_loader.request_meshes(model_file);
+		}
+		self.asset_loader = Some(asset_loader);
+	}
+
+	// Uploads whatever the asset loader has finished decoding, asking it for the textures of each model file's meshes as they arrive
+	// Returns how far along loading is, or `None` once everything has loaded and the scene is built
+	fn poll_loading(&mut self) -> Option<LoadProgress> {
+		let mut asset_loader = self.asset_loader.take()?;
+
+		while let Some(asset) = asset_loader.try_receive() {
+			self.upload_decoded_asset(&mut asset_loader, asset);
+		}
+
+		let progress = asset_loader.progress();
+		if !progress.finished() {
+			self.asset_loader = Some(asset_loader);
+			return Some(progress);
+		}
+
+		self.finish_loading(asset_loader.model_meshes);
+		None
+	}
+
+	// Uploads an asset the loader has finished decoding, asking it for the textures of a model file's meshes that aren't loaded yet
+	fn upload_decoded_asset(&mut self, asset_loader: &mut AssetLoader, asset: DecodedAsset) {
+		match asset {
+			DecodedAsset::Meshes { file, meshes } => {
+				// A model file that can't be read leaves the models using it with nothing to draw
+				let meshes = meshes.unwrap_or_else(|error| {
+					eprintln!("Can't load model file {}: {}", file, error);
+					vec![]
+				});
+
+				let mut mesh_names = vec![];
+				for decoded_mesh in meshes {
+					let mesh = Mesh::upload(&self.context.device, decoded_mesh);
+					for (texture, format, repeat_mode) in mesh.map_textures() {
+						if !self.scene.resources.textures.contains_key(&texture) {
+							asset_loader.request_texture(&texture, format, repeat_mode);
+						}
+					}
+
+					mesh_names.push(mesh.name.clone());
+					self.scene.resources.meshes.insert((file.clone(), mesh.name.clone()), mesh);
+				}
+				asset_loader.model_meshes.insert(file, mesh_names);
+			}
+			DecodedAsset::Texture { file, format, repeat_mode, image } => {
+				let image = image.unwrap_or_else(|_| panic!("Can't load texture file: {}", file));
+				let mut texture = Texture::from_image(&self.context.device, &self.context.queue, &image, &file, format, repeat_mode);
+				texture.generate_mipmaps(&self.context);
+				self.scene.resources.textures.insert(file, texture);
+			}
+		}
+	}
+
+	// Builds the scene from the loaded model files, along with everything else that depends on their meshes
+	fn finish_loading(&mut self, mut model_meshes: HashMap<String, Vec<String>>) {
+		let assets_path = self.assets_path.clone();
+		let assets_path = assets_path.as_path();
+		model_meshes.extend([self.preload_spline_meshes(), self.preload_csg_meshes(), self.preload_scatter_meshes()]);
 
-		self.build_scene(&model_meshes);
+		self.build_scene(&model_meshes, assets_path);
 		self.load_resources(&model_meshes, assets_path);
 
 		// Once the scene is populated and resources are loaded, each `Model` needs to associate itself with its mesh resources
 		self.scene.root.load_models_on_descendants(&self.scene.resources);
+		self.scene.root.update_world_transforms_of_descendants(&Transform::default(), &self.context.device);
+		self.scene.mark_changed();
+		self.resident_model_files = model_meshes.keys().cloned().collect();
+		self.model_meshes = model_meshes;
+
+		// Foliage grows on the courtyard floor, thinned into patches by a noise mask
+		self.add_scatter_layers();
+
+		// Everything built over the finished scene, like the collision triangles and distance field, is brought up to date by its hooks
+		self.collider_cache_directory = assets_path.join("cache").join("colliders");
+		self.scene.raise(SceneEvent::Loaded);
+		self.run_scene_hooks();
+
+		// Only the surface followed the window while loading, so everything else catches up if it was resized in the meantime
+		let config = &self.context.surface_configuration;
+		let size = winit::dpi::PhysicalSize::new(config.width, config.height);
+		if self.frame_textures.z_buffer.texture.size.width != size.width || self.frame_textures.z_buffer.texture.size.height != size.height {
+			self.resize(size);
+		}
 	}
 
-	fn preload_model_files(&mut self, model_files: &[&str], assets_path: &Path) -> HashMap<String, Vec<String>> {
-		model_files
-			.iter()
+	// Loads a scene file on top of the current scene, after loading whichever of its model files aren't loaded yet along with their textures and materials
+	// The files are decoded in parallel but waited on before returning, so streaming in a large scene holds up the frame it's loaded on
+	fn load_additive_scene(&mut self, file: &str) -> anyhow::Result<String> {
+		let assets_path = self.assets_path.clone();
+		let new_model_files = Scene::model_files_in_file(&assets_path, file)?
+			.into_iter()
+			.filter(|model_file| !self.model_meshes.contains_key(model_file))
+			.collect::<Vec<_>>();
+
+		let decoded_model_files = new_model_files
+			.into_par_iter()
 			.map(|model_file| {
-				let meshes = Mesh::load(&self.context.device, &self.context.queue, assets_path, model_file).unwrap_or_default();
-				let mesh_names = meshes.iter().map(|mesh| mesh.name.clone()).collect::<Vec<_>>();
+				let meshes = Mesh::decode(&assets_path, &model_file);
+				(model_file, meshes)
+			})
+			.collect::<Vec<_>>();
+		let mut loaded_model_meshes = HashMap::new();
+		for (model_file, meshes) in decoded_model_files {
+			let meshes = meshes.map_err(|error| anyhow!("Can't load model file {}: {}", model_file, error))?;
 
-				for mesh in meshes {
-					self.scene.resources.meshes.insert((String::from(*model_file), mesh.name.clone()), mesh);
+			let mut mesh_names = vec![];
+			for decoded_mesh in meshes {
+				let mesh = Mesh::upload(&self.context.device, decoded_mesh);
+				mesh_names.push(mesh.name.clone());
+				self.scene.resources.meshes.insert((model_file.clone(), mesh.name.clone()), mesh);
+			}
+			loaded_model_meshes.insert(model_file, mesh_names);
+		}
+		let loaded_model_file_count = loaded_model_meshes.len();
+		self.add_additive_scene(file, loaded_model_meshes)?;
+
+		Ok(format!("Loaded {}, along with {} model files it needed", file, loaded_model_file_count))
+	}
+
+	// Builds a scene file on top of the current scene once the model files it needs have been uploaded, giving those it brought in their materials first
+	fn add_additive_scene(&mut self, file: &str, loaded_model_meshes: HashMap<String, Vec<String>>) -> anyhow::Result<()> {
+		let assets_path = self.assets_path.clone();
+		if !loaded_model_meshes.is_empty() {
+			self.load_model_materials(&loaded_model_meshes, &assets_path);
+			self.model_meshes.extend(loaded_model_meshes);
+		}
+
+		self.scene.load_additive(&mut self.context, &assets_path, file, &self.model_meshes)?;
+		// Baking picks up the models of the new scene with an impostor distance, whose impostor materials didn't exist yet when the models were spawned
+		self.bake_impostors();
+		if let Some(root) = self.scene.root.find_descendant_mut(file) {
+			root.load_models_on_descendants(&self.scene.resources);
+		}
+		Ok(())
+	}
+
+	// Unloads a scene loaded on top of the current one, releasing the meshes, materials, and textures of the model files nothing else uses anymore
+	fn unload_additive_scene(&mut self, file: &str) -> anyhow::Result<String> {
+		let unused_model_files = self
+			.scene
+			.unload_additive(file)?
+			.into_iter()
+			.filter(|model_file| !self.resident_model_files.contains(model_file))
+			.collect::<Vec<_>>();
+
+		let mut released_textures = HashSet::new();
+		for model_file in &unused_model_files {
+			for mesh_name in self.model_meshes.remove(model_file).unwrap_or_default() {
+				if let Some(mesh) = self.scene.resources.meshes.swap_remove(&(model_file.clone(), mesh_name.clone())) {
+					released_textures.extend(mesh.map_textures().into_iter().map(|(texture, _, _)| texture));
+				}
+				for kind in ["scene_deferred", "scene_shadow", "scene_reflection", "scene_forward", "calc_voxel_lightmap", "scene_impostor"] {
+					self.scene.resources.materials.swap_remove(&format!("{}_{}.material", kind, mesh_name));
+				}
+				for kind in ["NORMAL", "ALBEDO", "ARM"] {
+					self.scene.resources.textures.swap_remove(&format!("IMPOSTOR_{}_ATLAS_{}", kind, mesh_name));
+				}
+			}
+		}
+
+		// Textures can be shared between model files, so only those none of the remaining meshes use are released
+		let used_textures = self
+			.scene
+			.resources
+			.meshes
+			.values()
+			.flat_map(|mesh| mesh.map_textures())
+			.map(|(texture, _, _)| texture)
+			.collect::<HashSet<_>>();
+		for texture in released_textures.difference(&used_textures) {
+			self.scene.resources.textures.swap_remove(texture);
+		}
+
+		// Removing resources moves others into their slots, so every model looks up its handles again and the colliders keyed by them are generated anew
+		self.scene.root.load_models_on_descendants(&self.scene.resources);
+		self.colliders.clear();
+
+		Ok(format!("Unloaded {}, releasing {} model files", file, unused_model_files.len()))
+	}
+
+	// Starts loading the chunks of the world near the camera, uploads what their loaders have decoded, adds those that are done to the scene, and unloads those left far behind
+	// Each chunk is added only once every model file it needs is uploaded, including those a loader of another chunk was already asked for
+	fn update_world_streaming(&mut self, delta_time: std::time::Duration) {
+		let mut world_streaming = match self.world_streaming.take() {
+			Some(world_streaming) => world_streaming,
+			None => return,
+		};
+		let camera_location = self.scene.find_entity(self.active_camera.as_str()).unwrap().get_cameras()[0].location;
+		let assets_path = self.assets_path.clone();
+
+		for chunk in &mut world_streaming.chunks {
+			let distance = chunk.center.distance(camera_location);
+			let wants_loaded = !world_streaming.stopping && distance < world_streaming.load_distance;
+			let wants_unloaded = world_streaming.stopping || distance > world_streaming.unload_distance;
+
+			match &mut chunk.state {
+				ChunkState::Unloaded if wants_loaded => {
+					let model_files = match Scene::model_files_in_file(&assets_path, &chunk.file) {
+						Ok(model_files) => model_files,
+						Err(error) => {
+							eprintln!("Can't stream in chunk {}: {}", chunk.file, error);
+							chunk.state = ChunkState::Failed;
+							continue;
+						}
+					};
+
+					let mut asset_loader = AssetLoader::new(&assets_path, self.render_settings.profile.skipped_texture_mips());
+					for model_file in &model_files {
+						if !self.model_meshes.contains_key(model_file) && world_streaming.pending_model_files.insert(model_file.clone()) {
+							asset_loader.request_meshes(model_file);
+						}
+					}
+					chunk.state = ChunkState::Loading { asset_loader, model_files };
+				}
+				ChunkState::Loading { asset_loader, model_files } => {
+					while let Some(asset) = asset_loader.try_receive() {
+						self.upload_decoded_asset(asset_loader, asset);
+					}
+					if !asset_loader.progress().finished() {
+						continue;
+					}
+
+					// The model files this chunk's loader brought in get their materials now, even if the chunk still waits on another loader
+					let loaded_model_meshes = std::mem::take(&mut asset_loader.model_meshes);
+					for model_file in loaded_model_meshes.keys() {
+						world_streaming.pending_model_files.remove(model_file);
+					}
+					if !loaded_model_meshes.is_empty() {
+						self.load_model_materials(&loaded_model_meshes, &assets_path);
+						self.model_meshes.extend(loaded_model_meshes);
+					}
+					// Those still missing are either on another chunk's loader or were released by a chunk unloading in the meantime, which are asked for again
+					let missing_model_files = model_files.iter().filter(|model_file| !self.model_meshes.contains_key(*model_file)).collect::<Vec<_>>();
+					if !missing_model_files.is_empty() {
+						for model_file in missing_model_files {
+							if world_streaming.pending_model_files.insert(model_file.clone()) {
+								asset_loader.request_meshes(model_file);
+							}
+						}
+						continue;
+					}
+
+					match self.add_additive_scene(&chunk.file, HashMap::new()) {
+						Ok(()) => {
+							// The chunk starts out dithered away entirely and fades in over the next frames
+							if let Some(root) = self.scene.root.find_descendant_mut(&chunk.file) {
+								root.set_fade_of_descendants(0., &self.context.device);
+							}
+							chunk.state = ChunkState::Loaded { fade_time: 0. };
+						}
+						Err(error) => {
+							eprintln!("Can't stream in chunk {}: {}", chunk.file, error);
+							chunk.state = ChunkState::Failed;
+						}
+					}
+				}
+				ChunkState::Loaded { .. } if wants_unloaded => {
+					if let Err(error) = self.unload_additive_scene(&chunk.file) {
+						eprintln!("Can't stream out chunk {}: {}", chunk.file, error);
+					}
+					chunk.state = ChunkState::Unloaded;
+				}
+				ChunkState::Loaded { fade_time } if *fade_time < CHUNK_FADE_IN_TIME => {
+					*fade_time += delta_time.as_secs_f32();
+					let fade = (*fade_time / CHUNK_FADE_IN_TIME).min(1.);
+					if let Some(root) = self.scene.root.find_descendant_mut(&chunk.file) {
+						root.set_fade_of_descendants(fade, &self.context.device);
+					}
+				}
+				_ => {}
+			}
+		}
+
+		// Once stopped, the world is let go of after its last chunk is unloaded
+		if !world_streaming.stopping || world_streaming.loaded_count() + world_streaming.loading_count() > 0 {
+			self.world_streaming = Some(world_streaming);
+		}
+	}
+
+	// Redoes the work depending on the scene that the events raised on it since the last run have made stale
+	fn run_scene_hooks(&mut self) {
+		for hook in self.scene.hooks.take_triggered() {
+			match hook {
+				// Static models stay put once the scene is built, so their triangles are gathered for the character controller to walk on
+				SceneHook::RebuildCollision => self.collision_world = CollisionWorld::build(&self.scene),
+				// The same triangles are baked into the distance field, which is cached since it takes a while, then the materials sampling it are rebuilt to see it
+				SceneHook::RebakeDistanceField => {
+					self.distance_field = DistanceField::load_or_bake(&self.assets_path.join("cache").join("distance_field.sdf"), self.collision_world.triangles());
+					self.upload_distance_field();
+					self.update_post_processing_pass_materials();
 				}
+				SceneHook::Revoxelize => self.voxel_update_scheduler.mark_all_dirty(),
+				SceneHook::ResetTemporalHistory => {
+					self.temporal_anti_aliasing.invalidate_history();
+					self.ssao_temporal.invalidate_history();
+					self.foveation.invalidate_history();
+				}
+			}
+		}
+	}
+
+	// Decodes the sky map named by the scene's environment, leaving the sky black if it can't be read, then rebuilds the materials sampling it
+	fn load_sky_map(&mut self) {
+		let source = self.scene.environment.sky_map.clone();
+		let faces = source.as_ref().and_then(|source| match CubemapFaces::decode(&self.assets_path, source) {
+			Ok(faces) => Some(faces),
+			Err(error) => {
+				eprintln!("Can't load the sky map: {}", error);
+				None
+			}
+		});
+
+		self.environment_bindings.set_sky_map(&self.context.device, &self.context.queue, source, faces.as_ref());
+		self.update_post_processing_pass_materials();
+	}
+
+	// Replaces the distance field's volume texture and placement with the current bake
+	fn upload_distance_field(&mut self) {
+		let [width, height, depth] = self.distance_field.resolution;
+		let texture = Texture::from_f16_array_3d(
+			&self.context.device,
+			&self.context.queue,
+			self.distance_field.texels().as_slice(),
+			(width, height, depth),
+			DISTANCE_FIELD_TEXTURE,
+			wgpu::TextureFormat::Rgba16Float,
+			wgpu::AddressMode::ClampToEdge,
+		);
+		self.scene.resources.textures.insert(String::from(DISTANCE_FIELD_TEXTURE), texture);
+
+		self.update_distance_field_uniform();
+	}
+
+	fn update_distance_field_uniform(&mut self) {
+		let uniform = self.distance_field.uniform(self.render_settings.distance_field_shading);
+		self.context.queue.write_buffer(&self.distance_field_buffer, 0, bytemuck::cast_slice(&[uniform]));
+	}
+
+	// Meshes extruded along splines at runtime, listed like the meshes of a model file so they get the same materials
+	fn preload_spline_meshes(&mut self) -> (String, Vec<String>) {
+		let extrusions = [
+			// Pipe looping over the cubes, which also serves as the rail for the cart riding it
+			("Spline Pipe", Self::spline_rail(), Profile::circle(0.08, 12), false),
+			// Road sweeping around the front of the cubes, laid over the floor
+			(
+				"Spline Road",
+				Spline::new(
+					SplineKind::Bezier,
+					vec![
+						cgmath::Point3::new(-8., 0., 7.),
+						cgmath::Point3::new(-4., 0., 11.),
+						cgmath::Point3::new(4., 0., 3.),
+						cgmath::Point3::new(8., 0., 7.),
+					],
+					false,
+				),
+				Profile::strip(1.5, 6),
+				true,
+			),
+		];
+
+		// Ground for the draped splines, sampled from the floor's triangles as seen from above
+		let ground_triangles = self
+			.scene
+			.resources
+			.meshes
+			.values()
+			.filter(|mesh| mesh.material_name.as_deref() == Some("Floor"))
+			.flat_map(|mesh| mesh.collision_triangles.iter().copied())
+			.collect::<Vec<_>>();
+		let ground = Collider::generate(ColliderKind::Heightfield, &ground_triangles);
+
+		let mut mesh_names = Vec::new();
+		for (name, spline, profile, draped) in extrusions {
+			let (mut vertices, indices) = spline.extrude(&profile, 0.25, 1.5);
+			if draped {
+				drape(&mut vertices, &indices, |x, z| ground.height_at(x, z), 0.02);
+			}
+
+			let mesh = self.scene.resources.add_mesh_from_vertices(&self.context, "SPLINE", name, &vertices, &indices).unwrap();
+			mesh.map_albedo = Some(String::from("cobblestone_albedo.jpg"));
+			mesh.map_arm = Some(String::from("cobblestone_arm.jpg"));
+			mesh.map_normal = Some(String::from("cobblestone_normal.jpg"));
+
+			mesh_names.push(String::from(name));
+		}
+
+		(String::from("SPLINE"), mesh_names)
+	}
+
+	// Solids combined with constructive solid geometry, listed like the meshes of a model file so they get the same materials
+	fn preload_csg_meshes(&mut self) -> (String, Vec<String>) {
+		// The classic CSG demo: the loaded beveled cube rounded off by a sphere, then drilled through along each axis
+		let cube = Csg::from_triangles(&self.scene.resources.meshes[&(String::from("cube.obj"), String::from("BeveledCube"))].collision_triangles);
+		let rounded_cube = cube.intersect(&Csg::sphere(cgmath::Point3::new(0., 0., 0.), 1.3, 24, 12));
+		let drill = |axis: cgmath::Vector3<f32>| Csg::cylinder(cgmath::Point3::from_vec(-axis * 1.5), cgmath::Point3::from_vec(axis * 1.5), 0.55, 24);
+		let drilled_cube = rounded_cube.subtract(&drill(cgmath::Vector3::unit_x()).union(&drill(cgmath::Vector3::unit_y())).union(&drill(cgmath::Vector3::unit_z())));
+
+		// A wall with a doorway, the kind of blockout geometry level prototyping starts from
+		let wall = Csg::cuboid(cgmath::Point3::new(0., 1.5, 0.), cgmath::Vector3::new(3., 1.5, 0.2));
+		let doorway = Csg::cuboid(cgmath::Point3::new(0., 1., 0.), cgmath::Vector3::new(0.6, 1.1, 0.5));
+		let wall_with_doorway = wall.subtract(&doorway);
+
+		let mut mesh_names = Vec::new();
+		for (name, solid) in [("CSG Drilled Cube", drilled_cube), ("CSG Doorway", wall_with_doorway)] {
+			let (vertices, indices) = solid.to_mesh(1.);
+
+			let mesh = self.scene.resources.add_mesh_from_vertices(&self.context, "CSG", name, &vertices, &indices).unwrap();
+			mesh.collision_triangles = collision_triangles(&vertices, &indices);
+			mesh.map_albedo = Some(String::from("dirt_albedo.jpg"));
+			mesh.map_arm = Some(String::from("dirt_arm.jpg"));
+			mesh.map_normal = Some(String::from("dirt_normal.jpg"));
+
+			mesh_names.push(String::from(name));
+		}
+
+		(String::from("CSG"), mesh_names)
+	}
+
+	// Foliage LODs made of crossed cards, fewer of them the farther away, textured with Sponza's potted plants
+	fn preload_scatter_meshes(&mut self) -> (String, Vec<String>) {
+		let plants = self.scene.resources.meshes.values().find(|mesh| mesh.material_name.as_deref() == Some("Plants"));
+		let (map_albedo, map_arm, map_normal, material_parameters) = match plants {
+			Some(plants) => (plants.map_albedo.clone(), plants.map_arm.clone(), plants.map_normal.clone(), plants.material_parameters),
+			None => (None, None, None, MaterialParameters::default()),
+		};
+
+		let mut mesh_names = Vec::new();
+		for (name, card_count) in [("Foliage LOD0", 4), ("Foliage LOD1", 2), ("Foliage LOD2", 1)] {
+			let (vertices, indices) = foliage_cards(card_count, 0.6, 0.5);
+
+			let mesh = self.scene.resources.add_mesh_from_vertices(&self.context, "SCATTER", name, &vertices, &indices).unwrap();
+			mesh.map_albedo = map_albedo.clone();
+			mesh.map_arm = map_arm.clone();
+			mesh.map_normal = map_normal.clone();
+			mesh.material_parameters = material_parameters;
+
+			mesh_names.push(String::from(name));
+		}
+
+		(String::from("SCATTER"), mesh_names)
+	}
+
+	fn add_scatter_layers(&mut self) {
+		let mask_dimensions = (64, 64);
+		let mask = crate::noise::generate_noise_texture_2d(&NoiseSettings::new(NoiseType::Perlin, 4, 7), mask_dimensions);
 
-				(String::from(*model_file), mesh_names)
+		let lods = [("Foliage LOD0", 8.), ("Foliage LOD1", 20.), ("Foliage LOD2", 40.)]
+			.into_iter()
+			.map(|(name, max_distance)| ScatterLod {
+				mesh: (String::from("SCATTER"), String::from(name)),
+				max_distance,
 			})
-			.collect::<HashMap<_, _>>()
+			.collect();
+		let settings = ScatterSettings {
+			material: String::from("Floor"),
+			mask: Some(ScatterMask::from_texels(&mask, mask_dimensions)),
+			density: 12.,
+			seed: 7,
+			min_scale: 0.6,
+			max_scale: 1.2,
+			max_slope: 30.,
+			lods,
+		};
+
+		let scene_camera = self.scene.find_entity(self.active_camera.as_str()).unwrap().get_cameras()[0];
+		self.scatter_renderer.add_layer(&self.context.device, &self.scene, &settings, &scene_camera.camera_buffer);
+	}
+
+	fn spline_rail() -> Spline {
+		let points = vec![
+			cgmath::Point3::new(-7., 2., -4.),
+			cgmath::Point3::new(0., 3., -6.),
+			cgmath::Point3::new(7., 2., -4.),
+			cgmath::Point3::new(7., 2.5, 4.),
+			cgmath::Point3::new(0., 1.5, 6.),
+			cgmath::Point3::new(-7., 2.5, 4.),
+		];
+
+		Spline::new(SplineKind::CatmullRom, points, true)
 	}
 
-	fn build_scene(&mut self, model_files: &HashMap<String, Vec<String>>) {
-		let voxel_camera_transform_x = Transform {
-			// location: cgmath::Point3::new(0., -5., -20.),
-			location: cgmath::Point3::new(15., 10., -5.),
-			// rotation: cgmath::Quaternion::from_angle_y(cgmath::Deg(90.)),
-			rotation: cgmath::Quaternion::look_at(cgmath::Vector3::new(-1., -1., -1.), cgmath::Vector3::new(0., 1., 0.)),
-			scale: cgmath::Vector3::new(1., 1., 1.),
+	fn build_scene(&mut self, model_files: &HashMap<String, Vec<String>>, assets_path: &Path) {
+		// The cameras, lights, and hand placed models come from the scene file, while the procedurally placed content below is generated here
+		// The meshes generated while preloading are already in the resources, so they carry over to the loaded scene
+		let resources = std::mem::take(&mut self.scene.resources);
+		self.scene = match Scene::from_file(&mut self.context, assets_path, "courtyard.json", model_files) {
+			Ok(scene) => scene,
+			Err(error) => panic!("Couldn't load the scene: {:?}", error),
 		};
-		// let voxel_camera_transform_y = Transform {
-		// 	location: cgmath::Point3::new(0., -0., -20.),
-		// 	// rotation: cgmath::Quaternion::from_angle_x(cgmath::Deg(90.)),
-		// 	rotation: cgmath::Quaternion::from_angle_x(cgmath::Deg(90.)),
-		// 	scale: cgmath::Vector3::new(1., 1., 1.),
-		// };
-		// let voxel_camera_transform_z = Transform {
-		// 	location: cgmath::Point3::new(0., -5., -10.),
-		// 	rotation: cgmath::Quaternion::new(1., 0., 0., 0.),
-		// 	scale: cgmath::Vector3::new(1., 1., 1.),
-		// };
-
-		let orthographic = OrthographicProjection::new(1, 1, 40.0, 0., 1000.0);
-
-		// Main camera
-		let projection = PerspectiveProjection::new(self.context.surface_configuration.width, self.context.surface_configuration.height, cgmath::Deg(45.0), 0.1, 50.0);
-		let main_camera = self.scene.root.new_child("Main Camera");
-		main_camera.add_camera_component(&self.context, Projection::Perspective(projection));
-		// main_camera.add_camera_component(&self.context, Projection::Orthographic(orthographic));
-		// main_camera.transform = camera_transform;
-		// main_camera.get_cameras_mut()[0].update_transform_and_matrices(&camera_transform, &mut self.context.queue);
-
-		// Voxel camera
-		let voxel_camera = self.scene.root.new_child("Voxel Camera");
-		voxel_camera.transform = voxel_camera_transform_x;
-		voxel_camera.add_camera_component(&self.context, Projection::Orthographic(orthographic));
-		voxel_camera.get_cameras_mut()[0].update_transform_and_matrices(&voxel_camera_transform_x, &mut self.context.queue);
-
-		// Spinning cube representing the light
-		let lamp = self.scene.root.new_child("Lamp Model");
-
-		let mut lamp_model = Model::new(("cube.obj", "BeveledCube"));
-		lamp_model.instances.instance_list[0].location.y = 4.;
-		lamp_model.instances.update_buffer(&self.context.device);
-		lamp.add_component(Component::Model(lamp_model));
-
-		let light_cube_movement = crate::scripts::light_cube_movement::LightCubeMovement;
-		lamp.add_component(Component::Behavior(Box::new(light_cube_movement)));
+		self.scene.resources = resources;
+
+		// Cinematic flying the camera around the tracked cube, played with P
+		match Timeline::load(assets_path, "flyover.json") {
+			Ok(timeline) => self.sequencer.timeline = Some(Arc::new(timeline)),
+			Err(error) => eprintln!("Couldn't load the timeline: {}", error),
+		}
 
 		// Array of cubes
 		let cubes = self.scene.root.new_child("Cubes");
 
 		let mut cube_model = Model::new(("cube.obj", "BeveledCube"));
+		cube_model.mobility = Mobility::Static;
 
 		const NUM_INSTANCES_PER_ROW: u32 = 10;
 		const SPACE_BETWEEN: f32 = 1.0;
@@ -217,33 +1000,140 @@ impl Engine {
 
 					let scale = cgmath::Vector3 { x: 0.25, y: 0.25, z: 0.25 };
 
-					Instance { location, rotation, scale }
+					Instance {
+						location,
+						rotation,
+						scale,
+						..Instance::new()
+					}
 				})
 			})
 			.collect::<Vec<_>>();
-		cube_model.instances.update_buffer(&self.context.device);
 
-		cubes.add_component(Component::Model(cube_model));
+		cubes.add_component(Component::Model(Box::new(cube_model)));
+
+		// Overlapping tinted and glass panes to show off the transparency modes and refraction
+		let transparent_panes = self.scene.root.new_child("Transparent Panes");
+		for (index, surface) in TRANSPARENT_SURFACES.iter().enumerate() {
+			let pane = transparent_panes.new_child(&format!("{} Pane", surface.name));
+
+			let mut pane_model = Model::new(("cube.obj", "BeveledCube"));
+			pane_model.transparent_surface = Some(String::from(surface.name));
+			pane_model.instances.instance_list[0].location = cgmath::Vector3::new(-1. + index as f32, 1.5, 2. - index as f32 * 0.75);
+			pane_model.instances.instance_list[0].scale = cgmath::Vector3::new(1., 1., 0.05);
+
+			pane.add_component(Component::Model(Box::new(pane_model)));
+		}
+
+		// Two mirrors facing each other across the scene, so each shows the other's reflection nested inside it
+		let mirrors = self.scene.root.new_child("Mirrors");
+		let mirror_placements = [("North Mirror", -7., 0.), ("South Mirror", 7., 180.)];
+		for (name, z, angle) in mirror_placements {
+			let mirror = mirrors.new_child(name);
+			mirror.transform.location = cgmath::Point3::new(0., 2., z);
+			mirror.transform.rotation = cgmath::Quaternion::from_angle_y(cgmath::Deg(angle));
+			mirror.add_component(Component::Mirror(Box::new(Mirror::new(&self.context, cgmath::Vector3::unit_z(), (1024, 576), 2))));
 
-		// Sponza
-		let sponza = self.scene.root.new_child("Sponza");
-		for mesh_name in model_files.get("sponza_pbr.obj").unwrap() {
-			let submesh = sponza.new_child(mesh_name);
+			// The thin box is pushed back so its front face lies on the mirror plane
+			let mut mirror_model = Model::new(("cube.obj", "BeveledCube"));
+			mirror_model.mirror_surface = Some(String::from(name));
+			let location = mirror.transform.location - mirror.transform.rotation * cgmath::Vector3::new(0., 0., 0.02);
+			let scale = cgmath::Point3 { x: 2., y: 1.25, z: 0.02 };
+			mirror_model.instances.transform_single_instance(location, mirror.transform.rotation, scale, &self.context.device);
+			mirror.add_component(Component::Model(Box::new(mirror_model)));
+		}
+
+		// A pair of portals at either end of the courtyard, each leading out of the other
+		let portals = self.scene.root.new_child("Portals");
+		let portal_placements = [("West Portal", "East Portal", -9., 90.), ("East Portal", "West Portal", 9., -90.)];
+		for (name, linked_name, x, angle) in portal_placements {
+			let portal = portals.new_child(name);
+			portal.transform.location = cgmath::Point3::new(x, 1.5, 3.);
+			portal.transform.rotation = cgmath::Quaternion::from_angle_y(cgmath::Deg(angle));
+			portal.add_component(Component::Portal(Box::new(Portal::new(&self.context, linked_name, cgmath::Vector3::unit_z(), (1024, 1024), 2))));
+
+			// The thin box is pushed back so its front face lies on the portal plane
+			let mut portal_model = Model::new(("cube.obj", "BeveledCube"));
+			portal_model.portal_surface = Some(String::from(name));
+			let location = portal.transform.location - portal.transform.rotation * cgmath::Vector3::new(0., 0., 0.02);
+			let scale = cgmath::Point3 { x: 1., y: 1.5, z: 0.02 };
+			portal_model.instances.transform_single_instance(location, portal.transform.rotation, scale, &self.context.device);
+			portal.add_component(Component::Model(Box::new(portal_model)));
+		}
 
-			let mut submesh_model = Model::new(("sponza_pbr.obj", mesh_name));
-			submesh_model.instances.update_buffer(&self.context.device);
+		// Meshes extruded along splines, with a cart riding the pipe's rail
+		let splines = self.scene.root.new_child("Splines");
+		for mesh_name in model_files.get("SPLINE").unwrap() {
+			let spline_model = Model::new(("SPLINE", mesh_name));
+			splines.new_child(mesh_name).add_component(Component::Model(Box::new(spline_model)));
+		}
 
-			submesh.add_component(Component::Model(submesh_model));
+		// Solids built with constructive solid geometry, static so the character controller collides with them
+		let csg_solids = self.scene.root.new_child("CSG Solids");
+		let csg_locations = [cgmath::Vector3::new(-9., 1.2, 0.), cgmath::Vector3::new(0., 0., -9.)];
+		for (mesh_name, location) in model_files.get("CSG").unwrap().iter().zip(csg_locations) {
+			let mut csg_model = Model::new(("CSG", mesh_name));
+			csg_model.mobility = Mobility::Static;
+			csg_model.instances.instance_list[0].location = location;
+			csg_solids.new_child(mesh_name).add_component(Component::Model(Box::new(csg_model)));
 		}
+
+		let spline_cart = self.scene.root.new_child("Spline Cart");
+		let spline_cart_model = Model::new(("cube.obj", "BeveledCube"));
+		spline_cart.add_component(Component::Model(Box::new(spline_cart_model)));
+		spline_cart.add_component(Component::Behavior(Box::new(SplineFollow::new(Arc::new(Self::spline_rail()), 3.))));
 	}
 
-	fn load_resources(&mut self, model_files: &HashMap<String, Vec<String>>, assets_path: &Path) {
+	// Gives every mesh of the model files the maps and materials its models draw with, loading whichever of its textures aren't loaded yet
+	// Done for all the model files once loading finishes, and again for those a scene loaded on top of it brings in
+	fn load_model_materials(&mut self, model_files: &HashMap<String, Vec<String>>, assets_path: &Path) {
 		let mut textures_to_load = HashSet::<(String, wgpu::TextureFormat, wgpu::AddressMode)>::new();
 		let mut model_materials_to_load = Vec::new();
 
-		// Meshes
-		let blit_quad_mesh = Mesh::new_blit_quad(&self.context.device, &self.context.queue);
-		self.scene.resources.meshes.insert((String::from("BLIT"), String::from("QUAD")), blit_quad_mesh);
+		// Maps the MTL files leave out are generated from their scalar values, so every mesh is bound to a full set of maps
+		let mut generated_maps = Vec::new();
+		for (model_name, mesh_names) in model_files {
+			for mesh_name in mesh_names {
+				if let Some(mesh) = self.scene.resources.meshes.get_mut(&(model_name.clone(), mesh_name.clone())) {
+					generated_maps.extend(mesh.fill_in_missing_maps());
+				}
+			}
+		}
+		for generated_map in generated_maps {
+			match generated_map {
+				GeneratedMap::Constant { name, rgba, format } => {
+					if !self.scene.resources.textures.contains_key(&name) {
+						self.scene
+							.resources
+							.add_texture_from_rgba(&self.context, &name, &rgba, (1, 1), format, wgpu::AddressMode::Repeat)
+							.unwrap();
+					}
+				}
+				GeneratedMap::PackedArm {
+					name,
+					roughness,
+					metallic,
+					parameters,
+				} => {
+					if self.scene.resources.textures.contains_key(&name) {
+						continue;
+					}
+
+					let mut texture = Texture::load_packed_arm(
+						&self.context.device,
+						&self.context.queue,
+						assets_path,
+						&name,
+						(roughness.as_deref(), parameters.roughness),
+						(metallic.as_deref(), parameters.metallic),
+						self.render_settings.profile.skipped_texture_mips(),
+					)
+					.unwrap_or_else(|_| panic!("Can't load the roughness and metalness maps packed into: {}", name));
+					texture.generate_mipmaps(&self.context);
+					self.scene.resources.textures.insert(name, texture);
+				}
+			}
+		}
 
 		for (model_name, mesh_names) in model_files {
 			for mesh_name in mesh_names {
@@ -255,72 +1145,191 @@ impl Engine {
 
 				for mesh in meshes {
 					// Mark the PBR textures to be loaded
-					if let Some(texture) = &mesh.map_albedo {
-						textures_to_load.insert((texture.clone(), wgpu::TextureFormat::Rgba8UnormSrgb, wgpu::AddressMode::Repeat));
-					}
-					if let Some(texture) = &mesh.map_arm {
-						textures_to_load.insert((texture.clone(), wgpu::TextureFormat::Rgba8Unorm, wgpu::AddressMode::Repeat));
-					}
-					if let Some(texture) = &mesh.map_normal {
-						textures_to_load.insert((texture.clone(), wgpu::TextureFormat::Rgba8Unorm, wgpu::AddressMode::Repeat));
-					}
+					textures_to_load.extend(mesh.map_textures());
 
-					// Prepare the material using those textures
-					model_materials_to_load.push((
-						format!("scene_deferred_{}.material", mesh.name.as_str()),
-						"scene_deferred.wgsl",
-						vec![mesh.map_albedo.clone(), mesh.map_arm.clone(), mesh.map_normal.clone(), Some(String::from("VOXEL_LIGHTMAP_TEXTURE"))]
-							.into_iter()
-							.flatten()
-							.collect::<Vec<_>>(),
-					));
+					// Only the PNG albedo maps have an alpha channel, which marks the cutout materials like Sponza's plants, vines, and chains
+					let cutout = mesh.map_albedo.as_ref().is_some_and(|albedo| albedo.ends_with(".png"));
+
+					// The materials are made from their templates once the textures are loaded
 					model_materials_to_load.push((
-						format!("calc_voxel_lightmap_{}.material", mesh.name.as_str()),
-						"calc_voxel_lightmap.wgsl",
-						vec![Some(String::from("VOXEL_CAMERA_MATRICES")), mesh.map_albedo.clone(), Some(String::from("VOXEL_LIGHTMAP"))]
-							.into_iter()
-							.flatten()
-							.collect::<Vec<_>>(),
+						(model_name.clone(), mesh_name.clone()),
+						mesh.name.clone(),
+						(cutout, mesh.material_parameters.two_sided),
+						[mesh.map_albedo.clone(), mesh.map_arm.clone(), mesh.map_normal.clone(), mesh.map_emissive.clone()],
 					));
 				}
 			}
 		}
 
-		// Shaders
-		let main_camera = self.scene.root.find_descendant("Main Camera").unwrap().get_cameras()[0];
-		let voxel_camera_x = self.scene.root.find_descendant("Voxel Camera").unwrap().get_cameras()[0];
-
-		let calc_voxel_lightmap_shader = {
-			let camera_matrices = ShaderBinding::Buffer(ShaderBindingBuffer::default());
-			let albedo_map = ShaderBinding::Texture(ShaderBindingTexture::default());
-			let voxel_lightmap_binding = ShaderBinding::Buffer(ShaderBindingBuffer {
-				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: false },
-				visible_in_stages: wgpu::ShaderStages::FRAGMENT,
-				..ShaderBindingBuffer::default()
-			});
+		// The model files' textures loaded at startup were already decoded in the background, and those generated at runtime are uploaded under the name the meshes use
+		// That leaves the textures of the meshes built at runtime, which weren't known until the model files had loaded, and those of model files loaded later on, so they're decoded in parallel here
+		let textures_to_load = textures_to_load
+			.into_iter()
+			.filter(|(file, _, _)| !self.scene.resources.textures.contains_key(file))
+			.collect::<Vec<_>>();
+		let skipped_mips = self.render_settings.profile.skipped_texture_mips();
+		let decoded_textures = textures_to_load
+			.into_par_iter()
+			.map(|(file, format, repeat_mode)| {
+				let image = Texture::decode(assets_path, &file, skipped_mips).unwrap_or_else(|_| panic!("Can't load texture file: {}", file));
+				(file, format, repeat_mode, image)
+			})
+			.collect::<Vec<_>>();
+		for (file, format, repeat_mode, image) in decoded_textures {
+			let mut loaded_texture = Texture::from_image(&self.context.device, &self.context.queue, &image, &file, format, repeat_mode);
+			loaded_texture.generate_mipmaps(&self.context);
+			self.scene.resources.textures.insert(file, loaded_texture);
+		}
 
-			Shader::new(
-				&self.context,
-				assets_path,
-				"calc_voxel_lightmap.wgsl",
-				vec![camera_matrices, albedo_map, voxel_lightmap_binding],
-				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+		// Every model gets an instance of each of these, which only binds the model's own maps and morph targets over the bindings shared by all of them
+		let voxel_lightmap_binding = MaterialDataBinding::Buffer(BufferBinding {
+			buffer: &self.voxel_storage_buffer,
+			offset: 0,
+			size: None,
+		});
+		let voxel_update_region_binding = MaterialDataBinding::Buffer(BufferBinding {
+			buffer: &self.voxel_update_scheduler.region_buffer,
+			offset: 0,
+			size: None,
+		});
+		let voxel_camera = self.scene.root.find_descendant("Voxel Camera").unwrap().get_cameras()[0];
+		let voxel_camera_binding = MaterialDataBinding::Buffer(BufferBinding {
+			buffer: &voxel_camera.camera_buffer,
+			offset: 0,
+			size: None,
+		});
+		let scene_deferred_template = MaterialTemplate::new(
+			"scene_deferred.wgsl",
+			vec![
+				None,
+				None,
+				None,
+				None,
+				Some(MaterialDataBinding::Texture(&self.voxel_light_map.texture)),
+				None,
+				Some(MaterialDataBinding::MaterialParams),
+			],
+		);
+		let scene_deferred_variant_template = |shader_name| MaterialTemplate {
+			shader_name,
+			..scene_deferred_template.clone()
+		};
+		let scene_deferred_cutout_template = scene_deferred_variant_template("scene_deferred_cutout");
+		let scene_deferred_two_sided_template = scene_deferred_variant_template("scene_deferred_two_sided");
+		let scene_deferred_cutout_two_sided_template = scene_deferred_variant_template("scene_deferred_cutout_two_sided");
+		let scene_shadow_template = MaterialTemplate::new("scene_shadow.wgsl", vec![None]);
+		let scene_shadow_two_sided_template = MaterialTemplate::new("scene_shadow_two_sided", vec![None]);
+		let scene_reflection_template = MaterialTemplate::new("scene_reflection.wgsl", vec![None]);
+		let scene_forward_template = MaterialTemplate::new("scene_forward", vec![None]);
+		let calc_voxel_lightmap_template = MaterialTemplate::new(
+			"calc_voxel_lightmap.wgsl",
+			vec![Some(voxel_camera_binding), None, Some(voxel_lightmap_binding), Some(voxel_update_region_binding)],
+		);
+
+		for (mesh_key, mesh_name, (cutout, two_sided), maps) in &model_materials_to_load {
+			let map_overrides = maps.iter().enumerate().filter_map(|(index, map)| Some((index, MaterialDataBinding::TextureName(map.as_deref()?))));
+			let albedo_override = || maps[0].as_deref().map(MaterialDataBinding::TextureName);
+			let morph_targets_binding = MaterialDataBinding::Buffer(BufferBinding {
+				buffer: &self.scene.resources.meshes[mesh_key].morph_targets.buffer,
+				offset: 0,
+				size: None,
+			});
+
+			let scene_deferred = match (cutout, two_sided) {
+				(false, false) => &scene_deferred_template,
+				(true, false) => &scene_deferred_cutout_template,
+				(false, true) => &scene_deferred_two_sided_template,
+				(true, true) => &scene_deferred_cutout_two_sided_template,
+			};
+			let scene_shadow = if *two_sided { &scene_shadow_two_sided_template } else { &scene_shadow_template };
+			let materials: [(String, &MaterialTemplate, BindingOverrides); 5] = [
+				(
+					format!("scene_deferred_{}.material", mesh_name),
+					scene_deferred,
+					map_overrides.chain([(5, morph_targets_binding)]).collect(),
+				),
+				(
+					format!("scene_shadow_{}.material", mesh_name),
+					scene_shadow,
+					albedo_override().map(|albedo| (0, albedo)).into_iter().collect(),
+				),
+				(
+					format!("scene_reflection_{}.material", mesh_name),
+					&scene_reflection_template,
+					albedo_override().map(|albedo| (0, albedo)).into_iter().collect(),
+				),
+				(
+					format!("scene_forward_{}.material", mesh_name),
+					&scene_forward_template,
+					albedo_override().map(|albedo| (0, albedo)).into_iter().collect(),
+				),
+				(
+					format!("calc_voxel_lightmap_{}.material", mesh_name),
+					&calc_voxel_lightmap_template,
+					albedo_override().map(|albedo| (1, albedo)).into_iter().collect(),
+				),
+			];
+			for (material_name, template, overrides) in materials {
+				let material = template.instantiate(&material_name, overrides, &self.scene.resources, &self.context.device);
+				self.scene.resources.materials.insert(material_name, material);
+			}
+		}
+	}
+
+	fn load_resources(&mut self, model_files: &HashMap<String, Vec<String>>, assets_path: &Path) {
+		// Meshes
+		let blit_quad_mesh = Mesh::new_blit_quad(&self.context.device, &self.context.queue);
+		self.scene.resources.meshes.insert((String::from("BLIT"), String::from("QUAD")), blit_quad_mesh);
+
+		// Uploaded before the shaders since they hold a borrow of the scene's cameras until the end of loading
+		self.upload_distance_field();
+
+		// Shaders, starting with those drawing straight to the surface, which are rebuilt if its format changes
+		self.load_surface_shaders(assets_path);
+
+		let main_camera = self.scene.root.find_descendant("Main Camera").unwrap().get_cameras()[0];
+
+		let calc_voxel_lightmap_shader = {
+			let camera_matrices = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let albedo_map = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let voxel_lightmap_binding = ShaderBinding::Buffer(ShaderBindingBuffer {
+				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: false },
+				visible_in_stages: wgpu::ShaderStages::FRAGMENT,
+				..ShaderBindingBuffer::default()
+			});
+			let update_region = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"calc_voxel_lightmap.wgsl",
+				vec![camera_matrices, albedo_map, voxel_lightmap_binding, update_region],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
 					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
 					depth_format: None,
+					vertex_format: VertexFormat::Packed,
 					use_instances: true,
 					scene_camera: None,
 					scene_lighting: Some(&self.scene_lighting),
-					scene_debug_buffer: None,
-					blend: true,
+					blend: Some(wgpu::BlendState::REPLACE),
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: true,
+					depth_bias: wgpu::DepthBiasState::default(),
 				}),
 			)
 		};
 		self.scene.resources.shaders.insert(calc_voxel_lightmap_shader.name.clone(), calc_voxel_lightmap_shader);
 
-		let scene_deferred_shader = {
+		// Cutout materials use a variant of the pipeline with alpha to coverage, so their edges anti-alias once the G-buffer is multisampled
+		// Two-sided materials use a variant without back-face culling, whose back faces get their normals flipped by the shader to face the camera
+		let create_scene_deferred_shader = |alpha_to_coverage: bool, two_sided: bool| {
 			let albedo_map = ShaderBinding::Texture(ShaderBindingTexture::default()); // Albedo map
 			let arm_map = ShaderBinding::Texture(ShaderBindingTexture::default()); // AO/Roughness/Metalness map
 			let normal_map = ShaderBinding::Texture(ShaderBindingTexture::default()); // Normal map
+			let emissive_map = ShaderBinding::Texture(ShaderBindingTexture::default()); // Emissive map
 			let voxel_light_map_binding = {
 				let binding_tex = ShaderBindingTexture {
 					dimensions: wgpu::TextureViewDimension::D3,
@@ -328,12 +1337,21 @@ impl Engine {
 				};
 				ShaderBinding::Texture(binding_tex)
 			};
+			let morph_targets = ShaderBinding::Buffer(ShaderBindingBuffer {
+				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: true },
+				visible_in_stages: wgpu::ShaderStages::VERTEX,
+				..ShaderBindingBuffer::default()
+			});
+			let material_params = ShaderBinding::Buffer(ShaderBindingBuffer {
+				visible_in_stages: wgpu::ShaderStages::FRAGMENT,
+				..ShaderBindingBuffer::default()
+			});
 
 			Shader::new(
 				&self.context,
 				assets_path,
 				"scene_deferred.wgsl",
-				vec![albedo_map, arm_map, normal_map, voxel_light_map_binding],
+				vec![albedo_map, arm_map, normal_map, emissive_map, voxel_light_map_binding, morph_targets, material_params],
 				// vec![albedo_map, arm_map, normal_map],
 				PipelineOptions::RenderPipeline(RenderPipelineOptions {
 					out_color_formats: vec![
@@ -341,17 +1359,301 @@ impl Engine {
 						// wgpu::TextureFormat::Rgba16Float,
 						wgpu::TextureFormat::Bgra8UnormSrgb,
 						wgpu::TextureFormat::Bgra8Unorm,
+						wgpu::TextureFormat::Rgba16Float,
+						wgpu::TextureFormat::Rg16Float,
 					],
 					depth_format: Some(wgpu::TextureFormat::Depth32Float),
+					vertex_format: VertexFormat::Model,
 					use_instances: true,
 					scene_camera: Some(main_camera),
 					scene_lighting: Some(&self.scene_lighting),
-					scene_debug_buffer: Some(&self.debug_buffer),
-					blend: true,
+					blend: Some(wgpu::BlendState::REPLACE),
+					depth_write: true,
+					cull_mode: if two_sided { None } else { Some(wgpu::Face::Back) },
+					sample_count: 1,
+					alpha_to_coverage,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
 				}),
 			)
 		};
+		let scene_deferred_shader = create_scene_deferred_shader(false, false);
+		let scene_deferred_cutout_shader = create_scene_deferred_shader(true, false);
+		let scene_deferred_two_sided_shader = create_scene_deferred_shader(false, true);
+		let scene_deferred_cutout_two_sided_shader = create_scene_deferred_shader(true, true);
 		self.scene.resources.shaders.insert(scene_deferred_shader.name.clone(), scene_deferred_shader);
+		self.scene.resources.shaders.insert(String::from("scene_deferred_cutout"), scene_deferred_cutout_shader);
+		self.scene.resources.shaders.insert(String::from("scene_deferred_two_sided"), scene_deferred_two_sided_shader);
+		self.scene
+			.resources
+			.shaders
+			.insert(String::from("scene_deferred_cutout_two_sided"), scene_deferred_cutout_two_sided_shader);
+
+		let scene_reflection_shader = {
+			let albedo_map = ShaderBinding::Texture(ShaderBindingTexture::default());
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"scene_reflection.wgsl",
+				vec![albedo_map],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
+					depth_format: Some(wgpu::TextureFormat::Depth32Float),
+					vertex_format: VertexFormat::Model,
+					use_instances: true,
+					scene_camera: Some(main_camera),
+					scene_lighting: Some(&self.scene_lighting),
+					blend: Some(wgpu::BlendState::REPLACE),
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Front),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(scene_reflection_shader.name.clone(), scene_reflection_shader);
+
+		// Render layers and portal views use the same forward shading as reflections, but seen directly rather than mirrored so they cull the back faces
+		let scene_forward_shader = {
+			let albedo_map = ShaderBinding::Texture(ShaderBindingTexture::default());
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"scene_reflection.wgsl",
+				vec![albedo_map],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
+					depth_format: Some(wgpu::TextureFormat::Depth32Float),
+					vertex_format: VertexFormat::Model,
+					use_instances: true,
+					scene_camera: Some(main_camera),
+					scene_lighting: Some(&self.scene_lighting),
+					blend: Some(wgpu::BlendState::REPLACE),
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(String::from("scene_forward"), scene_forward_shader);
+
+		let scene_mirror_shader = {
+			let reflection_camera = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let reflection_map = ShaderBinding::Texture(ShaderBindingTexture::default());
+
+			// Mirrors are drawn both in the main view and inside each other's reflections, so they cull nothing to work with either winding order
+			Shader::new(
+				&self.context,
+				assets_path,
+				"scene_mirror.wgsl",
+				vec![reflection_camera, reflection_map],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
+					depth_format: Some(wgpu::TextureFormat::Depth32Float),
+					vertex_format: VertexFormat::Model,
+					use_instances: true,
+					scene_camera: Some(main_camera),
+					scene_lighting: None,
+					blend: Some(wgpu::BlendState::REPLACE),
+					depth_write: true,
+					cull_mode: None,
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(scene_mirror_shader.name.clone(), scene_mirror_shader);
+
+		let scene_portal_shader = {
+			let portal_map = ShaderBinding::Texture(ShaderBindingTexture::default());
+
+			// Portals are drawn both in the main view and inside each other's views, and a flat quad seen from behind is simply skipped by its camera, so nothing is culled
+			Shader::new(
+				&self.context,
+				assets_path,
+				"scene_portal.wgsl",
+				vec![portal_map],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
+					depth_format: Some(wgpu::TextureFormat::Depth32Float),
+					vertex_format: VertexFormat::Model,
+					use_instances: true,
+					scene_camera: Some(main_camera),
+					scene_lighting: None,
+					blend: Some(wgpu::BlendState::REPLACE),
+					depth_write: true,
+					cull_mode: None,
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(scene_portal_shader.name.clone(), scene_portal_shader);
+
+		let scene_impostor_shader = {
+			let impostor = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let normal_atlas = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let albedo_atlas = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let arm_atlas = ShaderBinding::Texture(ShaderBindingTexture::default());
+
+			// The billboard always faces the camera, so it has no back to cull
+			Shader::new(
+				&self.context,
+				assets_path,
+				"scene_impostor.wgsl",
+				vec![impostor, normal_atlas, albedo_atlas, arm_atlas],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![
+						wgpu::TextureFormat::Rgba16Float,
+						wgpu::TextureFormat::Bgra8UnormSrgb,
+						wgpu::TextureFormat::Bgra8Unorm,
+						wgpu::TextureFormat::Rgba16Float,
+						wgpu::TextureFormat::Rg16Float,
+					],
+					depth_format: Some(wgpu::TextureFormat::Depth32Float),
+					vertex_format: VertexFormat::PositionOnly,
+					use_instances: true,
+					scene_camera: Some(main_camera),
+					scene_lighting: None,
+					blend: Some(wgpu::BlendState::REPLACE),
+					depth_write: true,
+					cull_mode: None,
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(scene_impostor_shader.name.clone(), scene_impostor_shader);
+
+		let scene_transparent_sorted_shader = {
+			let surface = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"scene_transparent_sorted.wgsl",
+				vec![surface],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
+					depth_format: Some(wgpu::TextureFormat::Depth32Float),
+					vertex_format: VertexFormat::Model,
+					use_instances: true,
+					scene_camera: Some(main_camera),
+					scene_lighting: Some(&self.scene_lighting),
+					blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+					depth_write: false,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(scene_transparent_sorted_shader.name.clone(), scene_transparent_sorted_shader);
+
+		let scene_precipitation_shader = {
+			let weather = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"scene_precipitation.wgsl",
+				vec![weather],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
+					depth_format: Some(wgpu::TextureFormat::Depth32Float),
+					vertex_format: VertexFormat::PositionOnly,
+					use_instances: false,
+					scene_camera: Some(main_camera),
+					scene_lighting: Some(&self.scene_lighting),
+					blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+					depth_write: false,
+					cull_mode: None,
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(scene_precipitation_shader.name.clone(), scene_precipitation_shader);
+
+		let scene_transparent_weighted_shader = {
+			let surface = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+
+			// Both the weighted colors and the coverage are plain sums, so every layer can be added in any order
+			let additive = wgpu::BlendComponent {
+				src_factor: wgpu::BlendFactor::One,
+				dst_factor: wgpu::BlendFactor::One,
+				operation: wgpu::BlendOperation::Add,
+			};
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"scene_transparent_weighted.wgsl",
+				vec![surface],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float, wgpu::TextureFormat::R16Float],
+					depth_format: Some(wgpu::TextureFormat::Depth32Float),
+					vertex_format: VertexFormat::Model,
+					use_instances: true,
+					scene_camera: Some(main_camera),
+					scene_lighting: Some(&self.scene_lighting),
+					blend: Some(wgpu::BlendState { color: additive, alpha: additive }),
+					depth_write: false,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(scene_transparent_weighted_shader.name.clone(), scene_transparent_weighted_shader);
+
+		let scene_transparent_glass_shader = {
+			let surface = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let scene_color = ShaderBinding::Texture(ShaderBindingTexture::default());
+
+			// Glass is opaque to the rest of the frame since it already contains the scene behind it
+			Shader::new(
+				&self.context,
+				assets_path,
+				"scene_transparent_glass.wgsl",
+				vec![surface, scene_color],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
+					depth_format: Some(wgpu::TextureFormat::Depth32Float),
+					vertex_format: VertexFormat::Model,
+					use_instances: true,
+					scene_camera: Some(main_camera),
+					scene_lighting: Some(&self.scene_lighting),
+					blend: None,
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(scene_transparent_glass_shader.name.clone(), scene_transparent_glass_shader);
 
 		let pass_ssao_kernel_shader = {
 			let samples_array = ShaderBinding::Buffer(ShaderBindingBuffer::default());
@@ -361,25 +1663,63 @@ impl Engine {
 				..ShaderBindingTexture::default()
 			});
 			let world_space_normal = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let ssao_temporal = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let velocity = ShaderBinding::Texture(ShaderBindingTexture::default());
 
 			Shader::new(
 				&self.context,
 				assets_path,
 				"pass_ssao_kernel.wgsl",
-				vec![samples_array, ssao_noise_texture, z_buffer, world_space_normal],
+				vec![samples_array, ssao_noise_texture, z_buffer, world_space_normal, ssao_temporal, velocity],
 				PipelineOptions::RenderPipeline(RenderPipelineOptions {
 					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
 					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
 					use_instances: false,
 					scene_camera: Some(main_camera),
 					scene_lighting: None,
-					scene_debug_buffer: None,
-					blend: true,
+					blend: Some(wgpu::BlendState::REPLACE),
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
 				}),
 			)
 		};
 		self.scene.resources.shaders.insert(pass_ssao_kernel_shader.name.clone(), pass_ssao_kernel_shader);
 
+		let pass_ssao_temporal_shader = {
+			let ssao_temporal = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let ssao_kernel = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let ssao_history = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let velocity = ShaderBinding::Texture(ShaderBindingTexture::default());
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"pass_ssao_temporal.wgsl",
+				vec![ssao_temporal, ssao_kernel, ssao_history, velocity],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
+					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
+					use_instances: false,
+					scene_camera: None,
+					scene_lighting: None,
+					blend: Some(wgpu::BlendState::REPLACE),
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(pass_ssao_temporal_shader.name.clone(), pass_ssao_temporal_shader);
+
 		let pass_ssao_blurred_shader = {
 			let ssao_kernel = ShaderBinding::Texture(ShaderBindingTexture::default());
 
@@ -391,11 +1731,17 @@ impl Engine {
 				PipelineOptions::RenderPipeline(RenderPipelineOptions {
 					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
 					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
 					use_instances: false,
 					scene_camera: None,
 					scene_lighting: None,
-					scene_debug_buffer: None,
-					blend: true,
+					blend: Some(wgpu::BlendState::REPLACE),
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
 				}),
 			)
 		};
@@ -409,31 +1755,96 @@ impl Engine {
 			let world_space_normal = ShaderBinding::Texture(ShaderBindingTexture::default());
 			let albedo_map = ShaderBinding::Texture(ShaderBindingTexture::default());
 			let arm_map = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let emissive_map = ShaderBinding::Texture(ShaderBindingTexture::default());
 			let ssao_blurred_map = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let foveation_settings = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let distance_field = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let distance_field_map = ShaderBinding::Texture(ShaderBindingTexture {
+				dimensions: wgpu::TextureViewDimension::D3,
+				..ShaderBindingTexture::default()
+			});
+			let weather = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let weather_noise = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let shadows = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let shadow_cascades = ShaderBinding::Texture(ShaderBindingTexture {
+				dimensions: wgpu::TextureViewDimension::D2Array,
+				sampled_value_data_type: wgpu::TextureSampleType::Depth,
+				comparison: true,
+				..ShaderBindingTexture::default()
+			});
+			let shadow_moments = ShaderBinding::Texture(ShaderBindingTexture {
+				sampled_value_data_type: wgpu::TextureSampleType::Float { filterable: false },
+				..ShaderBindingTexture::default()
+			});
+			let environment = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let point_shadows = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let point_shadow_cube = ShaderBinding::Texture(ShaderBindingTexture {
+				dimensions: wgpu::TextureViewDimension::D2Array,
+				sampled_value_data_type: wgpu::TextureSampleType::Depth,
+				comparison: true,
+				..ShaderBindingTexture::default()
+			});
+			let irradiance_map = ShaderBinding::Texture(ShaderBindingTexture {
+				dimensions: wgpu::TextureViewDimension::Cube,
+				..ShaderBindingTexture::default()
+			});
+			let prefiltered_map = ShaderBinding::Texture(ShaderBindingTexture {
+				dimensions: wgpu::TextureViewDimension::Cube,
+				..ShaderBindingTexture::default()
+			});
+			let brdf_lut = ShaderBinding::Texture(ShaderBindingTexture::default());
 
 			Shader::new(
 				&self.context,
 				assets_path,
 				"pass_pbr_shading.wgsl",
-				vec![z_buffer, world_space_normal, albedo_map, arm_map, ssao_blurred_map],
+				vec![
+					z_buffer,
+					world_space_normal,
+					albedo_map,
+					arm_map,
+					ssao_blurred_map,
+					foveation_settings,
+					distance_field,
+					distance_field_map,
+					weather,
+					weather_noise,
+					shadows,
+					shadow_cascades,
+					emissive_map,
+					shadow_moments,
+					environment,
+					point_shadows,
+					point_shadow_cube,
+					irradiance_map,
+					prefiltered_map,
+					brdf_lut,
+				],
 				PipelineOptions::RenderPipeline(RenderPipelineOptions {
 					// out_color_formats: vec![self.context.surface_configuration.format],
 					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
 					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
 					use_instances: false,
 					scene_camera: Some(main_camera),
 					scene_lighting: Some(&self.scene_lighting),
-					scene_debug_buffer: None,
-					blend: true,
+					blend: Some(wgpu::BlendState::REPLACE),
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
 				}),
 			)
 		};
 		self.scene.resources.shaders.insert(pass_pbr_shading_shader.name.clone(), pass_pbr_shading_shader);
 
-		let pass_motion_blur_shader = {
-			let pbr_shaded = ShaderBinding::Texture(ShaderBindingTexture::default());
-			let z_buffer_previous = ShaderBinding::Texture(ShaderBindingTexture {
-				sampled_value_data_type: wgpu::TextureSampleType::Depth,
+		// The sky map is drawn behind the shaded scene wherever the deferred pass left the depth clear
+		let pass_skybox_shader = {
+			let environment = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let sky_map = ShaderBinding::Texture(ShaderBindingTexture {
+				dimensions: wgpu::TextureViewDimension::Cube,
 				..ShaderBindingTexture::default()
 			});
 			let z_buffer = ShaderBinding::Texture(ShaderBindingTexture {
@@ -444,75 +1855,515 @@ impl Engine {
 			Shader::new(
 				&self.context,
 				assets_path,
-				"pass_motion_blur.wgsl",
-				vec![pbr_shaded, z_buffer_previous, z_buffer],
+				"pass_skybox.wgsl",
+				vec![environment, sky_map, z_buffer],
 				PipelineOptions::RenderPipeline(RenderPipelineOptions {
 					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
-					// out_color_formats: vec![self.context.surface_configuration.format],
 					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
 					use_instances: false,
 					scene_camera: Some(main_camera),
 					scene_lighting: None,
-					scene_debug_buffer: None,
-					blend: false,
+					blend: Some(wgpu::BlendState::REPLACE),
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
 				}),
 			)
 		};
-		self.scene.resources.shaders.insert(pass_motion_blur_shader.name.clone(), pass_motion_blur_shader);
+		self.scene.resources.shaders.insert(pass_skybox_shader.name.clone(), pass_skybox_shader);
 
-		let voxel_texture_generating_shader = {
-			let voxel_lightmap_binding = {
-				let mut binding_tex = ShaderBindingTexture {
-					visible_in_stages: wgpu::ShaderStages::COMPUTE,
-					..ShaderBindingTexture::default()
-				};
-				binding_tex.dimensions = wgpu::TextureViewDimension::D3;
-				ShaderBinding::StorageTexture(binding_tex, wgpu::TextureFormat::Rgba8Unorm)
-			};
-			let voxel_buffer_binding = ShaderBinding::Buffer(ShaderBindingBuffer {
-				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: false },
-				visible_in_stages: wgpu::ShaderStages::COMPUTE,
-				..ShaderBindingBuffer::default()
+		// The moments and their blur are written as 32 bit floats, which can't be blended
+		let shadow_moments_pipeline_options = || {
+			PipelineOptions::RenderPipeline(RenderPipelineOptions {
+				out_color_formats: vec![wgpu::TextureFormat::Rg32Float],
+				depth_format: None,
+				vertex_format: VertexFormat::PositionOnly,
+				use_instances: false,
+				scene_camera: None,
+				scene_lighting: None,
+				blend: None,
+				depth_write: true,
+				cull_mode: Some(wgpu::Face::Back),
+				sample_count: 1,
+				alpha_to_coverage: false,
+				conservative_rasterization: false,
+				depth_bias: wgpu::DepthBiasState::default(),
+			})
+		};
+
+		let pass_shadow_moments_shader = {
+			let shadows = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let shadow_cascades = ShaderBinding::Texture(ShaderBindingTexture {
+				dimensions: wgpu::TextureViewDimension::D2Array,
+				sampled_value_data_type: wgpu::TextureSampleType::Depth,
+				..ShaderBindingTexture::default()
 			});
 
 			Shader::new(
 				&self.context,
 				assets_path,
-				"compute_voxel_texture_generating.wgsl",
-				vec![voxel_lightmap_binding, voxel_buffer_binding],
-				PipelineOptions::ComputePipeline(ComputePipelineOptions {}),
+				"pass_shadow_moments.wgsl",
+				vec![shadows, shadow_cascades],
+				shadow_moments_pipeline_options(),
 			)
 		};
-		self.scene.resources.shaders.insert(voxel_texture_generating_shader.name.clone(), voxel_texture_generating_shader);
+		self.scene.resources.shaders.insert(pass_shadow_moments_shader.name.clone(), pass_shadow_moments_shader);
 
-		let pass_hdr_exposure_shader = {
-			let pbr_shaded = ShaderBinding::Texture(ShaderBindingTexture::default());
+		let pass_shadow_blur_shader = {
+			let shadows = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let blur_direction = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let moments = ShaderBinding::Texture(ShaderBindingTexture {
+				sampled_value_data_type: wgpu::TextureSampleType::Float { filterable: false },
+				..ShaderBindingTexture::default()
+			});
 
 			Shader::new(
 				&self.context,
 				assets_path,
-				"pass_hdr_exposure.wgsl",
-				vec![pbr_shaded],
+				"pass_shadow_blur.wgsl",
+				vec![shadows, blur_direction, moments],
+				shadow_moments_pipeline_options(),
+			)
+		};
+		self.scene.resources.shaders.insert(pass_shadow_blur_shader.name.clone(), pass_shadow_blur_shader);
+
+		let pass_foveation_fill_shader = {
+			let foveation_settings = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let shaded = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let history = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let velocity = ShaderBinding::Texture(ShaderBindingTexture::default());
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"pass_foveation_fill.wgsl",
+				vec![foveation_settings, shaded, history, velocity],
 				PipelineOptions::RenderPipeline(RenderPipelineOptions {
-					out_color_formats: vec![self.context.surface_configuration.format],
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
 					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
 					use_instances: false,
 					scene_camera: None,
 					scene_lighting: None,
-					scene_debug_buffer: None,
-					blend: true,
+					blend: None,
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
 				}),
 			)
 		};
-		self.scene.resources.shaders.insert(String::from("pass_hdr_exposure.wgsl"), pass_hdr_exposure_shader);
+		self.scene.resources.shaders.insert(pass_foveation_fill_shader.name.clone(), pass_foveation_fill_shader);
 
-		// Textures
-		self.scene.resources.textures.insert(
-			String::from("SSAO_NOISE"),
-			Texture::from_f16_array(
-				&self.context.device,
-				&self.context.queue,
-				crate::ssao::generate_noise_texture().as_slice(),
+		let pass_motion_blur_shader = {
+			let pbr_shaded = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let velocity = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let z_buffer = ShaderBinding::Texture(ShaderBindingTexture {
+				sampled_value_data_type: wgpu::TextureSampleType::Depth,
+				..ShaderBindingTexture::default()
+			});
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"pass_motion_blur.wgsl",
+				vec![pbr_shaded, velocity, z_buffer],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
+					// out_color_formats: vec![self.context.surface_configuration.format],
+					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
+					use_instances: false,
+					scene_camera: Some(main_camera),
+					scene_lighting: None,
+					blend: None,
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(pass_motion_blur_shader.name.clone(), pass_motion_blur_shader);
+
+		let pass_taa_resolve_shader = {
+			let taa_settings = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let pbr_shaded = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let taa_history = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let velocity = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let z_buffer = ShaderBinding::Texture(ShaderBindingTexture {
+				sampled_value_data_type: wgpu::TextureSampleType::Depth,
+				..ShaderBindingTexture::default()
+			});
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"pass_taa_resolve.wgsl",
+				vec![taa_settings, pbr_shaded, taa_history, velocity, z_buffer],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
+					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
+					use_instances: false,
+					scene_camera: Some(main_camera),
+					scene_lighting: None,
+					blend: None,
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(pass_taa_resolve_shader.name.clone(), pass_taa_resolve_shader);
+
+		let voxel_texture_generating_shader = {
+			let voxel_lightmap_binding = {
+				let mut binding_tex = ShaderBindingTexture {
+					visible_in_stages: wgpu::ShaderStages::COMPUTE,
+					..ShaderBindingTexture::default()
+				};
+				binding_tex.dimensions = wgpu::TextureViewDimension::D3;
+				ShaderBinding::StorageTexture(binding_tex, wgpu::TextureFormat::Rgba8Unorm)
+			};
+			let voxel_buffer_binding = ShaderBinding::Buffer(ShaderBindingBuffer {
+				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: false },
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+			let update_region_binding = ShaderBinding::Buffer(ShaderBindingBuffer {
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"compute_voxel_texture_generating.wgsl",
+				vec![voxel_lightmap_binding, voxel_buffer_binding, update_region_binding],
+				PipelineOptions::ComputePipeline(ComputePipelineOptions {}),
+			)
+		};
+		self.scene.resources.shaders.insert(voxel_texture_generating_shader.name.clone(), voxel_texture_generating_shader);
+
+		let meshlet_culling_shader = {
+			let camera_binding = ShaderBinding::Buffer(ShaderBindingBuffer {
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+			let hi_z_binding = ShaderBinding::Texture(ShaderBindingTexture {
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				sampled_value_data_type: wgpu::TextureSampleType::Float { filterable: false },
+				..ShaderBindingTexture::default()
+			});
+			let settings_binding = ShaderBinding::Buffer(ShaderBindingBuffer {
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+			let meshlets_binding = ShaderBinding::Buffer(ShaderBindingBuffer {
+				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: true },
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+			let draw_commands_binding = ShaderBinding::Buffer(ShaderBindingBuffer {
+				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: false },
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"compute_meshlet_culling.wgsl",
+				vec![camera_binding, hi_z_binding, settings_binding, meshlets_binding, draw_commands_binding],
+				PipelineOptions::ComputePipeline(ComputePipelineOptions {}),
+			)
+		};
+		self.scene.resources.shaders.insert(meshlet_culling_shader.name.clone(), meshlet_culling_shader);
+
+		let scatter_culling_shader = {
+			let camera_binding = ShaderBinding::Buffer(ShaderBindingBuffer {
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+			let settings_binding = ShaderBinding::Buffer(ShaderBindingBuffer {
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+			let instances_binding = ShaderBinding::Buffer(ShaderBindingBuffer {
+				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: true },
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+			let draw_commands_binding = ShaderBinding::Buffer(ShaderBindingBuffer {
+				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: false },
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+			let visible_instances_binding = ShaderBinding::Buffer(ShaderBindingBuffer {
+				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: false },
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"compute_scatter_culling.wgsl",
+				vec![camera_binding, settings_binding, instances_binding, draw_commands_binding, visible_instances_binding],
+				PipelineOptions::ComputePipeline(ComputePipelineOptions {}),
+			)
+		};
+		self.scene.resources.shaders.insert(scatter_culling_shader.name.clone(), scatter_culling_shader);
+
+		let pass_transparent_composite_shader = {
+			let accumulation = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let coverage = ShaderBinding::Texture(ShaderBindingTexture::default());
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"pass_transparent_composite.wgsl",
+				vec![accumulation, coverage],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
+					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
+					use_instances: false,
+					scene_camera: None,
+					scene_lighting: None,
+					blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(String::from("pass_transparent_composite.wgsl"), pass_transparent_composite_shader);
+
+		// Blend states are baked into pipelines, so each blend mode gets its own variant of the composite shader
+		for blend in LayerBlend::ALL {
+			let layer = ShaderBinding::Texture(ShaderBindingTexture::default());
+
+			let pass_layer_composite_shader = Shader::new(
+				&self.context,
+				assets_path,
+				"pass_layer_composite.wgsl",
+				vec![layer],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
+					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
+					use_instances: false,
+					scene_camera: None,
+					scene_lighting: None,
+					blend: Some(blend.blend_state()),
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			);
+			self.scene.resources.shaders.insert(format!("pass_layer_composite_{}", blend.name()), pass_layer_composite_shader);
+		}
+
+		// The composite shader is a plain copy of its texture, so without blending it also places each eye's view over its half of the frame
+		let pass_stereo_eye_shader = {
+			let eye = ShaderBinding::Texture(ShaderBindingTexture::default());
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"pass_layer_composite.wgsl",
+				vec![eye],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![wgpu::TextureFormat::Rgba16Float],
+					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
+					use_instances: false,
+					scene_camera: None,
+					scene_lighting: None,
+					blend: None,
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(String::from("pass_stereo_eye"), pass_stereo_eye_shader);
+
+		// Every fragment adds one to the count regardless of depth, so the heatmap shows the full cost of the layers hidden behind the front surface
+		let debug_overdraw_shader = Shader::new(
+			&self.context,
+			assets_path,
+			"debug_overdraw.wgsl",
+			vec![],
+			PipelineOptions::RenderPipeline(RenderPipelineOptions {
+				out_color_formats: vec![wgpu::TextureFormat::R16Float],
+				depth_format: None,
+				vertex_format: VertexFormat::PositionOnly,
+				use_instances: true,
+				scene_camera: Some(main_camera),
+				scene_lighting: None,
+				blend: Some(wgpu::BlendState {
+					color: wgpu::BlendComponent {
+						src_factor: wgpu::BlendFactor::One,
+						dst_factor: wgpu::BlendFactor::One,
+						operation: wgpu::BlendOperation::Add,
+					},
+					alpha: wgpu::BlendComponent::REPLACE,
+				}),
+				depth_write: false,
+				cull_mode: Some(wgpu::Face::Back),
+				sample_count: 1,
+				alpha_to_coverage: false,
+				conservative_rasterization: false,
+				depth_bias: wgpu::DepthBiasState::default(),
+			}),
+		);
+		self.scene.resources.shaders.insert(debug_overdraw_shader.name.clone(), debug_overdraw_shader);
+
+		// Shadow casters only need their depth, cut out by their albedo's alpha, so every material casts its shadows through one of these two depth-only variants
+		let create_scene_shadow_shader = |caster: ShadowCasterOptions| {
+			let albedo_map = ShaderBinding::Texture(ShaderBindingTexture::default());
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"scene_shadow.wgsl",
+				vec![albedo_map],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions::shadow_caster(main_camera, caster)),
+			)
+		};
+		let scene_shadow_shader = create_scene_shadow_shader(ShadowCasterOptions::CLOSED);
+		let scene_shadow_two_sided_shader = create_scene_shadow_shader(ShadowCasterOptions::TWO_SIDED);
+		self.scene.resources.shaders.insert(scene_shadow_shader.name.clone(), scene_shadow_shader);
+		self.scene.resources.shaders.insert(String::from("scene_shadow_two_sided"), scene_shadow_two_sided_shader);
+
+		let compute_overdraw_heatmap_shader = {
+			let overdraw = ShaderBinding::Texture(ShaderBindingTexture {
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingTexture::default()
+			});
+			let heatmap = ShaderBinding::StorageTexture(
+				ShaderBindingTexture {
+					visible_in_stages: wgpu::ShaderStages::COMPUTE,
+					..ShaderBindingTexture::default()
+				},
+				wgpu::TextureFormat::Rgba8Unorm,
+			);
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"compute_overdraw_heatmap.wgsl",
+				vec![overdraw, heatmap],
+				PipelineOptions::ComputePipeline(ComputePipelineOptions {}),
+			)
+		};
+		self.scene.resources.shaders.insert(compute_overdraw_heatmap_shader.name.clone(), compute_overdraw_heatmap_shader);
+
+		let compute_luminance_histogram_shader = {
+			let frame = ShaderBinding::Texture(ShaderBindingTexture {
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingTexture::default()
+			});
+			let histogram = ShaderBinding::Buffer(ShaderBindingBuffer {
+				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: false },
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+			let metering = ShaderBinding::Buffer(ShaderBindingBuffer {
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"compute_luminance_histogram.wgsl",
+				vec![frame, histogram, metering],
+				PipelineOptions::ComputePipeline(ComputePipelineOptions {}),
+			)
+		};
+		self.scene.resources.shaders.insert(compute_luminance_histogram_shader.name.clone(), compute_luminance_histogram_shader);
+
+		let histogram_graph_shader = {
+			let histogram = ShaderBinding::Buffer(ShaderBindingBuffer {
+				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: true },
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+			let graph = ShaderBinding::StorageTexture(
+				ShaderBindingTexture {
+					visible_in_stages: wgpu::ShaderStages::COMPUTE,
+					..ShaderBindingTexture::default()
+				},
+				wgpu::TextureFormat::Rgba8Unorm,
+			);
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"compute_luminance_histogram_graph.wgsl",
+				vec![histogram, graph],
+				PipelineOptions::ComputePipeline(ComputePipelineOptions {}),
+			)
+		};
+		self.scene.resources.shaders.insert(histogram_graph_shader.name.clone(), histogram_graph_shader);
+
+		let compute_auto_exposure_shader = {
+			let histogram = ShaderBinding::Buffer(ShaderBindingBuffer {
+				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: false },
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+			let adapted_exposure = ShaderBinding::Buffer(ShaderBindingBuffer {
+				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: false },
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+			let auto_exposure = ShaderBinding::Buffer(ShaderBindingBuffer {
+				visible_in_stages: wgpu::ShaderStages::COMPUTE,
+				..ShaderBindingBuffer::default()
+			});
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"compute_auto_exposure.wgsl",
+				vec![histogram, adapted_exposure, auto_exposure],
+				PipelineOptions::ComputePipeline(ComputePipelineOptions {}),
+			)
+		};
+		self.scene.resources.shaders.insert(compute_auto_exposure_shader.name.clone(), compute_auto_exposure_shader);
+
+		// Textures
+		self.scene.resources.textures.insert(
+			String::from("SSAO_NOISE"),
+			Texture::from_f16_array(
+				&self.context.device,
+				&self.context.queue,
+				crate::ssao::generate_noise_texture().as_slice(),
 				(4, 4),
 				"SSAO_NOISE",
 				wgpu::TextureFormat::Rgba16Float,
@@ -530,86 +2381,459 @@ impl Engine {
 			),
 		);
 
-		for texture_file in textures_to_load {
-			let mut loaded_texture = Texture::load(&self.context.device, &self.context.queue, assets_path, texture_file.0.as_str(), texture_file.1, texture_file.2)
-				.unwrap_or_else(|_| panic!("Can't load texture file: {}", texture_file.0));
-			loaded_texture.generate_mipmaps(&self.context);
-			self.scene.resources.textures.insert(texture_file.0, loaded_texture);
-		}
+		// Procedural noise textures for clouds, fog density, terrain splatting, and test materials
+		let noise_textures_2d = [
+			("NOISE_PERLIN_2D", NoiseSettings::new(NoiseType::Perlin, 8, 0)),
+			("NOISE_SIMPLEX_2D", NoiseSettings::new(NoiseType::Simplex, 8, 0)),
+			("NOISE_WORLEY_2D", NoiseSettings::new(NoiseType::Worley, 8, 0)),
+		];
+		for (name, settings) in noise_textures_2d {
+			let noise = crate::noise::generate_noise_texture_2d(&settings, (256, 256));
+			let mut texture = Texture::from_f16_array(
+				&self.context.device,
+				&self.context.queue,
+				noise.as_slice(),
+				(256, 256),
+				name,
+				wgpu::TextureFormat::Rgba16Float,
+				wgpu::AddressMode::Repeat,
+			);
+			texture.generate_mipmaps(&self.context);
+			self.scene.resources.textures.insert(String::from(name), texture);
+		}
+		let noise_textures_3d = [
+			("NOISE_PERLIN_3D", NoiseSettings::new(NoiseType::Perlin, 4, 0)),
+			("NOISE_WORLEY_3D", NoiseSettings::new(NoiseType::Worley, 4, 0)),
+		];
+		for (name, settings) in noise_textures_3d {
+			let noise = crate::noise::generate_noise_texture_3d(&settings, (64, 64, 64));
+			let texture = Texture::from_f16_array_3d(
+				&self.context.device,
+				&self.context.queue,
+				noise.as_slice(),
+				(64, 64, 64),
+				name,
+				wgpu::TextureFormat::Rgba16Float,
+				wgpu::AddressMode::Repeat,
+			);
+			self.scene.resources.textures.insert(String::from(name), texture);
+		}
+
+		// Materials
+		self.load_model_materials(model_files, assets_path);
+
+		let voxel_lightmap_binding = MaterialDataBinding::Buffer(BufferBinding {
+			buffer: &self.voxel_storage_buffer,
+			offset: 0,
+			size: None,
+		});
+		let voxel_update_region_binding = MaterialDataBinding::Buffer(BufferBinding {
+			buffer: &self.voxel_update_scheduler.region_buffer,
+			offset: 0,
+			size: None,
+		});
+		let voxel_texture_generating_material = Material::new(
+			"compute_voxel_texture_generating.material",
+			"compute_voxel_texture_generating.wgsl",
+			vec![
+				MaterialDataBinding::StorageTexture(&self.voxel_light_map.texture, Some(&self.voxel_light_map.storage_texture_view)),
+				voxel_lightmap_binding,
+				voxel_update_region_binding,
+			],
+			&self.scene.resources,
+			&self.context.device,
+		);
+		self.scene
+			.resources
+			.materials
+			.insert(String::from("compute_voxel_texture_generating.material"), voxel_texture_generating_material);
+
+		// Each mirror's material binds its reflection camera, to find where the reflection lands, along with the finished reflection itself
+		let mirror_materials = self
+			.scene
+			.root
+			.iter()
+			.flat_map(|entity| entity.get_mirrors().into_iter().map(move |mirror| (entity.name.as_str(), mirror)))
+			.map(|(name, mirror)| {
+				let material_name = format!("scene_mirror_{}.material", name);
+				let data_bindings = vec![
+					MaterialDataBinding::Buffer(BufferBinding {
+						buffer: &mirror.camera.camera_buffer,
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::Texture(&mirror.reflection_map.texture),
+				];
+
+				let material = Material::new(&material_name, "scene_mirror.wgsl", data_bindings, &self.scene.resources, &self.context.device);
+				(material_name, material)
+			})
+			.collect::<Vec<_>>();
+		self.scene.resources.materials.extend(mirror_materials);
+
+		// Each portal's material binds the finished view through it
+		let portal_materials = self
+			.scene
+			.root
+			.iter()
+			.flat_map(|entity| entity.get_portals().into_iter().map(move |portal| (entity.name.as_str(), portal)))
+			.map(|(name, portal)| {
+				let material_name = format!("scene_portal_{}.material", name);
+				let data_bindings = vec![MaterialDataBinding::Texture(&portal.portal_map.texture)];
+
+				let material = Material::new(&material_name, "scene_portal.wgsl", data_bindings, &self.scene.resources, &self.context.device);
+				(material_name, material)
+			})
+			.collect::<Vec<_>>();
+		self.scene.resources.materials.extend(portal_materials);
+
+		self.load_material_files(assets_path);
+		self.bake_impostors();
+		self.update_transparent_surface_materials();
+		self.update_post_processing_pass_materials();
+	}
+
+	// Builds the materials described by the files of the materials folder, leaving out those that name a missing shader or texture, or don't match their shader's bindings
+	fn load_material_files(&mut self, assets_path: &Path) {
+		let skipped_mips = self.render_settings.profile.skipped_texture_mips();
+		let mut material_files = Vec::new();
+		for material_file in MaterialFile::read_all(assets_path) {
+			let checked = match self.scene.resources.shaders.get(&material_file.shader) {
+				Some(shader) => material_file.check_against(shader),
+				None => Err(anyhow!("Material \"{}\" names the shader \"{}\", which doesn't exist", material_file.name, material_file.shader)),
+			};
+			// Textures generated at runtime or already loaded by the models are shared rather than loaded again
+			let loaded = checked.and_then(|_| {
+				let missing_textures = material_file
+					.textures()
+					.into_iter()
+					.filter(|(file, _, _)| !self.scene.resources.textures.contains_key(file))
+					.collect::<Vec<_>>();
+				missing_textures.into_iter().try_for_each(|(file, format, address_mode)| {
+					let image = Texture::decode(assets_path, &file, skipped_mips).map_err(|error| anyhow!("Can't load texture file {}: {}", file, error))?;
+					let mut texture = Texture::from_image(&self.context.device, &self.context.queue, &image, &file, format, address_mode);
+					texture.generate_mipmaps(&self.context);
+					self.scene.resources.textures.insert(file, texture);
+					Ok(())
+				})
+			});
+
+			match loaded {
+				Ok(_) => material_files.push(material_file),
+				Err(error) => eprintln!("Can't build material file \"{}\": {}", material_file.name, error),
+			}
+		}
+
+		self.material_file_resources = MaterialFileResources::new(&self.context.device, &material_files);
+		let mut parameter_buffers = self.material_file_resources.parameter_buffers.iter();
+		let mut samplers = self.material_file_resources.samplers.iter();
+		let materials = material_files
+			.iter()
+			.map(|material_file| {
+				// Each texture is sampled through the sampler made for its settings, which binds the same way as a depth texture's own sampler
+				let data_bindings = material_file
+					.bindings
+					.iter()
+					.map(|binding| match binding {
+						MaterialFileBinding::Texture { file, .. } => MaterialDataBinding::SampleableDepthTexture(&self.scene.resources.textures[file], samplers.next().unwrap()),
+						MaterialFileBinding::Parameters(_) => MaterialDataBinding::Buffer(parameter_buffers.next().unwrap().as_entire_buffer_binding()),
+					})
+					.collect();
+
+				let material = Material::new(&material_file.name, &material_file.shader, data_bindings, &self.scene.resources, &self.context.device);
+				(material_file.name.clone(), material)
+			})
+			.collect::<Vec<_>>();
+		self.scene.resources.materials.extend(materials);
+	}
+
+	// The shaders of the passes that draw into the surface, which depend on its format
+	fn load_surface_shaders(&mut self, assets_path: &Path) {
+		let main_camera = self.scene.root.find_descendant("Main Camera").unwrap().get_cameras()[0];
+
+		let pass_final_composite_shader = {
+			let pbr_shaded = ShaderBinding::Texture(ShaderBindingTexture::default());
+			let adapted_exposure = ShaderBinding::Buffer(ShaderBindingBuffer {
+				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: true },
+				visible_in_stages: wgpu::ShaderStages::FRAGMENT,
+				..ShaderBindingBuffer::default()
+			});
+			let final_composite = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"pass_final_composite.wgsl",
+				vec![pbr_shaded, adapted_exposure, final_composite],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![self.context.surface_configuration.format],
+					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
+					use_instances: false,
+					scene_camera: None,
+					scene_lighting: None,
+					blend: Some(wgpu::BlendState::REPLACE),
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(String::from("pass_final_composite.wgsl"), pass_final_composite_shader);
 
-		// Materials
-		let voxel_storage_buffer = self.context.device.create_buffer(&wgpu::BufferDescriptor {
-			label: Some("Voxel storage buffer"),
-			size: 128 * 128 * 128 * 4 * 4,
-			usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
-			mapped_at_creation: false,
-		});
+		let pass_debug_view_shader = {
+			let debug_view = ShaderBinding::Texture(ShaderBindingTexture::default());
 
-		let model_material_definitions = model_materials_to_load.iter().map(|(material_name, shader_name, data_bindings)| {
-			(
-				material_name.as_str(),
-				*shader_name,
-				data_bindings
-					.iter()
-					.map(|texture_path| match texture_path.as_str() {
-						"VOXEL_LIGHTMAP" => MaterialDataBinding::Buffer(BufferBinding {
-							buffer: &voxel_storage_buffer,
-							offset: 0,
-							size: None,
-						}),
-						"VOXEL_LIGHTMAP_TEXTURE" => MaterialDataBinding::Texture(&self.voxel_light_map.texture),
-						"VOXEL_CAMERA_MATRICES" => MaterialDataBinding::Buffer(BufferBinding {
-							buffer: &voxel_camera_x.camera_buffer,
-							offset: 0,
-							size: None,
-						}),
-						_ => MaterialDataBinding::TextureName(texture_path.as_str()),
-					})
-					.collect::<Vec<_>>(),
+			Shader::new(
+				&self.context,
+				assets_path,
+				"pass_debug_view.wgsl",
+				vec![debug_view],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![self.context.surface_configuration.format],
+					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
+					use_instances: false,
+					scene_camera: None,
+					scene_lighting: None,
+					blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
 			)
-		});
+		};
+		self.scene.resources.shaders.insert(pass_debug_view_shader.name.clone(), pass_debug_view_shader);
 
-		let material_definitions = [(
-			"compute_voxel_texture_generating.material",
-			"compute_voxel_texture_generating.wgsl",
-			vec![
-				MaterialDataBinding::StorageTexture(&self.voxel_light_map.texture, Some(&self.voxel_light_map.storage_texture_view)),
-				MaterialDataBinding::Buffer(wgpu::BufferBinding {
-					buffer: &voxel_storage_buffer,
+		let pass_console_shader = {
+			let console_text = ShaderBinding::Buffer(ShaderBindingBuffer {
+				visible_in_stages: wgpu::ShaderStages::FRAGMENT,
+				uniform_or_storage: wgpu::BufferBindingType::Storage { read_only: true },
+				..ShaderBindingBuffer::default()
+			});
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"pass_console.wgsl",
+				vec![console_text],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![self.context.surface_configuration.format],
+					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
+					use_instances: false,
+					scene_camera: None,
+					scene_lighting: None,
+					blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene.resources.shaders.insert(pass_console_shader.name.clone(), pass_console_shader);
+
+		let pass_debug_view_distance_field_shader = {
+			let distance_field = ShaderBinding::Buffer(ShaderBindingBuffer::default());
+			let distance_field_map = ShaderBinding::Texture(ShaderBindingTexture {
+				dimensions: wgpu::TextureViewDimension::D3,
+				..ShaderBindingTexture::default()
+			});
+
+			Shader::new(
+				&self.context,
+				assets_path,
+				"pass_debug_view_distance_field.wgsl",
+				vec![distance_field, distance_field_map],
+				PipelineOptions::RenderPipeline(RenderPipelineOptions {
+					out_color_formats: vec![self.context.surface_configuration.format],
+					depth_format: None,
+					vertex_format: VertexFormat::PositionOnly,
+					use_instances: false,
+					scene_camera: Some(main_camera),
+					scene_lighting: None,
+					blend: None,
+					depth_write: true,
+					cull_mode: Some(wgpu::Face::Back),
+					sample_count: 1,
+					alpha_to_coverage: false,
+					conservative_rasterization: false,
+					depth_bias: wgpu::DepthBiasState::default(),
+				}),
+			)
+		};
+		self.scene
+			.resources
+			.shaders
+			.insert(pass_debug_view_distance_field_shader.name.clone(), pass_debug_view_distance_field_shader);
+	}
+
+	fn rebuild_surface_pipelines(&mut self) {
+		let assets_path = self.assets_path.clone();
+		self.load_surface_shaders(&assets_path);
+		self.update_post_processing_pass_materials();
+		self.debug_draw = DebugDraw::new(&self.context);
+		self.last_frame = LastFrame::new(&self.context.device, &self.context.surface_configuration);
+	}
+
+	// Each mesh drawn by a model with an impostor distance has its atlases baked once, and the mesh's material is shared by all those models
+	fn bake_impostors(&mut self) {
+		let impostor_meshes = self
+			.scene
+			.root
+			.iter()
+			.flat_map(|entity| entity.get_models())
+			.filter(|model| model.impostor_distance.is_some())
+			.map(|model| model.mesh_name.clone())
+			// Meshes already baked for an earlier scene keep their atlases
+			.filter(|(_, mesh_name)| !self.scene.resources.materials.contains_key(&format!("scene_impostor_{}.material", mesh_name)))
+			.collect::<HashSet<_>>();
+
+		for mesh_key in impostor_meshes {
+			let mesh = &self.scene.resources.meshes[&mesh_key];
+			let texture = |map: &Option<String>| {
+				let map = map
+					.as_ref()
+					.unwrap_or_else(|| panic!("The mesh '{}:{}' needs albedo and ARM maps to bake its impostor", mesh_key.0, mesh_key.1));
+				&self.scene.resources.textures[map]
+			};
+			let atlas = ImpostorAtlas::bake(&self.context, mesh, texture(&mesh.map_albedo), texture(&mesh.map_arm));
+
+			let impostor_buffer = self.context.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label: Some(&format!("{} Impostor Buffer", mesh.name)),
+				contents: bytemuck::cast_slice(&[atlas.uniform]),
+				usage: wgpu::BufferUsages::UNIFORM,
+			});
+
+			let material_name = format!("scene_impostor_{}.material", mesh.name);
+			let [normal_name, albedo_name, arm_name] = ["NORMAL", "ALBEDO", "ARM"].map(|kind| format!("IMPOSTOR_{}_ATLAS_{}", kind, mesh.name));
+			self.scene.resources.textures.insert(normal_name.clone(), atlas.normal);
+			self.scene.resources.textures.insert(albedo_name.clone(), atlas.albedo);
+			self.scene.resources.textures.insert(arm_name.clone(), atlas.arm);
+
+			let data_bindings = vec![
+				MaterialDataBinding::Buffer(BufferBinding {
+					buffer: &impostor_buffer,
 					offset: 0,
 					size: None,
 				}),
-			],
-		)];
+				MaterialDataBinding::TextureName(&normal_name),
+				MaterialDataBinding::TextureName(&albedo_name),
+				MaterialDataBinding::TextureName(&arm_name),
+			];
+			let material = Material::new(&material_name, "scene_impostor.wgsl", data_bindings, &self.scene.resources, &self.context.device);
+			self.scene.resources.materials.insert(material_name, material);
+		}
+	}
 
-		let combined_materials = model_material_definitions.chain(material_definitions);
-		for (material_name, shader_name, data_bindings) in combined_materials {
-			let material = Material::new(material_name, shader_name, data_bindings, &self.scene.resources, &self.context.device);
-			self.scene.resources.materials.insert(String::from(material_name), material);
+	// Each transparent surface gets a material for both transparency modes so they can be switched between at runtime, and glass also binds the scene color copy
+	fn update_transparent_surface_materials(&mut self) {
+		for surface in TRANSPARENT_SURFACES {
+			let surface_buffer = self.context.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label: Some(&format!("{} Transparent Surface Buffer", surface.name)),
+				contents: bytemuck::cast_slice(&[surface.uniform()]),
+				usage: wgpu::BufferUsages::UNIFORM,
+			});
+
+			for kind in surface.material_kinds() {
+				let material_name = format!("scene_transparent_{}_{}.material", kind, surface.name);
+				let shader_name = format!("scene_transparent_{}.wgsl", kind);
+				let mut data_bindings = vec![MaterialDataBinding::Buffer(BufferBinding {
+					buffer: &surface_buffer,
+					offset: 0,
+					size: None,
+				})];
+				if surface.refraction.is_some() {
+					data_bindings.push(MaterialDataBinding::Texture(&self.frame_textures.scene_color_copy_map.texture));
+				}
+
+				let material = Material::new(&material_name, &shader_name, data_bindings, &self.scene.resources, &self.context.device);
+				self.scene.resources.materials.insert(material_name, material);
+			}
 		}
+	}
 
+	// Resizes the post-processing frame textures, rebuilding the materials that bind them, and revoxelizes everything if voxel GI comes back on after being left stale
+	fn apply_render_profile(&mut self) {
+		let profile = self.render_settings.profile;
+		let resolution_divisor = profile.post_process_resolution_divisor();
+		let device = &self.context.device;
+		let config = &self.context.surface_configuration;
+		self.frame_textures.ssao_kernel_map.set_resolution_divisor(device, config, resolution_divisor);
+		self.frame_textures.ssao_history_map.set_resolution_divisor(device, config, resolution_divisor);
+		self.frame_textures.ssao_history_previous_map.set_resolution_divisor(device, config, resolution_divisor);
+		self.frame_textures.ssao_blurred_map.set_resolution_divisor(device, config, resolution_divisor);
 		self.update_post_processing_pass_materials();
+		self.ssao_temporal.invalidate_history();
+		self.render_settings.checkerboard = profile.checkerboard_shading();
+
+		if profile.voxel_global_illumination() && !self.applied_render_profile.voxel_global_illumination() {
+			self.voxel_update_scheduler.mark_all_dirty();
+		}
+
+		self.applied_render_profile = profile;
+	}
+
+	// Switches every variant of the G-buffer pipeline to the chosen polygon mode, going back to filled if the GPU can't draw it
+	fn apply_rasterization(&mut self) {
+		let polygon_mode = self.render_settings.rasterization.polygon_mode();
+
+		let result = self
+			.scene
+			.resources
+			.shaders
+			.values_mut()
+			.filter(|shader| shader.name == "scene_deferred.wgsl")
+			.try_for_each(|shader| shader.set_polygon_mode(&self.context, &self.assets_path, polygon_mode));
+
+		match result {
+			Ok(_) => self.applied_rasterization = self.render_settings.rasterization,
+			Err(error) => {
+				eprintln!("Can't switch the scene to {:?} rasterization, so it stays filled: {}", self.render_settings.rasterization, error);
+				self.render_settings.rasterization = Rasterization::Filled;
+				self.apply_rasterization();
+			}
+		}
 	}
 
 	fn update_post_processing_pass_materials(&mut self) {
+		// Shared by both builds, since the samples are random and would otherwise change between even and odd frames
 		let ssao_samples_buffer = self.context.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
 			label: Some("SSAO samples buffer"),
 			contents: bytemuck::cast_slice(&crate::ssao::generate_sample_hemisphere()),
 			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
 		});
-		let z_buffer_previous_sampler = self.context.device.create_sampler(&wgpu::SamplerDescriptor {
-			label: Some("Z Buffer Previous sampleable sampler"),
-			address_mode_u: wgpu::AddressMode::ClampToEdge,
-			address_mode_v: wgpu::AddressMode::ClampToEdge,
-			address_mode_w: wgpu::AddressMode::ClampToEdge,
-			mag_filter: wgpu::FilterMode::Nearest,
-			min_filter: wgpu::FilterMode::Nearest,
-			mipmap_filter: wgpu::FilterMode::Nearest,
-			compare: None,
-			lod_min_clamp: -100.0,
-			lod_max_clamp: 100.0,
-			..Default::default()
-		});
+
+		// Materials sampling a temporal texture are built once more with the pairs swapped, for the frames of the other parity
+		let parity = self.frame_textures.parity();
+		let materials = self.post_processing_pass_materials(&ssao_samples_buffer);
+		self.frame_textures.swap_temporal_pairs();
+		let other_parity_materials = self.post_processing_pass_materials(&ssao_samples_buffer);
+		self.frame_textures.swap_temporal_pairs();
+
+		for ((material_name, material), (_, other_parity_material)) in materials.into_iter().zip(other_parity_materials) {
+			match material.sampled_textures.iter().any(|label| self.frame_textures.is_temporal(label)) {
+				true => {
+					self.scene.resources.materials.insert(temporal_material_name(&material_name, parity), material);
+					self.scene.resources.materials.insert(temporal_material_name(&material_name, 1 - parity), other_parity_material);
+				}
+				false => {
+					self.scene.resources.materials.insert(material_name, material);
+				}
+			}
+		}
+	}
+
+	fn post_processing_pass_materials(&self, ssao_samples_buffer: &wgpu::Buffer) -> Vec<(String, Material)> {
 		let z_buffer_sampler = self.context.device.create_sampler(&wgpu::SamplerDescriptor {
 			label: Some("Z Buffer sampleable sampler"),
 			address_mode_u: wgpu::AddressMode::ClampToEdge,
@@ -630,19 +2854,31 @@ impl Engine {
 				"pass_ssao_kernel.wgsl",
 				vec![
 					MaterialDataBinding::Buffer(wgpu::BufferBinding {
-						buffer: &ssao_samples_buffer,
+						buffer: ssao_samples_buffer,
 						offset: 0,
 						size: None,
 					}),
 					MaterialDataBinding::TextureName("SSAO_NOISE"),
 					MaterialDataBinding::SampleableDepthTexture(&self.frame_textures.z_buffer.texture, &z_buffer_sampler),
 					MaterialDataBinding::Texture(&self.frame_textures.world_space_normal.texture),
+					MaterialDataBinding::Buffer(self.ssao_temporal.ssao_temporal_buffer.as_entire_buffer_binding()),
+					MaterialDataBinding::Texture(&self.frame_textures.velocity_map.texture),
+				],
+			),
+			(
+				"pass_ssao_temporal.material",
+				"pass_ssao_temporal.wgsl",
+				vec![
+					MaterialDataBinding::Buffer(self.ssao_temporal.ssao_temporal_buffer.as_entire_buffer_binding()),
+					MaterialDataBinding::Texture(&self.frame_textures.ssao_kernel_map.texture),
+					MaterialDataBinding::Texture(&self.frame_textures.ssao_history_previous_map.texture),
+					MaterialDataBinding::Texture(&self.frame_textures.velocity_map.texture),
 				],
 			),
 			(
 				"pass_ssao_blurred.material",
 				"pass_ssao_blurred.wgsl",
-				vec![MaterialDataBinding::Texture(&self.frame_textures.ssao_kernel_map.texture)],
+				vec![MaterialDataBinding::Texture(&self.frame_textures.ssao_history_map.texture)],
 			),
 			(
 				"pass_pbr_shading.material",
@@ -653,6 +2889,118 @@ impl Engine {
 					MaterialDataBinding::Texture(&self.frame_textures.albedo_map.texture),
 					MaterialDataBinding::Texture(&self.frame_textures.arm_map.texture),
 					MaterialDataBinding::Texture(&self.frame_textures.ssao_blurred_map.texture),
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.foveation.settings_buffer,
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.distance_field_buffer,
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::TextureName(DISTANCE_FIELD_TEXTURE),
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.weather.weather_buffer,
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::TextureName("NOISE_PERLIN_2D"),
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.shadow_maps.shadow_buffer,
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::Texture(&self.shadow_maps.depth_array.texture),
+					MaterialDataBinding::Texture(&self.frame_textures.emissive_map.texture),
+					MaterialDataBinding::SampleableDepthTexture(&self.shadow_maps.moments_atlas.texture, &self.shadow_maps.moments_sampler),
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.environment_bindings.buffer,
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.point_shadow_map.point_shadow_buffer,
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::Texture(&self.point_shadow_map.depth_cube.texture),
+					MaterialDataBinding::Texture(&self.environment_bindings.irradiance_map),
+					MaterialDataBinding::Texture(&self.environment_bindings.prefiltered_map),
+					MaterialDataBinding::Texture(&self.environment_bindings.brdf_lut),
+				],
+			),
+			(
+				"pass_skybox.material",
+				"pass_skybox.wgsl",
+				vec![
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.environment_bindings.buffer,
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::Texture(&self.environment_bindings.sky_map),
+					MaterialDataBinding::SampleableDepthTexture(&self.frame_textures.z_buffer.texture, &z_buffer_sampler),
+				],
+			),
+			(
+				"pass_shadow_moments.material",
+				"pass_shadow_moments.wgsl",
+				vec![
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.shadow_maps.shadow_buffer,
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::SampleableDepthTexture(&self.shadow_maps.depth_array.texture, &self.shadow_maps.moments_sampler),
+				],
+			),
+			(
+				"pass_shadow_blur_horizontal.material",
+				"pass_shadow_blur.wgsl",
+				vec![
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.shadow_maps.shadow_buffer,
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.shadow_maps.blur_direction_buffers[0],
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::SampleableDepthTexture(&self.shadow_maps.moments_atlas.texture, &self.shadow_maps.moments_sampler),
+				],
+			),
+			(
+				"pass_shadow_blur_vertical.material",
+				"pass_shadow_blur.wgsl",
+				vec![
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.shadow_maps.shadow_buffer,
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.shadow_maps.blur_direction_buffers[1],
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::SampleableDepthTexture(&self.shadow_maps.moments_blur_atlas.texture, &self.shadow_maps.moments_sampler),
+				],
+			),
+			(
+				"pass_foveation_fill.material",
+				"pass_foveation_fill.wgsl",
+				vec![
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.foveation.settings_buffer,
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::Texture(&self.frame_textures.scene_color_copy_map.texture),
+					MaterialDataBinding::Texture(&self.frame_textures.checkerboard_history_previous_map.texture),
+					MaterialDataBinding::Texture(&self.frame_textures.velocity_map.texture),
 				],
 			),
 			(
@@ -660,21 +3008,150 @@ impl Engine {
 				"pass_motion_blur.wgsl",
 				vec![
 					MaterialDataBinding::Texture(&self.frame_textures.pbr_shaded_map.texture),
-					MaterialDataBinding::SampleableDepthTexture(&self.frame_textures.z_buffer_previous.texture, &z_buffer_previous_sampler),
+					MaterialDataBinding::Texture(&self.frame_textures.velocity_map.texture),
+					MaterialDataBinding::SampleableDepthTexture(&self.frame_textures.z_buffer.texture, &z_buffer_sampler),
+				],
+			),
+			(
+				"pass_taa_resolve.material",
+				"pass_taa_resolve.wgsl",
+				vec![
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.temporal_anti_aliasing.taa_buffer,
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::Texture(&self.frame_textures.pbr_shaded_map.texture),
+					MaterialDataBinding::Texture(&self.frame_textures.taa_history_previous_map.texture),
+					MaterialDataBinding::Texture(&self.frame_textures.velocity_map.texture),
 					MaterialDataBinding::SampleableDepthTexture(&self.frame_textures.z_buffer.texture, &z_buffer_sampler),
 				],
 			),
 			(
-				"pass_hdr_exposure.material",
-				"pass_hdr_exposure.wgsl",
-				vec![MaterialDataBinding::Texture(&self.frame_textures.motion_blur_map.texture)],
+				"pass_transparent_composite.material",
+				"pass_transparent_composite.wgsl",
+				vec![
+					MaterialDataBinding::Texture(&self.frame_textures.transparent_accumulation_map.texture),
+					MaterialDataBinding::Texture(&self.frame_textures.transparent_coverage_map.texture),
+				],
+			),
+			(
+				"pass_final_composite.material",
+				"pass_final_composite.wgsl",
+				vec![
+					MaterialDataBinding::Texture(&self.frame_textures.motion_blur_map.texture),
+					MaterialDataBinding::Buffer(self.auto_exposure.exposure_buffer.as_entire_buffer_binding()),
+					MaterialDataBinding::Buffer(self.final_composite.uniform_buffer.as_entire_buffer_binding()),
+				],
+			),
+			("debug_overdraw.material", "debug_overdraw.wgsl", vec![]),
+			(
+				"scene_precipitation.material",
+				"scene_precipitation.wgsl",
+				vec![MaterialDataBinding::Buffer(wgpu::BufferBinding {
+					buffer: &self.weather.weather_buffer,
+					offset: 0,
+					size: None,
+				})],
+			),
+			(
+				"compute_overdraw_heatmap.material",
+				"compute_overdraw_heatmap.wgsl",
+				vec![
+					MaterialDataBinding::Texture(&self.debug_view_textures.overdraw_map.texture),
+					MaterialDataBinding::StorageTexture(&self.debug_view_textures.overdraw_heatmap, None),
+				],
+			),
+			(
+				"compute_luminance_histogram.material",
+				"compute_luminance_histogram.wgsl",
+				vec![
+					MaterialDataBinding::Texture(&self.frame_textures.motion_blur_map.texture),
+					MaterialDataBinding::Buffer(self.debug_view_textures.luminance_histogram_buffer.as_entire_buffer_binding()),
+					MaterialDataBinding::Buffer(self.auto_exposure.average_metering_buffer.as_entire_buffer_binding()),
+				],
+			),
+			// The eye adaptation counts the shaded scene's luminance with the same shader as the histogram debug view, into a buffer of its own and weighted by its metering
+			(
+				"compute_auto_exposure_histogram.material",
+				"compute_luminance_histogram.wgsl",
+				vec![
+					MaterialDataBinding::Texture(&self.frame_textures.pbr_shaded_map.texture),
+					MaterialDataBinding::Buffer(self.auto_exposure.histogram_buffer.as_entire_buffer_binding()),
+					MaterialDataBinding::Buffer(self.auto_exposure.metering_buffer.as_entire_buffer_binding()),
+				],
+			),
+			(
+				"compute_auto_exposure.material",
+				"compute_auto_exposure.wgsl",
+				vec![
+					MaterialDataBinding::Buffer(self.auto_exposure.histogram_buffer.as_entire_buffer_binding()),
+					MaterialDataBinding::Buffer(self.auto_exposure.exposure_buffer.as_entire_buffer_binding()),
+					MaterialDataBinding::Buffer(self.auto_exposure.uniform_buffer.as_entire_buffer_binding()),
+				],
+			),
+			(
+				"compute_luminance_histogram_graph.material",
+				"compute_luminance_histogram_graph.wgsl",
+				vec![
+					MaterialDataBinding::Buffer(self.debug_view_textures.luminance_histogram_buffer.as_entire_buffer_binding()),
+					MaterialDataBinding::StorageTexture(&self.debug_view_textures.luminance_histogram_map, None),
+				],
+			),
+			(
+				"pass_debug_view_overdraw.material",
+				"pass_debug_view.wgsl",
+				vec![MaterialDataBinding::Texture(&self.debug_view_textures.overdraw_heatmap)],
+			),
+			(
+				"pass_debug_view_luminance_histogram.material",
+				"pass_debug_view.wgsl",
+				vec![MaterialDataBinding::Texture(&self.debug_view_textures.luminance_histogram_map)],
+			),
+			(
+				"pass_debug_view_distance_field.material",
+				"pass_debug_view_distance_field.wgsl",
+				vec![
+					MaterialDataBinding::Buffer(wgpu::BufferBinding {
+						buffer: &self.distance_field_buffer,
+						offset: 0,
+						size: None,
+					}),
+					MaterialDataBinding::TextureName(DISTANCE_FIELD_TEXTURE),
+				],
+			),
+			(
+				"pass_console.material",
+				"pass_console.wgsl",
+				vec![MaterialDataBinding::Buffer(self.console.console_buffer.as_entire_buffer_binding())],
 			),
 		];
 
+		let mut materials = Vec::new();
+
 		for (material_name, shader_name, data_bindings) in pass_material_definitions {
 			let material = Material::new(material_name, shader_name, data_bindings, &self.scene.resources, &self.context.device);
-			self.scene.resources.materials.insert(String::from(material_name), material);
+			materials.push((String::from(material_name), material));
+		}
+
+		for layer in &self.render_layers {
+			let material_name = format!("pass_layer_composite_{}.material", layer.name);
+			let shader_name = format!("pass_layer_composite_{}", layer.blend.name());
+			let data_bindings = vec![MaterialDataBinding::Texture(&layer.color_map.texture)];
+
+			let material = Material::new(&material_name, &shader_name, data_bindings, &self.scene.resources, &self.context.device);
+			materials.push((material_name, material));
+		}
+
+		for stereo_eye in &self.stereo_rig.eyes {
+			let material_name = format!("pass_stereo_{}_eye.material", stereo_eye.eye.name());
+			let data_bindings = vec![MaterialDataBinding::Texture(&stereo_eye.color_map.texture)];
+
+			let material = Material::new(&material_name, "pass_stereo_eye", data_bindings, &self.scene.resources, &self.context.device);
+			materials.push((material_name, material));
 		}
+
+		materials
 	}
 
 	fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -683,13 +3160,34 @@ impl Engine {
 			self.context.surface_configuration.height = new_size.height;
 			self.context.surface.configure(&self.context.device, &self.context.surface_configuration);
 
+			// There's no scene or materials to resize until loading finishes, which catches them up with the surface
+			if self.asset_loader.is_some() {
+				return;
+			}
+
 			match &mut self.scene.find_entity_mut(self.active_camera.as_str()).unwrap().get_cameras_mut()[0].projection {
 				Projection::Perspective(p) => p.resize(new_size.width, new_size.height),
 				Projection::Orthographic(o) => o.resize(new_size.width, new_size.height),
 			}
 
 			self.frame_textures.recreate_all(&self.context.device, &self.context.surface_configuration);
+			self.transient_attachments.recreate_all(&self.context.device, &self.context.surface_configuration);
+			self.debug_view_textures.recreate(&self.context.device, &self.context.surface_configuration);
+			for layer in &mut self.render_layers {
+				layer.resize(&self.context.device, &self.context.surface_configuration);
+			}
+			self.stereo_rig.resize(&self.context);
+			self.last_frame.resize(&self.context.device, &self.context.surface_configuration);
+			self.update_transparent_surface_materials();
 			self.update_post_processing_pass_materials();
+
+			// The culling materials bind the old pyramid, so the meshlet draws are rebuilt along with it
+			self.hi_z_pyramid = HiZPyramid::new(&self.context.device, self.frame_textures.z_buffers_previous_by_parity());
+			self.meshlet_renderer.invalidate();
+			self.temporal_anti_aliasing.invalidate_history();
+			self.ssao_temporal.invalidate_history();
+			self.foveation.invalidate_history();
+			self.frames_since_resize = 0;
 		}
 	}
 
@@ -699,26 +3197,65 @@ impl Engine {
 			DeviceEvent::Key(KeyboardInput {
 				virtual_keycode: Some(key), state, ..
 			}) => {
+				// The console takes the keyboard while it's open
+				if self.console.process_keyboard(*key, *state) {
+					return;
+				}
+
 				// self.scene.find_entity_mut(self.active_camera.as_str()).unwrap().get_cameras_mut()[0]
 				self.camera_controller.process_keyboard(*key, *state);
+				self.character_controller.process_keyboard(*key, *state);
+				self.follow_camera_controller.process_keyboard(*key, *state);
 				self.debug_buffer.process_keyboard(*key, *state);
+				self.render_settings.process_keyboard(*key, *state);
+				self.weather.process_keyboard(*key, *state);
+				self.time_of_day.process_keyboard(*key, *state);
+				self.sequencer.process_keyboard(*key, *state);
+				self.gpu_capture.process_keyboard(*key, *state);
+				self.behavior_input.process_keyboard(*key, *state);
 			}
 			// Scroll wheel movement
 			DeviceEvent::MouseWheel { delta, .. } => {
 				// self.scene.find_entity_mut(self.active_camera.as_str()).unwrap().get_cameras_mut()[0]
 				self.camera_controller.process_scroll(delta);
+				self.follow_camera_controller.process_scroll(delta);
 			}
-			// Mouse movement
-			DeviceEvent::MouseMotion { delta } => {
+			// Mouse movement, which is raw motion reported even while the cursor is elsewhere, so it only turns the camera while looking around
+			DeviceEvent::MouseMotion { delta } if self.camera_controller.looking() && !self.light_gizmos.dragging() => {
 				// self.scene.find_entity_mut(self.active_camera.as_str()).unwrap().get_cameras_mut()[0]
 				self.camera_controller.process_mouse(delta.0, delta.1);
+				self.character_controller.process_mouse(delta.0, delta.1);
+				self.follow_camera_controller.process_mouse(delta.0, delta.1);
 			}
 			_ => {}
 		}
 	}
 
 	pub fn process_window_event(&mut self, window_event: &WindowEvent, control_flow: &mut ControlFlow) {
+		self.light_gizmos.process_window_event(window_event);
+
 		match window_event {
+			// Close the console, if it's open, before the window
+			WindowEvent::KeyboardInput {
+				input: KeyboardInput {
+					state: ElementState::Pressed,
+					virtual_keycode: Some(VirtualKeyCode::Escape),
+					..
+				},
+				..
+			} if self.console.open => {
+				self.console.open = false;
+			}
+			WindowEvent::ReceivedCharacter(character) => {
+				self.console.process_character(*character);
+			}
+			// Holding the right mouse button looks around with the camera
+			WindowEvent::MouseInput { state, button, .. } => {
+				self.camera_controller.process_mouse_button(*button, *state);
+			}
+			WindowEvent::Focused(false) => {
+				self.camera_controller.release_look();
+			}
 			// Close window
 			WindowEvent::KeyboardInput {
 				input: KeyboardInput {
@@ -742,42 +3279,570 @@ impl Engine {
 		}
 	}
 
+	// Runs a line typed into the console, returning what to print back
+	fn run_console_command(&mut self, line: &str) -> anyhow::Result<String> {
+		let mut words = line.split_whitespace();
+		let command = words.next().unwrap_or_default();
+		let arguments = words.collect::<Vec<_>>();
+
+		match (command, arguments.as_slice()) {
+			("help", []) => {
+				let commands = CONSOLE_COMMANDS.iter().map(|(usage, description)| format!("{}: {}", usage, description));
+				let settings = RenderSettings::SETTING_NAMES.iter().map(|name| format!("{} [value]: Shows or changes the render setting", name));
+				let prefabs = format!("Prefabs: {}", CONSOLE_PREFABS.map(|(name, _)| name).join(", "));
+				Ok(commands.chain(settings).chain([prefabs]).collect::<Vec<_>>().join("\n"))
+			}
+			("exposure", []) => {
+				let camera = self.scene.find_entity(self.active_camera.as_str()).unwrap().get_cameras()[0];
+				Ok(format!("exposure {}", camera.exposure))
+			}
+			("exposure", [value]) => {
+				let exposure = value.parse::<f32>().map_err(|_| anyhow!("exposure takes a number"))?;
+				self.scene.find_entity_mut(self.active_camera.as_str()).unwrap().get_cameras_mut()[0].exposure = exposure;
+				Ok(format!("exposure {}", exposure))
+			}
+			("ambient", []) => Ok(self.scene.environment.ambient.description()),
+			("ambient", [part, values @ ..]) => {
+				let hemisphere = &mut self.scene.environment.ambient;
+				let numbers = values
+					.iter()
+					.map(|value| value.parse::<f32>())
+					.collect::<Result<Vec<_>, _>>()
+					.map_err(|_| anyhow!("ambient {} takes numbers", part));
+				match (*part, values) {
+					("sky" | "ground", [_, _, _]) => {
+						let numbers = numbers?;
+						let color = [numbers[0], numbers[1], numbers[2]];
+						match *part {
+							"sky" => hemisphere.sky_color = color,
+							_ => hemisphere.ground_color = color,
+						}
+					}
+					("intensity", [_]) => hemisphere.intensity = numbers?[0],
+					("mode", ["fallback"]) => hemisphere.mode = AmbientMode::Fallback,
+					("mode", ["additive"]) => hemisphere.mode = AmbientMode::Additive,
+					_ => bail!("ambient takes sky <r> <g> <b>, ground <r> <g> <b>, intensity <value>, or mode <fallback or additive>"),
+				}
+				Ok(hemisphere.description())
+			}
+			("fog", []) => Ok(self.scene.environment.fog.description()),
+			("fog", [part, values @ ..]) => {
+				let fog = &mut self.scene.environment.fog;
+				let numbers = values
+					.iter()
+					.map(|value| value.parse::<f32>())
+					.collect::<Result<Vec<_>, _>>()
+					.map_err(|_| anyhow!("fog {} takes numbers", part));
+				match (FogMode::ALL.iter().find(|mode| mode.name() == *part), *part, values) {
+					(Some(mode), _, []) => fog.mode = *mode,
+					(_, "color", [_, _, _]) => {
+						let numbers = numbers?;
+						fog.color = [numbers[0], numbers[1], numbers[2]];
+					}
+					(_, "distance", [_, _]) => {
+						let numbers = numbers?;
+						fog.start = numbers[0];
+						fog.end = numbers[1];
+					}
+					(_, "density", [_]) => fog.density = numbers?[0],
+					(_, "height", [_, _]) => {
+						let numbers = numbers?;
+						fog.base_height = numbers[0];
+						fog.height_falloff = numbers[1];
+					}
+					_ => bail!("fog takes off, linear, exponential, height, color <r> <g> <b>, distance <start> <end>, density <value>, or height <base> <falloff>"),
+				}
+				Ok(fog.description())
+			}
+			("grading", []) => Ok(self.scene.environment.grading.description()),
+			("grading", [part, value]) => {
+				let grading = &mut self.scene.environment.grading;
+				let number = value.parse::<f32>().map_err(|_| anyhow!("grading {} takes a number", part));
+				match *part {
+					"saturation" => grading.saturation = number?,
+					"contrast" => grading.contrast = number?,
+					"vignette" => grading.vignette = number?,
+					"grain" => grading.grain = number?,
+					_ => bail!("grading takes saturation <value>, contrast <value>, vignette <value>, or grain <value>"),
+				}
+				Ok(grading.description())
+			}
+			("environment", []) => Ok(self.scene.environment.description()),
+			("environment", ["save"]) => {
+				// Saved into the source assets rather than the copy the build made, which the next build would overwrite
+				self.scene.save_environment(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"))?;
+				Ok(format!("Saved the environment to {}", self.scene.file.as_deref().unwrap_or_default()))
+			}
+			("environment", [part, values @ ..]) => {
+				let environment = &mut self.scene.environment;
+				let numbers = values
+					.iter()
+					.map(|value| value.parse::<f32>())
+					.collect::<Result<Vec<_>, _>>()
+					.map_err(|_| anyhow!("environment {} takes numbers", part));
+				match (*part, values) {
+					("sky", [_, _, _]) => {
+						let numbers = numbers?;
+						environment.sky_ambient = [numbers[0], numbers[1], numbers[2]];
+					}
+					("exposure", [_]) => environment.exposure = numbers?[0],
+					// Loaded at the start of the next frame, once the environment names another sky map
+					("sky_map", ["none"]) => environment.sky_map = None,
+					("sky_map", [file]) => environment.sky_map = Some(CubemapSource::Equirectangular(String::from(*file))),
+					("sky_map", files) if files.len() == 6 => environment.sky_map = Some(CubemapSource::Faces(cube_face_files(files.iter().copied())?)),
+					("sky_map_intensity", [_]) => environment.sky_map_intensity = numbers?[0],
+					_ => bail!("environment takes sky <r> <g> <b>, exposure <value>, sky_map <panorama, 6 faces, or none>, sky_map_intensity <value>, or save"),
+				}
+				Ok(environment.description())
+			}
+			("spawn", [prefab]) => self.spawn_prefab(prefab),
+			// Entity names can have spaces, like the spawned prefabs' do
+			("despawn", name) if !name.is_empty() => {
+				let name = name.join(" ");
+				match self.scene.despawn(&name) {
+					Some(_) => Ok(format!("Despawned {}", name)),
+					None => bail!("There is no entity named \"{}\"", name),
+				}
+			}
+			("scene", []) => {
+				let files = self.scene.additive_scenes.iter().map(|additive_scene| additive_scene.file.as_str()).collect::<Vec<_>>();
+				match files.as_slice() {
+					[] => Ok(String::from("No scenes are loaded on top of the scene")),
+					files => Ok(format!("Loaded on top of the scene: {}", files.join(", "))),
+				}
+			}
+			("scene", ["load", file]) => self.load_additive_scene(file),
+			("scene", ["unload", file]) => self.unload_additive_scene(file),
+			("stream", []) => match &self.world_streaming {
+				Some(world_streaming) => Ok(format!(
+					"Streaming {}: {} of its {} chunks loaded and {} loading",
+					world_streaming.manifest,
+					world_streaming.loaded_count(),
+					world_streaming.chunks.len(),
+					world_streaming.loading_count()
+				)),
+				None => Ok(String::from("No world is being streamed")),
+			},
+			("stream", ["off"]) => match &mut self.world_streaming {
+				Some(world_streaming) => {
+					world_streaming.stopping = true;
+					Ok(format!("Stopped streaming {}, unloading its chunks", world_streaming.manifest))
+				}
+				None => bail!("No world is being streamed"),
+			},
+			("stream", [file]) => {
+				if self.world_streaming.is_some() {
+					bail!("A world is already being streamed, so stop it with \"stream off\" first");
+				}
+
+				let world_streaming = WorldStreaming::from_file(&self.assets_path, file)?;
+				let output = format!(
+					"Streaming {}, with {} chunks loaded within {} meters of the camera",
+					file,
+					world_streaming.chunks.len(),
+					world_streaming.load_distance
+				);
+				self.world_streaming = Some(world_streaming);
+				Ok(output)
+			}
+			("load", [file]) => {
+				let timeline = Timeline::load(&self.assets_path, file)?;
+				let duration = timeline.duration;
+				self.sequencer.timeline = Some(Arc::new(timeline));
+				self.sequencer.stop();
+				self.sequencer.play();
+				Ok(format!("Playing {}, {:.1} seconds long", file, duration))
+			}
+			("stats", []) => {
+				let passes = self
+					.frame_stats
+					.passes
+					.iter()
+					.map(|(pass, stats)| format!("{}: {} draws, {} triangles", pass, stats.draw_calls, stats.triangles));
+				Ok([self.frame_stats.summary()].into_iter().chain(passes).collect::<Vec<_>>().join("\n"))
+			}
+			("gpu", []) => Ok(self.context.gpu_report.clone()),
+			("benchmark", arguments) if arguments.len() <= 1 => {
+				let entity_count = match arguments {
+					[entity_count] => entity_count.parse::<usize>().map_err(|_| anyhow!("benchmark takes a number of entities"))?,
+					_ => 20000,
+				};
+				if entity_count == 0 {
+					bail!("benchmark needs at least one entity");
+				}
+
+				let camera = self.scene.find_entity(self.active_camera.as_str()).unwrap().get_cameras()[0];
+				let frustum = Frustum::from_matrix(camera.camera_uniform.vp_matrix());
+				traversal_benchmark::run(&mut self.scene, &frustum, entity_count, &self.context.device)
+			}
+			(setting, []) if RenderSettings::SETTING_NAMES.contains(&setting) => Ok(format!("{} {}", setting, self.render_settings.setting(setting).unwrap_or_default())),
+			(setting, [value]) if RenderSettings::SETTING_NAMES.contains(&setting) => {
+				self.render_settings.set_setting(setting, value)?;
+				Ok(format!("{} {}", setting, self.render_settings.setting(setting).unwrap_or_default()))
+			}
+			_ => bail!("Can't run \"{}\", see help for the commands", line),
+		}
+	}
+
+	// Answers whatever the debug server's clients asked for since the last frame
+	#[cfg(feature = "debug-server")]
+	fn serve_debug_requests(&mut self) {
+		let requests = match &mut self.debug_server {
+			Some(debug_server) => debug_server.poll(),
+			None => return,
+		};
+
+		for request in requests {
+			let result = self.answer_debug_request(&request.method, &request.params);
+			if let Some(debug_server) = &mut self.debug_server {
+				debug_server.respond(&request, result);
+			}
+		}
+	}
+
+	// The methods are:
+	// - `scene` for the whole entity hierarchy
+	// - `stats` for the last frame's draw statistics and CPU timings
+	// - `settings` for the render settings and exposure
+	// - `set` with `{ "name": ..., "value": ... }` params to change one of those settings, returning them all
+	// - `command` with `{ "line": ... }` params to run a console command, returning what it printed
+	#[cfg(feature = "debug-server")]
+	fn answer_debug_request(&mut self, method: &str, params: &crate::json::JsonValue) -> anyhow::Result<crate::json::JsonValue> {
+		use crate::json::JsonValue;
+
+		let settings = |engine: &Self| {
+			let mut settings = render_settings_json(&engine.render_settings);
+			if let JsonValue::Object(entries) = &mut settings {
+				let exposure = engine.scene.find_entity(engine.active_camera.as_str()).unwrap().get_cameras()[0].exposure;
+				entries.push((String::from("exposure"), JsonValue::Number(exposure as f64)));
+			}
+			settings
+		};
+
+		match method {
+			"scene" => Ok(entity_json(&self.scene.root)),
+			"stats" => Ok(frame_stats_json(&self.frame_stats)),
+			"settings" => Ok(settings(self)),
+			"set" => {
+				let name = params.get("name").and_then(JsonValue::as_str).ok_or_else(|| anyhow!("set needs the name of a setting"))?;
+				let value = match params.get("value") {
+					Some(JsonValue::String(value)) => value.clone(),
+					Some(JsonValue::Number(value)) => value.to_string(),
+					Some(JsonValue::Bool(value)) => String::from(if *value { "on" } else { "off" }),
+					_ => bail!("set needs a value"),
+				};
+
+				if name == "exposure" {
+					let exposure = value.parse::<f32>().map_err(|_| anyhow!("exposure takes a number"))?;
+					self.scene.find_entity_mut(self.active_camera.as_str()).unwrap().get_cameras_mut()[0].exposure = exposure;
+				} else {
+					self.render_settings.set_setting(name, &value)?;
+				}
+				Ok(settings(self))
+			}
+			"command" => {
+				let line = params.get("line").and_then(JsonValue::as_str).ok_or_else(|| anyhow!("command needs a line to run"))?;
+				Ok(JsonValue::String(self.run_console_command(line)?))
+			}
+			_ => bail!("There is no method named \"{}\"", method),
+		}
+	}
+
+	// Adds a copy of the prefab a few meters in front of the camera, where it stays
+	fn spawn_prefab(&mut self, prefab: &str) -> anyhow::Result<String> {
+		let scale = match CONSOLE_PREFABS.iter().find(|(name, _)| *name == prefab) {
+			Some((_, scale)) => *scale,
+			None => bail!("There is no prefab named \"{}\"", prefab),
+		};
+
+		let camera = self.scene.find_entity(self.active_camera.as_str()).unwrap().get_cameras()[0];
+		let direction = cgmath::Vector3::new(camera.yaw.0.cos(), camera.pitch.0.sin(), camera.yaw.0.sin()).normalize();
+		let location = (camera.location + direction * 3.).cast::<f64>().unwrap();
+
+		// Numbered by the first number not taken, which may be one freed up by despawning
+		let name = (1..)
+			.map(|number| format!("Spawned {} {}", prefab, number))
+			.find(|name| !self.scene.root.iter().any(|entity| entity.name == *name))
+			.unwrap();
+		let mut entity = Entity::new(&name);
+		entity.transform.location = location;
+
+		let mut model = Model::new(("cube.obj", "BeveledCube"));
+		model
+			.instances
+			.transform_single_instance(location, cgmath::Quaternion::new(1., 0., 0., 0.), cgmath::Point3::from(scale), &self.context.device);
+		entity.add_component(Component::Model(Box::new(model)));
+		self.scene.spawn(None, entity, &self.context.device)?;
+
+		Ok(format!("Spawned {} at {:.1}, {:.1}, {:.1}", name, location.x, location.y, location.z))
+	}
+
+	// Hides the cursor and holds it in place while the camera is looking around, and gives it back otherwise so the window behaves like any other
+	// Locking the cursor in place isn't supported everywhere, so it's confined to the window instead where it isn't
+	fn update_cursor_grab(&mut self, window: &Window) {
+		let looking = self.camera_controller.looking();
+		if looking == self.cursor_grabbed {
+			return;
+		}
+		self.cursor_grabbed = looking;
+
+		let grabbed = match looking {
+			true => window.set_cursor_grab(CursorGrabMode::Locked).or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined)),
+			false => window.set_cursor_grab(CursorGrabMode::None),
+		};
+		if let Err(error) = grabbed {
+			eprintln!("Can't grab the cursor: {}", error);
+		}
+		window.set_cursor_visible(!looking);
+	}
+
 	pub fn draw_frame(&mut self, window: &Window, control_flow: &mut ControlFlow) {
+		// Capping the frame rate by waiting out the rest of the frame's time keeps the GPU idle in between, saving power
+		if let Some(frame_rate_cap) = self.render_settings.profile.frame_rate_cap() {
+			let remaining_time = std::time::Duration::from_secs_f64(1. / frame_rate_cap).saturating_sub(self.frame_time.elapsed());
+			std::thread::sleep(remaining_time);
+		}
+
 		let now = std::time::Instant::now();
 		let dt = now - self.frame_time;
 		self.frame_time = now;
+
+		// Until the assets have loaded there's no scene to update or render, so the frame only shows how far along they are
+		if let Some(progress) = self.poll_loading() {
+			window.set_title(&format!("Seurat | Loading {}/{}", progress.loaded, progress.total));
+			if let Err(error) = self.render_loading_screen(progress) {
+				eprintln!("{:?}", error);
+			}
+			return;
+		}
+
+		self.update_cursor_grab(window);
+		self.gpu_capture.begin_frame(&self.context.device);
+		if let Some(gpu_time) = self.gpu_timer.as_mut().and_then(|gpu_timer| gpu_timer.read(&self.context.device)) {
+			self.frame_stats.record_gpu_time(gpu_time);
+		}
 		self.update(dt);
 
 		match self.render() {
 			Ok(_) => {}
-			// Reconfigure the surface if lost
-			Err(wgpu::SurfaceError::Lost) => self.resize(window.inner_size()),
+			// Reconfigure the surface if lost, rebuilding what draws into it if it came back with another format
+			Err(wgpu::SurfaceError::Lost) => {
+				if self.context.renegotiate_surface_format() {
+					self.rebuild_surface_pipelines();
+				}
+				self.resize(window.inner_size());
+			}
 			// The system is out of memory, we should probably quit
 			Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
 			// All other errors (Outdated, Timeout) should be resolved by the next frame
 			Err(e) => eprintln!("{:?}", e),
 		}
+		self.gpu_capture.end_frame(&self.context.device);
+
+		// There's no text rendering for an overlay, so the totals are shown in the window title
+		if let Some(summary) = self.frame_stats.take_summary() {
+			window.set_title(&format!("Seurat | {}", summary));
+		}
+	}
+
+	// Fills in a crash report the panic hook started with the last presented frame, the scene, the render settings, and the adapter
+	// The frame is read back last since it needs the GPU, which may be what went wrong
+	pub fn write_crash_report(&self, folder: &Path) {
+		crash_report::write_file(folder, "adapter.txt", |path| Ok(std::fs::write(path, crash_report::describe_adapter(&self.context))?));
+		crash_report::write_file(folder, "scene.txt", |path| Ok(std::fs::write(path, crash_report::describe_scene(&self.scene.root))?));
+		crash_report::write_file(folder, "render_settings.txt", |path| {
+			Ok(std::fs::write(path, crash_report::describe_render_settings(&self.render_settings))?)
+		});
+		crash_report::write_file(folder, "frame.png", |path| self.last_frame.save(&self.context, path));
+	}
+
+	// Rebuilds the pipelines of every shader whose file was saved since the last check, so shaders can be worked on without restarting
+	fn reload_changed_shaders(&mut self) {
+		let changed_shaders = self.shader_watcher.changed_shaders();
+		if changed_shaders.is_empty() {
+			return;
+		}
+
+		for file in changed_shaders {
+			// Variants like the cutout deferred shader are built from the same file under their own names
+			for shader in self.scene.resources.shaders.values_mut().filter(|shader| shader.name == file) {
+				match shader.reload(&self.context, self.shader_watcher.directory()) {
+					Ok(_) => println!("Reloaded shader {}", file),
+					Err(error) => eprintln!("Can't reload shader {}, so the previous version is kept: {}", file, error),
+				}
+			}
+		}
+
+		// The reloaded shaders may draw the materials using them differently
+		self.scene.raise(SceneEvent::MaterialChanged);
 	}
 
 	fn update(&mut self, delta_time: std::time::Duration) {
+		let update_start = std::time::Instant::now();
+
+		// What was written last frame becomes the previous frame's, both for the temporal textures and the instances' model matrices
+		self.frame_textures.advance_frame();
+		crate::instance::set_frame_index(self.frame_textures.frame_index);
+		self.update_distance_field_uniform();
+
+		// Commands typed into the console run before anything else so their changes show up this frame
+		for command in self.console.take_submitted() {
+			let output = self.run_console_command(&command).unwrap_or_else(|error| format!("Error: {}", error));
+			self.console.print(&output);
+		}
+		self.console.update(&self.context.queue, self.context.surface_configuration.width);
+		#[cfg(feature = "debug-server")]
+		self.serve_debug_requests();
+
+		// The profile can be changed at any time through the settings, so its effects are applied once it's settled for the frame
+		if self.render_settings.profile != self.applied_render_profile {
+			self.apply_render_profile();
+		}
+		if self.render_settings.rasterization != self.applied_rasterization {
+			self.apply_rasterization();
+		}
+
+		self.reload_changed_shaders();
+		// Chunks streamed in or out raise their scene events before the hooks run
+		self.update_world_streaming(delta_time);
+		self.run_scene_hooks();
+		if self.environment_bindings.sky_map_outdated(&self.scene.environment) {
+			self.load_sky_map();
+		}
+		self.environment_bindings.update(&self.scene.environment, &self.context.queue);
+		self.final_composite.update(&self.context.queue, &self.scene.environment.grading);
+
 		// Camera
+		let follow_target_location = match self.follow_camera_controller.target() {
+			Some(target) if self.follow_camera_controller.active() => self.scene.find_entity(target).map(|entity| entity.transform.location.cast::<f32>().unwrap()),
+			_ => None,
+		};
 		let scene_camera = &mut self.scene.find_entity_mut(self.active_camera.as_str()).unwrap().get_cameras_mut()[0];
-		self.camera_controller.update_camera(scene_camera, delta_time);
+		// The fly camera is set aside while following or walking, and picks up from wherever the other controller left the camera
+		if let Some(target_location) = follow_target_location {
+			self.follow_camera_controller.update_camera(scene_camera, target_location, &self.collision_world, delta_time);
+		} else if self.character_controller.active() {
+			self.character_controller.update_camera(scene_camera, &self.collision_world, delta_time);
+		} else {
+			self.camera_controller.update_camera(scene_camera, delta_time);
+		}
+		// A playing timeline takes over from the controllers, which carry on from wherever it leaves the camera
+		let timeline_events = self.sequencer.update(delta_time);
+		self.sequencer.apply_camera(scene_camera);
+		scene_camera.exposure = self.sequencer.parameter(TimelineParameter::Exposure, scene_camera.exposure);
+		let taa_enabled = scene_camera.post_effects.contains(&PostEffect::TemporalAntiAliasing);
+		let resolution = (self.context.surface_configuration.width, self.context.surface_configuration.height);
+		scene_camera.jitter = self.temporal_anti_aliasing.update(taa_enabled, resolution, &self.context.queue);
+		self.ssao_temporal.update(scene_camera.post_effects.contains(&PostEffect::AmbientOcclusion), &self.context.queue);
 		scene_camera.update_v_p_matrices(&mut self.context.queue);
 
+		// Render layers see from wherever the world camera does
+		for layer in &mut self.render_layers {
+			layer.follow(scene_camera, &mut self.context.queue);
+		}
+
+		self.foveation.update(&self.context.queue, self.render_settings.foveation, self.render_settings.checkerboard);
+		self.weather.intensity = self.sequencer.parameter(TimelineParameter::WeatherIntensity, self.weather.intensity);
+		self.weather.update(&self.context.queue, delta_time);
+
+		// The eyes sit either side of the world camera, which stands in for the tracked head
+		if self.render_settings.stereo {
+			self.stereo_rig.follow_head(scene_camera, &self.context.queue);
+		}
+
+		// The weapon is held at the same spot in view however the camera moves, low and to the right
+		let inverse_v_matrix = scene_camera.camera_uniform.v_matrix().invert().unwrap();
+		let (right, up, back) = (inverse_v_matrix.x.truncate(), inverse_v_matrix.y.truncate(), inverse_v_matrix.z.truncate());
+		let weapon_location = inverse_v_matrix.w.truncate() + right * 0.25 - up * 0.2 - back * 0.6;
+		let weapon_rotation = cgmath::Quaternion::from(cgmath::Matrix3::from_cols(right, up, back));
+		let first_person_weapon = self.scene.find_entity_mut("First Person Weapon").unwrap();
+		for model in &mut first_person_weapon.get_models_mut() {
+			let location = cgmath::Point3::new(weapon_location.x as f64, weapon_location.y as f64, weapon_location.z as f64);
+			let rotation = cgmath::Quaternion::new(weapon_rotation.s as f64, weapon_rotation.v.x as f64, weapon_rotation.v.y as f64, weapon_rotation.v.z as f64);
+			let scale = cgmath::Point3 { x: 0.04, y: 0.04, z: 0.3 };
+			model.instances.transform_single_instance(location, rotation, scale, &self.context.device);
+		}
+
 		// Light
 		let old_position: cgmath::Vector3<_> = self.scene_lighting.light_uniform.location.into();
-		let new_position = cgmath::Quaternion::from_axis_angle((0.0, 1.0, 0.0).into(), cgmath::Deg(25.0 * delta_time.as_secs_f32())) * old_position;
-		self.scene_lighting.light_uniform.location = new_position.into();
-		self.context
-			.queue
-			.write_buffer(&self.scene_lighting.light_buffer, 0, bytemuck::cast_slice(&[self.scene_lighting.light_uniform]));
+		// A lamp selected in the light editor stays where it's put instead of orbiting
+		let mut new_position = if self.light_gizmos.selected() {
+			old_position
+		} else {
+			cgmath::Quaternion::from_axis_angle((0.0, 1.0, 0.0).into(), cgmath::Deg(25.0 * delta_time.as_secs_f32())) * old_position
+		};
+
+		// The time of day moves the sun and tells the scripts when it rises and sets
+		self.time_of_day.hour = self.sequencer.parameter(TimelineParameter::Hour, self.time_of_day.hour);
+		let previous_global_illumination = self.time_of_day.global_illumination();
+		for event in self.time_of_day.update(delta_time) {
+			self.scene.root.send_time_of_day_event_to_descendants(event);
+		}
+		let time_of_day_lighting = self.time_of_day.lighting();
+		let (sky_ambient, lamp_scale) = if self.time_of_day.enabled {
+			if let Some(sun_entity) = self.scene.find_entity_mut("Sun") {
+				for light in sun_entity.get_lights_mut() {
+					if let Light::Sun(sun) = light {
+						sun.set_sky_position(self.time_of_day.sun_elevation(), self.time_of_day.sun_azimuth());
+						sun.illuminance = time_of_day_lighting.sun_illuminance;
+					}
+				}
+			}
+			(time_of_day_lighting.sky_ambient, time_of_day_lighting.lamp_scale)
+		} else {
+			(self.scene.environment.sky_ambient, 1.)
+		};
+		// Dynamic GI is voxelized over and over as the light changes, and handing off back to baked GI voxelizes it once more with the light it settled on
+		let global_illumination = self.time_of_day.global_illumination();
+		if global_illumination == GlobalIllumination::Dynamic || global_illumination != previous_global_illumination {
+			self.voxel_update_scheduler.mark_all_dirty();
+		}
+
+		let sun = self.scene.find_entity("Sun").and_then(|entity| {
+			entity.get_lights().into_iter().find_map(|light| match light {
+				Light::Sun(sun) => Some(*sun),
+				_ => None,
+			})
+		});
+		let screen_size = (self.context.surface_configuration.width, self.context.surface_configuration.height);
+		let gizmo_view = GizmoView::new(self.scene.find_entity(self.active_camera.as_str()).unwrap().get_cameras()[0], screen_size);
+		self.debug_draw.clear();
+		if let Some(kind) = self.render_settings.collider_view {
+			self.draw_colliders(kind);
+		}
+		if self.render_settings.freeze_frustum {
+			let camera = self.scene.find_entity(self.active_camera.as_str()).unwrap().get_cameras()[0];
+			let (vp_matrix, _) = *self.frozen_culling_view.get_or_insert((camera.camera_uniform.vp_matrix(), camera.location));
+			self.debug_draw.frustum(vp_matrix, [1., 0.8, 0.2, 1.]);
+		} else {
+			self.frozen_culling_view = None;
+		}
+		// Copied out since the lamp's entity is borrowed from the same scene while its light is worked out
+		let ambient = self.scene.environment.ambient;
 		let lamp_model = self.scene.find_entity_mut("Lamp Model").unwrap();
+		if let Some(Light::Lamp(lamp)) = lamp_model.get_lights_mut().into_iter().next() {
+			if self.render_settings.light_gizmos {
+				let mut lamp_location = cgmath::Point3::from_vec(new_position);
+				self.light_gizmos.update(&gizmo_view, &mut lamp_location, lamp);
+				self.light_gizmos.draw(&mut self.debug_draw, &gizmo_view, lamp_location, lamp, sun.as_ref());
+				new_position = lamp_location.to_vec();
+			} else {
+				self.light_gizmos.deselect();
+			}
+
+			self.scene_lighting.light_uniform = LightUniform::new(new_position.into(), lamp, sun.as_ref(), sky_ambient);
+			self.scene_lighting.light_uniform.intensity *= lamp_scale;
+			let voxel_global_illumination = self.render_settings.profile.voxel_global_illumination();
+			self.scene_lighting.light_uniform.set_hemisphere(&ambient, voxel_global_illumination);
+			self.scene_lighting.set_profile(&self.context, lamp.profile.as_ref());
+		}
 		let location = cgmath::Point3 {
 			x: new_position.x as f64,
 			y: new_position.y as f64,
 			z: new_position.z as f64,
 		};
+		// The lamp's entity follows its orbit so it's gathered with the rest of the scene's lights from where it's drawn
+		lamp_model.transform.location = location;
 		let rotation = lamp_model.transform.rotation;
 		let scale = cgmath::Point3 { x: 0.25, y: 0.25, z: 0.25 };
 		for model in &mut lamp_model.get_models_mut() {
@@ -785,265 +3850,1326 @@ impl Engine {
 		}
 
 		// Debugging
-		self.debug_buffer.update(delta_time, &mut self.context.queue);
+		self.debug_buffer.update(delta_time);
+
+		// The scene's environment balances the exposure of whichever camera is active
+		let exposure = self.scene.find_entity(self.active_camera.as_str()).unwrap().get_cameras()[0].exposure * self.scene.environment.exposure;
+		// Debug shading is written to reach the screen at its own brightness under the fixed exposure, which the eye adaptation would throw off
+		let auto_exposure_enabled = self.render_settings.auto_exposure && self.render_settings.debug_shading == DebugShading::None;
+		self.auto_exposure.update(&self.context.queue, delta_time, auto_exposure_enabled, &self.render_settings);
+		self.context.globals.update(
+			&self.context.queue,
+			&self.context.surface_configuration,
+			delta_time,
+			exposure,
+			self.debug_buffer.values,
+			self.render_settings.debug_shading,
+		);
+
+		// Analyze whatever audio was pushed through `audio_analysis.input()` since the last frame
+		if self.render_settings.audio_test_beat {
+			self.test_beat.play(&self.audio_analysis.input(), delta_time);
+		}
+		self.audio_analysis.update(delta_time);
+		self.context.globals.update_audio(&self.context.queue, &self.audio_analysis.uniform);
+
+		// Call update() on all entity behaviors
+		let behaviors_start = std::time::Instant::now();
+		let behavior_context = BehaviorContext::new(delta_time, &self.behavior_input, &self.scene.root);
+		self.scene.root.update_behaviors_of_descendants(&behavior_context);
+		let behaviors_time = behaviors_start.elapsed();
+
+		// The timeline's transform tracks are mixed over wherever the behaviors left their entities, and its events go out once everything is in place
+		self.sequencer.apply_transforms(&mut self.scene);
+		for event in &timeline_events {
+			let recipient = match &event.entity {
+				Some(entity) => self.scene.find_entity_mut(entity),
+				None => Some(&mut self.scene.root),
+			};
+			if let Some(recipient) = recipient {
+				recipient.send_timeline_event_to_descendants(event);
+			}
+		}
+
+		// Move the tracked cube along its motion track
+		if let Some(tracked_cube) = self.scene.find_entity_mut("Tracked Cube") {
+			let location = tracked_cube.transform.location;
+			let rotation = tracked_cube.transform.rotation;
+			let scale = cgmath::Point3 { x: 0.3, y: 0.15, z: 0.5 };
+			for model in &mut tracked_cube.get_models_mut() {
+				model.instances.transform_single_instance(location, rotation, scale, &self.context.device);
+			}
+		}
+
+		if let Some(spline_cart) = self.scene.find_entity_mut("Spline Cart") {
+			let location = spline_cart.transform.location;
+			let rotation = spline_cart.transform.rotation;
+			let scale = cgmath::Point3 { x: 0.2, y: 0.2, z: 0.35 };
+			for model in &mut spline_cart.get_models_mut() {
+				model.instances.transform_single_instance(location, rotation, scale, &self.context.device);
+			}
+		}
+
+		// Children follow wherever their parents were moved this frame, before the lights are gathered from where their entities ended up
+		self.scene.root.update_world_transforms_of_descendants(&Transform::default(), &self.context.device);
+		self.scene_lighting.update_punctual_lights(&self.context.queue, &self.scene.root, lamp_scale);
+
+		// The cascades follow the first sun in the scene, the same one the punctual lights mark as having cascaded shadows
+		let shadow_sun = self
+			.scene
+			.root
+			.iter()
+			.filter(|entity| entity.enabled)
+			.flat_map(|entity| entity.get_lights())
+			.find_map(|light| match light {
+				Light::Sun(sun) => Some(*sun),
+				_ => None,
+			});
+		let view_camera = &self.scene.find_entity(self.active_camera.as_str()).unwrap().get_cameras()[0].camera_uniform;
+		self.shadow_maps.update(view_camera, shadow_sun.as_ref(), &self.context.queue);
+
+		// Likewise the point shadow map follows the first lamp, the one the punctual lights mark as having point shadows
+		let shadow_lamp = self.scene.root.iter().filter(|entity| entity.enabled).find_map(|entity| {
+			entity.get_lights().into_iter().find_map(|light| match light {
+				Light::Lamp(lamp) => Some((entity, lamp)),
+				_ => None,
+			})
+		});
+		self.point_shadow_map.update(shadow_lamp, &self.context.queue);
+		self.context
+			.queue
+			.write_buffer(&self.scene_lighting.light_buffer, 0, bytemuck::cast_slice(&[self.scene_lighting.light_uniform]));
+
+		// Mirrors
+		let view_camera = self.scene.find_entity(self.active_camera.as_str()).unwrap().get_cameras()[0].camera_uniform;
+		self.scene.root.update_mirrors_of_descendants(&view_camera, &self.context.queue);
+
+		// Portals
+		let portal_frames = self
+			.scene
+			.root
+			.iter()
+			.flat_map(|entity| entity.get_portals().into_iter().map(move |portal| (entity.name.clone(), (entity.transform, portal.normal))))
+			.collect::<HashMap<_, _>>();
+		self.scene.root.update_portals_of_descendants(&portal_frames, &view_camera, &self.context.queue);
+
+		// Voxel GI
+		self.voxel_update_scheduler.track_transform_changes(&self.scene.root);
+		if self.render_settings.profile.voxel_global_illumination() {
+			self.voxel_update_scheduler.begin_frame(&self.context.queue);
+		} else {
+			self.voxel_update_scheduler.pause();
+		}
+
+		// Everything besides the behaviors is spent updating the buffers read by the GPU
+		self.frame_stats.record_cpu_time(CpuTimingScope::Behaviors, behaviors_time);
+		self.frame_stats.record_cpu_time(CpuTimingScope::Uniforms, update_start.elapsed() - behaviors_time);
+	}
+
+	// Outlines the chosen kind of collider of every static model, generating or loading each from the cache the first time it's shown
+	fn draw_colliders(&mut self, kind: ColliderKind) {
+		const COLLIDER_COLOR: [f32; 4] = [0.2, 1., 0.4, 1.];
+
+		for entity in &self.scene.root {
+			for model in entity.get_models() {
+				let mesh_handle = match model.mesh {
+					Some(mesh_handle) if model.mobility == Mobility::Static => mesh_handle,
+					_ => continue,
+				};
+
+				let ((model_file, mesh_name), mesh) = self.scene.resources.meshes.get_by_handle(mesh_handle).unwrap();
+				let (_, wireframe) = self.colliders.entry((mesh_handle, kind)).or_insert_with(|| {
+					let collider = Collider::load_or_generate(&self.collider_cache_directory, &format!("{}_{}", model_file, mesh_name), kind, &mesh.collision_triangles);
+					let wireframe = collider.wireframe();
+					(collider, wireframe)
+				});
+
+				for instance in model.instances.world_instances() {
+					let to_world = |point: cgmath::Point3<f32>| {
+						cgmath::Point3::from_vec(instance.location + instance.rotation * cgmath::Vector3::new(point.x * instance.scale.x, point.y * instance.scale.y, point.z * instance.scale.z))
+					};
+
+					for [start, end] in wireframe.iter() {
+						self.debug_draw.line(to_world(*start), to_world(*end), COLLIDER_COLOR);
+					}
+				}
+			}
+		}
+	}
+
+	// A progress bar across the middle of the window, built from the debug lines since they're all that can be drawn before the shaders are loaded
+	fn render_loading_screen(&mut self, progress: LoadProgress) -> Result<(), wgpu::SurfaceError> {
+		let surface_texture = self.context.surface.get_current_texture()?;
+		let surface_texture_view = surface_texture.texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+		// One line for each row of pixels the bar covers, drawn straight in clip space
+		let pixel_height = 2. / self.context.surface_configuration.height as f32;
+		let filled = -0.5 + progress.fraction();
+		self.debug_draw.clear();
+		for row in -LOADING_BAR_HEIGHT / 2..LOADING_BAR_HEIGHT / 2 {
+			let y = (row as f32 + 0.5) * pixel_height;
+			self.debug_draw.line(cgmath::Point3::new(-0.5, y, 0.), cgmath::Point3::new(0.5, y, 0.), [0.2, 0.2, 0.2, 1.]);
+			self.debug_draw.line(cgmath::Point3::new(-0.5, y, 0.), cgmath::Point3::new(filled, y, 0.), [0.9, 0.9, 0.9, 1.]);
+		}
+		self.debug_draw.upload(&self.context, cgmath::Matrix4::identity());
+
+		let mut encoder = self.context.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+			label: Some("Loading Screen Encoder"),
+		});
+		{
+			let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: Some("Loading Screen"),
+				color_attachments: &[wgpu::RenderPassColorAttachment {
+					view: &surface_texture_view,
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+						store: true,
+					},
+				}],
+				depth_stencil_attachment: None,
+			});
+			self.debug_draw.draw(&mut render_pass);
+		}
+		self.context.queue.submit(std::iter::once(encoder.finish()));
+		surface_texture.present();
+
+		Ok(())
+	}
+
+	fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+		let acquire_start = std::time::Instant::now();
+		let surface_texture = self.context.surface.get_current_texture()?;
+		self.frame_stats.record_cpu_time(CpuTimingScope::Acquire, acquire_start.elapsed());
+
+		let encode_start = std::time::Instant::now();
+		let surface_texture_view = surface_texture.texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+		// Allocated before any pass borrows them, for effects added since the last frame
+		self.transient_attachments
+			.allocate(&self.context.device, &self.context.surface_configuration, &self.effects, &self.scene.resources, &self.frame_textures);
+
+		let mut encoder = self.context.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Render Encoder") });
+		if let Some(gpu_timer) = &self.gpu_timer {
+			gpu_timer.begin(&mut encoder);
+		}
+
+		self.draw_lists.refresh(&self.scene);
+
+		// Shadows are finished before anything that's shaded, and reflections next since they're sampled by the mirrors drawn in the main view
+		self.render_shadow_cascades(&mut encoder);
+		self.render_point_shadow(&mut encoder);
+		self.render_mirrors(&mut encoder);
+		self.render_portals(&mut encoder);
+		self.render_layers(&mut encoder);
+		self.render_stereo_eyes(&mut encoder);
+
+		// The voxel lightmap covers the whole volume regardless of where the camera looks, so only the deferred pass is culled
+		// A frozen frustum culls from where it was frozen, though the GPU-culled meshlets and scatter layers still follow the camera since they read its buffer
+		let scene_camera = self.scene.find_entity(self.active_camera.as_str()).unwrap().get_cameras()[0];
+		let (culling_vp_matrix, eye) = self.frozen_culling_view.unwrap_or((scene_camera.camera_uniform.vp_matrix(), scene_camera.location));
+		let frustum = Frustum::from_matrix(culling_vp_matrix);
+		self.debug_draw.upload(&self.context, scene_camera.camera_uniform.vp_matrix());
+		self.draw_lists.cull(&self.scene, "Scene: Render Deferred", &frustum);
+		self.draw_lists.cull(&self.scene, "Scene: Render Impostors", &frustum);
+
+		// Models beyond their impostor distance are drawn as billboards instead of their full meshes
+		self.draw_lists.retain_visible(&self.scene, "Scene: Render Deferred", |model| !model.uses_impostor(eye));
+		self.draw_lists.retain_visible(&self.scene, "Scene: Render Impostors", |model| model.uses_impostor(eye));
+
+		let transparent_pass_name = match self.render_settings.transparency {
+			TransparencyMode::SortedBlending => "Scene: Render Transparent Sorted",
+			TransparencyMode::WeightedBlended => "Scene: Render Transparent Weighted",
+		};
+		self.draw_lists.cull(&self.scene, transparent_pass_name, &frustum);
+		if self.render_settings.transparency == TransparencyMode::SortedBlending {
+			self.draw_lists.sort_back_to_front(&self.scene, transparent_pass_name, scene_camera.location);
+		}
+		self.draw_lists.cull(&self.scene, "Scene: Render Glass", &frustum);
+		self.draw_lists.cull(&self.scene, "Scene: Render Mirrors", &frustum);
+		self.draw_lists.cull(&self.scene, "Scene: Render Portals", &frustum);
+
+		// Passes with nothing to do this frame stay in the graph but are skipped when it's encoded
+		let any_visible = |pass_name: &str| self.draw_lists.visible(pass_name).next().is_some();
+		let impostors_enabled = any_visible("Scene: Render Impostors");
+		let mirrors_enabled = any_visible("Scene: Render Mirrors");
+		let portals_enabled = any_visible("Scene: Render Portals");
+		let glass_enabled = any_visible("Scene: Render Glass");
+		let sorted_transparency_enabled = self.render_settings.transparency == TransparencyMode::SortedBlending && any_visible(transparent_pass_name);
+		let weighted_transparency_enabled = self.render_settings.transparency == TransparencyMode::WeightedBlended && any_visible(transparent_pass_name);
+
+		// The voxel lightmap is only rebuilt for the slabs marked dirty, so a static scene skips voxelization entirely
+		let voxel_update_region = self.voxel_update_scheduler.current_region();
+		let voxel_update_enabled = voxel_update_region.is_some();
+		let voxel_passes = {
+			let (z_min, z_max) = voxel_update_region.unwrap_or((0, 0));
+			[
+				Pass::RenderPass(RenderPass {
+					label: String::from("Scene: Render Voxel Lightmap"),
+					depth_attachment: None,
+					color_attachments: vec![
+						// &self.frame_textures.voxel_calculation_fragments_render_resolution.texture.view, // TODO: Update comment. Ignored, but wgpu seems to need at least one fragment output
+						ColorAttachment {
+							view: &self.scene.resources.textures.get("VOXEL_CALCULATION_FRAGMENTS_RENDER_RESOLUTION").unwrap().view,
+							load: LoadPolicy::DontCare,
+							store: false,
+						},
+					],
+					blit_material: None,
+					viewport: None,
+					scissor: None,
+					enabled: voxel_update_enabled,
+				}),
+				Pass::ComputePass(ComputePass {
+					label: String::from("Compute: Generate Voxel Mipmaps"),
+					material: String::from("compute_voxel_texture_generating.material"),
+					work_groups_size: (1, VOXEL_RESOLUTION, z_max - z_min),
+					enabled: voxel_update_enabled,
+				}),
+			]
+		};
+
+		// The active camera's post-processing stack decides which effects run, with each skipped one leaving its output as if the effect did nothing
+		let ambient_occlusion_enabled = scene_camera.post_effects.contains(&PostEffect::AmbientOcclusion);
+		let motion_blur_enabled = scene_camera.post_effects.contains(&PostEffect::MotionBlur);
+		let ambient_occlusion_passes = [
+			Pass::RenderPass(RenderPass {
+				label: String::from("Pass: SSAO Kernel"),
+				depth_attachment: None,
+				color_attachments: vec![ColorAttachment::dont_care(&self.frame_textures.ssao_kernel_map.texture.view)],
+				blit_material: Some(String::from("pass_ssao_kernel.material")),
+				viewport: None,
+				scissor: None,
+				enabled: ambient_occlusion_enabled,
+			}),
+			// Blended into the history before it's blurred, so the blur smooths over what noise is left once the history has converged
+			Pass::RenderPass(RenderPass {
+				label: String::from("Pass: SSAO Temporal"),
+				depth_attachment: None,
+				color_attachments: vec![ColorAttachment::dont_care(&self.frame_textures.ssao_history_map.texture.view)],
+				blit_material: Some(String::from("pass_ssao_temporal.material")),
+				viewport: None,
+				scissor: None,
+				enabled: ambient_occlusion_enabled,
+			}),
+			Pass::RenderPass(RenderPass {
+				label: String::from("Pass: SSAO Blurred"),
+				depth_attachment: None,
+				color_attachments: vec![ColorAttachment::dont_care(&self.frame_textures.ssao_blurred_map.texture.view)],
+				blit_material: Some(String::from("pass_ssao_blurred.material")),
+				viewport: None,
+				scissor: None,
+				enabled: ambient_occlusion_enabled,
+			}),
+			// Nothing is drawn, the pass only clears the map to white so shading sees no occlusion
+			Pass::RenderPass(RenderPass {
+				label: String::from("Pass: SSAO Skipped"),
+				depth_attachment: None,
+				color_attachments: vec![ColorAttachment::clear(&self.frame_textures.ssao_blurred_map.texture.view, wgpu::Color::WHITE)],
+				blit_material: None,
+				viewport: None,
+				scissor: None,
+				enabled: !ambient_occlusion_enabled,
+			}),
+		];
+		let motion_blur_passes = [
+			Pass::RenderPass(RenderPass {
+				label: String::from("Pass: Motion Blur"),
+				depth_attachment: None,
+				// color_attachment_types: vec![&surface_texture_view],
+				color_attachments: vec![ColorAttachment::dont_care(&self.frame_textures.motion_blur_map.texture.view)],
+				blit_material: Some(String::from("pass_motion_blur.material")),
+				viewport: None,
+				scissor: None,
+				enabled: motion_blur_enabled,
+			}),
+			// The final composite reads the motion blur map, so the unblurred scene is copied there instead
+			Pass::CopyTexturePass(CopyTexturePass {
+				label: String::from("Copy: Motion Blur Skipped"),
+				source: &self.frame_textures.pbr_shaded_map.texture,
+				destination: &self.frame_textures.motion_blur_map.texture,
+				enabled: !motion_blur_enabled,
+			}),
+		];
 
-		// Call update() on all entity behaviors
-		self.scene.root.update_behaviors_of_descendants();
-	}
+		// Layers are blended over the world after its post-processing, so motion blur doesn't smear a weapon that moves along with the camera
+		let layer_composite_passes = self
+			.render_layers
+			.iter()
+			.map(|layer| {
+				Pass::RenderPass(RenderPass {
+					label: format!("Pass: Composite {} Layer", layer.name),
+					depth_attachment: None,
+					color_attachments: vec![ColorAttachment::load(&self.frame_textures.motion_blur_map.texture.view)],
+					blit_material: Some(format!("pass_layer_composite_{}.material", layer.name)),
+					viewport: None,
+					scissor: None,
+					enabled: layer.enabled,
+				})
+			})
+			.collect::<Vec<_>>();
 
-	fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-		let surface_texture = self.context.surface.get_current_texture()?;
-		let surface_texture_view = surface_texture.texture.create_view(&wgpu::TextureViewDescriptor::default());
+		// Each eye's view replaces its half of the world view just before exposure, so both eyes share the main camera's tone mapping
+		let frame_width = self.context.surface_configuration.width as f32;
+		let frame_height = self.context.surface_configuration.height as f32;
+		let stereo_eye_passes = Eye::ALL
+			.iter()
+			.enumerate()
+			.map(|(index, eye)| {
+				Pass::RenderPass(RenderPass {
+					label: format!("Pass: Stereo {} Eye", eye.name()),
+					depth_attachment: None,
+					color_attachments: vec![ColorAttachment::load(&self.frame_textures.motion_blur_map.texture.view)],
+					blit_material: Some(format!("pass_stereo_{}_eye.material", eye.name())),
+					viewport: Some(Viewport::new(index as f32 * frame_width / 2., 0., frame_width / 2., frame_height)),
+					scissor: None,
+					enabled: self.render_settings.stereo,
+				})
+			})
+			.collect::<Vec<_>>();
 
-		let mut encoder = self.context.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Render Encoder") });
+		// Glass refracts a snapshot of the shaded opaque scene, so it is drawn into the scene itself and the other transparent models then blend over it
+		let glass_passes = vec![
+			Pass::CopyTexturePass(CopyTexturePass {
+				label: String::from("Copy: Scene Color"),
+				source: &self.frame_textures.pbr_shaded_map.texture,
+				destination: &self.frame_textures.scene_color_copy_map.texture,
+				enabled: glass_enabled,
+			}),
+			Pass::RenderPass(RenderPass {
+				label: String::from("Scene: Render Glass"),
+				depth_attachment: Some(DepthAttachment::load(&self.frame_textures.z_buffer.texture.view)),
+				color_attachments: vec![ColorAttachment::load(&self.frame_textures.pbr_shaded_map.texture.view)],
+				blit_material: None,
+				viewport: None,
+				scissor: None,
+				enabled: glass_enabled,
+			}),
+		];
 
-		let passes = vec![
+		// Transparent models are lit forward on top of the shaded opaque scene, testing against its depth without writing to it
+		let transparent_passes = [
 			Pass::RenderPass(RenderPass {
-				label: String::from("Scene: Render Voxel Lightmap"),
-				depth_attachment: None,
-				color_attachment_types: vec![
-					// &self.frame_textures.voxel_calculation_fragments_render_resolution.texture.view, // TODO: Update comment. Ignored, but wgpu seems to need at least one fragment output
-					&self.scene.resources.textures.get("VOXEL_CALCULATION_FRAGMENTS_RENDER_RESOLUTION").unwrap().view,
+				label: String::from("Scene: Render Transparent Sorted"),
+				depth_attachment: Some(DepthAttachment::load(&self.frame_textures.z_buffer.texture.view)),
+				color_attachments: vec![ColorAttachment::load(&self.frame_textures.pbr_shaded_map.texture.view)],
+				blit_material: None,
+				viewport: None,
+				scissor: None,
+				enabled: sorted_transparency_enabled,
+			}),
+			Pass::RenderPass(RenderPass {
+				label: String::from("Scene: Render Transparent Weighted"),
+				depth_attachment: Some(DepthAttachment::load(&self.frame_textures.z_buffer.texture.view)),
+				color_attachments: vec![
+					ColorAttachment::clear(&self.frame_textures.transparent_accumulation_map.texture.view, wgpu::Color::TRANSPARENT),
+					ColorAttachment::clear(&self.frame_textures.transparent_coverage_map.texture.view, wgpu::Color::TRANSPARENT),
 				],
 				blit_material: None,
-				clear_color: wgpu::Color { r: 0., g: 0., b: 0., a: 1.0 },
+				viewport: None,
+				scissor: None,
+				enabled: weighted_transparency_enabled,
 			}),
-			// Pass::RenderPass(RenderPass {
-			// 	label: String::from("Pass: Calc Voxel Lightmap"),
-			// 	depth_attachment: None,
-			// 	color_attachment_types: vec![
-			// 		// &self.frame_textures.voxel_calculation_fragments_render_resolution.texture.view, // TODO: Update comment. Ignored, but wgpu seems to need at least one fragment output
-			// 		&self.scene.resources.textures.get("VOXEL_CALCULATION_FRAGMENTS_RENDER_RESOLUTION").unwrap().view,
-			// 	],
-			// 	blit_material: None,
-			// 	clear_color: wgpu::Color { r: 0., g: 0., b: 0., a: 1.0 },
-			// }),
-			// Pass::RenderPass(RenderPass {
-			// 	label: String::from("Pass: Calc Voxel Lightmap"),
-			// 	depth_attachment: None,
-			// 	color_attachment_types: vec![
-			// 		// &self.frame_textures.voxel_calculation_fragments_render_resolution.texture.view, // TODO: Update comment. Ignored, but wgpu seems to need at least one fragment output
-			// 		&self.scene.resources.textures.get("VOXEL_CALCULATION_FRAGMENTS_RENDER_RESOLUTION").unwrap().view,
-			// 	],
-			// 	blit_material: None,
-			// 	clear_color: wgpu::Color { r: 0., g: 0., b: 0., a: 1.0 },
-			// }),
-			Pass::ComputePass(ComputePass {
-				label: String::from("Compute: Generate Voxel Mipmaps"),
-				material: String::from("compute_voxel_texture_generating.material"),
-				work_groups_size: (128, 128, 128),
+			Pass::RenderPass(RenderPass {
+				label: String::from("Pass: Transparent Composite"),
+				depth_attachment: None,
+				color_attachments: vec![ColorAttachment::load(&self.frame_textures.pbr_shaded_map.texture.view)],
+				blit_material: Some(String::from("pass_transparent_composite.material")),
+				viewport: None,
+				scissor: None,
+				enabled: weighted_transparency_enabled,
 			}),
+		];
+
+		// Rain and snow fall in front of everything else in the world, blended over it after the transparent models
+		let precipitation_pass = Pass::RenderPass(RenderPass {
+			label: String::from("Scene: Render Precipitation"),
+			depth_attachment: Some(DepthAttachment::load(&self.frame_textures.z_buffer.texture.view)),
+			color_attachments: vec![ColorAttachment::load(&self.frame_textures.pbr_shaded_map.texture.view)],
+			blit_material: None,
+			viewport: None,
+			scissor: None,
+			enabled: self.weather.particle_count() > 0,
+		});
+
+		// Debug views are drawn over the finished frame, with the histogram counting the scene's luminance before exposure
+		let overdraw_enabled = self.render_settings.debug_view == DebugView::Overdraw;
+		let luminance_histogram_enabled = self.render_settings.debug_view == DebugView::LuminanceHistogram;
+		if luminance_histogram_enabled {
+			self.debug_view_textures.clear_luminance_histogram(&self.context.queue);
+		}
+		let frame_dimensions = (self.context.surface_configuration.width, self.context.surface_configuration.height);
+		let pbr_shaded_size = self.frame_textures.pbr_shaded_map.texture.size;
+		let (histogram_width, histogram_height) = LUMINANCE_HISTOGRAM_DIMENSIONS;
+		let debug_view_passes = [
 			Pass::RenderPass(RenderPass {
-				label: String::from("Scene: Render Deferred"),
-				depth_attachment: Some(&self.frame_textures.z_buffer.texture.view),
-				color_attachment_types: vec![
-					// &self.frame_textures.motion_vector_map.texture.view,
-					&self.frame_textures.world_space_normal.texture.view,
-					&self.frame_textures.albedo_map.texture.view,
-					&self.frame_textures.arm_map.texture.view,
-				],
+				label: String::from("Debug: Render Overdraw"),
+				depth_attachment: None,
+				color_attachments: vec![ColorAttachment::clear(&self.debug_view_textures.overdraw_map.texture.view, wgpu::Color::TRANSPARENT)],
 				blit_material: None,
-				clear_color: wgpu::Color { r: 0., g: 0., b: 0., a: 1.0 },
+				viewport: None,
+				scissor: None,
+				enabled: overdraw_enabled,
+			}),
+			Pass::ComputePass(ComputePass {
+				label: String::from("Compute: Overdraw Heatmap"),
+				material: String::from("compute_overdraw_heatmap.material"),
+				work_groups_size: work_groups_for(frame_dimensions),
+				enabled: overdraw_enabled,
 			}),
 			Pass::RenderPass(RenderPass {
-				label: String::from("Pass: SSAO Kernel"),
+				label: String::from("Debug: Show Overdraw"),
 				depth_attachment: None,
-				color_attachment_types: vec![&self.frame_textures.ssao_kernel_map.texture.view],
-				blit_material: Some(String::from("pass_ssao_kernel.material")),
-				clear_color: wgpu::Color { r: 0., g: 0., b: 0., a: 1.0 },
+				color_attachments: vec![ColorAttachment::load(&surface_texture_view)],
+				blit_material: Some(String::from("pass_debug_view_overdraw.material")),
+				viewport: None,
+				scissor: None,
+				enabled: overdraw_enabled,
+			}),
+			Pass::ComputePass(ComputePass {
+				label: String::from("Compute: Luminance Histogram"),
+				material: String::from("compute_luminance_histogram.material"),
+				work_groups_size: work_groups_for(frame_dimensions),
+				enabled: luminance_histogram_enabled,
+			}),
+			Pass::ComputePass(ComputePass {
+				label: String::from("Compute: Luminance Histogram Graph"),
+				material: String::from("compute_luminance_histogram_graph.material"),
+				work_groups_size: work_groups_for(LUMINANCE_HISTOGRAM_DIMENSIONS),
+				enabled: luminance_histogram_enabled,
 			}),
+			// The graph is drawn at its own resolution in the bottom left corner
 			Pass::RenderPass(RenderPass {
-				label: String::from("Pass: SSAO Blurred"),
+				label: String::from("Debug: Show Luminance Histogram"),
 				depth_attachment: None,
-				color_attachment_types: vec![&self.frame_textures.ssao_blurred_map.texture.view],
-				blit_material: Some(String::from("pass_ssao_blurred.material")),
-				clear_color: wgpu::Color { r: 0., g: 0., b: 0., a: 1.0 },
+				color_attachments: vec![ColorAttachment::load(&surface_texture_view)],
+				blit_material: Some(String::from("pass_debug_view_luminance_histogram.material")),
+				viewport: Some(Viewport::new(
+					16.,
+					frame_dimensions.1 as f32 - histogram_height as f32 - 16.,
+					histogram_width as f32,
+					histogram_height as f32,
+				)),
+				scissor: None,
+				enabled: luminance_histogram_enabled,
 			}),
 			Pass::RenderPass(RenderPass {
-				label: String::from("Pass: PBR Shading"),
+				label: String::from("Debug: Show Distance Field"),
 				depth_attachment: None,
-				color_attachment_types: vec![&self.frame_textures.pbr_shaded_map.texture.view],
-				// color_attachment_types: vec![&surface_texture_view],
-				blit_material: Some(String::from("pass_pbr_shading.material")),
-				clear_color: wgpu::Color { r: 0., g: 0., b: 0., a: 1.0 },
+				color_attachments: vec![ColorAttachment::load(&surface_texture_view)],
+				blit_material: Some(String::from("pass_debug_view_distance_field.material")),
+				viewport: None,
+				scissor: None,
+				enabled: self.render_settings.debug_view == DebugView::DistanceField,
 			}),
 			Pass::RenderPass(RenderPass {
-				label: String::from("Pass: Motion Blur"),
+				label: String::from("Debug: Draw Gizmos"),
 				depth_attachment: None,
-				// color_attachment_types: vec![&surface_texture_view],
-				color_attachment_types: vec![&self.frame_textures.motion_blur_map.texture.view],
-				blit_material: Some(String::from("pass_motion_blur.material")),
-				clear_color: wgpu::Color { r: 0., g: 0., b: 0., a: 1.0 },
+				color_attachments: vec![ColorAttachment::load(&surface_texture_view)],
+				blit_material: None,
+				viewport: None,
+				scissor: None,
+				enabled: !self.debug_draw.is_empty(),
 			}),
+			// The console drops down over everything else along the top of the screen
 			Pass::RenderPass(RenderPass {
-				label: String::from("Pass: HDR Exposure"),
+				label: String::from("Debug: Show Console"),
 				depth_attachment: None,
-				color_attachment_types: vec![&surface_texture_view],
-				blit_material: Some(String::from("pass_hdr_exposure.material")),
-				clear_color: wgpu::Color { r: 0., g: 0., b: 0., a: 1.0 },
+				color_attachments: vec![ColorAttachment::load(&surface_texture_view)],
+				blit_material: Some(String::from("pass_console.material")),
+				viewport: Some(Viewport::new(0., 0., frame_dimensions.0 as f32, self.console.height().min(frame_dimensions.1) as f32)),
+				scissor: None,
+				enabled: self.console.open,
 			}),
 		];
 
-		let z_buffer_source_copy = self.frame_textures.z_buffer.texture.texture.as_image_copy();
-		let z_buffer_destination_copy = self.frame_textures.z_buffer_previous.texture.texture.as_image_copy();
-		let z_buffer_size = self.frame_textures.z_buffer.texture.size;
-		encoder.copy_texture_to_texture(z_buffer_source_copy, z_buffer_destination_copy, z_buffer_size);
+		let passes = voxel_passes
+			.into_iter()
+			.chain([
+				// Pass::RenderPass(RenderPass {
+				// 	label: String::from("Pass: Calc Voxel Lightmap"),
+				// 	depth_attachment: None,
+				// 	color_attachment_types: vec![
+				// 		// &self.frame_textures.voxel_calculation_fragments_render_resolution.texture.view, // TODO: Update comment. Ignored, but wgpu seems to need at least one fragment output
+				// 		&self.scene.resources.textures.get("VOXEL_CALCULATION_FRAGMENTS_RENDER_RESOLUTION").unwrap().view,
+				// 	],
+				// 	blit_material: None,
+				// 	clear_color: wgpu::Color { r: 0., g: 0., b: 0., a: 1.0 },
+				// }),
+				// Pass::RenderPass(RenderPass {
+				// 	label: String::from("Pass: Calc Voxel Lightmap"),
+				// 	depth_attachment: None,
+				// 	color_attachment_types: vec![
+				// 		// &self.frame_textures.voxel_calculation_fragments_render_resolution.texture.view, // TODO: Update comment. Ignored, but wgpu seems to need at least one fragment output
+				// 		&self.scene.resources.textures.get("VOXEL_CALCULATION_FRAGMENTS_RENDER_RESOLUTION").unwrap().view,
+				// 	],
+				// 	blit_material: None,
+				// 	clear_color: wgpu::Color { r: 0., g: 0., b: 0., a: 1.0 },
+				// }),
+				Pass::RenderPass(RenderPass {
+					label: String::from("Scene: Render Deferred"),
+					depth_attachment: Some(DepthAttachment::clear(&self.frame_textures.z_buffer.texture.view)),
+					color_attachments: vec![
+						// &self.frame_textures.motion_vector_map.texture.view,
+						ColorAttachment::clear(&self.frame_textures.world_space_normal.texture.view, wgpu::Color::BLACK),
+						ColorAttachment::clear(&self.frame_textures.albedo_map.texture.view, wgpu::Color::BLACK),
+						ColorAttachment::clear(&self.frame_textures.arm_map.texture.view, wgpu::Color::BLACK),
+						ColorAttachment::clear(&self.frame_textures.emissive_map.texture.view, wgpu::Color::BLACK),
+						ColorAttachment::clear(&self.frame_textures.velocity_map.texture.view, wgpu::Color::BLACK),
+					],
+					blit_material: None,
+					viewport: None,
+					scissor: None,
+					enabled: true,
+				}),
+				// Impostors write into the same G-buffer as the full meshes, so they're shaded just like them
+				Pass::RenderPass(RenderPass {
+					label: String::from("Scene: Render Impostors"),
+					depth_attachment: Some(DepthAttachment::load(&self.frame_textures.z_buffer.texture.view)),
+					color_attachments: vec![
+						ColorAttachment::load(&self.frame_textures.world_space_normal.texture.view),
+						ColorAttachment::load(&self.frame_textures.albedo_map.texture.view),
+						ColorAttachment::load(&self.frame_textures.arm_map.texture.view),
+						ColorAttachment::load(&self.frame_textures.emissive_map.texture.view),
+						ColorAttachment::load(&self.frame_textures.velocity_map.texture.view),
+					],
+					blit_material: None,
+					viewport: None,
+					scissor: None,
+					enabled: impostors_enabled,
+				}),
+			])
+			.chain(ambient_occlusion_passes)
+			.chain([
+				Pass::RenderPass(RenderPass {
+					label: String::from("Pass: PBR Shading"),
+					depth_attachment: None,
+					color_attachments: vec![ColorAttachment::dont_care(&self.frame_textures.pbr_shaded_map.texture.view)],
+					// color_attachment_types: vec![&surface_texture_view],
+					blit_material: Some(String::from("pass_pbr_shading.material")),
+					viewport: None,
+					scissor: None,
+					enabled: true,
+				}),
+				// The shading pass leaves the sky flat, so the sky map replaces it where there's one to draw
+				Pass::RenderPass(RenderPass {
+					label: String::from("Pass: Skybox"),
+					depth_attachment: None,
+					color_attachments: vec![ColorAttachment::load(&self.frame_textures.pbr_shaded_map.texture.view)],
+					blit_material: Some(String::from("pass_skybox.material")),
+					viewport: None,
+					scissor: None,
+					enabled: self.environment_bindings.sky_map_loaded(),
+				}),
+				// Foveated and checkerboard shading leave gaps that are filled from a copy of the shaded pixels, borrowing the scene color copy before the glass pass takes it over
+				Pass::CopyTexturePass(CopyTexturePass {
+					label: String::from("Copy: Foveated Shading"),
+					source: &self.frame_textures.pbr_shaded_map.texture,
+					destination: &self.frame_textures.scene_color_copy_map.texture,
+					enabled: self.render_settings.foveation || self.render_settings.checkerboard,
+				}),
+				Pass::RenderPass(RenderPass {
+					label: String::from("Pass: Foveation Fill"),
+					depth_attachment: None,
+					color_attachments: vec![ColorAttachment::load(&self.frame_textures.pbr_shaded_map.texture.view)],
+					blit_material: Some(String::from("pass_foveation_fill.material")),
+					viewport: None,
+					scissor: None,
+					enabled: self.render_settings.foveation || self.render_settings.checkerboard,
+				}),
+				// Kept before the mirrors and glass are drawn over it, since those are drawn in full each frame rather than in a checkerboard
+				Pass::CopyTexturePass(CopyTexturePass {
+					label: String::from("Copy: Checkerboard History"),
+					source: &self.frame_textures.pbr_shaded_map.texture,
+					destination: &self.frame_textures.checkerboard_history_map.texture,
+					enabled: self.render_settings.checkerboard,
+				}),
+				// Mirrors are left out of the deferred passes and drawn afterwards with their finished reflections
+				Pass::RenderPass(RenderPass {
+					label: String::from("Scene: Render Mirrors"),
+					depth_attachment: Some(DepthAttachment::load(&self.frame_textures.z_buffer.texture.view)),
+					color_attachments: vec![ColorAttachment::load(&self.frame_textures.pbr_shaded_map.texture.view)],
+					blit_material: None,
+					viewport: None,
+					scissor: None,
+					enabled: mirrors_enabled,
+				}),
+				Pass::RenderPass(RenderPass {
+					label: String::from("Scene: Render Portals"),
+					depth_attachment: Some(DepthAttachment::load(&self.frame_textures.z_buffer.texture.view)),
+					color_attachments: vec![ColorAttachment::load(&self.frame_textures.pbr_shaded_map.texture.view)],
+					blit_material: None,
+					viewport: None,
+					scissor: None,
+					enabled: portals_enabled,
+				}),
+			])
+			.chain(glass_passes)
+			.chain(transparent_passes)
+			.chain([precipitation_pass])
+			.chain(motion_blur_passes)
+			.chain(layer_composite_passes)
+			.chain(stereo_eye_passes)
+			.chain([
+				Pass::ComputePass(ComputePass {
+					label: String::from("Compute: Auto Exposure Histogram"),
+					material: String::from("compute_auto_exposure_histogram.material"),
+					work_groups_size: work_groups_for((pbr_shaded_size.width, pbr_shaded_size.height)),
+					enabled: self.render_settings.auto_exposure,
+				}),
+				// Always run, since it's also what resets the exposure to 1 while the eye adaptation is off
+				Pass::ComputePass(ComputePass {
+					label: String::from("Compute: Auto Exposure"),
+					material: String::from("compute_auto_exposure.material"),
+					work_groups_size: (1, 1, 1),
+					enabled: true,
+				}),
+			])
+			.chain([Pass::RenderPass(RenderPass {
+				label: String::from("Pass: Final Composite"),
+				depth_attachment: None,
+				color_attachments: vec![ColorAttachment::dont_care(&surface_texture_view)],
+				blit_material: Some(String::from("pass_final_composite.material")),
+				viewport: None,
+				scissor: None,
+				enabled: true,
+			})])
+			.chain(debug_view_passes);
+
+		// Meshlets are tested against last frame's depth, so the pyramid is built from the previous depth buffer and the culling finishes before the deferred pass draws them
+		if self.render_settings.meshlets {
+			let camera_buffer = &scene_camera.camera_buffer;
+			let deferred_draw_items = self.draw_lists.items("Scene: Render Deferred");
+			self.meshlet_renderer.refresh(&self.context.device, &self.scene, deferred_draw_items, &self.hi_z_pyramid, camera_buffer);
+
+			self.hi_z_pyramid.generate(&mut encoder, self.frame_textures.parity());
 
-		for pass in passes {
+			// Until a frame has been drawn into the new depth buffer, the pyramid is empty and would hide everything
+			let occlusion_culling = self.frames_since_resize > 0;
+			self.meshlet_renderer.record_culling(&mut encoder, &self.context, &self.scene, occlusion_culling);
+		}
+
+		// Scattered foliage is culled and sorted into LODs on the GPU, likewise before the deferred pass draws it
+		self.scatter_renderer.record_culling(&mut encoder, &self.context, &self.scene);
+
+		let mut render_graph = RenderGraph::new(passes.collect());
+		let scheduled = self
+			.effects
+			.iter()
+			.try_for_each(|effect| {
+				let enabled = effect.post_effect.map_or(true, |post_effect| scene_camera.post_effects.contains(&post_effect));
+				render_graph.add_effect(effect, enabled, &self.scene.resources, &self.frame_textures, &self.transient_attachments)
+			})
+			.and_then(|_| render_graph.schedule(&self.scene.resources, &self.draw_lists, &self.frame_textures, &self.transient_attachments, &surface_texture_view));
+		match scheduled {
+			Ok(_) => self.render_graph_error = None,
+			Err(e) => {
+				let error = format!("{:?}", e);
+				if self.render_graph_error.as_ref() != Some(&error) {
+					eprintln!("Can't schedule the render graph, so its passes run in the order they were added: {}", error);
+				}
+				self.render_graph_error = Some(error);
+			}
+		}
+		render_graph.bind_temporal_materials(&self.scene.resources, self.frame_textures.parity());
+
+		// Encoding consumes the passes, so a requested export has to happen first
+		if self.render_settings.export_frame_graph {
+			self.render_settings.export_frame_graph = false;
+
+			let dot = render_graph.export_dot(&self.scene.resources, &self.draw_lists, &self.frame_textures, &self.transient_attachments, &surface_texture_view);
+			match std::fs::write(FRAME_GRAPH_EXPORT_PATH, dot) {
+				Ok(_) => println!("Exported the frame graph to {}", FRAME_GRAPH_EXPORT_PATH),
+				Err(e) => eprintln!("Can't export the frame graph: {:?}", e),
+			}
+		}
+
+		for pass in render_graph.passes.into_iter().filter(Pass::enabled) {
 			match pass {
 				Pass::RenderPass(pass) => {
-					let color_attachments = pass
-						.color_attachment_types
-						.into_iter()
-						.map(|frame_texture_type| wgpu::RenderPassColorAttachment {
-							view: frame_texture_type,
-							resolve_target: None,
-							ops: wgpu::Operations {
-								load: wgpu::LoadOp::Clear(pass.clear_color),
-								store: true,
-							},
-						})
-						.collect::<Vec<wgpu::RenderPassColorAttachment>>();
-
-					let depth_stencil_attachment = pass.depth_attachment.map(|view| wgpu::RenderPassDepthStencilAttachment {
-						view,
-						depth_ops: Some(wgpu::Operations {
-							load: wgpu::LoadOp::Clear(1.0),
-							store: true,
-						}),
-						stencil_ops: None,
-					});
+					let color_attachments = pass.color_attachments.iter().map(ColorAttachment::wgpu_attachment).collect::<Vec<wgpu::RenderPassColorAttachment>>();
+					let depth_stencil_attachment = pass.depth_attachment.as_ref().map(DepthAttachment::wgpu_attachment);
 
-					let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+					let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
 						label: Some(pass.label.as_str()),
 						color_attachments: color_attachments.as_slice(),
 						depth_stencil_attachment,
 					});
 
-					if pass.label == "Scene: Render Deferred" {
+					if let Some(viewport) = pass.viewport {
+						render_pass.set_viewport(viewport.x, viewport.y, viewport.width, viewport.height, viewport.min_depth, viewport.max_depth);
+					}
+					if let Some(scissor) = pass.scissor {
+						render_pass.set_scissor_rect(scissor.x, scissor.y, scissor.width, scissor.height);
+					}
+
+					if pass.label == "Scene: Render Deferred" && voxel_update_region.is_some() {
 						self.voxel_light_map.generate_mipmaps(&self.context);
 					}
 
 					if pass.label == "Scene: Render Voxel Lightmap" {
-						self.draw_scene(render_pass, &pass.label);
+						self.draw_scene(&mut render_pass, &pass.label, scene_camera);
+					} else if pass.label == "Debug: Draw Gizmos" {
+						self.debug_draw.draw(&mut render_pass);
+					} else if pass.label == "Scene: Render Precipitation" {
+						self.draw_precipitation(&mut render_pass, &pass.label, scene_camera);
 					} else {
 						match pass.blit_material {
-							None => self.draw_scene(render_pass, &pass.label),
-							Some(material_name) => self.draw_quad(render_pass, material_name.as_str()),
+							None => self.draw_scene(&mut render_pass, &pass.label, scene_camera),
+							Some(material_name) => self.draw_quad(render_pass, &pass.label, material_name.as_str()),
 						}
 					}
 				}
 				Pass::ComputePass(pass) => {
 					let material = &self.scene.resources.materials.get(&pass.material).unwrap();
-					let shader = &self.scene.resources.shaders[material.shader_id];
+					let shader = &self.scene.resources.shaders[material.shader];
 					let pipeline = match &shader.pipeline {
 						crate::shader::PipelineType::RenderPipeline(_) => continue,
 						crate::shader::PipelineType::ComputePipeline(compute_pipeline) => compute_pipeline,
 					};
 					let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some(&pass.label) });
 					compute_pass.set_pipeline(pipeline);
-					compute_pass.set_bind_group(0, &material.bind_group, &[]);
+					compute_pass.set_bind_group(GLOBALS_BIND_GROUP, &self.context.globals.globals_bind_group, &[]);
+					compute_pass.set_bind_group(CAMERA_BIND_GROUP, &self.context.placeholder_bind_group, &[]);
+					compute_pass.set_bind_group(LIGHTING_BIND_GROUP, &self.context.placeholder_bind_group, &[]);
+					compute_pass.set_bind_group(MATERIAL_BIND_GROUP, &material.bind_group, &[]);
 					// compute_pass.insert_debug_marker("Running the compute shader");
 					let (x, y, z) = pass.work_groups_size;
 					compute_pass.dispatch(x, y, z);
 				}
+				Pass::CopyTexturePass(pass) => {
+					encoder.push_debug_group(&pass.label);
+					encoder.copy_texture_to_texture(pass.source.texture.as_image_copy(), pass.destination.texture.as_image_copy(), pass.source.size);
+					encoder.pop_debug_group();
+				}
 			}
 		}
 
-		self.context.queue.submit(std::iter::once(encoder.finish()));
+		// Kept for crash reports, since the surface's texture can't be read back once it's presented
+		self.last_frame.record_copy(&mut encoder, &surface_texture.texture);
+		if let Some(gpu_timer) = &mut self.gpu_timer {
+			gpu_timer.end(&mut encoder);
+		}
+
+		let command_buffer = encoder.finish();
+		self.frame_stats.record_cpu_time(CpuTimingScope::Encode, encode_start.elapsed());
+
+		let submit_start = std::time::Instant::now();
+		self.context.queue.submit(std::iter::once(command_buffer));
+		self.frame_stats.record_cpu_time(CpuTimingScope::Submit, submit_start.elapsed());
+
+		let present_start = std::time::Instant::now();
 		surface_texture.present();
+		self.frame_stats.record_cpu_time(CpuTimingScope::Present, present_start.elapsed());
+
+		self.frame_stats.finish_frame();
+		self.frames_since_resize = self.frames_since_resize.saturating_add(1);
 
 		Ok(())
 	}
 
-	fn draw_scene<'a>(&'a self, mut render_pass: wgpu::RenderPass<'a>, pass_name: &str) {
-		for entity in &self.scene.root {
-			for component in &entity.components {
-				if let Component::Model(model) = component {
-					let mesh = &self.scene.resources.meshes[model
-						.mesh
-						.unwrap_or_else(|| panic!("The mesh '{}:{}' is not loaded but is trying to be drawn", model.mesh_name.0, model.mesh_name.1))];
-					let maybe_material_index = match pass_name {
-						"Scene: Render Voxel Lightmap" => model.voxel_lightmap_material,
-						"Scene: Render Deferred" => model.scene_deferred_material,
-						_ => panic!("Invalid render pass for drawing scene {}", pass_name),
-					};
-					let material_index = maybe_material_index.unwrap_or_else(|| {
-						panic!(
-							"The material for pass '{}' is not loaded but is trying to be drawn with model '{}:{}'",
-							pass_name, model.mesh_name.0, model.mesh_name.1
-						)
-					});
-					let material = &self.scene.resources.materials[material_index];
-					let shader = &self.scene.resources.shaders[material.shader_id];
-					let pipeline = match &shader.pipeline {
-						crate::shader::PipelineType::RenderPipeline(render_pipeline) => render_pipeline,
-						crate::shader::PipelineType::ComputePipeline(_) => continue,
-					};
+	// Each cascade draws the opaque models its light camera can see into its own layer of the depth array
+	// A layer that only had static models in it is kept as it was until its light camera moves or the scene changes
+	fn render_shadow_cascades(&mut self, encoder: &mut wgpu::CommandEncoder) {
+		if !self.shadow_maps.enabled {
+			return;
+		}
 
-					let instances_buffer = model.instances.instances_buffer.as_ref();
-					let instances_range = 0..model.instances.instance_list.len() as u32;
+		for index in 0..self.shadow_maps.cascades.len() {
+			let cascade = &self.shadow_maps.cascades[index];
+			let vp_matrix = cascade.camera.camera_uniform.vp_matrix();
+			let frustum = Frustum::from_matrix(vp_matrix);
+			self.draw_lists.cull(&self.scene, "Scene: Render Shadows", &frustum);
 
-					render_pass.set_pipeline(pipeline);
+			let key = (self.scene.generation(), vp_matrix);
+			let all_static = self.draw_lists.visible_all_static("Scene: Render Shadows");
+			if all_static && cascade.cached == Some(key) {
+				continue;
+			}
 
-					render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-					render_pass.set_vertex_buffer(1, instances_buffer.unwrap().slice(..));
+			let label = format!("Scene: Render Shadow Cascade {}", index);
+			let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: Some(label.as_str()),
+				color_attachments: &[],
+				depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+					view: &self.shadow_maps.depth_array.layer_views[index],
+					depth_ops: Some(wgpu::Operations {
+						load: wgpu::LoadOp::Clear(1.0),
+						store: true,
+					}),
+					stencil_ops: None,
+				}),
+			});
 
-					render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+			self.draw_scene(&mut render_pass, "Scene: Render Shadows", &cascade.camera);
+			drop(render_pass);
 
-					let mut index = 0;
-					if shader.includes_camera {
-						let scene_camera = self.scene.find_entity(self.active_camera.as_str()).unwrap().get_cameras()[0];
-						render_pass.set_bind_group(index, &scene_camera.camera_bind_group, &[]);
-						index += 1;
-					}
-					if shader.includes_lighting {
-						render_pass.set_bind_group(index, &self.scene_lighting.light_bind_group, &[]);
-						index += 1;
-					}
-					if shader.includes_debugging {
-						render_pass.set_bind_group(index, &self.debug_buffer.debug_bind_group, &[]);
-						index += 1;
-					}
-					render_pass.set_bind_group(index, &material.bind_group, &[]);
+			self.shadow_maps.cascades[index].cached = all_static.then_some(key);
+		}
+
+		if self.shadow_maps.filter == ShadowFilter::Pcf {
+			return;
+		}
+
+		// The other filters read the cascades through the moments atlas, which is blurred horizontally into the second atlas and vertically back for the ones that are filtered
+		let mut moments_passes = vec![("Pass: Shadow Moments", "pass_shadow_moments.material", &self.shadow_maps.moments_atlas)];
+		if self.shadow_maps.moments_blurred() {
+			moments_passes.push(("Pass: Shadow Moments Blur Horizontal", "pass_shadow_blur_horizontal.material", &self.shadow_maps.moments_blur_atlas));
+			moments_passes.push(("Pass: Shadow Moments Blur Vertical", "pass_shadow_blur_vertical.material", &self.shadow_maps.moments_atlas));
+		}
+		for (label, material_name, target) in moments_passes {
+			let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: Some(label),
+				color_attachments: &[ColorAttachment::dont_care(&target.texture.view).wgpu_attachment()],
+				depth_stencil_attachment: None,
+			});
+			self.draw_quad(render_pass, label, material_name);
+		}
+	}
+
+	// Each face of the lamp's cube draws the opaque models around it into its own layer of the depth cube
+	// Like the cascades, a face that only had static models in it is kept until the lamp moves or the scene changes
+	fn render_point_shadow(&mut self, encoder: &mut wgpu::CommandEncoder) {
+		if !self.point_shadow_map.enabled {
+			return;
+		}
+
+		let lamp_models = self
+			.point_shadow_map
+			.lamp_entity
+			.as_deref()
+			.and_then(|name| self.scene.find_entity(name))
+			.map(Entity::get_models)
+			.unwrap_or_default();
+
+		for index in 0..self.point_shadow_map.faces.len() {
+			let camera = &self.point_shadow_map.faces[index];
+			let vp_matrix = camera.camera_uniform.vp_matrix();
+			let frustum = Frustum::from_matrix(vp_matrix);
+			self.draw_lists.cull(&self.scene, "Scene: Render Shadows", &frustum);
+			self.draw_lists
+				.retain_visible(&self.scene, "Scene: Render Shadows", |model| !lamp_models.iter().any(|lamp_model| std::ptr::eq(*lamp_model, model)));
+
+			let key = (self.scene.generation(), vp_matrix);
+			let all_static = self.draw_lists.visible_all_static("Scene: Render Shadows");
+			if all_static && self.point_shadow_map.cached_faces[index] == Some(key) {
+				continue;
+			}
+
+			let label = format!("Scene: Render Point Shadow Face {}", index);
+			let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: Some(label.as_str()),
+				color_attachments: &[],
+				depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+					view: &self.point_shadow_map.depth_cube.layer_views[index],
+					depth_ops: Some(wgpu::Operations {
+						load: wgpu::LoadOp::Clear(1.0),
+						store: true,
+					}),
+					stencil_ops: None,
+				}),
+			});
+
+			self.draw_scene(&mut render_pass, "Scene: Render Shadows", camera);
+			drop(render_pass);
+
+			self.point_shadow_map.cached_faces[index] = all_static.then_some(key);
+		}
+	}
+
+	// Each recursion level renders the reflection of every mirror that hasn't reached its depth limit, then copies them all so the next level can show mirrors inside reflections
+	fn render_mirrors(&mut self, encoder: &mut wgpu::CommandEncoder) {
+		let max_recursion_depth = self.scene.root.iter().flat_map(|entity| entity.get_mirrors()).map(|mirror| mirror.max_recursion_depth).max();
+		let max_recursion_depth = match max_recursion_depth {
+			Some(max_recursion_depth) => max_recursion_depth,
+			None => return,
+		};
+
+		for depth in 0..=max_recursion_depth {
+			let mirror_entities = self.scene.root.iter().flat_map(|entity| entity.get_mirrors().into_iter().map(move |mirror| (entity, mirror)));
+			let rendered_mirrors = mirror_entities.filter(|(_, mirror)| mirror.faces_view && depth <= mirror.max_recursion_depth).collect::<Vec<_>>();
+
+			for (entity, mirror) in &rendered_mirrors {
+				let frustum = Frustum::from_matrix(mirror.camera.camera_uniform.vp_matrix()).with_clip_plane(mirror.camera.clip_plane);
+				self.draw_lists.cull(&self.scene, "Scene: Render Reflection", &frustum);
+				self.draw_lists.cull(&self.scene, "Scene: Render Mirrors", &frustum);
+
+				// The first level has no finished reflections to show yet, and a mirror never sees its own surface
+				self.draw_lists
+					.retain_visible(&self.scene, "Scene: Render Mirrors", |model| depth > 0 && model.mirror_surface.as_deref() != Some(entity.name.as_str()));
+
+				let label = format!("Scene: Render Reflection of {}", entity.name);
+				let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+					label: Some(label.as_str()),
+					color_attachments: &[wgpu::RenderPassColorAttachment {
+						view: &mirror.color_map.texture.view,
+						resolve_target: None,
+						ops: wgpu::Operations {
+							load: wgpu::LoadOp::Clear(wgpu::Color { r: 0., g: 0., b: 0., a: 1.0 }),
+							store: true,
+						},
+					}],
+					depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+						view: &mirror.depth_map.texture.view,
+						depth_ops: Some(wgpu::Operations {
+							load: wgpu::LoadOp::Clear(1.0),
+							store: true,
+						}),
+						stencil_ops: None,
+					}),
+				});
+
+				self.draw_scene(&mut render_pass, "Scene: Render Reflection", &mirror.camera);
+				self.draw_scene(&mut render_pass, "Scene: Render Mirrors", &mirror.camera);
+			}
+
+			// Mirrors sample the copies, so none of them are read while they're being rendered into
+			for (_, mirror) in &rendered_mirrors {
+				let color_map = &mirror.color_map.texture;
+				encoder.copy_texture_to_texture(color_map.texture.as_image_copy(), mirror.reflection_map.texture.texture.as_image_copy(), color_map.size);
+			}
+		}
+	}
+
+	// Each recursion level renders the view through every portal that hasn't reached its depth limit, then copies them all so the next level can show portals inside those views
+	// Mirrors are finished before this, so they're seen through the portals at every level
+	fn render_portals(&mut self, encoder: &mut wgpu::CommandEncoder) {
+		let max_recursion_depth = self.scene.root.iter().flat_map(|entity| entity.get_portals()).map(|portal| portal.max_recursion_depth).max();
+		let max_recursion_depth = match max_recursion_depth {
+			Some(max_recursion_depth) => max_recursion_depth,
+			None => return,
+		};
+
+		for depth in 0..=max_recursion_depth {
+			let portal_entities = self.scene.root.iter().flat_map(|entity| entity.get_portals().into_iter().map(move |portal| (entity, portal)));
+			let rendered_portals = portal_entities.filter(|(_, portal)| portal.faces_view && depth <= portal.max_recursion_depth).collect::<Vec<_>>();
+
+			for (_, portal) in &rendered_portals {
+				let frustum = Frustum::from_matrix(portal.camera.camera_uniform.vp_matrix()).with_clip_plane(portal.camera.clip_plane);
+				self.draw_lists.cull(&self.scene, "Scene: Render Portal View", &frustum);
+				self.draw_lists.cull(&self.scene, "Scene: Render Mirrors", &frustum);
+				self.draw_lists.cull(&self.scene, "Scene: Render Portals", &frustum);
+
+				// The first level has no finished views to show yet, and the portal being looked out of is right at the camera's clip plane
+				self.draw_lists.retain_visible(&self.scene, "Scene: Render Portals", |model| {
+					depth > 0 && model.portal_surface.as_deref() != Some(portal.linked_portal.as_str())
+				});
+
+				let label = format!("Scene: Render View Through {}", portal.linked_portal);
+				let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+					label: Some(label.as_str()),
+					color_attachments: &[wgpu::RenderPassColorAttachment {
+						view: &portal.color_map.texture.view,
+						resolve_target: None,
+						ops: wgpu::Operations {
+							load: wgpu::LoadOp::Clear(wgpu::Color { r: 0., g: 0., b: 0., a: 1.0 }),
+							store: true,
+						},
+					}],
+					depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+						view: &portal.depth_map.texture.view,
+						depth_ops: Some(wgpu::Operations {
+							load: wgpu::LoadOp::Clear(1.0),
+							store: true,
+						}),
+						stencil_ops: None,
+					}),
+				});
+
+				self.draw_scene(&mut render_pass, "Scene: Render Portal View", &portal.camera);
+				self.draw_scene(&mut render_pass, "Scene: Render Mirrors", &portal.camera);
+				self.draw_scene(&mut render_pass, "Scene: Render Portals", &portal.camera);
+			}
+
+			// Portals sample the copies, so none of them are read while they're being rendered into
+			for (_, portal) in &rendered_portals {
+				let color_map = &portal.color_map.texture;
+				encoder.copy_texture_to_texture(color_map.texture.as_image_copy(), portal.portal_map.texture.texture.as_image_copy(), color_map.size);
+			}
+		}
+	}
+
+	// Each layer is drawn from its own camera into its own color and depth, cleared to transparent so its composite only covers what the layer drew
+	fn render_layers(&mut self, encoder: &mut wgpu::CommandEncoder) {
+		for layer in self.render_layers.iter().filter(|layer| layer.enabled) {
+			let frustum = Frustum::from_matrix(layer.camera.camera_uniform.vp_matrix());
+			self.draw_lists.cull(&self.scene, "Scene: Render Layers", &frustum);
+			self.draw_lists
+				.retain_visible(&self.scene, "Scene: Render Layers", |model| model.layer.as_deref() == Some(layer.name.as_str()));
+
+			let label = format!("Scene: Render {} Layer", layer.name);
+			let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: Some(label.as_str()),
+				color_attachments: &[wgpu::RenderPassColorAttachment {
+					view: &layer.color_map.texture.view,
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+						store: true,
+					},
+				}],
+				depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+					view: &layer.depth_map.texture.view,
+					depth_ops: Some(wgpu::Operations {
+						load: wgpu::LoadOp::Clear(1.0),
+						store: true,
+					}),
+					stencil_ops: None,
+				}),
+			});
+
+			self.draw_scene(&mut render_pass, "Scene: Render Layers", &layer.camera);
+		}
+	}
+
+	// Both eyes are lit forward like the views through portals, since the deferred passes and their frame textures are sized and set up for the single world view
+	fn render_stereo_eyes(&mut self, encoder: &mut wgpu::CommandEncoder) {
+		if !self.render_settings.stereo {
+			return;
+		}
 
+		for stereo_eye in &self.stereo_rig.eyes {
+			let frustum = Frustum::from_matrix(stereo_eye.camera.camera_uniform.vp_matrix());
+			self.draw_lists.cull(&self.scene, "Scene: Render Portal View", &frustum);
+			self.draw_lists.cull(&self.scene, "Scene: Render Mirrors", &frustum);
+			self.draw_lists.cull(&self.scene, "Scene: Render Portals", &frustum);
+
+			let label = format!("Scene: Render {} Eye", stereo_eye.eye.name());
+			let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: Some(label.as_str()),
+				color_attachments: &[wgpu::RenderPassColorAttachment {
+					view: &stereo_eye.color_map.texture.view,
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color { r: 0., g: 0., b: 0., a: 1.0 }),
+						store: true,
+					},
+				}],
+				depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+					view: &stereo_eye.depth_map.texture.view,
+					depth_ops: Some(wgpu::Operations {
+						load: wgpu::LoadOp::Clear(1.0),
+						store: true,
+					}),
+					stencil_ops: None,
+				}),
+			});
+
+			self.draw_scene(&mut render_pass, "Scene: Render Portal View", &stereo_eye.camera);
+			self.draw_scene(&mut render_pass, "Scene: Render Mirrors", &stereo_eye.camera);
+			self.draw_scene(&mut render_pass, "Scene: Render Portals", &stereo_eye.camera);
+		}
+	}
+
+	fn draw_scene<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, pass_name: &str, camera: &'a Camera) {
+		let mut counter = PassCounter::new();
+
+		// The overdraw heatmap counts the fragments of every opaque model, so it draws the deferred draw list with its own material
+		let draw_list_name = match pass_name {
+			"Debug: Render Overdraw" => "Scene: Render Deferred",
+			_ => pass_name,
+		};
+		counter.stats.culled = self.draw_lists.culled_count(draw_list_name) as u32;
+
+		for (draw_item_index, draw_item) in self.draw_lists.visible(draw_list_name) {
+			let model = draw_item.model(&self.scene);
+			// Impostors are drawn on a quad in place of the model's own mesh
+			let mesh = match pass_name {
+				"Scene: Render Impostors" => self.scene.resources.meshes.get(&(String::from("BLIT"), String::from("QUAD"))).unwrap(),
+				_ => &self.scene.resources.meshes[draw_item.mesh],
+			};
+			let material = match pass_name {
+				"Debug: Render Overdraw" => &self.scene.resources.materials["debug_overdraw.material"],
+				_ => &self.scene.resources.materials[draw_item.material],
+			};
+			let shader = &self.scene.resources.shaders[material.shader];
+			let pipeline = match &shader.pipeline {
+				crate::shader::PipelineType::RenderPipeline(render_pipeline) => render_pipeline,
+				crate::shader::PipelineType::ComputePipeline(_) => continue,
+			};
+
+			let instances_buffer = model.instances.instances_buffer.as_ref();
+			let instances_range = 0..model.instances.instance_list.len() as u32;
+
+			render_pass.set_pipeline(pipeline);
+			counter.pipeline(pipeline);
+
+			render_pass.set_vertex_buffer(0, mesh.vertex_buffer(shader.vertex_format).slice(..));
+			render_pass.set_vertex_buffer(1, instances_buffer.unwrap().slice(..));
+
+			render_pass.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
+
+			let placeholder = &self.context.placeholder_bind_group;
+			let camera_bind_group = if shader.includes_camera { &camera.camera_bind_group } else { placeholder };
+			let lighting_bind_group = if shader.includes_lighting { &self.scene_lighting.light_bind_group } else { placeholder };
+			let bind_groups = [
+				(GLOBALS_BIND_GROUP, &self.context.globals.globals_bind_group),
+				(CAMERA_BIND_GROUP, camera_bind_group),
+				(LIGHTING_BIND_GROUP, lighting_bind_group),
+				(MATERIAL_BIND_GROUP, &material.bind_group),
+			];
+			for (index, bind_group) in bind_groups {
+				render_pass.set_bind_group(index, bind_group, &[]);
+				counter.bind_group(index, bind_group);
+			}
+
+			let meshlet_draw = match pass_name {
+				"Scene: Render Deferred" if self.render_settings.meshlets => self.meshlet_renderer.draw(draw_item_index),
+				_ => None,
+			};
+			match meshlet_draw {
+				Some(meshlet_draw) => {
+					// The meshlets' draw arguments are laid out back to back, so they can all go in one call where multi-draw is supported
+					let draw_calls = if self.context.gpu_features.multi_draw_indirect {
+						render_pass.multi_draw_indexed_indirect(&meshlet_draw.draw_buffer, MeshletDraw::draw_offset(0), meshlet_draw.meshlet_count);
+						1
+					} else {
+						for meshlet_index in 0..meshlet_draw.meshlet_count {
+							render_pass.draw_indexed_indirect(&meshlet_draw.draw_buffer, MeshletDraw::draw_offset(meshlet_index));
+						}
+						meshlet_draw.meshlet_count
+					};
+
+					// The GPU culling decides how much of the mesh is drawn, so the whole mesh is counted as an upper bound
+					counter.stats.draw_calls += draw_calls;
+					counter.stats.instances += instances_range.len() as u32;
+					counter.stats.triangles += (mesh.index_count / 3) as u64 * instances_range.len() as u64;
+				}
+				None => {
+					counter.draw(mesh.index_count, instances_range.len() as u32);
 					render_pass.draw_indexed(0..mesh.index_count, 0, instances_range);
 				}
 			}
 		}
+
+		if pass_name == "Scene: Render Deferred" {
+			self.draw_scatter_layers(render_pass, camera, &mut counter);
+		}
+
+		self.frame_stats.record(pass_name, counter.stats);
+	}
+
+	// Each LOD of each scatter layer is one indirect draw, with the instance count filled in by the culling shader
+	fn draw_scatter_layers<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, camera: &'a Camera, counter: &mut PassCounter<'a>) {
+		for layer in self.scatter_renderer.layers.iter().filter(|layer| layer.instance_count > 0) {
+			for (lod_index, lod) in layer.lods.iter().enumerate() {
+				let mesh = &self.scene.resources.meshes[&lod.mesh];
+				let material = &self.scene.resources.materials[&format!("scene_deferred_{}.material", mesh.name)];
+				let shader = &self.scene.resources.shaders[material.shader];
+				let pipeline = match &shader.pipeline {
+					crate::shader::PipelineType::RenderPipeline(render_pipeline) => render_pipeline,
+					crate::shader::PipelineType::ComputePipeline(_) => continue,
+				};
+
+				render_pass.set_pipeline(pipeline);
+				counter.pipeline(pipeline);
+
+				render_pass.set_vertex_buffer(0, mesh.vertex_buffer(shader.vertex_format).slice(..));
+				render_pass.set_vertex_buffer(1, layer.visible_instances_buffer.slice(layer.visible_instances_offset(lod_index)..));
+
+				render_pass.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
+
+				let placeholder = &self.context.placeholder_bind_group;
+				let camera_bind_group = if shader.includes_camera { &camera.camera_bind_group } else { placeholder };
+				let lighting_bind_group = if shader.includes_lighting { &self.scene_lighting.light_bind_group } else { placeholder };
+				let bind_groups = [
+					(GLOBALS_BIND_GROUP, &self.context.globals.globals_bind_group),
+					(CAMERA_BIND_GROUP, camera_bind_group),
+					(LIGHTING_BIND_GROUP, lighting_bind_group),
+					(MATERIAL_BIND_GROUP, &material.bind_group),
+				];
+				for (index, bind_group) in bind_groups {
+					render_pass.set_bind_group(index, bind_group, &[]);
+					counter.bind_group(index, bind_group);
+				}
+
+				render_pass.draw_indexed_indirect(&layer.draw_buffer, ScatterLayer::draw_offset(lod_index));
+
+				// Only the GPU knows how many instances survived culling, so every instance is counted as an upper bound
+				counter.stats.draw_calls += 1;
+				counter.stats.instances += layer.instance_count;
+				counter.stats.triangles += (mesh.index_count / 3) as u64 * layer.instance_count as u64;
+			}
+		}
+	}
+
+	// Every particle is an instance of the blit quad, placed by the vertex shader
+	fn draw_precipitation<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, pass_name: &str, camera: &'a Camera) {
+		let mesh = &self.scene.resources.meshes[&(String::from("BLIT"), String::from("QUAD"))];
+		let material = &self.scene.resources.materials["scene_precipitation.material"];
+		let shader = &self.scene.resources.shaders[material.shader];
+		let pipeline = match &shader.pipeline {
+			crate::shader::PipelineType::RenderPipeline(render_pipeline) => render_pipeline,
+			crate::shader::PipelineType::ComputePipeline(_) => return,
+		};
+
+		let mut counter = PassCounter::new();
+
+		render_pass.set_pipeline(pipeline);
+		counter.pipeline(pipeline);
+
+		render_pass.set_vertex_buffer(0, mesh.vertex_buffer(shader.vertex_format).slice(..));
+
+		render_pass.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
+
+		let bind_groups = [
+			(GLOBALS_BIND_GROUP, &self.context.globals.globals_bind_group),
+			(CAMERA_BIND_GROUP, &camera.camera_bind_group),
+			(LIGHTING_BIND_GROUP, &self.scene_lighting.light_bind_group),
+			(MATERIAL_BIND_GROUP, &material.bind_group),
+		];
+		for (index, bind_group) in bind_groups {
+			render_pass.set_bind_group(index, bind_group, &[]);
+			counter.bind_group(index, bind_group);
+		}
+
+		let particle_count = self.weather.particle_count();
+		counter.draw(mesh.index_count, particle_count);
+		render_pass.draw_indexed(0..mesh.index_count, 0, 0..particle_count);
+
+		self.frame_stats.record(pass_name, counter.stats);
 	}
 
-	fn draw_quad<'a>(&'a self, mut render_pass: wgpu::RenderPass<'a>, material_name: &str) {
+	fn draw_quad<'a>(&'a self, mut render_pass: wgpu::RenderPass<'a>, pass_name: &str, material_name: &str) {
 		let mesh = &self.scene.resources.meshes.get(&(String::from("BLIT"), String::from("QUAD"))).unwrap();
 		let material = &self.scene.resources.materials.get(material_name).unwrap();
-		let shader = &self.scene.resources.shaders[material.shader_id];
+		let shader = &self.scene.resources.shaders[material.shader];
 		let pipeline = match &shader.pipeline {
 			crate::shader::PipelineType::RenderPipeline(render_pipeline) => render_pipeline,
 			crate::shader::PipelineType::ComputePipeline(_) => return,
 		};
 
+		let mut counter = PassCounter::new();
+
 		render_pass.set_pipeline(pipeline);
+		counter.pipeline(pipeline);
 
-		render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+		render_pass.set_vertex_buffer(0, mesh.vertex_buffer(shader.vertex_format).slice(..));
 
-		render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+		render_pass.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
 
-		let mut index = 0;
-		if shader.includes_camera {
+		let placeholder = &self.context.placeholder_bind_group;
+		let camera_bind_group = if shader.includes_camera {
 			let scene_camera = self.scene.find_entity(self.active_camera.as_str()).unwrap().get_cameras()[0];
-			render_pass.set_bind_group(index, &scene_camera.camera_bind_group, &[]);
-			index += 1;
-		}
-		if shader.includes_lighting {
-			render_pass.set_bind_group(index, &self.scene_lighting.light_bind_group, &[]);
-			index += 1;
-		}
-		if shader.includes_debugging {
-			render_pass.set_bind_group(index, &self.debug_buffer.debug_bind_group, &[]);
-			index += 1;
+			&scene_camera.camera_bind_group
+		} else {
+			placeholder
+		};
+		let lighting_bind_group = if shader.includes_lighting { &self.scene_lighting.light_bind_group } else { placeholder };
+		let bind_groups = [
+			(GLOBALS_BIND_GROUP, &self.context.globals.globals_bind_group),
+			(CAMERA_BIND_GROUP, camera_bind_group),
+			(LIGHTING_BIND_GROUP, lighting_bind_group),
+			(MATERIAL_BIND_GROUP, &material.bind_group),
+		];
+		for (index, bind_group) in bind_groups {
+			render_pass.set_bind_group(index, bind_group, &[]);
+			counter.bind_group(index, bind_group);
 		}
-		render_pass.set_bind_group(index, &material.bind_group, &[]);
 
 		render_pass.draw_indexed(0..mesh.index_count, 0, 0..1);
+		counter.draw(mesh.index_count, 1);
+
+		self.frame_stats.record(pass_name, counter.stats);
 	}
 }
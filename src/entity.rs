@@ -1,17 +1,28 @@
-use crate::behavior::Behavior;
-use crate::camera::{Camera, Projection};
+use crate::behavior::{Behavior, BehaviorContext};
+use crate::camera::{Camera, CameraUniform, Projection};
 use crate::component::Component;
 use crate::context::Context;
 use crate::light::Light;
+use crate::mirror::Mirror;
 use crate::model::Model;
+use crate::portal::Portal;
 use crate::scene::LoadedResources;
+use crate::time_of_day::TimeOfDayEvent;
+use crate::timeline::TimelineEvent;
 use crate::transform::Transform;
 
+use cgmath::Vector3;
+use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
+use std::collections::HashMap;
+
 #[derive(Debug)]
 pub struct Entity {
 	pub name: String,
 	pub enabled: bool,
+	// Relative to the parent entity, which the world transform composes with the transforms of all the ancestors
 	pub transform: Transform,
+	// Updated once a frame by `update_world_transforms_of_descendants`, so it's a frame behind any changes to the transforms made after that
+	pub world_transform: Transform,
 	pub components: Vec<Component>,
 	pub children: Vec<Entity>,
 }
@@ -22,6 +33,7 @@ impl Entity {
 			name: String::from(name),
 			enabled: true,
 			transform: Transform::default(),
+			world_transform: Transform::default(),
 			components: vec![],
 			children: vec![],
 		}
@@ -33,6 +45,12 @@ impl Entity {
 		self.children.last_mut().unwrap()
 	}
 
+	// Takes the named child out of the entity, along with its own children, or returns `None` if it has no child by that name
+	pub fn remove_child(&mut self, name: &str) -> Option<Entity> {
+		let index = self.children.iter().position(|child| child.name == name)?;
+		Some(self.children.remove(index))
+	}
+
 	pub fn add_component(&mut self, component: Component) {
 		self.components.push(component)
 	}
@@ -46,18 +64,48 @@ impl Entity {
 		EntityIter { stack: vec![self] }
 	}
 
-	pub fn update_behaviors_of_descendants(&mut self) {
+	pub fn update_behaviors_of_descendants(&mut self, context: &BehaviorContext) {
+		let mut iter_components = vec![];
+		std::mem::swap(&mut iter_components, &mut self.components);
+		for component in iter_components.iter_mut() {
+			if let Component::Behavior(behavior) = component {
+				behavior.update(self, context);
+			}
+		}
+		std::mem::swap(&mut iter_components, &mut self.components);
+
+		for child in self.children.iter_mut() {
+			child.update_behaviors_of_descendants(context);
+		}
+	}
+
+	pub fn send_time_of_day_event_to_descendants(&mut self, event: TimeOfDayEvent) {
 		let mut iter_components = vec![];
 		std::mem::swap(&mut iter_components, &mut self.components);
-		for component in iter_components.iter() {
+		for component in iter_components.iter_mut() {
 			if let Component::Behavior(behavior) = component {
-				behavior.update(self);
+				behavior.time_of_day_event(self, event);
 			}
 		}
 		std::mem::swap(&mut iter_components, &mut self.components);
 
 		for child in self.children.iter_mut() {
-			child.update_behaviors_of_descendants();
+			child.send_time_of_day_event_to_descendants(event);
+		}
+	}
+
+	pub fn send_timeline_event_to_descendants(&mut self, event: &TimelineEvent) {
+		let mut iter_components = vec![];
+		std::mem::swap(&mut iter_components, &mut self.components);
+		for component in iter_components.iter_mut() {
+			if let Component::Behavior(behavior) = component {
+				behavior.timeline_event(self, event);
+			}
+		}
+		std::mem::swap(&mut iter_components, &mut self.components);
+
+		for child in self.children.iter_mut() {
+			child.send_timeline_event_to_descendants(event);
 		}
 	}
 
@@ -76,6 +124,75 @@ impl Entity {
 		}
 	}
 
+	// Composes every entity's transform with its parent's world transform, and moves its models along with the parent
+	// A model's instances are placed in the same space as its entity's own transform, since the engine moves both of them to the same place
+	// Siblings only depend on their shared parent, so each child's subtree is updated on its own rayon task
+	pub fn update_world_transforms_of_descendants(&mut self, parent_world_transform: &Transform, device: &wgpu::Device) {
+		self.world_transform = self.transform.within(parent_world_transform);
+
+		for component in self.components.iter_mut() {
+			if let Component::Model(model) = component {
+				model.instances.set_parent_transform(parent_world_transform, device);
+			}
+		}
+
+		let world_transform = self.world_transform;
+		self.children.par_iter_mut().for_each(|child| child.update_world_transforms_of_descendants(&world_transform, device));
+	}
+
+	// Frees the instance buffers of the models on removed entities right away, rather than whenever the entity happens to be dropped
+	pub fn release_gpu_buffers_of_descendants(&mut self) {
+		for model in self.get_models_mut() {
+			model.instances.release_buffer();
+		}
+
+		for child in self.children.iter_mut() {
+			child.release_gpu_buffers_of_descendants();
+		}
+	}
+
+	// Places each mirror's reflection camera opposite the viewing camera across the plane given by its entity's transform
+	pub fn update_mirrors_of_descendants(&mut self, view_camera: &CameraUniform, queue: &wgpu::Queue) {
+		let transform = self.transform;
+		for component in self.components.iter_mut() {
+			if let Component::Mirror(mirror) = component {
+				mirror.update_reflection_camera(&transform, view_camera, queue);
+			}
+		}
+
+		for child in self.children.iter_mut() {
+			child.update_mirrors_of_descendants(view_camera, queue);
+		}
+	}
+
+	// Places each portal's camera on the far side of its linked portal, found by entity name among the transforms and normals of every portal in the scene
+	pub fn update_portals_of_descendants(&mut self, portal_frames: &HashMap<String, (Transform, Vector3<f64>)>, view_camera: &CameraUniform, queue: &wgpu::Queue) {
+		let transform = self.transform;
+		for component in self.components.iter_mut() {
+			if let Component::Portal(portal) = component {
+				let (linked_transform, linked_normal) = portal_frames
+					.get(&portal.linked_portal)
+					.unwrap_or_else(|| panic!("The portal on entity '{}' is linked to '{}', which has no portal", self.name, portal.linked_portal));
+				portal.update_portal_camera(&transform, linked_transform, *linked_normal, view_camera, queue);
+			}
+		}
+
+		for child in self.children.iter_mut() {
+			child.update_portals_of_descendants(portal_frames, view_camera, queue);
+		}
+	}
+
+	// Dithers away the models of the entity and its descendants by the same amount, see `Instances::fade`
+	pub fn set_fade_of_descendants(&mut self, fade: f32, device: &wgpu::Device) {
+		for model in self.get_models_mut() {
+			model.instances.set_fade(fade, device);
+		}
+
+		for child in self.children.iter_mut() {
+			child.set_fade_of_descendants(fade, device);
+		}
+	}
+
 	pub fn find_descendant(&self, name: &str) -> Option<&Entity> {
 		self.children.iter().find(|entity| entity.name == name)
 	}
@@ -88,7 +205,7 @@ impl Entity {
 		self.components
 			.iter()
 			.filter_map(|component| match component {
-				Component::Model(model) => Some(model),
+				Component::Model(model) => Some(model.as_ref()),
 				_ => None,
 			})
 			.collect()
@@ -98,7 +215,7 @@ impl Entity {
 		self.components
 			.iter_mut()
 			.filter_map(|component| match component {
-				Component::Model(model) => Some(model),
+				Component::Model(model) => Some(model.as_mut()),
 				_ => None,
 			})
 			.collect()
@@ -144,6 +261,26 @@ impl Entity {
 			.collect()
 	}
 
+	pub fn get_mirrors(&self) -> Vec<&Mirror> {
+		self.components
+			.iter()
+			.filter_map(|component| match component {
+				Component::Mirror(mirror) => Some(mirror.as_ref()),
+				_ => None,
+			})
+			.collect()
+	}
+
+	pub fn get_portals(&self) -> Vec<&Portal> {
+		self.components
+			.iter()
+			.filter_map(|component| match component {
+				Component::Portal(portal) => Some(portal.as_ref()),
+				_ => None,
+			})
+			.collect()
+	}
+
 	#[allow(clippy::borrowed_box)]
 	pub fn get_behaviors(&self) -> Vec<&Box<dyn Behavior>> {
 		self.components
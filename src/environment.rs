@@ -0,0 +1,364 @@
+use crate::json::JsonValue;
+use crate::light::{AmbientMode, HemisphereLight, DEFAULT_SKY_AMBIENT};
+use crate::texture::{CubemapFaces, CubemapSource, Texture, PREFILTERED_MIP_LEVELS};
+
+use anyhow::{anyhow, bail, Result};
+use wgpu::util::DeviceExt;
+
+// How fog thickens with distance from the camera
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FogMode {
+	Off,
+	// Clear up to the start distance and fully fogged past the end distance
+	Linear,
+	// Thickens evenly through the air, so each meter hides the same fraction of what's behind it
+	Exponential,
+	// Exponential fog that thins out going up from the base height, pooling in valleys and low ground
+	Height,
+}
+
+impl FogMode {
+	pub const ALL: [Self; 4] = [Self::Off, Self::Linear, Self::Exponential, Self::Height];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Off => "off",
+			Self::Linear => "linear",
+			Self::Exponential => "exponential",
+			Self::Height => "height",
+		}
+	}
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct EnvironmentUniform {
+	fog_color: [f32; 3],
+	// 0 for off, 1 for linear, 2 for exponential, and 3 for height
+	fog_mode: u32,
+	fog_start: f32,
+	fog_end: f32,
+	fog_density: f32,
+	fog_height_falloff: f32,
+	fog_base_height: f32,
+	// Zero while there's no sky map, so the shading pass only adds the hemisphere light and sky ambient
+	sky_map_intensity: f32,
+	// The prefiltered map's last mip level, which is blurred for a roughness of 1
+	prefiltered_max_mip: f32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: u32,
+}
+
+// Analytic fog faded over the shaded scene, a cheap stand-in for atmosphere that doesn't march through any volume
+#[derive(Debug, Copy, Clone)]
+pub struct Fog {
+	pub mode: FogMode,
+	// Linear RGB in the same units as the lighting, since it replaces the shaded color rather than being lit itself
+	pub color: [f32; 3],
+	// Meters from the camera where linear fog begins and where it hides everything
+	pub start: f32,
+	pub end: f32,
+	// Fraction of the light scattered away per meter by exponential fog, and by height fog at its base height
+	pub density: f32,
+	// How quickly height fog thins out per meter above its base height
+	pub height_falloff: f32,
+	pub base_height: f32,
+}
+
+impl Default for Fog {
+	fn default() -> Self {
+		Self {
+			mode: FogMode::Off,
+			color: [0.05, 0.055, 0.06],
+			start: 10.,
+			end: 80.,
+			density: 0.02,
+			height_falloff: 0.3,
+			base_height: 0.,
+		}
+	}
+}
+
+impl Fog {
+	// As the console's fog command shows it
+	pub fn description(&self) -> String {
+		let [red, green, blue] = self.color;
+		format!(
+			"fog {}, color {} {} {}, distance {} {}, density {}, height {} {}",
+			self.mode.name(),
+			red,
+			green,
+			blue,
+			self.start,
+			self.end,
+			self.density,
+			self.base_height,
+			self.height_falloff
+		)
+	}
+}
+
+// Finishing touches on the tone mapped frame, each of which the final composite skips while it's left at no effect
+#[derive(Debug, Copy, Clone)]
+pub struct ColorGrading {
+	// 0 is grayscale and 1 leaves the colors as they are, with higher values making them more vivid
+	pub saturation: f32,
+	// Pushes the colors away from middle gray above 1, or flattens them toward it below
+	pub contrast: f32,
+	// How much the corners darken, from 0 for not at all to 1 for black
+	pub vignette: f32,
+	// Strength of the film grain noise, which changes every frame
+	pub grain: f32,
+}
+
+impl Default for ColorGrading {
+	fn default() -> Self {
+		Self {
+			saturation: 1.,
+			contrast: 1.,
+			vignette: 0.,
+			grain: 0.,
+		}
+	}
+}
+
+impl ColorGrading {
+	// As the console's grading command shows it
+	pub fn description(&self) -> String {
+		format!("grading saturation {}, contrast {}, vignette {}, grain {}", self.saturation, self.contrast, self.vignette, self.grain)
+	}
+}
+
+// World-level rendering settings that belong to the scene rather than to any entity in it, loaded from and saved to the scene file's "environment" object
+#[derive(Debug, Clone)]
+pub struct SceneEnvironment {
+	// The hemisphere light filling in for the voxel GI
+	pub ambient: HemisphereLight,
+	pub fog: Fog,
+	pub grading: ColorGrading,
+	// Light arriving evenly from the whole sky, used while the time of day isn't animating it
+	pub sky_ambient: [f32; 3],
+	// Multiplied into the active camera's exposure, so a darker or brighter scene can be balanced without changing every camera
+	pub exposure: f32,
+	// Drawn behind everything and sampled for the ambient light, in the same units as the lighting once scaled by its intensity
+	pub sky_map: Option<CubemapSource>,
+	pub sky_map_intensity: f32,
+}
+
+impl Default for SceneEnvironment {
+	fn default() -> Self {
+		Self {
+			ambient: HemisphereLight::default(),
+			fog: Fog::default(),
+			grading: ColorGrading::default(),
+			sky_ambient: DEFAULT_SKY_AMBIENT,
+			exposure: 1.,
+			sky_map: None,
+			sky_map_intensity: 1.,
+		}
+	}
+}
+
+impl SceneEnvironment {
+	// Anything the object leaves out keeps its default, the same as when the scene file has no environment at all
+	pub fn from_json(description: &JsonValue) -> Result<Self> {
+		let number = |value: &JsonValue, key: &str| value.get(key).and_then(JsonValue::as_number).map(|number| number as f32);
+		let color = |value: &JsonValue, key: &str| value.get(key).and_then(JsonValue::as_numbers::<3>).map(|color| color.map(|channel| channel as f32));
+		let string = |value: &JsonValue, key: &str| value.get(key).and_then(JsonValue::as_str).map(String::from);
+
+		let mut environment = Self::default();
+		environment.sky_ambient = color(description, "sky_ambient").unwrap_or(environment.sky_ambient);
+		environment.exposure = number(description, "exposure").unwrap_or(environment.exposure);
+		environment.sky_map_intensity = number(description, "sky_map_intensity").unwrap_or(environment.sky_map_intensity);
+
+		// Either a single panorama or an array of the six faces
+		environment.sky_map = match description.get("sky_map") {
+			None | Some(JsonValue::Null) => None,
+			Some(JsonValue::String(file)) => Some(CubemapSource::Equirectangular(file.clone())),
+			Some(JsonValue::Array(files)) => Some(CubemapSource::Faces(cube_face_files(files.iter().filter_map(JsonValue::as_str))?)),
+			Some(_) => bail!("The sky map has to be the file of a panorama or an array of the files of the six faces"),
+		};
+
+		if let Some(ambient) = description.get("ambient") {
+			let hemisphere = &mut environment.ambient;
+			hemisphere.sky_color = color(ambient, "sky_color").unwrap_or(hemisphere.sky_color);
+			hemisphere.ground_color = color(ambient, "ground_color").unwrap_or(hemisphere.ground_color);
+			hemisphere.intensity = number(ambient, "intensity").unwrap_or(hemisphere.intensity);
+			if let Some(mode) = string(ambient, "mode") {
+				hemisphere.mode = *AmbientMode::ALL
+					.iter()
+					.find(|ambient_mode| ambient_mode.name() == mode)
+					.ok_or_else(|| anyhow!("There's no ambient mode \"{}\", it can be fallback or additive", mode))?;
+			}
+		}
+
+		if let Some(description) = description.get("fog") {
+			let fog = &mut environment.fog;
+			if let Some(mode) = string(description, "mode") {
+				fog.mode = *FogMode::ALL
+					.iter()
+					.find(|fog_mode| fog_mode.name() == mode)
+					.ok_or_else(|| anyhow!("There's no fog mode \"{}\", it can be off, linear, exponential, or height", mode))?;
+			}
+			fog.color = color(description, "color").unwrap_or(fog.color);
+			fog.start = number(description, "start").unwrap_or(fog.start);
+			fog.end = number(description, "end").unwrap_or(fog.end);
+			fog.density = number(description, "density").unwrap_or(fog.density);
+			fog.height_falloff = number(description, "height_falloff").unwrap_or(fog.height_falloff);
+			fog.base_height = number(description, "base_height").unwrap_or(fog.base_height);
+		}
+
+		if let Some(description) = description.get("grading") {
+			let grading = &mut environment.grading;
+			grading.saturation = number(description, "saturation").unwrap_or(grading.saturation);
+			grading.contrast = number(description, "contrast").unwrap_or(grading.contrast);
+			grading.vignette = number(description, "vignette").unwrap_or(grading.vignette);
+			grading.grain = number(description, "grain").unwrap_or(grading.grain);
+		}
+
+		Ok(environment)
+	}
+
+	pub fn to_json(&self) -> JsonValue {
+		// Going through the shortest decimal that reads back as the same f32 keeps the file from filling up with digits the f32 never had
+		let number = |value: f32| JsonValue::Number(value.to_string().parse().unwrap_or_default());
+		let color = |color: [f32; 3]| JsonValue::Array(color.iter().map(|channel| number(*channel)).collect());
+
+		let sky_map = match &self.sky_map {
+			None => JsonValue::Null,
+			Some(CubemapSource::Equirectangular(file)) => JsonValue::String(file.clone()),
+			Some(CubemapSource::Faces(files)) => JsonValue::Array(files.iter().cloned().map(JsonValue::String).collect()),
+		};
+
+		JsonValue::object([
+			("sky_ambient", color(self.sky_ambient)),
+			("exposure", number(self.exposure)),
+			("sky_map", sky_map),
+			("sky_map_intensity", number(self.sky_map_intensity)),
+			(
+				"ambient",
+				JsonValue::object([
+					("sky_color", color(self.ambient.sky_color)),
+					("ground_color", color(self.ambient.ground_color)),
+					("intensity", number(self.ambient.intensity)),
+					("mode", JsonValue::String(String::from(self.ambient.mode.name()))),
+				]),
+			),
+			(
+				"fog",
+				JsonValue::object([
+					("mode", JsonValue::String(String::from(self.fog.mode.name()))),
+					("color", color(self.fog.color)),
+					("start", number(self.fog.start)),
+					("end", number(self.fog.end)),
+					("density", number(self.fog.density)),
+					("height_falloff", number(self.fog.height_falloff)),
+					("base_height", number(self.fog.base_height)),
+				]),
+			),
+			(
+				"grading",
+				JsonValue::object([
+					("saturation", number(self.grading.saturation)),
+					("contrast", number(self.grading.contrast)),
+					("vignette", number(self.grading.vignette)),
+					("grain", number(self.grading.grain)),
+				]),
+			),
+		])
+	}
+
+	// As the console's environment command shows it, leaving the ambient light, fog, and grading to their own commands
+	pub fn description(&self) -> String {
+		let [red, green, blue] = self.sky_ambient;
+		let sky_map = match &self.sky_map {
+			None => String::from("none"),
+			Some(CubemapSource::Equirectangular(file)) => file.clone(),
+			Some(CubemapSource::Faces(files)) => files.join(" "),
+		};
+		format!(
+			"environment sky {} {} {}, exposure {}, sky_map {}, sky_map_intensity {}",
+			red, green, blue, self.exposure, sky_map, self.sky_map_intensity
+		)
+	}
+}
+
+// The files of a cubemap's six faces, which there have to be exactly six of
+pub fn cube_face_files<'a>(files: impl Iterator<Item = &'a str>) -> Result<[String; 6]> {
+	let files = files.map(String::from).collect::<Vec<_>>();
+	let count = files.len();
+	files.try_into().map_err(|_| anyhow!("A cubemap needs the files of its six faces, not {}", count))
+}
+
+// What the PBR shading and skybox passes bind of the scene's environment: the uniform buffer rewritten from it every frame, its sky map, and the image-based lighting filtered from it
+pub struct EnvironmentBindings {
+	pub buffer: wgpu::Buffer,
+	// A black cubemap while the scene has no sky map, or its sky map couldn't be read
+	pub sky_map: Texture,
+	// Filtered from the sky map whenever it's replaced, for the diffuse and specular light it casts on the scene
+	pub irradiance_map: Texture,
+	pub prefiltered_map: Texture,
+	pub brdf_lut: Texture,
+	// What the sky map was decoded from, so it's only decoded again once the environment names another one
+	sky_map_source: Option<CubemapSource>,
+	sky_map_loaded: bool,
+}
+
+impl EnvironmentBindings {
+	pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+		let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Environment Buffer"),
+			contents: bytemuck::cast_slice(&[<EnvironmentUniform as bytemuck::Zeroable>::zeroed()]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
+
+		let sky_map = Texture::cube_from_faces(device, queue, &CubemapFaces::filled([0.; 3]), "Sky Map");
+
+		Self {
+			buffer,
+			irradiance_map: Texture::irradiance_cube(device, queue, &sky_map),
+			prefiltered_map: Texture::prefiltered_cube(device, queue, &sky_map),
+			brdf_lut: Texture::brdf_lut(device, queue),
+			sky_map,
+			sky_map_source: None,
+			sky_map_loaded: false,
+		}
+	}
+
+	pub fn sky_map_outdated(&self, environment: &SceneEnvironment) -> bool {
+		self.sky_map_source != environment.sky_map
+	}
+
+	pub fn sky_map_loaded(&self) -> bool {
+		self.sky_map_loaded
+	}
+
+	// Replaces the sky map with the faces decoded from the source, or a black one if there are none, and filters it again for the image-based lighting
+	// The materials binding them have to be rebuilt afterward
+	pub fn set_sky_map(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, source: Option<CubemapSource>, faces: Option<&CubemapFaces>) {
+		self.sky_map_loaded = faces.is_some();
+		self.sky_map = Texture::cube_from_faces(device, queue, faces.unwrap_or(&CubemapFaces::filled([0.; 3])), "Sky Map");
+		self.irradiance_map = Texture::irradiance_cube(device, queue, &self.sky_map);
+		self.prefiltered_map = Texture::prefiltered_cube(device, queue, &self.sky_map);
+		self.sky_map_source = source;
+	}
+
+	pub fn update(&self, environment: &SceneEnvironment, queue: &wgpu::Queue) {
+		let fog = &environment.fog;
+		let uniform = EnvironmentUniform {
+			fog_color: fog.color,
+			fog_mode: fog.mode as u32,
+			fog_start: fog.start,
+			// Keeps linear fog from dividing by zero when both distances are the same
+			fog_end: fog.end.max(fog.start + 0.01),
+			fog_density: fog.density.max(0.),
+			fog_height_falloff: fog.height_falloff,
+			fog_base_height: fog.base_height,
+			sky_map_intensity: if self.sky_map_loaded { environment.sky_map_intensity } else { 0. },
+			prefiltered_max_mip: (PREFILTERED_MIP_LEVELS - 1) as f32,
+			_padding: 0,
+		};
+
+		queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+	}
+}
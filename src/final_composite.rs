@@ -0,0 +1,51 @@
+use crate::environment::ColorGrading;
+
+use wgpu::util::DeviceExt;
+
+// Which of the stages after tone mapping the final composite runs, so those left at no effect are skipped over
+// wgpu can't specialize a pipeline by constants, but every pixel takes the same side of these branches, so a skipped stage costs next to nothing
+const COLOR_GRADING: u32 = 1;
+const VIGNETTE: u32 = 2;
+const GRAIN: u32 = 4;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FinalCompositeUniform {
+	saturation: f32,
+	contrast: f32,
+	vignette: f32,
+	grain: f32,
+	stages: u32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: [u32; 3],
+}
+
+// Exposure, tone mapping, color grading, vignette, and grain are all applied in the one pass drawing into the surface, so the frame is only read and written once for all of them
+pub struct FinalComposite {
+	pub uniform_buffer: wgpu::Buffer,
+}
+
+impl FinalComposite {
+	pub fn new(device: &wgpu::Device) -> Self {
+		let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Final Composite Uniform Buffer"),
+			contents: bytemuck::cast_slice(&[<FinalCompositeUniform as bytemuck::Zeroable>::zeroed()]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
+
+		Self { uniform_buffer }
+	}
+
+	pub fn update(&self, queue: &wgpu::Queue, grading: &ColorGrading) {
+		let stage = |stage: u32, enabled: bool| if enabled { stage } else { 0 };
+		let uniform = FinalCompositeUniform {
+			saturation: grading.saturation.max(0.),
+			contrast: grading.contrast.max(0.),
+			vignette: grading.vignette.clamp(0., 1.),
+			grain: grading.grain.max(0.),
+			stages: stage(COLOR_GRADING, grading.saturation != 1. || grading.contrast != 1.) | stage(VIGNETTE, grading.vignette > 0.) | stage(GRAIN, grading.grain > 0.),
+			_padding: [0; 3],
+		};
+		queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+	}
+}
@@ -0,0 +1,154 @@
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Rad, Vector3};
+use std::time::Duration;
+use winit::dpi::PhysicalPosition;
+use winit::event::{ElementState, MouseScrollDelta, VirtualKeyCode};
+
+use crate::camera::Camera;
+use crate::collision::CollisionWorld;
+
+// Looking too far over or under the target would swing the boom through the floor or flip the view
+const MAX_ORBIT_PITCH: f32 = 1.4;
+const MIN_DISTANCE: f32 = 1.;
+const MAX_DISTANCE: f32 = 20.;
+// The boom's end is kept this far from any surface, which is a bit more than the near plane so walls never get clipped
+const PROBE_RADIUS: f32 = 0.2;
+// The boom snaps in as soon as something comes between the camera and its target, but only eases back out to its full length at this rate
+const BOOM_EXTEND_SPEED: f32 = 4.;
+const TARGET_SWITCH_DURATION: f32 = 1.;
+
+// Orbits the camera around a target entity at the end of a boom, which trails the target with some lag and pulls in to keep the camera out of walls
+#[derive(Debug)]
+pub struct FollowCameraController {
+	active: bool,
+	// Entities the camera can follow, cycled through in order
+	targets: Vec<String>,
+	target_index: usize,
+	// Offset of the pivot above the target's location, so the camera looks at the middle of the target rather than its origin
+	pivot_height: f32,
+	// Seconds for the pivot to close most of the gap to the target, or 0 to stay locked onto it
+	lag: f32,
+	distance: f32,
+	rotate_horizontal: f32,
+	rotate_vertical: f32,
+	scroll: f32,
+	sensitivity: f32,
+	lagged_pivot: Option<Vector3<f32>>,
+	boom_length: f32,
+	// Where the pivot was when the target last changed, and how far the pivot has blended from there toward the new target
+	switch_from: Option<Vector3<f32>>,
+	switch_blend: f32,
+}
+
+impl FollowCameraController {
+	pub fn new(targets: Vec<String>, distance: f32, lag: f32, sensitivity: f32) -> Self {
+		Self {
+			active: false,
+			targets,
+			target_index: 0,
+			pivot_height: 0.5,
+			lag,
+			distance,
+			rotate_horizontal: 0.0,
+			rotate_vertical: 0.0,
+			scroll: 0.0,
+			sensitivity,
+			lagged_pivot: None,
+			boom_length: distance,
+			switch_from: None,
+			switch_blend: 1.,
+		}
+	}
+
+	pub fn active(&self) -> bool {
+		self.active
+	}
+
+	pub fn target(&self) -> Option<&str> {
+		self.targets.get(self.target_index).map(|target| target.as_str())
+	}
+
+	pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
+		if state != ElementState::Pressed {
+			return matches!(key, VirtualKeyCode::C | VirtualKeyCode::Tab);
+		}
+
+		match key {
+			VirtualKeyCode::C => {
+				self.active = !self.active;
+				// The pivot starts right on the target again rather than trailing in from wherever it was last left
+				self.lagged_pivot = None;
+				self.switch_from = None;
+				true
+			}
+			VirtualKeyCode::Tab => {
+				if self.active && !self.targets.is_empty() {
+					self.target_index = (self.target_index + 1) % self.targets.len();
+					self.switch_from = self.lagged_pivot;
+					self.switch_blend = 0.;
+				}
+				true
+			}
+			_ => false,
+		}
+	}
+
+	pub fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
+		self.rotate_horizontal = mouse_dx as f32;
+		self.rotate_vertical = mouse_dy as f32;
+	}
+
+	pub fn process_scroll(&mut self, delta: &MouseScrollDelta) {
+		self.scroll = -match delta {
+			// I'm assuming a line is about 100 pixels
+			MouseScrollDelta::LineDelta(_, scroll) => scroll * 100.0,
+			MouseScrollDelta::PixelDelta(PhysicalPosition { y: scroll, .. }) => *scroll as f32,
+		};
+	}
+
+	pub fn update_camera(&mut self, camera: &mut Camera, target_location: Point3<f32>, collision_world: &CollisionWorld, dt: Duration) {
+		let dt = dt.as_secs_f32();
+
+		// Orbit around the target, using the camera's own angles so switching between controllers never turns the view
+		camera.yaw += Rad(self.rotate_horizontal) * self.sensitivity * dt;
+		camera.pitch += Rad(-self.rotate_vertical) * self.sensitivity * dt;
+		self.rotate_horizontal = 0.0;
+		self.rotate_vertical = 0.0;
+		camera.pitch = Rad(camera.pitch.0.clamp(-MAX_ORBIT_PITCH, MAX_ORBIT_PITCH));
+
+		// Scrolling lengthens or shortens the boom
+		self.distance = (self.distance + self.scroll * self.sensitivity * dt).clamp(MIN_DISTANCE, MAX_DISTANCE);
+		self.scroll = 0.0;
+
+		// Trail the target, closing the same fraction of the gap each second however the frame rate varies
+		let goal = target_location.to_vec() + Vector3::unit_y() * self.pivot_height;
+		let lagged_pivot = match self.lagged_pivot {
+			Some(pivot) if self.lag > 0. => pivot + (goal - pivot) * (1. - (-dt / self.lag).exp()),
+			_ => goal,
+		};
+		self.lagged_pivot = Some(lagged_pivot);
+
+		// Ease over from the previous target, with the blend itself easing in and out
+		self.switch_blend = (self.switch_blend + dt / TARGET_SWITCH_DURATION).min(1.);
+		let pivot = match self.switch_from {
+			Some(from) if self.switch_blend < 1. => {
+				let eased = self.switch_blend * self.switch_blend * (3. - 2. * self.switch_blend);
+				from + (lagged_pivot - from) * eased
+			}
+			_ => lagged_pivot,
+		};
+
+		// The boom points back from the pivot, opposite the way the camera looks
+		let (yaw_sin, yaw_cos) = camera.yaw.0.sin_cos();
+		let (pitch_sin, pitch_cos) = camera.pitch.0.sin_cos();
+		let forward = Vector3::new(pitch_cos * yaw_cos, pitch_sin, pitch_cos * yaw_sin).normalize();
+
+		let unobstructed_length = collision_world.sphere_cast(pivot, -forward, self.distance, PROBE_RADIUS);
+		self.boom_length = if unobstructed_length < self.boom_length {
+			unobstructed_length
+		} else {
+			(self.boom_length + BOOM_EXTEND_SPEED * dt).min(unobstructed_length)
+		};
+
+		camera.location = Point3::from_vec(pivot - forward * self.boom_length);
+	}
+}
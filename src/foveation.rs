@@ -0,0 +1,68 @@
+use wgpu::util::DeviceExt;
+
+// Screen regions shaded at a reduced rate, growing coarser away from where the viewer looks
+// wgpu has no variable rate shading, so the PBR shading pass skips pixels itself and a fill pass reconstructs them from their shaded neighbors:
+// - Inside the inner radius every pixel is shaded
+// - Between the radii every other pixel is shaded in a checkerboard
+// - Beyond the outer radius one pixel of each 2x2 block is shaded
+// Checkerboard shading takes turns between the halves of a checkerboard where every pixel would be shaded, filling in the other half from the last frame
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FoveationSettings {
+	// Where the viewer looks in UV space, which is the middle of the screen unless an eye tracker says otherwise
+	pub center: [f32; 2],
+	// Distances from the center in units of half the screen height, so the regions stay round at any aspect ratio
+	pub inner_radius: f32,
+	pub outer_radius: f32,
+	pub enabled: u32,
+	pub checkerboard: u32,
+	// 0 until checkerboard shading has reconstructed a frame, so the fill pass has the half skipped this frame from the last one
+	history_valid: u32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: u32,
+}
+
+impl Default for FoveationSettings {
+	fn default() -> Self {
+		Self {
+			center: [0.5, 0.5],
+			inner_radius: 0.5,
+			outer_radius: 0.9,
+			enabled: 0,
+			checkerboard: 0,
+			history_valid: 0,
+			_padding: 0,
+		}
+	}
+}
+
+pub struct Foveation {
+	pub settings: FoveationSettings,
+	pub settings_buffer: wgpu::Buffer,
+}
+
+impl Foveation {
+	pub fn new(device: &wgpu::Device) -> Self {
+		let settings = FoveationSettings::default();
+		let settings_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Foveation Settings Buffer"),
+			contents: bytemuck::cast_slice(&[settings]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
+
+		Self { settings, settings_buffer }
+	}
+
+	// Forgets that the last frame was shaded in a checkerboard, so the next one fills in its skipped half from its neighbors instead of a history that no longer matches the screen
+	pub fn invalidate_history(&mut self) {
+		self.settings.checkerboard = 0;
+	}
+
+	pub fn update(&mut self, queue: &wgpu::Queue, enabled: bool, checkerboard: bool) {
+		self.settings.enabled = enabled as u32;
+		// The history is only written on frames shaded in a checkerboard
+		self.settings.history_valid = (self.settings.checkerboard != 0 && checkerboard) as u32;
+		self.settings.checkerboard = checkerboard as u32;
+		queue.write_buffer(&self.settings_buffer, 0, bytemuck::cast_slice(&[self.settings]));
+	}
+}
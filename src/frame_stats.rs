@@ -0,0 +1,277 @@
+use indexmap::IndexMap;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// How often the summary shown in the window title is refreshed, since changing the title every frame is slow on some platforms
+const SUMMARY_INTERVAL: Duration = Duration::from_millis(500);
+// Number of frames the CPU timings are averaged over, smoothing out the spikes of individual frames
+const TIMING_WINDOW: usize = 120;
+
+// The stages of `Engine::draw_frame` that are timed on the CPU, in the order they run
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CpuTimingScope {
+	// Calling `update()` on every entity behavior
+	Behaviors,
+	// The rest of the frame's update, which writes the camera, light, instance, mirror, and voxel region buffers
+	Uniforms,
+	// Waiting for the next surface texture, which is where a vsync or GPU bound frame blocks
+	Acquire,
+	// Culling and recording the command buffer
+	Encode,
+	Submit,
+	Present,
+}
+
+impl CpuTimingScope {
+	pub const ALL: [CpuTimingScope; 6] = [Self::Behaviors, Self::Uniforms, Self::Acquire, Self::Encode, Self::Submit, Self::Present];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Behaviors => "behaviors",
+			Self::Uniforms => "uniforms",
+			Self::Acquire => "acquire",
+			Self::Encode => "encode",
+			Self::Submit => "submit",
+			Self::Present => "present",
+		}
+	}
+}
+
+// Average of the last `TIMING_WINDOW` samples
+#[derive(Debug, Default)]
+pub struct RollingAverage {
+	samples: VecDeque<Duration>,
+	sum: Duration,
+}
+
+impl RollingAverage {
+	pub fn push(&mut self, sample: Duration) {
+		if self.samples.len() == TIMING_WINDOW {
+			self.sum -= self.samples.pop_front().unwrap();
+		}
+		self.samples.push_back(sample);
+		self.sum += sample;
+	}
+
+	pub fn average(&self) -> Duration {
+		match self.samples.len() {
+			0 => Duration::ZERO,
+			count => self.sum / count as u32,
+		}
+	}
+}
+
+// The work submitted by the draws of one pass
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PassStats {
+	pub draw_calls: u32,
+	pub instances: u32,
+	pub triangles: u64,
+	pub pipeline_switches: u32,
+	pub bind_group_switches: u32,
+	// Models in the pass's draw list left undrawn because they were outside the view
+	pub culled: u32,
+}
+
+impl PassStats {
+	fn add(&mut self, other: &PassStats) {
+		self.draw_calls += other.draw_calls;
+		self.instances += other.instances;
+		self.triangles += other.triangles;
+		self.pipeline_switches += other.pipeline_switches;
+		self.bind_group_switches += other.bind_group_switches;
+		self.culled += other.culled;
+	}
+}
+
+// Counts the state changes and draws within one render pass, where binding the same object again doesn't count as a switch
+#[derive(Default)]
+pub struct PassCounter<'a> {
+	pub stats: PassStats,
+	pipeline: Option<&'a wgpu::RenderPipeline>,
+	bind_groups: [Option<&'a wgpu::BindGroup>; 4],
+}
+
+impl<'a> PassCounter<'a> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn pipeline(&mut self, pipeline: &'a wgpu::RenderPipeline) {
+		if !self.pipeline.is_some_and(|bound| std::ptr::eq(bound, pipeline)) {
+			self.stats.pipeline_switches += 1;
+			self.pipeline = Some(pipeline);
+		}
+	}
+
+	pub fn bind_group(&mut self, index: u32, bind_group: &'a wgpu::BindGroup) {
+		let bound = &mut self.bind_groups[index as usize];
+		if !bound.is_some_and(|bound| std::ptr::eq(bound, bind_group)) {
+			self.stats.bind_group_switches += 1;
+			*bound = Some(bind_group);
+		}
+	}
+
+	pub fn draw(&mut self, index_count: u32, instance_count: u32) {
+		self.stats.draw_calls += 1;
+		self.stats.instances += instance_count;
+		self.stats.triangles += (index_count / 3) as u64 * instance_count as u64;
+	}
+}
+
+// Measures the GPU's time for a whole frame with timestamps written at the start and end of its command buffer
+// Only made where the GPU supports timestamp queries, leaving the frame stats with CPU timings alone otherwise
+pub struct GpuTimer {
+	query_set: wgpu::QuerySet,
+	readback_buffer: wgpu::Buffer,
+	// Nanoseconds per timestamp tick
+	period: f32,
+	// Whether the last frame's timestamps are waiting to be read back
+	pending: bool,
+}
+
+impl GpuTimer {
+	pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+		let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+			label: Some("GPU Frame Timer Queries"),
+			ty: wgpu::QueryType::Timestamp,
+			count: 2,
+		});
+		let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("GPU Frame Timer Readback Buffer"),
+			size: 2 * std::mem::size_of::<u64>() as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+			mapped_at_creation: false,
+		});
+
+		Self {
+			query_set,
+			readback_buffer,
+			period: queue.get_timestamp_period(),
+			pending: false,
+		}
+	}
+
+	pub fn begin(&self, encoder: &mut wgpu::CommandEncoder) {
+		encoder.write_timestamp(&self.query_set, 0);
+	}
+
+	pub fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+		encoder.write_timestamp(&self.query_set, 1);
+		encoder.resolve_query_set(&self.query_set, 0..2, &self.readback_buffer, 0);
+		self.pending = true;
+	}
+
+	// Reads back the last frame's time just before the next frame is encoded, by when the GPU has usually finished with it, so the wait is short
+	pub fn read(&mut self, device: &wgpu::Device) -> Option<Duration> {
+		if !self.pending {
+			return None;
+		}
+		self.pending = false;
+
+		let slice = self.readback_buffer.slice(..);
+		let mapping = slice.map_async(wgpu::MapMode::Read);
+		device.poll(wgpu::Maintain::Wait);
+		pollster::block_on(mapping).ok()?;
+
+		let timestamps: [u64; 2] = bytemuck::pod_read_unaligned(&slice.get_mapped_range());
+		self.readback_buffer.unmap();
+
+		let ticks = timestamps[1].saturating_sub(timestamps[0]);
+		Some(Duration::from_nanos((ticks as f64 * self.period as f64) as u64))
+	}
+}
+
+// Per-pass draw statistics of the last finished frame, in the order the passes first drew that frame, along with rolling CPU timings
+#[derive(Debug)]
+pub struct FrameStats {
+	pub passes: IndexMap<String, PassStats>,
+	// Indexed by the order of `CpuTimingScope::ALL`
+	cpu_timings: [RollingAverage; 6],
+	// Only measured where the GPU supports timestamp queries
+	gpu_timing: RollingAverage,
+	// Filled in while the frame is encoded, which happens through shared borrows of the engine
+	current: RefCell<IndexMap<String, PassStats>>,
+	last_summary: Instant,
+}
+
+impl Default for FrameStats {
+	fn default() -> Self {
+		Self {
+			passes: IndexMap::new(),
+			cpu_timings: Default::default(),
+			gpu_timing: RollingAverage::default(),
+			current: RefCell::new(IndexMap::new()),
+			last_summary: Instant::now(),
+		}
+	}
+}
+
+impl FrameStats {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// Passes drawn more than once a frame, like the reflection pass of each mirror, are added together
+	pub fn record(&self, pass_name: &str, stats: PassStats) {
+		self.current.borrow_mut().entry(String::from(pass_name)).or_default().add(&stats);
+	}
+
+	pub fn finish_frame(&mut self) {
+		self.passes = self.current.take();
+	}
+
+	pub fn record_cpu_time(&mut self, scope: CpuTimingScope, duration: Duration) {
+		self.cpu_timings[scope as usize].push(duration);
+	}
+
+	// Averaged over the last `TIMING_WINDOW` frames that reached this scope
+	pub fn cpu_time(&self, scope: CpuTimingScope) -> Duration {
+		self.cpu_timings[scope as usize].average()
+	}
+
+	pub fn record_gpu_time(&mut self, duration: Duration) {
+		self.gpu_timing.push(duration);
+	}
+
+	// Averaged like the CPU timings, or `None` if the GPU can't measure it
+	pub fn gpu_time(&self) -> Option<Duration> {
+		match self.gpu_timing.average() {
+			Duration::ZERO => None,
+			average => Some(average),
+		}
+	}
+
+	pub fn total(&self) -> PassStats {
+		self.passes.values().fold(PassStats::default(), |mut total, stats| {
+			total.add(stats);
+			total
+		})
+	}
+
+	// The summary, returned at most once per `SUMMARY_INTERVAL`
+	pub fn take_summary(&mut self) -> Option<String> {
+		if self.last_summary.elapsed() < SUMMARY_INTERVAL {
+			return None;
+		}
+		self.last_summary = Instant::now();
+
+		Some(self.summary())
+	}
+
+	// A one line summary of the frame's totals
+	pub fn summary(&self) -> String {
+		let total = self.total();
+		let cpu_timings = CpuTimingScope::ALL
+			.iter()
+			.map(|scope| format!("{} {:.2} ms", scope.name(), self.cpu_time(*scope).as_secs_f64() * 1000.))
+			.collect::<Vec<_>>()
+			.join(", ");
+		let gpu_timing = self.gpu_time().map_or(String::new(), |gpu_time| format!(" | GPU: {:.2} ms", gpu_time.as_secs_f64() * 1000.));
+		format!(
+			"{} draws | {} culled | {} instances | {} triangles | {} pipeline switches | {} bind group switches | CPU: {}{}",
+			total.draw_calls, total.culled, total.instances, total.triangles, total.pipeline_switches, total.bind_group_switches, cpu_timings, gpu_timing
+		)
+	}
+}
@@ -1,17 +1,48 @@
 use crate::texture::Texture;
 
+#[derive(Debug)]
 pub struct FrameTexture {
 	pub texture: Texture,
 	pub label: String,
 	pub compare: Option<wgpu::CompareFunction>,
+	// The window's resolution is divided by this for textures that can be rendered at a lower resolution, like those of the blurrier post-processing effects
+	pub resolution_divisor: u32,
+	// One view of each layer for rendering into, which only textures with more than one layer have, like a cube map's faces
+	pub layer_views: Vec<wgpu::TextureView>,
 }
 
 impl FrameTexture {
 	pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, format: wgpu::TextureFormat, label: &str, compare: Option<wgpu::CompareFunction>) -> Self {
+		Self::with_size(device, (config.width, config.height), format, label, compare)
+	}
+
+	// Recreates the texture at the window's resolution divided by the divisor, leaving the materials that bind it to be rebuilt
+	pub fn set_resolution_divisor(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, resolution_divisor: u32) {
+		self.resolution_divisor = resolution_divisor.max(1);
+		self.recreate(device, config);
+	}
+
+	// For render targets with their own resolution independent of the window, which are not resized along with the other frame textures
+	pub fn with_size(device: &wgpu::Device, dimensions: (u32, u32), format: wgpu::TextureFormat, label: &str, compare: Option<wgpu::CompareFunction>) -> Self {
+		Self::with_layers(device, dimensions, 1, format, label, compare)
+	}
+
+	// Six square faces in the order +X, -X, +Y, -Y, +Z, -Z, each rendered through its own layer view
+	// The whole texture is sampled as an array of its faces rather than as a cube, so shaders pick the face and project into it themselves
+	pub fn cube(device: &wgpu::Device, resolution: u32, format: wgpu::TextureFormat, label: &str, compare: Option<wgpu::CompareFunction>) -> Self {
+		Self::with_layers(device, (resolution, resolution), 6, format, label, compare)
+	}
+
+	// Layers of the same size that are each rendered through their own layer view, and sampled together so shaders can pick a layer by index
+	pub fn array(device: &wgpu::Device, dimensions: (u32, u32), layers: u32, format: wgpu::TextureFormat, label: &str, compare: Option<wgpu::CompareFunction>) -> Self {
+		Self::with_layers(device, dimensions, layers, format, label, compare)
+	}
+
+	fn with_layers(device: &wgpu::Device, dimensions: (u32, u32), layers: u32, format: wgpu::TextureFormat, label: &str, compare: Option<wgpu::CompareFunction>) -> Self {
 		let size = wgpu::Extent3d {
-			width: config.width,
-			height: config.height,
-			depth_or_array_layers: 1,
+			width: dimensions.0,
+			height: dimensions.1,
+			depth_or_array_layers: layers,
 		};
 		let texture_descriptor = wgpu::TextureDescriptor {
 			label: Some(label),
@@ -24,7 +55,23 @@ impl FrameTexture {
 		};
 		let texture = device.create_texture(&texture_descriptor);
 
-		let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+		let view = texture.create_view(&wgpu::TextureViewDescriptor {
+			dimension: Some(if layers > 1 { wgpu::TextureViewDimension::D2Array } else { wgpu::TextureViewDimension::D2 }),
+			..Default::default()
+		});
+		let layer_views = match layers {
+			1 => vec![],
+			_ => (0..layers)
+				.map(|layer| {
+					texture.create_view(&wgpu::TextureViewDescriptor {
+						dimension: Some(wgpu::TextureViewDimension::D2),
+						base_array_layer: layer,
+						array_layer_count: std::num::NonZeroU32::new(1),
+						..Default::default()
+					})
+				})
+				.collect(),
+		};
 		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
 			address_mode_u: wgpu::AddressMode::ClampToEdge,
 			address_mode_v: wgpu::AddressMode::ClampToEdge,
@@ -39,39 +86,167 @@ impl FrameTexture {
 		});
 
 		Self {
-			texture: Texture { texture, view, sampler, format, size },
+			texture: Texture {
+				texture,
+				view,
+				sampler,
+				format,
+				size,
+				label: String::from(label),
+			},
 			label: String::from(label),
 			compare,
+			resolution_divisor: 1,
+			layer_views,
 		}
 	}
 
 	pub fn recreate(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
-		self.texture = Self::new(device, config, self.texture.format, self.label.as_str(), self.compare).texture;
+		let dimensions = ((config.width / self.resolution_divisor).max(1), (config.height / self.resolution_divisor).max(1));
+		self.texture = Self::with_size(device, dimensions, self.texture.format, self.label.as_str(), self.compare).texture;
+	}
+
+	// Trades the GPU textures with another frame texture of the same size and format, while each keeps its own label
+	fn swap_texture(&mut self, other: &mut FrameTexture) {
+		std::mem::swap(&mut self.texture, &mut other.texture);
+		std::mem::swap(&mut self.texture.label, &mut other.texture.label);
+		std::mem::swap(&mut self.layer_views, &mut other.layer_views);
 	}
 }
 
 pub struct FrameTextures {
 	pub z_buffer: FrameTexture,
+	// Last frame's depth, which the Hi-Z pyramid is built from to cull this frame's meshlets against
 	pub z_buffer_previous: FrameTexture,
 	pub world_space_normal: FrameTexture,
 	pub albedo_map: FrameTexture,
 	pub arm_map: FrameTexture,
+	pub emissive_map: FrameTexture,
+	// Screen space motion of each surface since the previous frame, from the deferred pass, for the anti-aliasing and motion blur to follow moving objects by
+	pub velocity_map: FrameTexture,
 	pub ssao_kernel_map: FrameTexture,
+	// The occlusion accumulated over the frames so far, written by this frame's temporal pass from last frame's in the previous history map
+	pub ssao_history_map: FrameTexture,
+	pub ssao_history_previous_map: FrameTexture,
 	pub ssao_blurred_map: FrameTexture,
 	pub pbr_shaded_map: FrameTexture,
+	// The shading reconstructed by checkerboard shading, for the next frame to fill in the half it skips from
+	pub checkerboard_history_map: FrameTexture,
+	pub checkerboard_history_previous_map: FrameTexture,
 	pub motion_blur_map: FrameTexture,
+	// The resolved frames blended together so far, written by this frame's resolve from last frame's in the previous history map
+	pub taa_history_map: FrameTexture,
+	pub taa_history_previous_map: FrameTexture,
+	pub transparent_accumulation_map: FrameTexture,
+	pub transparent_coverage_map: FrameTexture,
+	pub scene_color_copy_map: FrameTexture,
+	// Counts the frames, whose parity says which of each temporal pair's two textures is written this frame
+	pub frame_index: u64,
 }
 
 impl FrameTextures {
+	// Temporal textures are kept in pairs of the one written this frame and the one holding what was written last frame
+	// Instead of copying one into the other each frame, the two trade their textures, so what was just written is read as the previous frame's without a copy
+	// Materials bind whichever texture is in each place at the time, so those sampling a temporal texture are built once for each parity, see `temporal_material_name`
+	pub fn advance_frame(&mut self) {
+		self.frame_index += 1;
+		self.swap_temporal_pairs();
+	}
+
+	pub fn parity(&self) -> usize {
+		(self.frame_index % 2) as usize
+	}
+
+	// Makes the textures stand as they will on the frames of the other parity, for building the materials they'll bind then
+	pub fn swap_temporal_pairs(&mut self) {
+		self.z_buffer.swap_texture(&mut self.z_buffer_previous);
+		self.ssao_history_map.swap_texture(&mut self.ssao_history_previous_map);
+		self.checkerboard_history_map.swap_texture(&mut self.checkerboard_history_previous_map);
+		self.taa_history_map.swap_texture(&mut self.taa_history_previous_map);
+	}
+
+	// Whether a texture is written this frame or is the other half of a temporal pair
+	pub fn is_temporal(&self, label: &str) -> bool {
+		[
+			&self.z_buffer,
+			&self.z_buffer_previous,
+			&self.ssao_history_map,
+			&self.ssao_history_previous_map,
+			&self.checkerboard_history_map,
+			&self.checkerboard_history_previous_map,
+			&self.taa_history_map,
+			&self.taa_history_previous_map,
+		]
+		.iter()
+		.any(|frame_texture| frame_texture.label == label)
+	}
+
+	// Whether a texture holds what was written last frame, so reading it before anything writes it this frame is expected rather than stale
+	pub fn is_previous_frame(&self, label: &str) -> bool {
+		[
+			&self.z_buffer_previous,
+			&self.ssao_history_previous_map,
+			&self.checkerboard_history_previous_map,
+			&self.taa_history_previous_map,
+		]
+		.iter()
+		.any(|frame_texture| frame_texture.label == label)
+	}
+
+	// The textures the previous depth is held in on even and on odd frames, which are the two textures of the depth pair in some order
+	pub fn z_buffers_previous_by_parity(&self) -> [&Texture; 2] {
+		match self.parity() {
+			0 => [&self.z_buffer_previous.texture, &self.z_buffer.texture],
+			_ => [&self.z_buffer.texture, &self.z_buffer_previous.texture],
+		}
+	}
+
 	pub fn recreate_all(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
 		self.z_buffer.recreate(device, config);
 		self.z_buffer_previous.recreate(device, config);
 		self.world_space_normal.recreate(device, config);
 		self.albedo_map.recreate(device, config);
 		self.arm_map.recreate(device, config);
+		self.emissive_map.recreate(device, config);
+		self.velocity_map.recreate(device, config);
 		self.ssao_kernel_map.recreate(device, config);
+		self.ssao_history_map.recreate(device, config);
+		self.ssao_history_previous_map.recreate(device, config);
 		self.ssao_blurred_map.recreate(device, config);
 		self.pbr_shaded_map.recreate(device, config);
+		self.checkerboard_history_map.recreate(device, config);
+		self.checkerboard_history_previous_map.recreate(device, config);
 		self.motion_blur_map.recreate(device, config);
+		self.taa_history_map.recreate(device, config);
+		self.taa_history_previous_map.recreate(device, config);
+		self.transparent_accumulation_map.recreate(device, config);
+		self.transparent_coverage_map.recreate(device, config);
+		self.scene_color_copy_map.recreate(device, config);
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = &FrameTexture> {
+		[
+			&self.z_buffer,
+			&self.z_buffer_previous,
+			&self.world_space_normal,
+			&self.albedo_map,
+			&self.arm_map,
+			&self.emissive_map,
+			&self.velocity_map,
+			&self.ssao_kernel_map,
+			&self.ssao_history_map,
+			&self.ssao_history_previous_map,
+			&self.ssao_blurred_map,
+			&self.pbr_shaded_map,
+			&self.checkerboard_history_map,
+			&self.checkerboard_history_previous_map,
+			&self.motion_blur_map,
+			&self.taa_history_map,
+			&self.taa_history_previous_map,
+			&self.transparent_accumulation_map,
+			&self.transparent_coverage_map,
+			&self.scene_color_copy_map,
+		]
+		.into_iter()
 	}
 }
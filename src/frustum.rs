@@ -0,0 +1,43 @@
+use cgmath::{InnerSpace, Matrix3, Matrix4, Point3, Vector3, Vector4};
+
+// The six clipping planes of a camera's view-projection matrix, each stored as a normal (xyz) pointing into the frustum and a distance (w)
+#[derive(Debug, Copy, Clone)]
+pub struct Frustum {
+	planes: [Vector4<f32>; 6],
+	// The camera's user clip plane, if it has one, which hides everything behind it just like the frustum's own planes
+	clip_plane: Option<Vector4<f32>>,
+}
+
+impl Frustum {
+	pub fn from_matrix(vp: Matrix4<f32>) -> Self {
+		let row = |i: usize| Vector4::new(vp.x[i], vp.y[i], vp.z[i], vp.w[i]);
+		let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+
+		// wgpu clip space depth goes from 0 to 1, so the near plane is just the third row rather than `r3 + r2` like in OpenGL
+		let planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2].map(|plane| plane / plane.truncate().magnitude());
+
+		Self { planes, clip_plane: None }
+	}
+
+	pub fn with_clip_plane(mut self, clip_plane: Option<Vector4<f32>>) -> Self {
+		self.clip_plane = clip_plane.map(|plane| plane / plane.truncate().magnitude());
+		self
+	}
+
+	pub fn intersects_sphere(&self, center: Point3<f32>, radius: f32) -> bool {
+		self.planes
+			.iter()
+			.chain(&self.clip_plane)
+			.all(|plane| plane.x * center.x + plane.y * center.y + plane.z * center.z + plane.w >= -radius)
+	}
+
+	// Tests a box given by its center and the axes reaching from there to the middle of three of its faces, so it can be rotated and scaled with its model
+	// Each plane is compared against how far the box reaches toward it, which is the box's extent projected onto the plane's normal
+	pub fn intersects_box(&self, center: Point3<f32>, half_axes: Matrix3<f32>) -> bool {
+		self.planes.iter().chain(&self.clip_plane).all(|plane| {
+			let normal = plane.truncate();
+			let reach = half_axes.x.dot(normal).abs() + half_axes.y.dot(normal).abs() + half_axes.z.dot(normal).abs();
+			normal.dot(Vector3::new(center.x, center.y, center.z)) + plane.w >= -reach
+		})
+	}
+}
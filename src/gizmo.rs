@@ -0,0 +1,223 @@
+use crate::camera::Camera;
+use crate::debug_draw::{perpendicular_axes, DebugDraw};
+use crate::light::{Lamp, Sun};
+
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Vector3, Vector4};
+use winit::dpi::PhysicalPosition;
+use winit::event::{ElementState, MouseButton, WindowEvent};
+
+// Gizmos are sized as this fraction of their distance from the camera, so they stay the same size on screen
+const GIZMO_SCREEN_SIZE: f32 = 0.03;
+
+const SELECTED_COLOR: [f32; 4] = [1., 0.8, 0.1, 1.];
+const RANGE_COLOR: [f32; 4] = [1., 1., 1., 0.25];
+const CONE_COLOR: [f32; 4] = [1., 1., 1., 0.5];
+
+// Where the camera is and what it sees, captured before the lights are borrowed for editing
+pub struct GizmoView {
+	eye: Point3<f32>,
+	inverse_vp_matrix: Matrix4<f32>,
+	right: Vector3<f32>,
+	forward: Vector3<f32>,
+	screen_size: (f32, f32),
+}
+
+impl GizmoView {
+	pub fn new(camera: &Camera, screen_size: (u32, u32)) -> Self {
+		let inverse_v_matrix = camera.camera_uniform.v_matrix().invert().unwrap();
+
+		Self {
+			eye: camera.location,
+			inverse_vp_matrix: camera.camera_uniform.vp_matrix().invert().unwrap(),
+			right: inverse_v_matrix.x.truncate().normalize(),
+			forward: -inverse_v_matrix.z.truncate().normalize(),
+			screen_size: (screen_size.0 as f32, screen_size.1 as f32),
+		}
+	}
+
+	fn gizmo_size(&self, location: Point3<f32>) -> f32 {
+		(location - self.eye).magnitude() * GIZMO_SCREEN_SIZE
+	}
+
+	// World space ray from the camera through this pixel
+	fn ray(&self, cursor: PhysicalPosition<f64>) -> (Point3<f32>, Vector3<f32>) {
+		let x = cursor.x as f32 / self.screen_size.0 * 2. - 1.;
+		let y = 1. - cursor.y as f32 / self.screen_size.1 * 2.;
+
+		let unproject = |depth: f32| {
+			let point = self.inverse_vp_matrix * Vector4::new(x, y, depth, 1.);
+			Point3::from_homogeneous(point)
+		};
+		let near = unproject(0.);
+		let far = unproject(1.);
+
+		(near, (far - near).normalize())
+	}
+
+	// Where the ray crosses the plane facing the camera through this point, which is what dragged handles slide along
+	fn drag_plane_intersection(&self, cursor: PhysicalPosition<f64>, plane_point: Point3<f32>) -> Option<Point3<f32>> {
+		let (origin, direction) = self.ray(cursor);
+		let denominator = direction.dot(self.forward);
+		if denominator.abs() < 1e-6 {
+			return None;
+		}
+
+		let distance = (plane_point - origin).dot(self.forward) / denominator;
+		(distance > 0.).then(|| origin + direction * distance)
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Handle {
+	Location,
+	Radius,
+}
+
+// Lets the lamp be clicked in the viewport to select it, then dragged to move it or have its radius dragged from the edge of its range
+#[derive(Debug)]
+pub struct LightGizmos {
+	cursor: PhysicalPosition<f64>,
+	// Set by a click until the next update handles it
+	clicked: bool,
+	button_held: bool,
+	selected: bool,
+	dragging: Option<Handle>,
+	// Keeps the lamp from jumping to the cursor when it's grabbed off center
+	grab_offset: Vector3<f32>,
+}
+
+impl Default for LightGizmos {
+	fn default() -> Self {
+		Self {
+			cursor: PhysicalPosition::new(0., 0.),
+			clicked: false,
+			button_held: false,
+			selected: false,
+			dragging: None,
+			grab_offset: Vector3::new(0., 0., 0.),
+		}
+	}
+}
+
+impl LightGizmos {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn process_window_event(&mut self, window_event: &WindowEvent) {
+		match window_event {
+			WindowEvent::CursorMoved { position, .. } => self.cursor = *position,
+			WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+				self.button_held = *state == ElementState::Pressed;
+				self.clicked |= self.button_held;
+			}
+			_ => {}
+		}
+	}
+
+	pub fn selected(&self) -> bool {
+		self.selected
+	}
+
+	// The camera shouldn't turn while the mouse is moving a handle
+	pub fn dragging(&self) -> bool {
+		self.dragging.is_some()
+	}
+
+	pub fn deselect(&mut self) {
+		self.clicked = false;
+		self.selected = false;
+		self.dragging = None;
+	}
+
+	// Picks a handle under a new click, then moves whichever handle is held to follow the cursor
+	pub fn update(&mut self, view: &GizmoView, location: &mut Point3<f32>, lamp: &mut Lamp) {
+		if self.clicked {
+			self.clicked = false;
+
+			let (origin, direction) = view.ray(self.cursor);
+			let size = view.gizmo_size(*location);
+			let radius_handle = *location + view.right * lamp.radius;
+
+			// The radius handle is only shown, and so only grabbable, while the lamp is selected
+			self.dragging = if self.selected && ray_hits_sphere(origin, direction, radius_handle, view.gizmo_size(radius_handle)) {
+				Some(Handle::Radius)
+			} else if ray_hits_sphere(origin, direction, *location, size) {
+				Some(Handle::Location)
+			} else {
+				None
+			};
+			// Clicking anywhere else lets go of the lamp
+			self.selected = self.dragging.is_some();
+
+			if let Some(point) = view.drag_plane_intersection(self.cursor, *location) {
+				self.grab_offset = *location - point;
+			}
+		}
+
+		if !self.button_held {
+			self.dragging = None;
+		}
+
+		if let (Some(handle), Some(point)) = (self.dragging, view.drag_plane_intersection(self.cursor, *location)) {
+			match handle {
+				Handle::Location => *location = point + self.grab_offset,
+				Handle::Radius => lamp.radius = (point - *location).magnitude().max(view.gizmo_size(*location)),
+			}
+		}
+	}
+
+	pub fn draw(&self, debug_draw: &mut DebugDraw, view: &GizmoView, location: Point3<f32>, lamp: &Lamp, sun: Option<&Sun>) {
+		let size = view.gizmo_size(location);
+		let icon_color = if self.selected { SELECTED_COLOR } else { display_color(lamp.color) };
+
+		// Billboard icon of a bulb with rays, always facing the camera
+		draw_icon(debug_draw, view, location, size, icon_color);
+
+		// Range sphere, with the outline facing the camera carrying the radius handle
+		let range_color = if self.selected { SELECTED_COLOR } else { RANGE_COLOR };
+		debug_draw.circle(location, view.forward, lamp.radius, range_color);
+		if self.selected {
+			debug_draw.sphere(location, lamp.radius, RANGE_COLOR);
+
+			let radius_handle = location + view.right * lamp.radius;
+			debug_draw.circle(radius_handle, view.forward, view.gizmo_size(radius_handle) * 0.5, SELECTED_COLOR);
+		}
+
+		// Spot cone out to where the fixture's beam falls to half its peak intensity
+		if let Some(profile) = &lamp.profile {
+			debug_draw.cone(location, lamp.direction, lamp.radius, profile.beam_half_angle(), CONE_COLOR);
+		}
+
+		// The sun has no location, so its icon floats above the origin with a line pointing along its light
+		if let Some(sun) = sun {
+			let sun_icon = Point3::new(0., 0., 0.) + sun.direction * 10.;
+			let sun_size = view.gizmo_size(sun_icon);
+			draw_icon(debug_draw, view, sun_icon, sun_size, display_color(sun.final_color()));
+			debug_draw.line(sun_icon, sun_icon - sun.direction * sun_size * 6., display_color(sun.final_color()));
+		}
+	}
+}
+
+fn draw_icon(debug_draw: &mut DebugDraw, view: &GizmoView, location: Point3<f32>, size: f32, color: [f32; 4]) {
+	debug_draw.circle(location, view.forward, size, color);
+
+	let (tangent, bitangent) = perpendicular_axes(view.forward);
+	for ray in 0..8 {
+		let angle = ray as f32 / 8. * std::f32::consts::TAU;
+		let direction = tangent * angle.cos() + bitangent * angle.sin();
+		debug_draw.line(location + direction * size * 1.3, location + direction * size * 1.8, color);
+	}
+}
+
+// Light colors are scaled to the brightest channel so a dim or deeply tinted light's gizmo stays visible
+fn display_color(color: [f32; 3]) -> [f32; 4] {
+	let brightest = color[0].max(color[1]).max(color[2]).max(f32::EPSILON);
+	[color[0] / brightest, color[1] / brightest, color[2] / brightest, 1.]
+}
+
+fn ray_hits_sphere(origin: Point3<f32>, direction: Vector3<f32>, center: Point3<f32>, radius: f32) -> bool {
+	let to_center = center - origin;
+	let along_ray = to_center.dot(direction);
+	along_ray > 0. && (to_center - direction * along_ray).magnitude2() <= radius * radius
+}
@@ -0,0 +1,122 @@
+use crate::audio_analysis::AudioAnalysisUniform;
+use crate::render_settings::DebugShading;
+
+use wgpu::util::DeviceExt;
+use wgpu::{BindGroup, BindGroupLayout};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GlobalsUniform {
+	pub screen_size: [f32; 2],
+	pub inv_screen_size: [f32; 2],
+	// Seconds since the engine started
+	pub time: f32,
+	pub delta_time: f32,
+	pub frame_index: u32,
+	// Multiplier applied to the scene's radiance before tone mapping, taken from the active camera
+	pub exposure: f32,
+	// Tweaked at runtime with the keyboard by the `DebugBuffer`
+	pub debug_values: [f32; 4],
+	// Which `DebugShading` the G-buffer pass shades with in place of the materials, or 0 for none
+	pub debug_shading: u32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: [u32; 3],
+}
+
+// Values shared by every shader, so none of them need to declare their own bindings for common frame data
+// Binding 0 holds the `GlobalsUniform` and binding 1 the `AudioAnalysisUniform`
+pub struct Globals {
+	pub globals_uniform: GlobalsUniform,
+	pub globals_buffer: wgpu::Buffer,
+	pub audio_buffer: wgpu::Buffer,
+	pub globals_bind_group_layout: BindGroupLayout,
+	pub globals_bind_group: BindGroup,
+	start_time: std::time::Instant,
+}
+
+impl Globals {
+	pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+		let globals_uniform = GlobalsUniform {
+			screen_size: [config.width as f32, config.height as f32],
+			inv_screen_size: [1. / config.width as f32, 1. / config.height as f32],
+			time: 0.,
+			delta_time: 0.,
+			frame_index: 0,
+			exposure: 1.,
+			debug_values: [0., 0., 0., 0.],
+			debug_shading: 0,
+			_padding: [0; 3],
+		};
+
+		let globals_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Globals Buffer"),
+			contents: bytemuck::cast_slice(&[globals_uniform]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
+
+		let audio_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Audio Analysis Buffer"),
+			contents: bytemuck::cast_slice(&[AudioAnalysisUniform::default()]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
+
+		let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+			binding,
+			visibility: wgpu::ShaderStages::VERTEX_FRAGMENT | wgpu::ShaderStages::COMPUTE,
+			ty: wgpu::BindingType::Buffer {
+				ty: wgpu::BufferBindingType::Uniform,
+				has_dynamic_offset: false,
+				min_binding_size: None,
+			},
+			count: None,
+		};
+		let globals_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			entries: &[uniform_entry(0), uniform_entry(1)],
+			label: Some("Globals bind group layout"),
+		});
+
+		let globals_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			layout: &globals_bind_group_layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: globals_buffer.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: audio_buffer.as_entire_binding(),
+				},
+			],
+			label: Some("Globals bind group"),
+		});
+
+		Self {
+			globals_uniform,
+			globals_buffer,
+			audio_buffer,
+			globals_bind_group_layout,
+			globals_bind_group,
+			start_time: std::time::Instant::now(),
+		}
+	}
+
+	pub fn update(&mut self, queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration, delta_time: std::time::Duration, exposure: f32, debug_values: [f32; 4], debug_shading: DebugShading) {
+		self.globals_uniform = GlobalsUniform {
+			screen_size: [config.width as f32, config.height as f32],
+			inv_screen_size: [1. / config.width as f32, 1. / config.height as f32],
+			time: self.start_time.elapsed().as_secs_f32(),
+			delta_time: delta_time.as_secs_f32(),
+			frame_index: self.globals_uniform.frame_index.wrapping_add(1),
+			exposure,
+			debug_values,
+			debug_shading: debug_shading as u32,
+			_padding: [0; 3],
+		};
+
+		queue.write_buffer(&self.globals_buffer, 0, bytemuck::cast_slice(&[self.globals_uniform]));
+	}
+
+	pub fn update_audio(&self, queue: &wgpu::Queue, audio: &AudioAnalysisUniform) {
+		queue.write_buffer(&self.audio_buffer, 0, bytemuck::cast_slice(&[*audio]));
+	}
+}
@@ -0,0 +1,72 @@
+use winit::event::{ElementState, VirtualKeyCode};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum CaptureState {
+	Idle,
+	SingleFrameRequested,
+	SingleFrame,
+	StartRequested,
+	// Captures every frame until toggled off, for bugs that take a few frames to show up like the voxel lightmap's incremental updates
+	MultipleFrames,
+	StopRequested,
+}
+
+// Frame captures go through wgpu to a graphics debugger attached to the process, such as RenderDoc, and do nothing when none is attached
+#[derive(Debug)]
+pub struct GpuCapture {
+	state: CaptureState,
+}
+
+impl Default for GpuCapture {
+	fn default() -> Self {
+		Self { state: CaptureState::Idle }
+	}
+}
+
+impl GpuCapture {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// F12 captures just the next frame, while F11 starts and stops a capture spanning every frame in between
+	pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
+		if state != ElementState::Pressed {
+			return false;
+		}
+
+		self.state = match (key, self.state) {
+			(VirtualKeyCode::F12, CaptureState::Idle) => CaptureState::SingleFrameRequested,
+			(VirtualKeyCode::F11, CaptureState::Idle) => CaptureState::StartRequested,
+			(VirtualKeyCode::F11, CaptureState::MultipleFrames) => CaptureState::StopRequested,
+			_ => return false,
+		};
+
+		true
+	}
+
+	// Called before the frame's updates so the buffer writes they queue are part of the capture
+	pub fn begin_frame(&mut self, device: &wgpu::Device) {
+		self.state = match self.state {
+			CaptureState::SingleFrameRequested => {
+				device.start_capture();
+				CaptureState::SingleFrame
+			}
+			CaptureState::StartRequested => {
+				device.start_capture();
+				CaptureState::MultipleFrames
+			}
+			state => state,
+		};
+	}
+
+	// Called once the frame has been submitted and presented
+	pub fn end_frame(&mut self, device: &wgpu::Device) {
+		self.state = match self.state {
+			CaptureState::SingleFrame | CaptureState::StopRequested => {
+				device.stop_capture();
+				CaptureState::Idle
+			}
+			state => state,
+		};
+	}
+}
@@ -0,0 +1,85 @@
+// Optional GPU capabilities the engine can take advantage of, each requested only if the adapter supports it
+// Every path that relies on one checks it here first and falls back to the way that works everywhere
+#[derive(Debug, Copy, Clone, Default)]
+pub struct GpuFeatures {
+	// Arrays of textures indexed freely in shaders, which would let materials share one bind group
+	// Nothing draws bindlessly yet, so every material keeps its own bind group either way
+	pub bindless_textures: bool,
+	// Many indirect draws in one call, used for the meshlets of a mesh instead of one draw each
+	pub multi_draw_indirect: bool,
+	// Rasterizes every pixel a triangle touches at all, used by voxelization so thin triangles don't slip between the voxels
+	pub conservative_rasterization: bool,
+	// Timestamps written by the GPU, used to measure its frame time alongside the CPU timings
+	pub timestamp_queries: bool,
+	// Triangles rasterized as only their edges or their corners, used by the wireframe and point debug rasterization
+	pub polygon_mode_line: bool,
+	pub polygon_mode_point: bool,
+}
+
+impl GpuFeatures {
+	fn bindless_texture_features() -> wgpu::Features {
+		wgpu::Features::TEXTURE_BINDING_ARRAY | wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING
+	}
+
+	pub fn from_adapter(adapter: &wgpu::Adapter) -> Self {
+		let supported = adapter.features();
+
+		Self {
+			bindless_textures: supported.contains(Self::bindless_texture_features()),
+			multi_draw_indirect: supported.contains(wgpu::Features::MULTI_DRAW_INDIRECT),
+			conservative_rasterization: supported.contains(wgpu::Features::CONSERVATIVE_RASTERIZATION),
+			timestamp_queries: supported.contains(wgpu::Features::TIMESTAMP_QUERY),
+			polygon_mode_line: supported.contains(wgpu::Features::POLYGON_MODE_LINE),
+			polygon_mode_point: supported.contains(wgpu::Features::POLYGON_MODE_POINT),
+		}
+	}
+
+	// The features to request from the device, which are exactly the supported ones the engine makes use of
+	pub fn wgpu_features(&self) -> wgpu::Features {
+		let optional_features = [
+			(self.bindless_textures, Self::bindless_texture_features()),
+			(self.multi_draw_indirect, wgpu::Features::MULTI_DRAW_INDIRECT),
+			(self.conservative_rasterization, wgpu::Features::CONSERVATIVE_RASTERIZATION),
+			(self.timestamp_queries, wgpu::Features::TIMESTAMP_QUERY),
+			(self.polygon_mode_line, wgpu::Features::POLYGON_MODE_LINE),
+			(self.polygon_mode_point, wgpu::Features::POLYGON_MODE_POINT),
+		];
+
+		optional_features
+			.iter()
+			.filter(|(enabled, _)| *enabled)
+			.fold(wgpu::Features::empty(), |features, (_, feature)| features | *feature)
+	}
+
+	// Kept for the `gpu` console command so bug reports can show which paths were taken and why
+	pub fn report(&self, adapter: &wgpu::Adapter) -> String {
+		let info = adapter.get_info();
+		let limits = adapter.limits();
+		let enabled = |enabled: bool| if enabled { "enabled" } else { "unsupported, using the fallback" };
+
+		format!(
+			"GPU: {} ({:?}, {:?}, vendor {:#06x}, device {:#06x})\n\
+			Limits: {:?}\n\
+			Features: {:?}\n\
+			Bindless textures: {}\n\
+			Multi-draw indirect: {}\n\
+			Conservative rasterization: {}\n\
+			Timestamp queries: {}\n\
+			Line polygon mode: {}\n\
+			Point polygon mode: {}",
+			info.name,
+			info.backend,
+			info.device_type,
+			info.vendor,
+			info.device,
+			limits,
+			adapter.features(),
+			enabled(self.bindless_textures),
+			enabled(self.multi_draw_indirect),
+			enabled(self.conservative_rasterization),
+			enabled(self.timestamp_queries),
+			enabled(self.polygon_mode_line),
+			enabled(self.polygon_mode_point),
+		)
+	}
+}
@@ -0,0 +1,135 @@
+use anyhow::{anyhow, bail, Result};
+
+// Each option can be given on the command line, like `--backend vulkan` or `--backend=vulkan`, or by its environment variable, with the command line taking precedence
+const OPTIONS: [(&str, &str); 4] = [
+	("--backend", "SEURAT_BACKEND"),
+	("--adapter", "SEURAT_ADAPTER"),
+	("--power-preference", "SEURAT_POWER_PREFERENCE"),
+	("--present-mode", "SEURAT_PRESENT_MODE"),
+];
+
+// Which GPU to render with, for machines with more than one like hybrid-GPU laptops
+// `--backend`: vulkan, dx12, dx11, metal, gl, or all to let wgpu pick
+// `--adapter`: the index of an adapter in the list printed by `--list-adapters`, which overrides the power preference
+// `--power-preference`: low for the integrated GPU or high for the discrete one
+// `--present-mode`: fifo for vsync, mailbox for vsync with lower latency, or immediate to allow tearing
+// `--list-adapters`: prints the adapters on the chosen backends and exits instead of starting the engine
+#[derive(Debug, Clone)]
+pub struct GpuSelection {
+	pub backends: wgpu::Backends,
+	pub adapter_index: Option<usize>,
+	pub power_preference: wgpu::PowerPreference,
+	pub present_mode: wgpu::PresentMode,
+	pub print_adapters: bool,
+}
+
+impl Default for GpuSelection {
+	fn default() -> Self {
+		Self {
+			backends: wgpu::Backends::all(),
+			adapter_index: None,
+			power_preference: wgpu::PowerPreference::default(),
+			present_mode: wgpu::PresentMode::Fifo,
+			print_adapters: false,
+		}
+	}
+}
+
+impl GpuSelection {
+	// Reads the environment variables first, then the command line arguments over them
+	pub fn from_environment() -> Result<Self> {
+		let mut selection = Self::default();
+
+		for (option, variable) in OPTIONS {
+			if let Ok(value) = std::env::var(variable) {
+				selection.set(option, &value).map_err(|error| anyhow!("{} (from {})", error, variable))?;
+			}
+		}
+
+		let mut arguments = std::env::args().skip(1);
+		while let Some(argument) = arguments.next() {
+			if argument == "--list-adapters" {
+				selection.print_adapters = true;
+				continue;
+			}
+
+			let (option, value) = match argument.split_once('=') {
+				Some((option, value)) => (String::from(option), String::from(value)),
+				None => {
+					let value = arguments.next().ok_or_else(|| anyhow!("The {} option needs a value", argument))?;
+					(argument, value)
+				}
+			};
+			selection.set(&option, &value)?;
+		}
+
+		Ok(selection)
+	}
+
+	fn set(&mut self, option: &str, value: &str) -> Result<()> {
+		match option {
+			"--backend" => {
+				self.backends = match value.to_lowercase().as_str() {
+					"vulkan" => wgpu::Backends::VULKAN,
+					"dx12" => wgpu::Backends::DX12,
+					"dx11" => wgpu::Backends::DX11,
+					"metal" => wgpu::Backends::METAL,
+					"gl" => wgpu::Backends::GL,
+					"all" => wgpu::Backends::all(),
+					_ => bail!("There's no backend named \"{}\", it can be vulkan, dx12, dx11, metal, gl, or all", value),
+				}
+			}
+			"--adapter" => self.adapter_index = Some(value.parse().map_err(|_| anyhow!("The adapter has to be given by its index, not \"{}\"", value))?),
+			"--power-preference" => {
+				self.power_preference = match value.to_lowercase().as_str() {
+					"low" => wgpu::PowerPreference::LowPower,
+					"high" => wgpu::PowerPreference::HighPerformance,
+					_ => bail!("There's no power preference named \"{}\", it can be low or high", value),
+				}
+			}
+			"--present-mode" => {
+				self.present_mode = match value.to_lowercase().as_str() {
+					"fifo" => wgpu::PresentMode::Fifo,
+					"mailbox" => wgpu::PresentMode::Mailbox,
+					"immediate" => wgpu::PresentMode::Immediate,
+					_ => bail!("There's no present mode named \"{}\", it can be fifo, mailbox, or immediate", value),
+				}
+			}
+			_ => bail!("Unknown option \"{}\", the options are {} and --list-adapters", option, OPTIONS.map(|(option, _)| option).join(", ")),
+		}
+
+		Ok(())
+	}
+
+	// Every adapter on the chosen backends, numbered the way `--adapter` picks them
+	pub fn list_adapters(&self, instance: &wgpu::Instance) -> String {
+		instance
+			.enumerate_adapters(self.backends)
+			.enumerate()
+			.map(|(index, adapter)| {
+				let info = adapter.get_info();
+				format!("Adapter {}: {} ({:?}, {:?})", index, info.name, info.backend, info.device_type)
+			})
+			.collect::<Vec<_>>()
+			.join("\n")
+	}
+
+	// The chosen adapter if there is one that can draw to the surface, otherwise the one wgpu picks for the power preference
+	pub async fn request_adapter(&self, instance: &wgpu::Instance, surface: &wgpu::Surface) -> Option<wgpu::Adapter> {
+		if let Some(index) = self.adapter_index {
+			match instance.enumerate_adapters(self.backends).nth(index) {
+				Some(adapter) if adapter.is_surface_supported(surface) => return Some(adapter),
+				Some(_) => eprintln!("Adapter {} can't draw to the window, so one is picked by the power preference instead", index),
+				None => eprintln!("There's no adapter {}, so one is picked by the power preference instead", index),
+			}
+		}
+
+		instance
+			.request_adapter(&wgpu::RequestAdapterOptions {
+				power_preference: self.power_preference,
+				compatible_surface: Some(surface),
+				force_fallback_adapter: false,
+			})
+			.await
+	}
+}
@@ -0,0 +1,176 @@
+use crate::texture::Texture;
+
+use std::borrow::Cow;
+
+const WORKGROUP_SIZE: u32 = 8;
+
+// A depth pyramid where each texel holds the farthest depth of the texels it covers in the level below, used to cheaply test if bounds are hidden behind already drawn geometry
+pub struct HiZPyramid {
+	pub texture: Texture,
+	depth_copy_pipeline: wgpu::ComputePipeline,
+	downsample_pipeline: wgpu::ComputePipeline,
+	// One per mip level, the first copying the depth buffer into the base level and the rest each reducing the level above them
+	// The depth buffer it's built from trades textures with the current one each frame, so the first level is copied through the bind group of the frame's parity
+	depth_copy_bind_groups: [wgpu::BindGroup; 2],
+	level_bind_groups: Vec<wgpu::BindGroup>,
+	level_sizes: Vec<(u32, u32)>,
+}
+
+impl HiZPyramid {
+	// Takes the texture the previous frame's depth is held in on even frames and the one it's held in on odd frames
+	pub fn new(device: &wgpu::Device, depth_textures: [&Texture; 2]) -> Self {
+		let size = wgpu::Extent3d {
+			width: depth_textures[0].size.width,
+			height: depth_textures[0].size.height,
+			depth_or_array_layers: 1,
+		};
+		let format = wgpu::TextureFormat::R32Float;
+		let mip_level_count = size.max_mips();
+
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("Hi-Z Pyramid"),
+			size,
+			mip_level_count,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format,
+			usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+		});
+		let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+		// Depth values can't be filtered together, so texels are only ever read exactly
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some("Hi-Z Pyramid Sampler"),
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			address_mode_w: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Nearest,
+			min_filter: wgpu::FilterMode::Nearest,
+			mipmap_filter: wgpu::FilterMode::Nearest,
+			..Default::default()
+		});
+
+		let storage_entry = wgpu::BindGroupLayoutEntry {
+			binding: 1,
+			visibility: wgpu::ShaderStages::COMPUTE,
+			ty: wgpu::BindingType::StorageTexture {
+				access: wgpu::StorageTextureAccess::WriteOnly,
+				format,
+				view_dimension: wgpu::TextureViewDimension::D2,
+			},
+			count: None,
+		};
+		let source_entry = |sample_type| wgpu::BindGroupLayoutEntry {
+			binding: 0,
+			visibility: wgpu::ShaderStages::COMPUTE,
+			ty: wgpu::BindingType::Texture {
+				multisampled: false,
+				view_dimension: wgpu::TextureViewDimension::D2,
+				sample_type,
+			},
+			count: None,
+		};
+
+		let depth_copy_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Hi-Z Depth Copy bind group layout"),
+			entries: &[source_entry(wgpu::TextureSampleType::Depth), storage_entry],
+		});
+		let downsample_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Hi-Z Downsample bind group layout"),
+			entries: &[source_entry(wgpu::TextureSampleType::Float { filterable: false }), storage_entry],
+		});
+
+		let depth_copy_pipeline = create_pipeline(device, &depth_copy_layout, "Hi-Z Depth Copy", include_str!("../assets/shaders/utility_hi_z_depth_copy.wgsl"));
+		let downsample_pipeline = create_pipeline(device, &downsample_layout, "Hi-Z Downsample", include_str!("../assets/shaders/utility_hi_z_downsample.wgsl"));
+
+		let level_views = (0..mip_level_count)
+			.map(|mip| {
+				texture.create_view(&wgpu::TextureViewDescriptor {
+					label: Some("Hi-Z Pyramid level"),
+					format: None,
+					dimension: None,
+					aspect: wgpu::TextureAspect::All,
+					base_mip_level: mip,
+					mip_level_count: std::num::NonZeroU32::new(1),
+					base_array_layer: 0,
+					array_layer_count: None,
+				})
+			})
+			.collect::<Vec<_>>();
+
+		let level_bind_group = |layout: &wgpu::BindGroupLayout, source: &wgpu::TextureView, level: usize| {
+			device.create_bind_group(&wgpu::BindGroupDescriptor {
+				label: Some("Hi-Z Pyramid level bind group"),
+				layout,
+				entries: &[
+					wgpu::BindGroupEntry {
+						binding: 0,
+						resource: wgpu::BindingResource::TextureView(source),
+					},
+					wgpu::BindGroupEntry {
+						binding: 1,
+						resource: wgpu::BindingResource::TextureView(&level_views[level]),
+					},
+				],
+			})
+		};
+		let depth_copy_bind_groups = depth_textures.map(|depth_texture| level_bind_group(&depth_copy_layout, &depth_texture.view, 0));
+		let level_bind_groups = (1..mip_level_count as usize)
+			.map(|level| level_bind_group(&downsample_layout, &level_views[level - 1], level))
+			.collect();
+
+		let level_sizes = (0..mip_level_count).map(|level| ((size.width >> level).max(1), (size.height >> level).max(1))).collect();
+
+		Self {
+			texture: Texture {
+				texture,
+				view,
+				sampler,
+				format,
+				size,
+				label: String::from("Hi-Z Pyramid"),
+			},
+			depth_copy_pipeline,
+			downsample_pipeline,
+			depth_copy_bind_groups,
+			level_bind_groups,
+			level_sizes,
+		}
+	}
+
+	// Records rebuilding every level from the depth texture that holds the previous frame's depth on frames of the given parity
+	pub fn generate(&self, encoder: &mut wgpu::CommandEncoder, parity: usize) {
+		let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+			label: Some("Compute: Generate Hi-Z Pyramid"),
+		});
+
+		let bind_groups = std::iter::once(&self.depth_copy_bind_groups[parity]).chain(&self.level_bind_groups);
+		for (level, (bind_group, (width, height))) in bind_groups.zip(&self.level_sizes).enumerate() {
+			let pipeline = if level == 0 { &self.depth_copy_pipeline } else { &self.downsample_pipeline };
+
+			compute_pass.set_pipeline(pipeline);
+			compute_pass.set_bind_group(0, bind_group, &[]);
+			compute_pass.dispatch(width.div_ceil(WORKGROUP_SIZE), height.div_ceil(WORKGROUP_SIZE), 1);
+		}
+	}
+}
+
+fn create_pipeline(device: &wgpu::Device, bind_group_layout: &wgpu::BindGroupLayout, label: &str, source: &'static str) -> wgpu::ComputePipeline {
+	let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+		label: Some(label),
+		source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+	});
+
+	let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+		label: Some(label),
+		bind_group_layouts: &[bind_group_layout],
+		push_constant_ranges: &[],
+	});
+
+	device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+		label: Some(label),
+		layout: Some(&pipeline_layout),
+		module: &shader,
+		entry_point: "main",
+	})
+}
@@ -0,0 +1,157 @@
+use anyhow::{anyhow, bail, Result};
+use half::f16;
+use std::path::Path;
+
+// Resolution of the baked profile texture, over the 180° of vertical angles and (for asymmetric fixtures) the 360° of horizontal angles
+pub const PROFILE_TEXTURE_WIDTH: u32 = 64;
+pub const PROFILE_TEXTURE_HEIGHT: u32 = 32;
+
+// Photometric distribution of a real light fixture, measured by its manufacturer and shared in the IESNA LM-63 file format
+// Only type C photometry is supported, which is what architectural fixtures use: vertical angles run from 0° straight down the fixture's aim to 180° straight up, and horizontal angles around that axis
+#[derive(Debug, Clone)]
+pub struct IesProfile {
+	pub vertical_angles: Vec<f32>,
+	pub horizontal_angles: Vec<f32>,
+	// Candela for every vertical angle, listed once per horizontal angle, already scaled by the file's multiplier
+	pub candela: Vec<Vec<f32>>,
+}
+
+impl IesProfile {
+	pub fn load(directory: &Path, file: &str) -> Result<Self> {
+		let path = directory.join("lights").join(file);
+		let text = std::fs::read_to_string(path)?;
+
+		Self::parse(&text)
+	}
+
+	pub fn parse(text: &str) -> Result<Self> {
+		// Everything before the TILT line is free form keywords describing the fixture
+		let mut lines = text.lines();
+		let tilt = lines
+			.find_map(|line| line.trim().strip_prefix("TILT="))
+			.ok_or_else(|| anyhow!("IES file is missing its TILT line"))?
+			.trim()
+			.to_string();
+
+		let mut values = lines.flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ',')).filter(|value| !value.is_empty());
+		let mut next = || -> Result<f32> {
+			let value = values.next().ok_or_else(|| anyhow!("IES file ended early"))?;
+			value.parse::<f32>().map_err(|_| anyhow!("IES file has a malformed number \"{}\"", value))
+		};
+
+		// Tilt data only describes how the lamp's output changes when the fixture is mounted at an angle, which doesn't affect the distribution
+		if tilt == "INCLUDE" {
+			let _lamp_to_luminaire_geometry = next()?;
+			let tilt_angle_count = next()? as usize;
+			for _ in 0..tilt_angle_count * 2 {
+				next()?;
+			}
+		}
+
+		let _lamp_count = next()?;
+		let _lumens_per_lamp = next()?;
+		let multiplier = next()?;
+		let vertical_angle_count = next()? as usize;
+		let horizontal_angle_count = next()? as usize;
+		let photometric_type = next()? as u32;
+		// Units type, luminous opening width, length, and height, ballast factor, a reserved value, and input watts
+		for _ in 0..7 {
+			next()?;
+		}
+
+		if photometric_type != 1 {
+			bail!("IES file uses type {} photometry, but only type C is supported", if photometric_type == 2 { "B" } else { "A" });
+		}
+		if vertical_angle_count == 0 || horizontal_angle_count == 0 {
+			bail!("IES file has no candela values");
+		}
+
+		let vertical_angles = (0..vertical_angle_count).map(|_| next()).collect::<Result<Vec<_>>>()?;
+		let horizontal_angles = (0..horizontal_angle_count).map(|_| next()).collect::<Result<Vec<_>>>()?;
+		let candela = (0..horizontal_angle_count)
+			.map(|_| (0..vertical_angle_count).map(|_| next().map(|value| value * multiplier)).collect::<Result<Vec<_>>>())
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(Self {
+			vertical_angles,
+			horizontal_angles,
+			candela,
+		})
+	}
+
+	pub fn peak_candela(&self) -> f32 {
+		self.candela.iter().flatten().fold(0., |peak, &value| peak.max(value))
+	}
+
+	// Widest angle in degrees from the aim where the intensity is still at least half its peak, the conventional edge of a fixture's beam
+	pub fn beam_half_angle(&self) -> f32 {
+		let half_peak = self.peak_candela() * 0.5;
+		(0..=180).map(|angle| angle as f32).rfind(|&angle| self.sample(angle, 0.) >= half_peak).unwrap_or_default()
+	}
+
+	// Fixtures that only list one horizontal angle emit the same in every direction around their axis, so a 1D profile captures them
+	pub fn rotationally_symmetric(&self) -> bool {
+		self.horizontal_angles.len() == 1
+	}
+
+	// Interpolated candela in this direction, with both angles in degrees
+	pub fn sample(&self, vertical_angle: f32, horizontal_angle: f32) -> f32 {
+		// The last horizontal angle says which symmetry the file relies on to only list part of the full circle
+		let last_horizontal_angle = self.horizontal_angles.last().copied().unwrap_or_default();
+		let horizontal_angle = horizontal_angle.rem_euclid(360.);
+		let horizontal_angle = match last_horizontal_angle as u32 {
+			0 => 0.,
+			// Symmetric in each quadrant
+			90 => 90. - (horizontal_angle % 180. - 90.).abs(),
+			// Symmetric about the 0° to 180° plane
+			180 => 180. - (horizontal_angle - 180.).abs(),
+			_ => horizontal_angle,
+		};
+
+		let (horizontal_index, horizontal_blend) = interpolation_index(&self.horizontal_angles, horizontal_angle);
+		let sample_vertical = |candela: &[f32]| {
+			// Directions outside the measured vertical range emit nothing, such as above a downlight
+			if vertical_angle < self.vertical_angles[0] || vertical_angle > *self.vertical_angles.last().unwrap() {
+				return 0.;
+			}
+
+			let (index, blend) = interpolation_index(&self.vertical_angles, vertical_angle);
+			let next_index = (index + 1).min(candela.len() - 1);
+			candela[index] + (candela[next_index] - candela[index]) * blend
+		};
+
+		let next_horizontal_index = (horizontal_index + 1).min(self.candela.len() - 1);
+		let current = sample_vertical(&self.candela[horizontal_index]);
+		let next = sample_vertical(&self.candela[next_horizontal_index]);
+		current + (next - current) * horizontal_blend
+	}
+
+	// Normalized by the peak candela so the profile only shapes the light and its uniform intensity sets the brightness
+	// The texture's U spans the vertical angles from 0° to 180°, and V the horizontal angles around the full circle, collapsing to a single row when the fixture is rotationally symmetric
+	pub fn bake_texture_data(&self) -> (Vec<[f16; 4]>, (u32, u32)) {
+		let dimensions = (PROFILE_TEXTURE_WIDTH, if self.rotationally_symmetric() { 1 } else { PROFILE_TEXTURE_HEIGHT });
+		let peak_candela = self.peak_candela().max(f32::EPSILON);
+
+		let texels = (0..dimensions.1)
+			.flat_map(|y| (0..dimensions.0).map(move |x| (x, y)))
+			.map(|(x, y)| {
+				let vertical_angle = x as f32 / (dimensions.0 - 1) as f32 * 180.;
+				let horizontal_angle = y as f32 / dimensions.1 as f32 * 360.;
+				let value = f16::from_f32(self.sample(vertical_angle, horizontal_angle) / peak_candela);
+
+				[value, value, value, f16::ONE]
+			})
+			.collect();
+
+		(texels, dimensions)
+	}
+}
+
+// Index of the last angle at or below this one, and how far it is toward the next angle
+fn interpolation_index(angles: &[f32], angle: f32) -> (usize, f32) {
+	let index = angles.iter().rposition(|&candidate| candidate <= angle).unwrap_or(0);
+	match angles.get(index + 1) {
+		Some(&next_angle) if next_angle > angles[index] => (index, ((angle - angles[index]) / (next_angle - angles[index])).clamp(0., 1.)),
+		_ => (index, 0.),
+	}
+}
@@ -0,0 +1,244 @@
+use crate::camera::OPENGL_TO_WGPU_MATRIX;
+use crate::context::Context;
+use crate::frame_texture::FrameTexture;
+use crate::mesh::{Mesh, VertexFormat};
+use crate::texture::Texture;
+
+use cgmath::{EuclideanSpace, Matrix4, Point3, Vector3};
+use std::borrow::Cow;
+use wgpu::util::DeviceExt;
+
+// The atlas is a grid of views around the model, with a column per yaw angle and a row per pitch angle from level up to `IMPOSTOR_MAX_PITCH`
+pub const IMPOSTOR_YAW_STEPS: u32 = 8;
+pub const IMPOSTOR_PITCH_STEPS: u32 = 3;
+pub const IMPOSTOR_MAX_PITCH: cgmath::Deg<f32> = cgmath::Deg(60.);
+const IMPOSTOR_CELL_RESOLUTION: u32 = 128;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ImpostorUniform {
+	pub bounds_center: [f32; 3],
+	pub bounds_radius: f32,
+	pub yaw_steps: f32,
+	pub pitch_steps: f32,
+	// In radians
+	pub max_pitch: f32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	pub _padding: f32,
+}
+
+// The G-buffer contents of a model baked from every angle in the grid, drawn on a camera facing billboard in place of the model once it's far enough away
+pub struct ImpostorAtlas {
+	pub normal: Texture,
+	pub albedo: Texture,
+	pub arm: Texture,
+	pub uniform: ImpostorUniform,
+}
+
+impl ImpostorAtlas {
+	// Renders each view with an orthographic camera fit to the mesh's bounding sphere into its own cell of the atlases, ignoring morph targets
+	pub fn bake(context: &Context, mesh: &Mesh, albedo_map: &Texture, arm_map: &Texture) -> Self {
+		let device = &context.device;
+		let dimensions = (IMPOSTOR_YAW_STEPS * IMPOSTOR_CELL_RESOLUTION, IMPOSTOR_PITCH_STEPS * IMPOSTOR_CELL_RESOLUTION);
+
+		let atlas = |label: &str, format| Texture::from_dimensions(device, dimensions, &format!("{} {}", mesh.name, label), format, wgpu::AddressMode::ClampToEdge);
+		let mut normal = atlas("Impostor Normal Atlas", wgpu::TextureFormat::Rgba16Float);
+		let mut albedo = atlas("Impostor Albedo Atlas", wgpu::TextureFormat::Rgba8UnormSrgb);
+		let mut arm = atlas("Impostor ARM Atlas", wgpu::TextureFormat::Rgba8Unorm);
+		let depth = FrameTexture::with_size(device, dimensions, wgpu::TextureFormat::Depth32Float, "Impostor Bake Z-buffer", None);
+
+		let view_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Impostor Bake View bind group layout"),
+			entries: &[wgpu::BindGroupLayoutEntry {
+				binding: 0,
+				visibility: wgpu::ShaderStages::VERTEX,
+				ty: wgpu::BindingType::Buffer {
+					ty: wgpu::BufferBindingType::Uniform,
+					has_dynamic_offset: false,
+					min_binding_size: None,
+				},
+				count: None,
+			}],
+		});
+		let texture_entries = (0..2)
+			.flat_map(|index| {
+				[
+					wgpu::BindGroupLayoutEntry {
+						binding: index * 2,
+						visibility: wgpu::ShaderStages::FRAGMENT,
+						ty: wgpu::BindingType::Texture {
+							multisampled: false,
+							view_dimension: wgpu::TextureViewDimension::D2,
+							sample_type: wgpu::TextureSampleType::Float { filterable: true },
+						},
+						count: None,
+					},
+					wgpu::BindGroupLayoutEntry {
+						binding: index * 2 + 1,
+						visibility: wgpu::ShaderStages::FRAGMENT,
+						ty: wgpu::BindingType::Sampler { comparison: false, filtering: true },
+						count: None,
+					},
+				]
+			})
+			.collect::<Vec<_>>();
+		let textures_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Impostor Bake Textures bind group layout"),
+			entries: texture_entries.as_slice(),
+		});
+		let textures_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Impostor Bake Textures bind group"),
+			layout: &textures_layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: wgpu::BindingResource::TextureView(&albedo_map.view),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: wgpu::BindingResource::Sampler(&albedo_map.sampler),
+				},
+				wgpu::BindGroupEntry {
+					binding: 2,
+					resource: wgpu::BindingResource::TextureView(&arm_map.view),
+				},
+				wgpu::BindGroupEntry {
+					binding: 3,
+					resource: wgpu::BindingResource::Sampler(&arm_map.sampler),
+				},
+			],
+		});
+
+		let pipeline = create_pipeline(device, &[&view_layout, &textures_layout], [normal.format, albedo.format, arm.format]);
+
+		// Each view looks at the bounding sphere's center from far enough away that the whole sphere fits between the near and far planes
+		let center = Vector3::from(mesh.bounds_center);
+		let radius = mesh.bounds_radius;
+		let projection = OPENGL_TO_WGPU_MATRIX * cgmath::ortho(-radius, radius, -radius, radius, radius, radius * 3.);
+		let view_bind_groups = (0..IMPOSTOR_PITCH_STEPS)
+			.flat_map(|row| (0..IMPOSTOR_YAW_STEPS).map(move |column| (column, row)))
+			.map(|(column, row)| {
+				let direction = view_direction(column, row);
+				let eye = Point3::from_vec(center + direction * radius * 2.);
+				let vp_matrix: [[f32; 4]; 4] = (projection * Matrix4::look_at_rh(eye, Point3::from_vec(center), Vector3::unit_y())).into();
+
+				let view_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+					label: Some("Impostor Bake View Buffer"),
+					contents: bytemuck::cast_slice(&[vp_matrix]),
+					usage: wgpu::BufferUsages::UNIFORM,
+				});
+				let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+					label: Some("Impostor Bake View bind group"),
+					layout: &view_layout,
+					entries: &[wgpu::BindGroupEntry {
+						binding: 0,
+						resource: view_buffer.as_entire_binding(),
+					}],
+				});
+
+				((column, row), bind_group)
+			})
+			.collect::<Vec<_>>();
+
+		let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Impostor Bake Encoder") });
+		{
+			// Texels left uncovered keep zero alpha, which the billboard discards
+			let color_attachments = [&normal, &albedo, &arm].map(|texture| wgpu::RenderPassColorAttachment {
+				view: &texture.view,
+				resolve_target: None,
+				ops: wgpu::Operations {
+					load: wgpu::LoadOp::Clear(wgpu::Color { r: 0., g: 0., b: 0., a: 0. }),
+					store: true,
+				},
+			});
+			let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: Some("Bake: Impostor Atlas"),
+				color_attachments: &color_attachments,
+				depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+					view: &depth.texture.view,
+					depth_ops: Some(wgpu::Operations {
+						load: wgpu::LoadOp::Clear(1.0),
+						store: true,
+					}),
+					stencil_ops: None,
+				}),
+			});
+
+			render_pass.set_pipeline(&pipeline);
+			render_pass.set_vertex_buffer(0, mesh.vertex_buffer(VertexFormat::Model).slice(..));
+			render_pass.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
+			render_pass.set_bind_group(1, &textures_bind_group, &[]);
+
+			// The viewport confines each view to its cell, since the orthographic projection fits the bounding sphere exactly
+			for ((column, row), bind_group) in &view_bind_groups {
+				let cell = IMPOSTOR_CELL_RESOLUTION as f32;
+				render_pass.set_viewport(*column as f32 * cell, *row as f32 * cell, cell, cell, 0., 1.);
+				render_pass.set_bind_group(0, bind_group, &[]);
+				render_pass.draw_indexed(0..mesh.index_count, 0, 0..1);
+			}
+		}
+		context.queue.submit(std::iter::once(encoder.finish()));
+
+		normal.generate_mipmaps(context);
+		albedo.generate_mipmaps(context);
+		arm.generate_mipmaps(context);
+
+		let uniform = ImpostorUniform {
+			bounds_center: mesh.bounds_center,
+			bounds_radius: mesh.bounds_radius,
+			yaw_steps: IMPOSTOR_YAW_STEPS as f32,
+			pitch_steps: IMPOSTOR_PITCH_STEPS as f32,
+			max_pitch: cgmath::Rad::from(IMPOSTOR_MAX_PITCH).0,
+			_padding: 0.,
+		};
+
+		Self { normal, albedo, arm, uniform }
+	}
+}
+
+// Unit vector from the model toward the camera of a cell, which the billboard shader inverts to pick the cell closest to the current view
+fn view_direction(column: u32, row: u32) -> Vector3<f32> {
+	let yaw = std::f32::consts::TAU * column as f32 / IMPOSTOR_YAW_STEPS as f32;
+	let pitch = cgmath::Rad::from(IMPOSTOR_MAX_PITCH).0 * row as f32 / (IMPOSTOR_PITCH_STEPS - 1) as f32;
+
+	Vector3::new(pitch.cos() * yaw.cos(), pitch.sin(), pitch.cos() * yaw.sin())
+}
+
+fn create_pipeline(device: &wgpu::Device, bind_group_layouts: &[&wgpu::BindGroupLayout], formats: [wgpu::TextureFormat; 3]) -> wgpu::RenderPipeline {
+	let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+		label: Some("Impostor Bake shader module"),
+		source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../assets/shaders/utility_impostor_bake.wgsl"))),
+	});
+	let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+		label: Some("Impostor Bake pipeline layout"),
+		bind_group_layouts,
+		push_constant_ranges: &[],
+	});
+
+	device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+		label: Some("Impostor Bake pipeline"),
+		layout: Some(&layout),
+		vertex: wgpu::VertexState {
+			module: &shader,
+			entry_point: "main",
+			buffers: &[VertexFormat::Model.layout()],
+		},
+		fragment: Some(wgpu::FragmentState {
+			module: &shader,
+			entry_point: "main",
+			targets: &formats.map(wgpu::ColorTargetState::from),
+		}),
+		primitive: wgpu::PrimitiveState {
+			cull_mode: Some(wgpu::Face::Back),
+			..Default::default()
+		},
+		depth_stencil: Some(wgpu::DepthStencilState {
+			format: wgpu::TextureFormat::Depth32Float,
+			depth_write_enabled: true,
+			depth_compare: wgpu::CompareFunction::Less,
+			stencil: wgpu::StencilState::default(),
+			bias: wgpu::DepthBiasState::default(),
+		}),
+		multisample: wgpu::MultisampleState::default(),
+	})
+}
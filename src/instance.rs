@@ -1,9 +1,34 @@
+use crate::morph::MAX_MORPH_TARGETS;
+use crate::transform::Transform;
+
+use cgmath::{ElementWise, EuclideanSpace};
+use std::sync::atomic::{AtomicU64, Ordering};
 use wgpu::{util::DeviceExt, Device};
 
+// Index of the frame being prepared, which stamps when each model's instance buffer was last written
+// Set once at the start of each frame's update by the engine, so models don't need the frame passed down through the entity tree
+static FRAME_INDEX: AtomicU64 = AtomicU64::new(0);
+
+pub fn set_frame_index(frame_index: u64) {
+	FRAME_INDEX.store(frame_index, Ordering::Relaxed);
+}
+
 #[derive(Debug)]
 pub struct Instances {
+	// Placed within the parent transform, so use `world_instances` for where they are in the world
 	pub instance_list: Vec<Instance>,
 	pub instances_buffer: Option<wgpu::Buffer>,
+	// World transform of the parent of the entity the model is on, kept up to date by `Entity::update_world_transforms_of_descendants`
+	pub parent_transform: Transform,
+	// Written into every instance's data for the G-buffer pass to pass on to the shading pass, see `Model::set_receives_shadows`
+	pub receives_shadows: bool,
+	// From 0 for invisible to 1 for fully drawn, which the G-buffer pass dithers the model's pixels away by while it fades in after streaming
+	pub fade: f32,
+	// Model matrices last written to the buffer, and those the frame before it was drawn with, which the G-buffer pass takes each instance's motion from
+	models: Vec<[[f32; 4]; 4]>,
+	previous_models: Vec<[[f32; 4]; 4]>,
+	// Frame the matrices were last written in, so writing them again the same frame doesn't lose the previous frame's
+	written_frame: u64,
 }
 
 impl Instances {
@@ -13,20 +38,77 @@ impl Instances {
 		Self {
 			instance_list: vec![origin],
 			instances_buffer: None,
+			parent_transform: Transform::default(),
+			receives_shadows: true,
+			fade: 1.,
+			models: Vec::new(),
+			previous_models: Vec::new(),
+			written_frame: 0,
 		}
 	}
 
+	// Each instance carried into world space by the parent transform
+	pub fn world_instances(&self) -> impl Iterator<Item = Instance> + '_ {
+		self.instance_list.iter().map(|instance| instance.within(&self.parent_transform))
+	}
+
+	// Only rebuilds the buffer when the parent has moved, or it hasn't been built yet, since this is called for every model each frame
+	// A model that moved is written once more the frame after it stops, so its previous matrices catch up and it no longer reads as moving
+	pub fn set_parent_transform(&mut self, parent_transform: &Transform, device: &Device) {
+		let settled = self.models == self.previous_models || self.written_frame == FRAME_INDEX.load(Ordering::Relaxed);
+		if self.parent_transform == *parent_transform && self.instances_buffer.is_some() && settled {
+			return;
+		}
+
+		self.parent_transform = *parent_transform;
+		self.update_buffer(device);
+	}
+
 	pub fn transform_single_instance(&mut self, location: cgmath::Point3<f64>, rotation: cgmath::Quaternion<f64>, scale: cgmath::Point3<f64>, device: &Device) {
 		let location = cgmath::Vector3::new(location.x as f32, location.y as f32, location.z as f32);
 		let rotation = cgmath::Quaternion::new(rotation.s as f32, rotation.v.x as f32, rotation.v.y as f32, rotation.v.z as f32);
 		let scale = cgmath::Vector3::new(scale.x as f32, scale.y as f32, scale.z as f32);
 
-		self.instance_list = vec![Instance { location, rotation, scale }];
+		let morph_weights = self.instance_list.first().map(|instance| instance.morph_weights).unwrap_or_default();
+
+		self.instance_list = vec![Instance {
+			location,
+			rotation,
+			scale,
+			morph_weights,
+		}];
+		self.update_buffer(device);
+	}
+
+	pub fn set_morph_weights(&mut self, weights: &[f32], device: &Device) {
+		for instance in &mut self.instance_list {
+			instance.set_morph_weights(weights);
+		}
+		self.update_buffer(device);
+	}
+
+	pub fn set_fade(&mut self, fade: f32, device: &Device) {
+		self.fade = fade;
 		self.update_buffer(device);
 	}
 
 	pub fn update_buffer(&mut self, device: &Device) {
-		let instance_data = self.instance_list.iter().map(Instance::to_raw).collect::<Vec<_>>();
+		let mut instance_data = self.world_instances().map(|instance| instance.to_raw(self.receives_shadows, self.fade)).collect::<Vec<_>>();
+
+		// The matrices written in an earlier frame are the ones the last frame was drawn with, but not those already rewritten earlier in this one
+		let frame_index = FRAME_INDEX.load(Ordering::Relaxed);
+		if self.written_frame != frame_index {
+			self.previous_models = std::mem::take(&mut self.models);
+			self.written_frame = frame_index;
+		}
+		self.models = instance_data.iter().map(|instance| instance.model).collect();
+
+		// Instances that were added or removed since have no previous matrices to pair with, so they're all drawn as if standing still
+		if self.previous_models.len() == instance_data.len() {
+			for (instance, previous_model) in instance_data.iter_mut().zip(&self.previous_models) {
+				instance.previous_model = *previous_model;
+			}
+		}
 
 		let instances_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
 			label: Some("Instance Buffer"),
@@ -36,6 +118,16 @@ impl Instances {
 
 		self.instances_buffer = Some(instances_buffer);
 	}
+
+	// Destroys the buffer, which is built again by the next `update_buffer` if the instances are ever drawn again
+	pub fn release_buffer(&mut self) {
+		if let Some(buffer) = self.instances_buffer.take() {
+			buffer.destroy();
+		}
+		// It may be drawn somewhere else entirely by then, which shouldn't smear across the screen as motion
+		self.models.clear();
+		self.previous_models.clear();
+	}
 }
 
 impl Default for Instances {
@@ -49,6 +141,7 @@ pub struct Instance {
 	pub location: cgmath::Vector3<f32>,
 	pub rotation: cgmath::Quaternion<f32>,
 	pub scale: cgmath::Vector3<f32>,
+	pub morph_weights: [f32; MAX_MORPH_TARGETS],
 }
 
 impl Instance {
@@ -57,12 +150,42 @@ impl Instance {
 			location: cgmath::Vector3::new(0., 0., 0.),
 			rotation: cgmath::Quaternion::new(1., 0., 0., 0.),
 			scale: cgmath::Vector3::new(1., 1., 1.),
+			morph_weights: [0.; MAX_MORPH_TARGETS],
+		}
+	}
+
+	pub fn set_morph_weights(&mut self, weights: &[f32]) {
+		self.morph_weights = [0.; MAX_MORPH_TARGETS];
+		for (weight, new_weight) in self.morph_weights.iter_mut().zip(weights) {
+			*weight = *new_weight;
 		}
 	}
 
-	pub fn to_raw(&self) -> InstanceRaw {
+	// The instance placed within a parent transform, the same way as `Transform::within`
+	pub fn within(&self, parent: &Transform) -> Self {
+		let parent_location = parent.location.cast::<f32>().unwrap();
+		let parent_rotation = parent.rotation.cast::<f32>().unwrap();
+		let parent_scale = parent.scale.cast::<f32>().unwrap();
+
+		Self {
+			location: parent_location.to_vec() + parent_rotation * parent_scale.mul_element_wise(self.location),
+			rotation: parent_rotation * self.rotation,
+			scale: parent_scale.mul_element_wise(self.scale),
+			morph_weights: self.morph_weights,
+		}
+	}
+
+	// The previous model matrix starts out the same as the current one, for `Instances::update_buffer` to replace if the instance was drawn last frame
+	pub fn to_raw(&self, receives_shadows: bool, fade: f32) -> InstanceRaw {
+		let model = (cgmath::Matrix4::from_translation(self.location) * cgmath::Matrix4::from(self.rotation) * cgmath::Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)).into();
+
 		InstanceRaw {
-			model: (cgmath::Matrix4::from_translation(self.location) * cgmath::Matrix4::from(self.rotation) * cgmath::Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)).into(),
+			model,
+			morph_weights: self.morph_weights,
+			receives_shadows: if receives_shadows { 1. } else { 0. },
+			fade,
+			_padding: [0.; 2],
+			previous_model: model,
 		}
 	}
 }
@@ -77,6 +200,14 @@ impl Default for Instance {
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct InstanceRaw {
 	model: [[f32; 4]; 4],
+	morph_weights: [f32; MAX_MORPH_TARGETS],
+	// 1 if the shadow maps darken the model, or 0 to leave it lit as if nothing stood in the way
+	receives_shadows: f32,
+	fade: f32,
+	// Keeps the previous model matrix aligned to 16 bytes, as the scatter culling shader's storage buffer struct lays it out
+	_padding: [f32; 2],
+	// Where the instance was drawn the frame before, for the G-buffer pass to tell its own motion apart from the camera's
+	previous_model: [[f32; 4]; 4],
 }
 
 impl InstanceRaw {
@@ -117,6 +248,54 @@ impl InstanceRaw {
 					shader_location: 7,
 					format: wgpu::VertexFormat::Float32x4,
 				},
+				// morph target weights (1/2)
+				wgpu::VertexAttribute {
+					offset: std::mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
+					shader_location: 8,
+					format: wgpu::VertexFormat::Float32x4,
+				},
+				// morph target weights (2/2)
+				wgpu::VertexAttribute {
+					offset: std::mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+					shader_location: 9,
+					format: wgpu::VertexFormat::Float32x4,
+				},
+				// shadow receiving
+				wgpu::VertexAttribute {
+					offset: std::mem::size_of::<[f32; 24]>() as wgpu::BufferAddress,
+					shader_location: 10,
+					format: wgpu::VertexFormat::Float32,
+				},
+				// streaming fade
+				wgpu::VertexAttribute {
+					offset: std::mem::size_of::<[f32; 25]>() as wgpu::BufferAddress,
+					shader_location: 11,
+					format: wgpu::VertexFormat::Float32,
+				},
+				// previous model matrix (1/4)
+				wgpu::VertexAttribute {
+					offset: std::mem::size_of::<[f32; 28]>() as wgpu::BufferAddress,
+					shader_location: 12,
+					format: wgpu::VertexFormat::Float32x4,
+				},
+				// previous model matrix (2/4)
+				wgpu::VertexAttribute {
+					offset: std::mem::size_of::<[f32; 32]>() as wgpu::BufferAddress,
+					shader_location: 13,
+					format: wgpu::VertexFormat::Float32x4,
+				},
+				// previous model matrix (3/4)
+				wgpu::VertexAttribute {
+					offset: std::mem::size_of::<[f32; 36]>() as wgpu::BufferAddress,
+					shader_location: 14,
+					format: wgpu::VertexFormat::Float32x4,
+				},
+				// previous model matrix (4/4)
+				wgpu::VertexAttribute {
+					offset: std::mem::size_of::<[f32; 40]>() as wgpu::BufferAddress,
+					shader_location: 15,
+					format: wgpu::VertexFormat::Float32x4,
+				},
 			],
 		}
 	}
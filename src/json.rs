@@ -0,0 +1,299 @@
+use anyhow::{anyhow, bail, Result};
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+
+// Just enough of JSON to read the motion track, timeline, and scene assets and to talk to debugging tools, since nothing else in the engine needs a full parser
+#[derive(Debug, Clone)]
+pub enum JsonValue {
+	Null,
+	Number(f64),
+	Bool(bool),
+	String(String),
+	Array(Vec<JsonValue>),
+	Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+	pub fn object<'a>(entries: impl IntoIterator<Item = (&'a str, JsonValue)>) -> Self {
+		JsonValue::Object(entries.into_iter().map(|(key, value)| (String::from(key), value)).collect())
+	}
+
+	pub fn get(&self, key: &str) -> Option<&JsonValue> {
+		match self {
+			JsonValue::Object(entries) => entries.iter().find(|(entry_key, _)| entry_key == key).map(|(_, value)| value),
+			_ => None,
+		}
+	}
+
+	pub fn as_number(&self) -> Option<f64> {
+		match self {
+			JsonValue::Number(number) => Some(*number),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			JsonValue::String(string) => Some(string),
+			_ => None,
+		}
+	}
+
+	pub fn as_array(&self) -> Option<&[JsonValue]> {
+		match self {
+			JsonValue::Array(values) => Some(values),
+			_ => None,
+		}
+	}
+
+	pub fn as_numbers<const N: usize>(&self) -> Option<[f64; N]> {
+		match self {
+			JsonValue::Array(values) if values.len() == N => {
+				let mut numbers = [0.; N];
+				for (number, value) in numbers.iter_mut().zip(values) {
+					*number = value.as_number()?;
+				}
+				Some(numbers)
+			}
+			_ => None,
+		}
+	}
+}
+
+// Written compactly on one line
+impl Display for JsonValue {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			JsonValue::Null => write!(f, "null"),
+			// JSON has no infinities or NaN
+			JsonValue::Number(number) if !number.is_finite() => write!(f, "null"),
+			JsonValue::Number(number) => write!(f, "{}", number),
+			JsonValue::Bool(value) => write!(f, "{}", value),
+			JsonValue::String(string) => write_string(f, string),
+			JsonValue::Array(values) => {
+				write!(f, "[")?;
+				for (index, value) in values.iter().enumerate() {
+					if index > 0 {
+						write!(f, ",")?;
+					}
+					write!(f, "{}", value)?;
+				}
+				write!(f, "]")
+			}
+			JsonValue::Object(entries) => {
+				write!(f, "{{")?;
+				for (index, (key, value)) in entries.iter().enumerate() {
+					if index > 0 {
+						write!(f, ",")?;
+					}
+					write_string(f, key)?;
+					write!(f, ":{}", value)?;
+				}
+				write!(f, "}}")
+			}
+		}
+	}
+}
+
+fn write_string(f: &mut Formatter<'_>, string: &str) -> fmt::Result {
+	write!(f, "\"")?;
+	for character in string.chars() {
+		match character {
+			'"' => write!(f, "\\\"")?,
+			'\\' => write!(f, "\\\\")?,
+			'\n' => write!(f, "\\n")?,
+			'\r' => write!(f, "\\r")?,
+			'\t' => write!(f, "\\t")?,
+			_ if character.is_control() => write!(f, "\\u{:04x}", character as u32)?,
+			_ => write!(f, "{}", character)?,
+		}
+	}
+	write!(f, "\"")
+}
+
+pub struct JsonParser<'a> {
+	text: &'a str,
+	position: usize,
+}
+
+impl<'a> JsonParser<'a> {
+	pub fn new(text: &'a str) -> Self {
+		Self { text, position: 0 }
+	}
+
+	pub fn parse_document(&mut self) -> Result<JsonValue> {
+		let value = self.parse_value()?;
+		self.skip_whitespace();
+		if self.position < self.text.len() {
+			bail!("JSON has unexpected text after its end at byte {}", self.position);
+		}
+		Ok(value)
+	}
+
+	// The bytes of the named value in the document's top-level object, if it has one, so just that value can be rewritten without reformatting the rest of the file
+	pub fn find_top_level_value(&mut self, key: &str) -> Result<Option<Range<usize>>> {
+		self.expect('{')?;
+
+		self.skip_whitespace();
+		if self.peek() == Some('}') {
+			return Ok(None);
+		}
+
+		loop {
+			self.skip_whitespace();
+			let entry_key = self.parse_string()?;
+			self.expect(':')?;
+
+			self.skip_whitespace();
+			let start = self.position;
+			self.parse_value()?;
+			if entry_key == key {
+				return Ok(Some(start..self.position));
+			}
+
+			self.skip_whitespace();
+			match self.peek() {
+				Some(',') => self.position += 1,
+				Some('}') => return Ok(None),
+				_ => bail!("JSON expected ',' or '}}' at byte {}", self.position),
+			}
+		}
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.text[self.position..].chars().next()
+	}
+
+	fn skip_whitespace(&mut self) {
+		while self.peek().is_some_and(char::is_whitespace) {
+			self.position += 1;
+		}
+	}
+
+	fn expect(&mut self, expected: char) -> Result<()> {
+		self.skip_whitespace();
+		match self.peek() {
+			Some(found) if found == expected => {
+				self.position += found.len_utf8();
+				Ok(())
+			}
+			_ => bail!("JSON expected '{}' at byte {}", expected, self.position),
+		}
+	}
+
+	fn parse_value(&mut self) -> Result<JsonValue> {
+		self.skip_whitespace();
+		let rest = &self.text[self.position..];
+
+		match self.peek() {
+			Some('{') => self.parse_object(),
+			Some('[') => self.parse_array(),
+			Some('"') => Ok(JsonValue::String(self.parse_string()?)),
+			Some('-' | '0'..='9') => {
+				let length = rest.find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))).unwrap_or(rest.len());
+				let number = rest[..length].parse::<f64>().map_err(|_| anyhow!("JSON has a malformed number \"{}\"", &rest[..length]))?;
+				self.position += length;
+				Ok(JsonValue::Number(number))
+			}
+			_ => {
+				let (value, length) = if rest.starts_with("true") {
+					(JsonValue::Bool(true), 4)
+				} else if rest.starts_with("false") {
+					(JsonValue::Bool(false), 5)
+				} else if rest.starts_with("null") {
+					(JsonValue::Null, 4)
+				} else {
+					bail!("JSON has an unexpected value at byte {}", self.position);
+				};
+				self.position += length;
+				Ok(value)
+			}
+		}
+	}
+
+	fn parse_array(&mut self) -> Result<JsonValue> {
+		self.expect('[')?;
+		let mut values = vec![];
+
+		self.skip_whitespace();
+		if self.peek() == Some(']') {
+			self.position += 1;
+			return Ok(JsonValue::Array(values));
+		}
+
+		loop {
+			values.push(self.parse_value()?);
+			self.skip_whitespace();
+			match self.peek() {
+				Some(',') => self.position += 1,
+				Some(']') => {
+					self.position += 1;
+					return Ok(JsonValue::Array(values));
+				}
+				_ => bail!("JSON expected ',' or ']' at byte {}", self.position),
+			}
+		}
+	}
+
+	fn parse_object(&mut self) -> Result<JsonValue> {
+		self.expect('{')?;
+		let mut entries = vec![];
+
+		self.skip_whitespace();
+		if self.peek() == Some('}') {
+			self.position += 1;
+			return Ok(JsonValue::Object(entries));
+		}
+
+		loop {
+			self.skip_whitespace();
+			let key = self.parse_string()?;
+			self.expect(':')?;
+			entries.push((key, self.parse_value()?));
+
+			self.skip_whitespace();
+			match self.peek() {
+				Some(',') => self.position += 1,
+				Some('}') => {
+					self.position += 1;
+					return Ok(JsonValue::Object(entries));
+				}
+				_ => bail!("JSON expected ',' or '}}' at byte {}", self.position),
+			}
+		}
+	}
+
+	fn parse_string(&mut self) -> Result<String> {
+		self.expect('"')?;
+		let mut string = String::new();
+
+		let mut characters = self.text[self.position..].char_indices();
+		while let Some((offset, character)) = characters.next() {
+			match character {
+				'"' => {
+					self.position += offset + 1;
+					return Ok(string);
+				}
+				'\\' => {
+					let escaped = match characters.next().map(|(_, escaped)| escaped) {
+						Some('n') => '\n',
+						Some('t') => '\t',
+						Some('r') => '\r',
+						Some('b') => '\u{8}',
+						Some('f') => '\u{c}',
+						Some('u') => {
+							let hex = characters.by_ref().take(4).map(|(_, digit)| digit).collect::<String>();
+							u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+						}
+						Some(other) => other,
+						None => break,
+					};
+					string.push(escaped);
+				}
+				_ => string.push(character),
+			}
+		}
+
+		bail!("JSON has an unterminated string")
+	}
+}
@@ -1,6 +1,78 @@
+use cgmath::{InnerSpace, Vector3};
+use half::f16;
+use std::sync::Arc;
 use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout};
 
 use crate::context::Context;
+use crate::entity::Entity;
+use crate::ies::IesProfile;
+use crate::texture::Texture;
+
+// Dim gray skylight, used until something like the time of day sets the sky's color
+pub const DEFAULT_SKY_AMBIENT: [f32; 3] = [0.05, 0.05, 0.05];
+// Lights past this many in the scene are left unlit by the shading pass, which keeps their storage buffer a fixed size
+pub const MAX_PUNCTUAL_LIGHTS: usize = 64;
+
+// Whether the hemisphere light only stands in for the voxel GI while it's off, or is always added as a base level of ambient light
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AmbientMode {
+	Fallback,
+	Additive,
+}
+
+impl AmbientMode {
+	pub const ALL: [Self; 2] = [Self::Fallback, Self::Additive];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Fallback => "fallback",
+			Self::Additive => "additive",
+		}
+	}
+}
+
+// Ambient light from the sky above and the ground below, blended by which way each surface faces, so a scene without GI isn't left flat in the shadows
+#[derive(Debug, Copy, Clone)]
+pub struct HemisphereLight {
+	// Linear RGB, scaled by the intensity
+	pub sky_color: [f32; 3],
+	pub ground_color: [f32; 3],
+	// In the same units as the sky ambient
+	pub intensity: f32,
+	pub mode: AmbientMode,
+}
+
+impl Default for HemisphereLight {
+	// A pale blue sky over a warm gray ground
+	fn default() -> Self {
+		Self {
+			sky_color: [0.6, 0.75, 1.],
+			ground_color: [0.35, 0.3, 0.25],
+			intensity: 0.05,
+			mode: AmbientMode::Fallback,
+		}
+	}
+}
+
+impl HemisphereLight {
+	// As the console's ambient command shows it
+	pub fn description(&self) -> String {
+		let [sky_red, sky_green, sky_blue] = self.sky_color;
+		let [ground_red, ground_green, ground_blue] = self.ground_color;
+
+		format!(
+			"ambient sky {} {} {}, ground {} {} {}, intensity {}, mode {}",
+			sky_red,
+			sky_green,
+			sky_blue,
+			ground_red,
+			ground_green,
+			ground_blue,
+			self.intensity,
+			self.mode.name()
+		)
+	}
+}
 
 #[derive(Debug)]
 pub enum Light {
@@ -8,19 +80,306 @@ pub enum Light {
 	Sun(Sun),
 }
 
-#[derive(Debug)]
-pub struct Lamp {}
+// Point light radiating equally in every direction, unless it's given the measured profile of a real fixture
+#[derive(Debug, Clone)]
+pub struct Lamp {
+	// Linear RGB, kept near unit brightness so the intensity alone decides how bright the light is
+	pub color: [f32; 3],
+	// Total luminous flux, as printed on a light bulb's packaging
+	pub lumens: f32,
+	// Distance at which the falloff is smoothly windowed down to zero, so the light can be culled beyond it
+	pub radius: f32,
+	// Turns the lamp into a spotlight shaped like the fixture, whose candela values then set the brightness instead of the lumens
+	pub profile: Option<Arc<IesProfile>>,
+	// Unit vector the profile's 0° vertical angle points along, straight down for ceiling fixtures
+	pub direction: Vector3<f32>,
+	// Casts soft shadows through the distance field while the render settings trace them, best turned off for a lamp inside a closed mesh that would shadow all of its light
+	pub distance_field_shadows: bool,
+}
 
-#[derive(Debug)]
-pub struct Sun {}
+impl Default for Lamp {
+	fn default() -> Self {
+		Self {
+			color: [1., 1., 1.],
+			lumens: 60.,
+			radius: 20.,
+			profile: None,
+			direction: -Vector3::unit_y(),
+			distance_field_shadows: true,
+		}
+	}
+}
+
+impl Lamp {
+	pub fn new(lumens: f32, radius: f32) -> Self {
+		Self { lumens, radius, ..Self::default() }
+	}
+
+	pub fn with_temperature(mut self, kelvin: f32) -> Self {
+		self.color = color_temperature_to_rgb(kelvin);
+		self
+	}
+
+	pub fn with_profile(mut self, profile: Arc<IesProfile>, direction: Vector3<f32>) -> Self {
+		self.profile = Some(profile);
+		self.direction = direction.normalize();
+		self
+	}
+
+	// Luminous intensity, since an isotropic point light spreads its flux over the 4π steradians of the full sphere
+	pub fn candela(&self) -> f32 {
+		self.lumens / (4. * std::f32::consts::PI)
+	}
+
+	pub fn set_candela(&mut self, candela: f32) {
+		self.lumens = candela * 4. * std::f32::consts::PI;
+	}
+}
+
+// How the edges of a sun's cascaded shadows are softened, listed from the cheapest to the costliest
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ShadowFilter {
+	// A fixed 3x3 grid of filtered depth comparisons, evenly soft everywhere
+	Pcf,
+	// Exponential shadow maps, which blur an exponential of each depth so wide soft edges cost one lookup, but leak light near where occluders touch the ground
+	Exponential,
+	// Variance shadow maps, which blur each depth and its square to estimate how much is occluded, smooth but bleeding light where occluders overlap
+	Variance,
+	// Percentage-closer soft shadows, which search for the occluders first and widen the comparisons by how far behind them the surface is, so shadows harden toward their contact points
+	Pcss,
+}
+
+// Directional light infinitely far away, so it reaches everything from the same direction without falling off
+#[derive(Debug, Copy, Clone)]
+pub struct Sun {
+	// Unit vector pointing from the scene toward the sun
+	pub direction: Vector3<f32>,
+	// Linear RGB above the atmosphere, ignored when the color is derived from the atmosphere instead
+	pub color: [f32; 3],
+	// Lux arriving on a surface facing the sun, around 100000 for real sunlight at noon, or above the atmosphere when the color is derived from it
+	pub illuminance: f32,
+	// Half the angle the disk spans in the sky, which widens its specular highlight on smooth surfaces
+	pub angular_radius: f32,
+	// Tints the color by how much of it survives the path through the atmosphere, reddening it toward the horizon
+	pub atmospheric_color: bool,
+	// Casts soft shadows through the distance field while the render settings trace them
+	pub distance_field_shadows: bool,
+	// Applied to the cascaded shadows when this is the sun that casts them
+	pub shadow_filter: ShadowFilter,
+}
+
+impl Sun {
+	pub fn new(direction: Vector3<f32>, illuminance: f32) -> Self {
+		Self {
+			direction: direction.normalize(),
+			color: [1., 1., 1.],
+			illuminance,
+			// The real sun's disk is about half a degree across
+			angular_radius: 0.00465,
+			atmospheric_color: false,
+			distance_field_shadows: true,
+			shadow_filter: ShadowFilter::Pcf,
+		}
+	}
+
+	pub fn with_atmospheric_color(mut self) -> Self {
+		self.atmospheric_color = true;
+		self
+	}
+
+	// Points a sun at this elevation above the horizon and azimuth clockwise from -Z, both in degrees
+	pub fn set_sky_position(&mut self, elevation: f32, azimuth: f32) {
+		let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+		self.direction = Vector3::new(elevation.cos() * azimuth.sin(), elevation.sin(), -elevation.cos() * azimuth.cos());
+	}
+
+	pub fn elevation(&self) -> f32 {
+		self.direction.y.clamp(-1., 1.).asin().to_degrees()
+	}
+
+	pub fn final_color(&self) -> [f32; 3] {
+		if self.atmospheric_color {
+			atmospheric_transmittance(self.elevation())
+		} else {
+			self.color
+		}
+	}
+}
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct LightUniform {
 	pub location: [f32; 3],
+	// Candela, packed into the space after the location since uniforms pad a `vec3` to 16 bytes (4 floats)
+	pub intensity: f32,
+	pub color: [f32; 3],
+	pub radius: f32,
+	pub sun_direction: [f32; 3],
+	// Zero when the scene has no sun
+	pub sun_illuminance: f32,
+	pub sun_color: [f32; 3],
+	pub sun_angular_radius: f32,
+	pub lamp_direction: [f32; 3],
+	// Whether the lamp's intensity is shaped by the profile texture bound next to this uniform
+	pub lamp_has_profile: u32,
+	// Whether each light casts shadows traced through the distance field
+	pub lamp_distance_field_shadows: u32,
+	pub sun_distance_field_shadows: u32,
+	// How many of the punctual lights in the storage buffer bound next to this uniform are in use
+	pub punctual_light_count: u32,
 	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
-	pub _padding: u32,
+	_padding: u32,
+	// Light arriving evenly from the whole sky rather than from the sun's disk, lighting every surface alike
+	pub sky_ambient: [f32; 3],
+	_sky_padding: u32,
+	// The hemisphere light's colors already scaled by its intensity, or black while it has nothing to fill in for
+	pub hemisphere_sky_color: [f32; 3],
+	_hemisphere_sky_padding: u32,
+	pub hemisphere_ground_color: [f32; 3],
+	_hemisphere_ground_padding: u32,
+}
+
+impl LightUniform {
+	pub fn new(location: [f32; 3], lamp: &Lamp, sun: Option<&Sun>, sky_ambient: [f32; 3]) -> Self {
+		let (sun_direction, sun_illuminance, sun_color, sun_angular_radius) = match sun {
+			Some(sun) => (sun.direction.into(), sun.illuminance, sun.final_color(), sun.angular_radius),
+			None => ([0., 1., 0.], 0., [0., 0., 0.], 0.),
+		};
+
+		Self {
+			location,
+			intensity: lamp.profile.as_ref().map_or_else(|| lamp.candela(), |profile| profile.peak_candela()),
+			color: lamp.color,
+			radius: lamp.radius,
+			sun_direction,
+			sun_illuminance,
+			sun_color,
+			sun_angular_radius,
+			lamp_direction: lamp.direction.into(),
+			lamp_has_profile: lamp.profile.is_some() as u32,
+			lamp_distance_field_shadows: lamp.distance_field_shadows as u32,
+			sun_distance_field_shadows: matches!(sun, Some(sun) if sun.distance_field_shadows) as u32,
+			punctual_light_count: 0,
+			_padding: 0,
+			sky_ambient,
+			_sky_padding: 0,
+			hemisphere_sky_color: [0.; 3],
+			_hemisphere_sky_padding: 0,
+			hemisphere_ground_color: [0.; 3],
+			_hemisphere_ground_padding: 0,
+		}
+	}
+
+	// A fallback hemisphere light stays dark while the voxel GI provides the ambient light instead
+	pub fn set_hemisphere(&mut self, hemisphere: &HemisphereLight, global_illumination: bool) {
+		let intensity = match hemisphere.mode {
+			AmbientMode::Fallback if global_illumination => 0.,
+			AmbientMode::Fallback | AmbientMode::Additive => hemisphere.intensity,
+		};
+
+		self.hemisphere_sky_color = hemisphere.sky_color.map(|channel| channel * intensity);
+		self.hemisphere_ground_color = hemisphere.ground_color.map(|channel| channel * intensity);
+	}
+}
+
+// One light of the scene as the PBR shading pass iterates over them, whether it's a lamp or a sun
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PunctualLight {
+	// Ignored for a sun, which is infinitely far away
+	pub position: [f32; 3],
+	// Candela for a lamp or lux for a sun
+	pub intensity: f32,
 	pub color: [f32; 3],
+	// Distance where a lamp's falloff reaches zero, or the angle a sun's disk spans in radians
+	pub radius: f32,
+	// Aim of a lamp's profile, or the unit vector pointing toward a sun
+	pub direction: [f32; 3],
+	// 0 for a lamp and 1 for a sun
+	pub kind: u32,
+	// Whether the lamp's intensity is shaped by the profile texture, which only the lamp it was baked from can be
+	pub has_profile: u32,
+	pub distance_field_shadows: u32,
+	// Whether the sun's shadows are looked up in the cascaded shadow maps, which only follow the first sun in the scene
+	pub cascaded_shadows: u32,
+	// Whether the lamp's shadows are looked up in the point shadow map, which only follows the first lamp in the scene
+	pub point_shadows: u32,
+}
+
+impl PunctualLight {
+	pub fn new(light: &Light, position: [f32; 3], has_profile: bool) -> Self {
+		match light {
+			Light::Lamp(lamp) => Self {
+				position,
+				intensity: lamp.profile.as_ref().map_or_else(|| lamp.candela(), |profile| profile.peak_candela()),
+				color: lamp.color,
+				radius: lamp.radius,
+				direction: lamp.direction.into(),
+				kind: 0,
+				has_profile: has_profile as u32,
+				distance_field_shadows: lamp.distance_field_shadows as u32,
+				cascaded_shadows: 0,
+				point_shadows: 0,
+			},
+			Light::Sun(sun) => Self {
+				position,
+				intensity: sun.illuminance,
+				color: sun.final_color(),
+				radius: sun.angular_radius,
+				direction: sun.direction.into(),
+				kind: 1,
+				has_profile: 0,
+				distance_field_shadows: sun.distance_field_shadows as u32,
+				cascaded_shadows: 0,
+				point_shadows: 0,
+			},
+		}
+	}
+}
+
+// Fraction of each color channel of sunlight that reaches the ground without being scattered away, for a sun at this elevation in degrees
+// Rayleigh scattering by air molecules removes blue the fastest and Mie scattering by aerosols dims every channel alike, both over a longer path nearer the horizon
+pub fn atmospheric_transmittance(elevation: f32) -> [f32; 3] {
+	// Sea level scattering coefficients per meter and the heights over which each kind of particle thins out by a factor of e
+	const RAYLEIGH_SCATTERING: [f32; 3] = [5.8e-6, 13.5e-6, 33.1e-6];
+	const RAYLEIGH_SCALE_HEIGHT: f32 = 8000.;
+	const MIE_SCATTERING: f32 = 21e-6;
+	const MIE_SCALE_HEIGHT: f32 = 1200.;
+
+	if elevation < 0. {
+		return [0., 0., 0.];
+	}
+
+	// Relative length of the path through the atmosphere compared to looking straight up (Kasten and Young 1989)
+	let elevation = elevation.min(90.);
+	let air_mass = 1. / (elevation.to_radians().sin() + 0.50572 * (elevation + 6.07995).powf(-1.6364));
+
+	RAYLEIGH_SCATTERING.map(|rayleigh| (-(rayleigh * RAYLEIGH_SCALE_HEIGHT + MIE_SCATTERING * MIE_SCALE_HEIGHT) * air_mass).exp())
+}
+
+// Approximates the linear RGB color of a black body at this temperature, from 1000 K (candle flame) through 6500 K (daylight) to 40000 K (clear blue sky)
+// Fitted to the CIE 1964 color matching functions by Tanner Helland, then normalized so the brightest channel is 1
+pub fn color_temperature_to_rgb(kelvin: f32) -> [f32; 3] {
+	let temperature = kelvin.clamp(1000., 40000.) / 100.;
+
+	let red = if temperature <= 66. { 255. } else { 329.69873 * (temperature - 60.).powf(-0.13320476) };
+	let green = if temperature <= 66. {
+		99.4708 * temperature.ln() - 161.11957
+	} else {
+		288.12216 * (temperature - 60.).powf(-0.075514846)
+	};
+	let blue = if temperature >= 66. {
+		255.
+	} else if temperature <= 19. {
+		0.
+	} else {
+		138.51773 * (temperature - 10.).ln() - 305.0448
+	};
+
+	// The fit produces sRGB values, which are linearized like the albedo textures are in the shaders
+	let [red, green, blue] = [red, green, blue].map(|channel| (channel.clamp(0., 255.) / 255.).powf(2.2));
+	let brightest = red.max(green).max(blue);
+	[red / brightest, green / brightest, blue / brightest]
 }
 
 pub struct SceneLighting {
@@ -28,15 +387,17 @@ pub struct SceneLighting {
 	pub light_buffer: wgpu::Buffer,
 	pub light_bind_group_layout: BindGroupLayout,
 	pub light_bind_group: BindGroup,
+	// Every lamp and sun in the scene, gathered again each frame
+	pub punctual_light_buffer: wgpu::Buffer,
+	// Baked from the lamp's IES profile, or a single white texel while it has none
+	pub light_profile: Texture,
+	// The profile the texture was baked from, so it's only rebuilt when the lamp switches to another one
+	light_profile_source: Option<Arc<IesProfile>>,
 }
 
 impl SceneLighting {
 	pub fn new(context: &Context) -> Self {
-		let light_uniform = LightUniform {
-			location: [2.0, 2.0, 2.0],
-			_padding: 0,
-			color: [25.0, 25.0, 25.0],
-		};
+		let light_uniform = LightUniform::new([2.0, 2.0, 2.0], &Lamp::default(), None, DEFAULT_SKY_AMBIENT);
 
 		// We'll want to update our lights location, so we use COPY_DST
 		let light_buffer = context.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -45,34 +406,163 @@ impl SceneLighting {
 			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
 		});
 
+		let punctual_light_buffer = context.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Punctual Light Buffer"),
+			contents: bytemuck::cast_slice(&[<PunctualLight as bytemuck::Zeroable>::zeroed(); MAX_PUNCTUAL_LIGHTS]),
+			usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+		});
+
 		let light_bind_group_layout = context.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-			entries: &[wgpu::BindGroupLayoutEntry {
-				binding: 0,
-				visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-				ty: wgpu::BindingType::Buffer {
-					ty: wgpu::BufferBindingType::Uniform,
-					has_dynamic_offset: false,
-					min_binding_size: None,
+			entries: &[
+				wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 1,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						multisampled: false,
+						view_dimension: wgpu::TextureViewDimension::D2,
+						sample_type: wgpu::TextureSampleType::Float { filterable: true },
+					},
+					count: None,
 				},
-				count: None,
-			}],
+				wgpu::BindGroupLayoutEntry {
+					binding: 2,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Sampler { comparison: false, filtering: true },
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 3,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Storage { read_only: true },
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
+			],
 			label: None,
 		});
 
-		let light_bind_group = context.device.create_bind_group(&wgpu::BindGroupDescriptor {
-			layout: &light_bind_group_layout,
-			entries: &[wgpu::BindGroupEntry {
-				binding: 0,
-				resource: light_buffer.as_entire_binding(),
-			}],
-			label: None,
-		});
+		let light_profile = profile_texture(context, None);
+		let light_bind_group = create_light_bind_group(context, &light_bind_group_layout, &light_buffer, &punctual_light_buffer, &light_profile);
 
 		Self {
 			light_uniform,
 			light_buffer,
 			light_bind_group_layout,
 			light_bind_group,
+			punctual_light_buffer,
+			light_profile,
+			light_profile_source: None,
 		}
 	}
+
+	// Rebakes the profile texture when the lamp's profile has changed since the last call
+	pub fn set_profile(&mut self, context: &Context, profile: Option<&Arc<IesProfile>>) {
+		let unchanged = match (profile, &self.light_profile_source) {
+			(Some(profile), Some(source)) => Arc::ptr_eq(profile, source),
+			(None, None) => true,
+			_ => false,
+		};
+		if unchanged {
+			return;
+		}
+
+		self.light_profile = profile_texture(context, profile.map(|profile| profile.as_ref()));
+		self.light_bind_group = create_light_bind_group(context, &self.light_bind_group_layout, &self.light_buffer, &self.punctual_light_buffer, &self.light_profile);
+		self.light_profile_source = profile.cloned();
+	}
+
+	// Gathers every light component of the enabled entities into the storage buffer and counts them in the uniform, which still has to be written afterward
+	// Lamps are dimmed by the scale the time of day gives them, the same as the one lamp in the uniform
+	pub fn update_punctual_lights(&mut self, queue: &wgpu::Queue, root: &Entity, lamp_scale: f32) {
+		let mut punctual_lights = vec![];
+		let mut found_sun = false;
+		let mut found_lamp = false;
+		for entity in root.iter().filter(|entity| entity.enabled) {
+			let location = entity.world_transform.location;
+			let position = [location.x as f32, location.y as f32, location.z as f32];
+
+			for light in entity.get_lights() {
+				let has_profile = match (light, &self.light_profile_source) {
+					(Light::Lamp(Lamp { profile: Some(profile), .. }), Some(source)) => Arc::ptr_eq(profile, source),
+					_ => false,
+				};
+
+				let mut punctual_light = PunctualLight::new(light, position, has_profile);
+				match light {
+					Light::Lamp(_) => {
+						punctual_light.intensity *= lamp_scale;
+						punctual_light.point_shadows = !found_lamp as u32;
+						found_lamp = true;
+					}
+					Light::Sun(_) => {
+						punctual_light.cascaded_shadows = !found_sun as u32;
+						found_sun = true;
+					}
+				}
+				punctual_lights.push(punctual_light);
+			}
+		}
+
+		punctual_lights.truncate(MAX_PUNCTUAL_LIGHTS);
+		if !punctual_lights.is_empty() {
+			queue.write_buffer(&self.punctual_light_buffer, 0, bytemuck::cast_slice(punctual_lights.as_slice()));
+		}
+		self.light_uniform.punctual_light_count = punctual_lights.len() as u32;
+	}
+}
+
+fn profile_texture(context: &Context, profile: Option<&IesProfile>) -> Texture {
+	let (texels, dimensions) = match profile {
+		Some(profile) => profile.bake_texture_data(),
+		None => (vec![[f16::ONE; 4]], (1, 1)),
+	};
+
+	// Vertical angles past either end clamp to the nearest measurement, and only the first mip is written so shaders must sample level 0
+	Texture::from_f16_array(
+		&context.device,
+		&context.queue,
+		texels.as_slice(),
+		dimensions,
+		"Light profile",
+		wgpu::TextureFormat::Rgba16Float,
+		wgpu::AddressMode::ClampToEdge,
+	)
+}
+
+fn create_light_bind_group(context: &Context, layout: &BindGroupLayout, light_buffer: &wgpu::Buffer, punctual_light_buffer: &wgpu::Buffer, light_profile: &Texture) -> BindGroup {
+	context.device.create_bind_group(&wgpu::BindGroupDescriptor {
+		layout,
+		entries: &[
+			wgpu::BindGroupEntry {
+				binding: 0,
+				resource: light_buffer.as_entire_binding(),
+			},
+			wgpu::BindGroupEntry {
+				binding: 1,
+				resource: wgpu::BindingResource::TextureView(&light_profile.view),
+			},
+			wgpu::BindGroupEntry {
+				binding: 2,
+				resource: wgpu::BindingResource::Sampler(&light_profile.sampler),
+			},
+			wgpu::BindGroupEntry {
+				binding: 3,
+				resource: punctual_light_buffer.as_entire_binding(),
+			},
+		],
+		label: None,
+	})
 }
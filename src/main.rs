@@ -1,27 +1,83 @@
+mod asset_loader;
+mod audio_analysis;
+mod auto_exposure;
 mod behavior;
+mod binary_cache;
 mod camera;
 mod camera_controller;
+mod character_controller;
+mod collider;
+mod collision;
 mod component;
+mod console;
 mod context;
+mod crash_report;
+mod csg;
 mod debug_buffer;
+mod debug_draw;
+#[cfg(feature = "debug-server")]
+mod debug_server;
+mod debug_view;
+mod distance_field;
+mod draw_list;
 mod engine;
 mod entity;
+mod environment;
+mod final_composite;
+mod follow_camera_controller;
+mod foveation;
+mod frame_stats;
 mod frame_texture;
+mod frustum;
+mod gizmo;
+mod globals;
+mod gpu_capture;
+mod gpu_features;
+mod gpu_selection;
+mod hi_z;
+mod ies;
+mod impostor;
 mod instance;
+mod json;
 mod light;
 mod material;
+mod material_file;
 mod mesh;
+mod meshlet;
+mod mirror;
 mod model;
+mod morph;
+mod motion_track;
+mod noise;
 mod pass;
+mod portal;
+mod render_graph;
+mod render_layer;
+mod render_settings;
+mod resource_map;
+mod scatter;
 mod scene;
+mod scene_hooks;
 mod scripts;
 mod shader;
+mod shadow_map;
+mod spline;
 mod ssao;
+mod stereo;
+mod temporal_anti_aliasing;
 mod texture;
+mod time_of_day;
+mod timeline;
 mod transform;
+mod transparency;
+mod traversal_benchmark;
 mod voxel_texture;
+mod voxel_update;
+mod weather;
+mod world_streaming;
 
 use crate::engine::Engine;
+use crate::gpu_selection::GpuSelection;
 
 use winit::{
 	dpi::PhysicalSize,
@@ -34,16 +90,32 @@ fn main() {
 	// Enable logging
 	env_logger::init();
 
+	// Panics leave a crash report behind to diagnose them from after the fact
+	crash_report::install_panic_hook();
+
 	// Root directory to load assets from
 	let assets_path = std::path::Path::new(env!("OUT_DIR")).join("assets");
 
+	// Which GPU to render with, as chosen on the command line or in the environment
+	let gpu_selection = match GpuSelection::from_environment() {
+		Ok(gpu_selection) => gpu_selection,
+		Err(error) => {
+			eprintln!("{}", error);
+			std::process::exit(1);
+		}
+	};
+	if gpu_selection.print_adapters {
+		println!("{}", gpu_selection.list_adapters(&wgpu::Instance::new(gpu_selection.backends)));
+		return;
+	}
+
 	// Initialize the window
 	let event_loop = EventLoop::new();
 	let window = WindowBuilder::new().with_inner_size(PhysicalSize::new(1920, 1080)).with_title("Seurat").build(&event_loop).unwrap();
 
 	// Initialize the engine
-	let mut engine = pollster::block_on(Engine::new(&window));
-	engine.load(&assets_path);
+	let mut engine = pollster::block_on(Engine::new(&window, &gpu_selection));
+	crash_report::report_crashes(&mut engine, |engine| engine.load(&assets_path));
 
 	// Handle events, simulate, and draw frames repeatedly until the program is closed
 	event_loop.run(move |event, _, control_flow| {
@@ -53,11 +125,11 @@ fn main() {
 		// Process events and frame draw requests
 		match event {
 			// Handle user input from a human input device (mouse, keyboard, etc.)
-			Event::DeviceEvent { ref event, .. } => engine.process_input(event),
+			Event::DeviceEvent { ref event, .. } => crash_report::report_crashes(&mut engine, |engine| engine.process_input(event)),
 			// Close, resize, etc. as requested by the window
-			Event::WindowEvent { ref event, window_id: id } if id == window.id() => engine.process_window_event(event, control_flow),
+			Event::WindowEvent { ref event, window_id: id } if id == window.id() => crash_report::report_crashes(&mut engine, |engine| engine.process_window_event(event, control_flow)),
 			// Draw the next frame as requested
-			Event::RedrawRequested(_) => engine.draw_frame(&window, control_flow),
+			Event::RedrawRequested(_) => crash_report::report_crashes(&mut engine, |engine| engine.draw_frame(&window, control_flow)),
 			// Request the next frame be drawn
 			Event::MainEventsCleared => window.request_redraw(),
 			_ => (),
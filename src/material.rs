@@ -1,30 +1,124 @@
-use crate::scene::LoadedResources;
+use crate::scene::{LoadedResources, ShaderHandle};
 use crate::shader::ShaderBinding;
 use crate::texture::Texture;
 
+use wgpu::util::DeviceExt;
+
+// Factors applied on top of what the material's maps give, in a uniform buffer each material has of its own
+// The defaults leave the maps as they are, so materials only need to set the ones they change
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialParams {
+	pub base_color_factor: [f32; 4],
+	pub emissive_factor: [f32; 3],
+	pub roughness_factor: f32,
+	// How many times the maps repeat across the UVs
+	pub uv_tiling: [f32; 2],
+	pub metallic_factor: f32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: f32,
+}
+
+impl Default for MaterialParams {
+	fn default() -> Self {
+		Self {
+			base_color_factor: [1.; 4],
+			emissive_factor: [1.; 3],
+			roughness_factor: 1.,
+			uv_tiling: [1.; 2],
+			metallic_factor: 1.,
+			_padding: 0.,
+		}
+	}
+}
+
+// One of the values of `MaterialParams`, for changing it on its own with `Material::set_param`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MaterialParam {
+	BaseColorFactor([f32; 4]),
+	RoughnessFactor(f32),
+	MetallicFactor(f32),
+	EmissiveFactor([f32; 3]),
+	UvTiling([f32; 2]),
+}
+
 pub struct Material {
-	pub shader_id: usize,
+	pub shader: ShaderHandle,
 	pub name: String,
 	pub bind_group: wgpu::BindGroup,
+	// Labels of the textures bound for reading and for writing as storage, kept so the frame graph can show which passes depend on which textures
+	pub sampled_textures: Vec<String>,
+	pub storage_textures: Vec<String>,
+	// Kept on the CPU too so one value can be changed without reading the others back from the buffer
+	pub params: MaterialParams,
+	pub params_buffer: wgpu::Buffer,
 }
 
 impl Material {
 	pub fn new(material_name: &str, shader_name: &str, data_bindings: Vec<MaterialDataBinding>, resources: &LoadedResources, device: &wgpu::Device) -> Self {
 		let shader = &resources.shaders[shader_name];
 
-		let shader_id = resources.shaders.get_index_of(shader_name).unwrap();
+		let shader_handle = resources.shaders.handle(shader_name).unwrap();
 		let name = String::from(material_name);
+		let sampled_textures = data_bindings
+			.iter()
+			.filter_map(|data_binding| match data_binding {
+				MaterialDataBinding::Texture(texture) | MaterialDataBinding::SampleableDepthTexture(texture, _) => Some(texture.label.clone()),
+				MaterialDataBinding::TextureName(texture) => Some(resources.textures[*texture].label.clone()),
+				MaterialDataBinding::Buffer(_) | MaterialDataBinding::MaterialParams | MaterialDataBinding::StorageTexture(_, _) => None,
+			})
+			.collect();
+		let storage_textures = data_bindings
+			.iter()
+			.filter_map(|data_binding| match data_binding {
+				MaterialDataBinding::StorageTexture(texture, _) => Some(texture.label.clone()),
+				_ => None,
+			})
+			.collect();
+		let params = MaterialParams::default();
+		let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some(material_name),
+			contents: bytemuck::cast_slice(&[params]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
 		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
 			layout: &shader.bind_group_layout,
-			entries: bind_group_entries(material_name, shader, data_bindings, resources).as_slice(),
+			entries: bind_group_entries(material_name, shader, data_bindings, &params_buffer, resources).as_slice(),
 			label: Some(material_name),
 		});
 
-		Self { shader_id, name, bind_group }
+		Self {
+			shader: shader_handle,
+			name,
+			bind_group,
+			sampled_textures,
+			storage_textures,
+			params,
+			params_buffer,
+		}
+	}
+
+	// Only has an effect on materials whose shader binds `MaterialDataBinding::MaterialParams`
+	pub fn set_param(&mut self, queue: &wgpu::Queue, param: MaterialParam) {
+		match param {
+			MaterialParam::BaseColorFactor(factor) => self.params.base_color_factor = factor,
+			MaterialParam::RoughnessFactor(factor) => self.params.roughness_factor = factor,
+			MaterialParam::MetallicFactor(factor) => self.params.metallic_factor = factor,
+			MaterialParam::EmissiveFactor(factor) => self.params.emissive_factor = factor,
+			MaterialParam::UvTiling(tiling) => self.params.uv_tiling = tiling,
+		}
+
+		queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[self.params]));
 	}
 }
 
-fn bind_group_entries<'a>(material_name: &'a str, shader: &'a crate::shader::Shader, data_bindings: Vec<MaterialDataBinding<'a>>, resources: &'a LoadedResources) -> Vec<wgpu::BindGroupEntry<'a>> {
+fn bind_group_entries<'a>(
+	material_name: &'a str,
+	shader: &'a crate::shader::Shader,
+	data_bindings: Vec<MaterialDataBinding<'a>>,
+	params_buffer: &'a wgpu::Buffer,
+	resources: &'a LoadedResources,
+) -> Vec<wgpu::BindGroupEntry<'a>> {
 	let mut binding_index = 0;
 
 	shader
@@ -41,6 +135,7 @@ fn bind_group_entries<'a>(material_name: &'a str, shader: &'a crate::shader::Sha
 					.get(index)
 					.and_then(|material_data_binding| match material_data_binding {
 						MaterialDataBinding::Buffer(buffer) => Some(buffer.clone()),
+						MaterialDataBinding::MaterialParams => Some(params_buffer.as_entire_buffer_binding()),
 						MaterialDataBinding::Texture(_) | &MaterialDataBinding::TextureName(_) | MaterialDataBinding::SampleableDepthTexture(_, _) | MaterialDataBinding::StorageTexture(_, _) => None,
 					})
 					.unwrap_or_else(|| panic!("Provided binding data for material '{}' does not match the shader definition", material_name));
@@ -61,7 +156,7 @@ fn bind_group_entries<'a>(material_name: &'a str, shader: &'a crate::shader::Sha
 						&MaterialDataBinding::SampleableDepthTexture(texture, sampler) => Some((sampler, &texture.view)),
 						&MaterialDataBinding::StorageTexture(texture, view) => Some((&texture.sampler, view.unwrap_or(&texture.view))),
 						MaterialDataBinding::TextureName(texture) => Some((&resources.textures[*texture].sampler, &resources.textures[*texture].view)),
-						MaterialDataBinding::Buffer(_) => None,
+						MaterialDataBinding::Buffer(_) | MaterialDataBinding::MaterialParams => None,
 					})
 					.unwrap_or_else(|| panic!("Provided binding data for material '{}' does not match the shader definition", material_name));
 
@@ -88,7 +183,7 @@ fn bind_group_entries<'a>(material_name: &'a str, shader: &'a crate::shader::Sha
 						&MaterialDataBinding::SampleableDepthTexture(texture, sampler) => Some((sampler, &texture.view)),
 						&MaterialDataBinding::StorageTexture(texture, view) => Some((&texture.sampler, view.unwrap_or(&texture.view))),
 						MaterialDataBinding::TextureName(texture) => Some((&resources.textures[*texture].sampler, &resources.textures[*texture].view)),
-						MaterialDataBinding::Buffer(_) => None,
+						MaterialDataBinding::Buffer(_) | MaterialDataBinding::MaterialParams => None,
 					})
 					.unwrap_or_else(|| panic!("Provided binding data for material '{}' does not match the shader definition", material_name));
 
@@ -100,10 +195,55 @@ fn bind_group_entries<'a>(material_name: &'a str, shader: &'a crate::shader::Sha
 		.collect()
 }
 
+// Bindings given by their position among the shader's bindings, replacing the template's there
+pub type BindingOverrides<'a> = Vec<(usize, MaterialDataBinding<'a>)>;
+
+// A shader along with the bindings shared by most of its materials, so each material made from it only gives the bindings that differ
+#[derive(Clone)]
+pub struct MaterialTemplate<'a> {
+	pub shader_name: &'a str,
+	// One for each of the shader's bindings, in order, with `None` for those every instance has to give itself
+	pub default_bindings: Vec<Option<MaterialDataBinding<'a>>>,
+}
+
+impl<'a> MaterialTemplate<'a> {
+	pub fn new(shader_name: &'a str, default_bindings: Vec<Option<MaterialDataBinding<'a>>>) -> Self {
+		Self { shader_name, default_bindings }
+	}
+
+	// Creates a material using the template's bindings, except for the overrides given by their position among the shader's bindings
+	pub fn instantiate(&self, material_name: &str, overrides: BindingOverrides<'a>, resources: &LoadedResources, device: &wgpu::Device) -> Material {
+		let mut data_bindings = self.default_bindings.clone();
+		for (index, binding) in overrides {
+			match data_bindings.get_mut(index) {
+				Some(data_binding) => *data_binding = Some(binding),
+				None => panic!(
+					"Material '{}' overrides binding {} but its shader '{}' only has {}",
+					material_name,
+					index,
+					self.shader_name,
+					data_bindings.len()
+				),
+			}
+		}
+
+		let data_bindings = data_bindings
+			.into_iter()
+			.enumerate()
+			.map(|(index, data_binding)| data_binding.unwrap_or_else(|| panic!("Material '{}' is missing binding {}, which its template leaves to each instance", material_name, index)))
+			.collect();
+
+		Material::new(material_name, self.shader_name, data_bindings, resources, device)
+	}
+}
+
+#[derive(Clone)]
 pub enum MaterialDataBinding<'a> {
 	Buffer(wgpu::BufferBinding<'a>),
 	Texture(&'a Texture),
 	SampleableDepthTexture(&'a Texture, &'a wgpu::Sampler),
 	StorageTexture(&'a Texture, Option<&'a wgpu::TextureView>),
 	TextureName(&'a str),
+	// The material's own `MaterialParams` uniform buffer
+	MaterialParams,
 }
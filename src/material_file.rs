@@ -0,0 +1,210 @@
+use crate::json::{JsonParser, JsonValue};
+use crate::shader::{Shader, ShaderBinding};
+
+use anyhow::{anyhow, bail, Result};
+use std::path::Path;
+use wgpu::util::DeviceExt;
+
+// Where the material files are read from, inside the assets folder
+pub const MATERIALS_FOLDER: &str = "materials";
+
+// How a texture of a material file is sampled, which gets its own sampler so the same texture file can be sampled differently by other materials
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SamplerSettings {
+	pub address_mode: wgpu::AddressMode,
+	pub filter: wgpu::FilterMode,
+}
+
+impl Default for SamplerSettings {
+	fn default() -> Self {
+		Self {
+			address_mode: wgpu::AddressMode::Repeat,
+			filter: wgpu::FilterMode::Linear,
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub enum MaterialFileBinding {
+	// A texture file in the assets folder, or one generated at runtime under that name, read as sRGB if it holds colors
+	Texture { file: String, color: bool, sampler: SamplerSettings },
+	// Numbers put in a uniform buffer in the order they're given, padded out to whole vec4s as uniforms need
+	Parameters(Vec<f32>),
+}
+
+// A material described in a ".material" file of the materials folder instead of being put together in the engine, named after its file
+// The file is JSON like the scenes, naming one of the engine's shaders and giving a binding for each of the shader's, in order:
+// - {"texture": "<file>", "color": <true for sRGB>, "address_mode": "repeat|clamp|mirror", "filter": "linear|nearest"}
+// - {"parameters": [<numbers>]}
+#[derive(Debug, Clone)]
+pub struct MaterialFile {
+	pub name: String,
+	pub shader: String,
+	pub bindings: Vec<MaterialFileBinding>,
+}
+
+impl MaterialFile {
+	pub fn from_file(path: &Path) -> Result<Self> {
+		let name = path.file_name().and_then(|name| name.to_str()).ok_or_else(|| anyhow!("Material file {:?} has no name", path))?;
+		let text = std::fs::read_to_string(path)?;
+		let root = JsonParser::new(&text).parse_document()?;
+
+		let shader = root.get("shader").and_then(JsonValue::as_str).ok_or_else(|| anyhow!("Material \"{}\" names no shader", name))?;
+		let bindings = root
+			.get("bindings")
+			.and_then(JsonValue::as_array)
+			.unwrap_or_default()
+			.iter()
+			.enumerate()
+			.map(|(index, binding)| parse_binding(binding).map_err(|error| anyhow!("Binding {} of material \"{}\": {}", index, name, error)))
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(Self {
+			name: String::from(name),
+			shader: String::from(shader),
+			bindings,
+		})
+	}
+
+	// Every material file in the materials folder that can be read, with those that can't left out after saying why
+	pub fn read_all(assets_path: &Path) -> Vec<Self> {
+		let entries = match std::fs::read_dir(assets_path.join(MATERIALS_FOLDER)) {
+			Ok(entries) => entries,
+			Err(_) => return vec![],
+		};
+
+		let mut paths = entries
+			.flatten()
+			.map(|entry| entry.path())
+			.filter(|path| path.extension().is_some_and(|extension| extension == "material"))
+			.collect::<Vec<_>>();
+		paths.sort();
+
+		paths
+			.iter()
+			.filter_map(|path| match Self::from_file(path) {
+				Ok(material_file) => Some(material_file),
+				Err(error) => {
+					eprintln!("Can't read material file {:?}: {}", path, error);
+					None
+				}
+			})
+			.collect()
+	}
+
+	// The texture files it samples, in the format and address mode they're loaded with
+	pub fn textures(&self) -> Vec<(String, wgpu::TextureFormat, wgpu::AddressMode)> {
+		self.bindings
+			.iter()
+			.filter_map(|binding| match binding {
+				MaterialFileBinding::Texture { file, color, sampler } => {
+					let format = if *color { wgpu::TextureFormat::Rgba8UnormSrgb } else { wgpu::TextureFormat::Rgba8Unorm };
+					Some((file.clone(), format, sampler.address_mode))
+				}
+				MaterialFileBinding::Parameters(_) => None,
+			})
+			.collect()
+	}
+
+	// Checked before the material is built, which would otherwise panic on bindings that don't match its shader's
+	pub fn check_against(&self, shader: &Shader) -> Result<()> {
+		if self.bindings.len() != shader.shader_bindings.len() {
+			bail!(
+				"Material \"{}\" gives {} bindings but its shader \"{}\" has {}",
+				self.name,
+				self.bindings.len(),
+				self.shader,
+				shader.shader_bindings.len()
+			);
+		}
+
+		for (index, (binding, shader_binding)) in self.bindings.iter().zip(&shader.shader_bindings).enumerate() {
+			match (binding, shader_binding) {
+				(MaterialFileBinding::Texture { .. }, ShaderBinding::Texture(_)) | (MaterialFileBinding::Parameters(_), ShaderBinding::Buffer(_)) => {}
+				_ => bail!("Binding {} of material \"{}\" isn't the kind its shader \"{}\" takes there", index, self.name, self.shader),
+			}
+		}
+
+		Ok(())
+	}
+}
+
+fn parse_binding(binding: &JsonValue) -> Result<MaterialFileBinding> {
+	if let Some(file) = binding.get("texture").and_then(JsonValue::as_str) {
+		let string = |key: &str| binding.get(key).and_then(JsonValue::as_str);
+
+		let address_mode = match string("address_mode").unwrap_or("repeat") {
+			"repeat" => wgpu::AddressMode::Repeat,
+			"clamp" => wgpu::AddressMode::ClampToEdge,
+			"mirror" => wgpu::AddressMode::MirrorRepeat,
+			other => bail!("There's no address mode \"{}\", it can be repeat, clamp, or mirror", other),
+		};
+		let filter = match string("filter").unwrap_or("linear") {
+			"linear" => wgpu::FilterMode::Linear,
+			"nearest" => wgpu::FilterMode::Nearest,
+			other => bail!("There's no filter \"{}\", it can be linear or nearest", other),
+		};
+
+		return Ok(MaterialFileBinding::Texture {
+			file: String::from(file),
+			color: matches!(binding.get("color"), Some(JsonValue::Bool(true))),
+			sampler: SamplerSettings { address_mode, filter },
+		});
+	}
+
+	if let Some(parameters) = binding.get("parameters").and_then(JsonValue::as_array) {
+		let numbers = parameters
+			.iter()
+			.map(|parameter| parameter.as_number().map(|number| number as f32))
+			.collect::<Option<Vec<_>>>()
+			.ok_or_else(|| anyhow!("The parameters have to be numbers"))?;
+		return Ok(MaterialFileBinding::Parameters(numbers));
+	}
+
+	bail!("A binding has to be a texture or parameters")
+}
+
+// The buffers and samplers the materials built from the material files bind, which are kept for as long as those materials are
+#[derive(Default)]
+pub struct MaterialFileResources {
+	pub parameter_buffers: Vec<wgpu::Buffer>,
+	pub samplers: Vec<wgpu::Sampler>,
+}
+
+impl MaterialFileResources {
+	// Creates the uniform buffer and samplers for each material file, in the order of its bindings
+	pub fn new(device: &wgpu::Device, material_files: &[MaterialFile]) -> Self {
+		let mut resources = Self::default();
+
+		for material_file in material_files {
+			for binding in &material_file.bindings {
+				match binding {
+					MaterialFileBinding::Texture { sampler, .. } => resources.samplers.push(device.create_sampler(&wgpu::SamplerDescriptor {
+						label: Some(material_file.name.as_str()),
+						address_mode_u: sampler.address_mode,
+						address_mode_v: sampler.address_mode,
+						address_mode_w: sampler.address_mode,
+						mag_filter: sampler.filter,
+						min_filter: sampler.filter,
+						mipmap_filter: sampler.filter,
+						lod_min_clamp: -100.0,
+						lod_max_clamp: 100.0,
+						..Default::default()
+					})),
+					MaterialFileBinding::Parameters(parameters) => {
+						let mut contents = parameters.clone();
+						contents.resize(parameters.len().div_ceil(4).max(1) * 4, 0.);
+
+						resources.parameter_buffers.push(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+							label: Some(material_file.name.as_str()),
+							contents: bytemuck::cast_slice(contents.as_slice()),
+							usage: wgpu::BufferUsages::UNIFORM,
+						}));
+					}
+				}
+			}
+		}
+
+		resources
+	}
+}
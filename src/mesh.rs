@@ -1,22 +1,138 @@
-use anyhow::Result;
-use cgmath::InnerSpace;
+use crate::meshlet::{build_meshlets, Meshlet};
+use crate::morph::{MorphTarget, MorphTargets};
+
+use anyhow::{anyhow, Result};
+use half::f16;
 use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
-use std::{mem, path::Path};
+use std::{
+	mem,
+	path::{Path, PathBuf},
+};
 use tobj::LoadOptions;
 use wgpu::util::DeviceExt;
 
 pub struct Mesh {
 	pub name: String,
 	pub vertex_buffer: wgpu::Buffer,
+	// Compact copies of the vertex data for pipelines that don't need every attribute, see `VertexFormat`
+	pub position_vertex_buffer: wgpu::Buffer,
+	pub packed_vertex_buffer: wgpu::Buffer,
 	pub index_buffer: wgpu::Buffer,
+	pub index_format: wgpu::IndexFormat,
 	pub index_count: u32,
+	pub vertex_capacity: u32,
+	// Counted in bytes, since the buffer holds u16 or u32 indices depending on how large they are
+	pub index_capacity: u64,
+	// Object space sphere enclosing every vertex, used for culling
+	pub bounds_center: [f32; 3],
+	pub bounds_radius: f32,
+	// Half the size of the object space box enclosing every vertex, centered on the sphere, which fits long and flat meshes more tightly for culling
+	pub bounds_half_extents: [f32; 3],
+	// Clusters of the index buffer for the GPU culled meshlet path, left empty for meshes that never take it
+	pub meshlets: Vec<Meshlet>,
+	// Object space copy of the triangles kept on the CPU for the collision world, left empty for meshes that are never collided with
+	pub collision_triangles: Vec<[[f32; 3]; 3]>,
+	// Name of the material in the model file, which meshes built at runtime don't have
+	pub material_name: Option<String>,
 	pub map_albedo: Option<String>,
 	pub map_arm: Option<String>,
 	pub map_normal: Option<String>,
+	pub map_emissive: Option<String>,
+	// Separate roughness and metalness maps from the MTL file's PBR extensions, which get packed into an ARM map when it has no combined one
+	pub map_roughness: Option<String>,
+	pub map_metallic: Option<String>,
+	// Scalar values standing in for any of the maps the MTL file leaves out
+	pub material_parameters: MaterialParameters,
+	pub morph_targets: MorphTargets,
+}
+
+// Everything about a mesh from a model file that's worked out on the CPU, waiting to be uploaded by `Mesh::upload`
+pub struct DecodedMesh {
+	name: String,
+	path: PathBuf,
+	vertices: Vec<ModelVertex>,
+	indices: Vec<u32>,
+	bounds_center: [f32; 3],
+	bounds_radius: f32,
+	bounds_half_extents: [f32; 3],
+	meshlets: Vec<Meshlet>,
+	collision_triangles: Vec<[[f32; 3]; 3]>,
+	material_name: Option<String>,
+	map_albedo: Option<String>,
+	map_arm: Option<String>,
+	map_normal: Option<String>,
+	map_emissive: Option<String>,
+	map_roughness: Option<String>,
+	map_metallic: Option<String>,
+	material_parameters: MaterialParameters,
+}
+
+// The scalar values of an MTL material, from its PBR extensions (`Pr`, `Pm`, `Ke`) where it has them
+#[derive(Debug, Copy, Clone)]
+pub struct MaterialParameters {
+	pub albedo: [f32; 3],
+	pub roughness: f32,
+	pub metallic: f32,
+	pub emissive: [f32; 3],
+	// Thin surfaces like cloth and leaves are drawn from behind too instead of being back-face culled, set by our own `two_sided 1` extension
+	pub two_sided: bool,
+}
+
+impl Default for MaterialParameters {
+	fn default() -> Self {
+		Self {
+			albedo: [0.8; 3],
+			roughness: 0.5,
+			metallic: 0.,
+			emissive: [0.; 3],
+			two_sided: false,
+		}
+	}
+}
+
+impl MaterialParameters {
+	// Materials without a `Pr` have their roughness approximated from the Blinn-Phong specular exponent, which is what older exporters write instead
+	fn from_mtl(material: &tobj::Material) -> Self {
+		let scalar = |key: &str| material.unknown_param.get(key).and_then(|value| value.trim().parse::<f32>().ok());
+		let color = |key: &str| {
+			let channels = material.unknown_param.get(key)?.split_whitespace().map(|value| value.parse::<f32>().ok()).collect::<Option<Vec<_>>>()?;
+			match channels.as_slice() {
+				[value] => Some([*value; 3]),
+				[r, g, b] => Some([*r, *g, *b]),
+				_ => None,
+			}
+		};
+
+		Self {
+			albedo: material.diffuse,
+			roughness: scalar("Pr").unwrap_or_else(|| (2. / (material.shininess.max(0.) + 2.)).sqrt()).clamp(0., 1.),
+			metallic: scalar("Pm").unwrap_or(0.).clamp(0., 1.),
+			emissive: color("Ke").unwrap_or([0.; 3]),
+			two_sided: scalar("two_sided").is_some_and(|value| value != 0.),
+		}
+	}
+}
+
+// A texture standing in for a map the MTL file leaves out, which is generated before the model's textures are loaded
+pub enum GeneratedMap {
+	// One texel of a scalar value
+	Constant {
+		name: String,
+		rgba: [u8; 4],
+		format: wgpu::TextureFormat,
+	},
+	// Separate roughness and metalness maps packed into the channels of an ARM map, with the scalar value filling in for whichever is missing
+	PackedArm {
+		name: String,
+		roughness: Option<String>,
+		metallic: Option<String>,
+		parameters: MaterialParameters,
+	},
 }
 
 impl Mesh {
-	pub fn load(device: &wgpu::Device, _queue: &wgpu::Queue, directory: &Path, file: &str) -> Result<Vec<Mesh>> {
+	// Reads every mesh in a model file and works out everything about them that doesn't need the GPU, which is slow enough for Sponza to be done on a worker thread
+	pub fn decode(directory: &Path, file: &str) -> Result<Vec<DecodedMesh>> {
 		let path = directory.join("models").join(file);
 
 		let (obj_models, obj_materials) = tobj::load_obj(
@@ -30,7 +146,7 @@ impl Mesh {
 
 		let obj_materials = obj_materials.unwrap_or_default();
 
-		let meshes = obj_models
+		obj_models
 			.par_iter()
 			.map(|m| {
 				let mut vertices = (0..m.mesh.positions.len() / 3)
@@ -40,102 +156,180 @@ impl Mesh {
 							position: [m.mesh.positions[i * 3], m.mesh.positions[i * 3 + 1], m.mesh.positions[i * 3 + 2]],
 							uv: [m.mesh.texcoords[i * 2], m.mesh.texcoords[i * 2 + 1]],
 							normal: [m.mesh.normals[i * 3], m.mesh.normals[i * 3 + 1], m.mesh.normals[i * 3 + 2]],
-							tangent: [0.0; 3], // Tangent value is calculated in the code below
+							tangent: [0.0; 4], // Tangent value is calculated in the code below
 						}
 					})
 					.collect::<Vec<_>>();
 
-				// let mut triangles_included = (0..vertices.len()).collect::<Vec<_>>();
-
-				// Calculate tangents. We're going to use the triangles, so we need to loop through the indices in chunks of 3
-				for a in m.mesh.indices.chunks(3) {
-					let i1 = a[0] as usize;
-					let i2 = a[1] as usize;
-					let i3 = a[2] as usize;
-
-					let v1 = vertices[i1].position;
-					let v2 = vertices[i2].position;
-					let v3 = vertices[i3].position;
+				if !generate_tangents(&mut vertices, &m.mesh.indices) {
+					return Err(anyhow!("Could not generate tangents for mesh '{}' in {:?}", m.name, path));
+				}
 
-					let w1 = vertices[i1].uv;
-					let w2 = vertices[i2].uv;
-					let w3 = vertices[i3].uv;
+				let (bounds_center, bounds_radius) = bounding_sphere(&vertices);
+				let (_, bounds_half_extents) = bounding_box(&vertices);
+				let meshlets = build_meshlets(&vertices, &m.mesh.indices);
+				let collision_triangles = collision_triangles(&vertices, &m.mesh.indices);
 
-					let x1 = v2[0] - v1[0];
-					let x2 = v3[0] - v1[0];
-					let y1 = v2[1] - v1[1];
-					let y2 = v3[1] - v1[1];
-					let z1 = v2[2] - v1[2];
-					let z2 = v3[2] - v1[2];
+				let material = m.mesh.material_id.map(|index| &obj_materials[index]);
+				let map = |texture: fn(&tobj::Material) -> &String| material.map(texture).filter(|name| !name.is_empty()).cloned();
+				// The PBR extensions aren't understood by tobj, and their values can start with options like `-bm 1.0` before the file name
+				let extension_map = |key: &str| {
+					material
+						.and_then(|material| material.unknown_param.get(key))
+						.and_then(|value| value.split_whitespace().last())
+						.map(String::from)
+				};
 
-					let s1 = w2[0] - w1[0];
-					let s2 = w3[0] - w1[0];
-					let t1 = w2[1] - w1[1];
-					let t2 = w3[1] - w1[1];
+				Ok(DecodedMesh {
+					name: m.name.clone(),
+					path: path.clone(),
+					vertices,
+					indices: m.mesh.indices.clone(),
+					bounds_center,
+					bounds_radius,
+					bounds_half_extents,
+					meshlets,
+					collision_triangles,
+					material_name: material.map(|material| material.name.clone()),
+					map_albedo: map(|material| &material.diffuse_texture),
+					// By convention the ARM map is given as the shininess map, since MTL has no slot for one
+					map_arm: map(|material| &material.shininess_texture),
+					map_normal: map(|material| &material.normal_texture).or_else(|| extension_map("norm")),
+					map_emissive: extension_map("map_Ke"),
+					map_roughness: extension_map("map_Pr"),
+					map_metallic: extension_map("map_Pm"),
+					material_parameters: material.map(MaterialParameters::from_mtl).unwrap_or_default(),
+				})
+			})
+			.collect::<Result<Vec<_>>>()
+	}
 
-					let r = 1. / (s1 * t2 - s2 * t1);
-					let sdir = [(t2 * x1 - t1 * x2) * r, (t2 * y1 - t1 * y2) * r, (t2 * z1 - t1 * z2) * r];
+	// Creates the buffers for a decoded mesh, which has to happen on the thread that owns the GPU
+	pub fn upload(device: &wgpu::Device, decoded: DecodedMesh) -> Self {
+		let DecodedMesh {
+			name,
+			path,
+			vertices,
+			indices,
+			bounds_center,
+			bounds_radius,
+			bounds_half_extents,
+			meshlets,
+			collision_triangles,
+			material_name,
+			map_albedo,
+			map_arm,
+			map_normal,
+			map_emissive,
+			map_roughness,
+			map_metallic,
+			material_parameters,
+		} = decoded;
 
-					vertices[i1].tangent[0] += sdir[0];
-					vertices[i1].tangent[1] += sdir[1];
-					vertices[i1].tangent[2] += sdir[2];
+		let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some(&format!("{:?} Vertex Buffer", path)),
+			contents: bytemuck::cast_slice(&vertices),
+			usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+		});
+		let position_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some(&format!("{:?} Position Vertex Buffer", path)),
+			contents: bytemuck::cast_slice(&vertices.iter().map(PositionVertex::from).collect::<Vec<_>>()),
+			usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+		});
+		let packed_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some(&format!("{:?} Packed Vertex Buffer", path)),
+			contents: bytemuck::cast_slice(&vertices.iter().map(PackedVertex::from).collect::<Vec<_>>()),
+			usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+		});
+		let (index_data, index_format) = pack_indices(&indices);
+		let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some(&format!("{:?} Index Buffer", name)),
+			contents: index_data.as_slice(),
+			usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+		});
 
-					vertices[i2].tangent[0] += sdir[0];
-					vertices[i2].tangent[1] += sdir[1];
-					vertices[i2].tangent[2] += sdir[2];
+		Mesh {
+			morph_targets: MorphTargets::empty(device, &name),
+			name,
+			vertex_buffer,
+			position_vertex_buffer,
+			packed_vertex_buffer,
+			index_buffer,
+			index_format,
+			index_count: indices.len() as u32,
+			vertex_capacity: vertices.len() as u32,
+			index_capacity: index_data.len() as u64,
+			bounds_center,
+			bounds_radius,
+			bounds_half_extents,
+			meshlets,
+			collision_triangles,
+			material_name,
+			map_albedo,
+			map_arm,
+			map_normal,
+			map_emissive,
+			map_roughness,
+			map_metallic,
+			material_parameters,
+		}
+	}
 
-					vertices[i3].tangent[0] += sdir[0];
-					vertices[i3].tangent[1] += sdir[1];
-					vertices[i3].tangent[2] += sdir[2];
-				}
+	// The image files of the mesh's maps along with how each is sampled, which only the albedo and emissive maps are as sRGB
+	pub fn map_textures(&self) -> Vec<(String, wgpu::TextureFormat, wgpu::AddressMode)> {
+		[
+			(&self.map_albedo, wgpu::TextureFormat::Rgba8UnormSrgb),
+			(&self.map_arm, wgpu::TextureFormat::Rgba8Unorm),
+			(&self.map_normal, wgpu::TextureFormat::Rgba8Unorm),
+			(&self.map_emissive, wgpu::TextureFormat::Rgba8UnormSrgb),
+		]
+		.into_iter()
+		.filter_map(|(map, format)| Some((map.clone()?, format, wgpu::AddressMode::Repeat)))
+		.collect()
+	}
 
-				for a in &mut vertices {
-					let n = cgmath::Vector3::new(a.normal[0], a.normal[1], a.normal[2]);
-					let t = cgmath::Vector3::new(a.tangent[0], a.tangent[1], a.tangent[2]);
+	// Gives every map the mesh is missing a generated stand-in, named after what it's made from so meshes with the same values share one texture
+	pub fn fill_in_missing_maps(&mut self) -> Vec<GeneratedMap> {
+		let parameters = self.material_parameters;
+		let mut generated = Vec::new();
+		let mut constant = |kind: &str, rgba: [u8; 4], format: wgpu::TextureFormat| {
+			let name = format!("CONSTANT_{}_{:02X}{:02X}{:02X}{:02X}", kind, rgba[0], rgba[1], rgba[2], rgba[3]);
+			generated.push(GeneratedMap::Constant { name: name.clone(), rgba, format });
+			name
+		};
+		// The albedo and emissive maps are sampled as sRGB, so the linear MTL colors are encoded to match
+		let srgb = |color: [f32; 3]| color.map(|channel| (channel.clamp(0., 1.).powf(1. / 2.2) * 255.).round() as u8);
+		let unorm = |value: f32| (value.clamp(0., 1.) * 255.).round() as u8;
 
-					// Gram-Schmidt orthogonalize
-					let orthogonalized = (t - n * cgmath::dot(n, t)).normalize();
-					a.tangent[0] = orthogonalized.x;
-					a.tangent[1] = orthogonalized.y;
-					a.tangent[2] = orthogonalized.z;
+		if self.map_albedo.is_none() {
+			let [r, g, b] = srgb(parameters.albedo);
+			self.map_albedo = Some(constant("ALBEDO", [r, g, b, 255], wgpu::TextureFormat::Rgba8UnormSrgb));
+		}
+		if self.map_emissive.is_none() {
+			let [r, g, b] = srgb(parameters.emissive);
+			self.map_emissive = Some(constant("EMISSIVE", [r, g, b, 255], wgpu::TextureFormat::Rgba8UnormSrgb));
+		}
+		if self.map_normal.is_none() {
+			self.map_normal = Some(constant("NORMAL", [128, 128, 255, 255], wgpu::TextureFormat::Rgba8Unorm));
+		}
+		if self.map_arm.is_none() {
+			self.map_arm = Some(match (&self.map_roughness, &self.map_metallic) {
+				(None, None) => constant("ARM", [0, unorm(parameters.roughness), unorm(parameters.metallic), 255], wgpu::TextureFormat::Rgba8Unorm),
+				(roughness, metallic) => {
+					let source = |map: &Option<String>, value: f32| map.clone().unwrap_or_else(|| format!("{:.3}", value));
+					let name = format!("PACKED_ARM_{}_{}", source(roughness, parameters.roughness), source(metallic, parameters.metallic));
+					generated.push(GeneratedMap::PackedArm {
+						name: name.clone(),
+						roughness: roughness.clone(),
+						metallic: metallic.clone(),
+						parameters,
+					});
+					name
 				}
+			});
+		}
 
-				let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-					label: Some(&format!("{:?} Vertex Buffer", path)),
-					contents: bytemuck::cast_slice(&vertices),
-					usage: wgpu::BufferUsages::VERTEX,
-				});
-				let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-					label: Some(&format!("{:?} Index Buffer", m.name)),
-					contents: bytemuck::cast_slice(&m.mesh.indices),
-					usage: wgpu::BufferUsages::INDEX,
-				});
-
-				let (map_albedo, map_arm, map_normal) = if let Some(index) = m.mesh.material_id {
-					let material = &obj_materials[index];
-
-					(
-						Some(material.diffuse_texture.clone()).filter(|name| !name.is_empty()),
-						Some(material.shininess_texture.clone()).filter(|name| !name.is_empty()),
-						Some(material.normal_texture.clone()).filter(|name| !name.is_empty()),
-					)
-				} else {
-					(None, None, None)
-				};
-
-				Ok(Mesh {
-					name: m.name.clone(),
-					vertex_buffer,
-					index_buffer,
-					index_count: m.mesh.indices.len() as u32,
-					map_albedo,
-					map_arm,
-					map_normal,
-				})
-			})
-			.collect::<Result<Vec<_>>>()?;
-
-		Ok(meshes)
+		generated
 	}
 
 	pub fn new_blit_quad(device: &wgpu::Device, _queue: &wgpu::Queue) -> Self {
@@ -144,16 +338,28 @@ impl Mesh {
 			position: [point.0, point.1, 0.5],
 			uv: [0.0; 2],
 			normal: [0.0; 3],
-			tangent: [0.0; 3],
+			tangent: [0.0; 4],
 		});
 
-		let indices: [u32; 6] = [2, 1, 0, 3, 1, 2];
+		let indices: [u16; 6] = [2, 1, 0, 3, 1, 2];
+		let (bounds_center, bounds_radius) = bounding_sphere(&vertices);
+		let (_, bounds_half_extents) = bounding_box(&vertices);
 
 		let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
 			label: Some("Blit Quad Vertex Buffer"),
 			contents: bytemuck::cast_slice(&vertices),
 			usage: wgpu::BufferUsages::VERTEX,
 		});
+		let position_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Blit Quad Position Vertex Buffer"),
+			contents: bytemuck::cast_slice(&vertices.iter().map(PositionVertex::from).collect::<Vec<_>>()),
+			usage: wgpu::BufferUsages::VERTEX,
+		});
+		let packed_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Blit Quad Packed Vertex Buffer"),
+			contents: bytemuck::cast_slice(&vertices.iter().map(PackedVertex::from).collect::<Vec<_>>()),
+			usage: wgpu::BufferUsages::VERTEX,
+		});
 		let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
 			label: Some("Blit Quad Index Buffer"),
 			contents: bytemuck::cast_slice(&indices),
@@ -163,13 +369,230 @@ impl Mesh {
 		Self {
 			name: String::from("Blit Quad"),
 			vertex_buffer,
+			position_vertex_buffer,
+			packed_vertex_buffer,
 			index_buffer,
+			index_format: wgpu::IndexFormat::Uint16,
 			index_count: 6,
+			vertex_capacity: 4,
+			index_capacity: mem::size_of_val(&indices) as u64,
+			bounds_center,
+			bounds_radius,
+			bounds_half_extents,
+			meshlets: vec![],
+			collision_triangles: vec![],
+			material_name: None,
 			map_albedo: None,
 			map_arm: None,
 			map_normal: None,
+			map_emissive: None,
+			map_roughness: None,
+			map_metallic: None,
+			material_parameters: MaterialParameters::default(),
+			morph_targets: MorphTargets::empty(device, "Blit Quad"),
+		}
+	}
+
+	// Creates a mesh meant to be rewritten at runtime (debug shapes, trails, CPU cloth, etc.), with room reserved for it to grow before buffers must be reallocated
+	pub fn new_dynamic(device: &wgpu::Device, queue: &wgpu::Queue, name: &str, vertices: &[ModelVertex], indices: &[u32]) -> Self {
+		let vertex_capacity = grown_capacity(vertices.len());
+		let index_count_capacity = grown_capacity(indices.len());
+
+		let mut mesh = Self {
+			name: String::from(name),
+			vertex_buffer: create_dynamic_buffer::<ModelVertex>(device, &format!("{} Vertex Buffer", name), vertex_capacity, wgpu::BufferUsages::VERTEX),
+			position_vertex_buffer: create_dynamic_buffer::<PositionVertex>(device, &format!("{} Position Vertex Buffer", name), vertex_capacity, wgpu::BufferUsages::VERTEX),
+			packed_vertex_buffer: create_dynamic_buffer::<PackedVertex>(device, &format!("{} Packed Vertex Buffer", name), vertex_capacity, wgpu::BufferUsages::VERTEX),
+			index_buffer: create_dynamic_buffer::<u32>(device, &format!("{} Index Buffer", name), index_count_capacity, wgpu::BufferUsages::INDEX),
+			index_format: wgpu::IndexFormat::Uint32,
+			index_count: 0,
+			vertex_capacity,
+			index_capacity: index_count_capacity as u64 * mem::size_of::<u32>() as u64,
+			bounds_center: [0.; 3],
+			bounds_radius: 0.,
+			bounds_half_extents: [0.; 3],
+			meshlets: vec![],
+			collision_triangles: vec![],
+			material_name: None,
+			map_albedo: None,
+			map_arm: None,
+			map_normal: None,
+			map_emissive: None,
+			map_roughness: None,
+			map_metallic: None,
+			material_parameters: MaterialParameters::default(),
+			morph_targets: MorphTargets::empty(device, name),
+		};
+
+		mesh.update_vertices(device, queue, vertices);
+		mesh.update_indices(device, queue, indices);
+		mesh
+	}
+
+	// Uploads new vertex data, reallocating the buffer (with headroom) only when the data outgrows its current capacity
+	pub fn update_vertices(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, vertices: &[ModelVertex]) {
+		if vertices.len() as u32 > self.vertex_capacity {
+			self.vertex_capacity = grown_capacity(vertices.len());
+			self.vertex_buffer = create_dynamic_buffer::<ModelVertex>(device, &format!("{} Vertex Buffer", self.name), self.vertex_capacity, wgpu::BufferUsages::VERTEX);
+			self.position_vertex_buffer = create_dynamic_buffer::<PositionVertex>(device, &format!("{} Position Vertex Buffer", self.name), self.vertex_capacity, wgpu::BufferUsages::VERTEX);
+			self.packed_vertex_buffer = create_dynamic_buffer::<PackedVertex>(device, &format!("{} Packed Vertex Buffer", self.name), self.vertex_capacity, wgpu::BufferUsages::VERTEX);
+		}
+
+		let position_vertices = vertices.iter().map(PositionVertex::from).collect::<Vec<_>>();
+		let packed_vertices = vertices.iter().map(PackedVertex::from).collect::<Vec<_>>();
+		queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(vertices));
+		queue.write_buffer(&self.position_vertex_buffer, 0, bytemuck::cast_slice(&position_vertices));
+		queue.write_buffer(&self.packed_vertex_buffer, 0, bytemuck::cast_slice(&packed_vertices));
+
+		let (bounds_center, bounds_radius) = bounding_sphere(vertices);
+		self.bounds_center = bounds_center;
+		self.bounds_radius = bounds_radius;
+		self.bounds_half_extents = bounding_box(vertices).1;
+	}
+
+	// Uploads new index data, reallocating the buffer (with headroom) only when the data outgrows its current capacity
+	// Grown buffers have room for u32 indices, so switching between u16 and u32 indices afterwards never needs a bigger one
+	pub fn update_indices(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, indices: &[u32]) {
+		let (index_data, index_format) = pack_indices(indices);
+		if index_data.len() as u64 > self.index_capacity {
+			let index_count_capacity = grown_capacity(indices.len());
+			self.index_buffer = create_dynamic_buffer::<u32>(device, &format!("{} Index Buffer", self.name), index_count_capacity, wgpu::BufferUsages::INDEX);
+			self.index_capacity = index_count_capacity as u64 * mem::size_of::<u32>() as u64;
+		}
+
+		queue.write_buffer(&self.index_buffer, 0, index_data.as_slice());
+		self.index_format = index_format;
+		self.index_count = indices.len() as u32;
+	}
+
+	pub fn vertex_buffer(&self, vertex_format: VertexFormat) -> &wgpu::Buffer {
+		match vertex_format {
+			VertexFormat::Model => &self.vertex_buffer,
+			VertexFormat::PositionOnly => &self.position_vertex_buffer,
+			VertexFormat::Packed => &self.packed_vertex_buffer,
 		}
 	}
+
+	// Materials bind the morph targets buffer when they are created, so this must be called before the mesh's materials are built
+	pub fn set_morph_targets(&mut self, device: &wgpu::Device, targets: &[MorphTarget]) {
+		let vertex_count = targets.iter().map(|target| target.position_deltas.len().max(target.normal_deltas.len())).max().unwrap_or_default();
+
+		self.morph_targets = MorphTargets::new(device, &self.name, vertex_count as u32, targets);
+	}
+}
+
+// Fills in mikktspace tangents so normal maps baked by standard tools shade correctly, returning false if the geometry is unsuitable (such as having no faces)
+pub fn generate_tangents(vertices: &mut [ModelVertex], indices: &[u32]) -> bool {
+	bevy_mikktspace::generate_tangents(&mut TangentGeometry { vertices, indices })
+}
+
+struct TangentGeometry<'a> {
+	vertices: &'a mut [ModelVertex],
+	indices: &'a [u32],
+}
+
+impl<'a> TangentGeometry<'a> {
+	fn vertex(&self, face: usize, vert: usize) -> &ModelVertex {
+		&self.vertices[self.indices[face * 3 + vert] as usize]
+	}
+}
+
+impl<'a> bevy_mikktspace::Geometry for TangentGeometry<'a> {
+	fn num_faces(&self) -> usize {
+		self.indices.len() / 3
+	}
+
+	fn num_vertices_of_face(&self, _face: usize) -> usize {
+		3
+	}
+
+	fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+		self.vertex(face, vert).position
+	}
+
+	fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+		self.vertex(face, vert).normal
+	}
+
+	// Textures are sampled with V flipped, so the tangent frame has to be built in that same space
+	fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+		let uv = self.vertex(face, vert).uv;
+		[uv[0], 1. - uv[1]]
+	}
+
+	// Vertices shared between faces keep whichever tangent was written last, which only differs where mikktspace would have split the vertex
+	fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+		let index = self.indices[face * 3 + vert] as usize;
+		self.vertices[index].tangent = tangent;
+	}
+}
+
+// Uses u16 indices whenever every index fits, halving the index bandwidth of typical submeshes
+fn pack_indices(indices: &[u32]) -> (Vec<u8>, wgpu::IndexFormat) {
+	// 0xFFFF is reserved as the primitive restart value, so it can't be used as an index
+	if indices.iter().all(|index| *index < u16::MAX as u32) {
+		let mut short_indices = indices.iter().map(|index| *index as u16).collect::<Vec<_>>();
+
+		// Buffer writes must be a multiple of 4 bytes, so an odd number of u16 indices gets an unused one appended
+		if short_indices.len() % 2 == 1 {
+			short_indices.push(0);
+		}
+
+		(bytemuck::cast_slice(&short_indices).to_vec(), wgpu::IndexFormat::Uint16)
+	} else {
+		(bytemuck::cast_slice(indices).to_vec(), wgpu::IndexFormat::Uint32)
+	}
+}
+
+// Copies the positions of each triangle for the collision world, which works on triangles rather than indexed vertices
+pub fn collision_triangles(vertices: &[ModelVertex], indices: &[u32]) -> Vec<[[f32; 3]; 3]> {
+	indices.chunks_exact(3).map(|triangle| [0, 1, 2].map(|corner| vertices[triangle[corner] as usize].position)).collect()
+}
+
+// The center and half the size of the axis-aligned box enclosing every vertex
+pub fn bounding_box(vertices: &[ModelVertex]) -> ([f32; 3], [f32; 3]) {
+	if vertices.is_empty() {
+		return ([0.; 3], [0.; 3]);
+	}
+
+	let (min, max) = vertices.iter().fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), vertex| {
+		let p = vertex.position;
+		([min[0].min(p[0]), min[1].min(p[1]), min[2].min(p[2])], [max[0].max(p[0]), max[1].max(p[1]), max[2].max(p[2])])
+	});
+
+	([0, 1, 2].map(|axis| (min[axis] + max[axis]) * 0.5), [0, 1, 2].map(|axis| (max[axis] - min[axis]) * 0.5))
+}
+
+// Centered on the bounding box rather than the optimal sphere, which is cheap and close enough for culling
+pub fn bounding_sphere(vertices: &[ModelVertex]) -> ([f32; 3], f32) {
+	if vertices.is_empty() {
+		return ([0.; 3], 0.);
+	}
+
+	let (center, _) = bounding_box(vertices);
+
+	let radius_squared = vertices
+		.iter()
+		.map(|vertex| {
+			let p = vertex.position;
+			(p[0] - center[0]).powi(2) + (p[1] - center[1]).powi(2) + (p[2] - center[2]).powi(2)
+		})
+		.fold(0., f32::max);
+
+	(center, radius_squared.sqrt())
+}
+
+fn grown_capacity(length: usize) -> u32 {
+	(length as u32).max(1).next_power_of_two()
+}
+
+fn create_dynamic_buffer<T>(device: &wgpu::Device, label: &str, capacity: u32, usage: wgpu::BufferUsages) -> wgpu::Buffer {
+	device.create_buffer(&wgpu::BufferDescriptor {
+		label: Some(label),
+		size: (capacity as usize * mem::size_of::<T>()) as wgpu::BufferAddress,
+		usage: usage | wgpu::BufferUsages::COPY_DST,
+		mapped_at_creation: false,
+	})
 }
 
 pub trait Vertex {
@@ -182,7 +605,8 @@ pub struct ModelVertex {
 	pub position: [f32; 3],
 	pub uv: [f32; 2],
 	pub normal: [f32; 3],
-	pub tangent: [f32; 3],
+	// The 4th component is the bitangent sign (handedness), following the mikktspace convention
+	pub tangent: [f32; 4],
 }
 
 impl Vertex for ModelVertex {
@@ -213,8 +637,116 @@ impl Vertex for ModelVertex {
 				wgpu::VertexAttribute {
 					offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
 					shader_location: 3,
+					format: wgpu::VertexFormat::Float32x4,
+				},
+			],
+		}
+	}
+}
+
+// Selects which of a mesh's vertex buffers, and the matching layout, a pipeline reads from
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VertexFormat {
+	// Every attribute at full precision
+	Model,
+	// Just the position, for passes that only need coverage or depth
+	PositionOnly,
+	// Full precision position with half precision UVs and 8-bit normals and tangents, half the size of `Model`
+	Packed,
+}
+
+impl VertexFormat {
+	pub fn layout<'a>(&self) -> wgpu::VertexBufferLayout<'a> {
+		match self {
+			VertexFormat::Model => ModelVertex::layout(),
+			VertexFormat::PositionOnly => PositionVertex::layout(),
+			VertexFormat::Packed => PackedVertex::layout(),
+		}
+	}
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PositionVertex {
+	pub position: [f32; 3],
+}
+
+impl From<&ModelVertex> for PositionVertex {
+	fn from(vertex: &ModelVertex) -> Self {
+		Self { position: vertex.position }
+	}
+}
+
+impl Vertex for PositionVertex {
+	fn layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+		wgpu::VertexBufferLayout {
+			array_stride: mem::size_of::<PositionVertex>() as wgpu::BufferAddress,
+			step_mode: wgpu::VertexStepMode::Vertex,
+			attributes: &[
+				// position
+				wgpu::VertexAttribute {
+					offset: 0,
+					shader_location: 0,
+					format: wgpu::VertexFormat::Float32x3,
+				},
+			],
+		}
+	}
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PackedVertex {
+	pub position: [f32; 3],
+	pub uv: [f16; 2],
+	// Signed normalized, with the 4th component unused for the normal and holding the handedness for the tangent
+	pub normal: [i8; 4],
+	pub tangent: [i8; 4],
+}
+
+impl From<&ModelVertex> for PackedVertex {
+	fn from(vertex: &ModelVertex) -> Self {
+		let snorm = |value: f32| (value.clamp(-1., 1.) * 127.).round() as i8;
+
+		Self {
+			position: vertex.position,
+			uv: vertex.uv.map(f16::from_f32),
+			normal: [snorm(vertex.normal[0]), snorm(vertex.normal[1]), snorm(vertex.normal[2]), 0],
+			tangent: vertex.tangent.map(snorm),
+		}
+	}
+}
+
+impl Vertex for PackedVertex {
+	fn layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+		wgpu::VertexBufferLayout {
+			array_stride: mem::size_of::<PackedVertex>() as wgpu::BufferAddress,
+			step_mode: wgpu::VertexStepMode::Vertex,
+			attributes: &[
+				// position
+				wgpu::VertexAttribute {
+					offset: 0,
+					shader_location: 0,
 					format: wgpu::VertexFormat::Float32x3,
 				},
+				// uv
+				wgpu::VertexAttribute {
+					offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+					shader_location: 1,
+					format: wgpu::VertexFormat::Float16x2,
+				},
+				// normal
+				wgpu::VertexAttribute {
+					offset: (mem::size_of::<[f32; 3]>() + mem::size_of::<[f16; 2]>()) as wgpu::BufferAddress,
+					shader_location: 2,
+					format: wgpu::VertexFormat::Snorm8x4,
+				},
+				// tangent
+				wgpu::VertexAttribute {
+					offset: (mem::size_of::<[f32; 3]>() + mem::size_of::<[f16; 2]>() + mem::size_of::<[i8; 4]>()) as wgpu::BufferAddress,
+					shader_location: 3,
+					format: wgpu::VertexFormat::Snorm8x4,
+				},
 			],
 		}
 	}
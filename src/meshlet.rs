@@ -0,0 +1,274 @@
+use crate::context::Context;
+use crate::draw_list::DrawItem;
+use crate::hi_z::HiZPyramid;
+use crate::material::{Material, MaterialDataBinding};
+use crate::mesh::{bounding_sphere, ModelVertex};
+use crate::model::Mobility;
+use crate::scene::Scene;
+use crate::shader::{CAMERA_BIND_GROUP, GLOBALS_BIND_GROUP, LIGHTING_BIND_GROUP, MATERIAL_BIND_GROUP};
+
+use cgmath::Vector3;
+use std::mem;
+use wgpu::util::DeviceExt;
+use wgpu::BufferBinding;
+
+// Kept small so each cluster's bounds are tight enough to be culled on their own
+const MAX_MESHLET_TRIANGLES: usize = 64;
+const MAX_MESHLET_VERTICES: usize = 64;
+
+// Must match the workgroup size in `compute_meshlet_culling.wgsl`
+const CULLING_WORKGROUP_SIZE: u32 = 64;
+
+// A cluster of triangles covering a contiguous range of its mesh's index buffer
+#[derive(Debug, Copy, Clone)]
+pub struct Meshlet {
+	pub first_index: u32,
+	pub index_count: u32,
+	// Object space sphere enclosing the cluster's vertices
+	pub bounds_center: [f32; 3],
+	pub bounds_radius: f32,
+}
+
+// Greedily groups consecutive triangles until a cluster runs out of room for triangles or unique vertices, which keeps the index buffer untouched
+pub fn build_meshlets(vertices: &[ModelVertex], indices: &[u32]) -> Vec<Meshlet> {
+	let mut meshlets = Vec::new();
+	let mut first_index = 0;
+	let mut meshlet_vertices: Vec<u32> = Vec::with_capacity(MAX_MESHLET_VERTICES);
+
+	for (triangle_index, triangle) in indices.chunks_exact(3).enumerate() {
+		let new_vertex_count = triangle.iter().filter(|index| !meshlet_vertices.contains(index)).count();
+		let triangle_count = (triangle_index * 3 - first_index) / 3;
+
+		if triangle_count == MAX_MESHLET_TRIANGLES || meshlet_vertices.len() + new_vertex_count > MAX_MESHLET_VERTICES {
+			meshlets.push(new_meshlet(vertices, &meshlet_vertices, first_index, triangle_index * 3));
+			first_index = triangle_index * 3;
+			meshlet_vertices.clear();
+		}
+
+		for index in triangle {
+			if !meshlet_vertices.contains(index) {
+				meshlet_vertices.push(*index);
+			}
+		}
+	}
+
+	let end_index = indices.len() - indices.len() % 3;
+	if end_index > first_index {
+		meshlets.push(new_meshlet(vertices, &meshlet_vertices, first_index, end_index));
+	}
+
+	meshlets
+}
+
+fn new_meshlet(vertices: &[ModelVertex], meshlet_vertices: &[u32], first_index: usize, end_index: usize) -> Meshlet {
+	let cluster_vertices = meshlet_vertices.iter().map(|index| vertices[*index as usize]).collect::<Vec<_>>();
+	let (bounds_center, bounds_radius) = bounding_sphere(&cluster_vertices);
+
+	Meshlet {
+		first_index: first_index as u32,
+		index_count: (end_index - first_index) as u32,
+		bounds_center,
+		bounds_radius,
+	}
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct MeshletCullingSettings {
+	instance_count: u32,
+	meshlet_count: u32,
+	occlusion_culling: u32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct MeshletRaw {
+	// World space bounding sphere with the radius in the last component
+	bounds: [f32; 4],
+	first_index: u32,
+	index_count: u32,
+	_padding: [u32; 2],
+}
+
+// Laid out as the arguments of `draw_indexed_indirect()`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct DrawIndexedIndirect {
+	index_count: u32,
+	instance_count: u32,
+	first_index: u32,
+	base_vertex: i32,
+	first_instance: u32,
+}
+
+// The GPU side of one draw item's meshlets: their world space bounds, the indirect draws the culling shader fills in, and the material binding both
+pub struct MeshletDraw {
+	pub draw_buffer: wgpu::Buffer,
+	pub meshlet_count: u32,
+	settings_buffer: wgpu::Buffer,
+	settings: MeshletCullingSettings,
+	_meshlets_buffer: wgpu::Buffer,
+	culling_material: Material,
+}
+
+impl MeshletDraw {
+	// Offset into `draw_buffer` of the indirect draw arguments for the given meshlet
+	pub fn draw_offset(meshlet_index: u32) -> wgpu::BufferAddress {
+		meshlet_index as wgpu::BufferAddress * mem::size_of::<DrawIndexedIndirect>() as wgpu::BufferAddress
+	}
+}
+
+// Draws static models as GPU culled clusters, rebuilding the per-draw buffers whenever the deferred pass's draw list or the Hi-Z pyramid is replaced
+pub struct MeshletRenderer {
+	generation: Option<u64>,
+	// Indexed like the deferred pass's draw list, with `None` for items that take the regular path
+	draws: Vec<Option<MeshletDraw>>,
+}
+
+impl MeshletRenderer {
+	pub fn new() -> Self {
+		Self { generation: None, draws: vec![] }
+	}
+
+	// Forces a rebuild on the next refresh, needed when the Hi-Z pyramid bound by the culling materials is recreated
+	pub fn invalidate(&mut self) {
+		self.generation = None;
+	}
+
+	pub fn refresh(&mut self, device: &wgpu::Device, scene: &Scene, draw_items: &[DrawItem], hi_z_pyramid: &HiZPyramid, camera_buffer: &wgpu::Buffer) {
+		if self.generation == Some(scene.generation()) {
+			return;
+		}
+
+		self.draws = draw_items.iter().map(|draw_item| create_meshlet_draw(device, scene, draw_item, hi_z_pyramid, camera_buffer)).collect();
+		self.generation = Some(scene.generation());
+	}
+
+	pub fn draw(&self, draw_item_index: usize) -> Option<&MeshletDraw> {
+		self.draws.get(draw_item_index).and_then(Option::as_ref)
+	}
+
+	// Records the culling of every meshlet, which must happen after the Hi-Z pyramid is generated and before the deferred pass draws
+	pub fn record_culling(&self, encoder: &mut wgpu::CommandEncoder, context: &Context, scene: &Scene, occlusion_culling: bool) {
+		let shader = &scene.resources.shaders["compute_meshlet_culling.wgsl"];
+		let pipeline = match &shader.pipeline {
+			crate::shader::PipelineType::RenderPipeline(_) => return,
+			crate::shader::PipelineType::ComputePipeline(compute_pipeline) => compute_pipeline,
+		};
+
+		for meshlet_draw in self.draws.iter().flatten() {
+			let settings = MeshletCullingSettings {
+				occlusion_culling: occlusion_culling as u32,
+				..meshlet_draw.settings
+			};
+			context.queue.write_buffer(&meshlet_draw.settings_buffer, 0, bytemuck::cast_slice(&[settings]));
+		}
+
+		let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+			label: Some("Compute: Cull Meshlets"),
+		});
+		compute_pass.set_pipeline(pipeline);
+		compute_pass.set_bind_group(GLOBALS_BIND_GROUP, &context.globals.globals_bind_group, &[]);
+		compute_pass.set_bind_group(CAMERA_BIND_GROUP, &context.placeholder_bind_group, &[]);
+		compute_pass.set_bind_group(LIGHTING_BIND_GROUP, &context.placeholder_bind_group, &[]);
+
+		for meshlet_draw in self.draws.iter().flatten() {
+			compute_pass.set_bind_group(MATERIAL_BIND_GROUP, &meshlet_draw.culling_material.bind_group, &[]);
+			compute_pass.dispatch(meshlet_draw.meshlet_count.div_ceil(CULLING_WORKGROUP_SIZE), 1, 1);
+		}
+	}
+}
+
+// Only static models with a single instance take the meshlet path, since their world space meshlet bounds can be computed once up front
+fn create_meshlet_draw(device: &wgpu::Device, scene: &Scene, draw_item: &DrawItem, hi_z_pyramid: &HiZPyramid, camera_buffer: &wgpu::Buffer) -> Option<MeshletDraw> {
+	let model = draw_item.model(scene);
+	let mesh = &scene.resources.meshes[draw_item.mesh];
+	if model.mobility != Mobility::Static || model.instances.instance_list.len() != 1 || mesh.meshlets.is_empty() {
+		return None;
+	}
+
+	let instance = model.instances.world_instances().next().unwrap();
+	let scale = instance.scale;
+	let max_scale = scale.x.abs().max(scale.y.abs()).max(scale.z.abs());
+
+	let meshlets = mesh
+		.meshlets
+		.iter()
+		.map(|meshlet| {
+			let center = Vector3::from(meshlet.bounds_center);
+			let world_center = instance.location + instance.rotation * Vector3::new(center.x * scale.x, center.y * scale.y, center.z * scale.z);
+
+			MeshletRaw {
+				bounds: [world_center.x, world_center.y, world_center.z, meshlet.bounds_radius * max_scale],
+				first_index: meshlet.first_index,
+				index_count: meshlet.index_count,
+				_padding: [0; 2],
+			}
+		})
+		.collect::<Vec<_>>();
+	let meshlet_count = meshlets.len() as u32;
+
+	let settings = MeshletCullingSettings {
+		instance_count: 1,
+		meshlet_count,
+		occlusion_culling: 0,
+		_padding: 0,
+	};
+	let settings_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+		label: Some(&format!("{} Meshlet Culling Settings Buffer", mesh.name)),
+		contents: bytemuck::cast_slice(&[settings]),
+		usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+	});
+	let meshlets_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+		label: Some(&format!("{} Meshlets Buffer", mesh.name)),
+		contents: bytemuck::cast_slice(&meshlets),
+		usage: wgpu::BufferUsages::STORAGE,
+	});
+	let draw_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+		label: Some(&format!("{} Meshlet Draw Buffer", mesh.name)),
+		size: MeshletDraw::draw_offset(meshlet_count),
+		usage: wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::STORAGE,
+		mapped_at_creation: false,
+	});
+
+	let culling_material = Material::new(
+		&format!("{} Meshlet Culling", mesh.name),
+		"compute_meshlet_culling.wgsl",
+		vec![
+			MaterialDataBinding::Buffer(BufferBinding {
+				buffer: camera_buffer,
+				offset: 0,
+				size: None,
+			}),
+			MaterialDataBinding::Texture(&hi_z_pyramid.texture),
+			MaterialDataBinding::Buffer(BufferBinding {
+				buffer: &settings_buffer,
+				offset: 0,
+				size: None,
+			}),
+			MaterialDataBinding::Buffer(BufferBinding {
+				buffer: &meshlets_buffer,
+				offset: 0,
+				size: None,
+			}),
+			MaterialDataBinding::Buffer(BufferBinding {
+				buffer: &draw_buffer,
+				offset: 0,
+				size: None,
+			}),
+		],
+		&scene.resources,
+		device,
+	);
+
+	Some(MeshletDraw {
+		draw_buffer,
+		meshlet_count,
+		settings_buffer,
+		settings,
+		_meshlets_buffer: meshlets_buffer,
+		culling_material,
+	})
+}
@@ -0,0 +1,87 @@
+use crate::camera::{Camera, CameraUniform, PerspectiveProjection, Projection};
+use crate::context::Context;
+use crate::frame_texture::FrameTexture;
+use crate::transform::Transform;
+
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, SquareMatrix, Vector3, Vector4};
+
+// Moves the clipping plane slightly in front of the mirror so geometry only touching it doesn't leak into the reflection
+const CLIP_PLANE_OFFSET: f32 = 0.01;
+
+// A planar reflector that renders the scene as seen by the viewing camera mirrored across its plane, which its model's material then samples
+#[derive(Debug)]
+pub struct Mirror {
+	// Facing direction of the plane in the entity's local space, with the plane passing through the entity's location
+	pub normal: Vector3<f64>,
+	// How many levels of mirrors seen inside this mirror's reflection are themselves rendered, with 0 leaving every mirror out of it
+	pub max_recursion_depth: u32,
+	// The reflection is skipped while the viewing camera is behind the plane
+	pub faces_view: bool,
+	pub camera: Camera,
+	pub color_map: FrameTexture,
+	pub depth_map: FrameTexture,
+	// Copy of the last finished reflection, which is what the mirror's material samples so mirrors can appear inside reflections still being rendered
+	pub reflection_map: FrameTexture,
+}
+
+impl Mirror {
+	// The resolution of the reflection is independent of the window size, so distant or small mirrors can be rendered cheaply
+	pub fn new(context: &Context, normal: Vector3<f64>, resolution: (u32, u32), max_recursion_depth: u32) -> Self {
+		// The projection is replaced every frame by the viewing camera's
+		let projection = PerspectiveProjection::new(resolution.0, resolution.1, cgmath::Deg(45.0), 0.1, 50.0);
+		let mut camera = Camera::new(context, Projection::Perspective(projection));
+		// Reflections are shaded in a single forward pass and then seen through the viewing camera's own post-processing, so applying any here would double it up
+		camera.post_effects.clear();
+
+		let color_map = FrameTexture::with_size(&context.device, resolution, wgpu::TextureFormat::Rgba16Float, "Mirror Color Map frame texture", None);
+		let depth_map = FrameTexture::with_size(
+			&context.device,
+			resolution,
+			wgpu::TextureFormat::Depth32Float,
+			"Mirror Z-buffer frame texture",
+			Some(wgpu::CompareFunction::LessEqual),
+		);
+		let reflection_map = FrameTexture::with_size(&context.device, resolution, wgpu::TextureFormat::Rgba16Float, "Mirror Reflection Map frame texture", None);
+
+		Self {
+			normal,
+			max_recursion_depth,
+			faces_view: false,
+			camera,
+			color_map,
+			depth_map,
+			reflection_map,
+		}
+	}
+
+	pub fn update_reflection_camera(&mut self, transform: &Transform, view_camera: &CameraUniform, queue: &wgpu::Queue) {
+		let rotation = cgmath::Quaternion::new(transform.rotation.s as f32, transform.rotation.v.x as f32, transform.rotation.v.y as f32, transform.rotation.v.z as f32);
+		let normal = (rotation * Vector3::new(self.normal.x as f32, self.normal.y as f32, self.normal.z as f32)).normalize();
+		let location = Vector3::new(transform.location.x as f32, transform.location.y as f32, transform.location.z as f32);
+		let plane = normal.extend(-normal.dot(location));
+
+		let v = view_camera.v_matrix();
+		let eye = v.invert().unwrap().w;
+		self.faces_view = plane.dot(eye) > 0.;
+		if !self.faces_view {
+			return;
+		}
+
+		// Reflecting the world before viewing it puts the reflection camera behind the plane, looking back through it
+		let (n, d) = (normal, plane.w);
+		let reflection = Matrix4::from_cols(
+			Vector4::new(1. - 2. * n.x * n.x, -2. * n.x * n.y, -2. * n.x * n.z, 0.),
+			Vector4::new(-2. * n.x * n.y, 1. - 2. * n.y * n.y, -2. * n.y * n.z, 0.),
+			Vector4::new(-2. * n.x * n.z, -2. * n.y * n.z, 1. - 2. * n.z * n.z, 0.),
+			(n * -2. * d).extend(1.),
+		);
+		let reflected_v = v * reflection;
+		let reflected_eye = Point3::from_vec((reflection * eye).truncate());
+
+		// Everything behind the mirror would otherwise be drawn in front of the reflection, so it's clipped away by the mirror plane
+		// Unlike an oblique near plane, this leaves the depth precision and the frustum's far plane as they are
+		self.camera.clip_plane = Some(Vector4::new(n.x, n.y, n.z, d - CLIP_PLANE_OFFSET));
+
+		self.camera.update_from_matrices(reflected_eye, reflected_v, view_camera.p_matrix(), queue);
+	}
+}
@@ -1,11 +1,48 @@
-use crate::{instance::Instances, scene::LoadedResources};
+use crate::instance::Instances;
+use crate::scene::{LoadedResources, MaterialHandle, MeshHandle};
+
+use cgmath::{EuclideanSpace, InnerSpace, Point3};
+
+// Static models are assumed to never move after the scene is built, so per-frame work like voxel GI re-voxelization, shadow map redraws, and draw list culling bounds can be skipped for them
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mobility {
+	Static,
+	Movable,
+}
 
 #[derive(Debug)]
 pub struct Model {
 	pub mesh_name: (String, String),
-	pub mesh: Option<usize>,
-	pub voxel_lightmap_material: Option<usize>,
-	pub scene_deferred_material: Option<usize>,
+	pub mobility: Mobility,
+	pub mesh: Option<MeshHandle>,
+	pub voxel_lightmap_material: Option<MaterialHandle>,
+	pub scene_deferred_material: Option<MaterialHandle>,
+	// Depth-only material drawing the model into the shadow maps, cut out and culled to match its deferred material
+	pub scene_shadow_material: Option<MaterialHandle>,
+	// Left out of the shadow maps when off, for things like small props whose shadows aren't worth drawing or fixtures that shouldn't block the light
+	pub casts_shadows: bool,
+	// Transparent models skip the deferred and voxel passes and are drawn with the named transparent surface instead
+	pub transparent_surface: Option<String>,
+	pub transparent_sorted_material: Option<MaterialHandle>,
+	pub transparent_weighted_material: Option<MaterialHandle>,
+	pub glass_material: Option<MaterialHandle>,
+	// Used to draw the model inside mirror reflections, which are lit forward rather than through the deferred passes
+	pub reflection_material: Option<MaterialHandle>,
+	// Mirror models skip the deferred pass and instead show the reflection rendered by the `Mirror` component on the named entity
+	pub mirror_surface: Option<String>,
+	pub mirror_material: Option<MaterialHandle>,
+	// Portal models skip the deferred pass and instead show the view through the `Portal` component on the named entity
+	pub portal_surface: Option<String>,
+	pub portal_material: Option<MaterialHandle>,
+	// Beyond this distance from the camera, the model swaps to a billboard of its mesh's impostor atlas baked at load time
+	pub impostor_distance: Option<f32>,
+	pub impostor_material: Option<MaterialHandle>,
+	// Models in a render layer are left out of every world pass and only drawn, lit forward, by the layer with this name
+	pub layer: Option<String>,
+	// Used by the views that are lit forward and seen directly rather than mirrored, which are render layers and the views through portals
+	pub forward_material: Option<MaterialHandle>,
+	// Names a material built from a material file to use as the forward material in place of the one made for the mesh
+	pub forward_material_file: Option<String>,
 	pub instances: Instances,
 }
 
@@ -13,18 +50,100 @@ impl Model {
 	pub fn new(mesh: (&str, &str)) -> Self {
 		Self {
 			mesh_name: (String::from(mesh.0), String::from(mesh.1)),
+			mobility: Mobility::Movable,
 			mesh: None,
 			voxel_lightmap_material: None,
 			scene_deferred_material: None,
+			scene_shadow_material: None,
+			casts_shadows: true,
+			transparent_surface: None,
+			transparent_sorted_material: None,
+			transparent_weighted_material: None,
+			glass_material: None,
+			reflection_material: None,
+			mirror_surface: None,
+			mirror_material: None,
+			portal_surface: None,
+			portal_material: None,
+			impostor_distance: None,
+			impostor_material: None,
+			layer: None,
+			forward_material: None,
+			forward_material_file: None,
 			instances: Instances::new(),
 		}
 	}
 
+	// Whether the shadow maps darken the model, which is stored with its instances since the G-buffer pass passes it on to the shading pass per pixel
+	pub fn set_receives_shadows(&mut self, receives_shadows: bool, device: &wgpu::Device) {
+		if self.instances.receives_shadows != receives_shadows {
+			self.instances.receives_shadows = receives_shadows;
+			self.instances.update_buffer(device);
+		}
+	}
+
+	// Weights beyond the number of morph targets on the mesh are ignored by the shader
+	pub fn set_morph_weights(&mut self, weights: &[f32], device: &wgpu::Device) {
+		self.instances.set_morph_weights(weights, device);
+	}
+
 	pub fn load(&mut self, resources: &LoadedResources) {
-		self.mesh = Some(resources.meshes.get_index_of(&(self.mesh_name.0.clone(), self.mesh_name.1.clone())).unwrap());
+		self.mesh = Some(resources.meshes.handle(&(self.mesh_name.0.clone(), self.mesh_name.1.clone())).unwrap());
 		let voxel_material_name = format!("calc_voxel_lightmap_{}.material", self.mesh_name.1);
-		self.voxel_lightmap_material = Some(resources.materials.get_index_of(&voxel_material_name).unwrap());
+		self.voxel_lightmap_material = Some(resources.materials.handle(&voxel_material_name).unwrap());
 		let scene_deferred_material_name = format!("scene_deferred_{}.material", self.mesh_name.1);
-		self.scene_deferred_material = Some(resources.materials.get_index_of(&scene_deferred_material_name).unwrap());
+		self.scene_deferred_material = Some(resources.materials.handle(&scene_deferred_material_name).unwrap());
+		let scene_shadow_material_name = format!("scene_shadow_{}.material", self.mesh_name.1);
+		self.scene_shadow_material = Some(resources.materials.handle(&scene_shadow_material_name).unwrap());
+		let reflection_material_name = format!("scene_reflection_{}.material", self.mesh_name.1);
+		self.reflection_material = Some(resources.materials.handle(&reflection_material_name).unwrap());
+		match &self.forward_material_file {
+			Some(material_file) => {
+				let forward_material = resources.materials.handle(material_file);
+				self.forward_material =
+					Some(forward_material.unwrap_or_else(|| panic!("The material file '{}' used by model '{}:{}' is not loaded", material_file, self.mesh_name.0, self.mesh_name.1)));
+			}
+			None => {
+				let forward_material_name = format!("scene_forward_{}.material", self.mesh_name.1);
+				self.forward_material = Some(resources.materials.handle(&forward_material_name).unwrap());
+			}
+		}
+
+		if self.impostor_distance.is_some() {
+			let impostor_material_name = format!("scene_impostor_{}.material", self.mesh_name.1);
+			self.impostor_material = Some(resources.materials.handle(&impostor_material_name).unwrap());
+		}
+
+		if let Some(mirror) = &self.mirror_surface {
+			let mirror_material_name = format!("scene_mirror_{}.material", mirror);
+			let mirror_material = resources.materials.handle(&mirror_material_name);
+			self.mirror_material = Some(mirror_material.unwrap_or_else(|| panic!("The mirror '{}' used by model '{}:{}' is not loaded", mirror, self.mesh_name.0, self.mesh_name.1)));
+		}
+
+		if let Some(portal) = &self.portal_surface {
+			let portal_material_name = format!("scene_portal_{}.material", portal);
+			let portal_material = resources.materials.handle(&portal_material_name);
+			self.portal_material = Some(portal_material.unwrap_or_else(|| panic!("The portal '{}' used by model '{}:{}' is not loaded", portal, self.mesh_name.0, self.mesh_name.1)));
+		}
+
+		// Glass surfaces only have a glass material and the other transparent surfaces only have the sorted and weighted ones
+		if let Some(surface) = &self.transparent_surface {
+			let material = |kind: &str| resources.materials.handle(&format!("scene_transparent_{}_{}.material", kind, surface));
+			self.transparent_sorted_material = material("sorted");
+			self.transparent_weighted_material = material("weighted");
+			self.glass_material = material("glass");
+
+			if self.transparent_sorted_material.is_none() && self.glass_material.is_none() {
+				panic!("The transparent surface '{}' used by model '{}:{}' is not loaded", surface, self.mesh_name.0, self.mesh_name.1);
+			}
+		}
+	}
+
+	// The impostor is only used once every instance is beyond the distance, since all the instances are drawn together
+	pub fn uses_impostor(&self, eye: Point3<f32>) -> bool {
+		match self.impostor_distance {
+			Some(distance) => self.instances.world_instances().all(|instance| (instance.location - eye.to_vec()).magnitude() > distance),
+			None => false,
+		}
 	}
 }
@@ -0,0 +1,90 @@
+use wgpu::util::DeviceExt;
+
+// Must match the two `morph_weights` vec4 instance attributes read by `scene_deferred.wgsl`
+pub const MAX_MORPH_TARGETS: usize = 8;
+
+// Per-vertex offsets from the base mesh, blended in the vertex shader by the weights stored on each `Instance`
+#[derive(Debug, Clone)]
+pub struct MorphTarget {
+	pub name: String,
+	pub position_deltas: Vec<[f32; 3]>,
+	pub normal_deltas: Vec<[f32; 3]>,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct MorphTargetsHeader {
+	vertex_count: u32,
+	target_count: u32,
+	// The deltas array that follows the header is aligned to 16 bytes in WGSL
+	_padding: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct MorphDeltaRaw {
+	position: [f32; 4],
+	normal: [f32; 4],
+}
+
+pub struct MorphTargets {
+	pub target_names: Vec<String>,
+	pub vertex_count: u32,
+	// Read-only storage buffer laid out as a header followed by `target_count` blocks of `vertex_count` deltas
+	pub buffer: wgpu::Buffer,
+}
+
+impl MorphTargets {
+	pub fn new(device: &wgpu::Device, mesh_name: &str, vertex_count: u32, targets: &[MorphTarget]) -> Self {
+		let targets = &targets[..targets.len().min(MAX_MORPH_TARGETS)];
+
+		let header = MorphTargetsHeader {
+			vertex_count,
+			target_count: targets.len() as u32,
+			_padding: [0; 2],
+		};
+
+		let mut deltas = targets
+			.iter()
+			.flat_map(|target| {
+				(0..vertex_count as usize).map(move |i| {
+					let position = target.position_deltas.get(i).copied().unwrap_or_default();
+					let normal = target.normal_deltas.get(i).copied().unwrap_or_default();
+
+					MorphDeltaRaw {
+						position: [position[0], position[1], position[2], 0.],
+						normal: [normal[0], normal[1], normal[2], 0.],
+					}
+				})
+			})
+			.collect::<Vec<_>>();
+
+		// A storage binding can't be empty, so meshes without morph targets still get a single zeroed delta
+		if deltas.is_empty() {
+			deltas.push(MorphDeltaRaw { position: [0.; 4], normal: [0.; 4] });
+		}
+
+		let mut contents = bytemuck::bytes_of(&header).to_vec();
+		contents.extend_from_slice(bytemuck::cast_slice(&deltas));
+
+		let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some(&format!("{} Morph Targets Buffer", mesh_name)),
+			contents: contents.as_slice(),
+			usage: wgpu::BufferUsages::STORAGE,
+		});
+
+		Self {
+			target_names: targets.iter().map(|target| target.name.clone()).collect(),
+			vertex_count,
+			buffer,
+		}
+	}
+
+	pub fn empty(device: &wgpu::Device, mesh_name: &str) -> Self {
+		Self::new(device, mesh_name, 0, &[])
+	}
+
+	pub fn index_of(&self, target_name: &str) -> Option<usize> {
+		self.target_names.iter().position(|name| name == target_name)
+	}
+}
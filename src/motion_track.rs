@@ -0,0 +1,180 @@
+use crate::json::{JsonParser, JsonValue};
+
+use anyhow::{anyhow, bail, Result};
+use cgmath::{InnerSpace, Point3, Quaternion};
+use std::path::Path;
+
+// One tracked sample, with rotation left out by tracks that only record positions
+#[derive(Debug, Copy, Clone)]
+pub struct MotionKeyframe {
+	pub time: f64,
+	pub location: Point3<f64>,
+	pub rotation: Option<Quaternion<f64>>,
+}
+
+// Timestamped positions and rotations recorded by an external tracker, such as a motion capture rig or a phone's camera tracking, for replaying on an entity
+// Two formats are read, chosen by file extension:
+// - CSV with one keyframe per row, either in the column order `time, x, y, z[, qw, qx, qy, qz]` or in any order named by a header row using those names
+// - JSON holding an array of keyframes, bare or under a "keyframes" key, each an object like `{ "time": 0.5, "location": [x, y, z], "rotation": [w, x, y, z] }`
+#[derive(Debug, Clone)]
+pub struct MotionTrack {
+	// Sorted by time
+	pub keyframes: Vec<MotionKeyframe>,
+}
+
+impl MotionTrack {
+	pub fn load(directory: &Path, file: &str) -> Result<Self> {
+		let path = directory.join("tracks").join(file);
+		let text = std::fs::read_to_string(&path)?;
+
+		match path.extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_ascii_lowercase()).as_deref() {
+			Some("csv") => Self::parse_csv(&text),
+			Some("json") => Self::parse_json(&text),
+			_ => bail!("Motion track \"{}\" must be a .csv or .json file", file),
+		}
+	}
+
+	pub fn parse_csv(text: &str) -> Result<Self> {
+		let mut rows = text.lines().map(|line| line.trim()).filter(|line| !line.is_empty() && !line.starts_with('#')).peekable();
+
+		// A header row is told apart from the data by not starting with a number
+		let has_header = rows.peek().is_some_and(|first| first.split(',').next().unwrap_or_default().trim().parse::<f64>().is_err());
+		let columns = match rows.next_if(|_| has_header) {
+			Some(header) => header.split(',').map(|name| name.trim().to_ascii_lowercase()).collect::<Vec<_>>(),
+			None => ["time", "x", "y", "z", "qw", "qx", "qy", "qz"].map(String::from).to_vec(),
+		};
+		let column = |name: &str| columns.iter().position(|column| column == name);
+
+		let missing = |name: &str| anyhow!("Motion track CSV has no \"{}\" column", name);
+		let time_column = column("time").or_else(|| column("t")).ok_or_else(|| missing("time"))?;
+		let location_columns = [
+			column("x").ok_or_else(|| missing("x"))?,
+			column("y").ok_or_else(|| missing("y"))?,
+			column("z").ok_or_else(|| missing("z"))?,
+		];
+		let rotation_columns = match (column("qw"), column("qx"), column("qy"), column("qz")) {
+			(Some(w), Some(x), Some(y), Some(z)) => Some([w, x, y, z]),
+			_ => None,
+		};
+
+		let keyframes = rows
+			.enumerate()
+			.map(|(row_index, row)| {
+				let values = row.split(',').map(|value| value.trim()).collect::<Vec<_>>();
+				let value = |index: usize| -> Result<f64> {
+					let value = values.get(index).ok_or_else(|| anyhow!("Motion track CSV row {} is missing a value", row_index + 1))?;
+					value.parse::<f64>().map_err(|_| anyhow!("Motion track CSV has a malformed number \"{}\"", value))
+				};
+
+				// Rows from a tracker that lost sight of its target may leave the rotation blank, which is then only interpolated over
+				let rotation = match rotation_columns {
+					Some([w, x, y, z]) if values.get(w).is_some_and(|value| !value.is_empty()) => Some(Quaternion::new(value(w)?, value(x)?, value(y)?, value(z)?)),
+					_ => None,
+				};
+
+				Ok(MotionKeyframe {
+					time: value(time_column)?,
+					location: Point3::new(value(location_columns[0])?, value(location_columns[1])?, value(location_columns[2])?),
+					rotation,
+				})
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		Self::from_keyframes(keyframes)
+	}
+
+	pub fn parse_json(text: &str) -> Result<Self> {
+		Self::from_json(&JsonParser::new(text).parse_document()?)
+	}
+
+	// Also used for the keyframes of tracks embedded in other assets, like timelines
+	pub fn from_json(root: &JsonValue) -> Result<Self> {
+		let keyframes = match root {
+			JsonValue::Array(keyframes) => keyframes,
+			JsonValue::Object(_) => match root.get("keyframes") {
+				Some(JsonValue::Array(keyframes)) => keyframes,
+				_ => bail!("Motion track JSON has no \"keyframes\" array"),
+			},
+			_ => bail!("Motion track JSON must be an array of keyframes"),
+		};
+
+		let keyframes = keyframes
+			.iter()
+			.map(|keyframe| {
+				let time = keyframe
+					.get("time")
+					.and_then(JsonValue::as_number)
+					.ok_or_else(|| anyhow!("Motion track JSON keyframe is missing its time"))?;
+				let location = keyframe
+					.get("location")
+					.and_then(JsonValue::as_numbers::<3>)
+					.ok_or_else(|| anyhow!("Motion track JSON keyframe needs a location of 3 numbers"))?;
+				let rotation = match keyframe.get("rotation") {
+					None | Some(JsonValue::Null) => None,
+					Some(rotation) => {
+						let [w, x, y, z] = rotation.as_numbers::<4>().ok_or_else(|| anyhow!("Motion track JSON keyframe needs a rotation of 4 numbers"))?;
+						Some(Quaternion::new(w, x, y, z))
+					}
+				};
+
+				Ok(MotionKeyframe {
+					time,
+					location: Point3::from(location),
+					rotation,
+				})
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		Self::from_keyframes(keyframes)
+	}
+
+	fn from_keyframes(mut keyframes: Vec<MotionKeyframe>) -> Result<Self> {
+		if keyframes.is_empty() {
+			bail!("Motion track has no keyframes");
+		}
+
+		// Trackers don't always write their samples in order, and their quaternions drift away from unit length
+		keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+		for keyframe in &mut keyframes {
+			keyframe.rotation = keyframe.rotation.filter(|rotation| rotation.magnitude2() > f64::EPSILON).map(|rotation| rotation.normalize());
+		}
+
+		Ok(Self { keyframes })
+	}
+
+	pub fn start_time(&self) -> f64 {
+		self.keyframes[0].time
+	}
+
+	pub fn duration(&self) -> f64 {
+		self.keyframes.last().unwrap().time - self.start_time()
+	}
+
+	// Interpolated location and rotation at this time since the start of the track, holding the first and last keyframes outside of it
+	pub fn sample(&self, time: f64) -> (Point3<f64>, Option<Quaternion<f64>>) {
+		let time = self.start_time() + time;
+		let next_index = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+		let previous = &self.keyframes[next_index.saturating_sub(1)];
+		let next = &self.keyframes[next_index.min(self.keyframes.len() - 1)];
+
+		let span = next.time - previous.time;
+		let blend = if span > f64::EPSILON { ((time - previous.time) / span).clamp(0., 1.) } else { 0. };
+		let location = previous.location + (next.location - previous.location) * blend;
+
+		// Keyframes without a rotation are bridged by the nearest ones on either side that have one
+		let rotation_before = self.keyframes[..next_index]
+			.iter()
+			.rev()
+			.find_map(|keyframe| keyframe.rotation.map(|rotation| (keyframe.time, rotation)));
+		let rotation_after = self.keyframes[next_index..].iter().find_map(|keyframe| keyframe.rotation.map(|rotation| (keyframe.time, rotation)));
+		let rotation = match (rotation_before, rotation_after) {
+			(Some((before_time, before)), Some((after_time, after))) if after_time - before_time > f64::EPSILON => {
+				Some(before.slerp(after, ((time - before_time) / (after_time - before_time)).clamp(0., 1.)))
+			}
+			(Some((_, rotation)), _) | (None, Some((_, rotation))) => Some(rotation),
+			(None, None) => None,
+		};
+
+		(location, rotation)
+	}
+}
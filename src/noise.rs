@@ -0,0 +1,338 @@
+use half::f16;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NoiseType {
+	Perlin,
+	Simplex,
+	Worley,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct NoiseSettings {
+	pub noise_type: NoiseType,
+	// Number of lattice cells across the texture for the lowest octave, the noise tiles seamlessly at this period (except simplex)
+	pub frequency: u32,
+	pub seed: u64,
+}
+
+impl NoiseSettings {
+	pub fn new(noise_type: NoiseType, frequency: u32, seed: u64) -> Self {
+		Self { noise_type, frequency, seed }
+	}
+}
+
+// Each RGBA channel holds the same noise at successively doubled frequencies (useful for building up fBm in a shader with a single sample)
+pub fn generate_noise_texture_2d(settings: &NoiseSettings, dimensions: (u32, u32)) -> Vec<[f16; 4]> {
+	let permutation = PermutationTable::new(settings.seed);
+
+	(0..dimensions.0 * dimensions.1)
+		.into_par_iter()
+		.map(|i| {
+			let (x, y) = (i % dimensions.0, i / dimensions.0);
+			let u = x as f32 / dimensions.0 as f32;
+			let v = y as f32 / dimensions.1 as f32;
+
+			let mut octaves = [f16::from_f32(0.); 4];
+			for (octave, channel) in octaves.iter_mut().enumerate() {
+				let period = settings.frequency << octave;
+				let (px, py) = (u * period as f32, v * period as f32);
+
+				let value = match settings.noise_type {
+					NoiseType::Perlin => perlin_2d(&permutation, px, py, period) * 0.5 + 0.5,
+					NoiseType::Simplex => simplex_2d(&permutation, px, py) * 0.5 + 0.5,
+					NoiseType::Worley => 1. - worley_2d(&permutation, px, py, period),
+				};
+				*channel = f16::from_f32(value.clamp(0., 1.));
+			}
+
+			octaves
+		})
+		.collect::<Vec<_>>()
+}
+
+pub fn generate_noise_texture_3d(settings: &NoiseSettings, dimensions: (u32, u32, u32)) -> Vec<[f16; 4]> {
+	let permutation = PermutationTable::new(settings.seed);
+
+	(0..dimensions.0 * dimensions.1 * dimensions.2)
+		.into_par_iter()
+		.map(|i| {
+			let (x, y, z) = (i % dimensions.0, (i / dimensions.0) % dimensions.1, i / (dimensions.0 * dimensions.1));
+			let u = x as f32 / dimensions.0 as f32;
+			let v = y as f32 / dimensions.1 as f32;
+			let w = z as f32 / dimensions.2 as f32;
+
+			let mut octaves = [f16::from_f32(0.); 4];
+			for (octave, channel) in octaves.iter_mut().enumerate() {
+				let period = settings.frequency << octave;
+				let (px, py, pz) = (u * period as f32, v * period as f32, w * period as f32);
+
+				let value = match settings.noise_type {
+					NoiseType::Perlin => perlin_3d(&permutation, px, py, pz, period) * 0.5 + 0.5,
+					NoiseType::Simplex => simplex_3d(&permutation, px, py, pz) * 0.5 + 0.5,
+					NoiseType::Worley => 1. - worley_3d(&permutation, px, py, pz, period),
+				};
+				*channel = f16::from_f32(value.clamp(0., 1.));
+			}
+
+			octaves
+		})
+		.collect::<Vec<_>>()
+}
+
+// Shuffled 0-255 lattice hash shared by all the noise types, doubled in length so chained lookups never need to wrap
+pub struct PermutationTable {
+	values: [u8; 512],
+}
+
+impl PermutationTable {
+	pub fn new(seed: u64) -> Self {
+		let mut rng = StdRng::seed_from_u64(seed);
+		let mut shuffled = (0..=255).collect::<Vec<u8>>();
+		shuffled.shuffle(&mut rng);
+
+		let mut values = [0; 512];
+		for (i, value) in values.iter_mut().enumerate() {
+			*value = shuffled[i & 255];
+		}
+
+		Self { values }
+	}
+
+	fn hash_2d(&self, x: i32, y: i32) -> usize {
+		let a = self.values[(x & 255) as usize] as usize;
+		self.values[a + (y & 255) as usize] as usize
+	}
+
+	fn hash_3d(&self, x: i32, y: i32, z: i32) -> usize {
+		let a = self.values[(x & 255) as usize] as usize;
+		let b = self.values[a + (y & 255) as usize] as usize;
+		self.values[b + (z & 255) as usize] as usize
+	}
+}
+
+// Range: -1 to 1
+pub fn perlin_2d(permutation: &PermutationTable, x: f32, y: f32, period: u32) -> f32 {
+	let (x0, y0) = (x.floor(), y.floor());
+	let (fx, fy) = (x - x0, y - y0);
+	let (ix, iy) = (x0 as i32, y0 as i32);
+
+	let gradient = |cx: i32, cy: i32, dx: f32, dy: f32| {
+		let hash = permutation.hash_2d(wrap(cx, period), wrap(cy, period));
+		let angle = (hash & 7) as f32 * std::f32::consts::FRAC_PI_4;
+		angle.cos() * dx + angle.sin() * dy
+	};
+
+	let n00 = gradient(ix, iy, fx, fy);
+	let n10 = gradient(ix + 1, iy, fx - 1., fy);
+	let n01 = gradient(ix, iy + 1, fx, fy - 1.);
+	let n11 = gradient(ix + 1, iy + 1, fx - 1., fy - 1.);
+
+	let (u, v) = (fade(fx), fade(fy));
+	lerp(lerp(n00, n10, u), lerp(n01, n11, u), v) * std::f32::consts::SQRT_2
+}
+
+// Range: -1 to 1
+pub fn perlin_3d(permutation: &PermutationTable, x: f32, y: f32, z: f32, period: u32) -> f32 {
+	let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+	let (fx, fy, fz) = (x - x0, y - y0, z - z0);
+	let (ix, iy, iz) = (x0 as i32, y0 as i32, z0 as i32);
+
+	let gradient = |cx: i32, cy: i32, cz: i32, dx: f32, dy: f32, dz: f32| {
+		let hash = permutation.hash_3d(wrap(cx, period), wrap(cy, period), wrap(cz, period));
+		let g = GRADIENTS_3D[hash % GRADIENTS_3D.len()];
+		g[0] * dx + g[1] * dy + g[2] * dz
+	};
+
+	let n000 = gradient(ix, iy, iz, fx, fy, fz);
+	let n100 = gradient(ix + 1, iy, iz, fx - 1., fy, fz);
+	let n010 = gradient(ix, iy + 1, iz, fx, fy - 1., fz);
+	let n110 = gradient(ix + 1, iy + 1, iz, fx - 1., fy - 1., fz);
+	let n001 = gradient(ix, iy, iz + 1, fx, fy, fz - 1.);
+	let n101 = gradient(ix + 1, iy, iz + 1, fx - 1., fy, fz - 1.);
+	let n011 = gradient(ix, iy + 1, iz + 1, fx, fy - 1., fz - 1.);
+	let n111 = gradient(ix + 1, iy + 1, iz + 1, fx - 1., fy - 1., fz - 1.);
+
+	let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+	let nx00 = lerp(n000, n100, u);
+	let nx10 = lerp(n010, n110, u);
+	let nx01 = lerp(n001, n101, u);
+	let nx11 = lerp(n011, n111, u);
+
+	lerp(lerp(nx00, nx10, v), lerp(nx01, nx11, v), w)
+}
+
+// Range: -1 to 1 (based on Stefan Gustavson's reference implementation)
+pub fn simplex_2d(permutation: &PermutationTable, x: f32, y: f32) -> f32 {
+	let skew = 0.5 * (3_f32.sqrt() - 1.);
+	let unskew = (3. - 3_f32.sqrt()) / 6.;
+
+	// Skew the input space to find which simplex cell we're in
+	let s = (x + y) * skew;
+	let (i, j) = ((x + s).floor(), (y + s).floor());
+	let t = (i + j) * unskew;
+	let (x0, y0) = (x - (i - t), y - (j - t));
+
+	// Determine which of the two triangles of the skewed cell the point is in
+	let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+
+	let x1 = x0 - i1 as f32 + unskew;
+	let y1 = y0 - j1 as f32 + unskew;
+	let x2 = x0 - 1. + 2. * unskew;
+	let y2 = y0 - 1. + 2. * unskew;
+
+	let (ii, jj) = (i as i32, j as i32);
+	let corner = |hash: usize, dx: f32, dy: f32| {
+		let t = 0.5 - dx * dx - dy * dy;
+		if t < 0. {
+			0.
+		} else {
+			let g = GRADIENTS_3D[hash % GRADIENTS_3D.len()];
+			t.powi(4) * (g[0] * dx + g[1] * dy)
+		}
+	};
+
+	let n0 = corner(permutation.hash_2d(ii, jj), x0, y0);
+	let n1 = corner(permutation.hash_2d(ii + i1, jj + j1), x1, y1);
+	let n2 = corner(permutation.hash_2d(ii + 1, jj + 1), x2, y2);
+
+	70. * (n0 + n1 + n2)
+}
+
+// Range: -1 to 1 (based on Stefan Gustavson's reference implementation)
+pub fn simplex_3d(permutation: &PermutationTable, x: f32, y: f32, z: f32) -> f32 {
+	let skew = 1. / 3.;
+	let unskew = 1. / 6.;
+
+	// Skew the input space to find which simplex cell we're in
+	let s = (x + y + z) * skew;
+	let (i, j, k) = ((x + s).floor(), (y + s).floor(), (z + s).floor());
+	let t = (i + j + k) * unskew;
+	let (x0, y0, z0) = (x - (i - t), y - (j - t), z - (k - t));
+
+	// Determine which of the six tetrahedra of the skewed cell the point is in
+	let ((i1, j1, k1), (i2, j2, k2)) = if x0 >= y0 {
+		if y0 >= z0 {
+			((1, 0, 0), (1, 1, 0))
+		} else if x0 >= z0 {
+			((1, 0, 0), (1, 0, 1))
+		} else {
+			((0, 0, 1), (1, 0, 1))
+		}
+	} else if y0 < z0 {
+		((0, 0, 1), (0, 1, 1))
+	} else if x0 < z0 {
+		((0, 1, 0), (0, 1, 1))
+	} else {
+		((0, 1, 0), (1, 1, 0))
+	};
+
+	let offsets = [
+		(x0, y0, z0),
+		(x0 - i1 as f32 + unskew, y0 - j1 as f32 + unskew, z0 - k1 as f32 + unskew),
+		(x0 - i2 as f32 + 2. * unskew, y0 - j2 as f32 + 2. * unskew, z0 - k2 as f32 + 2. * unskew),
+		(x0 - 1. + 3. * unskew, y0 - 1. + 3. * unskew, z0 - 1. + 3. * unskew),
+	];
+	let (ii, jj, kk) = (i as i32, j as i32, k as i32);
+	let hashes = [
+		permutation.hash_3d(ii, jj, kk),
+		permutation.hash_3d(ii + i1, jj + j1, kk + k1),
+		permutation.hash_3d(ii + i2, jj + j2, kk + k2),
+		permutation.hash_3d(ii + 1, jj + 1, kk + 1),
+	];
+
+	let sum = offsets
+		.iter()
+		.zip(hashes.iter())
+		.map(|(&(dx, dy, dz), &hash)| {
+			let t = 0.6 - dx * dx - dy * dy - dz * dz;
+			if t < 0. {
+				0.
+			} else {
+				let g = GRADIENTS_3D[hash % GRADIENTS_3D.len()];
+				t.powi(4) * (g[0] * dx + g[1] * dy + g[2] * dz)
+			}
+		})
+		.sum::<f32>();
+
+	32. * sum
+}
+
+// Distance to the nearest feature point, one point per cell. Range: 0 to ~1
+pub fn worley_2d(permutation: &PermutationTable, x: f32, y: f32, period: u32) -> f32 {
+	let (cx, cy) = (x.floor() as i32, y.floor() as i32);
+
+	let mut nearest = f32::MAX;
+	for oy in -1..=1 {
+		for ox in -1..=1 {
+			let (nx, ny) = (cx + ox, cy + oy);
+			let hash = permutation.hash_2d(wrap(nx, period), wrap(ny, period));
+			let feature_x = nx as f32 + feature_offset(permutation, hash, 0);
+			let feature_y = ny as f32 + feature_offset(permutation, hash, 1);
+
+			let distance = ((feature_x - x).powi(2) + (feature_y - y).powi(2)).sqrt();
+			nearest = nearest.min(distance);
+		}
+	}
+
+	nearest
+}
+
+// Distance to the nearest feature point, one point per cell. Range: 0 to ~1
+pub fn worley_3d(permutation: &PermutationTable, x: f32, y: f32, z: f32, period: u32) -> f32 {
+	let (cx, cy, cz) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+
+	let mut nearest = f32::MAX;
+	for oz in -1..=1 {
+		for oy in -1..=1 {
+			for ox in -1..=1 {
+				let (nx, ny, nz) = (cx + ox, cy + oy, cz + oz);
+				let hash = permutation.hash_3d(wrap(nx, period), wrap(ny, period), wrap(nz, period));
+				let feature_x = nx as f32 + feature_offset(permutation, hash, 0);
+				let feature_y = ny as f32 + feature_offset(permutation, hash, 1);
+				let feature_z = nz as f32 + feature_offset(permutation, hash, 2);
+
+				let distance = ((feature_x - x).powi(2) + (feature_y - y).powi(2) + (feature_z - z).powi(2)).sqrt();
+				nearest = nearest.min(distance);
+			}
+		}
+	}
+
+	nearest
+}
+
+const GRADIENTS_3D: [[f32; 3]; 12] = [
+	[1., 1., 0.],
+	[-1., 1., 0.],
+	[1., -1., 0.],
+	[-1., -1., 0.],
+	[1., 0., 1.],
+	[-1., 0., 1.],
+	[1., 0., -1.],
+	[-1., 0., -1.],
+	[0., 1., 1.],
+	[0., -1., 1.],
+	[0., 1., -1.],
+	[0., -1., -1.],
+];
+
+// Pseudorandom 0-1 position of a cell's feature point along one axis
+fn feature_offset(permutation: &PermutationTable, hash: usize, axis: usize) -> f32 {
+	permutation.values[(hash + axis * 85) & 511] as f32 / 255.
+}
+
+fn wrap(coordinate: i32, period: u32) -> i32 {
+	coordinate.rem_euclid(period.max(1) as i32)
+}
+
+// Quintic smoothstep used to interpolate between lattice points
+fn fade(t: f32) -> f32 {
+	t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn lerp(a: f32, b: f32, factor: f32) -> f32 {
+	a + (b - a) * factor
+}
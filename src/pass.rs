@@ -1,16 +1,173 @@
+use crate::camera::PostEffect;
+use crate::texture::Texture;
+
 use wgpu::TextureView;
 
+// Each variant is named after the struct it wraps
+#[allow(clippy::enum_variant_names)]
 pub enum Pass<'a> {
 	RenderPass(RenderPass<'a>),
 	ComputePass(ComputePass),
+	CopyTexturePass(CopyTexturePass<'a>),
+}
+
+impl<'a> Pass<'a> {
+	pub fn label(&self) -> &str {
+		match self {
+			Pass::RenderPass(pass) => &pass.label,
+			Pass::ComputePass(pass) => &pass.label,
+			Pass::CopyTexturePass(pass) => &pass.label,
+		}
+	}
+
+	pub fn enabled(&self) -> bool {
+		match self {
+			Pass::RenderPass(pass) => pass.enabled,
+			Pass::ComputePass(pass) => pass.enabled,
+			Pass::CopyTexturePass(pass) => pass.enabled,
+		}
+	}
 }
 
 pub struct RenderPass<'a> {
 	pub label: String,
-	pub depth_attachment: Option<&'a TextureView>,
-	pub color_attachment_types: Vec<&'a TextureView>,
+	pub depth_attachment: Option<DepthAttachment<'a>>,
+	pub color_attachments: Vec<ColorAttachment<'a>>,
 	pub blit_material: Option<String>,
-	pub clear_color: wgpu::Color,
+	// Confines drawing to part of the attachments, for split screen views, UI clipping, or rendering only a tile of an expensive pass while debugging it
+	pub viewport: Option<Viewport>,
+	pub scissor: Option<ScissorRect>,
+	// Evaluated every frame, so features can be toggled or have nothing to draw without the graph changing shape
+	pub enabled: bool,
+}
+
+// Maps clip space onto this rectangle of the attachments, in pixels from their top left corner
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Viewport {
+	pub x: f32,
+	pub y: f32,
+	pub width: f32,
+	pub height: f32,
+	pub min_depth: f32,
+	pub max_depth: f32,
+}
+
+impl Viewport {
+	pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+		Self {
+			x,
+			y,
+			width,
+			height,
+			min_depth: 0.,
+			max_depth: 1.,
+		}
+	}
+}
+
+// Discards fragments outside this rectangle in pixels, which must lie within the attachments
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ScissorRect {
+	pub x: u32,
+	pub y: u32,
+	pub width: u32,
+	pub height: u32,
+}
+
+// What a pass does with an attachment's existing contents when it begins
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LoadPolicy<T> {
+	// Keeps the contents, for passes that draw over or accumulate into earlier results
+	Load,
+	Clear(T),
+	// For passes that overwrite every texel, wgpu can't skip the load so this clears to whatever value is cheapest
+	DontCare,
+}
+
+pub struct ColorAttachment<'a> {
+	pub view: &'a TextureView,
+	pub load: LoadPolicy<wgpu::Color>,
+	// Contents nothing reads afterwards can be discarded at the end of the pass instead of written back
+	pub store: bool,
+}
+
+pub struct DepthAttachment<'a> {
+	pub view: &'a TextureView,
+	pub load: LoadPolicy<f32>,
+	pub store: bool,
+}
+
+impl<'a> ColorAttachment<'a> {
+	pub fn clear(view: &'a TextureView, color: wgpu::Color) -> Self {
+		Self {
+			view,
+			load: LoadPolicy::Clear(color),
+			store: true,
+		}
+	}
+
+	pub fn load(view: &'a TextureView) -> Self {
+		Self {
+			view,
+			load: LoadPolicy::Load,
+			store: true,
+		}
+	}
+
+	pub fn dont_care(view: &'a TextureView) -> Self {
+		Self {
+			view,
+			load: LoadPolicy::DontCare,
+			store: true,
+		}
+	}
+
+	pub fn wgpu_attachment(&self) -> wgpu::RenderPassColorAttachment<'a> {
+		let load = match self.load {
+			LoadPolicy::Load => wgpu::LoadOp::Load,
+			LoadPolicy::Clear(color) => wgpu::LoadOp::Clear(color),
+			LoadPolicy::DontCare => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+		};
+
+		wgpu::RenderPassColorAttachment {
+			view: self.view,
+			resolve_target: None,
+			ops: wgpu::Operations { load, store: self.store },
+		}
+	}
+}
+
+impl<'a> DepthAttachment<'a> {
+	// Depth is always cleared to the far plane
+	pub fn clear(view: &'a TextureView) -> Self {
+		Self {
+			view,
+			load: LoadPolicy::Clear(1.),
+			store: true,
+		}
+	}
+
+	pub fn load(view: &'a TextureView) -> Self {
+		Self {
+			view,
+			load: LoadPolicy::Load,
+			store: true,
+		}
+	}
+
+	pub fn wgpu_attachment(&self) -> wgpu::RenderPassDepthStencilAttachment<'a> {
+		let load = match self.load {
+			LoadPolicy::Load => wgpu::LoadOp::Load,
+			LoadPolicy::Clear(depth) => wgpu::LoadOp::Clear(depth),
+			LoadPolicy::DontCare => wgpu::LoadOp::Clear(1.),
+		};
+
+		wgpu::RenderPassDepthStencilAttachment {
+			view: self.view,
+			depth_ops: Some(wgpu::Operations { load, store: self.store }),
+			stencil_ops: None,
+		}
+	}
 }
 
 #[derive(Debug)]
@@ -18,4 +175,30 @@ pub struct ComputePass {
 	pub label: String,
 	pub material: String,
 	pub work_groups_size: (u32, u32, u32),
+	pub enabled: bool,
+}
+
+// Copies a whole texture between passes, such as to snapshot a frame texture that a later pass both samples and draws into
+pub struct CopyTexturePass<'a> {
+	pub label: String,
+	pub source: &'a Texture,
+	pub destination: &'a Texture,
+	pub enabled: bool,
+}
+
+// A full screen effect declared by the textures it reads and writes, which the render graph slots into the frame ahead of another pass instead of `Engine::render` listing it
+// A blit reads whatever its material samples, so every texture involved is named by its label
+pub struct EffectPass {
+	pub label: String,
+	pub operation: EffectOperation,
+	// Label of the pass this one has to run ahead of
+	pub before: String,
+	// Only runs while the active camera's post-processing stack includes this effect, if it names one
+	pub post_effect: Option<PostEffect>,
+}
+
+pub enum EffectOperation {
+	// Draws the material over the output, through a transient attachment copied back afterwards if the material also samples it
+	Blit { material: String, output: String },
+	Copy { source: String, destination: String },
 }
@@ -0,0 +1,97 @@
+use crate::camera::{Camera, CameraUniform, PerspectiveProjection, Projection};
+use crate::context::Context;
+use crate::frame_texture::FrameTexture;
+use crate::transform::Transform;
+
+use cgmath::{Deg, EuclideanSpace, InnerSpace, Matrix4, Point3, SquareMatrix, Vector3};
+
+// Moves the clipping plane slightly in front of the linked portal so its own surface doesn't get drawn into the view through it
+const CLIP_PLANE_OFFSET: f32 = 0.01;
+
+// One of a pair of linked portals, which shows the scene as seen looking out of the other portal, as if stepping into this one leads out of the other
+#[derive(Debug)]
+pub struct Portal {
+	// Name of the entity with the other portal of the pair
+	pub linked_portal: String,
+	// Facing direction of the portal in the entity's local space, with the plane passing through the entity's location
+	// Views are turned around the local Y axis on their way through, so this has to lie in the local XZ plane
+	pub normal: Vector3<f64>,
+	// How many levels of portals seen through this portal are themselves rendered, with 0 leaving every portal out of the view through it
+	pub max_recursion_depth: u32,
+	// The view through the portal is skipped while the viewing camera is behind it
+	pub faces_view: bool,
+	pub camera: Camera,
+	pub color_map: FrameTexture,
+	pub depth_map: FrameTexture,
+	// Copy of the last finished view, which is what the portal's material samples so portals can appear inside views still being rendered
+	pub portal_map: FrameTexture,
+}
+
+impl Portal {
+	// The resolution of the view is independent of the window size, so distant or small portals can be rendered cheaply
+	pub fn new(context: &Context, linked_portal: &str, normal: Vector3<f64>, resolution: (u32, u32), max_recursion_depth: u32) -> Self {
+		// The projection is replaced every frame by the viewing camera's
+		let projection = PerspectiveProjection::new(resolution.0, resolution.1, cgmath::Deg(45.0), 0.1, 50.0);
+		let mut camera = Camera::new(context, Projection::Perspective(projection));
+		// Like reflections, the view is shaded in a single forward pass and then seen through the viewing camera's own post-processing
+		camera.post_effects.clear();
+
+		let color_map = FrameTexture::with_size(&context.device, resolution, wgpu::TextureFormat::Rgba16Float, "Portal Color Map frame texture", None);
+		let depth_map = FrameTexture::with_size(
+			&context.device,
+			resolution,
+			wgpu::TextureFormat::Depth32Float,
+			"Portal Z-buffer frame texture",
+			Some(wgpu::CompareFunction::LessEqual),
+		);
+		let portal_map = FrameTexture::with_size(&context.device, resolution, wgpu::TextureFormat::Rgba16Float, "Portal View Map frame texture", None);
+
+		Self {
+			linked_portal: String::from(linked_portal),
+			normal,
+			max_recursion_depth,
+			faces_view: false,
+			camera,
+			color_map,
+			depth_map,
+			portal_map,
+		}
+	}
+
+	// Places the portal camera where the viewing camera would be if it kept its pose relative to this portal but had been carried over to the linked one
+	pub fn update_portal_camera(&mut self, transform: &Transform, linked_transform: &Transform, linked_normal: Vector3<f64>, view_camera: &CameraUniform, queue: &wgpu::Queue) {
+		let (entrance, entrance_normal) = portal_frame(transform, self.normal);
+		let (exit, exit_normal) = portal_frame(linked_transform, linked_normal);
+
+		let entrance_location = entrance.w.truncate();
+		let entrance_plane = entrance_normal.extend(-entrance_normal.dot(entrance_location));
+
+		let v = view_camera.v_matrix();
+		let eye = v.invert().unwrap().w;
+		self.faces_view = entrance_plane.dot(eye) > 0.;
+		if !self.faces_view {
+			return;
+		}
+
+		// Walking into the front of this portal leads out of the front of the other, so the view is turned around on the way through
+		let turn_around = Matrix4::from_angle_y(Deg(180.));
+		let to_exit = exit * turn_around * entrance.invert().unwrap();
+		let portal_v = v * to_exit.invert().unwrap();
+		let portal_eye = Point3::from_vec((to_exit * eye).truncate());
+
+		// Whatever lies behind the linked portal would otherwise block the view out of it
+		let exit_location = exit.w.truncate();
+		self.camera.clip_plane = Some(exit_normal.extend(-exit_normal.dot(exit_location) - CLIP_PLANE_OFFSET));
+
+		self.camera.update_from_matrices(portal_eye, portal_v, view_camera.p_matrix(), queue);
+	}
+}
+
+// The world matrix of a portal's entity without its scale, along with its world space facing direction
+fn portal_frame(transform: &Transform, normal: Vector3<f64>) -> (Matrix4<f32>, Vector3<f32>) {
+	let rotation = cgmath::Quaternion::new(transform.rotation.s as f32, transform.rotation.v.x as f32, transform.rotation.v.y as f32, transform.rotation.v.z as f32);
+	let location = Vector3::new(transform.location.x as f32, transform.location.y as f32, transform.location.z as f32);
+	let normal = (rotation * Vector3::new(normal.x as f32, normal.y as f32, normal.z as f32)).normalize();
+
+	(Matrix4::from_translation(location) * Matrix4::from(rotation), normal)
+}
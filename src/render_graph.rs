@@ -0,0 +1,446 @@
+use crate::draw_list::DrawListCache;
+use crate::frame_texture::{FrameTexture, FrameTextures};
+use crate::pass::{ColorAttachment, CopyTexturePass, EffectOperation, EffectPass, LoadPolicy, Pass, RenderPass};
+use crate::scene::LoadedResources;
+use crate::texture::Texture;
+
+use anyhow::{anyhow, bail, Result};
+use indexmap::IndexMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt::Write;
+
+// Written to the working directory, render it with `dot -Tsvg frame_graph.dot -o frame_graph.svg`
+pub const FRAME_GRAPH_EXPORT_PATH: &str = "frame_graph.dot";
+
+// Name of the material built for the frames of the given parity, for materials sampling a temporal texture, see `FrameTextures::advance_frame`
+// Even frames use the material's own name, so passes naming it bind the right one without knowing it's temporal
+pub fn temporal_material_name(material_name: &str, parity: usize) -> String {
+	match parity {
+		0 => String::from(material_name),
+		_ => format!("{} (odd frames)", material_name),
+	}
+}
+
+// How a pass touches a texture, in the order the accesses are listed for each pass
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Access {
+	Read,
+	Write,
+}
+
+// Each access of a pass, named by the label of the texture it touches
+type PassAccesses = Vec<(String, Access, &'static str)>;
+
+// The passes recorded for one frame, put in execution order by scheduling them, and which can be inspected before they're encoded
+pub struct RenderGraph<'a> {
+	pub passes: Vec<Pass<'a>>,
+	// Label of the pass each effect pass has to run ahead of, or `None` for the passes listed in the order they run
+	anchors: Vec<Option<String>>,
+}
+
+impl<'a> RenderGraph<'a> {
+	pub fn new(passes: Vec<Pass<'a>>) -> Self {
+		let anchors = passes.iter().map(|_| None).collect();
+		Self { passes, anchors }
+	}
+
+	// Turns an effect into the passes that carry it out, finding its textures by their labels, to be placed ahead of its anchor once the graph is scheduled
+	// Effects added ahead of the same anchor run in the order they were added
+	pub fn add_effect(&mut self, effect: &EffectPass, enabled: bool, resources: &'a LoadedResources, frame_textures: &'a FrameTextures, transient_attachments: &'a TransientAttachments) -> Result<()> {
+		let texture = |label: &str| find_texture(resources, frame_textures, label).ok_or_else(|| anyhow!("The effect pass \"{}\" uses the texture \"{}\", which doesn't exist", effect.label, label));
+
+		let passes = match &effect.operation {
+			EffectOperation::Blit { material, output } => {
+				let output_texture = texture(output)?;
+				let samples_output = match resources.materials.get(material.as_str()) {
+					Some(material) => material.sampled_textures.contains(output),
+					None => bail!("The effect pass \"{}\" draws with the material \"{}\", which doesn't exist", effect.label, material),
+				};
+
+				// A texture can't be sampled while it's being drawn into, so the effect draws into a transient attachment that's copied back over it
+				match samples_output {
+					false => vec![Pass::RenderPass(RenderPass {
+						label: effect.label.clone(),
+						depth_attachment: None,
+						color_attachments: vec![ColorAttachment::dont_care(&output_texture.view)],
+						blit_material: Some(material.clone()),
+						viewport: None,
+						scissor: None,
+						enabled,
+					})],
+					true => {
+						let transient = transient_attachments
+							.get(output_texture.format)
+							.ok_or_else(|| anyhow!("The effect pass \"{}\" needs a transient {:?} attachment, which wasn't allocated", effect.label, output_texture.format))?;
+
+						vec![
+							Pass::RenderPass(RenderPass {
+								label: effect.label.clone(),
+								depth_attachment: None,
+								color_attachments: vec![ColorAttachment::dont_care(&transient.view)],
+								blit_material: Some(material.clone()),
+								viewport: None,
+								scissor: None,
+								enabled,
+							}),
+							Pass::CopyTexturePass(CopyTexturePass {
+								label: format!("{}: Copy Back", effect.label),
+								source: transient,
+								destination: output_texture,
+								enabled,
+							}),
+						]
+					}
+				}
+			}
+			EffectOperation::Copy { source, destination } => vec![Pass::CopyTexturePass(CopyTexturePass {
+				label: effect.label.clone(),
+				source: texture(source)?,
+				destination: texture(destination)?,
+				enabled,
+			})],
+		};
+
+		self.anchors.extend(passes.iter().map(|_| Some(effect.before.clone())));
+		self.passes.extend(passes);
+		Ok(())
+	}
+
+	// Puts the passes in an order where each one runs after the passes writing what it reads, and before those overwriting it
+	// Each effect pass joins the listed passes just ahead of its anchor, and its dependencies are found from what it and the passes around it touch
+	// Left in the order the passes were added if it fails, which is when an anchor is missing or a transient attachment is read before anything writes it
+	pub fn schedule(
+		&mut self,
+		resources: &LoadedResources,
+		draw_lists: &DrawListCache,
+		frame_textures: &FrameTextures,
+		transient_attachments: &TransientAttachments,
+		surface_view: &wgpu::TextureView,
+	) -> Result<()> {
+		let accesses = self.accesses(resources, draw_lists, frame_textures, transient_attachments, surface_view);
+
+		let mut sequence = (0..self.passes.len()).filter(|index| self.anchors[*index].is_none()).collect::<Vec<_>>();
+		let mut dependencies = vec![vec![]; self.passes.len()];
+		for (index, anchor) in self.anchors.iter().enumerate() {
+			let anchor = match anchor {
+				Some(anchor) => anchor,
+				None => continue,
+			};
+
+			match sequence.iter().position(|other| self.anchors[*other].is_none() && self.passes[*other].label() == anchor) {
+				Some(position) => {
+					dependencies[sequence[position]].push(index);
+					sequence.insert(position, index);
+				}
+				None => bail!("The effect pass \"{}\" runs ahead of \"{}\", which isn't in the render graph", self.passes[index].label(), anchor),
+			}
+		}
+
+		// Reads depend on the last write, and writes on the last write and every read since, so nothing sees contents from the wrong point in the frame
+		let mut last_writes = HashMap::<&str, usize>::new();
+		let mut reads_since_write = HashMap::<&str, Vec<usize>>::new();
+		for &index in &sequence {
+			for (label, access, _) in &accesses[index] {
+				dependencies[index].extend(last_writes.get(label.as_str()).filter(|writer| **writer != index));
+				match access {
+					Access::Read => {
+						if !last_writes.contains_key(label.as_str()) && transient_attachments.holds(label) {
+							bail!("The pass \"{}\" reads the transient attachment \"{}\" before any pass writes it", self.passes[index].label(), label);
+						}
+						reads_since_write.entry(label.as_str()).or_default().push(index);
+					}
+					Access::Write => {
+						let readers = reads_since_write.remove(label.as_str()).unwrap_or_default();
+						dependencies[index].extend(readers.into_iter().filter(|reader| *reader != index));
+						last_writes.insert(label.as_str(), index);
+					}
+				}
+			}
+		}
+
+		// Kahn's algorithm, running whichever ready pass comes first in the sequence so passes with nothing between them keep their listed order
+		let mut positions = vec![0; self.passes.len()];
+		for (position, index) in sequence.iter().enumerate() {
+			positions[*index] = position;
+		}
+		let mut dependents = vec![vec![]; self.passes.len()];
+		let mut remaining = vec![0; self.passes.len()];
+		for (index, index_dependencies) in dependencies.iter_mut().enumerate() {
+			index_dependencies.sort_unstable();
+			index_dependencies.dedup();
+			remaining[index] = index_dependencies.len();
+			for dependency in index_dependencies.iter() {
+				dependents[*dependency].push(index);
+			}
+		}
+		let mut ready = (0..self.passes.len())
+			.filter(|index| remaining[*index] == 0)
+			.map(|index| Reverse((positions[index], index)))
+			.collect::<BinaryHeap<_>>();
+		let mut order = Vec::with_capacity(self.passes.len());
+		while let Some(Reverse((_, index))) = ready.pop() {
+			order.push(index);
+			for dependent in &dependents[index] {
+				remaining[*dependent] -= 1;
+				if remaining[*dependent] == 0 {
+					ready.push(Reverse((positions[*dependent], *dependent)));
+				}
+			}
+		}
+		if order.len() < self.passes.len() {
+			bail!("The render graph's passes depend on each other in a cycle");
+		}
+
+		let mut passes = self.passes.drain(..).map(Some).collect::<Vec<_>>();
+		let mut anchors = self.anchors.drain(..).map(Some).collect::<Vec<_>>();
+		for index in order {
+			self.passes.push(passes[index].take().unwrap());
+			self.anchors.push(anchors[index].take().unwrap());
+		}
+
+		Ok(())
+	}
+
+	// Points the blit and compute passes at the materials built for this frame's parity, so they sample the temporal textures where they stand this frame
+	// Draw lists are left alone, since no scene material samples a temporal texture
+	pub fn bind_temporal_materials(&mut self, resources: &LoadedResources, parity: usize) {
+		for pass in &mut self.passes {
+			let material = match pass {
+				Pass::RenderPass(RenderPass { blit_material: Some(material), .. }) => material,
+				Pass::ComputePass(pass) => &mut pass.material,
+				_ => continue,
+			};
+
+			let temporal_material = temporal_material_name(material, parity);
+			if resources.materials.contains_key(temporal_material.as_str()) {
+				*material = temporal_material;
+			}
+		}
+	}
+
+	// Graphviz description of the passes, the textures they read and write, and the span of passes each texture is live for
+	// Textures read before any pass writes them this frame still hold last frame's contents, so they are highlighted as the usual suspects for stale data
+	// The previous halves of the temporal pairs are meant to be read that way, so they're marked as holding last frame's instead
+	// Work recorded outside the pass list, like mirror reflections and the Hi-Z pyramid, is not included
+	pub fn export_dot(
+		&self,
+		resources: &LoadedResources,
+		draw_lists: &DrawListCache,
+		frame_textures: &FrameTextures,
+		transient_attachments: &TransientAttachments,
+		surface_view: &wgpu::TextureView,
+	) -> String {
+		let pass_accesses = self
+			.passes
+			.iter()
+			.map(Pass::label)
+			.zip(self.accesses(resources, draw_lists, frame_textures, transient_attachments, surface_view))
+			.collect::<Vec<_>>();
+
+		// The first and last pass touching each texture, and whether its first access reads last frame's contents
+		let mut lifetimes = IndexMap::<String, (usize, usize, bool)>::new();
+		for (pass_index, (_, accesses)) in pass_accesses.iter().enumerate() {
+			for (label, access, _) in accesses {
+				lifetimes
+					.entry(label.clone())
+					.and_modify(|(_, last, _)| *last = pass_index)
+					.or_insert((pass_index, pass_index, *access == Access::Read));
+			}
+		}
+
+		let mut dot = String::new();
+		writeln!(dot, "digraph \"Frame\" {{").unwrap();
+		writeln!(dot, "\trankdir=LR;").unwrap();
+		writeln!(dot, "\tnode [fontname=\"Helvetica\"];").unwrap();
+		writeln!(dot, "\tedge [fontname=\"Helvetica\", fontsize=10];").unwrap();
+
+		for (pass_index, ((label, _), pass)) in pass_accesses.iter().zip(&self.passes).enumerate() {
+			let (style, note) = if pass.enabled() { ("filled", "") } else { ("\"filled,dashed\"", "\\nskipped") };
+			writeln!(
+				dot,
+				"\t\"pass_{}\" [shape=box, style={}, fillcolor=\"#dde6f0\", label=\"{}: {}{}\"];",
+				pass_index,
+				style,
+				pass_index,
+				escape(label),
+				note
+			)
+			.unwrap();
+		}
+		for (texture_index, (label, (first, last, reads_previous_frame))) in lifetimes.iter().enumerate() {
+			let (color, note) = if frame_textures.is_previous_frame(label) {
+				("#e6dcf0", "\\nlast frame's, swapped each frame")
+			} else if *reads_previous_frame {
+				("#f4c7c3", "\\nread before written this frame")
+			} else {
+				("#e8f0dd", "")
+			};
+			writeln!(
+				dot,
+				"\t\"texture_{}\" [shape=ellipse, style=filled, fillcolor=\"{}\", label=\"{}\\nlive for passes {} to {}{}\"];",
+				texture_index,
+				color,
+				escape(label),
+				first,
+				last,
+				note
+			)
+			.unwrap();
+		}
+
+		// Execution order is shown as a faint chain so the dataflow edges stay readable
+		for pass_index in 1..pass_accesses.len() {
+			writeln!(dot, "\t\"pass_{}\" -> \"pass_{}\" [style=dotted, color=gray];", pass_index - 1, pass_index).unwrap();
+		}
+		for (pass_index, (_, accesses)) in pass_accesses.iter().enumerate() {
+			for (label, access, usage) in accesses {
+				let texture_index = lifetimes.get_index_of(label).unwrap();
+				match access {
+					Access::Read => writeln!(dot, "\t\"texture_{}\" -> \"pass_{}\" [label=\"{}\"];", texture_index, pass_index, usage).unwrap(),
+					Access::Write => writeln!(dot, "\t\"pass_{}\" -> \"texture_{}\" [label=\"{}\", color=\"#b03a2e\"];", pass_index, texture_index, usage).unwrap(),
+				}
+			}
+		}
+
+		writeln!(dot, "}}").unwrap();
+		dot
+	}
+
+	// What each pass reads and writes, by texture label, with attachments found among the frame textures, the scene's textures, and the transient attachments
+	fn accesses(
+		&self,
+		resources: &LoadedResources,
+		draw_lists: &DrawListCache,
+		frame_textures: &FrameTextures,
+		transient_attachments: &TransientAttachments,
+		surface_view: &wgpu::TextureView,
+	) -> Vec<PassAccesses> {
+		let attachment_textures = frame_textures
+			.iter()
+			.chain(transient_attachments.attachments.iter())
+			.map(|frame_texture| &frame_texture.texture)
+			.chain(resources.textures.values())
+			.collect::<Vec<_>>();
+		let attachment_label = |view: &wgpu::TextureView| {
+			if std::ptr::eq(view, surface_view) {
+				return String::from("Surface");
+			}
+
+			let texture = attachment_textures.iter().find(|texture| std::ptr::eq(&texture.view, view));
+			texture.map_or_else(|| String::from("Unknown Attachment"), |texture| texture.label.clone())
+		};
+
+		self.passes
+			.iter()
+			.map(|pass| match pass {
+				// Skipped passes don't touch anything this frame, so they only appear in the execution order
+				_ if !pass.enabled() => vec![],
+				Pass::RenderPass(pass) => {
+					let materials = match &pass.blit_material {
+						Some(material_name) => vec![&resources.materials[material_name.as_str()]],
+						None => draw_lists.visible(&pass.label).map(|(_, draw_item)| &resources.materials[draw_item.material]).collect(),
+					};
+					let reads = materials
+						.into_iter()
+						.flat_map(|material| material.sampled_textures.iter().cloned())
+						.map(|label| (label, Access::Read, "sampled"));
+
+					// Loaded attachments are blended or depth tested against their existing contents
+					let depth = pass.depth_attachment.iter().flat_map(|attachment| {
+						let label = attachment_label(attachment.view);
+						let load = (attachment.load == LoadPolicy::Load).then(|| (label.clone(), Access::Read, "depth load"));
+						load.into_iter().chain([(label, Access::Write, if attachment.store { "depth" } else { "depth discarded" })])
+					});
+					let colors = pass.color_attachments.iter().flat_map(|attachment| {
+						let label = attachment_label(attachment.view);
+						let load = (attachment.load == LoadPolicy::Load).then(|| (label.clone(), Access::Read, "color load"));
+						load.into_iter().chain([(label, Access::Write, if attachment.store { "color" } else { "color discarded" })])
+					});
+
+					dedup(reads.chain(depth).chain(colors).collect())
+				}
+				Pass::ComputePass(pass) => {
+					let material = &resources.materials[pass.material.as_str()];
+					let reads = material.sampled_textures.iter().map(|label| (label.clone(), Access::Read, "sampled"));
+					let writes = material.storage_textures.iter().map(|label| (label.clone(), Access::Write, "storage"));
+
+					dedup(reads.chain(writes).collect())
+				}
+				Pass::CopyTexturePass(pass) => {
+					vec![
+						(pass.source.label.clone(), Access::Read, "copy source"),
+						(pass.destination.label.clone(), Access::Write, "copy destination"),
+					]
+				}
+			})
+			.collect()
+	}
+}
+
+// Draw lists bind the same textures through many materials, so repeated accesses are collapsed into a single edge
+fn dedup(accesses: PassAccesses) -> PassAccesses {
+	accesses.into_iter().fold(vec![], |mut unique, access| {
+		if !unique.contains(&access) {
+			unique.push(access);
+		}
+		unique
+	})
+}
+
+fn escape(label: &str) -> String {
+	label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Looks a texture up by its label among the frame textures and then the scene's textures
+fn find_texture<'a>(resources: &'a LoadedResources, frame_textures: &'a FrameTextures, label: &str) -> Option<&'a Texture> {
+	frame_textures
+		.iter()
+		.map(|frame_texture| &frame_texture.texture)
+		.chain(resources.textures.values())
+		.find(|texture| texture.label == label)
+}
+
+// Render targets for effect passes that draw over a texture their material also samples, holding the result only until it's copied back
+// One is kept per format and shared by all those passes, since scheduling orders each one's use of it after the previous one's copy back
+#[derive(Default)]
+pub struct TransientAttachments {
+	attachments: Vec<FrameTexture>,
+}
+
+impl TransientAttachments {
+	// Allocates one at the window's resolution for each format needed by the effects, unless it already exists
+	pub fn allocate(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, effects: &[EffectPass], resources: &LoadedResources, frame_textures: &FrameTextures) {
+		for effect in effects {
+			let (material, output) = match &effect.operation {
+				EffectOperation::Blit { material, output } => (material, output),
+				EffectOperation::Copy { .. } => continue,
+			};
+
+			let samples_output = resources.materials.get(material.as_str()).is_some_and(|material| material.sampled_textures.contains(output));
+			let format = match find_texture(resources, frame_textures, output) {
+				Some(texture) if samples_output => texture.format,
+				_ => continue,
+			};
+
+			if self.get(format).is_none() {
+				let label = format!("Transient {:?} Attachment frame texture", format);
+				self.attachments.push(FrameTexture::new(device, config, format, label.as_str(), None));
+			}
+		}
+	}
+
+	pub fn get(&self, format: wgpu::TextureFormat) -> Option<&Texture> {
+		self.attachments.iter().map(|attachment| &attachment.texture).find(|texture| texture.format == format)
+	}
+
+	pub fn recreate_all(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+		for attachment in &mut self.attachments {
+			attachment.recreate(device, config);
+		}
+	}
+
+	fn holds(&self, label: &str) -> bool {
+		self.attachments.iter().any(|attachment| attachment.label == label)
+	}
+}
@@ -0,0 +1,113 @@
+use crate::camera::{Camera, PerspectiveProjection, Projection};
+use crate::context::Context;
+use crate::frame_texture::FrameTexture;
+
+use cgmath::Rad;
+
+// How a layer's color is combined with everything composited beneath it, where the layer's color is already premultiplied by how much of each pixel it covers
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LayerBlend {
+	AlphaOver,
+	Additive,
+	Multiply,
+	// Brightens like additive blending but never past the brighter of the two, though HDR values above 1 still push beyond it
+	Screen,
+}
+
+impl LayerBlend {
+	pub const ALL: [LayerBlend; 4] = [LayerBlend::AlphaOver, LayerBlend::Additive, LayerBlend::Multiply, LayerBlend::Screen];
+
+	// Used to name the composite pipeline built for each blend mode
+	pub fn name(&self) -> &'static str {
+		match self {
+			LayerBlend::AlphaOver => "alpha_over",
+			LayerBlend::Additive => "additive",
+			LayerBlend::Multiply => "multiply",
+			LayerBlend::Screen => "screen",
+		}
+	}
+
+	pub fn blend_state(&self) -> wgpu::BlendState {
+		let component = |src_factor, dst_factor| wgpu::BlendComponent {
+			src_factor,
+			dst_factor,
+			operation: wgpu::BlendOperation::Add,
+		};
+
+		// Where the layer drew nothing its color and alpha are zero, so every mode leaves the pixels beneath untouched there
+		let color = match self {
+			LayerBlend::AlphaOver => component(wgpu::BlendFactor::One, wgpu::BlendFactor::OneMinusSrcAlpha),
+			LayerBlend::Additive => component(wgpu::BlendFactor::One, wgpu::BlendFactor::One),
+			LayerBlend::Multiply => component(wgpu::BlendFactor::Dst, wgpu::BlendFactor::OneMinusSrcAlpha),
+			LayerBlend::Screen => component(wgpu::BlendFactor::One, wgpu::BlendFactor::OneMinusSrc),
+		};
+		let alpha = component(wgpu::BlendFactor::One, wgpu::BlendFactor::OneMinusSrcAlpha);
+
+		wgpu::BlendState { color, alpha }
+	}
+}
+
+// Models drawn from their own camera into their own color and depth, then blended over the finished world view
+// Since a layer never depth tests against the world, a first person weapon drawn with a close near plane can't poke through the walls the player stands against
+#[derive(Debug)]
+pub struct RenderLayer {
+	// Models join the layer by naming it, and are then left out of the world passes
+	pub name: String,
+	pub blend: LayerBlend,
+	pub enabled: bool,
+	// Follows the world camera's location and orientation, with only its projection differing
+	pub camera: Camera,
+	pub color_map: FrameTexture,
+	pub depth_map: FrameTexture,
+}
+
+impl RenderLayer {
+	pub fn new<F: Into<Rad<f32>>>(context: &Context, name: &str, blend: LayerBlend, fovy: F, znear: f32, zfar: f32) -> Self {
+		let (width, height) = (context.surface_configuration.width, context.surface_configuration.height);
+		let projection = PerspectiveProjection::new(width, height, fovy, znear, zfar);
+		let mut camera = Camera::new(context, Projection::Perspective(projection));
+		// Layers are composited after the world's post-processing, so they have none of their own
+		camera.post_effects.clear();
+
+		let color_map = FrameTexture::new(
+			&context.device,
+			&context.surface_configuration,
+			wgpu::TextureFormat::Rgba16Float,
+			&format!("{} Layer Color Map frame texture", name),
+			None,
+		);
+		let depth_map = FrameTexture::new(
+			&context.device,
+			&context.surface_configuration,
+			wgpu::TextureFormat::Depth32Float,
+			&format!("{} Layer Z-buffer frame texture", name),
+			Some(wgpu::CompareFunction::LessEqual),
+		);
+
+		Self {
+			name: String::from(name),
+			blend,
+			enabled: true,
+			camera,
+			color_map,
+			depth_map,
+		}
+	}
+
+	pub fn follow(&mut self, world_camera: &Camera, queue: &mut wgpu::Queue) {
+		self.camera.location = world_camera.location;
+		self.camera.pitch = world_camera.pitch;
+		self.camera.yaw = world_camera.yaw;
+		self.camera.exposure = world_camera.exposure;
+		self.camera.update_v_p_matrices(queue);
+	}
+
+	pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+		self.color_map.recreate(device, config);
+		self.depth_map.recreate(device, config);
+
+		if let Projection::Perspective(projection) = &mut self.camera.projection {
+			projection.resize(config.width, config.height);
+		}
+	}
+}
@@ -0,0 +1,393 @@
+use crate::collider::ColliderKind;
+
+use anyhow::{anyhow, bail, Result};
+use winit::event::{ElementState, VirtualKeyCode};
+
+// How transparent models are composited over the opaque scene
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransparencyMode {
+	// Draw list sorted back to front by distance and alpha blended, exact for non-intersecting models but sorting is per model rather than per fragment
+	SortedBlending,
+	// Order-independent weighted average of every layer, approximate but needs no sorting and handles intersecting and heavily overlapping surfaces
+	WeightedBlended,
+}
+
+// Visualization drawn over the finished frame for tuning and performance analysis
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DebugView {
+	None,
+	// Graph of the scene's luminance before exposure, for tuning the tone mapping
+	LuminanceHistogram,
+	// Heatmap of how many opaque fragments were shaded at each pixel, ignoring the depth test
+	Overdraw,
+	// Ray march through the baked signed distance field of the static geometry, shaded by its normals and darkened by the steps each ray took
+	DistanceField,
+}
+
+// How the G-buffer pass rasterizes the scene's triangles, for inspecting the geometry underneath the materials
+// Anything other than filled needs a GPU feature, and stays filled where it's missing
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rasterization {
+	Filled,
+	Wireframe,
+	Points,
+}
+
+impl Rasterization {
+	pub fn polygon_mode(&self) -> wgpu::PolygonMode {
+		match self {
+			Self::Filled => wgpu::PolygonMode::Fill,
+			Self::Wireframe => wgpu::PolygonMode::Line,
+			Self::Points => wgpu::PolygonMode::Point,
+		}
+	}
+}
+
+// Unlit colors the G-buffer pass shades the scene with in place of its materials, for finding problems like seams in the normals or stretched UVs
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DebugShading {
+	None,
+	// World space normals after normal mapping, mapped from -1 to 1 into 0 to 1
+	Normals,
+	// Texture coordinates in red and green, wrapped into 0 to 1
+	Uvs,
+	// Checkerboard over the texture coordinates, which shows how evenly they're spread across the surfaces
+	Checker,
+}
+
+// Which pixels the eye adaptation measures the scene's brightness by
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MeteringMode {
+	// Every pixel counts the same
+	Average,
+	// Every pixel counts, but those toward the middle of the screen count up to four times as much as the corners
+	CenterWeighted,
+	// Only the pixels in a small circle in the middle of the screen count, so whatever is looked at is exposed well however bright its surroundings are
+	Spot,
+}
+
+// Lighting traced through the distance field of the static geometry, which reaches farther than the screen space effects but misses details smaller than its voxels
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DistanceFieldShading {
+	Off,
+	// Soft shadows from the lights that cast them, softer the bigger the light looks
+	Shadows,
+	// Shadows plus ambient occlusion from the surroundings within a few meters, combined with SSAO which keeps the small creases the field is too coarse for
+	ShadowsAndAmbientOcclusion,
+}
+
+// Presets trading quality for speed, picked at startup by the kind of GPU
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RenderProfile {
+	Full,
+	// For integrated GPUs, which share their memory bandwidth with the CPU: no voxel GI, SSAO at half resolution, checkerboard shading, textures without their largest mip, and a capped frame rate
+	// There's no screen space reflection pass to turn off, so mirrors keep rendering their reflections
+	LowPower,
+}
+
+impl RenderProfile {
+	pub fn for_adapter(adapter_info: &wgpu::AdapterInfo) -> Self {
+		match adapter_info.device_type {
+			wgpu::DeviceType::IntegratedGpu => Self::LowPower,
+			_ => Self::Full,
+		}
+	}
+
+	pub fn voxel_global_illumination(&self) -> bool {
+		*self == Self::Full
+	}
+
+	// Switched on and off along with the profile, though it can still be changed on its own afterwards
+	pub fn checkerboard_shading(&self) -> bool {
+		*self == Self::LowPower
+	}
+
+	// Divides the window's resolution for the frame textures of the post-processing effects that are blurry anyway
+	pub fn post_process_resolution_divisor(&self) -> u32 {
+		match self {
+			Self::Full => 1,
+			Self::LowPower => 2,
+		}
+	}
+
+	// Largest mip levels left out of the textures as they're loaded, so changing the profile only affects textures loaded after it
+	pub fn skipped_texture_mips(&self) -> u32 {
+		match self {
+			Self::Full => 0,
+			Self::LowPower => 1,
+		}
+	}
+
+	// Frames per second, left to the display's refresh rate when `None`
+	pub fn frame_rate_cap(&self) -> Option<f64> {
+		match self {
+			Self::Full => None,
+			Self::LowPower => Some(30.),
+		}
+	}
+}
+
+// Rendering features that can be switched at runtime, mostly for comparing experimental paths against the default ones
+#[derive(Debug)]
+pub struct RenderSettings {
+	pub profile: RenderProfile,
+	// Draws static models as clusters culled on the GPU against the frustum and the previous frame's Hi-Z pyramid
+	pub meshlets: bool,
+	pub transparency: TransparencyMode,
+	pub debug_view: DebugView,
+	pub distance_field_shading: DistanceFieldShading,
+	pub rasterization: Rasterization,
+	pub debug_shading: DebugShading,
+	// Adapts the exposure to the scene's average luminance over time, with the camera's exposure compensating on top
+	pub auto_exposure: bool,
+	pub metering: MeteringMode,
+	// How quickly the eye adapts per second when the scene gets brighter and when it gets darker, since eyes adjust to light sooner than to the dark
+	pub exposure_brightening_speed: f32,
+	pub exposure_darkening_speed: f32,
+	// Range in stops the adapted exposure stays within, so a black screen or the sun filling the view doesn't push it to extremes
+	pub exposure_min_ev: f32,
+	pub exposure_max_ev: f32,
+	// Holds the exposure at this many stops in place of adapting it, until it's set back to none
+	pub manual_exposure_ev: Option<f32>,
+	// Shows the lights as gizmos that can be clicked and dragged to move them or change their range
+	pub light_gizmos: bool,
+	// Shades fewer pixels toward the edges of the screen, where a headset's lenses blur them anyway, and fills in the rest from their neighbors
+	pub foveation: bool,
+	// Shades half the pixels each frame in alternating checkerboard halves, filling in the other half from the last frame where it can be reprojected
+	pub checkerboard: bool,
+	// Renders a view for each eye side by side in place of the single world view, as a headset would be sent them
+	pub stereo: bool,
+	// Feeds a synthesized beat to the audio analysis in place of a soundtrack, so the lamp pulses with the bass
+	pub audio_test_beat: bool,
+	// Outlines the colliders generated for the static models, cycling through each kind of collider
+	pub collider_view: Option<ColliderKind>,
+	// Keeps culling and picking impostors from where the camera was when it was turned on, drawing that frustum so what it leaves out can be seen by flying around it
+	pub freeze_frustum: bool,
+	// Requests a Graphviz export of the next frame's render graph, cleared again once it's written
+	pub export_frame_graph: bool,
+}
+
+impl Default for RenderSettings {
+	fn default() -> Self {
+		Self {
+			profile: RenderProfile::Full,
+			meshlets: false,
+			transparency: TransparencyMode::SortedBlending,
+			debug_view: DebugView::None,
+			distance_field_shading: DistanceFieldShading::Off,
+			rasterization: Rasterization::Filled,
+			debug_shading: DebugShading::None,
+			auto_exposure: true,
+			metering: MeteringMode::Average,
+			exposure_brightening_speed: 3.,
+			exposure_darkening_speed: 1.,
+			exposure_min_ev: -5.,
+			exposure_max_ev: 4.,
+			manual_exposure_ev: None,
+			light_gizmos: false,
+			foveation: false,
+			checkerboard: false,
+			stereo: false,
+			audio_test_beat: false,
+			collider_view: None,
+			freeze_frustum: false,
+			export_frame_graph: false,
+		}
+	}
+}
+
+// Names the settings' choices are read and changed by
+const RENDER_PROFILES: [(&str, RenderProfile); 2] = [("full", RenderProfile::Full), ("low_power", RenderProfile::LowPower)];
+const TRANSPARENCY_MODES: [(&str, TransparencyMode); 2] = [("sorted_blending", TransparencyMode::SortedBlending), ("weighted_blended", TransparencyMode::WeightedBlended)];
+const DEBUG_VIEWS: [(&str, DebugView); 4] = [
+	("none", DebugView::None),
+	("luminance_histogram", DebugView::LuminanceHistogram),
+	("overdraw", DebugView::Overdraw),
+	("distance_field", DebugView::DistanceField),
+];
+const DISTANCE_FIELD_SHADINGS: [(&str, DistanceFieldShading); 3] = [
+	("off", DistanceFieldShading::Off),
+	("shadows", DistanceFieldShading::Shadows),
+	("shadows_and_ambient_occlusion", DistanceFieldShading::ShadowsAndAmbientOcclusion),
+];
+const RASTERIZATIONS: [(&str, Rasterization); 3] = [("filled", Rasterization::Filled), ("wireframe", Rasterization::Wireframe), ("points", Rasterization::Points)];
+const METERING_MODES: [(&str, MeteringMode); 3] = [("average", MeteringMode::Average), ("center_weighted", MeteringMode::CenterWeighted), ("spot", MeteringMode::Spot)];
+const DEBUG_SHADINGS: [(&str, DebugShading); 4] = [
+	("none", DebugShading::None),
+	("normals", DebugShading::Normals),
+	("uvs", DebugShading::Uvs),
+	("checker", DebugShading::Checker),
+];
+
+impl RenderSettings {
+	// Settings that can be read and changed by name, like from the console, with `export_frame_graph` left out since it's a request rather than a setting
+	pub const SETTING_NAMES: [&'static str; 21] = [
+		"profile",
+		"meshlets",
+		"transparency",
+		"debug_view",
+		"distance_field_shading",
+		"rasterization",
+		"debug_shading",
+		"auto_exposure",
+		"metering",
+		"exposure_brightening_speed",
+		"exposure_darkening_speed",
+		"exposure_min_ev",
+		"exposure_max_ev",
+		"manual_exposure_ev",
+		"light_gizmos",
+		"foveation",
+		"checkerboard",
+		"stereo",
+		"audio_test_beat",
+		"collider_view",
+		"freeze_frustum",
+	];
+
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn setting(&self, name: &str) -> Option<String> {
+		let on_off = |value: bool| if value { "on" } else { "off" };
+		fn choice<T: PartialEq>(choices: &[(&'static str, T)], value: T) -> &'static str {
+			choices.iter().find(|(_, choice)| *choice == value).map_or("", |(name, _)| *name)
+		}
+
+		let value = match name {
+			"profile" => choice(&RENDER_PROFILES, self.profile),
+			"meshlets" => on_off(self.meshlets),
+			"transparency" => choice(&TRANSPARENCY_MODES, self.transparency),
+			"debug_view" => choice(&DEBUG_VIEWS, self.debug_view),
+			"distance_field_shading" => choice(&DISTANCE_FIELD_SHADINGS, self.distance_field_shading),
+			"rasterization" => choice(&RASTERIZATIONS, self.rasterization),
+			"debug_shading" => choice(&DEBUG_SHADINGS, self.debug_shading),
+			"auto_exposure" => on_off(self.auto_exposure),
+			"metering" => choice(&METERING_MODES, self.metering),
+			"exposure_brightening_speed" => return Some(self.exposure_brightening_speed.to_string()),
+			"exposure_darkening_speed" => return Some(self.exposure_darkening_speed.to_string()),
+			"exposure_min_ev" => return Some(self.exposure_min_ev.to_string()),
+			"exposure_max_ev" => return Some(self.exposure_max_ev.to_string()),
+			"manual_exposure_ev" => return Some(self.manual_exposure_ev.map_or(String::from("none"), |ev| ev.to_string())),
+			"light_gizmos" => on_off(self.light_gizmos),
+			"foveation" => on_off(self.foveation),
+			"checkerboard" => on_off(self.checkerboard),
+			"stereo" => on_off(self.stereo),
+			"audio_test_beat" => on_off(self.audio_test_beat),
+			"collider_view" => self.collider_view.map_or("none", |kind| kind.name()),
+			"freeze_frustum" => on_off(self.freeze_frustum),
+			_ => return None,
+		};
+
+		Some(String::from(value))
+	}
+
+	// Switches take on, off, or toggle, numbers take numbers, and the rest take the names of their choices
+	pub fn set_setting(&mut self, name: &str, value: &str) -> Result<()> {
+		let on_off = |current: bool| match value {
+			"on" | "true" | "1" => Ok(true),
+			"off" | "false" | "0" => Ok(false),
+			"toggle" => Ok(!current),
+			_ => Err(anyhow!("{} takes on, off, or toggle", name)),
+		};
+		let number = || value.parse::<f32>().ok().filter(|number| number.is_finite()).ok_or_else(|| anyhow!("{} takes a number", name));
+		fn choose<T: Copy>(name: &str, value: &str, choices: &[(&str, T)]) -> Result<T> {
+			match choices.iter().find(|(choice_name, _)| *choice_name == value) {
+				Some((_, choice)) => Ok(*choice),
+				None => bail!("{} takes one of: {}", name, choices.iter().map(|(choice_name, _)| *choice_name).collect::<Vec<_>>().join(", ")),
+			}
+		}
+
+		match name {
+			"profile" => self.profile = choose(name, value, &RENDER_PROFILES)?,
+			"meshlets" => self.meshlets = on_off(self.meshlets)?,
+			"transparency" => self.transparency = choose(name, value, &TRANSPARENCY_MODES)?,
+			"debug_view" => self.debug_view = choose(name, value, &DEBUG_VIEWS)?,
+			"distance_field_shading" => self.distance_field_shading = choose(name, value, &DISTANCE_FIELD_SHADINGS)?,
+			"rasterization" => self.rasterization = choose(name, value, &RASTERIZATIONS)?,
+			"debug_shading" => self.debug_shading = choose(name, value, &DEBUG_SHADINGS)?,
+			"auto_exposure" => self.auto_exposure = on_off(self.auto_exposure)?,
+			"metering" => self.metering = choose(name, value, &METERING_MODES)?,
+			"exposure_brightening_speed" => self.exposure_brightening_speed = number()?.max(0.),
+			"exposure_darkening_speed" => self.exposure_darkening_speed = number()?.max(0.),
+			"exposure_min_ev" => self.exposure_min_ev = number()?.min(self.exposure_max_ev),
+			"exposure_max_ev" => self.exposure_max_ev = number()?.max(self.exposure_min_ev),
+			"manual_exposure_ev" => self.manual_exposure_ev = if value == "none" { None } else { Some(number()?) },
+			"light_gizmos" => self.light_gizmos = on_off(self.light_gizmos)?,
+			"foveation" => self.foveation = on_off(self.foveation)?,
+			"checkerboard" => self.checkerboard = on_off(self.checkerboard)?,
+			"stereo" => self.stereo = on_off(self.stereo)?,
+			"audio_test_beat" => self.audio_test_beat = on_off(self.audio_test_beat)?,
+			"collider_view" => {
+				let kinds = ColliderKind::ALL.map(|kind| (kind.name(), Some(kind)));
+				self.collider_view = choose(name, value, &[[("none", None)].as_slice(), kinds.as_slice()].concat())?;
+			}
+			"freeze_frustum" => self.freeze_frustum = on_off(self.freeze_frustum)?,
+			_ => bail!("There is no setting named \"{}\"", name),
+		}
+
+		Ok(())
+	}
+
+	pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
+		if state != ElementState::Pressed {
+			return false;
+		}
+
+		match key {
+			VirtualKeyCode::M => self.meshlets = !self.meshlets,
+			VirtualKeyCode::G => self.export_frame_graph = true,
+			VirtualKeyCode::L => self.light_gizmos = !self.light_gizmos,
+			VirtualKeyCode::O => self.foveation = !self.foveation,
+			VirtualKeyCode::X => self.stereo = !self.stereo,
+			VirtualKeyCode::B => self.audio_test_beat = !self.audio_test_beat,
+			VirtualKeyCode::Y => self.freeze_frustum = !self.freeze_frustum,
+			VirtualKeyCode::T => {
+				self.transparency = match self.transparency {
+					TransparencyMode::SortedBlending => TransparencyMode::WeightedBlended,
+					TransparencyMode::WeightedBlended => TransparencyMode::SortedBlending,
+				}
+			}
+			VirtualKeyCode::V => {
+				self.debug_view = match self.debug_view {
+					DebugView::None => DebugView::LuminanceHistogram,
+					DebugView::LuminanceHistogram => DebugView::Overdraw,
+					DebugView::Overdraw => DebugView::DistanceField,
+					DebugView::DistanceField => DebugView::None,
+				}
+			}
+			VirtualKeyCode::H => {
+				self.distance_field_shading = match self.distance_field_shading {
+					DistanceFieldShading::Off => DistanceFieldShading::Shadows,
+					DistanceFieldShading::Shadows => DistanceFieldShading::ShadowsAndAmbientOcclusion,
+					DistanceFieldShading::ShadowsAndAmbientOcclusion => DistanceFieldShading::Off,
+				}
+			}
+			VirtualKeyCode::Z => {
+				self.rasterization = match self.rasterization {
+					Rasterization::Filled => Rasterization::Wireframe,
+					Rasterization::Wireframe => Rasterization::Points,
+					Rasterization::Points => Rasterization::Filled,
+				}
+			}
+			VirtualKeyCode::U => {
+				self.debug_shading = match self.debug_shading {
+					DebugShading::None => DebugShading::Normals,
+					DebugShading::Normals => DebugShading::Uvs,
+					DebugShading::Uvs => DebugShading::Checker,
+					DebugShading::Checker => DebugShading::None,
+				}
+			}
+			VirtualKeyCode::K => {
+				self.collider_view = match self.collider_view {
+					None => Some(ColliderKind::ALL[0]),
+					Some(kind) => ColliderKind::ALL.iter().skip_while(|other| **other != kind).nth(1).copied(),
+				}
+			}
+			_ => return false,
+		}
+
+		true
+	}
+}
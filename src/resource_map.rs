@@ -0,0 +1,160 @@
+use indexmap::{Equivalent, IndexMap};
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::{Deref, Index};
+
+// Refers to a resource by its slot in a `ResourceMap`, along with the generation of the resource in that slot when the handle was made
+// A handle outliving its resource, because it was removed and another took its slot, is caught when it's used instead of silently giving the wrong resource
+pub struct Handle<T> {
+	index: usize,
+	generation: u64,
+	resource: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+	fn new(index: usize, generation: u64) -> Self {
+		Self {
+			index,
+			generation,
+			resource: PhantomData,
+		}
+	}
+}
+
+// Implemented by hand since deriving them would require the resource type to implement them too
+impl<T> Clone for Handle<T> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+	fn eq(&self, other: &Self) -> bool {
+		self.index == other.index && self.generation == other.generation
+	}
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.index.hash(state);
+		self.generation.hash(state);
+	}
+}
+
+impl<T> Debug for Handle<T> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "Handle({}, generation {})", self.index, self.generation)
+	}
+}
+
+// Resources by name, in the order they were added, which hands out handles for the per-frame paths that can't afford looking up names
+// Inserting a resource under a name that's already taken replaces it in place, so its handles stay valid and refer to the new one
+pub struct ResourceMap<K, T> {
+	resources: IndexMap<K, T>,
+	// The generation of the resource in each slot, taken from a counter for the whole map so no two resources ever share one
+	generations: Vec<u64>,
+	next_generation: u64,
+}
+
+impl<K: Hash + Eq, T> ResourceMap<K, T> {
+	pub fn new() -> Self {
+		Self {
+			resources: IndexMap::new(),
+			generations: Vec::new(),
+			next_generation: 0,
+		}
+	}
+
+	pub fn insert(&mut self, key: K, resource: T) -> Handle<T> {
+		let (index, _) = self.resources.insert_full(key, resource);
+		if index == self.generations.len() {
+			self.generations.push(self.next_generation);
+			self.next_generation += 1;
+		}
+
+		Handle::new(index, self.generations[index])
+	}
+
+	// Removes the resource by moving the last one into its slot, which leaves the handles to both of them invalid
+	pub fn swap_remove<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<T> {
+		let (index, _, resource) = self.resources.swap_remove_full(key)?;
+
+		self.generations.swap_remove(index);
+		if let Some(generation) = self.generations.get_mut(index) {
+			*generation = self.next_generation;
+			self.next_generation += 1;
+		}
+
+		Some(resource)
+	}
+
+	pub fn handle<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<Handle<T>> {
+		let index = self.resources.get_index_of(key)?;
+		Some(Handle::new(index, self.generations[index]))
+	}
+
+	// `None` if the handle's resource has since been removed
+	pub fn get_by_handle(&self, handle: Handle<T>) -> Option<(&K, &T)> {
+		if self.generations.get(handle.index) != Some(&handle.generation) {
+			return None;
+		}
+
+		self.resources.get_index(handle.index)
+	}
+
+	pub fn get_mut<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<&mut T> {
+		self.resources.get_mut(key)
+	}
+
+	pub fn values_mut(&mut self) -> indexmap::map::ValuesMut<'_, K, T> {
+		self.resources.values_mut()
+	}
+}
+
+impl<K: Hash + Eq, T> Default for ResourceMap<K, T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<K: Hash + Eq, T> Extend<(K, T)> for ResourceMap<K, T> {
+	fn extend<I: IntoIterator<Item = (K, T)>>(&mut self, resources: I) {
+		for (key, resource) in resources {
+			self.insert(key, resource);
+		}
+	}
+}
+
+// Everything that only reads the resources, like looking them up by name and iterating over them, comes straight from the map
+// Handing out the map mutably is left out, since removing from it directly would skip invalidating the handles
+impl<K, T> Deref for ResourceMap<K, T> {
+	type Target = IndexMap<K, T>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.resources
+	}
+}
+
+impl<K: Hash + Eq, T> Index<Handle<T>> for ResourceMap<K, T> {
+	type Output = T;
+
+	fn index(&self, handle: Handle<T>) -> &T {
+		match self.get_by_handle(handle) {
+			Some((_, resource)) => resource,
+			None => panic!("{:?} refers to a resource that has since been removed", handle),
+		}
+	}
+}
+
+impl<K: Hash + Eq, T, Q: ?Sized + Hash + Equivalent<K>> Index<&Q> for ResourceMap<K, T> {
+	type Output = T;
+
+	fn index(&self, key: &Q) -> &T {
+		&self.resources[key]
+	}
+}
@@ -0,0 +1,390 @@
+use crate::context::Context;
+use crate::instance::InstanceRaw;
+use crate::material::{Material, MaterialDataBinding};
+use crate::mesh::{generate_tangents, ModelVertex};
+use crate::model::Mobility;
+use crate::scene::Scene;
+use crate::shader::{CAMERA_BIND_GROUP, GLOBALS_BIND_GROUP, LIGHTING_BIND_GROUP, MATERIAL_BIND_GROUP};
+
+use cgmath::{InnerSpace, Quaternion, Rad, Rotation3, Vector3};
+use half::f16;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::mem;
+use wgpu::util::DeviceExt;
+use wgpu::BufferBinding;
+
+// Must match the workgroup size in `compute_scatter_culling.wgsl`
+const CULLING_WORKGROUP_SIZE: u32 = 64;
+// Must match the size of the LOD distances vector in `compute_scatter_culling.wgsl`
+pub const MAX_SCATTER_LODS: usize = 4;
+
+// Grayscale map stretched over the top down bounds of a layer's surfaces, where white keeps every instance and black none
+#[derive(Debug, Clone)]
+pub struct ScatterMask {
+	values: Vec<f32>,
+	dimensions: (u32, u32),
+}
+
+impl ScatterMask {
+	// Reads the red channel of texture data, such as a generated noise texture
+	pub fn from_texels(texels: &[[f16; 4]], dimensions: (u32, u32)) -> Self {
+		Self {
+			values: texels.iter().map(|texel| texel[0].to_f32().clamp(0., 1.)).collect(),
+			dimensions,
+		}
+	}
+
+	fn sample(&self, u: f32, v: f32) -> f32 {
+		let x = ((u.clamp(0., 1.) * self.dimensions.0 as f32) as u32).min(self.dimensions.0 - 1);
+		let y = ((v.clamp(0., 1.) * self.dimensions.1 as f32) as u32).min(self.dimensions.1 - 1);
+		self.values[(y * self.dimensions.0 + x) as usize]
+	}
+}
+
+// Mesh drawn for instances nearer to the camera than its distance and farther than the previous LOD's
+#[derive(Debug, Clone)]
+pub struct ScatterLod {
+	pub mesh: (String, String),
+	pub max_distance: f32,
+}
+
+// Describes how a layer of grass or props is spread over the static models using one material
+#[derive(Debug, Clone)]
+pub struct ScatterSettings {
+	pub material: String,
+	pub mask: Option<ScatterMask>,
+	// Average instances per square meter of level ground
+	pub density: f32,
+	// The same seed always places the same instances on the same surfaces
+	pub seed: u64,
+	pub min_scale: f32,
+	pub max_scale: f32,
+	// Surfaces whose normal is farther from straight up than this many degrees are left bare
+	pub max_slope: f32,
+	// Ordered from the nearest to the farthest, with instances beyond the last culled, and no more than `MAX_SCATTER_LODS` of them
+	pub lods: Vec<ScatterLod>,
+}
+
+// Laid out like the instances in `compute_scatter_culling.wgsl`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ScatterInstance {
+	pub location: [f32; 3],
+	pub scale: f32,
+	// Quaternion with the real part last
+	pub rotation: [f32; 4],
+}
+
+// Picks area weighted random points on the triangles of every static model using the settings' material
+pub fn scatter_instances(scene: &Scene, settings: &ScatterSettings) -> Vec<ScatterInstance> {
+	let mut triangles = Vec::new();
+	for entity in &scene.root {
+		for model in entity.get_models() {
+			let mesh = match model.mesh {
+				Some(mesh) if model.mobility == Mobility::Static => &scene.resources.meshes[mesh],
+				_ => continue,
+			};
+			if mesh.material_name.as_deref() != Some(settings.material.as_str()) {
+				continue;
+			}
+
+			for instance in model.instances.world_instances() {
+				let scale = instance.scale;
+				let to_world = |point: [f32; 3]| instance.location + instance.rotation * Vector3::new(point[0] * scale.x, point[1] * scale.y, point[2] * scale.z);
+
+				triangles.extend(mesh.collision_triangles.iter().map(|triangle| triangle.map(to_world)));
+			}
+		}
+	}
+
+	// The mask is stretched over the top down bounds of every matching triangle
+	let (mut min, mut max) = ([f32::MAX; 2], [f32::MIN; 2]);
+	for corner in triangles.iter().flatten() {
+		min = [min[0].min(corner.x), min[1].min(corner.z)];
+		max = [max[0].max(corner.x), max[1].max(corner.z)];
+	}
+	let extent = [(max[0] - min[0]).max(f32::EPSILON), (max[1] - min[1]).max(f32::EPSILON)];
+
+	let min_up = settings.max_slope.to_radians().cos();
+	let mut rng = StdRng::seed_from_u64(settings.seed);
+	let mut instances = Vec::new();
+
+	for [a, b, c] in triangles {
+		let cross = (b - a).cross(c - a);
+		let area = cross.magnitude() * 0.5;
+		if area <= f32::MIN_POSITIVE || cross.normalize().y < min_up {
+			continue;
+		}
+
+		// Whole instances for the whole part of the expected count, and maybe one more for the fraction left over
+		let expected = area * settings.density;
+		let count = expected.floor() as usize + (rng.gen::<f32>() < expected.fract()) as usize;
+
+		for _ in 0..count {
+			// Folding the unit square in half along its diagonal keeps the points uniform over the triangle
+			let (mut s, mut t) = (rng.gen::<f32>(), rng.gen::<f32>());
+			if s + t > 1. {
+				(s, t) = (1. - s, 1. - t);
+			}
+			let location = a + (b - a) * s + (c - a) * t;
+
+			let yaw = Quaternion::from_angle_y(Rad(rng.gen::<f32>() * std::f32::consts::TAU));
+			let scale = rng.gen_range(settings.min_scale..=settings.max_scale.max(settings.min_scale));
+
+			if let Some(mask) = &settings.mask {
+				if rng.gen::<f32>() >= mask.sample((location.x - min[0]) / extent[0], (location.z - min[1]) / extent[1]) {
+					continue;
+				}
+			}
+
+			instances.push(ScatterInstance {
+				location: location.into(),
+				scale,
+				rotation: [yaw.v.x, yaw.v.y, yaw.v.z, yaw.s],
+			});
+		}
+	}
+
+	instances
+}
+
+// Crossed vertical cards for a clump of foliage, textured with an alpha cutout leaf texture
+// Fewer cards suit the farther LODs, and each card is given both windings so it can be seen from behind with back face culling
+pub fn foliage_cards(card_count: usize, width: f32, height: f32) -> (Vec<ModelVertex>, Vec<u32>) {
+	let mut vertices = Vec::new();
+	let mut indices = Vec::new();
+
+	for card in 0..card_count.max(1) {
+		let angle = std::f32::consts::PI * card as f32 / card_count.max(1) as f32;
+		let across = Vector3::new(angle.cos(), 0., angle.sin()) * (width * 0.5);
+		let facing = Vector3::new(-angle.sin(), 0., angle.cos());
+
+		for side in [1., -1.] {
+			let first = vertices.len() as u32;
+			let normal = facing * side;
+			let corners = [
+				(-across, 0., 0.),
+				(across, 1., 0.),
+				(across + Vector3::unit_y() * height, 1., 1.),
+				(-across + Vector3::unit_y() * height, 0., 1.),
+			];
+
+			for (position, u, v) in corners {
+				vertices.push(ModelVertex {
+					position: position.into(),
+					uv: [u, 1. - v],
+					// Tilted up a little so the lighting reads like a rounded clump rather than flat cards
+					normal: (normal + Vector3::unit_y() * 0.5).normalize().into(),
+					tangent: [0.; 4],
+				});
+			}
+
+			if side > 0. {
+				indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+			} else {
+				indices.extend([first, first + 2, first + 1, first, first + 3, first + 2]);
+			}
+		}
+	}
+
+	generate_tangents(&mut vertices, &indices);
+
+	(vertices, indices)
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ScatterCullingSettings {
+	instance_count: u32,
+	lod_count: u32,
+	// Radius around each instance's origin enclosing every LOD at a scale of one
+	bounds_radius: f32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: u32,
+	lod_distances: [f32; MAX_SCATTER_LODS],
+}
+
+// Laid out as the arguments of `draw_indexed_indirect()`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct DrawIndexedIndirect {
+	index_count: u32,
+	instance_count: u32,
+	first_index: u32,
+	base_vertex: i32,
+	first_instance: u32,
+}
+
+// The GPU side of one layer: every instance, and the visible ones the culling shader sorts by LOD into instance buffers drawn indirectly
+pub struct ScatterLayer {
+	pub lods: Vec<ScatterLod>,
+	pub instance_count: u32,
+	pub draw_buffer: wgpu::Buffer,
+	// Holds room for every instance at each LOD, one after another
+	pub visible_instances_buffer: wgpu::Buffer,
+	// Emptied before each culling pass, with the index counts of each LOD's mesh
+	empty_draws: Vec<DrawIndexedIndirect>,
+	_instances_buffer: wgpu::Buffer,
+	_settings_buffer: wgpu::Buffer,
+	culling_material: Material,
+}
+
+impl ScatterLayer {
+	// Offset into `draw_buffer` of the indirect draw arguments for the given LOD
+	pub fn draw_offset(lod: usize) -> wgpu::BufferAddress {
+		(lod * mem::size_of::<DrawIndexedIndirect>()) as wgpu::BufferAddress
+	}
+
+	// Offset into `visible_instances_buffer` of the instances drawn with the given LOD
+	pub fn visible_instances_offset(&self, lod: usize) -> wgpu::BufferAddress {
+		(lod * self.instance_count.max(1) as usize * mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress
+	}
+}
+
+// Spreads grass and props over surfaces by the thousands, culling them and choosing their LODs in a compute shader so the CPU never touches them after loading
+pub struct ScatterRenderer {
+	pub layers: Vec<ScatterLayer>,
+}
+
+impl ScatterRenderer {
+	pub fn new() -> Self {
+		Self { layers: vec![] }
+	}
+
+	// The LOD meshes and the culling shader need to be loaded into the scene's resources first
+	pub fn add_layer(&mut self, device: &wgpu::Device, scene: &Scene, settings: &ScatterSettings, camera_buffer: &wgpu::Buffer) {
+		let lods = settings.lods.iter().take(MAX_SCATTER_LODS).cloned().collect::<Vec<_>>();
+		let instances = scatter_instances(scene, settings);
+		let instance_count = instances.len() as u32;
+
+		let meshes = lods.iter().map(|lod| &scene.resources.meshes[&lod.mesh]).collect::<Vec<_>>();
+		let bounds_radius = meshes.iter().map(|mesh| Vector3::from(mesh.bounds_center).magnitude() + mesh.bounds_radius).fold(0., f32::max);
+		let mut lod_distances = [0.; MAX_SCATTER_LODS];
+		for (distance, lod) in lod_distances.iter_mut().zip(&lods) {
+			*distance = lod.max_distance;
+		}
+
+		let settings = ScatterCullingSettings {
+			instance_count,
+			lod_count: lods.len() as u32,
+			bounds_radius,
+			_padding: 0,
+			lod_distances,
+		};
+		let empty_draws = meshes
+			.iter()
+			.map(|mesh| DrawIndexedIndirect {
+				index_count: mesh.index_count,
+				instance_count: 0,
+				first_index: 0,
+				base_vertex: 0,
+				first_instance: 0,
+			})
+			.collect::<Vec<_>>();
+
+		let label = &layer_label(&lods);
+		let settings_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some(&format!("{} Scatter Culling Settings Buffer", label)),
+			contents: bytemuck::cast_slice(&[settings]),
+			usage: wgpu::BufferUsages::UNIFORM,
+		});
+		// Storage buffers can't be empty, so a layer that found nowhere to grow keeps one unused instance
+		let placeholder = [ScatterInstance {
+			location: [0.; 3],
+			scale: 0.,
+			rotation: [0., 0., 0., 1.],
+		}];
+		let instances_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some(&format!("{} Scatter Instances Buffer", label)),
+			contents: bytemuck::cast_slice(if instances.is_empty() { &placeholder } else { instances.as_slice() }),
+			usage: wgpu::BufferUsages::STORAGE,
+		});
+		let draw_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some(&format!("{} Scatter Draw Buffer", label)),
+			contents: bytemuck::cast_slice(&empty_draws),
+			usage: wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+		});
+		let visible_instances_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some(&format!("{} Scatter Visible Instances Buffer", label)),
+			size: (lods.len().max(1) * instance_count.max(1) as usize * mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
+			mapped_at_creation: false,
+		});
+
+		let culling_material = Material::new(
+			&format!("{} Scatter Culling", label),
+			"compute_scatter_culling.wgsl",
+			vec![
+				MaterialDataBinding::Buffer(BufferBinding {
+					buffer: camera_buffer,
+					offset: 0,
+					size: None,
+				}),
+				MaterialDataBinding::Buffer(BufferBinding {
+					buffer: &settings_buffer,
+					offset: 0,
+					size: None,
+				}),
+				MaterialDataBinding::Buffer(BufferBinding {
+					buffer: &instances_buffer,
+					offset: 0,
+					size: None,
+				}),
+				MaterialDataBinding::Buffer(BufferBinding {
+					buffer: &draw_buffer,
+					offset: 0,
+					size: None,
+				}),
+				MaterialDataBinding::Buffer(BufferBinding {
+					buffer: &visible_instances_buffer,
+					offset: 0,
+					size: None,
+				}),
+			],
+			&scene.resources,
+			device,
+		);
+
+		self.layers.push(ScatterLayer {
+			lods,
+			instance_count,
+			draw_buffer,
+			visible_instances_buffer,
+			empty_draws,
+			_instances_buffer: instances_buffer,
+			_settings_buffer: settings_buffer,
+			culling_material,
+		});
+	}
+
+	// Records the culling of every layer, which must finish before the deferred pass draws them
+	pub fn record_culling(&self, encoder: &mut wgpu::CommandEncoder, context: &Context, scene: &Scene) {
+		let shader = &scene.resources.shaders["compute_scatter_culling.wgsl"];
+		let pipeline = match &shader.pipeline {
+			crate::shader::PipelineType::RenderPipeline(_) => return,
+			crate::shader::PipelineType::ComputePipeline(compute_pipeline) => compute_pipeline,
+		};
+
+		// Each frame's visible instances are counted up from zero by the culling shader
+		for layer in &self.layers {
+			context.queue.write_buffer(&layer.draw_buffer, 0, bytemuck::cast_slice(&layer.empty_draws));
+		}
+
+		let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Compute: Cull Scatter") });
+		compute_pass.set_pipeline(pipeline);
+		compute_pass.set_bind_group(GLOBALS_BIND_GROUP, &context.globals.globals_bind_group, &[]);
+		compute_pass.set_bind_group(CAMERA_BIND_GROUP, &context.placeholder_bind_group, &[]);
+		compute_pass.set_bind_group(LIGHTING_BIND_GROUP, &context.placeholder_bind_group, &[]);
+
+		for layer in self.layers.iter().filter(|layer| layer.instance_count > 0) {
+			compute_pass.set_bind_group(MATERIAL_BIND_GROUP, &layer.culling_material.bind_group, &[]);
+			compute_pass.dispatch(layer.instance_count.div_ceil(CULLING_WORKGROUP_SIZE), 1, 1);
+		}
+	}
+}
+
+// Layers are labeled by their nearest LOD's mesh
+fn layer_label(lods: &[ScatterLod]) -> String {
+	lods.first().map_or_else(|| String::from("Empty"), |lod| lod.mesh.1.clone())
+}
@@ -1,16 +1,37 @@
+use crate::camera::{OrthographicProjection, PerspectiveProjection, PostEffect, Projection};
+use crate::component::Component;
+use crate::context::Context;
 use crate::entity::Entity;
+use crate::environment::SceneEnvironment;
+use crate::json::{JsonParser, JsonValue};
+use crate::light::{Lamp, Light, ShadowFilter, Sun};
 use crate::material::Material;
-use crate::mesh::Mesh;
+use crate::mesh::{Mesh, ModelVertex};
+use crate::model::{Mobility, Model};
+use crate::resource_map::{Handle, ResourceMap};
+use crate::scene_hooks::{SceneEvent, SceneHooks};
+use crate::scripts::behavior_from_json;
 use crate::shader::Shader;
 use crate::texture::Texture;
 
-use indexmap::IndexMap;
-use std::collections::HashMap;
+use anyhow::{anyhow, bail, Context as _, Result};
+use cgmath::{Point3, Quaternion, Rotation, Rotation3, Vector3};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
 pub struct Scene {
 	pub root: Entity,
 	pub entity_name_paths: HashMap<String, Vec<usize>>,
 	pub resources: LoadedResources,
+	pub environment: SceneEnvironment,
+	// The scene description file this was built from in the assets' scenes folder, where the environment is saved back to
+	pub file: Option<String>,
+	// The dependent work triggered by the events raised on the scene, run by the engine before the next frame is updated
+	pub hooks: SceneHooks,
+	// The scene files loaded on top of this one, in the order they were loaded
+	pub additive_scenes: Vec<AdditiveScene>,
+	// Incremented whenever entities, components, visibility, or materials change so caches like the draw lists know to rebuild
+	generation: u64,
 }
 
 impl Scene {
@@ -22,9 +43,183 @@ impl Scene {
 			root: Entity::new("Scene Root"),
 			entity_name_paths,
 			resources: LoadedResources::new(),
+			environment: SceneEnvironment::default(),
+			file: None,
+			hooks: SceneHooks::default(),
+			additive_scenes: Vec::new(),
+			generation: 0,
 		}
 	}
 
+	// Builds the entities declared in a scene description file in the assets' scenes folder, so scenes can be edited without recompiling
+	// The file holds an "entities" array of objects like `{ "name": "Lamp", "transform": { ... }, "components": [ ... ], "children": [ ... ] }`:
+	// - A transform may give its "location" and "scale" as arrays of 3 numbers, and its rotation either as "rotation" in degrees about each axis or as a "look_direction" to face
+	// - Each component object has a "type" of model, camera, lamp, sun, or behavior, along with that type's parameters
+	// - A model without a "mesh" gets a child entity for each mesh in its file, named after the mesh
+	// - A model casts and receives shadows unless its "casts_shadows" or "receives_shadows" is false
+	// - A sun's "shadow_filter" of pcf, exponential, variance, or pcss picks how the edges of its shadows are softened
+	// An optional "environment" object beside the entities holds the scene's "ambient" light, "fog", "grading", "sky_ambient", and "exposure"
+	// - Its "sky_map" names a panorama in the assets' skies folder, or gives an array of the files of the six cube faces, scaled by its "sky_map_intensity"
+	pub fn from_file(context: &mut Context, assets_path: &Path, file: &str, model_files: &HashMap<String, Vec<String>>) -> Result<Self> {
+		let text = std::fs::read_to_string(assets_path.join("scenes").join(file))?;
+		let root = JsonParser::new(&text).parse_document()?;
+
+		let mut scene = Self::new();
+		scene.file = Some(String::from(file));
+		if let Some(environment) = root.get("environment") {
+			scene.environment = SceneEnvironment::from_json(environment).with_context(|| format!("Couldn't read the environment of scene \"{}\"", file))?;
+		}
+		for entity in root.get("entities").and_then(JsonValue::as_array).unwrap_or_default() {
+			add_entity(&mut scene.root, entity, context, assets_path, model_files)?;
+		}
+
+		Ok(scene)
+	}
+
+	// The model files used by the models of a scene description file, which have to be loaded before it's built
+	pub fn model_files_in_file(assets_path: &Path, file: &str) -> Result<Vec<String>> {
+		fn collect<'a>(entity: &'a JsonValue, model_files: &mut Vec<&'a str>) {
+			for component in entity.get("components").and_then(JsonValue::as_array).unwrap_or_default() {
+				if component.get("type").and_then(JsonValue::as_str) == Some("model") {
+					model_files.extend(component.get("file").and_then(JsonValue::as_str));
+				}
+			}
+			for child in entity.get("children").and_then(JsonValue::as_array).unwrap_or_default() {
+				collect(child, model_files);
+			}
+		}
+
+		let text = std::fs::read_to_string(assets_path.join("scenes").join(file))?;
+		let root = JsonParser::new(&text).parse_document()?;
+
+		let mut model_files = Vec::new();
+		for entity in root.get("entities").and_then(JsonValue::as_array).unwrap_or_default() {
+			collect(entity, &mut model_files);
+		}
+		model_files.sort_unstable();
+		model_files.dedup();
+		Ok(model_files.into_iter().map(String::from).collect())
+	}
+
+	// Builds the entities of another scene description file beside those already in the scene, under a root entity named after the file
+	// Its environment is ignored, since the scene keeps the one it was first loaded with, and the model files it uses have to be loaded already
+	pub fn load_additive(&mut self, context: &mut Context, assets_path: &Path, file: &str, model_files: &HashMap<String, Vec<String>>) -> Result<()> {
+		if self.additive_scenes.iter().any(|additive_scene| additive_scene.file == file) {
+			bail!("Scene \"{}\" is already loaded", file);
+		}
+
+		let text = std::fs::read_to_string(assets_path.join("scenes").join(file))?;
+		let description = JsonParser::new(&text).parse_document()?;
+
+		let mut root = Entity::new(file);
+		for entity in description.get("entities").and_then(JsonValue::as_array).unwrap_or_default() {
+			add_entity(&mut root, entity, context, assets_path, model_files).with_context(|| format!("Couldn't load scene \"{}\"", file))?;
+		}
+
+		let mut used_model_files = root.iter().flat_map(|entity| entity.get_models()).map(|model| model.mesh_name.0.clone()).collect::<Vec<_>>();
+		used_model_files.sort_unstable();
+		used_model_files.dedup();
+
+		self.spawn(None, root, &context.device)?;
+		self.additive_scenes.push(AdditiveScene {
+			file: String::from(file),
+			model_files: used_model_files,
+		});
+		self.raise(SceneEvent::Streamed);
+		Ok(())
+	}
+
+	// Removes a scene loaded with `load_additive` along with all of its entities
+	// Returns the model files it used that nothing left in the scene uses anymore, whose resources can be released without affecting the other scenes
+	pub fn unload_additive(&mut self, file: &str) -> Result<Vec<String>> {
+		let index = self
+			.additive_scenes
+			.iter()
+			.position(|additive_scene| additive_scene.file == file)
+			.ok_or_else(|| anyhow!("Scene \"{}\" isn't loaded on top of the scene", file))?;
+		let additive_scene = self.additive_scenes.remove(index);
+		self.despawn(file);
+		self.raise(SceneEvent::Streamed);
+
+		let still_used = self.root.iter().flat_map(|entity| entity.get_models()).map(|model| model.mesh_name.0.as_str()).collect::<HashSet<_>>();
+		Ok(additive_scene.model_files.into_iter().filter(|model_file| !still_used.contains(model_file.as_str())).collect())
+	}
+
+	// Writes the environment into the scene description file it was loaded from, leaving the rest of the file exactly as it was written
+	pub fn save_environment(&self, assets_path: &Path) -> Result<()> {
+		let file = self.file.as_ref().ok_or_else(|| anyhow!("The scene wasn't loaded from a file to save its environment to"))?;
+		let path = assets_path.join("scenes").join(file);
+		let mut text = std::fs::read_to_string(&path)?;
+		let environment = self.environment.to_json().to_string();
+
+		match JsonParser::new(&text).find_top_level_value("environment")? {
+			Some(range) => text.replace_range(range, &environment),
+			None => {
+				let opening = text.find('{').ok_or_else(|| anyhow!("Scene \"{}\" isn't a JSON object", file))?;
+				text.insert_str(opening + 1, &format!("\n\t\"environment\": {},", environment));
+			}
+		}
+
+		std::fs::write(path, text)?;
+		Ok(())
+	}
+
+	pub fn generation(&self) -> u64 {
+		self.generation
+	}
+
+	// Must be called after adding or removing entities or components, toggling `enabled`, or reassigning materials
+	pub fn mark_changed(&mut self) {
+		self.generation += 1;
+	}
+
+	// Queues whatever has to be redone after the event, which also covers `mark_changed`
+	pub fn raise(&mut self, event: SceneEvent) {
+		self.hooks.raise(event);
+		self.mark_changed();
+	}
+
+	// Adds the entity, along with its children, under the named parent or the root if `None`, so it's drawn and updated from the next frame on
+	// Its models' meshes and materials have to be loaded already, since nothing new is loaded from the assets
+	pub fn spawn(&mut self, parent_name: Option<&str>, mut entity: Entity, device: &wgpu::Device) -> Result<()> {
+		for model in entity.iter().flat_map(|entity| entity.get_models()) {
+			if self.resources.meshes.handle(&model.mesh_name).is_none() {
+				bail!("Can't spawn \"{}\" since its mesh '{}:{}' isn't loaded", entity.name, model.mesh_name.0, model.mesh_name.1);
+			}
+		}
+		entity.load_models_on_descendants(&self.resources);
+
+		// Entities are looked up by name, so the name must be unique across the whole tree rather than only among its siblings
+		if self.root.iter().any(|existing| existing.name == entity.name) {
+			bail!("Can't spawn \"{}\" since there's already an entity by that name", entity.name);
+		}
+
+		let parent = match parent_name {
+			Some(parent_name) => self
+				.find_entity_mut(parent_name)
+				.ok_or_else(|| anyhow!("Can't spawn \"{}\" under \"{}\", which doesn't exist", entity.name, parent_name))?,
+			None => &mut self.root,
+		};
+		entity.update_world_transforms_of_descendants(&parent.world_transform, device);
+		parent.children.push(entity);
+
+		// Spawning only appends, so the cached paths stay valid
+		self.mark_changed();
+		Ok(())
+	}
+
+	// Removes the named entity and its children from wherever they are in the scene, releasing their models' GPU buffers, and returns it if it was found
+	pub fn despawn(&mut self, name: &str) -> Option<Entity> {
+		let parent = find_parent_mut(&mut self.root, name)?;
+		let mut entity = parent.remove_child(name)?;
+		entity.release_gpu_buffers_of_descendants();
+
+		// Removing a child shifts the indices of its later siblings, which may be part of any of the cached paths
+		self.entity_name_paths.retain(|_, index_path| index_path.is_empty());
+		self.mark_changed();
+		Some(entity)
+	}
+
 	pub fn find_entity(&self, name: &str) -> Option<&Entity> {
 		if let Some(index_path) = self.entity_name_paths.get(name) {
 			// First traverse the index paths for the non-mutabe entity to check if it exists
@@ -68,21 +263,225 @@ impl Scene {
 	}
 }
 
+// The entity whose child has the given name, searching the whole tree below the given entity
+fn find_parent_mut<'a>(entity: &'a mut Entity, child_name: &str) -> Option<&'a mut Entity> {
+	if entity.children.iter().any(|child| child.name == child_name) {
+		return Some(entity);
+	}
+
+	entity.children.iter_mut().find_map(|child| find_parent_mut(child, child_name))
+}
+
+// A scene file loaded on top of the main scene, whose entities are all under a root entity named after the file so it can be unloaded again on its own
+pub struct AdditiveScene {
+	pub file: String,
+	// The model files its models use, whose resources are released along with it unless another scene still uses them
+	pub model_files: Vec<String>,
+}
+
+fn add_entity(parent: &mut Entity, description: &JsonValue, context: &mut Context, assets_path: &Path, model_files: &HashMap<String, Vec<String>>) -> Result<()> {
+	let name = description.get("name").and_then(JsonValue::as_str).ok_or_else(|| anyhow!("Scene entity is missing its name"))?;
+	let entity = parent.new_child(name);
+
+	if let Some(transform) = description.get("transform") {
+		let numbers = |key: &str| {
+			transform
+				.get(key)
+				.map(|value| value.as_numbers::<3>().ok_or_else(|| anyhow!("\"{}\"'s {} must be an array of 3 numbers", name, key)))
+		};
+
+		if let Some(location) = numbers("location") {
+			entity.transform.location = Point3::from(location?);
+		}
+		if let Some(rotation) = numbers("rotation") {
+			let [x, y, z] = rotation?;
+			entity.transform.rotation = Quaternion::from_angle_x(cgmath::Deg(x)) * Quaternion::from_angle_y(cgmath::Deg(y)) * Quaternion::from_angle_z(cgmath::Deg(z));
+		}
+		if let Some(direction) = numbers("look_direction") {
+			entity.transform.rotation = Quaternion::look_at(Vector3::from(direction?), Vector3::unit_y());
+		}
+		if let Some(scale) = numbers("scale") {
+			entity.transform.scale = Vector3::from(scale?);
+		}
+	}
+
+	for component in description.get("components").and_then(JsonValue::as_array).unwrap_or_default() {
+		add_component(entity, component, context, assets_path, model_files).with_context(|| format!("Couldn't add a component to \"{}\"", name))?;
+	}
+
+	// Cameras start out at the camera controller's default view, so they're only moved to the entity's transform if the file gives one
+	if description.get("transform").is_some() {
+		let transform = entity.transform;
+		for camera in entity.get_cameras_mut() {
+			camera.update_transform_and_matrices(&transform, &mut context.queue);
+		}
+	}
+
+	for child in description.get("children").and_then(JsonValue::as_array).unwrap_or_default() {
+		add_entity(entity, child, context, assets_path, model_files)?;
+	}
+
+	Ok(())
+}
+
+fn add_component(entity: &mut Entity, component: &JsonValue, context: &mut Context, assets_path: &Path, model_files: &HashMap<String, Vec<String>>) -> Result<()> {
+	let number = |key: &str| component.get(key).and_then(JsonValue::as_number);
+	let string = |key: &str| component.get(key).and_then(JsonValue::as_str);
+
+	match string("type").ok_or_else(|| anyhow!("Component is missing its type"))? {
+		"model" => {
+			let file = string("file").ok_or_else(|| anyhow!("Model is missing its file"))?;
+			let mesh_names = model_files.get(file).ok_or_else(|| anyhow!("Model file \"{}\" isn't loaded", file))?;
+
+			let build_model = |mesh_name: &str| -> Result<Model> {
+				let mut model = Model::new((file, mesh_name));
+				model.mobility = match string("mobility").unwrap_or("movable") {
+					"static" => Mobility::Static,
+					"movable" => Mobility::Movable,
+					other => bail!("There's no mobility \"{}\", it can be static or movable", other),
+				};
+				model.impostor_distance = number("impostor_distance").map(|distance| distance as f32);
+				model.layer = string("layer").map(String::from);
+				model.forward_material_file = string("forward_material").map(String::from);
+
+				if let Some(instance) = component.get("instance") {
+					if let Some([x, y, z]) = instance.get("location").and_then(JsonValue::as_numbers::<3>) {
+						model.instances.instance_list[0].location = cgmath::Vector3::new(x as f32, y as f32, z as f32);
+					}
+					if let Some([x, y, z]) = instance.get("scale").and_then(JsonValue::as_numbers::<3>) {
+						model.instances.instance_list[0].scale = cgmath::Vector3::new(x as f32, y as f32, z as f32);
+					}
+				}
+
+				// Both are on unless turned off
+				model.casts_shadows = !matches!(component.get("casts_shadows"), Some(JsonValue::Bool(false)));
+				model.set_receives_shadows(!matches!(component.get("receives_shadows"), Some(JsonValue::Bool(false))), &context.device);
+
+				Ok(model)
+			};
+
+			match string("mesh") {
+				Some(mesh_name) => {
+					if !mesh_names.iter().any(|name| name == mesh_name) {
+						bail!("Model file \"{}\" has no mesh \"{}\"", file, mesh_name);
+					}
+					entity.add_component(Component::Model(Box::new(build_model(mesh_name)?)));
+				}
+				None => {
+					for mesh_name in mesh_names {
+						let model = build_model(mesh_name)?;
+						entity.new_child(mesh_name).add_component(Component::Model(Box::new(model)));
+					}
+				}
+			}
+		}
+		"camera" => {
+			let near = number("near").unwrap_or(0.1) as f32;
+			let far = number("far").unwrap_or(1000.) as f32;
+			let projection = match string("projection").unwrap_or("perspective") {
+				"perspective" => {
+					let (width, height) = (context.surface_configuration.width, context.surface_configuration.height);
+					Projection::Perspective(PerspectiveProjection::new(width, height, cgmath::Deg(number("fov").unwrap_or(45.) as f32), near, far))
+				}
+				"orthographic" => Projection::Orthographic(OrthographicProjection::new(1, 1, number("size").unwrap_or(10.) as f32, near, far)),
+				other => bail!("There's no projection \"{}\", it can be perspective or orthographic", other),
+			};
+			entity.add_camera_component(context, projection);
+
+			if let Some(names) = component.get("post_effects").and_then(JsonValue::as_array) {
+				let post_effects = names
+					.iter()
+					.map(|name| match name.as_str() {
+						Some("ambient_occlusion") => Ok(PostEffect::AmbientOcclusion),
+						Some("temporal_anti_aliasing") => Ok(PostEffect::TemporalAntiAliasing),
+						Some("motion_blur") => Ok(PostEffect::MotionBlur),
+						_ => Err(anyhow!("There's no post effect {}, they can be ambient_occlusion, temporal_anti_aliasing, or motion_blur", name)),
+					})
+					.collect::<Result<Vec<_>>>()?;
+				entity.get_cameras_mut().last_mut().unwrap().post_effects = post_effects;
+			}
+		}
+		"lamp" => {
+			let default = Lamp::default();
+			let mut lamp = Lamp::new(number("lumens").unwrap_or(default.lumens as f64) as f32, number("radius").unwrap_or(default.radius as f64) as f32);
+			if let Some(kelvin) = number("temperature") {
+				lamp = lamp.with_temperature(kelvin as f32);
+			}
+			entity.add_component(Component::Light(Light::Lamp(lamp)));
+		}
+		"sun" => {
+			let mut sun = Sun::new(Vector3::unit_y(), number("illuminance").unwrap_or(1.) as f32);
+			if matches!(component.get("atmospheric_color"), Some(JsonValue::Bool(true))) {
+				sun = sun.with_atmospheric_color();
+			}
+			sun.set_sky_position(number("elevation").unwrap_or(90.) as f32, number("azimuth").unwrap_or(0.) as f32);
+			sun.shadow_filter = match string("shadow_filter").unwrap_or("pcf") {
+				"pcf" => ShadowFilter::Pcf,
+				"exponential" => ShadowFilter::Exponential,
+				"variance" => ShadowFilter::Variance,
+				"pcss" => ShadowFilter::Pcss,
+				other => bail!("There's no shadow filter \"{}\", it can be pcf, exponential, variance, or pcss", other),
+			};
+			entity.add_component(Component::Light(Light::Sun(sun)));
+		}
+		"behavior" => entity.add_component(Component::Behavior(behavior_from_json(component, assets_path)?)),
+		other => bail!("There's no component type \"{}\", it can be model, camera, lamp, sun, or behavior", other),
+	}
+
+	Ok(())
+}
+
+pub type ShaderHandle = Handle<Shader>;
+pub type MaterialHandle = Handle<Material>;
+pub type MeshHandle = Handle<Mesh>;
+
 pub struct LoadedResources {
-	pub shaders: IndexMap<String, Shader>,
-	pub textures: IndexMap<String, Texture>,
-	pub materials: IndexMap<String, Material>,
-	pub meshes: IndexMap<(String, String), Mesh>,
+	pub shaders: ResourceMap<String, Shader>,
+	pub textures: ResourceMap<String, Texture>,
+	pub materials: ResourceMap<String, Material>,
+	pub meshes: ResourceMap<(String, String), Mesh>,
 }
 
 impl LoadedResources {
 	pub fn new() -> Self {
 		Self {
-			shaders: IndexMap::new(),
-			textures: IndexMap::new(),
-			materials: IndexMap::new(),
-			meshes: IndexMap::new(),
+			shaders: ResourceMap::new(),
+			textures: ResourceMap::new(),
+			materials: ResourceMap::new(),
+			meshes: ResourceMap::new(),
+		}
+	}
+
+	// Uploads a texture generated at runtime along with its mipmaps, registered under a name that meshes can use in their maps in place of a file in the assets
+	// The pixels are tightly packed rows of RGBA with 1, 2, or 4 bytes per channel to match the format
+	pub fn add_texture_from_rgba(&mut self, context: &Context, name: &str, rgba: &[u8], dimensions: (u32, u32), format: wgpu::TextureFormat, repeat_mode: wgpu::AddressMode) -> Result<&Texture> {
+		let texel_count = (dimensions.0 * dimensions.1) as usize;
+		if texel_count == 0 || ![1, 2, 4].iter().any(|bytes_per_channel| rgba.len() == texel_count * 4 * bytes_per_channel) {
+			bail!("Texture \"{}\" has {} bytes, which isn't RGBA for {}x{} texels", name, rgba.len(), dimensions.0, dimensions.1);
+		}
+
+		let mut texture = Texture::from_rgba_data(&context.device, &context.queue, rgba, dimensions, name, format, repeat_mode);
+		texture.generate_mipmaps(context);
+
+		self.textures.insert(String::from(name), texture);
+		Ok(&self.textures[name])
+	}
+
+	// Uploads a mesh generated at runtime, registered under a model name and mesh name that a `Model` can then be given like those loaded from model files
+	// The mesh is returned so its maps and collision triangles can be filled in before the materials are built
+	pub fn add_mesh_from_vertices(&mut self, context: &Context, model_name: &str, mesh_name: &str, vertices: &[ModelVertex], indices: &[u32]) -> Result<&mut Mesh> {
+		if indices.len() % 3 != 0 {
+			bail!("Mesh \"{}\" has {} indices, which isn't a whole number of triangles", mesh_name, indices.len());
 		}
+		if let Some(index) = indices.iter().find(|index| **index as usize >= vertices.len()) {
+			bail!("Mesh \"{}\" has index {} past its {} vertices", mesh_name, index, vertices.len());
+		}
+
+		let mesh = Mesh::new_dynamic(&context.device, &context.queue, mesh_name, vertices, indices);
+
+		let key = (String::from(model_name), String::from(mesh_name));
+		self.meshes.insert(key.clone(), mesh);
+		Ok(self.meshes.get_mut(&key).unwrap())
 	}
 }
 
@@ -0,0 +1,51 @@
+use std::collections::BTreeSet;
+
+// Something that happened to the scene which the work derived from it has to catch up with
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SceneEvent {
+	// The scene finished loading from its file, along with everything generated around it
+	Loaded,
+	// A model was given another material, or a material's maps, parameters, or shader were replaced
+	MaterialChanged,
+	// Another scene file was loaded on top of the scene or unloaded from it, bringing or taking away its geometry
+	Streamed,
+}
+
+impl SceneEvent {
+	fn hooks(&self) -> &'static [SceneHook] {
+		match self {
+			Self::Loaded | Self::Streamed => &[SceneHook::RebuildCollision, SceneHook::RebakeDistanceField, SceneHook::Revoxelize, SceneHook::ResetTemporalHistory],
+			Self::MaterialChanged => &[SceneHook::Revoxelize, SceneHook::ResetTemporalHistory],
+		}
+	}
+}
+
+// Work derived from the scene that has to be redone when it changes, declared in the order it runs since later work reads what earlier work produced
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SceneHook {
+	// The static triangles the character controller walks on, which are the only spatial structure built over the scene's geometry
+	RebuildCollision,
+	// Baked from the collision triangles, so it has to wait for them
+	RebakeDistanceField,
+	// The voxelized lighting takes its colors from the materials
+	Revoxelize,
+	// A changed surface would otherwise smear through the frames blended from before it changed
+	ResetTemporalHistory,
+}
+
+// Collects the events raised on the scene, then hands back each hook they trigger once, so whoever changes the scene doesn't need to know what depends on what
+#[derive(Debug, Default)]
+pub struct SceneHooks {
+	triggered: BTreeSet<SceneHook>,
+}
+
+impl SceneHooks {
+	pub fn raise(&mut self, event: SceneEvent) {
+		self.triggered.extend(event.hooks());
+	}
+
+	// The hooks triggered since the last call, in the order they have to run in
+	pub fn take_triggered(&mut self) -> Vec<SceneHook> {
+		std::mem::take(&mut self.triggered).into_iter().collect()
+	}
+}
@@ -1,11 +1,11 @@
-use crate::behavior::Behavior;
+use crate::behavior::{Behavior, BehaviorContext};
 use crate::entity::Entity;
 
 #[derive(Debug)]
 pub struct LightCubeMovement;
 
 impl Behavior for LightCubeMovement {
-	fn update(&self, entity: &mut Entity) {
+	fn update(&mut self, entity: &mut Entity, _context: &BehaviorContext) {
 		entity.transform.location.y += 0.01;
 	}
 }
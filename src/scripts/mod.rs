@@ -1 +1,74 @@
 pub mod light_cube_movement;
+pub mod motion_track_playback;
+pub mod spline_follow;
+pub mod steering;
+
+use crate::behavior::Behavior;
+use crate::json::JsonValue;
+use crate::motion_track::MotionTrack;
+
+use anyhow::{anyhow, bail, Result};
+use cgmath::Point3;
+use std::path::Path;
+use std::sync::Arc;
+
+// Builds the script named by a scene file's behavior component, with its parameters read from the same object
+// Spline following is left out since its spline is generated in code rather than loaded from an asset
+pub fn behavior_from_json(component: &JsonValue, assets_path: &Path) -> Result<Box<dyn Behavior>> {
+	let name = component
+		.get("behavior")
+		.and_then(JsonValue::as_str)
+		.ok_or_else(|| anyhow!("Behavior component is missing its \"behavior\" name"))?;
+
+	let number = |key: &str| {
+		component
+			.get(key)
+			.and_then(JsonValue::as_number)
+			.ok_or_else(|| anyhow!("The {} behavior is missing its \"{}\"", name, key))
+	};
+	let point = |key: &str| {
+		component
+			.get(key)
+			.and_then(JsonValue::as_numbers::<3>)
+			.map(Point3::from)
+			.ok_or_else(|| anyhow!("The {} behavior's \"{}\" must be an array of 3 numbers", name, key))
+	};
+	let flag = |key: &str| matches!(component.get(key), Some(JsonValue::Bool(true)));
+
+	Ok(match name {
+		"light_cube_movement" => Box::new(light_cube_movement::LightCubeMovement),
+		// The target can be a fixed location or another entity to follow
+		"look_at" => match component.get("target_entity").and_then(JsonValue::as_str) {
+			Some(target_entity) => Box::new(steering::LookAt::at_entity(String::from(target_entity), number("turn_rate")?)),
+			None => Box::new(steering::LookAt::new(point("target")?, number("turn_rate")?)),
+		},
+		"seek" => Box::new(steering::Seek::new(point("target")?, number("max_speed")?)),
+		"flee" => Box::new(steering::Flee::new(point("threat")?, number("max_speed")?, number("panic_distance")?)),
+		"wander" => Box::new(steering::Wander::new(
+			point("center")?,
+			number("leash_distance")?,
+			number("max_speed")?,
+			number("seed").unwrap_or(0.) as u64,
+		)),
+		"patrol" => {
+			let waypoints = component
+				.get("waypoints")
+				.and_then(JsonValue::as_array)
+				.unwrap_or_default()
+				.iter()
+				.map(|waypoint| waypoint.as_numbers::<3>().map(Point3::from))
+				.collect::<Option<Vec<_>>>()
+				.ok_or_else(|| anyhow!("The patrol behavior's waypoints must each be an array of 3 numbers"))?;
+			Box::new(steering::Patrol::new(waypoints, number("max_speed")?, flag("ping_pong")))
+		}
+		"motion_track_playback" => {
+			let file = component
+				.get("track")
+				.and_then(JsonValue::as_str)
+				.ok_or_else(|| anyhow!("The motion track playback behavior is missing its \"track\""))?;
+			let track = MotionTrack::load(assets_path, file)?;
+			Box::new(motion_track_playback::MotionTrackPlayback::new(Arc::new(track), flag("looping")))
+		}
+		other => bail!("There's no behavior named \"{}\"", other),
+	})
+}
@@ -0,0 +1,38 @@
+use crate::behavior::{Behavior, BehaviorContext};
+use crate::entity::Entity;
+use crate::motion_track::MotionTrack;
+
+use std::sync::Arc;
+
+// Replays a recorded motion track on the entity's transform, leaving the rotation alone for tracks that only record positions
+#[derive(Debug)]
+pub struct MotionTrackPlayback {
+	// Shared so several entities can replay the same recording, each with its own playback time
+	pub track: Arc<MotionTrack>,
+	pub speed: f64,
+	// Starts over from the beginning after the last keyframe instead of holding there
+	pub looping: bool,
+	pub time: f64,
+}
+
+impl MotionTrackPlayback {
+	pub fn new(track: Arc<MotionTrack>, looping: bool) -> Self {
+		Self { track, speed: 1., looping, time: 0. }
+	}
+}
+
+impl Behavior for MotionTrackPlayback {
+	fn update(&mut self, entity: &mut Entity, context: &BehaviorContext) {
+		let duration = self.track.duration();
+		self.time += context.delta_time.as_secs_f64() * self.speed;
+		if self.looping && duration > 0. {
+			self.time = self.time.rem_euclid(duration);
+		}
+
+		let (location, rotation) = self.track.sample(self.time);
+		entity.transform.location = location;
+		if let Some(rotation) = rotation {
+			entity.transform.rotation = rotation;
+		}
+	}
+}
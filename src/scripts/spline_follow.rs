@@ -0,0 +1,61 @@
+use crate::behavior::{Behavior, BehaviorContext};
+use crate::entity::Entity;
+use crate::spline::{ArcLengthTable, Spline};
+
+use cgmath::{InnerSpace, Matrix3, Quaternion, Rotation, Vector3};
+use std::sync::Arc;
+use winit::event::VirtualKeyCode;
+
+// Rides along a spline at a steady speed facing the way it travels, like a cart on a rail or a camera on a dolly track
+// Holding the left arrow key stops the ride and holding the right one doubles its speed, for getting a better look at something along the way
+#[derive(Debug)]
+pub struct SplineFollow {
+	// Shared so several entities can ride the same rail
+	pub spline: Arc<Spline>,
+	arc_lengths: ArcLengthTable,
+	// Meters per second, negative to ride backward
+	pub speed: f32,
+	pub distance: f32,
+}
+
+impl SplineFollow {
+	pub fn new(spline: Arc<Spline>, speed: f32) -> Self {
+		let arc_lengths = spline.arc_lengths(32);
+
+		Self {
+			spline,
+			arc_lengths,
+			speed,
+			distance: 0.,
+		}
+	}
+}
+
+impl Behavior for SplineFollow {
+	fn update(&mut self, entity: &mut Entity, context: &BehaviorContext) {
+		let length = self.arc_lengths.length();
+		let throttle = match (context.key_held(VirtualKeyCode::Left), context.key_held(VirtualKeyCode::Right)) {
+			(true, false) => 0.,
+			(false, true) => 2.,
+			_ => 1.,
+		};
+		self.distance += self.speed * throttle * context.delta_time.as_secs_f32();
+		// Closed splines loop around while open ones stop at either end
+		self.distance = if self.spline.closed && length > 0. {
+			self.distance.rem_euclid(length)
+		} else {
+			self.distance.clamp(0., length)
+		};
+
+		let t = self.arc_lengths.parameter_at(self.distance);
+		entity.transform.location = self.spline.point(t).cast::<f64>().unwrap();
+
+		let direction = self.spline.derivative(t) * self.speed.signum();
+		if direction.magnitude2() > f32::MIN_POSITIVE {
+			// The view matrix rotation maps world space into the look space, so its inverse orients the entity
+			let direction = direction.normalize().cast::<f64>().unwrap();
+			let up = if direction.y.abs() > 0.999 { Vector3::unit_z() } else { Vector3::unit_y() };
+			entity.transform.rotation = Quaternion::from(Matrix3::look_to_rh(direction, up)).invert();
+		}
+	}
+}
@@ -0,0 +1,226 @@
+use crate::behavior::{Behavior, BehaviorContext};
+use crate::entity::Entity;
+
+use cgmath::{InnerSpace, Matrix3, MetricSpace, Point3, Quaternion, Rotation, Vector3, Zero};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Moves toward a target location, slowing down once inside the arrival radius so it settles instead of overshooting
+#[derive(Debug)]
+pub struct Seek {
+	pub target: Point3<f64>,
+	pub max_speed: f64,
+	pub arrival_radius: f64,
+}
+
+impl Seek {
+	pub fn new(target: Point3<f64>, max_speed: f64) -> Self {
+		Self {
+			target,
+			max_speed,
+			arrival_radius: 1.,
+		}
+	}
+}
+
+impl Behavior for Seek {
+	fn update(&mut self, entity: &mut Entity, context: &BehaviorContext) {
+		move_towards(entity, self.target, self.max_speed, self.arrival_radius, context.delta_time.as_secs_f64());
+	}
+}
+
+// Moves directly away from a threat location until it is farther than the panic distance
+#[derive(Debug)]
+pub struct Flee {
+	pub threat: Point3<f64>,
+	pub max_speed: f64,
+	pub panic_distance: f64,
+}
+
+impl Flee {
+	pub fn new(threat: Point3<f64>, max_speed: f64, panic_distance: f64) -> Self {
+		Self { threat, max_speed, panic_distance }
+	}
+}
+
+impl Behavior for Flee {
+	fn update(&mut self, entity: &mut Entity, context: &BehaviorContext) {
+		let away = entity.transform.location - self.threat;
+		let distance = away.magnitude();
+		if distance >= self.panic_distance {
+			return;
+		}
+
+		// Standing exactly on the threat has no escape direction, so pick an arbitrary one
+		let direction = if distance > f64::EPSILON { away / distance } else { Vector3::unit_x() };
+		entity.transform.location += direction * self.max_speed * context.delta_time.as_secs_f64();
+	}
+}
+
+// Drifts around on the horizontal plane by nudging its heading with a small random jitter each update, turning back toward the center when it strays too far
+#[derive(Debug)]
+pub struct Wander {
+	pub center: Point3<f64>,
+	pub leash_distance: f64,
+	pub max_speed: f64,
+	// Maximum change of heading in radians per second
+	pub jitter: f64,
+	heading: f64,
+	rng: StdRng,
+}
+
+impl Wander {
+	pub fn new(center: Point3<f64>, leash_distance: f64, max_speed: f64, seed: u64) -> Self {
+		let mut rng = StdRng::seed_from_u64(seed);
+		let heading = rng.gen_range(0. ..std::f64::consts::TAU);
+
+		Self {
+			center,
+			leash_distance,
+			max_speed,
+			jitter: 2.,
+			heading,
+			rng,
+		}
+	}
+}
+
+impl Behavior for Wander {
+	fn update(&mut self, entity: &mut Entity, context: &BehaviorContext) {
+		let delta_time = context.delta_time.as_secs_f64();
+
+		let to_center = self.center - entity.transform.location;
+		if to_center.x * to_center.x + to_center.z * to_center.z > self.leash_distance * self.leash_distance {
+			self.heading = to_center.z.atan2(to_center.x);
+		} else {
+			self.heading += self.rng.gen_range(-1. ..=1.) * self.jitter * delta_time;
+		}
+
+		let direction = Vector3::new(self.heading.cos(), 0., self.heading.sin());
+		entity.transform.location += direction * self.max_speed * delta_time;
+	}
+}
+
+// Visits each waypoint in order, either looping back to the first or reversing direction at the ends
+#[derive(Debug)]
+pub struct Patrol {
+	pub waypoints: Vec<Point3<f64>>,
+	pub max_speed: f64,
+	pub arrival_radius: f64,
+	pub ping_pong: bool,
+	current: usize,
+	reversed: bool,
+}
+
+impl Patrol {
+	pub fn new(waypoints: Vec<Point3<f64>>, max_speed: f64, ping_pong: bool) -> Self {
+		Self {
+			waypoints,
+			max_speed,
+			arrival_radius: 0.1,
+			ping_pong,
+			current: 0,
+			reversed: false,
+		}
+	}
+
+	fn advance(&mut self) {
+		let last = self.waypoints.len() - 1;
+
+		if !self.ping_pong {
+			self.current = if self.current == last { 0 } else { self.current + 1 };
+			return;
+		}
+
+		if (self.reversed && self.current == 0) || (!self.reversed && self.current == last) {
+			self.reversed = !self.reversed;
+		}
+		if last > 0 {
+			self.current = if self.reversed { self.current - 1 } else { self.current + 1 };
+		}
+	}
+}
+
+impl Behavior for Patrol {
+	fn update(&mut self, entity: &mut Entity, context: &BehaviorContext) {
+		if self.waypoints.is_empty() {
+			return;
+		}
+
+		let waypoint = self.waypoints[self.current];
+		if entity.transform.location.distance(waypoint) <= self.arrival_radius {
+			self.advance();
+		}
+
+		// Patrols keep full speed through waypoints, so there is no arrival slowdown here
+		move_towards(entity, self.waypoints[self.current], self.max_speed, 0., context.delta_time.as_secs_f64());
+	}
+}
+
+// Turns the entity so its forward (-Z) axis faces the target, limited to a maximum turn rate
+#[derive(Debug)]
+pub struct LookAt {
+	pub target: Point3<f64>,
+	// Another entity whose location the target follows, for as long as there's an entity by that name
+	pub target_entity: Option<String>,
+	// Radians per second
+	pub turn_rate: f64,
+}
+
+impl LookAt {
+	pub fn new(target: Point3<f64>, turn_rate: f64) -> Self {
+		Self {
+			target,
+			target_entity: None,
+			turn_rate,
+		}
+	}
+
+	pub fn at_entity(target_entity: String, turn_rate: f64) -> Self {
+		Self {
+			target: Point3::new(0., 0., 0.),
+			target_entity: Some(target_entity),
+			turn_rate,
+		}
+	}
+}
+
+impl Behavior for LookAt {
+	fn update(&mut self, entity: &mut Entity, context: &BehaviorContext) {
+		if let Some(target_transform) = self.target_entity.as_deref().and_then(|name| context.world_transform(name)) {
+			self.target = target_transform.location;
+		}
+
+		let direction = self.target - entity.transform.location;
+		if direction.is_zero() {
+			return;
+		}
+
+		// The view matrix rotation maps world space into the look space, so its inverse orients the entity
+		let up = if direction.normalize().y.abs() > 0.999 { Vector3::unit_z() } else { Vector3::unit_y() };
+		let target_rotation = Quaternion::from(Matrix3::look_to_rh(direction, up)).invert();
+
+		let current_rotation = entity.transform.rotation;
+		let remaining_angle = 2. * current_rotation.dot(target_rotation).abs().min(1.).acos();
+		let max_step = self.turn_rate * context.delta_time.as_secs_f64();
+
+		entity.transform.rotation = if remaining_angle <= max_step {
+			target_rotation
+		} else {
+			current_rotation.slerp(target_rotation, max_step / remaining_angle)
+		};
+	}
+}
+
+fn move_towards(entity: &mut Entity, target: Point3<f64>, max_speed: f64, arrival_radius: f64, delta_time: f64) {
+	let offset = target - entity.transform.location;
+	let distance = offset.magnitude();
+	if distance <= f64::EPSILON {
+		return;
+	}
+
+	let speed = if distance < arrival_radius { max_speed * distance / arrival_radius } else { max_speed };
+	let step = (speed * delta_time).min(distance);
+
+	entity.transform.location += offset / distance * step;
+}
@@ -1,13 +1,21 @@
 use crate::camera::Camera;
 use crate::context::Context;
-use crate::debug_buffer::DebugBuffer;
 use crate::instance::InstanceRaw;
 use crate::light::SceneLighting;
-use crate::mesh::{ModelVertex, Vertex};
+use crate::mesh::VertexFormat;
 
-use std::path::Path;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 use wgpu::{BindGroupLayout, PipelineLayout};
 
+// Every pipeline built from a `Shader` uses the same bind group slots, with a placeholder in any slot it has no use for
+pub const GLOBALS_BIND_GROUP: u32 = 0;
+pub const CAMERA_BIND_GROUP: u32 = 1;
+pub const LIGHTING_BIND_GROUP: u32 = 2;
+pub const MATERIAL_BIND_GROUP: u32 = 3;
+
 pub struct Shader {
 	pub name: String,
 	pub bind_group_layout: BindGroupLayout,
@@ -16,7 +24,11 @@ pub struct Shader {
 	pub shader_bindings: Vec<ShaderBinding>,
 	pub includes_camera: bool,
 	pub includes_lighting: bool,
-	pub includes_debugging: bool,
+	pub vertex_format: VertexFormat,
+	// Kept to rebuild the pipeline when the shader's code changes, or `None` for compute pipelines which need nothing beyond the layout
+	render_pipeline_state: Option<RenderPipelineState>,
+	// Pipelines built for the polygon modes that aren't in use, kept so switching back and forth doesn't rebuild them each time
+	other_polygon_mode_pipelines: HashMap<wgpu::PolygonMode, wgpu::RenderPipeline>,
 }
 
 impl Shader {
@@ -29,20 +41,23 @@ impl Shader {
 			label: Some(format!("Shader \"{}\" bind group layout", file).as_str()),
 		});
 
-		let (camera_layout, lighting_layout, debug_layout) = if let PipelineOptions::RenderPipeline(render_options) = &options {
+		let (camera_layout, lighting_layout) = if let PipelineOptions::RenderPipeline(render_options) = &options {
 			let camera_layout = render_options.scene_camera.map(|camera| &camera.camera_bind_group_layout);
 			let lighting_layout = render_options.scene_lighting.map(|lighting| &lighting.light_bind_group_layout);
-			let debug_layout = render_options.scene_debug_buffer.map(|debug_buffer| &debug_buffer.debug_bind_group_layout);
 
-			(camera_layout, lighting_layout, debug_layout)
+			(camera_layout, lighting_layout)
 		} else {
-			(None, None, None)
+			(None, None)
 		};
 
-		let layout = Some(&bind_group_layout);
-		let layouts = vec![camera_layout, lighting_layout, debug_layout, layout].into_iter().flatten().collect::<Vec<_>>();
-
-		let bind_group_layouts = layouts.as_slice();
+		// In the order of the bind group slots
+		let placeholder_layout = &context.placeholder_bind_group_layout;
+		let bind_group_layouts = &[
+			&context.globals.globals_bind_group_layout,
+			camera_layout.unwrap_or(placeholder_layout),
+			lighting_layout.unwrap_or(placeholder_layout),
+			&bind_group_layout,
+		];
 		let pipeline_layout = context.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
 			label: Some(format!("Shader \"{}\" pipeline layout", file).as_str()),
 			bind_group_layouts,
@@ -58,30 +73,33 @@ impl Shader {
 			source: wgpu::ShaderSource::Wgsl(shader_code.into()),
 		};
 
-		let (mut includes_camera, mut includes_lighting, mut includes_debugging) = (false, false, false);
+		let (mut includes_camera, mut includes_lighting) = (false, false);
+		let mut vertex_format = VertexFormat::Model;
+		let mut render_pipeline_state = None;
 
 		let pipeline = match options {
 			PipelineOptions::RenderPipeline(render_options) => {
 				includes_camera = render_options.scene_camera.is_some();
 				includes_lighting = render_options.scene_lighting.is_some();
-				includes_debugging = render_options.scene_debug_buffer.is_some();
-
-				let vertex_layouts = if render_options.use_instances {
-					vec![ModelVertex::layout(), InstanceRaw::layout()]
-				} else {
-					vec![ModelVertex::layout()]
+				vertex_format = render_options.vertex_format;
+
+				let state = RenderPipelineState {
+					out_color_formats: render_options.out_color_formats,
+					depth_format: render_options.depth_format,
+					vertex_format: render_options.vertex_format,
+					use_instances: render_options.use_instances,
+					blend: render_options.blend,
+					depth_write: render_options.depth_write,
+					cull_mode: render_options.cull_mode,
+					sample_count: render_options.sample_count,
+					alpha_to_coverage: render_options.alpha_to_coverage,
+					// Without support the pipeline is rasterized normally, which only loses the coverage of the thinnest triangles
+					conservative: render_options.conservative_rasterization && context.gpu_features.conservative_rasterization,
+					depth_bias: render_options.depth_bias,
+					polygon_mode: wgpu::PolygonMode::Fill,
 				};
-				let vertex_layouts = vertex_layouts.as_slice();
-
-				let render_pipeline = create_render_pipeline(
-					&context.device,
-					&pipeline_layout,
-					render_options.out_color_formats,
-					render_options.depth_format,
-					render_options.blend,
-					vertex_layouts,
-					shader_module_descriptor,
-				);
+				let render_pipeline = create_render_pipeline(&context.device, &pipeline_layout, &state, shader_module_descriptor);
+				render_pipeline_state = Some(state);
 
 				PipelineType::RenderPipeline(render_pipeline)
 			}
@@ -100,11 +118,164 @@ impl Shader {
 			shader_bindings: in_shader_bindings,
 			includes_camera,
 			includes_lighting,
-			includes_debugging,
+			vertex_format,
+			render_pipeline_state,
+			other_polygon_mode_pipelines: HashMap::new(),
+		}
+	}
+
+	// Rebuilds the pipeline from the shader's file, keeping the old pipeline if the new code doesn't compile
+	// The bindings are declared in the engine rather than the shader, so the layouts stay the same and the materials' bind groups remain valid
+	pub fn reload(&mut self, context: &Context, directory: &Path) -> Result<()> {
+		let shader_code = std::fs::read_to_string(directory.join("shaders").join(&self.name))?;
+
+		// The code is parsed and validated with the same naga wgpu uses before it reaches the device, whose error handler would panic on it instead
+		let module = naga::front::wgsl::parse_str(&shader_code).map_err(|error| anyhow!("{}", error.emit_to_string(&shader_code)))?;
+		naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+			.validate(&module)
+			.map_err(|error| anyhow!("{}", error))?;
+
+		let label = format!("Shader \"{}\" module descriptor", self.name);
+		let shader_module_descriptor = wgpu::ShaderModuleDescriptor {
+			label: Some(label.as_str()),
+			source: wgpu::ShaderSource::Wgsl(shader_code.into()),
+		};
+
+		let pipeline = match &self.render_pipeline_state {
+			Some(state) => PipelineType::RenderPipeline(create_render_pipeline(&context.device, &self.pipeline_layout, state, shader_module_descriptor)),
+			None => PipelineType::ComputePipeline(create_compute_pipeline(&context.device, &self.pipeline_layout, shader_module_descriptor)),
+		};
+
+		self.pipeline = pipeline;
+		// The cached pipelines were built from the old code
+		self.other_polygon_mode_pipelines.clear();
+		Ok(())
+	}
+
+	// Switches the render pipeline to rasterize its triangles filled, as lines, or as points, building the pipeline for that mode the first time it's used
+	// Modes the GPU doesn't support are refused, leaving the current pipeline in place
+	pub fn set_polygon_mode(&mut self, context: &Context, directory: &Path, polygon_mode: wgpu::PolygonMode) -> Result<()> {
+		let state = match &mut self.render_pipeline_state {
+			Some(state) => state,
+			None => return Err(anyhow!("Shader {} is a compute shader, which has no polygon mode", self.name)),
+		};
+		if state.polygon_mode == polygon_mode {
+			return Ok(());
+		}
+
+		let supported = match polygon_mode {
+			wgpu::PolygonMode::Fill => true,
+			wgpu::PolygonMode::Line => context.gpu_features.polygon_mode_line,
+			wgpu::PolygonMode::Point => context.gpu_features.polygon_mode_point,
+		};
+		if !supported {
+			return Err(anyhow!("The GPU doesn't support the {:?} polygon mode", polygon_mode));
+		}
+
+		let pipeline = match self.other_polygon_mode_pipelines.remove(&polygon_mode) {
+			Some(pipeline) => pipeline,
+			None => {
+				let shader_code = std::fs::read_to_string(directory.join("shaders").join(&self.name))?;
+				let label = format!("Shader \"{}\" module descriptor", self.name);
+				let shader_module_descriptor = wgpu::ShaderModuleDescriptor {
+					label: Some(label.as_str()),
+					source: wgpu::ShaderSource::Wgsl(shader_code.into()),
+				};
+
+				let state = RenderPipelineState { polygon_mode, ..state.clone() };
+				create_render_pipeline(&context.device, &self.pipeline_layout, &state, shader_module_descriptor)
+			}
+		};
+
+		if let PipelineType::RenderPipeline(previous_pipeline) = std::mem::replace(&mut self.pipeline, PipelineType::RenderPipeline(pipeline)) {
+			self.other_polygon_mode_pipelines.insert(state.polygon_mode, previous_pipeline);
+		}
+		state.polygon_mode = polygon_mode;
+
+		Ok(())
+	}
+}
+
+// Notices when shader files are saved, so pipelines can be rebuilt while the engine runs
+// This watches the source assets rather than the copy the engine loads from, since that copy is only refreshed by building again
+pub struct ShaderWatcher {
+	directory: PathBuf,
+	modified_times: HashMap<String, SystemTime>,
+	last_check: Instant,
+}
+
+impl ShaderWatcher {
+	// Checking the modification times is cheap, but there's no need to do it every frame
+	const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+	pub fn new(directory: &Path) -> Self {
+		let mut watcher = Self {
+			directory: directory.to_path_buf(),
+			modified_times: HashMap::new(),
+			last_check: Instant::now(),
+		};
+		watcher.modified_times = watcher.read_modified_times();
+		watcher
+	}
+
+	// The assets folder the watched shaders are in, for `Shader::reload`
+	pub fn directory(&self) -> &Path {
+		&self.directory
+	}
+
+	// The names of the shader files saved since the last call
+	pub fn changed_shaders(&mut self) -> Vec<String> {
+		if self.last_check.elapsed() < Self::CHECK_INTERVAL {
+			return vec![];
 		}
+		self.last_check = Instant::now();
+
+		let modified_times = self.read_modified_times();
+		let changed = modified_times
+			.iter()
+			.filter(|(file, time)| self.modified_times.get(*file) != Some(*time))
+			.map(|(file, _)| file.clone())
+			.collect();
+		self.modified_times = modified_times;
+
+		changed
+	}
+
+	fn read_modified_times(&self) -> HashMap<String, SystemTime> {
+		let entries = match std::fs::read_dir(self.directory.join("shaders")) {
+			Ok(entries) => entries,
+			Err(_) => return HashMap::new(),
+		};
+
+		entries
+			.flatten()
+			.filter_map(|entry| {
+				let file = entry.file_name().into_string().ok()?;
+				let modified = entry.metadata().and_then(|metadata| metadata.modified()).ok()?;
+				file.ends_with(".wgsl").then_some((file, modified))
+			})
+			.collect()
 	}
 }
 
+// The parts of a render pipeline's options that are needed again to rebuild it
+#[derive(Clone)]
+struct RenderPipelineState {
+	out_color_formats: Vec<wgpu::TextureFormat>,
+	depth_format: Option<wgpu::TextureFormat>,
+	vertex_format: VertexFormat,
+	use_instances: bool,
+	blend: Option<wgpu::BlendState>,
+	depth_write: bool,
+	cull_mode: Option<wgpu::Face>,
+	sample_count: u32,
+	alpha_to_coverage: bool,
+	conservative: bool,
+	depth_bias: wgpu::DepthBiasState,
+	// Always starts out filled, and is only changed for inspecting the geometry by `Shader::set_polygon_mode`
+	polygon_mode: wgpu::PolygonMode,
+}
+
 fn build_bind_group_layout_entries(bindings: &[ShaderBinding]) -> Vec<wgpu::BindGroupLayoutEntry> {
 	let mut binding_index = 0;
 
@@ -145,8 +316,8 @@ fn build_bind_group_layout_entries(bindings: &[ShaderBinding]) -> Vec<wgpu::Bind
 						binding: binding + 1,
 						visibility: texture.visible_in_stages,
 						ty: wgpu::BindingType::Sampler {
-							comparison: false,
-							filtering: texture.sampled_value_data_type == wgpu::TextureSampleType::Float { filterable: true },
+							comparison: texture.comparison,
+							filtering: texture.comparison || texture.sampled_value_data_type == wgpu::TextureSampleType::Float { filterable: true },
 						},
 						count: None,
 					},
@@ -171,40 +342,32 @@ fn build_bind_group_layout_entries(bindings: &[ShaderBinding]) -> Vec<wgpu::Bind
 		.collect()
 }
 
-fn create_render_pipeline(
-	device: &wgpu::Device,
-	layout: &wgpu::PipelineLayout,
-	color_formats: Vec<wgpu::TextureFormat>,
-	depth_format: Option<wgpu::TextureFormat>,
-	blend: bool,
-	vertex_layouts: &[wgpu::VertexBufferLayout],
-	shader_module_descriptor: wgpu::ShaderModuleDescriptor,
-) -> wgpu::RenderPipeline {
+fn create_render_pipeline(device: &wgpu::Device, layout: &wgpu::PipelineLayout, render_options: &RenderPipelineState, shader_module_descriptor: wgpu::ShaderModuleDescriptor) -> wgpu::RenderPipeline {
 	let shader = device.create_shader_module(&shader_module_descriptor);
 
+	let vertex_layouts = if render_options.use_instances {
+		vec![render_options.vertex_format.layout(), InstanceRaw::layout()]
+	} else {
+		vec![render_options.vertex_format.layout()]
+	};
+
 	device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
 		label: Some("Render Pipeline"),
 		layout: Some(layout),
 		vertex: wgpu::VertexState {
 			module: &shader,
 			entry_point: "main",
-			buffers: vertex_layouts,
+			buffers: vertex_layouts.as_slice(),
 		},
 		fragment: Some(wgpu::FragmentState {
 			module: &shader,
 			entry_point: "main",
-			targets: color_formats
-				.into_iter()
+			targets: render_options
+				.out_color_formats
+				.iter()
 				.map(|format| wgpu::ColorTargetState {
-					format,
-					blend: if blend {
-						Some(wgpu::BlendState {
-							alpha: wgpu::BlendComponent::REPLACE,
-							color: wgpu::BlendComponent::REPLACE,
-						})
-					} else {
-						None
-					},
+					format: *format,
+					blend: render_options.blend,
 					write_mask: wgpu::ColorWrites::ALL,
 				})
 				.collect::<Vec<_>>()
@@ -214,25 +377,26 @@ fn create_render_pipeline(
 			topology: wgpu::PrimitiveTopology::TriangleList,
 			strip_index_format: None,
 			front_face: wgpu::FrontFace::Ccw,
-			cull_mode: Some(wgpu::Face::Back),
-			// Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-			polygon_mode: wgpu::PolygonMode::Fill,
+			cull_mode: render_options.cull_mode,
+			// Line requires Features::POLYGON_MODE_LINE and Point requires Features::POLYGON_MODE_POINT
+			polygon_mode: render_options.polygon_mode,
 			// Requires Features::DEPTH_CLAMPING
 			clamp_depth: false,
 			// Requires Features::CONSERVATIVE_RASTERIZATION
-			conservative: false,
+			conservative: render_options.conservative,
 		},
-		depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+		depth_stencil: render_options.depth_format.map(|format| wgpu::DepthStencilState {
 			format,
-			depth_write_enabled: true,
+			depth_write_enabled: render_options.depth_write,
 			depth_compare: wgpu::CompareFunction::Less,
 			stencil: wgpu::StencilState::default(),
-			bias: wgpu::DepthBiasState::default(),
+			bias: render_options.depth_bias,
 		}),
 		multisample: wgpu::MultisampleState {
-			count: 1,
+			count: render_options.sample_count,
 			mask: !0,
-			alpha_to_coverage_enabled: false,
+			// Coverage can only be spread across multiple samples, so single sampled pipelines ignore the option
+			alpha_to_coverage_enabled: render_options.alpha_to_coverage && render_options.sample_count > 1,
 		},
 	})
 }
@@ -261,11 +425,75 @@ pub enum PipelineOptions<'a> {
 pub struct RenderPipelineOptions<'a> {
 	pub out_color_formats: Vec<wgpu::TextureFormat>,
 	pub depth_format: Option<wgpu::TextureFormat>,
+	pub vertex_format: VertexFormat,
 	pub use_instances: bool,
 	pub scene_camera: Option<&'a Camera>,
 	pub scene_lighting: Option<&'a SceneLighting>,
-	pub scene_debug_buffer: Option<&'a DebugBuffer>,
-	pub blend: bool,
+	// Applied to every color target, or `None` to overwrite them
+	pub blend: Option<wgpu::BlendState>,
+	// Transparent geometry tests against the depth buffer without writing to it
+	pub depth_write: bool,
+	// Mirror reflections flip the winding order, so they cull the front faces instead of the back faces
+	pub cull_mode: Option<wgpu::Face>,
+	// Must match the sample count of the attachments the pipeline draws into
+	pub sample_count: u32,
+	// Used by cutout materials like foliage to turn their alpha into MSAA sample coverage, giving anti-aliased edges without sorting
+	pub alpha_to_coverage: bool,
+	// Rasterizes every pixel a triangle touches, where the GPU supports it, so voxelization doesn't miss triangles thinner than a voxel
+	pub conservative_rasterization: bool,
+	// Pushes the written depth away from the camera, by a constant amount and by more on surfaces at a steep angle to it, so coplanar geometry like decals and shadow casters doesn't z-fight
+	// Only takes effect with a depth format, and is left at its default of no bias by everything else
+	pub depth_bias: wgpu::DepthBiasState,
+}
+
+impl<'a> RenderPipelineOptions<'a> {
+	// Depth-only preset for drawing a material's models into the shadow maps, with no color targets and the casters kept from shadowing themselves by the given culling and bias
+	// Uses the full model vertex format so the shadow shader can read the UVs to cut out alpha-tested surfaces
+	pub fn shadow_caster(scene_camera: &'a Camera, caster: ShadowCasterOptions) -> Self {
+		Self {
+			out_color_formats: vec![],
+			depth_format: Some(wgpu::TextureFormat::Depth32Float),
+			vertex_format: VertexFormat::Model,
+			use_instances: true,
+			scene_camera: Some(scene_camera),
+			scene_lighting: None,
+			blend: None,
+			depth_write: true,
+			cull_mode: if caster.cull_front_faces { Some(wgpu::Face::Front) } else { None },
+			sample_count: 1,
+			alpha_to_coverage: false,
+			conservative_rasterization: false,
+			depth_bias: caster.depth_bias,
+		}
+	}
+}
+
+// How the depth written by a shadow caster is kept behind the surfaces it lights, so they don't shadow themselves with acne
+pub struct ShadowCasterOptions {
+	// Draws only the faces pointing away from the light, whose depth lies behind the lit faces, which works for closed meshes
+	// Two-sided meshes are open and have no back faces to draw, so they leave this off and rely on the depth bias alone
+	pub cull_front_faces: bool,
+	// Surfaces at a steep angle to the light cover a wide depth range within each texel, so the slope-scaled part pushes them back by more than the ones facing it
+	pub depth_bias: wgpu::DepthBiasState,
+}
+
+impl ShadowCasterOptions {
+	pub const CLOSED: Self = Self {
+		cull_front_faces: true,
+		depth_bias: wgpu::DepthBiasState {
+			constant: 2,
+			slope_scale: 2.,
+			clamp: 0.01,
+		},
+	};
+	pub const TWO_SIDED: Self = Self {
+		cull_front_faces: false,
+		depth_bias: wgpu::DepthBiasState {
+			constant: 4,
+			slope_scale: 4.,
+			clamp: 0.02,
+		},
+	};
 }
 
 pub struct ComputePipelineOptions {}
@@ -298,6 +526,8 @@ pub struct ShaderBindingTexture {
 	pub multisampled: bool,
 	pub dimensions: wgpu::TextureViewDimension,
 	pub sampled_value_data_type: wgpu::TextureSampleType,
+	// Depth textures sampled for shadows compare against a reference depth, which their sampler filters across neighboring texels
+	pub comparison: bool,
 }
 impl Default for ShaderBindingTexture {
 	fn default() -> Self {
@@ -306,6 +536,7 @@ impl Default for ShaderBindingTexture {
 			multisampled: false,
 			dimensions: wgpu::TextureViewDimension::D2,
 			sampled_value_data_type: wgpu::TextureSampleType::Float { filterable: true },
+			comparison: false,
 		}
 	}
 }
@@ -0,0 +1,320 @@
+use crate::camera::{Camera, CameraUniform, OrthographicProjection, PerspectiveProjection, Projection, OPENGL_TO_WGPU_MATRIX};
+use crate::context::Context;
+use crate::entity::Entity;
+use crate::frame_texture::FrameTexture;
+use crate::light::{Lamp, ShadowFilter, Sun};
+
+use cgmath::{Deg, EuclideanSpace, InnerSpace, Matrix4, Point3, SquareMatrix, Vector3, Vector4};
+use wgpu::util::DeviceExt;
+
+pub const SHADOW_CASCADE_COUNT: usize = 4;
+// Each cascade gets the same resolution, so the nearer ones spread it over less of the view and show finer shadows
+const SHADOW_MAP_RESOLUTION: u32 = 2048;
+// How far behind each cascade's slice of the view its light camera starts, so objects outside the view still cast shadows into it
+const SHADOW_CASTER_DISTANCE: f32 = 50.;
+// Each cascade's quarter of the moments atlas, at half its depth map's resolution since the moments are blurred anyway
+const SHADOW_MOMENTS_RESOLUTION: u32 = SHADOW_MAP_RESOLUTION / 2;
+// Blends between splitting the view's depth range evenly (0) and logarithmically (1), where logarithmic matches how perspective shrinks distant texels
+const CASCADE_SPLIT_LAMBDA: f32 = 0.8;
+// Each face of the lamp's cube covers a quarter of the way around it, so it gets half the resolution of a cascade
+const POINT_SHADOW_RESOLUTION: u32 = 1024;
+// Nearest distance from the lamp that can cast a shadow, since the faces' perspective loses depth precision quickly closer in
+const POINT_SHADOW_NEAR: f32 = 0.05;
+// The direction each face of the cube looks, and which way is up in it, in the order of the depth cube's layers
+const POINT_SHADOW_FACES: [([f32; 3], [f32; 3]); 6] = [
+	([1., 0., 0.], [0., 1., 0.]),
+	([-1., 0., 0.], [0., 1., 0.]),
+	([0., 1., 0.], [0., 0., 1.]),
+	([0., -1., 0.], [0., 0., 1.]),
+	([0., 0., 1.], [0., 1., 0.]),
+	([0., 0., -1.], [0., 1., 0.]),
+];
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadowUniform {
+	cascade_vp_matrices: [[[f32; 4]; 4]; SHADOW_CASCADE_COUNT],
+	// Distance along the view direction where each cascade ends
+	cascade_far_distances: [f32; SHADOW_CASCADE_COUNT],
+	// World space width of one texel of each cascade, which sets how far a surface is pushed out of its own shadow
+	cascade_texel_sizes: [f32; SHADOW_CASCADE_COUNT],
+	// World space distance between each cascade's near and far planes, for turning the differences between its depths into meters
+	cascade_depth_ranges: [f32; SHADOW_CASCADE_COUNT],
+	// Zero while the scene has no sun to cast shadows
+	enabled: u32,
+	// 0 for PCF, 1 for exponential, 2 for variance, and 3 for PCSS
+	filter: u32,
+	// How far the penumbra widens per meter between an occluder and the surface behind it, from the size of the sun's disk
+	penumbra_spread: f32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointShadowUniform {
+	face_vp_matrices: [[[f32; 4]; 4]; 6],
+	position: [f32; 3],
+	// Zero while the scene has no lamp to cast shadows
+	enabled: u32,
+	// World space width of one texel per meter from the lamp, since the faces' texels grow with distance
+	texel_scale: f32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: [u32; 3],
+}
+
+// One slice of the view's depth range, rendered from the sun into its own layer of the depth array
+pub struct ShadowCascade {
+	pub camera: Camera,
+	// The scene generation and view projection the layer was last drawn with, when only static models were in it, so it can be kept until either changes
+	pub cached: Option<(u64, Matrix4<f32>)>,
+}
+
+// Cascaded shadow maps for the scene's sun, with the view split into slices by distance that each get a depth map fit around them
+// The PBR shading pass picks the cascade covering each fragment and compares its depth against the one seen from the sun
+// Filters other than PCF also fill the moments atlas, a quarter for each cascade, which exponential and variance shadows blur and PCSS searches for occluders in
+pub struct ShadowMaps {
+	pub cascades: Vec<ShadowCascade>,
+	// A layer for each cascade, bound as one array so the cascades together take up only a single texture and sampler of the shading pass
+	pub depth_array: FrameTexture,
+	pub enabled: bool,
+	pub filter: ShadowFilter,
+	pub shadow_buffer: wgpu::Buffer,
+	// 32 bit floats keep the exponentials and squared depths precise, but can't be filtered by a sampler, so they're read texel by texel through this one
+	pub moments_atlas: FrameTexture,
+	pub moments_blur_atlas: FrameTexture,
+	pub moments_sampler: wgpu::Sampler,
+	// The horizontal then vertical direction of the two blur passes
+	pub blur_direction_buffers: [wgpu::Buffer; 2],
+}
+
+impl ShadowMaps {
+	pub fn new(context: &Context) -> Self {
+		let cascades = (0..SHADOW_CASCADE_COUNT)
+			.map(|_| {
+				// The projection is replaced every frame by one fit around the cascade's slice of the view
+				let projection = OrthographicProjection::new(SHADOW_MAP_RESOLUTION, SHADOW_MAP_RESOLUTION, 1., 0., 1.);
+				let mut camera = Camera::new(context, Projection::Orthographic(projection));
+				camera.post_effects.clear();
+
+				ShadowCascade { camera, cached: None }
+			})
+			.collect();
+
+		let depth_array = FrameTexture::array(
+			&context.device,
+			(SHADOW_MAP_RESOLUTION, SHADOW_MAP_RESOLUTION),
+			SHADOW_CASCADE_COUNT as u32,
+			wgpu::TextureFormat::Depth32Float,
+			"Shadow Cascades frame texture",
+			Some(wgpu::CompareFunction::LessEqual),
+		);
+
+		let shadow_buffer = context.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Shadow Buffer"),
+			contents: bytemuck::cast_slice(&[<ShadowUniform as bytemuck::Zeroable>::zeroed()]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
+
+		let moments_atlas_size = (SHADOW_MOMENTS_RESOLUTION * 2, SHADOW_MOMENTS_RESOLUTION * 2);
+		let moments_atlas = FrameTexture::with_size(&context.device, moments_atlas_size, wgpu::TextureFormat::Rg32Float, "Shadow Moments Atlas frame texture", None);
+		let moments_blur_atlas = FrameTexture::with_size(&context.device, moments_atlas_size, wgpu::TextureFormat::Rg32Float, "Shadow Moments Blur Atlas frame texture", None);
+		let moments_sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some("Shadow Moments sampler"),
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			address_mode_w: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Nearest,
+			min_filter: wgpu::FilterMode::Nearest,
+			mipmap_filter: wgpu::FilterMode::Nearest,
+			..Default::default()
+		});
+
+		let blur_direction_buffers = [[1., 0., 0., 0.], [0., 1., 0., 0.]].map(|direction: [f32; 4]| {
+			context.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label: Some("Shadow Blur Direction Buffer"),
+				contents: bytemuck::cast_slice(&direction),
+				usage: wgpu::BufferUsages::UNIFORM,
+			})
+		});
+
+		Self {
+			cascades,
+			depth_array,
+			enabled: false,
+			filter: ShadowFilter::Pcf,
+			shadow_buffer,
+			moments_atlas,
+			moments_blur_atlas,
+			moments_sampler,
+			blur_direction_buffers,
+		}
+	}
+
+	// Only the exponential and variance moments are blurred, while PCSS reads them as they are
+	pub fn moments_blurred(&self) -> bool {
+		matches!(self.filter, ShadowFilter::Exponential | ShadowFilter::Variance)
+	}
+
+	// Fits each cascade's light camera around its slice of the view camera's frustum, looking along the sun's direction
+	pub fn update(&mut self, view_camera: &CameraUniform, sun: Option<&Sun>, queue: &wgpu::Queue) {
+		let sun = match sun {
+			Some(sun) if sun.illuminance > 0. && sun.direction.y > 0. => sun,
+			_ => {
+				self.enabled = false;
+				queue.write_buffer(&self.shadow_buffer, 0, bytemuck::cast_slice(&[<ShadowUniform as bytemuck::Zeroable>::zeroed()]));
+				return;
+			}
+		};
+		self.enabled = true;
+		self.filter = sun.shadow_filter;
+
+		// The frustum's corners on its near and far planes, which the slices lie between
+		let inverse_vp = view_camera.vp_matrix().invert().unwrap();
+		let corner = |x: f32, y: f32, z: f32| {
+			let corner = inverse_vp * Vector4::new(x, y, z, 1.);
+			corner.truncate() / corner.w
+		};
+		let near_corners = [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)].map(|(x, y)| corner(x, y, 0.));
+		let far_corners = [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)].map(|(x, y)| corner(x, y, 1.));
+
+		let inverse_v = view_camera.v_matrix().invert().unwrap();
+		let eye = inverse_v.w.truncate();
+		let forward = -inverse_v.z.truncate().normalize();
+		let near = (near_corners.iter().sum::<Vector3<f32>>() / 4. - eye).dot(forward).max(0.01);
+		let far = (far_corners.iter().sum::<Vector3<f32>>() / 4. - eye).dot(forward).max(near + 0.01);
+
+		// Practical split scheme (Zhang et al. 2006)
+		let split_distance = |index: usize| {
+			let fraction = index as f32 / SHADOW_CASCADE_COUNT as f32;
+			let logarithmic = near * (far / near).powf(fraction);
+			let uniform = near + (far - near) * fraction;
+			CASCADE_SPLIT_LAMBDA * logarithmic + (1. - CASCADE_SPLIT_LAMBDA) * uniform
+		};
+
+		let light_direction = sun.direction.normalize();
+		let up = if light_direction.y.abs() > 0.99 { Vector3::unit_z() } else { Vector3::unit_y() };
+
+		let mut uniform = <ShadowUniform as bytemuck::Zeroable>::zeroed();
+		uniform.enabled = 1;
+		uniform.filter = sun.shadow_filter as u32;
+		uniform.penumbra_spread = 2. * sun.angular_radius.tan();
+		for (index, cascade) in self.cascades.iter_mut().enumerate() {
+			let (slice_near, slice_far) = (split_distance(index), split_distance(index + 1));
+
+			// Points along each corner's ray are spaced linearly by their distance along the view direction
+			let slice_corners = near_corners
+				.iter()
+				.zip(far_corners.iter())
+				.flat_map(|(near_corner, far_corner)| [slice_near, slice_far].map(|distance| *near_corner + (*far_corner - *near_corner) * ((distance - near) / (far - near))))
+				.collect::<Vec<_>>();
+
+			// A bounding sphere keeps the cascade the same size as the view turns, so its texels don't change size and make the edges shimmer
+			let center = slice_corners.iter().sum::<Vector3<f32>>() / slice_corners.len() as f32;
+			let radius = slice_corners.iter().map(|corner| (*corner - center).magnitude()).fold(0., f32::max);
+			let radius = (radius * 16.).ceil() / 16.;
+
+			let light_eye = Point3::from_vec(center + light_direction * (radius + SHADOW_CASTER_DISTANCE));
+			let v = Matrix4::look_at_rh(light_eye, Point3::from_vec(center), up);
+			let depth_range = 2. * radius + SHADOW_CASTER_DISTANCE;
+			let p = OPENGL_TO_WGPU_MATRIX * cgmath::ortho(-radius, radius, -radius, radius, 0., depth_range);
+
+			// Moving the light camera only in whole texels keeps the edges from crawling as the view moves
+			let origin = p * v * Vector4::new(0., 0., 0., 1.);
+			let texel_origin = origin.truncate().truncate() * (SHADOW_MAP_RESOLUTION as f32 / 2.);
+			let offset = (texel_origin.map(f32::round) - texel_origin) * (2. / SHADOW_MAP_RESOLUTION as f32);
+			let p = Matrix4::from_translation(offset.extend(0.)) * p;
+
+			cascade.camera.update_from_matrices(light_eye, v, p, queue);
+
+			uniform.cascade_vp_matrices[index] = (p * v).into();
+			uniform.cascade_far_distances[index] = slice_far;
+			uniform.cascade_texel_sizes[index] = 2. * radius / SHADOW_MAP_RESOLUTION as f32;
+			uniform.cascade_depth_ranges[index] = depth_range;
+		}
+
+		queue.write_buffer(&self.shadow_buffer, 0, bytemuck::cast_slice(&[uniform]));
+	}
+}
+
+// Omnidirectional shadows for the scene's first lamp, with the scene rendered from the lamp into each of the six faces of a depth cube
+// The PBR shading pass picks the face a fragment lies in from the direction to it, then compares its depth against the one seen from the lamp
+pub struct PointShadowMap {
+	pub faces: Vec<Camera>,
+	// The scene generation and view projection each face was last drawn with, when only static models were in it, so it can be kept until either changes
+	pub cached_faces: [Option<(u64, Matrix4<f32>)>; 6],
+	pub depth_cube: FrameTexture,
+	pub enabled: bool,
+	// The lamp sits inside its entity's own models, which are left out of its shadows so they don't hide it from everything
+	pub lamp_entity: Option<String>,
+	pub point_shadow_buffer: wgpu::Buffer,
+}
+
+impl PointShadowMap {
+	pub fn new(context: &Context) -> Self {
+		let faces = (0..POINT_SHADOW_FACES.len())
+			.map(|_| {
+				// The view is replaced every frame by one looking out from the lamp
+				let projection = PerspectiveProjection::new(POINT_SHADOW_RESOLUTION, POINT_SHADOW_RESOLUTION, Deg(90.), POINT_SHADOW_NEAR, 1.);
+				let mut camera = Camera::new(context, Projection::Perspective(projection));
+				camera.post_effects.clear();
+				camera
+			})
+			.collect();
+
+		let depth_cube = FrameTexture::cube(
+			&context.device,
+			POINT_SHADOW_RESOLUTION,
+			wgpu::TextureFormat::Depth32Float,
+			"Point Shadow Cube frame texture",
+			Some(wgpu::CompareFunction::LessEqual),
+		);
+
+		let point_shadow_buffer = context.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Point Shadow Buffer"),
+			contents: bytemuck::cast_slice(&[<PointShadowUniform as bytemuck::Zeroable>::zeroed()]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
+
+		Self {
+			faces,
+			cached_faces: [None; 6],
+			depth_cube,
+			enabled: false,
+			lamp_entity: None,
+			point_shadow_buffer,
+		}
+	}
+
+	// Moves the six face cameras to the lamp, reaching out as far as its light does
+	pub fn update(&mut self, lamp: Option<(&Entity, &Lamp)>, queue: &wgpu::Queue) {
+		let (entity, lamp) = match lamp {
+			Some((entity, lamp)) if lamp.radius > POINT_SHADOW_NEAR => (entity, lamp),
+			_ => {
+				self.enabled = false;
+				self.lamp_entity = None;
+				queue.write_buffer(&self.point_shadow_buffer, 0, bytemuck::cast_slice(&[<PointShadowUniform as bytemuck::Zeroable>::zeroed()]));
+				return;
+			}
+		};
+		self.enabled = true;
+		self.lamp_entity = Some(entity.name.clone());
+
+		let location = entity.world_transform.location;
+		let position = Point3::new(location.x as f32, location.y as f32, location.z as f32);
+		let p = OPENGL_TO_WGPU_MATRIX * cgmath::perspective(Deg(90.), 1., POINT_SHADOW_NEAR, lamp.radius);
+
+		let mut uniform = <PointShadowUniform as bytemuck::Zeroable>::zeroed();
+		uniform.position = position.into();
+		uniform.enabled = 1;
+		uniform.texel_scale = 2. / POINT_SHADOW_RESOLUTION as f32;
+		for (index, (camera, (direction, up))) in self.faces.iter_mut().zip(POINT_SHADOW_FACES).enumerate() {
+			let v = Matrix4::look_at_rh(position, position + Vector3::from(direction), Vector3::from(up));
+			camera.update_from_matrices(position, v, p, queue);
+
+			uniform.face_vp_matrices[index] = (p * v).into();
+		}
+
+		queue.write_buffer(&self.point_shadow_buffer, 0, bytemuck::cast_slice(&[uniform]));
+	}
+}
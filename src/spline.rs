@@ -0,0 +1,354 @@
+use crate::mesh::{generate_tangents, ModelVertex};
+
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Quaternion, Rad, Rotation3, Vector2, Vector3};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SplineKind {
+	// Cubic segments sharing their end points, given as `start, control, control, end, control, control, end, ...`
+	Bezier,
+	// Passes through every point, leaving each in the direction from its previous neighbor to its next one
+	CatmullRom,
+}
+
+// A piecewise cubic curve, evaluated by a parameter running from 0 at the start to the segment count at the end
+// Closed splines join their last point back to the first, with a Bezier spline giving the closing segment's two control points after its last end point
+#[derive(Debug, Clone)]
+pub struct Spline {
+	pub kind: SplineKind,
+	pub points: Vec<Point3<f32>>,
+	pub closed: bool,
+}
+
+impl Spline {
+	pub fn new(kind: SplineKind, points: Vec<Point3<f32>>, closed: bool) -> Self {
+		Self { kind, points, closed }
+	}
+
+	pub fn segment_count(&self) -> usize {
+		let count = self.points.len();
+		match (self.kind, self.closed) {
+			(SplineKind::Bezier, false) => count.saturating_sub(1) / 3,
+			(SplineKind::Bezier, true) => count / 3,
+			(SplineKind::CatmullRom, false) => count.saturating_sub(1),
+			(SplineKind::CatmullRom, true) if count >= 2 => count,
+			(SplineKind::CatmullRom, true) => 0,
+		}
+	}
+
+	// The four Bezier control points of a segment, which Catmull-Rom segments are converted to so both kinds share the evaluation
+	fn segment(&self, index: usize) -> [Point3<f32>; 4] {
+		let count = self.points.len();
+		let point = |index: usize| self.points[index % count];
+
+		match self.kind {
+			SplineKind::Bezier => [point(index * 3), point(index * 3 + 1), point(index * 3 + 2), point(index * 3 + 3)],
+			SplineKind::CatmullRom => {
+				// Open splines have no neighbor beyond their ends, so the end points stand in for them
+				let neighbor = |offset: isize| {
+					let index = index as isize + offset;
+					if self.closed {
+						point(index.rem_euclid(count as isize) as usize)
+					} else {
+						self.points[index.clamp(0, count as isize - 1) as usize]
+					}
+				};
+				let (previous, start, end, next) = (neighbor(-1), neighbor(0), neighbor(1), neighbor(2));
+
+				[start, start + (end - previous) / 6., end - (next - start) / 6., end]
+			}
+		}
+	}
+
+	// Splits the spline's parameter into a segment and how far along that segment it is
+	fn locate(&self, t: f32) -> (usize, f32) {
+		let segments = self.segment_count();
+		let t = t.clamp(0., segments as f32);
+		let index = (t.floor() as usize).min(segments.saturating_sub(1));
+
+		(index, t - index as f32)
+	}
+
+	pub fn point(&self, t: f32) -> Point3<f32> {
+		let (index, t) = self.locate(t);
+		let [p0, p1, p2, p3] = self.segment(index);
+		let s = 1. - t;
+
+		Point3::from_vec(p0.to_vec() * (s * s * s) + p1.to_vec() * (3. * s * s * t) + p2.to_vec() * (3. * s * t * t) + p3.to_vec() * (t * t * t))
+	}
+
+	// The derivative with respect to the parameter, pointing along the curve with a length of its speed
+	pub fn derivative(&self, t: f32) -> Vector3<f32> {
+		let (index, t) = self.locate(t);
+		let [p0, p1, p2, p3] = self.segment(index);
+		let s = 1. - t;
+
+		(p1 - p0) * (3. * s * s) + (p2 - p1) * (6. * s * t) + (p3 - p2) * (3. * t * t)
+	}
+
+	// Samples the curve's length so points can be placed by distance traveled rather than by parameter, which speeds up and slows down with the control points' spacing
+	pub fn arc_lengths(&self, samples_per_segment: usize) -> ArcLengthTable {
+		let sample_count = self.segment_count() * samples_per_segment.max(1);
+
+		let mut parameters = vec![0.];
+		let mut distances = vec![0.];
+		let mut previous = self.point(0.);
+		for sample in 1..=sample_count {
+			let t = sample as f32 / samples_per_segment.max(1) as f32;
+			let point = self.point(t);
+
+			parameters.push(t);
+			distances.push(distances.last().unwrap() + (point - previous).magnitude());
+			previous = point;
+		}
+
+		ArcLengthTable { parameters, distances }
+	}
+
+	// Evenly spaced frames along the whole curve that twist as little as possible, using the double reflection method (Wang et al. 2008)
+	// The up direction starts as close to world up as the curve allows, and on closed splines any twist left over at the seam is spread along the loop so the ends meet
+	pub fn frames(&self, arc_lengths: &ArcLengthTable, count: usize) -> Vec<SplineFrame> {
+		let count = count.max(2);
+		let length = arc_lengths.length();
+
+		let mut frames = (0..count)
+			.map(|index| {
+				let t = arc_lengths.parameter_at(length * index as f32 / (count - 1) as f32);
+				let derivative = self.derivative(t);
+				let tangent = if derivative.magnitude2() > f32::MIN_POSITIVE {
+					derivative.normalize()
+				} else {
+					-Vector3::unit_z()
+				};
+
+				SplineFrame {
+					location: self.point(t),
+					tangent,
+					up: Vector3::unit_y(),
+					right: Vector3::unit_x(),
+				}
+			})
+			.collect::<Vec<_>>();
+
+		let first_tangent = frames[0].tangent;
+		let reference = if first_tangent.y.abs() > 0.999 { Vector3::unit_z() } else { Vector3::unit_y() };
+		frames[0].up = (reference - first_tangent * reference.dot(first_tangent)).normalize();
+
+		for index in 1..count {
+			let (previous, current) = (frames[index - 1], frames[index]);
+
+			// Reflect the previous frame across the plane between the two locations, then across the plane between the reflected and actual tangents
+			let step = current.location - previous.location;
+			let (reflected_up, reflected_tangent) = if step.magnitude2() > f32::MIN_POSITIVE {
+				let reflect = |vector: Vector3<f32>| vector - step * (2. * step.dot(vector) / step.magnitude2());
+				(reflect(previous.up), reflect(previous.tangent))
+			} else {
+				(previous.up, previous.tangent)
+			};
+			let correction = current.tangent - reflected_tangent;
+			let up = if correction.magnitude2() > f32::MIN_POSITIVE {
+				reflected_up - correction * (2. * correction.dot(reflected_up) / correction.magnitude2())
+			} else {
+				reflected_up
+			};
+
+			frames[index].up = (up - current.tangent * up.dot(current.tangent)).normalize();
+		}
+
+		if self.closed {
+			let (first, last) = (frames[0], frames[count - 1]);
+			let twist = last.up.cross(first.up).dot(last.tangent).atan2(last.up.dot(first.up));
+
+			for (index, frame) in frames.iter_mut().enumerate() {
+				let rotation = Quaternion::from_axis_angle(frame.tangent, Rad(twist * index as f32 / (count - 1) as f32));
+				frame.up = rotation * frame.up;
+			}
+		}
+
+		for frame in &mut frames {
+			frame.right = frame.tangent.cross(frame.up);
+		}
+
+		frames
+	}
+
+	// Sweeps a profile along the curve into triangles for a runtime mesh, such as a pipe from a circle or a road from a strip
+	// Rings of the profile are placed every `segment_length`, and the texture repeats every `uv_scale` both around the profile and along the curve
+	pub fn extrude(&self, profile: &Profile, segment_length: f32, uv_scale: f32) -> (Vec<ModelVertex>, Vec<u32>) {
+		if self.segment_count() == 0 || profile.points.len() < 2 {
+			return (vec![], vec![]);
+		}
+
+		let arc_lengths = self.arc_lengths(32);
+		let length = arc_lengths.length();
+		let ring_count = (length / segment_length.max(f32::EPSILON)).ceil().max(1.) as usize + 1;
+		let frames = self.frames(&arc_lengths, ring_count);
+
+		// Closed profiles repeat their first point at the end so the texture can wrap around without a seam in the UVs
+		let mut points = profile.points.clone();
+		if profile.closed {
+			points.extend(profile.points.first());
+		}
+		let normals = profile_normals(&points, profile.closed);
+		let mut u = vec![0.];
+		for pair in points.windows(2) {
+			u.push(u.last().unwrap() + (pair[1] - pair[0]).magnitude() / uv_scale);
+		}
+
+		let mut vertices = Vec::with_capacity(ring_count * points.len());
+		for (ring, frame) in frames.iter().enumerate() {
+			let v = length * ring as f32 / (ring_count - 1) as f32 / uv_scale;
+
+			for ((point, normal), u) in points.iter().zip(&normals).zip(&u) {
+				let position = frame.location + frame.right * point.x + frame.up * point.y;
+				let normal = frame.right * normal.x + frame.up * normal.y;
+
+				vertices.push(ModelVertex {
+					position: position.into(),
+					uv: [*u, v],
+					normal: normal.into(),
+					tangent: [0.; 4],
+				});
+			}
+		}
+
+		let ring_size = points.len() as u32;
+		let mut indices = Vec::with_capacity((ring_count - 1) * (points.len() - 1) * 6);
+		for ring in 0..ring_count as u32 - 1 {
+			for point in 0..ring_size - 1 {
+				let current = ring * ring_size + point;
+				let next_ring = current + ring_size;
+
+				indices.extend([current, next_ring, current + 1, current + 1, next_ring, next_ring + 1]);
+			}
+		}
+
+		generate_tangents(&mut vertices, &indices);
+
+		(vertices, indices)
+	}
+}
+
+// Distances along a spline at increasing parameters, for converting between the two
+#[derive(Debug, Clone)]
+pub struct ArcLengthTable {
+	parameters: Vec<f32>,
+	distances: Vec<f32>,
+}
+
+impl ArcLengthTable {
+	pub fn length(&self) -> f32 {
+		*self.distances.last().unwrap()
+	}
+
+	// The parameter reached after traveling a distance from the start, interpolated between the samples
+	pub fn parameter_at(&self, distance: f32) -> f32 {
+		if self.distances.len() < 2 {
+			return 0.;
+		}
+
+		let distance = distance.clamp(0., self.length());
+		let next = self.distances.partition_point(|sample| *sample < distance).clamp(1, self.distances.len() - 1);
+
+		let (start, end) = (self.distances[next - 1], self.distances[next]);
+		let blend = if end > start { (distance - start) / (end - start) } else { 0. };
+
+		self.parameters[next - 1] + (self.parameters[next] - self.parameters[next - 1]) * blend
+	}
+}
+
+// Orientation at a point on a spline, with the tangent pointing along the curve
+#[derive(Debug, Copy, Clone)]
+pub struct SplineFrame {
+	pub location: Point3<f32>,
+	pub tangent: Vector3<f32>,
+	pub up: Vector3<f32>,
+	pub right: Vector3<f32>,
+}
+
+// Cross section swept along a spline, with X toward the frame's right and Y toward its up
+// The surface faces to the right of the direction the points are walked, which is outward for counter-clockwise shapes
+#[derive(Debug, Clone)]
+pub struct Profile {
+	pub points: Vec<Vector2<f32>>,
+	pub closed: bool,
+}
+
+impl Profile {
+	// Outward facing, for pipes and tubes
+	pub fn circle(radius: f32, sides: usize) -> Self {
+		let sides = sides.max(3);
+		let points = (0..sides)
+			.map(|side| {
+				let angle = std::f32::consts::TAU * side as f32 / sides as f32;
+				Vector2::new(angle.cos(), angle.sin()) * radius
+			})
+			.collect();
+
+		Self { points, closed: true }
+	}
+
+	// Flat and facing up, for roads and paths, split across its width into segments so it can bend to fit uneven ground
+	pub fn strip(width: f32, segments: usize) -> Self {
+		let segments = segments.max(1);
+		let points = (0..=segments).map(|point| Vector2::new(width * (0.5 - point as f32 / segments as f32), 0.)).collect();
+
+		Self { points, closed: false }
+	}
+}
+
+// Lays an extruded mesh over the ground like a decal, moving each vertex to the ground's height below it plus an offset that keeps it from flickering through
+// Vertices with no ground below them stay where they are, and the normals and tangents are rebuilt to follow the new shape
+pub fn drape(vertices: &mut [ModelVertex], indices: &[u32], height_at: impl Fn(f32, f32) -> Option<f32>, offset: f32) {
+	for vertex in vertices.iter_mut() {
+		if let Some(height) = height_at(vertex.position[0], vertex.position[2]) {
+			vertex.position[1] = height + offset;
+		}
+	}
+
+	// Area weighted face normals summed at each vertex
+	let mut normals = vec![Vector3::new(0., 0., 0.); vertices.len()];
+	for triangle in indices.chunks_exact(3) {
+		let [a, b, c] = [0, 1, 2].map(|corner| Point3::from(vertices[triangle[corner] as usize].position));
+		let face_normal = (b - a).cross(c - a);
+		for index in triangle {
+			normals[*index as usize] += face_normal;
+		}
+	}
+	for (vertex, normal) in vertices.iter_mut().zip(normals) {
+		if normal.magnitude2() > f32::MIN_POSITIVE {
+			vertex.normal = normal.normalize().into();
+		}
+	}
+
+	generate_tangents(vertices, indices);
+}
+
+// Normals of each profile point, averaging the directions of the edges on either side
+// Closed profiles are given with their first point repeated at the end, and the edges continue around through it
+fn profile_normals(points: &[Vector2<f32>], closed: bool) -> Vec<Vector2<f32>> {
+	let last = points.len() - 1;
+
+	(0..points.len())
+		.map(|index| {
+			let previous = match index {
+				0 if closed => points[last - 1],
+				0 => points[0],
+				_ => points[index - 1],
+			};
+			let next = if index < last {
+				points[index + 1]
+			} else if closed {
+				points[1]
+			} else {
+				points[last]
+			};
+
+			let direction = next - previous;
+			if direction.magnitude2() > f32::MIN_POSITIVE {
+				Vector2::new(direction.y, -direction.x).normalize()
+			} else {
+				Vector2::unit_y()
+			}
+		})
+		.collect()
+}
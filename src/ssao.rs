@@ -1,6 +1,60 @@
 use cgmath::{InnerSpace, Vector3};
 use half::f16;
 use rand::Rng;
+use wgpu::util::DeviceExt;
+
+// Fraction of each pixel's occlusion carried over from the history while it holds still, where more converges further but lags behind what's newly revealed
+const HISTORY_WEIGHT: f32 = 0.9;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SsaoTemporalUniform {
+	// Zero while the history holds nothing usable, which also has every pixel take the most samples
+	history_weight: f32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: [f32; 3],
+}
+
+// Temporal accumulation of the ambient occlusion, which blends each frame's noisy occlusion into a history reprojected by the velocity buffer
+// The kernel takes only a few samples, a different few each frame, where the history can be trusted, and more where the pixel has moved too far for it to be
+pub struct SsaoTemporal {
+	history_valid: bool,
+	pub ssao_temporal_buffer: wgpu::Buffer,
+}
+
+impl SsaoTemporal {
+	pub fn new(device: &wgpu::Device) -> Self {
+		let ssao_temporal_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("SSAO Temporal Buffer"),
+			contents: bytemuck::cast_slice(&[<SsaoTemporalUniform as bytemuck::Zeroable>::zeroed()]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
+
+		Self {
+			history_valid: false,
+			ssao_temporal_buffer,
+		}
+	}
+
+	// The history no longer lines up with the frame, like after the SSAO maps are recreated at a new size
+	pub fn invalidate_history(&mut self) {
+		self.history_valid = false;
+	}
+
+	pub fn update(&mut self, enabled: bool, queue: &wgpu::Queue) {
+		if !enabled {
+			self.history_valid = false;
+			return;
+		}
+
+		let uniform = SsaoTemporalUniform {
+			history_weight: if self.history_valid { HISTORY_WEIGHT } else { 0. },
+			_padding: [0.; 3],
+		};
+		queue.write_buffer(&self.ssao_temporal_buffer, 0, bytemuck::cast_slice(&[uniform]));
+		self.history_valid = true;
+	}
+}
 
 pub fn generate_noise_texture() -> Vec<[f16; 4]> {
 	let mut rng = rand::thread_rng();
@@ -0,0 +1,182 @@
+use crate::camera::{Camera, PerspectiveProjection, Projection, OPENGL_TO_WGPU_MATRIX};
+use crate::context::Context;
+use crate::frame_texture::FrameTexture;
+
+use cgmath::{EuclideanSpace, Matrix3, Matrix4, Point3, Quaternion, SquareMatrix, Vector3};
+
+const ZNEAR: f32 = 0.1;
+const ZFAR: f32 = 50.;
+// Typical distance between a person's pupils, which sets how far apart the two eyes see from
+const DEFAULT_INTERPUPILLARY_DISTANCE: f32 = 0.064;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Eye {
+	Left,
+	Right,
+}
+
+impl Eye {
+	pub const ALL: [Eye; 2] = [Eye::Left, Eye::Right];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Eye::Left => "Left",
+			Eye::Right => "Right",
+		}
+	}
+}
+
+// Angles in radians from the eye's forward direction to each edge of its view, negative toward the left and bottom, laid out like OpenXR's `XrFovf`
+// Headset lenses sit off center from the eyes, so the views are asymmetric, wider toward the outside than toward the nose
+#[derive(Debug, Copy, Clone)]
+pub struct EyeFov {
+	pub angle_left: f32,
+	pub angle_right: f32,
+	pub angle_up: f32,
+	pub angle_down: f32,
+}
+
+impl EyeFov {
+	// Roughly what current headsets report, used until a tracker supplies the real values
+	pub fn default_for(eye: Eye) -> Self {
+		let (outer, inner) = (50_f32.to_radians(), 43_f32.to_radians());
+		let (vertical_up, vertical_down) = (44_f32.to_radians(), -50_f32.to_radians());
+
+		match eye {
+			Eye::Left => Self {
+				angle_left: -outer,
+				angle_right: inner,
+				angle_up: vertical_up,
+				angle_down: vertical_down,
+			},
+			Eye::Right => Self {
+				angle_left: -inner,
+				angle_right: outer,
+				angle_up: vertical_up,
+				angle_down: vertical_down,
+			},
+		}
+	}
+
+	pub fn p_matrix(&self, znear: f32, zfar: f32) -> Matrix4<f32> {
+		let left = znear * self.angle_left.tan();
+		let right = znear * self.angle_right.tan();
+		let bottom = znear * self.angle_down.tan();
+		let top = znear * self.angle_up.tan();
+
+		OPENGL_TO_WGPU_MATRIX * cgmath::frustum(left, right, bottom, top, znear, zfar)
+	}
+}
+
+// Where an eye is in world space and which way it's turned, with the eye looking down its local -Z axis, laid out like OpenXR's `XrPosef`
+#[derive(Debug, Copy, Clone)]
+pub struct EyePose {
+	pub location: Point3<f32>,
+	pub orientation: Quaternion<f32>,
+}
+
+#[derive(Debug)]
+pub struct StereoEye {
+	pub eye: Eye,
+	pub fov: EyeFov,
+	pub camera: Camera,
+	pub color_map: FrameTexture,
+	pub depth_map: FrameTexture,
+}
+
+// A view for each eye, rendered side by side into the window the way a headset's compositor would receive them
+// There is no OpenXR session to supply tracked poses, so the eyes are placed either side of the main camera, but a tracker can set them directly with `update_from_poses`
+#[derive(Debug)]
+pub struct StereoRig {
+	pub eyes: [StereoEye; 2],
+	pub interpupillary_distance: f32,
+}
+
+impl StereoRig {
+	pub fn new(context: &Context) -> Self {
+		let eyes = Eye::ALL.map(|eye| {
+			// The projection is replaced every frame by the one built from the eye's field of view
+			let projection = PerspectiveProjection::new(context.surface_configuration.width / 2, context.surface_configuration.height, cgmath::Deg(90.0), ZNEAR, ZFAR);
+			let mut camera = Camera::new(context, Projection::Perspective(projection));
+			// Like the views through portals, each eye is shaded in a single forward pass and then seen through the main camera's post-processing
+			camera.post_effects.clear();
+
+			let (color_map, depth_map) = eye_frame_textures(context, eye, eye_resolution(&context.surface_configuration));
+
+			StereoEye {
+				eye,
+				fov: EyeFov::default_for(eye),
+				camera,
+				color_map,
+				depth_map,
+			}
+		});
+
+		Self {
+			eyes,
+			interpupillary_distance: DEFAULT_INTERPUPILLARY_DISTANCE,
+		}
+	}
+
+	// Stands in for head tracking by treating the main camera as the point between the eyes
+	pub fn follow_head(&mut self, head: &Camera, queue: &wgpu::Queue) {
+		let head_world = head.camera_uniform.v_matrix().invert().unwrap();
+		let head_orientation = Quaternion::from(Matrix3::from_cols(head_world.x.truncate(), head_world.y.truncate(), head_world.z.truncate()));
+		let right = head_orientation * Vector3::unit_x();
+		let half_separation = self.interpupillary_distance * 0.5;
+
+		let poses = Eye::ALL.map(|eye| {
+			let side = if eye == Eye::Left { -1. } else { 1. };
+			EyePose {
+				location: head.location + right * (side * half_separation),
+				orientation: head_orientation,
+			}
+		});
+
+		self.update_from_poses(poses, queue);
+	}
+
+	// Takes the pose of each eye as reported by a tracker, in the order of `Eye::ALL`
+	pub fn update_from_poses(&mut self, poses: [EyePose; 2], queue: &wgpu::Queue) {
+		for (stereo_eye, pose) in self.eyes.iter_mut().zip(poses) {
+			let world = Matrix4::from_translation(pose.location.to_vec()) * Matrix4::from(pose.orientation);
+			let v = world.invert().unwrap();
+			let p = stereo_eye.fov.p_matrix(ZNEAR, ZFAR);
+
+			stereo_eye.camera.update_from_matrices(pose.location, v, p, queue);
+		}
+	}
+
+	pub fn resize(&mut self, context: &Context) {
+		let resolution = eye_resolution(&context.surface_configuration);
+		for stereo_eye in &mut self.eyes {
+			let (color_map, depth_map) = eye_frame_textures(context, stereo_eye.eye, resolution);
+			stereo_eye.color_map = color_map;
+			stereo_eye.depth_map = depth_map;
+		}
+	}
+}
+
+// Each eye fills half the window's width
+fn eye_resolution(config: &wgpu::SurfaceConfiguration) -> (u32, u32) {
+	((config.width / 2).max(1), config.height)
+}
+
+fn eye_frame_textures(context: &Context, eye: Eye, resolution: (u32, u32)) -> (FrameTexture, FrameTexture) {
+	let color_map = FrameTexture::with_size(
+		&context.device,
+		resolution,
+		wgpu::TextureFormat::Rgba16Float,
+		&format!("{} Eye Color Map frame texture", eye.name()),
+		None,
+	);
+	let depth_map = FrameTexture::with_size(
+		&context.device,
+		resolution,
+		wgpu::TextureFormat::Depth32Float,
+		&format!("{} Eye Z-buffer frame texture", eye.name()),
+		Some(wgpu::CompareFunction::LessEqual),
+	);
+
+	(color_map, depth_map)
+}
@@ -0,0 +1,79 @@
+use cgmath::Vector2;
+use wgpu::util::DeviceExt;
+
+// The jitter cycles through this many points of the Halton sequence, spread evenly enough over a pixel to resolve its edges
+const JITTER_SEQUENCE_LENGTH: u32 = 8;
+// Fraction of each resolved pixel carried over from the history, where more is smoother but takes longer to catch up with what's newly revealed
+const HISTORY_WEIGHT: f32 = 0.9;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TemporalAntiAliasingUniform {
+	// Zero while the history holds nothing usable, like on the first frame after a resize or after being turned back on
+	history_weight: f32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: [f32; 3],
+}
+
+// Temporal anti-aliasing, which jitters the camera by a different fraction of a pixel every frame and blends each frame into a history of the previous ones
+// The resolve pass finds where each pixel was last frame from the velocity buffer written by the deferred pass, and clamps the history to the colors around it so it doesn't ghost
+pub struct TemporalAntiAliasing {
+	frame_index: u32,
+	history_valid: bool,
+	pub taa_buffer: wgpu::Buffer,
+}
+
+impl TemporalAntiAliasing {
+	pub fn new(device: &wgpu::Device) -> Self {
+		let taa_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Temporal Anti-Aliasing Buffer"),
+			contents: bytemuck::cast_slice(&[<TemporalAntiAliasingUniform as bytemuck::Zeroable>::zeroed()]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
+
+		Self {
+			frame_index: 0,
+			history_valid: false,
+			taa_buffer,
+		}
+	}
+
+	// The history no longer lines up with the frame, like after the frame textures are recreated at a new size
+	pub fn invalidate_history(&mut self) {
+		self.history_valid = false;
+	}
+
+	// Steps to the next jitter and returns it in normalized device coordinates for a view of this resolution, or no jitter while it's disabled
+	pub fn update(&mut self, enabled: bool, resolution: (u32, u32), queue: &wgpu::Queue) -> Vector2<f32> {
+		if !enabled {
+			self.history_valid = false;
+			return Vector2::new(0., 0.);
+		}
+
+		let uniform = TemporalAntiAliasingUniform {
+			history_weight: if self.history_valid { HISTORY_WEIGHT } else { 0. },
+			_padding: [0.; 3],
+		};
+		queue.write_buffer(&self.taa_buffer, 0, bytemuck::cast_slice(&[uniform]));
+		self.history_valid = true;
+
+		// The sequence starts from 1, since its first point lies on the pixel's corner
+		self.frame_index = self.frame_index % JITTER_SEQUENCE_LENGTH + 1;
+		let offset = Vector2::new(halton(self.frame_index, 2), halton(self.frame_index, 3)) - Vector2::new(0.5, 0.5);
+
+		// Normalized device coordinates span 2 units across the view
+		Vector2::new(offset.x * 2. / resolution.0 as f32, offset.y * 2. / resolution.1 as f32)
+	}
+}
+
+// Low-discrepancy sequence in 0 to 1, made by mirroring the index's digits in this base around the decimal point
+fn halton(mut index: u32, base: u32) -> f32 {
+	let mut fraction = 1.;
+	let mut result = 0.;
+	while index > 0 {
+		fraction /= base as f32;
+		result += fraction * (index % base) as f32;
+		index /= base;
+	}
+	result
+}
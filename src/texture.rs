@@ -1,24 +1,222 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use half::f16;
 use image::GenericImageView;
 use std::{borrow::Cow, path::Path};
+use wgpu::util::DeviceExt;
 
 use crate::context::Context;
 
+// Faces of equirectangular skies are a quarter as wide as the panorama, since that's how much of it each one covers, up to this size
+const MAX_CUBEMAP_FACE_RESOLUTION: u32 = 1024;
+// The irradiance changes so slowly across the sphere that a few texels per face hold all of it
+const IRRADIANCE_RESOLUTION: u32 = 32;
+// Mirror-like surfaces reflect the prefiltered map's largest mip, and each mip after it is blurred for a rougher surface up to a roughness of 1 at the last
+const PREFILTERED_RESOLUTION: u32 = 128;
+pub const PREFILTERED_MIP_LEVELS: u32 = 5;
+const BRDF_LUT_RESOLUTION: u32 = 256;
+
+// Where the six faces of a cubemap come from, read from the assets' skies folder
+#[derive(Debug, Clone, PartialEq)]
+pub enum CubemapSource {
+	// Square images of the same size, ordered +X, -X, +Y, -Y, +Z, -Z
+	Faces([String; 6]),
+	// A single panorama wrapped around the sphere, usually an HDR image so the sky can be brighter than white
+	Equirectangular(String),
+}
+
+// The linear color of every texel of a cubemap, one face after another, decoded without touching the GPU and then uploaded with `Texture::cube_from_faces`
+pub struct CubemapFaces {
+	pub resolution: u32,
+	pub texels: Vec<[f32; 3]>,
+}
+
+impl CubemapFaces {
+	// A single texel per face, so a cubemap can be bound while there's no sky to fill it
+	pub fn filled(color: [f32; 3]) -> Self {
+		Self {
+			resolution: 1,
+			texels: vec![color; 6],
+		}
+	}
+
+	pub fn decode(directory: &Path, source: &CubemapSource) -> Result<Self> {
+		let directory = directory.join("skies");
+
+		match source {
+			CubemapSource::Faces(files) => {
+				let mut resolution = None;
+				let mut texels = Vec::new();
+				for file in files {
+					let image = image::open(directory.join(file))?.to_rgb8();
+					let (width, height) = image.dimensions();
+					if width != height || resolution.is_some_and(|resolution| resolution != width) {
+						bail!("The cubemap face {} is {}x{}, but the faces have to be squares of the same size", file, width, height);
+					}
+					resolution = Some(width);
+					texels.extend(image.pixels().map(|pixel| pixel.0.map(srgb_to_linear)));
+				}
+
+				Ok(Self {
+					resolution: resolution.unwrap_or(1),
+					texels,
+				})
+			}
+			CubemapSource::Equirectangular(file) => {
+				let (width, height, panorama) = decode_linear_image(&directory.join(file))?;
+				let resolution = (width / 4).next_power_of_two().min(MAX_CUBEMAP_FACE_RESOLUTION);
+
+				// Each texel of each face looks up the panorama in the direction it points, between the panorama's four nearest pixels
+				let texels = (0..6)
+					.flat_map(|face| (0..resolution * resolution).map(move |index| (face, index % resolution, index / resolution)))
+					.map(|(face, x, y)| {
+						let [dx, dy, dz] = cube_face_direction(face, (x as f32 + 0.5) / resolution as f32, (y as f32 + 0.5) / resolution as f32);
+						let length = (dx * dx + dy * dy + dz * dz).sqrt();
+						let u = 0.5 + dz.atan2(dx) / std::f32::consts::TAU;
+						let v = (dy / length).clamp(-1., 1.).acos() / std::f32::consts::PI;
+
+						let (px, py) = (u * width as f32 - 0.5, (v * height as f32 - 0.5).clamp(0., height as f32 - 1.));
+						let (x0, y0) = (px.floor(), py.floor());
+						let (fx, fy) = (px - x0, py - y0);
+						let pixel = |x: f32, y: f32| {
+							// Longitude wraps around, while latitude stops at the poles
+							let x = (x as i64).rem_euclid(width as i64) as usize;
+							let y = (y as i64).clamp(0, height as i64 - 1) as usize;
+							panorama[y * width as usize + x]
+						};
+						let (top_left, top_right, bottom_left, bottom_right) = (pixel(x0, y0), pixel(x0 + 1., y0), pixel(x0, y0 + 1.), pixel(x0 + 1., y0 + 1.));
+
+						[0, 1, 2].map(|channel| {
+							let top = top_left[channel] + (top_right[channel] - top_left[channel]) * fx;
+							let bottom = bottom_left[channel] + (bottom_right[channel] - bottom_left[channel]) * fx;
+							top + (bottom - top) * fy
+						})
+					})
+					.collect();
+
+				Ok(Self { resolution, texels })
+			}
+		}
+	}
+
+	// Each face shrunk to half its size by averaging every 2x2 block of texels, for the next mip level down
+	fn downsampled(&self) -> Self {
+		let resolution = (self.resolution / 2).max(1);
+		let face_size = (self.resolution * self.resolution) as usize;
+		let texel = |face: usize, x: u32, y: u32| self.texels[face * face_size + (y.min(self.resolution - 1) * self.resolution + x.min(self.resolution - 1)) as usize];
+
+		let texels = (0..6)
+			.flat_map(|face| (0..resolution * resolution).map(move |index| (face, index % resolution, index / resolution)))
+			.map(|(face, x, y)| {
+				let block = [
+					texel(face, x * 2, y * 2),
+					texel(face, x * 2 + 1, y * 2),
+					texel(face, x * 2, y * 2 + 1),
+					texel(face, x * 2 + 1, y * 2 + 1),
+				];
+				[0, 1, 2].map(|channel| block.iter().map(|texel| texel[channel]).sum::<f32>() / 4.)
+			})
+			.collect();
+
+		Self { resolution, texels }
+	}
+}
+
+// The direction a point on a cube face points in, given in the face's texture coordinates from 0 to 1, following the face order and orientation the GPU samples cubemaps with
+fn cube_face_direction(face: usize, u: f32, v: f32) -> [f32; 3] {
+	let (u, v) = (u * 2. - 1., v * 2. - 1.);
+	match face {
+		0 => [1., -v, -u],
+		1 => [-1., -v, u],
+		2 => [u, 1., v],
+		3 => [u, -1., -v],
+		4 => [u, -v, 1.],
+		_ => [-u, -v, -1.],
+	}
+}
+
+fn srgb_to_linear(channel: u8) -> f32 {
+	(channel as f32 / 255.).powf(2.2)
+}
+
+// Reads an image into linear colors, taking HDR images as they are and converting the others from sRGB
+fn decode_linear_image(path: &Path) -> Result<(u32, u32, Vec<[f32; 3]>)> {
+	if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("hdr")) {
+		let decoder = image::codecs::hdr::HdrDecoder::new(std::io::BufReader::new(std::fs::File::open(path)?))?;
+		let metadata = decoder.metadata();
+		let pixels = decoder.read_image_hdr()?;
+		return Ok((metadata.width, metadata.height, pixels.into_iter().map(|pixel| pixel.0).collect()));
+	}
+
+	let image = image::open(path)?.to_rgb8();
+	let (width, height) = image.dimensions();
+	Ok((width, height, image.pixels().map(|pixel| pixel.0.map(srgb_to_linear)).collect()))
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct CubeFilterUniform {
+	face: u32,
+	roughness: f32,
+	source_resolution: f32,
+	target_resolution: f32,
+}
+
+#[derive(Debug)]
 pub struct Texture {
 	pub texture: wgpu::Texture,
 	pub view: wgpu::TextureView,
 	pub sampler: wgpu::Sampler,
 	pub format: wgpu::TextureFormat,
 	pub size: wgpu::Extent3d,
+	pub label: String,
 }
 
 impl Texture {
-	pub fn load(device: &wgpu::Device, queue: &wgpu::Queue, directory: &Path, file: &str, format: wgpu::TextureFormat, repeat_mode: wgpu::AddressMode) -> Result<Self> {
-		let path = directory.join("models").join(file);
-		let image = image::open(path.clone())?;
+	// Reads an image file without touching the GPU, so it can be done on a worker thread and uploaded with `from_image` once it's ready
+	// Leaves out the given number of the largest mip levels by shrinking the image before it's uploaded, to save memory on GPUs short of it
+	pub fn decode(directory: &Path, file: &str, skipped_mips: u32) -> Result<image::DynamicImage> {
+		let mut image = image::open(directory.join("models").join(file))?;
+		if skipped_mips > 0 {
+			let (width, height) = image.dimensions();
+			image = image.resize_exact((width >> skipped_mips).max(1), (height >> skipped_mips).max(1), image::imageops::FilterType::Triangle);
+		}
 
-		Ok(Self::from_image(device, queue, &image, path.to_str().unwrap_or_default(), format, repeat_mode))
+		Ok(image)
+	}
+
+	// Packs separate roughness and metalness maps into the green and blue channels of an ARM map, with no occlusion in red
+	// A missing map is filled in with its scalar value, and the other is stretched to the size of the first
+	pub fn load_packed_arm(
+		device: &wgpu::Device,
+		queue: &wgpu::Queue,
+		directory: &Path,
+		label: &str,
+		roughness: (Option<&str>, f32),
+		metallic: (Option<&str>, f32),
+		skipped_mips: u32,
+	) -> Result<Self> {
+		let load = |file: Option<&str>| file.map(|file| image::open(directory.join("models").join(file)).map(|image| image.to_luma8())).transpose();
+		let (roughness_map, metallic_map) = (load(roughness.0)?, load(metallic.0)?);
+
+		let (width, height) = roughness_map.as_ref().or(metallic_map.as_ref()).map_or((1, 1), |map| map.dimensions());
+		let (width, height) = ((width >> skipped_mips).max(1), (height >> skipped_mips).max(1));
+		let channel = |map: Option<image::GrayImage>, value: f32| match map {
+			Some(map) if map.dimensions() == (width, height) => map,
+			Some(map) => image::imageops::resize(&map, width, height, image::imageops::FilterType::Triangle),
+			None => image::GrayImage::from_pixel(width, height, image::Luma([(value.clamp(0., 1.) * 255.).round() as u8])),
+		};
+		let (roughness_channel, metallic_channel) = (channel(roughness_map, roughness.1), channel(metallic_map, metallic.1));
+
+		let rgba = roughness_channel.pixels().zip(metallic_channel.pixels()).flat_map(|(r, m)| [0, r[0], m[0], 255]).collect::<Vec<u8>>();
+		Ok(Self::from_rgba_data(
+			device,
+			queue,
+			&rgba,
+			(width, height),
+			label,
+			wgpu::TextureFormat::Rgba8Unorm,
+			wgpu::AddressMode::Repeat,
+		))
 	}
 
 	pub fn from_image_bytes(device: &wgpu::Device, queue: &wgpu::Queue, bytes: &[u8], label: &str, format: wgpu::TextureFormat, repeat_mode: wgpu::AddressMode) -> Result<Self> {
@@ -32,6 +230,20 @@ impl Texture {
 		Self::from_rgba_data(device, queue, rgba_data, dimensions, label, format, repeat_mode)
 	}
 
+	pub fn from_f16_array_3d(
+		device: &wgpu::Device,
+		queue: &wgpu::Queue,
+		rgba: &[[f16; 4]],
+		dimensions: (u32, u32, u32),
+		label: &str,
+		format: wgpu::TextureFormat,
+		repeat_mode: wgpu::AddressMode,
+	) -> Self {
+		let rgba_data: &[u8] = bytemuck::cast_slice(rgba);
+
+		Self::from_rgba_data_3d(device, queue, rgba_data, dimensions, label, format, repeat_mode)
+	}
+
 	pub fn from_image(device: &wgpu::Device, queue: &wgpu::Queue, img: &image::DynamicImage, label: &str, format: wgpu::TextureFormat, repeat_mode: wgpu::AddressMode) -> Self {
 		let rgba: &[u8] = &img.to_rgba8();
 		let dimensions = img.dimensions();
@@ -67,7 +279,53 @@ impl Texture {
 			..Default::default()
 		});
 
-		Self { texture, view, sampler, format, size }
+		Self {
+			texture,
+			view,
+			sampler,
+			format,
+			size,
+			label: String::from(label),
+		}
+	}
+
+	// Written by compute shaders and then sampled, so it has no mipmaps to keep in sync
+	pub fn storage(device: &wgpu::Device, dimensions: (u32, u32), label: &str, format: wgpu::TextureFormat) -> Self {
+		let size = wgpu::Extent3d {
+			width: dimensions.0,
+			height: dimensions.1,
+			depth_or_array_layers: 1,
+		};
+
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some(label),
+			size,
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format,
+			usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+		});
+
+		let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			address_mode_w: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Nearest,
+			min_filter: wgpu::FilterMode::Nearest,
+			mipmap_filter: wgpu::FilterMode::Nearest,
+			..Default::default()
+		});
+
+		Self {
+			texture,
+			view,
+			sampler,
+			format,
+			size,
+			label: String::from(label),
+		}
 	}
 
 	pub fn from_rgba_data(device: &wgpu::Device, queue: &wgpu::Queue, rgba_data: &[u8], dimensions: (u32, u32), label: &str, format: wgpu::TextureFormat, repeat_mode: wgpu::AddressMode) -> Self {
@@ -115,7 +373,393 @@ impl Texture {
 			..Default::default()
 		});
 
-		Self { texture, view, sampler, format, size }
+		Self {
+			texture,
+			view,
+			sampler,
+			format,
+			size,
+			label: String::from(label),
+		}
+	}
+
+	pub fn from_rgba_data_3d(
+		device: &wgpu::Device,
+		queue: &wgpu::Queue,
+		rgba_data: &[u8],
+		dimensions: (u32, u32, u32),
+		label: &str,
+		format: wgpu::TextureFormat,
+		repeat_mode: wgpu::AddressMode,
+	) -> Self {
+		let size = wgpu::Extent3d {
+			width: dimensions.0,
+			height: dimensions.1,
+			depth_or_array_layers: dimensions.2,
+		};
+
+		// Volume textures only get their base level since `generate_mipmaps` is a 2D render pass
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some(label),
+			size,
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D3,
+			format,
+			usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+		});
+
+		let color_bytes_per_channel = rgba_data.len() as u32 / (dimensions.0 * dimensions.1 * dimensions.2) / 4;
+		queue.write_texture(
+			wgpu::ImageCopyTexture {
+				aspect: wgpu::TextureAspect::All,
+				texture: &texture,
+				mip_level: 0,
+				origin: wgpu::Origin3d::ZERO,
+			},
+			rgba_data,
+			wgpu::ImageDataLayout {
+				offset: 0,
+				bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0 * color_bytes_per_channel),
+				rows_per_image: std::num::NonZeroU32::new(dimensions.1),
+			},
+			size,
+		);
+
+		let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			address_mode_u: repeat_mode,
+			address_mode_v: repeat_mode,
+			address_mode_w: repeat_mode,
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			mipmap_filter: wgpu::FilterMode::Linear,
+			..Default::default()
+		});
+
+		Self {
+			texture,
+			view,
+			sampler,
+			format,
+			size,
+			label: String::from(label),
+		}
+	}
+
+	// Uploads the faces as a cube texture of half floats, since HDR skies are brighter than an 8 bit format can hold
+	// Its mips are averaged down on the CPU, because `generate_mipmaps` only blits 2D textures, and the smallest of them stand in for the light arriving over a whole hemisphere
+	pub fn cube_from_faces(device: &wgpu::Device, queue: &wgpu::Queue, faces: &CubemapFaces, label: &str) -> Self {
+		let size = wgpu::Extent3d {
+			width: faces.resolution,
+			height: faces.resolution,
+			depth_or_array_layers: 6,
+		};
+		let mip_level_count = 32 - faces.resolution.leading_zeros();
+		let format = wgpu::TextureFormat::Rgba16Float;
+
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some(label),
+			size,
+			mip_level_count,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format,
+			usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+		});
+
+		let mut mip = None;
+		for mip_level in 0..mip_level_count {
+			let level = match &mip {
+				None => faces,
+				Some(level) => level,
+			};
+			let rgba = level
+				.texels
+				.iter()
+				.map(|&[r, g, b]| [f16::from_f32(r), f16::from_f32(g), f16::from_f32(b), f16::ONE])
+				.collect::<Vec<_>>();
+			queue.write_texture(
+				wgpu::ImageCopyTexture {
+					aspect: wgpu::TextureAspect::All,
+					texture: &texture,
+					mip_level,
+					origin: wgpu::Origin3d::ZERO,
+				},
+				bytemuck::cast_slice(&rgba),
+				wgpu::ImageDataLayout {
+					offset: 0,
+					bytes_per_row: std::num::NonZeroU32::new(8 * level.resolution),
+					rows_per_image: std::num::NonZeroU32::new(level.resolution),
+				},
+				wgpu::Extent3d {
+					width: level.resolution,
+					height: level.resolution,
+					depth_or_array_layers: 6,
+				},
+			);
+			mip = Some(level.downsampled());
+		}
+
+		let view = texture.create_view(&wgpu::TextureViewDescriptor {
+			dimension: Some(wgpu::TextureViewDimension::Cube),
+			..Default::default()
+		});
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			address_mode_w: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			mipmap_filter: wgpu::FilterMode::Linear,
+			..Default::default()
+		});
+
+		Self {
+			texture,
+			view,
+			sampler,
+			format,
+			size,
+			label: String::from(label),
+		}
+	}
+
+	// The light a diffuse surface facing each direction receives from the sky map, with the sky's radiance summed over the hemisphere around it
+	pub fn irradiance_cube(device: &wgpu::Device, queue: &wgpu::Queue, sky_map: &Texture) -> Self {
+		Self::filtered_cube(device, queue, sky_map, "fs_irradiance", IRRADIANCE_RESOLUTION, 1, "Irradiance Map")
+	}
+
+	// The sky map blurred by the GGX lobe of a roughness that rises with each mip, for the reflections of surfaces of every roughness
+	pub fn prefiltered_cube(device: &wgpu::Device, queue: &wgpu::Queue, sky_map: &Texture) -> Self {
+		Self::filtered_cube(device, queue, sky_map, "fs_prefilter", PREFILTERED_RESOLUTION, PREFILTERED_MIP_LEVELS, "Prefiltered Map")
+	}
+
+	// Renders every face of every mip of a new cubemap with one of the filters of the cube filter shader, reading the source cubemap
+	fn filtered_cube(device: &wgpu::Device, queue: &wgpu::Queue, source: &Texture, entry_point: &str, resolution: u32, mip_level_count: u32, label: &str) -> Self {
+		let size = wgpu::Extent3d {
+			width: resolution,
+			height: resolution,
+			depth_or_array_layers: 6,
+		};
+		let format = wgpu::TextureFormat::Rgba16Float;
+
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some(label),
+			size,
+			mip_level_count,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format,
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+		});
+
+		let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+			label: None,
+			source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../assets/shaders/utility_cube_filter.wgsl"))),
+		});
+
+		let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+			label: Some(label),
+			layout: None,
+			vertex: wgpu::VertexState {
+				module: &shader,
+				entry_point: "vs_main",
+				buffers: &[],
+			},
+			fragment: Some(wgpu::FragmentState {
+				module: &shader,
+				entry_point,
+				targets: &[format.into()],
+			}),
+			primitive: wgpu::PrimitiveState {
+				topology: wgpu::PrimitiveTopology::TriangleStrip,
+				..Default::default()
+			},
+			depth_stencil: None,
+			multisample: wgpu::MultisampleState::default(),
+		});
+
+		let bind_group_layout = pipeline.get_bind_group_layout(0);
+
+		let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Render Encoder") });
+
+		for mip_level in 0..mip_level_count {
+			// The irradiance has no roughness, and the prefiltered mips' roughness rises evenly from 0 to 1
+			let roughness = match mip_level_count {
+				1 => 1.,
+				_ => mip_level as f32 / (mip_level_count - 1) as f32,
+			};
+
+			for face in 0..6 {
+				let uniform = CubeFilterUniform {
+					face,
+					roughness,
+					source_resolution: source.size.width as f32,
+					target_resolution: (resolution >> mip_level).max(1) as f32,
+				};
+				let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+					label: Some("Cube Filter Buffer"),
+					contents: bytemuck::cast_slice(&[uniform]),
+					usage: wgpu::BufferUsages::UNIFORM,
+				});
+
+				let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+					layout: &bind_group_layout,
+					entries: &[
+						wgpu::BindGroupEntry {
+							binding: 0,
+							resource: buffer.as_entire_binding(),
+						},
+						wgpu::BindGroupEntry {
+							binding: 1,
+							resource: wgpu::BindingResource::TextureView(&source.view),
+						},
+						wgpu::BindGroupEntry {
+							binding: 2,
+							resource: wgpu::BindingResource::Sampler(&source.sampler),
+						},
+					],
+					label: None,
+				});
+
+				let view = texture.create_view(&wgpu::TextureViewDescriptor {
+					label: Some("face"),
+					dimension: Some(wgpu::TextureViewDimension::D2),
+					base_mip_level: mip_level,
+					mip_level_count: std::num::NonZeroU32::new(1),
+					base_array_layer: face,
+					array_layer_count: std::num::NonZeroU32::new(1),
+					..Default::default()
+				});
+
+				let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+					label: None,
+					color_attachments: &[wgpu::RenderPassColorAttachment {
+						view: &view,
+						resolve_target: None,
+						ops: wgpu::Operations {
+							load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+							store: true,
+						},
+					}],
+					depth_stencil_attachment: None,
+				});
+				rpass.set_pipeline(&pipeline);
+				rpass.set_bind_group(0, &bind_group, &[]);
+				rpass.draw(0..4, 0..1);
+			}
+		}
+
+		queue.submit(Some(encoder.finish()));
+
+		let view = texture.create_view(&wgpu::TextureViewDescriptor {
+			dimension: Some(wgpu::TextureViewDimension::Cube),
+			..Default::default()
+		});
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			address_mode_w: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			mipmap_filter: wgpu::FilterMode::Linear,
+			..Default::default()
+		});
+
+		Self {
+			texture,
+			view,
+			sampler,
+			format,
+			size,
+			label: String::from(label),
+		}
+	}
+
+	// The split-sum approximation's scale and bias to F0 for every view angle and roughness, which doesn't depend on the sky so it's only rendered once
+	pub fn brdf_lut(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+		let size = wgpu::Extent3d {
+			width: BRDF_LUT_RESOLUTION,
+			height: BRDF_LUT_RESOLUTION,
+			depth_or_array_layers: 1,
+		};
+		let format = wgpu::TextureFormat::Rg16Float;
+
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("BRDF LUT"),
+			size,
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format,
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+		});
+		let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+		let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+			label: None,
+			source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../assets/shaders/utility_brdf_lut.wgsl"))),
+		});
+
+		let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+			label: Some("BRDF LUT"),
+			layout: None,
+			vertex: wgpu::VertexState {
+				module: &shader,
+				entry_point: "vs_main",
+				buffers: &[],
+			},
+			fragment: Some(wgpu::FragmentState {
+				module: &shader,
+				entry_point: "fs_main",
+				targets: &[format.into()],
+			}),
+			primitive: wgpu::PrimitiveState {
+				topology: wgpu::PrimitiveTopology::TriangleStrip,
+				..Default::default()
+			},
+			depth_stencil: None,
+			multisample: wgpu::MultisampleState::default(),
+		});
+
+		let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Render Encoder") });
+		{
+			let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: None,
+				color_attachments: &[wgpu::RenderPassColorAttachment {
+					view: &view,
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+						store: true,
+					},
+				}],
+				depth_stencil_attachment: None,
+			});
+			rpass.set_pipeline(&pipeline);
+			rpass.draw(0..4, 0..1);
+		}
+		queue.submit(Some(encoder.finish()));
+
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			address_mode_w: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			mipmap_filter: wgpu::FilterMode::Nearest,
+			..Default::default()
+		});
+
+		Self {
+			texture,
+			view,
+			sampler,
+			format,
+			size,
+			label: String::from("BRDF LUT"),
+		}
 	}
 
 	pub fn generate_mipmaps(&mut self, context: &Context) {
@@ -0,0 +1,162 @@
+use crate::light::DEFAULT_SKY_AMBIENT;
+
+use std::time::Duration;
+use winit::event::{ElementState, VirtualKeyCode};
+
+// Highest the sun climbs at noon, in degrees above the horizon
+const NOON_ELEVATION: f32 = 60.;
+// Within this many degrees of the horizon the lighting changes quickly enough that baked GI would fall behind
+const TWILIGHT_ELEVATION: f32 = 10.;
+
+// Passed to the behaviors of every entity as the sun crosses the horizon
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimeOfDayEvent {
+	Dawn,
+	Dusk,
+}
+
+// How the voxel GI keeps up with the changing lighting
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GlobalIllumination {
+	// Voxelized once with the lighting as it is and then left alone, which is free but goes stale as the sun moves
+	Baked,
+	// Voxelized again every frame, spread over several frames by the voxel update scheduler
+	Dynamic,
+}
+
+// The lighting at one hour of the day, blended with its neighbors for the hours in between
+#[derive(Debug, Copy, Clone)]
+pub struct TimeOfDayKey {
+	pub hour: f32,
+	// Lux, in the same units as the `Sun`, so it reaches zero at night
+	pub sun_illuminance: f32,
+	// Linear RGB light from the whole sky, which keeps the night from going completely black
+	pub sky_ambient: [f32; 3],
+	// Multiplier on the lamps' brightness, turning them up as it gets dark
+	pub lamp_scale: f32,
+}
+
+// Moves the sun across the sky over a 24 hour day and lights the scene along a curve of keys, firing events at dawn and dusk
+// GI can be handed off between baked and dynamic: kept dynamic through dawn and dusk while the light changes fastest, then baked again once it settles
+pub struct TimeOfDay {
+	// Drives the lighting while on, leaving the scene's lights as they are otherwise
+	pub enabled: bool,
+	// From 0 to 24, with midnight at 0
+	pub hour: f32,
+	// Real seconds for a whole day to pass
+	pub day_length: f32,
+	// Sorted by hour, wrapping around from the last back to the first
+	pub keys: Vec<TimeOfDayKey>,
+	// Switches GI between baked and dynamic by the sun's height, or keeps it dynamic the whole day when off
+	pub global_illumination_switching: bool,
+}
+
+impl Default for TimeOfDay {
+	fn default() -> Self {
+		let key = |hour, sun_illuminance, sky_ambient, lamp_scale| TimeOfDayKey {
+			hour,
+			sun_illuminance,
+			sky_ambient,
+			lamp_scale,
+		};
+
+		Self {
+			enabled: false,
+			hour: 16.,
+			day_length: 240.,
+			keys: vec![
+				key(0., 0., [0.006, 0.008, 0.016], 1.5),
+				key(5., 0., [0.008, 0.01, 0.02], 1.5),
+				// Warm twilight glows in the sky before the sun clears the horizon and after it sets, while the sun itself is dark below it
+				key(6., 0., [0.035, 0.028, 0.03], 1.3),
+				key(7.5, 0.5, [0.045, 0.04, 0.042], 1.1),
+				key(9., 1., [0.05, 0.05, 0.055], 1.),
+				key(15., 1., [0.05, 0.05, 0.055], 1.),
+				key(16.5, 0.5, [0.05, 0.042, 0.04], 1.1),
+				key(18., 0., [0.035, 0.026, 0.03], 1.3),
+				key(19., 0., [0.01, 0.01, 0.022], 1.5),
+			],
+			global_illumination_switching: true,
+		}
+	}
+}
+
+impl TimeOfDay {
+	pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
+		if key != VirtualKeyCode::N || state != ElementState::Pressed {
+			return false;
+		}
+
+		self.enabled = !self.enabled;
+
+		true
+	}
+
+	// Advances the clock, returning the events for any horizon the sun crossed
+	pub fn update(&mut self, delta_time: Duration) -> Vec<TimeOfDayEvent> {
+		if !self.enabled {
+			return vec![];
+		}
+
+		let previous_elevation = self.sun_elevation();
+		self.hour = (self.hour + delta_time.as_secs_f32() * 24. / self.day_length.max(f32::EPSILON)).rem_euclid(24.);
+		let elevation = self.sun_elevation();
+
+		match (previous_elevation < 0., elevation < 0.) {
+			(true, false) => vec![TimeOfDayEvent::Dawn],
+			(false, true) => vec![TimeOfDayEvent::Dusk],
+			_ => vec![],
+		}
+	}
+
+	// Degrees above the horizon, rising at 6:00, peaking at noon, and setting at 18:00
+	pub fn sun_elevation(&self) -> f32 {
+		NOON_ELEVATION * ((self.hour - 6.) / 12. * std::f32::consts::PI).sin()
+	}
+
+	// Degrees clockwise from -Z, rising in the east at +X and setting in the west
+	pub fn sun_azimuth(&self) -> f32 {
+		(90. + (self.hour - 6.) * 15.).rem_euclid(360.)
+	}
+
+	pub fn global_illumination(&self) -> GlobalIllumination {
+		if !self.enabled || (self.global_illumination_switching && self.sun_elevation().abs() > TWILIGHT_ELEVATION) {
+			GlobalIllumination::Baked
+		} else {
+			GlobalIllumination::Dynamic
+		}
+	}
+
+	// The keys on either side of the current hour blended together
+	pub fn lighting(&self) -> TimeOfDayKey {
+		let (first, last) = match (self.keys.first(), self.keys.last()) {
+			(Some(first), Some(last)) => (*first, *last),
+			_ => {
+				return TimeOfDayKey {
+					hour: self.hour,
+					sun_illuminance: 1.,
+					sky_ambient: DEFAULT_SKY_AMBIENT,
+					lamp_scale: 1.,
+				}
+			}
+		};
+
+		let next_index = self.keys.iter().position(|key| key.hour > self.hour);
+		let (previous, next) = match next_index {
+			Some(0) | None => (last, first),
+			Some(index) => (self.keys[index - 1], self.keys[index]),
+		};
+
+		// Between the last key and the first the span crosses midnight
+		let span = (next.hour - previous.hour).rem_euclid(24.);
+		let blend = if span > 0. { (self.hour - previous.hour).rem_euclid(24.) / span } else { 0. };
+		let mix = |a: f32, b: f32| a + (b - a) * blend;
+
+		TimeOfDayKey {
+			hour: self.hour,
+			sun_illuminance: mix(previous.sun_illuminance, next.sun_illuminance),
+			sky_ambient: [0, 1, 2].map(|channel| mix(previous.sky_ambient[channel], next.sky_ambient[channel])),
+			lamp_scale: mix(previous.lamp_scale, next.lamp_scale),
+		}
+	}
+}
@@ -0,0 +1,406 @@
+use crate::camera::Camera;
+use crate::json::{JsonParser, JsonValue};
+use crate::motion_track::MotionTrack;
+use crate::scene::Scene;
+
+use anyhow::{anyhow, bail, Result};
+use cgmath::{InnerSpace, Point3, Quaternion, Rad, Vector3};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use winit::event::{ElementState, VirtualKeyCode};
+
+// Seconds the playhead jumps when scrubbing with the bracket keys
+const SCRUB_STEP: f64 = 1.;
+
+// Scene wide values a parameter track can drive, since materials have no parameters of their own to animate
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimelineParameter {
+	// The active camera's exposure
+	Exposure,
+	// The weather's intensity, from a drizzle to a downpour
+	WeatherIntensity,
+	// The time of day's hour, which only moves the sun while the time of day is enabled
+	Hour,
+}
+
+impl TimelineParameter {
+	fn from_name(name: &str) -> Result<Self> {
+		match name {
+			"exposure" => Ok(Self::Exposure),
+			"weather_intensity" => Ok(Self::WeatherIntensity),
+			"hour" => Ok(Self::Hour),
+			_ => bail!("Timeline has no parameter named \"{}\"", name),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub enum TimelineTrackKind {
+	// Flies the active camera along the track, aiming it at the target if there is one or along the track's rotation otherwise
+	Camera { motion: MotionTrack, target: Option<Point3<f64>> },
+	// Moves an entity found by name, on top of wherever its own behaviors put it
+	Transform { entity: String, motion: MotionTrack },
+	// Linearly interpolated `(time, value)` keys, held at the first and last outside of them
+	Parameter { parameter: TimelineParameter, keys: Vec<(f64, f32)> },
+}
+
+// One track laid out on the timeline, mixed over whatever its target was already doing by its weight
+// Tracks with the same target are mixed in the order they're listed, so later ones are layered over earlier ones
+#[derive(Debug, Clone)]
+pub struct TimelineTrack {
+	pub kind: TimelineTrackKind,
+	// Seconds into the timeline where the track's first keyframe plays
+	pub start: f64,
+	pub weight: f32,
+	// Seconds to fade the weight up from zero after the track starts, and back down before it ends, so tracks ease in and out of each other instead of cutting
+	pub blend_in: f64,
+	pub blend_out: f64,
+}
+
+impl TimelineTrack {
+	pub fn duration(&self) -> f64 {
+		match &self.kind {
+			TimelineTrackKind::Camera { motion, .. } | TimelineTrackKind::Transform { motion, .. } => motion.duration(),
+			TimelineTrackKind::Parameter { keys, .. } => keys.last().map_or(0., |(time, _)| *time),
+		}
+	}
+
+	// Weight at this time in the timeline, zero outside the span of the track
+	pub fn weight_at(&self, time: f64) -> f32 {
+		let local_time = time - self.start;
+		let duration = self.duration();
+		if local_time < 0. || local_time > duration {
+			return 0.;
+		}
+
+		let ramp = |elapsed: f64, length: f64| if length > 0. { (elapsed / length).clamp(0., 1.) } else { 1. };
+		let envelope = ramp(local_time, self.blend_in).min(ramp(duration - local_time, self.blend_out));
+
+		// Smoothstepped so the fades don't start and stop abruptly
+		let envelope = envelope * envelope * (3. - 2. * envelope);
+		self.weight * envelope as f32
+	}
+
+	fn sample_parameter(keys: &[(f64, f32)], time: f64) -> f32 {
+		let next_index = keys.partition_point(|(key_time, _)| *key_time <= time);
+		let (previous_time, previous_value) = keys[next_index.saturating_sub(1)];
+		let (next_time, next_value) = keys[next_index.min(keys.len() - 1)];
+
+		let span = next_time - previous_time;
+		let blend = if span > f64::EPSILON { ((time - previous_time) / span).clamp(0., 1.) } else { 0. };
+		previous_value + (next_value - previous_value) * blend as f32
+	}
+}
+
+// A named cue at a moment in the timeline, passed to the behaviors of the entity it names and its descendants, or of the whole scene if it names none
+#[derive(Debug, Clone)]
+pub struct TimelineEvent {
+	pub time: f64,
+	pub name: String,
+	pub entity: Option<String>,
+}
+
+// A cinematic authored as tracks for the camera, entity transforms, and scene parameters, plus events for scripts to react to
+// Loaded from JSON files in the `timelines` asset folder, shaped like:
+// `{ "tracks": [{ "type": "camera", "start": 0, "weight": 1, "blend_in": 1, "blend_out": 1, "target": [x, y, z], "keyframes": [...] }], "events": [{ "time": 2, "name": "...", "entity": "..." }] }`
+// where the keyframes are those of a JSON motion track, transform tracks name their "entity", and parameter tracks name their "parameter" with "keys" of `[time, value]` pairs
+#[derive(Debug, Clone)]
+pub struct Timeline {
+	pub tracks: Vec<TimelineTrack>,
+	// Sorted by time
+	pub events: Vec<TimelineEvent>,
+	// Seconds until the last track or event ends, unless given longer by the file
+	pub duration: f64,
+}
+
+impl Timeline {
+	pub fn load(directory: &Path, file: &str) -> Result<Self> {
+		let text = std::fs::read_to_string(directory.join("timelines").join(file))?;
+		Self::parse_json(&text)
+	}
+
+	pub fn parse_json(text: &str) -> Result<Self> {
+		let root = JsonParser::new(text).parse_document()?;
+
+		let tracks = root
+			.get("tracks")
+			.and_then(JsonValue::as_array)
+			.unwrap_or_default()
+			.iter()
+			.map(Self::parse_track)
+			.collect::<Result<Vec<_>>>()?;
+
+		let mut events = root
+			.get("events")
+			.and_then(JsonValue::as_array)
+			.unwrap_or_default()
+			.iter()
+			.map(|event| {
+				Ok(TimelineEvent {
+					time: event.get("time").and_then(JsonValue::as_number).ok_or_else(|| anyhow!("Timeline event is missing its time"))?,
+					name: event.get("name").and_then(JsonValue::as_str).ok_or_else(|| anyhow!("Timeline event is missing its name"))?.to_string(),
+					entity: event.get("entity").and_then(JsonValue::as_str).map(String::from),
+				})
+			})
+			.collect::<Result<Vec<_>>>()?;
+		events.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+		let end_of_tracks = tracks.iter().map(|track| track.start + track.duration());
+		let end_of_events = events.iter().map(|event| event.time);
+		let duration = end_of_tracks.chain(end_of_events).chain(root.get("duration").and_then(JsonValue::as_number)).fold(0., f64::max);
+
+		Ok(Self { tracks, events, duration })
+	}
+
+	fn parse_track(track: &JsonValue) -> Result<TimelineTrack> {
+		let number = |key: &str, default: f64| track.get(key).and_then(JsonValue::as_number).unwrap_or(default);
+		let string = |key: &str| track.get(key).and_then(JsonValue::as_str).ok_or_else(|| anyhow!("Timeline track is missing its \"{}\"", key));
+
+		let kind = match string("type")? {
+			"camera" => TimelineTrackKind::Camera {
+				motion: MotionTrack::from_json(track)?,
+				target: track.get("target").and_then(JsonValue::as_numbers::<3>).map(Point3::from),
+			},
+			"transform" => TimelineTrackKind::Transform {
+				entity: string("entity")?.to_string(),
+				motion: MotionTrack::from_json(track)?,
+			},
+			"parameter" => {
+				let mut keys = track
+					.get("keys")
+					.and_then(JsonValue::as_array)
+					.unwrap_or_default()
+					.iter()
+					.map(|key| key.as_numbers::<2>().map(|[time, value]| (time, value as f32)))
+					.collect::<Option<Vec<_>>>()
+					.ok_or_else(|| anyhow!("Timeline parameter track keys must each be a pair of numbers"))?;
+				if keys.is_empty() {
+					bail!("Timeline parameter track has no keys");
+				}
+				keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+				TimelineTrackKind::Parameter {
+					parameter: TimelineParameter::from_name(string("parameter")?)?,
+					keys,
+				}
+			}
+			other => bail!("Timeline has no track type \"{}\"", other),
+		};
+
+		Ok(TimelineTrack {
+			kind,
+			start: number("start", 0.),
+			weight: number("weight", 1.) as f32,
+			blend_in: number("blend_in", 0.),
+			blend_out: number("blend_out", 0.),
+		})
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SequencerState {
+	// Leaves the scene alone, handing the camera and entities back to their controllers and behaviors
+	Stopped,
+	Playing,
+	// Holds the scene at the playhead, which can still be scrubbed
+	Paused,
+}
+
+// Plays back a timeline, applying its tracks to the scene over whatever the controllers and behaviors did this frame
+// P plays and pauses, and the bracket keys scrub backward and forward
+pub struct Sequencer {
+	pub timeline: Option<Arc<Timeline>>,
+	pub state: SequencerState,
+	// Seconds into the timeline
+	pub time: f64,
+	pub speed: f64,
+	// Starts over from the beginning after the end instead of stopping there
+	pub looping: bool,
+	// Values the parameters had before the timeline first drove them, which its tracks are mixed over and which are put back once it stops
+	base_parameters: Vec<(TimelineParameter, f32)>,
+}
+
+impl Sequencer {
+	pub fn new() -> Self {
+		Self {
+			timeline: None,
+			state: SequencerState::Stopped,
+			time: 0.,
+			speed: 1.,
+			looping: false,
+			base_parameters: vec![],
+		}
+	}
+
+	pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
+		if state != ElementState::Pressed || self.timeline.is_none() {
+			return false;
+		}
+
+		match key {
+			VirtualKeyCode::P => match self.state {
+				SequencerState::Playing => self.pause(),
+				SequencerState::Stopped | SequencerState::Paused => self.play(),
+			},
+			VirtualKeyCode::LBracket => self.seek(self.time - SCRUB_STEP),
+			VirtualKeyCode::RBracket => self.seek(self.time + SCRUB_STEP),
+			_ => return false,
+		}
+
+		true
+	}
+
+	pub fn play(&mut self) {
+		if self.state == SequencerState::Stopped {
+			self.time = 0.;
+		}
+		self.state = SequencerState::Playing;
+	}
+
+	pub fn pause(&mut self) {
+		if self.state == SequencerState::Playing {
+			self.state = SequencerState::Paused;
+		}
+	}
+
+	pub fn stop(&mut self) {
+		self.state = SequencerState::Stopped;
+	}
+
+	// Moves the playhead without firing the events it skips over, so scrubbing back and forth while authoring doesn't set off one-time cues
+	// Scrubbing a stopped timeline pauses it there so the result can be seen
+	pub fn seek(&mut self, time: f64) {
+		let duration = self.timeline.as_ref().map_or(0., |timeline| timeline.duration);
+		self.time = time.clamp(0., duration);
+		if self.state == SequencerState::Stopped {
+			self.state = SequencerState::Paused;
+		}
+	}
+
+	// Advances the playhead, returning the events it passed in the order they happened
+	pub fn update(&mut self, delta_time: Duration) -> Vec<TimelineEvent> {
+		let timeline = match (&self.timeline, self.state) {
+			(Some(timeline), SequencerState::Playing) => timeline.clone(),
+			_ => return vec![],
+		};
+
+		let previous_time = self.time;
+		let time = previous_time + delta_time.as_secs_f64() * self.speed.max(0.);
+		let passed = |from: f64, to: f64| timeline.events.iter().filter(move |event| event.time >= from && event.time < to).cloned();
+
+		if time < timeline.duration {
+			self.time = time;
+			passed(previous_time, time).collect()
+		} else if self.looping && timeline.duration > 0. {
+			self.time = time.rem_euclid(timeline.duration);
+			passed(previous_time, f64::INFINITY).chain(passed(0., self.time)).collect()
+		} else {
+			self.time = timeline.duration;
+			self.state = SequencerState::Stopped;
+			passed(previous_time, f64::INFINITY).collect()
+		}
+	}
+
+	fn active_tracks(&self) -> impl Iterator<Item = (&TimelineTrack, f32)> + '_ {
+		let time = self.time;
+		let timeline = match self.state {
+			SequencerState::Stopped => None,
+			SequencerState::Playing | SequencerState::Paused => self.timeline.as_deref(),
+		};
+
+		timeline
+			.into_iter()
+			.flat_map(|timeline| timeline.tracks.iter())
+			.map(move |track| (track, track.weight_at(time)))
+			.filter(|(_, weight)| *weight > 0.)
+	}
+
+	// Flies the camera along its tracks, blending its aim by pitch and yaw since the camera can't roll
+	// The camera controllers only nudge the camera from wherever it was, so a partial weight pulls it toward the track each frame rather than holding it partway
+	pub fn apply_camera(&self, camera: &mut Camera) {
+		for (track, weight) in self.active_tracks() {
+			let (motion, target) = match &track.kind {
+				TimelineTrackKind::Camera { motion, target } => (motion, target),
+				_ => continue,
+			};
+
+			let (location, rotation) = motion.sample(self.time - track.start);
+			let location = location.cast::<f32>().unwrap();
+			let direction = match (target, rotation) {
+				(Some(target), _) => Some(target.cast::<f32>().unwrap() - location),
+				(None, Some(rotation)) => Some((rotation * -Vector3::unit_z()).cast::<f32>().unwrap()),
+				(None, None) => None,
+			};
+
+			camera.location += (location - camera.location) * weight;
+			if let Some(direction) = direction.filter(|direction| direction.magnitude2() > f32::EPSILON) {
+				// The inverse of how `Camera::calculate_v_matrix()` builds its look direction
+				let horizontal = (direction.x * direction.x + direction.z * direction.z).sqrt().max(f32::EPSILON);
+				let pitch = (direction.y / horizontal).clamp(-1., 1.).asin();
+				let yaw = direction.z.atan2(direction.x);
+
+				// Yaw is turned the short way around
+				let yaw_difference = (yaw - camera.yaw.0 + std::f32::consts::PI).rem_euclid(2. * std::f32::consts::PI) - std::f32::consts::PI;
+				camera.pitch = Rad(camera.pitch.0 + (pitch - camera.pitch.0) * weight);
+				camera.yaw = Rad(camera.yaw.0 + yaw_difference * weight);
+			}
+		}
+	}
+
+	// Moves the entities named by the transform tracks, leaving their rotation alone for tracks without one
+	pub fn apply_transforms(&self, scene: &mut Scene) {
+		for (track, weight) in self.active_tracks() {
+			let (entity_name, motion) = match &track.kind {
+				TimelineTrackKind::Transform { entity, motion } => (entity, motion),
+				_ => continue,
+			};
+			let entity = match scene.find_entity_mut(entity_name) {
+				Some(entity) => entity,
+				None => continue,
+			};
+
+			let (location, rotation) = motion.sample(self.time - track.start);
+			let weight = weight as f64;
+			entity.transform.location += (location - entity.transform.location) * weight;
+			if let Some(rotation) = rotation {
+				entity.transform.rotation = blend_rotation(entity.transform.rotation, rotation, weight);
+			}
+		}
+	}
+
+	// The value the parameter should have now, mixed from its tracks over the value it had before the timeline started driving it
+	// Once the timeline stops, the value it had before is given back a single time and the current one is left alone after that
+	pub fn parameter(&mut self, parameter: TimelineParameter, value: f32) -> f32 {
+		let base_index = self.base_parameters.iter().position(|(base_parameter, _)| *base_parameter == parameter);
+		if self.state == SequencerState::Stopped {
+			return match base_index {
+				Some(index) => self.base_parameters.remove(index).1,
+				None => value,
+			};
+		}
+
+		let base = match base_index {
+			Some(index) => self.base_parameters[index].1,
+			None => {
+				self.base_parameters.push((parameter, value));
+				value
+			}
+		};
+
+		self.active_tracks().fold(base, |value, (track, weight)| match &track.kind {
+			TimelineTrackKind::Parameter { parameter: track_parameter, keys } if *track_parameter == parameter => {
+				value + (TimelineTrack::sample_parameter(keys, self.time - track.start) - value) * weight
+			}
+			_ => value,
+		})
+	}
+}
+
+fn blend_rotation(from: Quaternion<f64>, to: Quaternion<f64>, weight: f64) -> Quaternion<f64> {
+	if weight >= 1. {
+		to
+	} else {
+		from.normalize().slerp(to, weight)
+	}
+}
@@ -1,6 +1,8 @@
 pub use winit::dpi::Position;
 
-#[derive(Debug, Copy, Clone)]
+use cgmath::{ElementWise, EuclideanSpace};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Transform {
 	pub location: cgmath::Point3<f64>,
 	pub rotation: cgmath::Quaternion<f64>,
@@ -11,10 +13,20 @@ impl Transform {
 	pub fn new(location: cgmath::Point3<f64>, rotation: cgmath::Quaternion<f64>, scale: cgmath::Vector3<f64>) -> Self {
 		Self { location, rotation, scale }
 	}
+
+	// This transform placed within the space of a parent transform, which is how an entity's world transform is built up from its ancestors'
+	// Exact unless a rotated child has a non-uniformly scaled parent, where the shear that would need is left out since it can't be held by a location, rotation, and scale
+	pub fn within(&self, parent: &Transform) -> Self {
+		Self {
+			location: parent.location + parent.rotation * parent.scale.mul_element_wise(self.location.to_vec()),
+			rotation: parent.rotation * self.rotation,
+			scale: parent.scale.mul_element_wise(self.scale),
+		}
+	}
 }
 
 impl Default for Transform {
 	fn default() -> Self {
-		Self::new(cgmath::Point3::new(0., 0., 0.), cgmath::Quaternion::new(1., 0., 0., 0.), cgmath::Vector3::new(0., 0., 0.))
+		Self::new(cgmath::Point3::new(0., 0., 0.), cgmath::Quaternion::new(1., 0., 0., 0.), cgmath::Vector3::new(1., 1., 1.))
 	}
 }
@@ -0,0 +1,84 @@
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TransparentSurfaceUniform {
+	pub tint: [f32; 4],
+	pub index_of_refraction: f32,
+	pub roughness: f32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	pub _padding: [f32; 2],
+}
+
+// A named look that transparent models reference through `Model::transparent_surface`, each getting its own material per transparency mode
+#[derive(Debug, Copy, Clone)]
+pub struct TransparentSurface {
+	pub name: &'static str,
+	// RGB color with the opacity in the alpha channel, which for glass is instead how strongly the refracted scene is tinted
+	pub tint: [f32; 4],
+	// Glass surfaces bend and blur the scene behind them rather than blending over it
+	pub refraction: Option<Refraction>,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Refraction {
+	pub index_of_refraction: f32,
+	// Widens the blur of the refracted scene, from clear at 0 to frosted at 1
+	pub roughness: f32,
+}
+
+impl TransparentSurface {
+	pub fn uniform(&self) -> TransparentSurfaceUniform {
+		let refraction = self.refraction.unwrap_or(Refraction {
+			index_of_refraction: 1.,
+			roughness: 0.,
+		});
+
+		TransparentSurfaceUniform {
+			tint: self.tint,
+			index_of_refraction: refraction.index_of_refraction,
+			roughness: refraction.roughness,
+			_padding: [0.; 2],
+		}
+	}
+
+	// Glass is drawn with its own shader in its own pass, while other surfaces get a material for each transparency mode
+	pub fn material_kinds(&self) -> &'static [&'static str] {
+		match self.refraction {
+			Some(_) => &["glass"],
+			None => &["sorted", "weighted"],
+		}
+	}
+}
+
+pub const TRANSPARENT_SURFACES: [TransparentSurface; 5] = [
+	TransparentSurface {
+		name: "Red Tinted",
+		tint: [0.9, 0.15, 0.1, 0.35],
+		refraction: None,
+	},
+	TransparentSurface {
+		name: "Green Tinted",
+		tint: [0.15, 0.8, 0.2, 0.35],
+		refraction: None,
+	},
+	TransparentSurface {
+		name: "Blue Tinted",
+		tint: [0.1, 0.3, 0.9, 0.35],
+		refraction: None,
+	},
+	TransparentSurface {
+		name: "Clear Glass",
+		tint: [0.85, 0.95, 1., 0.5],
+		refraction: Some(Refraction {
+			index_of_refraction: 1.5,
+			roughness: 0.,
+		}),
+	},
+	TransparentSurface {
+		name: "Frosted Glass",
+		tint: [0.9, 0.9, 0.9, 0.3],
+		refraction: Some(Refraction {
+			index_of_refraction: 1.45,
+			roughness: 0.6,
+		}),
+	},
+];
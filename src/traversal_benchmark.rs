@@ -0,0 +1,127 @@
+use crate::component::Component;
+use crate::draw_list::DrawListCache;
+use crate::entity::Entity;
+use crate::frustum::Frustum;
+use crate::model::Model;
+use crate::scene::Scene;
+use crate::transform::Transform;
+
+use anyhow::{bail, Result};
+use cgmath::{Point3, Quaternion};
+use std::time::{Duration, Instant};
+
+// How many times each step is repeated on every thread count, with the times averaged
+const BENCHMARK_REPETITIONS: u32 = 10;
+// Cubes in each group under the root, so the tree is a few levels deep rather than one long list of children
+const BENCHMARK_GROUP_SIZE: usize = 100;
+// Distance between neighboring cubes, which spreads them far enough that culling keeps only some of them
+const BENCHMARK_SPACING: f64 = 2.;
+
+// The average time each step of the traversal took on one thread count
+struct BenchmarkTimings {
+	threads: usize,
+	transforms: Duration,
+	draw_lists: Duration,
+	culling: Duration,
+}
+
+// Times updating the world transforms, building the draw lists, and culling the deferred pass of a generated scene of beveled cubes, once with each of a growing number of threads
+// The generated scene takes the place of the scene's entities while it runs, so the same code paths are measured as in a frame, and they're put back afterwards
+pub fn run(scene: &mut Scene, frustum: &Frustum, entity_count: usize, device: &wgpu::Device) -> Result<String> {
+	if !scene.resources.meshes.contains_key(&(String::from("cube.obj"), String::from("BeveledCube"))) {
+		bail!("Can't benchmark since the beveled cube mesh isn't loaded");
+	}
+
+	// Doubling up to every core, along with the core count itself when it isn't a power of two
+	let max_threads = rayon::current_num_threads();
+	let mut thread_counts = (0..).map(|power| 1 << power).take_while(|threads| *threads < max_threads).collect::<Vec<_>>();
+	thread_counts.push(max_threads);
+	let thread_pools = thread_counts
+		.iter()
+		.map(|threads| rayon::ThreadPoolBuilder::new().num_threads(*threads).build())
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let benchmark_root = generate_entities(scene, entity_count, device);
+	let scene_root = std::mem::replace(&mut scene.root, benchmark_root);
+
+	// Moving the root back and forth makes every entity's world transform and instance buffer change each time, as they would if the whole scene moved
+	let timings = thread_pools
+		.iter()
+		.zip(thread_counts)
+		.map(|(thread_pool, threads)| {
+			thread_pool.install(|| {
+				let mut timings = BenchmarkTimings {
+					threads,
+					transforms: Duration::ZERO,
+					draw_lists: Duration::ZERO,
+					culling: Duration::ZERO,
+				};
+
+				for _ in 0..BENCHMARK_REPETITIONS {
+					scene.root.transform.location.y = BENCHMARK_SPACING - scene.root.transform.location.y;
+
+					let start = Instant::now();
+					scene.root.update_world_transforms_of_descendants(&Transform::default(), device);
+					timings.transforms += start.elapsed();
+
+					let mut draw_lists = DrawListCache::new();
+					let start = Instant::now();
+					draw_lists.refresh(scene);
+					timings.draw_lists += start.elapsed();
+
+					let start = Instant::now();
+					draw_lists.cull(scene, "Scene: Render Deferred", frustum);
+					timings.culling += start.elapsed();
+				}
+
+				timings
+			})
+		})
+		.collect::<Vec<_>>();
+
+	scene.root = scene_root;
+	scene.mark_changed();
+
+	let milliseconds = |duration: Duration| duration.as_secs_f64() * 1000. / BENCHMARK_REPETITIONS as f64;
+	let single_threaded = &timings[0];
+	let lines = timings.iter().map(|timings| {
+		let step = |duration: Duration, single_threaded: Duration| format!("{:.2} ms ({:.1}x)", milliseconds(duration), milliseconds(single_threaded) / milliseconds(duration).max(f64::EPSILON));
+		format!(
+			"{} {}: transforms {}, draw lists {}, culling {}",
+			timings.threads,
+			if timings.threads == 1 { "thread" } else { "threads" },
+			step(timings.transforms, single_threaded.transforms),
+			step(timings.draw_lists, single_threaded.draw_lists),
+			step(timings.culling, single_threaded.culling)
+		)
+	});
+
+	let heading = format!("{} entities, averaged over {} runs", entity_count, BENCHMARK_REPETITIONS);
+	Ok([heading].into_iter().chain(lines).collect::<Vec<_>>().join("\n"))
+}
+
+// Lays the cubes out in a square grid centered on the origin, split into groups of rows
+fn generate_entities(scene: &Scene, entity_count: usize, device: &wgpu::Device) -> Entity {
+	let mut root = Entity::new("Benchmark Root");
+	let side = (entity_count as f64).sqrt().ceil() as usize;
+
+	for (group_index, group_start) in (0..entity_count).step_by(BENCHMARK_GROUP_SIZE).enumerate() {
+		let group = root.new_child(&format!("Benchmark Group {}", group_index));
+
+		for index in group_start..(group_start + BENCHMARK_GROUP_SIZE).min(entity_count) {
+			let location = Point3::new((index % side) as f64 - side as f64 / 2., 0., (index / side) as f64 - side as f64 / 2.) * BENCHMARK_SPACING;
+
+			let entity = group.new_child(&format!("Benchmark Cube {}", index));
+			entity.transform.location = location;
+
+			let mut model = Model::new(("cube.obj", "BeveledCube"));
+			model
+				.instances
+				.transform_single_instance(location, Quaternion::new(1., 0., 0., 0.), Point3::new(0.25, 0.25, 0.25), device);
+			entity.add_component(Component::Model(Box::new(model)));
+		}
+	}
+
+	root.load_models_on_descendants(&scene.resources);
+	root
+}
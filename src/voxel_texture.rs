@@ -2,6 +2,9 @@ use std::borrow::Cow;
 
 use crate::{context::Context, texture::Texture};
 
+// Must match the workgroup size of the 3D mipmap shader
+const MIPMAP_WORKGROUP_SIZE: u32 = 4;
+
 pub struct VoxelTexture {
 	pub texture: Texture,
 	pub storage_texture_view: wgpu::TextureView,
@@ -9,6 +12,9 @@ pub struct VoxelTexture {
 	pub format: wgpu::TextureFormat,
 	pub label: String,
 	pub compare: Option<wgpu::CompareFunction>,
+	mipmap_pipeline: wgpu::ComputePipeline,
+	// One per mip level after the first, each reading the level above it and writing its own
+	mip_bind_groups: Vec<wgpu::BindGroup>,
 }
 
 impl VoxelTexture {
@@ -54,38 +60,21 @@ impl VoxelTexture {
 			array_layer_count: None,
 		});
 
-		Self {
-			texture: Texture { texture, view, sampler, format, size },
-			storage_texture_view,
-			size,
-			format,
-			label: String::from(label),
-			compare,
-		}
-	}
-
-	pub fn generate_mipmaps(&mut self, context: &Context) {
-		let mut encoder = context.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Render Encoder") });
-
-		let shader = context.device.create_shader_module(&wgpu::ShaderModuleDescriptor {
-			label: None,
+		let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+			label: Some("3D Texture Mipmap Downsampler"),
 			source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../assets/shaders/utility_3d_mipmap_blit.wgsl"))),
 		});
-
-		let pipeline = context.device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+		let mipmap_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
 			label: Some("3D Texture Mipmap Downsampler"),
 			layout: None,
 			module: &shader,
 			entry_point: "main",
 		});
+		let bind_group_layout = mipmap_pipeline.get_bind_group_layout(0);
 
-		let bind_group_layout = pipeline.get_bind_group_layout(0);
-
-		let mip_level_count = self.size.max_mips();
-
-		let views = (0..mip_level_count)
+		let mip_views = (0..texture_descriptor.mip_level_count)
 			.map(|mip| {
-				self.texture.texture.create_view(&wgpu::TextureViewDescriptor {
+				texture.create_view(&wgpu::TextureViewDescriptor {
 					label: Some("mip"),
 					format: None,
 					dimension: None,
@@ -97,30 +86,65 @@ impl VoxelTexture {
 				})
 			})
 			.collect::<Vec<_>>();
+		let mip_bind_groups = mip_views
+			.windows(2)
+			.map(|views| {
+				device.create_bind_group(&wgpu::BindGroupDescriptor {
+					label: Some("3D Texture Mipmap bind group"),
+					layout: &bind_group_layout,
+					entries: &[
+						wgpu::BindGroupEntry {
+							binding: 0,
+							resource: wgpu::BindingResource::TextureView(&views[0]),
+						},
+						wgpu::BindGroupEntry {
+							binding: 1,
+							resource: wgpu::BindingResource::TextureView(&views[1]),
+						},
+					],
+				})
+			})
+			.collect();
 
-		for target_mip in 1..mip_level_count as usize {
-			let bind_group = context.device.create_bind_group(&wgpu::BindGroupDescriptor {
-				layout: &bind_group_layout,
-				entries: &[
-					wgpu::BindGroupEntry {
-						binding: 0,
-						resource: wgpu::BindingResource::TextureView(&views[target_mip - 1]),
-					},
-					wgpu::BindGroupEntry {
-						binding: 1,
-						resource: wgpu::BindingResource::TextureView(&views[target_mip]),
-					},
-				],
-				label: None,
-			});
+		Self {
+			texture: Texture {
+				texture,
+				view,
+				sampler,
+				format,
+				size,
+				label: String::from(label),
+			},
+			storage_texture_view,
+			size,
+			format,
+			label: String::from(label),
+			compare,
+			mipmap_pipeline,
+			mip_bind_groups,
+		}
+	}
 
-			let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-				label: Some(&format!("3D Mipmap Compute Pass: target mip {}", target_mip)),
+	// Averages each mip level down from the one above it, so cone traces wider than a voxel read the light of the whole region they cover
+	pub fn generate_mipmaps(&mut self, context: &Context) {
+		let mut encoder = context.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Voxel Mipmap Encoder") });
+
+		{
+			let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+				label: Some("Compute: Generate Voxel Mipmaps"),
 			});
-			cpass.set_pipeline(&pipeline);
-			cpass.set_bind_group(0, &bind_group, &[]);
-			let size = self.size.mip_level_size(target_mip.try_into().unwrap(), true);
-			cpass.dispatch(size.width, size.height, size.depth_or_array_layers);
+			compute_pass.set_pipeline(&self.mipmap_pipeline);
+
+			for (index, bind_group) in self.mip_bind_groups.iter().enumerate() {
+				let size = self.size.mip_level_size(index as u32 + 1, true);
+
+				compute_pass.set_bind_group(0, bind_group, &[]);
+				compute_pass.dispatch(
+					size.width.div_ceil(MIPMAP_WORKGROUP_SIZE),
+					size.height.div_ceil(MIPMAP_WORKGROUP_SIZE),
+					size.depth_or_array_layers.div_ceil(MIPMAP_WORKGROUP_SIZE),
+				);
+			}
 		}
 
 		context.queue.submit(Some(encoder.finish()));
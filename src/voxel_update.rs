@@ -0,0 +1,159 @@
+use crate::entity::Entity;
+use crate::model::Mobility;
+use crate::transform::Transform;
+
+use cgmath::Point3;
+use std::collections::HashMap;
+use wgpu::util::DeviceExt;
+
+// Must match the voxelized volume in `calc_voxel_lightmap.wgsl`
+pub const VOXEL_RESOLUTION: u32 = 128;
+const VOXEL_VOLUME_CENTER: [f64; 3] = [0., 5., 0.];
+const VOXEL_VOLUME_DIMENSIONS: [f64; 3] = [30., 14., 20.];
+
+// Moved entities dirty the volume around them by this much beyond their scale, since model extents aren't known here
+const MOVED_ENTITY_PADDING: f64 = 1.;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct VoxelUpdateRegionUniform {
+	z_min: u32,
+	z_max: u32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: [u32; 2],
+}
+
+// Splits the voxel volume into slabs along Z and re-voxelizes only the dirty ones, a few per frame, so static geometry isn't rebuilt every frame
+pub struct VoxelUpdateScheduler {
+	pub slabs_per_frame: u32,
+	pub region_buffer: wgpu::Buffer,
+	dirty_slabs: Vec<bool>,
+	current_region: Option<(u32, u32)>,
+	previous_transforms: HashMap<String, Transform>,
+}
+
+impl VoxelUpdateScheduler {
+	pub fn new(device: &wgpu::Device, slab_count: u32, slabs_per_frame: u32) -> Self {
+		let region_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Voxel Update Region Buffer"),
+			contents: bytemuck::cast_slice(&[VoxelUpdateRegionUniform { z_min: 0, z_max: 0, _padding: [0; 2] }]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
+
+		// Nothing has been voxelized yet, so the whole volume starts out dirty
+		Self {
+			slabs_per_frame,
+			region_buffer,
+			dirty_slabs: vec![true; slab_count as usize],
+			current_region: None,
+			previous_transforms: HashMap::new(),
+		}
+	}
+
+	pub fn mark_region_dirty(&mut self, min: Point3<f64>, max: Point3<f64>) {
+		let normalized_min = world_to_normalized_volume(min);
+		let normalized_max = world_to_normalized_volume(max);
+
+		// Changes entirely outside of the volume can't affect the voxelized lighting
+		if (0..3).any(|axis| normalized_max[axis] < 0. || normalized_min[axis] > 1.) {
+			return;
+		}
+
+		let slab_count = self.dirty_slabs.len();
+		let first_slab = (normalized_min[2].max(0.) * slab_count as f64) as usize;
+		let last_slab = ((normalized_max[2].min(1.) * slab_count as f64) as usize).min(slab_count - 1);
+
+		self.dirty_slabs[first_slab..=last_slab].iter_mut().for_each(|dirty| *dirty = true);
+	}
+
+	// For changes that affect the lighting everywhere, like the sun moving
+	pub fn mark_all_dirty(&mut self) {
+		self.dirty_slabs.iter_mut().for_each(|dirty| *dirty = true);
+	}
+
+	// Compares every entity with a movable model against its transform from the previous frame and dirties the volume at both its old and new place
+	// Entities with models that are gone since the previous frame were despawned, so the volume is dirtied where they last were and they're forgotten
+	pub fn track_transform_changes(&mut self, root: &Entity) {
+		let capacity = self.previous_transforms.len();
+		let mut despawned = std::mem::replace(&mut self.previous_transforms, HashMap::with_capacity(capacity));
+
+		for entity in root {
+			let models = entity.get_models();
+			if models.is_empty() {
+				continue;
+			}
+
+			let transform = entity.world_transform;
+			let previous = despawned.remove(&entity.name);
+			self.previous_transforms.insert(entity.name.clone(), transform);
+
+			// Static models are only remembered so they can be cleared from the volume once despawned
+			if !models.iter().any(|model| model.mobility == Mobility::Movable) {
+				continue;
+			}
+
+			match previous {
+				Some(previous) if previous.location == transform.location && previous.rotation == transform.rotation && previous.scale == transform.scale => {}
+				Some(previous) => {
+					self.mark_transform_dirty(&previous);
+					self.mark_transform_dirty(&transform);
+				}
+				None => self.mark_transform_dirty(&transform),
+			}
+		}
+
+		for transform in despawned.values() {
+			self.mark_transform_dirty(transform);
+		}
+	}
+
+	// Claims up to `slabs_per_frame` contiguous dirty slabs for this frame's voxelization and uploads their voxel range for the shaders
+	pub fn begin_frame(&mut self, queue: &wgpu::Queue) {
+		self.current_region = None;
+
+		let first_slab = match self.dirty_slabs.iter().position(|dirty| *dirty) {
+			Some(slab) => slab,
+			None => return,
+		};
+		let slab_run = self.dirty_slabs[first_slab..].iter().take(self.slabs_per_frame.max(1) as usize).take_while(|dirty| **dirty).count();
+		self.dirty_slabs[first_slab..first_slab + slab_run].iter_mut().for_each(|dirty| *dirty = false);
+
+		let voxels_per_slab = VOXEL_RESOLUTION / self.dirty_slabs.len() as u32;
+		let z_min = first_slab as u32 * voxels_per_slab;
+		let z_max = if first_slab + slab_run == self.dirty_slabs.len() {
+			VOXEL_RESOLUTION
+		} else {
+			z_min + slab_run as u32 * voxels_per_slab
+		};
+
+		let uniform = VoxelUpdateRegionUniform { z_min, z_max, _padding: [0; 2] };
+		queue.write_buffer(&self.region_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+		self.current_region = Some((z_min, z_max));
+	}
+
+	// Voxelizes nothing this frame, keeping the dirty slabs for when voxelization resumes
+	pub fn pause(&mut self) {
+		self.current_region = None;
+	}
+
+	// The range of voxel Z layers being re-voxelized this frame, or `None` when the whole volume is up to date
+	pub fn current_region(&self) -> Option<(u32, u32)> {
+		self.current_region
+	}
+
+	fn mark_transform_dirty(&mut self, transform: &Transform) {
+		let radius = transform.scale.x.abs().max(transform.scale.y.abs()).max(transform.scale.z.abs()) + MOVED_ENTITY_PADDING;
+		let location = transform.location;
+
+		let min = Point3::new(location.x - radius, location.y - radius, location.z - radius);
+		let max = Point3::new(location.x + radius, location.y + radius, location.z + radius);
+		self.mark_region_dirty(min, max);
+	}
+}
+
+fn world_to_normalized_volume(point: Point3<f64>) -> [f64; 3] {
+	let point = [point.x, point.y, point.z];
+
+	[0, 1, 2].map(|axis| (point[axis] - VOXEL_VOLUME_CENTER[axis]) / VOXEL_VOLUME_DIMENSIONS[axis] + 0.5)
+}
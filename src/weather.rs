@@ -0,0 +1,141 @@
+use cgmath::Vector3;
+use std::time::Duration;
+use wgpu::util::DeviceExt;
+use winit::event::{ElementState, VirtualKeyCode};
+
+// Drops or flakes drawn at full intensity, fewer as it lightens
+pub const MAX_PRECIPITATION_PARTICLES: u32 = 16384;
+// Half the size of the box around the camera the particles wrap around in, since only the nearby ones are big enough to see
+const PRECIPITATION_AREA: f32 = 12.;
+// Seconds for surfaces to go from dry to soaked in a downpour, and back to dry once it stops
+const SOAKING_TIME: f32 = 20.;
+const DRYING_TIME: f32 = 60.;
+// Seconds for snow to build up to a full cover in heavy snowfall, and to melt away once it stops
+const SNOW_ACCUMULATION_TIME: f32 = 90.;
+const MELTING_TIME: f32 = 120.;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Precipitation {
+	None,
+	Rain,
+	Snow,
+}
+
+impl Precipitation {
+	// Meters per second, which for snow is slow enough that the wind carries it more than it falls
+	fn fall_speed(&self) -> f32 {
+		match self {
+			Self::None => 0.,
+			Self::Rain => 9.,
+			Self::Snow => 1.2,
+		}
+	}
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct WeatherUniform {
+	// Meters per second the wind carries the particles sideways
+	wind: [f32; 3],
+	// 0 for none, 1 for rain, and 2 for snow
+	precipitation: u32,
+	intensity: f32,
+	// How soaked the surfaces open to the sky are, from 0 for dry to 1 for standing water in every dip
+	wetness: f32,
+	// How much snow has settled on the surfaces facing up, from 0 for none to 1 for a full cover
+	snow_cover: f32,
+	fall_speed: f32,
+	area: f32,
+	// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
+	_padding: [u32; 3],
+}
+
+// Falling rain or snow, and what it leaves behind on the surfaces: rain soaks them and pools into puddles, then dries off slowly once it stops, and snow settles over the ground before melting away
+// The surfaces respond in the PBR shading pass, and the particles are generated entirely in their vertex shader from the time, so nothing needs simulating on the CPU
+pub struct Weather {
+	pub precipitation: Precipitation,
+	// From 0 for a drizzle or flurry to 1 for a downpour or blizzard
+	pub intensity: f32,
+	pub wind: Vector3<f32>,
+	pub wetness: f32,
+	pub snow_cover: f32,
+	pub weather_buffer: wgpu::Buffer,
+}
+
+impl Weather {
+	// Starts out clear, with the buffer filled in by the first update
+	pub fn new(device: &wgpu::Device) -> Self {
+		let weather_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("Weather Buffer"),
+			contents: bytemuck::cast_slice(&[<WeatherUniform as bytemuck::Zeroable>::zeroed()]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
+
+		Self {
+			precipitation: Precipitation::None,
+			intensity: 0.7,
+			wind: Vector3::new(1.5, 0., 0.5),
+			wetness: 0.,
+			snow_cover: 0.,
+			weather_buffer,
+		}
+	}
+
+	pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
+		if key != VirtualKeyCode::R || state != ElementState::Pressed {
+			return false;
+		}
+
+		self.precipitation = match self.precipitation {
+			Precipitation::None => Precipitation::Rain,
+			Precipitation::Rain => Precipitation::Snow,
+			Precipitation::Snow => Precipitation::None,
+		};
+
+		true
+	}
+
+	// Soaks, dries, buries, and melts the surfaces over time, then uploads the result
+	pub fn update(&mut self, queue: &wgpu::Queue, delta_time: Duration) {
+		let delta_time = delta_time.as_secs_f32();
+		let intensity = self.intensity.clamp(0., 1.);
+
+		self.wetness = match self.precipitation {
+			Precipitation::Rain => self.wetness + delta_time * intensity / SOAKING_TIME,
+			// Settled snow keeps the ground beneath it damp as it melts
+			Precipitation::Snow | Precipitation::None => (self.wetness - delta_time / DRYING_TIME).max(self.snow_cover * 0.3),
+		}
+		.clamp(0., 1.);
+
+		self.snow_cover = match self.precipitation {
+			Precipitation::Snow => self.snow_cover + delta_time * intensity / SNOW_ACCUMULATION_TIME,
+			// Rain washes snow away faster than it would melt on its own
+			Precipitation::Rain => self.snow_cover - delta_time * 4. / MELTING_TIME,
+			Precipitation::None => self.snow_cover - delta_time / MELTING_TIME,
+		}
+		.clamp(0., 1.);
+
+		queue.write_buffer(&self.weather_buffer, 0, bytemuck::cast_slice(&[self.uniform()]));
+	}
+
+	// How many particles to draw this frame
+	pub fn particle_count(&self) -> u32 {
+		match self.precipitation {
+			Precipitation::None => 0,
+			Precipitation::Rain | Precipitation::Snow => (MAX_PRECIPITATION_PARTICLES as f32 * self.intensity.clamp(0., 1.)) as u32,
+		}
+	}
+
+	fn uniform(&self) -> WeatherUniform {
+		WeatherUniform {
+			wind: self.wind.into(),
+			precipitation: self.precipitation as u32,
+			intensity: self.intensity.clamp(0., 1.),
+			wetness: self.wetness,
+			snow_cover: self.snow_cover,
+			fall_speed: self.precipitation.fall_speed(),
+			area: PRECIPITATION_AREA,
+			_padding: [0; 3],
+		}
+	}
+}
@@ -0,0 +1,90 @@
+use crate::asset_loader::AssetLoader;
+use crate::json::{JsonParser, JsonValue};
+
+use anyhow::{anyhow, bail, Result};
+use cgmath::Point3;
+use std::collections::HashSet;
+use std::path::Path;
+
+// Seconds a chunk takes to fade in once it's added to the scene, long enough that it doesn't pop in at the edge of view
+pub const CHUNK_FADE_IN_TIME: f32 = 1.5;
+
+pub enum ChunkState {
+	Unloaded,
+	// The model files it needs are decoded on worker threads and uploaded as they arrive, while the chunk is left out of the scene
+	Loading { asset_loader: AssetLoader, model_files: Vec<String> },
+	// Seconds since it was added to the scene, which stops counting once it has fully faded in
+	Loaded { fade_time: f32 },
+	// Its scene file couldn't be read or built, so it isn't tried again until the manifest is streamed anew
+	Failed,
+}
+
+// A scene file of the world loaded on top of the scene while the camera is near it
+pub struct StreamingChunk {
+	pub file: String,
+	pub center: Point3<f32>,
+	pub state: ChunkState,
+}
+
+// Loads and unloads the chunks of a world too large to keep in memory all at once by how far the camera is from each of them
+// A world's manifest in the scenes folder lists its chunks, each a scene file with the "center" it's measured from
+// - Chunks start loading once the camera is within the manifest's "load_distance" of them, and are unloaded once it's beyond the "unload_distance"
+// - The gap between the two keeps a chunk from loading and unloading over and over while the camera lingers around its edge
+pub struct WorldStreaming {
+	pub manifest: String,
+	pub chunks: Vec<StreamingChunk>,
+	pub load_distance: f32,
+	pub unload_distance: f32,
+	// Model files a chunk's loader has been asked for, so chunks that share them wait on that loader instead of decoding them again
+	pub pending_model_files: HashSet<String>,
+	// Set to unload every chunk and load no more, after which the world is dropped
+	pub stopping: bool,
+}
+
+impl WorldStreaming {
+	pub fn from_file(assets_path: &Path, file: &str) -> Result<Self> {
+		let text = std::fs::read_to_string(assets_path.join("scenes").join(file))?;
+		let root = JsonParser::new(&text).parse_document()?;
+
+		let load_distance = root.get("load_distance").and_then(JsonValue::as_number).unwrap_or(100.) as f32;
+		let unload_distance = root.get("unload_distance").and_then(JsonValue::as_number).map_or(load_distance * 1.25, |distance| distance as f32);
+		if unload_distance < load_distance {
+			bail!("World \"{}\" unloads its chunks closer than it loads them", file);
+		}
+
+		let mut chunks = Vec::new();
+		for chunk in root.get("chunks").and_then(JsonValue::as_array).unwrap_or_default() {
+			let scene = chunk
+				.get("scene")
+				.and_then(JsonValue::as_str)
+				.ok_or_else(|| anyhow!("A chunk of world \"{}\" has no scene file", file))?;
+			let [x, y, z] = chunk
+				.get("center")
+				.and_then(JsonValue::as_numbers::<3>)
+				.ok_or_else(|| anyhow!("Chunk \"{}\" of world \"{}\" needs a center of 3 numbers", scene, file))?;
+
+			chunks.push(StreamingChunk {
+				file: String::from(scene),
+				center: Point3::new(x as f32, y as f32, z as f32),
+				state: ChunkState::Unloaded,
+			});
+		}
+
+		Ok(Self {
+			manifest: String::from(file),
+			chunks,
+			load_distance,
+			unload_distance,
+			pending_model_files: HashSet::new(),
+			stopping: false,
+		})
+	}
+
+	pub fn loaded_count(&self) -> usize {
+		self.chunks.iter().filter(|chunk| matches!(chunk.state, ChunkState::Loaded { .. })).count()
+	}
+
+	pub fn loading_count(&self) -> usize {
+		self.chunks.iter().filter(|chunk| matches!(chunk.state, ChunkState::Loading { .. })).count()
+	}
+}